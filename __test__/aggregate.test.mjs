@@ -0,0 +1,55 @@
+import test from "ava";
+import { Readable } from "stream";
+import csv from "../main.js";
+import { CsvAggregator } from "../index.js";
+
+const sales = "region,revenue,id\nEU,10,1\nUS,5,2\nEU,,3\nEU,2.5,4\nUS,n/a,5\n";
+
+test("CsvAggregator groups and aggregates across chunks", (t) => {
+  const aggregator = new CsvAggregator({
+    groupBy: "region",
+    metrics: { revenue: ["sum", "mean"], id: "count" },
+  });
+  aggregator.push(sales.slice(0, 20));
+  aggregator.push(Buffer.from(sales.slice(20)));
+
+  t.deepEqual(aggregator.getResult(), [
+    { region: "EU", revenue_sum: 12.5, revenue_mean: 6.25, id: 3 },
+    { region: "US", revenue_sum: 5, revenue_mean: 5, id: 2 },
+  ]);
+});
+
+test("CsvAggregator without groupBy reports one group", (t) => {
+  const aggregator = new CsvAggregator({
+    metrics: { b: "max" },
+    parser: { separator: ";" },
+  });
+  aggregator.push("a;b\n1;x\n");
+
+  t.deepEqual(aggregator.getResult(), [{ b: null }]);
+});
+
+test("CsvAggregator rejects unknown metrics and columns", (t) => {
+  t.throws(() => new CsvAggregator({ metrics: { id: "median" } }), {
+    message: /Unknown metric "median"/,
+  });
+
+  const aggregator = new CsvAggregator({ groupBy: "country", metrics: {} });
+  t.throws(() => aggregator.push(sales), { message: /Column country not found/ });
+});
+
+test("aggregate() streams CSV in and groups out", async (t) => {
+  const groups = [];
+  await new Promise((resolve, reject) => {
+    Readable.from([sales])
+      .pipe(csv.aggregate({ groupBy: ["region"], metrics: { id: "count" } }))
+      .on("data", (group) => groups.push(group))
+      .on("end", resolve)
+      .on("error", reject);
+  });
+
+  t.deepEqual(groups, [
+    { region: "EU", id: 3 },
+    { region: "US", id: 2 },
+  ]);
+});
@@ -0,0 +1,67 @@
+import test from "ava";
+import { mkdtempSync, readFileSync, writeFileSync } from "fs";
+import { tmpdir } from "os";
+import { join } from "path";
+import { CsvWriter } from "../main.js";
+
+const tempFile = () => join(mkdtempSync(join(tmpdir(), "append-")), "out.csv");
+
+test("appendTo starts a missing file with the header", (t) => {
+  const path = tempFile();
+  const writer = new CsvWriter({ bom: true });
+  writer.appendTo(path, [{ id: 1, name: "a" }]);
+  writer.appendTo(path, [{ id: 2, name: "b" }]);
+  t.is(readFileSync(path, "utf8"), "\uFEFFid,name\n1,a\n2,b\n");
+});
+
+test("appendTo follows the existing header without repeating it", (t) => {
+  const path = tempFile();
+  writeFileSync(path, "\uFEFFname,id\nx,0");
+
+  // Keys in another order are written in the file's order
+  new CsvWriter({ bom: true }).appendTo(path, [{ id: 1, name: "a, b" }]);
+  t.is(readFileSync(path, "utf8"), '\uFEFFname,id\nx,0\n"a, b",1\n');
+});
+
+test("appendTo fails on a header that does not match", (t) => {
+  const path = tempFile();
+  writeFileSync(path, "id,amount\n1,2\n");
+
+  t.throws(() => new CsvWriter().appendTo(path, [{ id: 1, total: 3 }]), {
+    message: `Header of ${path} does not match: expected id,total, found id,amount`,
+  });
+  t.throws(() => new CsvWriter({ headers: ["amount", "id"] }).appendTo(path, [[2, 1]]), {
+    message: /expected amount,id, found id,amount/,
+  });
+  t.is(readFileSync(path, "utf8"), "id,amount\n1,2\n");
+});
+
+test("appendTo matches headers holding the separator", (t) => {
+  const path = tempFile();
+  writeFileSync(path, '"Revenue, net",id\n1,2\n');
+
+  new CsvWriter().appendTo(path, [{ id: 3, "Revenue, net": 4 }]);
+  t.is(readFileSync(path, "utf8"), '"Revenue, net",id\n1,2\n4,3\n');
+  // Names are quoted as in the file, so the comma inside one is not a column
+  t.throws(() => new CsvWriter().appendTo(path, [{ "Revenue, gross": 1, id: 2 }]), {
+    message: /expected "Revenue, gross",id, found "Revenue, net",id$/,
+  });
+});
+
+test("appendTo reads the header in the writer's dialect and encoding", (t) => {
+  const path = tempFile();
+  const writer = new CsvWriter({ separator: ";", newline: "\r\n", encoding: "utf-16le" });
+  writer.appendTo(path, [{ "a;b": 1, c: 2 }]);
+  writer.appendTo(path, [{ c: 4, "a;b": 3 }]);
+  new CsvWriter({ separator: ";", newline: "\r\n", encoding: "utf-16le" }).appendTo(path, [
+    { c: 6, "a;b": 5 },
+  ]);
+  t.is(readFileSync(path, "utf16le"), '"a;b";c\r\n1;2\r\n3;4\r\n5;6\r\n');
+});
+
+test("appendTo appends array rows without headers unchecked", (t) => {
+  const path = tempFile();
+  writeFileSync(path, "1,2\n");
+  new CsvWriter().appendTo(path, [[3, 4]]);
+  t.is(readFileSync(path, "utf8"), "1,2\n3,4\n");
+});
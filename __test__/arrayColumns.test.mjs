@@ -0,0 +1,39 @@
+import test from "ava";
+import { Readable } from "stream";
+import csv, { CsvParser } from "../main.js";
+
+test("arrayColumns splits cells on a separator per column", (t) => {
+  const parser = new CsvParser({ arrayColumns: { tags: ";", ids: " | " } });
+  const rows = parser.push(
+    'name,tags,ids\nbox,"red;green;blue",1 | 2\nbag,,3\n',
+  );
+
+  t.deepEqual(rows, [
+    { name: "box", tags: ["red", "green", "blue"], ids: ["1", "2"] },
+    { name: "bag", tags: [], ids: ["3"] },
+  ]);
+});
+
+test("arrayColumns lists use arraySeparator", async (t) => {
+  const rows = await Readable.from(["a,b\nx/y,z/\n"])
+    .pipe(csv({ arrayColumns: ["a", "b"], arraySeparator: "/" }))
+    .toArray();
+
+  t.deepEqual(rows, [{ a: ["x", "y"], b: ["z", ""] }]);
+});
+
+test("arrayColumns converts items with columnTypes", (t) => {
+  const parser = new CsvParser({
+    arrayColumns: ["ts"],
+    columnTypes: { ts: { type: "date", output: "millis" } },
+  });
+  const [row] = parser.push("ts\n2024-01-02T03:04:00Z;soon\n");
+
+  t.deepEqual(row, { ts: [1704164640000, "soon"] });
+});
+
+test("arrayColumns rejects an empty separator", (t) => {
+  t.throws(() => new CsvParser({ arrayColumns: { tags: "" } }), {
+    message: /arrayColumns.tags must not be empty/,
+  });
+});
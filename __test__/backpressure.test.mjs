@@ -0,0 +1,126 @@
+import test from "ava";
+import { pipeline } from "stream/promises";
+import { Readable, Transform, Writable } from "stream";
+import csv, { CsvParserStream } from "../main.js";
+import { CsvParser } from "../index.js";
+
+const rows = (count) => "a,b\n" + Array.from({ length: count }, (_, i) => `${i},x`).join("\n");
+
+test("write buffers input until read parses it", (t) => {
+  const parser = new CsvParser();
+  t.is(parser.write("a,b\n1,x\n2,"), undefined);
+  parser.write(Buffer.from("y\n3,z"));
+
+  t.deepEqual(
+    parser.read(1).rows.map((row) => row.a),
+    ["1"],
+  );
+  t.deepEqual(
+    parser.read().rows.map((row) => row.a),
+    ["2"],
+  );
+  // The last row waits for more input or the end
+  t.deepEqual(parser.read().rows, []);
+  t.deepEqual(parser.read(null, true).rows, [{ a: "3", b: "z" }]);
+  t.deepEqual(parser.read(null, true).rows, []);
+});
+
+test("read defaults to batchSize rows and returns row errors", (t) => {
+  const parser = new CsvParser({ batchSize: 2, strict: true });
+  parser.write("a,b\n1,x\n2\n3,x\n4,x\n");
+
+  // A row that fails does not count towards the batch
+  const first = parser.read();
+  t.deepEqual(
+    first.rows.map((row) => row.a),
+    ["1", "3"],
+  );
+  t.is(first.errors.length, 1);
+  t.is(first.errors[0].index, 1);
+  t.deepEqual(
+    parser.read().rows.map((row) => row.a),
+    ["4"],
+  );
+  t.deepEqual(parser.read(10).rows, []);
+});
+
+test("csv() returns an exported Transform", (t) => {
+  const stream = csv();
+  t.true(stream instanceof CsvParserStream);
+  t.true(stream instanceof Transform);
+  t.true(new CsvParserStream(["x"]) instanceof Transform);
+});
+
+test("a slow consumer holds back a large chunk", async (t) => {
+  const stream = csv({ batchSize: 10 });
+  let written = false;
+  stream.write(rows(1000), () => {
+    written = true;
+  });
+  await new Promise((resolve) => setImmediate(resolve));
+
+  // Only the batches that fit in the readable buffer are parsed
+  t.false(written);
+  t.true(stream.readableLength < 16 + 10);
+
+  const seen = [];
+  stream.on("data", (row) => seen.push(row.a));
+  await new Promise((resolve) => stream.end(resolve));
+  t.true(written);
+  t.is(seen.length, 1000);
+  t.is(seen[999], "999");
+});
+
+test("headers are emitted before the first row", async (t) => {
+  const events = [];
+  await pipeline(
+    Readable.from(["a,b\n1,", "x\n2,y\n"]),
+    csv({ batchSize: 1 })
+      .on("headers", (headers) => events.push(headers))
+      .on("data", (row) => events.push(row.a)),
+    new Writable({ objectMode: true, write: (row, encoding, callback) => callback() }),
+  );
+  t.deepEqual(events, [["a", "b"], "1", "2"]);
+});
+
+test("headers are emitted for input without rows", async (t) => {
+  let headers;
+  const stream = csv().on("headers", (names) => {
+    headers = names;
+  });
+  stream.resume();
+  stream.end("a,b\n");
+  await new Promise((resolve) => stream.on("end", resolve));
+  t.deepEqual(headers, ["a", "b"]);
+});
+
+test("row errors fail the stream after the rows before them", async (t) => {
+  const seen = [];
+  const error = await t.throwsAsync(
+    pipeline(
+      Readable.from(["a,b\n1,x\n2\n3,x\n"]),
+      csv({ strict: true, batchSize: 1 }).on("data", (row) => seen.push(row.a)),
+    ),
+    { instanceOf: RangeError },
+  );
+  t.is(error.message, "Row length does not match headers");
+  t.deepEqual(seen, ["1"]);
+});
+
+test("a slow writable paces a piped file", async (t) => {
+  let most = 0;
+  const stream = csv({ batchSize: 5 });
+  await pipeline(
+    Readable.from([rows(500)]),
+    stream,
+    new Writable({
+      objectMode: true,
+      highWaterMark: 1,
+      write(row, encoding, callback) {
+        most = Math.max(most, stream.readableLength);
+        setImmediate(callback);
+      },
+    }),
+  );
+  t.true(most <= 16 + 5);
+});
@@ -0,0 +1,11 @@
+import test from "ava";
+import { CsvParser } from "../index.js";
+
+// Hooks are registered in Rust by an addon embedding the parser; this one
+// registers none
+test("an unregistered batchHook is refused", (t) => {
+  t.throws(() => new CsvParser({ batchHook: "enrich" }), {
+    instanceOf: TypeError,
+    message: 'batchHook "enrich" is not registered',
+  });
+});
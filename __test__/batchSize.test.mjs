@@ -0,0 +1,52 @@
+import test from "ava";
+import { CsvParser } from "../index.js";
+import { collect } from "./helpers/helper.mjs";
+
+const input = "a\n1\n2\n3\n4\n5\n6\n7";
+
+test("push returns at most batchSize rows", (t) => {
+  const parser = new CsvParser({ batchSize: 3 });
+  const batches = [parser.push(Buffer.from(input))];
+  let rows;
+  while ((rows = parser.next()).length > 0) {
+    batches.push(rows);
+  }
+  while ((rows = parser.flush()).length > 0) {
+    batches.push(rows);
+  }
+
+  t.deepEqual(
+    batches.map((batch) => batch.map((row) => row.a)),
+    [["1", "2", "3"], ["4", "5", "6"], ["7"]],
+  );
+});
+
+test("flush drains rows left behind by push", (t) => {
+  const parser = new CsvParser({ batchSize: 2 });
+  t.is(parser.push(Buffer.from(input)).length, 2);
+
+  const rows = [];
+  let batch;
+  while ((batch = parser.flush()).length > 0) {
+    t.true(batch.length <= 2);
+    rows.push(...batch);
+  }
+  t.deepEqual(
+    rows.map((row) => row.a),
+    ["3", "4", "5", "6", "7"],
+  );
+});
+
+test("batchSize must be positive", (t) => {
+  t.throws(() => new CsvParser({ batchSize: 0 }), {
+    message: /batchSize must be a positive integer/,
+  });
+});
+
+test("stream emits every row with batchSize", async (t) => {
+  const { error, lines } = await collect("option-batch-size", { batchSize: 2 });
+
+  t.false(error, "no err");
+  t.is(lines.length, 7, "7 rows");
+  t.is(lines[6].a, "7", "last row");
+});
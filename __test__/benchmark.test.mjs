@@ -0,0 +1,19 @@
+import test from "ava";
+import { benchmark } from "../main.js";
+
+test("benchmark reports micro, macro and fixture results", (t) => {
+  const results = benchmark({ rows: 100, columns: 4, iterations: 1 });
+
+  t.true(results.some((result) => result.kind === "micro"));
+  t.true(results.some((result) => result.kind === "macro"));
+  t.deepEqual(
+    results.filter((result) => result.kind === "fixture").map((result) => result.name),
+    ["quoted-heavy", "wide rows", "CRLF line endings", "UTF-16LE input"],
+  );
+  for (const result of results) {
+    // Wide rows hold 16 times the cells, in a 16th of the rows
+    t.is(result.rows, result.name === "wide rows" ? 6 : 100, result.name);
+    t.true(result.bytesPerSecond > 0, result.name);
+    t.true(result.rowsPerSecond > 0, result.name);
+  }
+});
@@ -0,0 +1,18 @@
+import test from "ava";
+import { CsvParser } from "../main.js";
+
+const aliases = { email: ["E-Mail", "email_address"], name: ["Full Name"] };
+
+test("columnAliases reads differently named headers under one key", (t) => {
+  const rows = (input) => new CsvParser({ columnAliases: aliases }).push(input);
+  t.deepEqual(rows("Email,Full Name\na@b.c,Ann\n"), [{ email: "a@b.c", name: "Ann" }]);
+  t.deepEqual(rows("E-MAIL,NAME,id\nb@c.d,Bob,2\n"), [{ email: "b@c.d", name: "Bob", id: "2" }]);
+  t.deepEqual(rows("email_address\nc@d.e\n"), [{ email: "c@d.e" }]);
+});
+
+test("columnAliases rejects a name listed under two keys", (t) => {
+  t.throws(() => new CsvParser({ columnAliases: { email: ["mail"], contact: ["MAIL"] } }), {
+    instanceOf: TypeError,
+    message: 'columnAliases: "mail" names both "contact" and "email"',
+  });
+});
@@ -0,0 +1,91 @@
+import test from "ava";
+import { Readable } from "stream";
+import csv, { CsvParser } from "../main.js";
+
+test("columnTypes parses dates with a format and time zone", (t) => {
+  const parser = new CsvParser({
+    columnTypes: {
+      created_at: {
+        type: "date",
+        format: "%d/%m/%Y %H:%M",
+        tz: "Europe/Oslo",
+      },
+    },
+  });
+  const rows = parser.push("id,created_at\n1,02/01/2024 04:04\n2,\n3,soon\n");
+
+  t.true(rows[0].created_at instanceof Date);
+  t.is(rows[0].created_at.toISOString(), "2024-01-02T03:04:00.000Z");
+  t.is(rows[1].created_at, null);
+  t.is(rows[2].created_at, "soon");
+});
+
+test("columnTypes returns epoch millis for ISO values", async (t) => {
+  const rows = await Readable.from([
+    "ts\n2024-01-02T03:04:00+01:00\n2024-01-02\n",
+  ])
+    .pipe(csv({ columnTypes: { ts: { type: "date", output: "millis" } } }))
+    .toArray();
+
+  t.deepEqual(rows, [{ ts: 1704161040000 }, { ts: 1704153600000 }]);
+});
+
+test("columnTypes rejects unknown types and zones", (t) => {
+  t.throws(() => new CsvParser({ columnTypes: { a: { type: "int" } } }), {
+    message: /columnTypes.a.type must be "date"/,
+  });
+  t.throws(
+    () => new CsvParser({ columnTypes: { a: { type: "date", tz: "Mars" } } }),
+    { message: /Unknown time zone "Mars"/ },
+  );
+});
+
+test("columnTypes parses numbers with the locale's separators", async (t) => {
+  const input = "item;price;qty\nA;1.234,56;2\nB;;-3\nC;n/a;1.000\n";
+  const rows = await Readable.from([input])
+    .pipe(
+      csv({
+        separator: ";",
+        decimalSeparator: ",",
+        thousandsSeparator: ".",
+        columnTypes: { price: { type: "number" }, qty: { type: "number" } },
+      }),
+    )
+    .toArray();
+
+  t.deepEqual(rows, [
+    { item: "A", price: 1234.56, qty: 2 },
+    { item: "B", price: null, qty: -3 },
+    { item: "C", price: "n/a", qty: 1000 },
+  ]);
+});
+
+test("number columns keep their separators across checkpoints", (t) => {
+  const parser = new CsvParser({
+    decimalSeparator: ",",
+    columnTypes: { n: { type: "number" } },
+  });
+  parser.push("n\n");
+  const restored = CsvParser.fromCheckpoint(parser.getCheckpoint());
+  t.deepEqual(restored.push('"0,5"\n'), [{ n: 0.5 }]);
+  t.throws(() => new CsvParser({ decimalSeparator: ".", thousandsSeparator: "." }), {
+    instanceOf: TypeError,
+    message: "decimalSeparator and thousandsSeparator must differ, both are '.'",
+  });
+});
+
+test("number columns normalize currency and percent cells", (t) => {
+  const parser = new CsvParser({
+    columnTypes: {
+      amount: { type: "number", currency: true },
+      rate: { type: "number", percent: true },
+    },
+  });
+  const rows = parser.push('amount,rate\n"$1,234.50",12.5%\n(€20),3\nUSD 5,n/a\n');
+
+  t.deepEqual(rows, [
+    { amount: 1234.5, rate: 0.125 },
+    { amount: -20, rate: 3 },
+    { amount: "USD 5", rate: "n/a" },
+  ]);
+});
@@ -0,0 +1,61 @@
+import test from "ava";
+import { Readable } from "stream";
+import { gunzipSync } from "zlib";
+import { CsvRewriter, CsvWriter, rewrite } from "../main.js";
+
+const rows = Array.from({ length: 500 }, (_, i) => ({ id: i, name: `row ${i % 7}` }));
+const csv = "id,name\n" + rows.map(({ id, name }) => `${id},${name}\n`).join("");
+
+test("writeBuffer gzips output until finish", (t) => {
+  const writer = new CsvWriter({ compression: { type: "gzip", level: 9 } });
+  const parts = [writer.writeBuffer(rows.slice(0, 250)), writer.writeBuffer(rows.slice(250))];
+  parts.push(writer.finish());
+  const gzipped = Buffer.concat(parts);
+  t.true(gzipped.length < csv.length / 4);
+  t.is(gunzipSync(gzipped).toString(), csv);
+  t.is(writer.finish().length, 0);
+
+  // Output after finish() is another member of the same stream
+  const more = Buffer.concat([writer.writeBuffer([{ id: 500, name: "x" }]), writer.finish()]);
+  t.is(gunzipSync(Buffer.concat([gzipped, more])).toString(), csv + "500,x\n");
+});
+
+test("fromIterable ends the compressed stream", async (t) => {
+  const chunks = await CsvWriter.fromIterable(rows, { compression: { type: "gzip" } }).toArray();
+  t.is(gunzipSync(Buffer.concat(chunks)).toString(), csv);
+});
+
+test("zstd output starts with a zstd frame", (t) => {
+  const writer = new CsvWriter({ compression: { type: "zstd" } });
+  const out = Buffer.concat([writer.writeBuffer(rows), writer.finish()]);
+  t.is(out.readUInt32LE(0), 0xfd2fb528);
+  t.true(out.length < csv.length / 4);
+});
+
+test("rewrite compresses its output", async (t) => {
+  const chunks = await Readable.from([csv.replaceAll(",", ";")])
+    .pipe(rewrite({ input: { separator: ";" }, compression: { type: "gzip", level: 1 } }))
+    .toArray();
+  t.is(gunzipSync(Buffer.concat(chunks)).toString(), csv);
+
+  const rewriter = new CsvRewriter({ output: { compression: { type: "gzip" } } });
+  const out = Buffer.concat([rewriter.push("a\n1\n"), rewriter.flush()]);
+  t.is(gunzipSync(out).toString(), "a\n1\n");
+});
+
+test("compression options are checked", (t) => {
+  t.throws(() => new CsvWriter({ compression: { type: "brotli" } }), {
+    instanceOf: TypeError,
+    message: 'compression.type must be "gzip" or "zstd", got "brotli"',
+  });
+  t.throws(() => new CsvWriter({ compression: { type: "gzip", level: 10 } }), {
+    message: "compression.level must be between 0 and 9 for gzip, got 10",
+  });
+  t.throws(() => new CsvRewriter({ compression: { type: "zstd", level: 0 } }), {
+    message: "compression.level must be between 1 and 22 for zstd, got 0",
+  });
+  const writer = new CsvWriter({ compression: { type: "gzip" } });
+  t.throws(() => writer.write([{ a: 1 }]), {
+    message: "write() cannot compress; use writeBuffer() with compression",
+  });
+});
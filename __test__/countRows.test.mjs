@@ -0,0 +1,15 @@
+import test from "ava";
+import { countRows } from "../index.js";
+import { fixture } from "./helpers/helper.mjs";
+
+test("countRows counts records, not lines", (t) => {
+  const input = Buffer.from('a,b\n1,"x\ny"\n# note\n2,3');
+
+  t.is(countRows(input), 3);
+  t.is(countRows(input, { skipComments: true }), 2);
+  t.is(countRows(input, { headers: false }), 4);
+});
+
+test("countRows reads a file path", (t) => {
+  t.is(countRows(fixture("large-dataset.csv")), 7268);
+});
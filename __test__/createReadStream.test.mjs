@@ -0,0 +1,66 @@
+import test from "ava";
+import fs from "fs";
+import os from "os";
+import path from "path";
+import { createReadStream } from "../main.js";
+import { CsvFileReader } from "../index.js";
+
+function input(name, contents) {
+  const file = path.join(
+    os.tmpdir(),
+    `fast-csv-parser-createReadStream-${name}.csv`,
+  );
+  fs.writeFileSync(file, contents);
+  return file;
+}
+
+const rows = Array.from({ length: 1000 }, (_, i) => `${i},${i * 2}`);
+const numbers = input("numbers", `n,double\n${rows.join("\n")}`);
+
+test("createReadStream emits the rows of a file", async (t) => {
+  const seen = [];
+  for await (const row of createReadStream(numbers)) {
+    seen.push(row);
+  }
+
+  t.is(seen.length, 1000);
+  t.deepEqual(seen[0], { n: "0", double: "0" });
+  t.deepEqual(seen[999], { n: "999", double: "1998" });
+});
+
+test("createReadStream stops parsing while the consumer is slow", async (t) => {
+  const stream = createReadStream(numbers, { highWaterMark: 4 });
+  await new Promise((resolve) => stream.once("readable", resolve));
+  await new Promise((resolve) => setTimeout(resolve, 50));
+
+  t.true(stream.readableLength <= 4);
+  stream.destroy();
+});
+
+test("createReadStream passes parser options and reports errors", async (t) => {
+  const semicolons = input("semicolons", "a;b\n1;2\n");
+  const seen = [];
+  for await (const row of createReadStream(semicolons, { separator: ";" })) {
+    seen.push(row);
+  }
+  t.deepEqual(seen, [{ a: "1", b: "2" }]);
+
+  await t.throwsAsync(
+    async () => {
+      for await (const row of createReadStream(`${semicolons}.missing`)) {
+        t.fail(`unexpected row ${row}`);
+      }
+    },
+    { message: /Cannot open/ },
+  );
+});
+
+test("CsvFileReader reads rows in batches", async (t) => {
+  const reader = new CsvFileReader(numbers);
+  t.is(reader.headers, null);
+
+  t.is((await reader.read(10)).length, 10);
+  t.deepEqual(reader.headers, ["n", "double"]);
+  t.is((await reader.read(5000)).length, 990);
+  t.deepEqual(await reader.read(10), []);
+});
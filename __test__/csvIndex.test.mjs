@@ -0,0 +1,29 @@
+import test from "ava";
+import fs from "fs";
+import { CsvIndex } from "../index.js";
+import { fixture } from "./helpers/helper.mjs";
+
+test("CsvIndex looks up rows by key", (t) => {
+  const index = CsvIndex.build(fixture("basic.csv"), { column: "a" });
+
+  t.is(index.size, 1);
+  t.deepEqual(index.lookup("1"), [{ a: "1", b: "2", c: "3" }]);
+  t.deepEqual(index.lookup("missing"), []);
+});
+
+test("CsvIndex locations point into the file", (t) => {
+  const file = fixture("quotes+newlines.csv");
+  const index = CsvIndex.build(file, { column: "a" });
+  const contents = fs.readFileSync(file);
+
+  for (const key of ["1", "2"]) {
+    const [{ offset, length }] = index.locate(key);
+    t.true(contents.subarray(offset, offset + length).toString().startsWith(key));
+  }
+});
+
+test("CsvIndex reports unknown columns", (t) => {
+  t.throws(() => CsvIndex.build(fixture("basic.csv"), { column: "z" }), {
+    message: /Column z not found/,
+  });
+});
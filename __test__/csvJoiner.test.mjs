@@ -0,0 +1,46 @@
+import test from "ava";
+import { CsvJoiner } from "../index.js";
+
+const customers = Buffer.from("id,name\n1,Ann\n2,Bob\n2,Bo\n");
+
+test("CsvJoiner inner join across chunks", (t) => {
+  const joiner = new CsvJoiner(customers, { key: "customer", rightKey: "id" });
+  const rows = [
+    ...joiner.push("customer,amount\n1,10\n3,"),
+    ...joiner.push(Buffer.from("20\n2,30")),
+    ...joiner.flush(),
+  ];
+
+  t.is(joiner.size, 2);
+  t.deepEqual(rows, [
+    { customer: "1", amount: "10", name: "Ann" },
+    { customer: "2", amount: "30", name: "Bob" },
+    { customer: "2", amount: "30", name: "Bo" },
+  ]);
+});
+
+test("CsvJoiner left join keeps unmatched rows", (t) => {
+  const joiner = new CsvJoiner(customers, {
+    key: "id",
+    type: "left",
+    left: { separator: ";" },
+  });
+  const rows = [...joiner.push("id;name\n3;Cy\n1;Al\n"), ...joiner.flush()];
+
+  t.deepEqual(rows, [
+    { id: "3", name: "Cy", name_right: "" },
+    { id: "1", name: "Al", name_right: "Ann" },
+  ]);
+});
+
+test("CsvJoiner rejects unknown columns and join types", (t) => {
+  t.throws(() => new CsvJoiner(customers, { key: "missing" }), {
+    message: /Column missing not found/,
+  });
+  t.throws(() => new CsvJoiner(customers, { key: "id", type: "outer" }), {
+    message: /type must be "inner" or "left"/,
+  });
+  t.throws(() => new CsvJoiner("does-not-exist.csv", { key: "id" }), {
+    message: /Cannot open/,
+  });
+});
@@ -0,0 +1,33 @@
+import test from "ava";
+import { CsvStats } from "../index.js";
+
+test("CsvStats aggregates columns across chunks", (t) => {
+  const stats = new CsvStats({ topK: 1 });
+  stats.push("amount,city\n10,Oslo\n2");
+  stats.push(Buffer.from("0,Oslo\n,Rome\n30,Oslo"));
+
+  const [amount, city] = stats.getStats();
+  t.is(stats.rows, 4);
+  t.like(amount, {
+    name: "amount",
+    count: 3,
+    nullCount: 1,
+    numericCount: 3,
+    min: 10,
+    max: 30,
+    mean: 20,
+    stddev: 10,
+  });
+  t.deepEqual(city.top, [{ value: "Oslo", count: 3 }]);
+  t.is(city.mean, undefined);
+});
+
+test("CsvStats takes parser options", (t) => {
+  const stats = new CsvStats({ parser: { separator: ";" } });
+  stats.push("a;b\n1;2\n");
+
+  t.deepEqual(
+    stats.getStats().map((column) => column.name),
+    ["a", "b"],
+  );
+});
@@ -0,0 +1,68 @@
+import test from "ava";
+import fs from "fs";
+import csv from "../main.js";
+import { CsvParser } from "../index.js";
+import { fixture } from "./helpers/helper.mjs";
+
+test("dedupe: true drops repeated rows", (t) => {
+  const parser = new CsvParser({ dedupe: true });
+  const rows = [
+    ...parser.push("a,b\n1,x\n1,y\n1,"),
+    ...parser.push('x\n"1",x\n'),
+    ...parser.flush(),
+  ];
+
+  t.deepEqual(rows, [
+    { a: "1", b: "x" },
+    { a: "1", b: "y" },
+  ]);
+  t.is(parser.duplicates, 2);
+});
+
+test("dedupe compares key columns only", (t) => {
+  const parser = new CsvParser({ dedupe: { keys: ["b"] } });
+  const rows = parser.push("a,b\n1,x\n2,x\n3,y\n");
+
+  t.deepEqual(
+    rows.map((row) => row.a),
+    ["1", "3"],
+  );
+  t.is(parser.duplicates, 1);
+});
+
+test("dedupe with a Bloom filter", (t) => {
+  const parser = new CsvParser({
+    dedupe: { bloom: { capacity: 1000, falsePositiveRate: 0.001 } },
+  });
+  const rows = parser.push("a\n1\n2\n1\n3\n2\n");
+
+  t.deepEqual(
+    rows.map((row) => row.a),
+    ["1", "2", "3"],
+  );
+  t.is(parser.duplicates, 2);
+});
+
+test("dedupe reports unknown key columns", (t) => {
+  const parser = new CsvParser({ dedupe: { keys: ["missing"] } });
+
+  t.throws(() => parser.push("a\n1\n"), { message: /Column missing not found/ });
+});
+
+test("stream reports the duplicates dropped", async (t) => {
+  const parser = csv({ dedupe: { keys: ["email"] } });
+  const lines = [];
+  await new Promise((resolve, reject) => {
+    fs.createReadStream(fixture("option-dedupe.csv"))
+      .pipe(parser)
+      .on("data", (line) => lines.push(line))
+      .on("end", resolve)
+      .on("error", reject);
+  });
+
+  t.deepEqual(
+    lines.map((line) => line.id),
+    ["1", "2"],
+  );
+  t.is(parser.duplicates, 2);
+});
@@ -0,0 +1,59 @@
+import test from "ava";
+import { CsvParser, head } from "../index.js";
+
+function parse(input, options) {
+  const parser = new CsvParser(options);
+  return [...parser.push(Buffer.from(input)), ...parser.flush()];
+}
+
+test("tsv and psv dialects set the separator", (t) => {
+  t.deepEqual(parse("a\tb\n1\t2\n", { dialect: "tsv" }), [{ a: "1", b: "2" }]);
+  t.deepEqual(parse("a|b\n1|2\n", { dialect: "psv" }), [{ a: "1", b: "2" }]);
+});
+
+test("options given with a dialect override it", (t) => {
+  t.deepEqual(parse("a;b\n1;2\n", { dialect: "tsv", separator: ";" }), [{ a: "1", b: "2" }]);
+});
+
+test("excel dialect reads sep= lines and formula strings", (t) => {
+  t.deepEqual(parse('sep=;\r\nzip;name\r\n="01234";Ann\r\n', { dialect: "excel" }), [
+    { zip: "01234", name: "Ann" },
+  ]);
+});
+
+test("postgres dialect reads the COPY text format", (t) => {
+  const input = [
+    "id\tnote\tshipped_at",
+    "1\tline 1\\nline 2\t\\N",
+    "2\ttab\\there, back\\\\slash, \\101\\x42\t2024-01-02",
+    "\\.",
+    "",
+  ].join("\n");
+
+  t.deepEqual(parse(input, { dialect: "postgres" }), [
+    { id: "1", note: "line 1\nline 2", shipped_at: null },
+    { id: "2", note: "tab\there, back\\slash, AB", shipped_at: "2024-01-02" },
+  ]);
+});
+
+test("postgres dialect keeps quotes and escaped line breaks as data", (t) => {
+  t.deepEqual(parse('a\tb\n"x\tline\\\nbreak\n', { dialect: "postgres" }), [
+    { a: '"x', b: "line\nbreak" },
+  ]);
+});
+
+test("nullValue makes matching cells null", (t) => {
+  t.deepEqual(parse("a,b\nNULL,x\n", { nullValue: "NULL" }), [{ a: null, b: "x" }]);
+});
+
+test("head does not guess the separator of a dialect", (t) => {
+  const { rows, dialect } = head(Buffer.from("a,b\tc\n1,2\t3\n"), 1, { dialect: "tsv" });
+  t.deepEqual(rows, [{ "a,b": "1,2", c: "3" }]);
+  t.is(dialect.separator, "\t");
+});
+
+test("an unknown dialect is refused", (t) => {
+  t.throws(() => new CsvParser({ dialect: "json" }), {
+    message: 'dialect must be "csv", "tsv", "psv", "excel" or "postgres", got "json"',
+  });
+});
@@ -0,0 +1,62 @@
+import test from "ava";
+import { mkdtempSync, writeFileSync } from "fs";
+import { tmpdir } from "os";
+import { join } from "path";
+import { diff } from "../main.js";
+
+const files = (old, next) => {
+  const dir = mkdtempSync(join(tmpdir(), "diff-"));
+  writeFileSync(join(dir, "old.csv"), old);
+  writeFileSync(join(dir, "new.csv"), next);
+  return [join(dir, "old.csv"), join(dir, "new.csv")];
+};
+
+test("diff reports added, removed and changed rows in key order", (t) => {
+  const [oldPath, newPath] = files(
+    "id,name,qty\n3,c,1\n1,a,1\n2,b,1\n",
+    "id,qty,name\n1,1,a\n4,1,d\n2,5,B\n",
+  );
+  const changes = diff(oldPath, newPath, { key: "id" });
+  t.deepEqual(changes.columns, ["id", "name", "qty"]);
+  t.deepEqual(changes.next(), [
+    {
+      type: "changed",
+      key: "2",
+      before: { id: "2", name: "b", qty: "1" },
+      after: { id: "2", qty: "5", name: "B" },
+      columns: ["name", "qty"],
+    },
+    { type: "removed", key: "3", before: { id: "3", name: "c", qty: "1" }, columns: [] },
+    { type: "added", key: "4", after: { id: "4", qty: "1", name: "d" }, columns: [] },
+  ]);
+  t.deepEqual(changes.next(), []);
+  t.is(changes.unchanged, 1);
+});
+
+test("diff returns changes in batches with a small memory limit", (t) => {
+  const old = ["id,v", ...Array.from({ length: 50 }, (_, i) => `${i},${i}`)].join("\n");
+  const next = ["id,v", ...Array.from({ length: 50 }, (_, i) => `${i},${i % 10 ? i : "x"}`)].join(
+    "\n",
+  );
+  const changes = diff(...files(old, next), { key: "id", memoryLimit: 64 });
+  t.is(changes.next(3).length, 3);
+  t.is(changes.next(3).length, 2);
+  t.is(changes.next(3).length, 0);
+  t.is(changes.unchanged, 45);
+});
+
+test("diff uses the parser options for both files", (t) => {
+  const [oldPath, newPath] = files("id;v\n1;a\n", "id;v\n1;b\n");
+  const [change] = diff(oldPath, newPath, { key: "id", parser: { separator: ";" } }).next();
+  t.deepEqual(change.columns, ["v"]);
+});
+
+test("diff fails on a missing key column or file", (t) => {
+  const [oldPath, newPath] = files("id\n1\n", "name\na\n");
+  t.throws(() => diff(oldPath, newPath, { key: "id" }), {
+    message: "New input: Column id not found",
+  });
+  t.throws(() => diff(join(tmpdir(), "no-such.csv"), newPath, { key: "id" }), {
+    message: /^Cannot open .*no-such\.csv/,
+  });
+});
@@ -0,0 +1,35 @@
+import test from "ava";
+import { mkdtempSync, writeFileSync } from "fs";
+import { tmpdir } from "os";
+import { join } from "path";
+import { distinct } from "../main.js";
+
+const input = Buffer.from("country,city\nFR,Paris\nDE,Berlin\nFR,Lyon\nFR,Paris\n");
+
+test("distinct returns the values of a column in order of first appearance", (t) => {
+  t.deepEqual(distinct(input, "country"), ["FR", "DE"]);
+  t.deepEqual(distinct(input, ["country", "city"]), [
+    ["FR", "Paris"],
+    ["DE", "Berlin"],
+    ["FR", "Lyon"],
+  ]);
+});
+
+test("distinct counts rows per value", (t) => {
+  t.deepEqual(distinct(input, "country", { counts: true }), [
+    { value: "FR", count: 3 },
+    { value: "DE", count: 1 },
+  ]);
+  t.deepEqual(distinct(input, ["city"], { counts: true })[0], { value: ["Paris"], count: 2 });
+});
+
+test("distinct reads a file with parser options", (t) => {
+  const path = join(mkdtempSync(join(tmpdir(), "distinct-")), "in.csv");
+  writeFileSync(path, "# export\nid;tag\n1;a\n2;\\N\n3;a\n");
+  const options = { parser: { separator: ";", skipComments: true, nullValue: "\\N" } };
+  t.deepEqual(distinct(path, "tag", options), ["a", ""]);
+});
+
+test("distinct fails on a missing column", (t) => {
+  t.throws(() => distinct(input, "zip"), { message: "Column zip not found" });
+});
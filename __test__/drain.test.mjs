@@ -0,0 +1,81 @@
+import test from "ava";
+import { PassThrough } from "stream";
+import csv, { drainAll } from "../main.js";
+
+test("drain flushes a partial final row", async (t) => {
+  const parser = csv();
+  const lines = [];
+  parser.on("data", (line) => lines.push(line));
+
+  parser.write(Buffer.from("a,b\n1,2\n3,4"));
+  await parser.drain();
+
+  t.deepEqual(lines, [
+    { a: "1", b: "2" },
+    { a: "3", b: "4" },
+  ]);
+  t.true(parser.writableFinished);
+});
+
+test("drainAll unpipes sources and ends every active stream", async (t) => {
+  const source = new PassThrough();
+  const first = csv();
+  const second = csv();
+  const lines = [];
+  first.on("data", (line) => lines.push(line));
+  second.on("data", (line) => lines.push(line));
+
+  source.pipe(first);
+  source.write("x\n1\n2");
+  second.write(Buffer.from("y\n3"));
+  await new Promise((resolve) => setImmediate(resolve));
+
+  await drainAll();
+
+  t.deepEqual(lines.map((line) => Object.values(line)[0]).sort(), ["1", "2", "3"]);
+  t.notThrows(() => source.write("ignored\n"));
+});
+
+test("drain destroys a stream nobody reads after the timeout", async (t) => {
+  const parser = csv();
+  const rows = Array.from({ length: 100 }, (_, i) => `${i}`).join("\n");
+  parser.write(Buffer.from(`n\n${rows}\n`));
+  await new Promise((resolve) => setImmediate(resolve));
+
+  await parser.drain({ timeout: 20 });
+  t.true(parser.destroyed);
+  t.false(parser.writableFinished);
+});
+
+test("drainAll passes the checkpoint of each finished stream", async (t) => {
+  const parser = csv();
+  const input = Buffer.from("a\n1\n2\n");
+  parser.on("data", () => {});
+  parser.write(input);
+  const stuck = csv();
+  stuck.write(Buffer.from(`b\n${"1\n".repeat(100)}`));
+  await new Promise((resolve) => setImmediate(resolve));
+
+  const checkpoints = [];
+  await drainAll({
+    timeout: 20,
+    onCheckpoint: async (checkpoint, stream) => checkpoints.push([checkpoint, stream]),
+  });
+
+  t.is(checkpoints.length, 1);
+  t.is(checkpoints[0][1], parser);
+  t.is(checkpoints[0][0].offset, input.length);
+  t.true(stuck.destroyed);
+});
+
+test("drainAll skips streams that never took input", async (t) => {
+  const unused = csv();
+  let ended = false;
+  unused.on("finish", () => {
+    ended = true;
+  });
+
+  await drainAll();
+  t.false(ended);
+  t.false(unused.writableEnded);
+});
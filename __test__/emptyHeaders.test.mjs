@@ -0,0 +1,59 @@
+import test from "ava";
+import { CsvParser } from "../index.js";
+import csv from "../main.js";
+import { Readable } from "stream";
+
+function parse(input, options) {
+  const parser = new CsvParser(options);
+  return [...parser.push(Buffer.from(input)), ...parser.flush()];
+}
+
+test("empty and _ headers are dropped and listed by default", (t) => {
+  const parser = new CsvParser({});
+  const rows = [...parser.push(Buffer.from("a,,_,d\n1,2,3,4\n")), ...parser.flush()];
+  t.deepEqual(rows, [{ a: "1", d: "4" }]);
+  t.deepEqual(parser.getDroppedColumns(), [1, 2]);
+  t.deepEqual(new CsvParser({}).getDroppedColumns(), []);
+});
+
+test("keep-positional keys the columns by their index", (t) => {
+  t.deepEqual(parse("a,,_,d\n1,2,3,4\n", { emptyHeaders: "keep-positional" }), [
+    { a: "1", _1: "2", _2: "3", d: "4" },
+  ]);
+});
+
+test("error fails the header row", (t) => {
+  t.throws(() => parse("a,,c\n1,2,3\n", { emptyHeaders: "error" }), {
+    message: /Column 2 has an empty or "_" header/,
+  });
+  t.deepEqual(parse("a,b\n1,2\n", { emptyHeaders: "error" }), [{ a: "1", b: "2" }]);
+});
+
+test("an invalid emptyHeaders throws", (t) => {
+  t.throws(() => new CsvParser({ emptyHeaders: "skip" }), {
+    instanceOf: TypeError,
+    message: 'emptyHeaders must be "drop", "keep-positional" or "error", got "skip"',
+  });
+});
+
+test("the stream emits droppedColumns after headers", async (t) => {
+  const events = [];
+  const stream = Readable.from(["id,,name\n1,x,Ann\n"]).pipe(csv());
+  stream.on("headers", (headers) => events.push(["headers", headers]));
+  stream.on("droppedColumns", (columns) => events.push(["droppedColumns", columns]));
+  const rows = await stream.toArray();
+  t.deepEqual(rows, [{ id: "1", name: "Ann" }]);
+  t.deepEqual(events, [
+    ["headers", ["id", "", "name"]],
+    ["droppedColumns", [1]],
+  ]);
+  t.deepEqual(stream.getDroppedColumns(), [1]);
+});
+
+test("no droppedColumns event without empty headers", async (t) => {
+  let emitted = false;
+  const stream = Readable.from(["id,name\n1,Ann\n"]).pipe(csv());
+  stream.on("droppedColumns", () => (emitted = true));
+  await stream.toArray();
+  t.false(emitted);
+});
@@ -0,0 +1,42 @@
+import test from "ava";
+import { CsvParser } from "../index.js";
+import { head } from "../main.js";
+
+function parse(options, input) {
+  const parser = new CsvParser(options);
+  return [...parser.push(input), ...parser.flush()];
+}
+
+test("UTF-32LE input is detected from its BOM", (t) => {
+  const text = "name,city\nJosé,Oslo\n";
+  const input = Buffer.alloc(4 + text.length * 4);
+  input.writeUInt32LE(0xfeff, 0);
+  [...text].forEach((char, i) => input.writeUInt32LE(char.codePointAt(0), 4 + i * 4));
+
+  t.deepEqual(parse({}, input), [{ name: "José", city: "Oslo" }]);
+});
+
+test("encoding decodes EBCDIC input with NL line ends", (t) => {
+  // "name,city\x85José,Oslo\x85" in IBM037
+  const input = Buffer.from("958194856b8389a3a815d196a2516bd6a2939615", "hex");
+
+  t.deepEqual(parse({ encoding: "ibm037" }, input), [{ name: "José", city: "Oslo" }]);
+});
+
+test("encoding auto guesses legacy encodings without a BOM", (t) => {
+  const latin = Buffer.from("name,city\nJos\xe9,Z\xfcrich\nFran\xe7ois,Gen\xe8ve\n", "latin1");
+  t.deepEqual(parse({ encoding: "auto" }, latin)[0], { name: "José", city: "Zürich" });
+
+  const sjis = Buffer.from("96bc914f2c93738e730a8e5293632c938c8b9e0a", "hex");
+  t.deepEqual(parse({ encoding: "auto" }, sjis), [{ 名前: "山田", 都市: "東京" }]);
+
+  t.is(head(sjis, 1, { encoding: "auto" }).dialect.encoding, "shift_jis");
+  t.deepEqual(parse({ encoding: "auto" }, Buffer.from("a\né\n")), [{ a: "é" }]);
+});
+
+test("encoding rejects encodings that are not ASCII-compatible and unknown labels", (t) => {
+  t.throws(() => new CsvParser({ encoding: "iso-2022-jp" }), {
+    message: /decode it with a Transcoder first/,
+  });
+  t.throws(() => new CsvParser({ encoding: "klingon" }), { message: /klingon/ });
+});
@@ -0,0 +1,35 @@
+import test from "ava";
+import csv, { CsvWriter } from "../main.js";
+
+function parse(input, opts) {
+  return new Promise((resolve) => {
+    const lines = [];
+    const parser = csv(opts);
+    parser.on("data", (line) => lines.push(line));
+    parser.on("end", () => resolve(lines));
+    parser.end(Buffer.from(input));
+  });
+}
+
+test("excel unwraps =\"...\" cells", async (t) => {
+  const lines = await parse('id,amount\n="000123",="1,50"\n', { excel: true });
+
+  t.deepEqual(lines, [{ id: "000123", amount: "1,50" }]);
+});
+
+test("writer formula guard", (t) => {
+  const writer = new CsvWriter({ formulaGuard: true });
+  const out = writer.write([
+    { name: "Ann", note: "=1+1" },
+    { name: "@Bob", note: "ok" },
+  ]);
+
+  t.is(out, "name,note\nAnn,'=1+1\n'@Bob,ok\n");
+});
+
+test("writer quotes and arrays", (t) => {
+  const writer = new CsvWriter();
+  const out = writer.write([["a,b", 'say "hi"', null, 3]]);
+
+  t.is(out, '"a,b","say ""hi""",,3\n');
+});
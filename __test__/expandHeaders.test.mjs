@@ -0,0 +1,46 @@
+import test from "ava";
+import { Readable } from "stream";
+import csv, { CsvParser } from "../main.js";
+
+test("expandHeaders nests dotted and bracketed headers", (t) => {
+  const parser = new CsvParser({ expandHeaders: true });
+  const rows = parser.push(
+    "id,address.city,address.zip,tags[0],tags[1],items[0].name,a..b\n" +
+      "1,Oslo,0150,red,blue,pen,q\n",
+  );
+
+  t.deepEqual(rows, [
+    {
+      id: "1",
+      address: { city: "Oslo", zip: "0150" },
+      tags: ["red", "blue"],
+      items: [{ name: "pen" }],
+      "a..b": "q",
+    },
+  ]);
+  t.true(Array.isArray(rows[0].tags));
+});
+
+test("expandHeaders keeps sparse arrays with skipEmptyFields", (t) => {
+  const parser = new CsvParser({ expandHeaders: true, skipEmptyFields: true });
+  const [row] = parser.push("tags[0],tags[1],meta[en]\n,blue,hi\n");
+
+  t.is(row.tags.length, 2);
+  t.false(0 in row.tags);
+  t.is(row.tags[1], "blue");
+  t.deepEqual(row.meta, { en: "hi" });
+});
+
+test("expandHeaders nests after mapHeaders and mapValues", async (t) => {
+  const rows = await Readable.from(["user_name,user_age\nAda,36\n"])
+    .pipe(
+      csv({
+        expandHeaders: true,
+        mapHeaders: ({ header }) => header.replace("_", "."),
+        mapValues: ({ value }) => value.toUpperCase(),
+      }),
+    )
+    .toArray();
+
+  t.deepEqual(rows, [{ user: { name: "ADA", age: "36" } }]);
+});
@@ -0,0 +1,61 @@
+import test from "ava";
+import { CsvParser, unpackRows } from "../main.js";
+
+const input = "a,b\n1,2,3,4\n5,6\n";
+
+function parse(options) {
+  const parser = new CsvParser(options);
+  return [...parser.push(input), ...parser.flush()];
+}
+
+test("extra cells are keyed _<index> by default", (t) => {
+  t.deepEqual(parse()[0], { a: "1", b: "2", _2: "3", _3: "4" });
+});
+
+test("extraColumns prefix renames extra keys", (t) => {
+  t.deepEqual(parse({ extraColumns: { prefix: "col" } })[0], {
+    a: "1",
+    b: "2",
+    col2: "3",
+    col3: "4",
+  });
+});
+
+test("extraColumns: 'drop' leaves extra cells out", (t) => {
+  t.deepEqual(parse({ extraColumns: "drop" }), [
+    { a: "1", b: "2" },
+    { a: "5", b: "6" },
+  ]);
+});
+
+test("extraColumns collects extra cells into an array", (t) => {
+  t.deepEqual(parse({ extraColumns: "collect" }), [
+    { a: "1", b: "2", _rest: ["3", "4"] },
+    { a: "5", b: "6" },
+  ]);
+  t.deepEqual(parse({ extraColumns: { collect: "more" } })[0].more, [
+    "3",
+    "4",
+  ]);
+  t.deepEqual(parse({ extraColumns: "collect", raw: true })[0]._rest, [
+    Buffer.from("3"),
+    Buffer.from("4"),
+  ]);
+});
+
+test("collected cells survive packing", (t) => {
+  const parser = new CsvParser({ extraColumns: "collect" });
+  const batch = parser.pushPacked(input);
+  t.is(batch.rest, "_rest");
+  t.deepEqual(unpackRows(batch), parse({ extraColumns: "collect" }));
+});
+
+test("bad extraColumns values throw", (t) => {
+  t.throws(() => new CsvParser({ extraColumns: "keep" }), {
+    message: /extraColumns must be "drop", "collect" or an object/,
+  });
+  t.throws(
+    () => new CsvParser({ extraColumns: { prefix: "x", collect: "y" } }),
+    { message: /either prefix or collect/ },
+  );
+});
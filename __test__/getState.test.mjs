@@ -0,0 +1,71 @@
+import test from "ava";
+import { CsvParser } from "../index.js";
+
+function parseWhole(input, options) {
+  const parser = new CsvParser(options);
+  return [...parser.push(Buffer.from(input)), ...parser.flush()];
+}
+
+// The rows of `input` pushed as two chunks split at every byte
+function splits(input, options) {
+  const buffer = Buffer.from(input);
+  const results = [];
+  for (let i = 1; i < buffer.length; i++) {
+    const parser = new CsvParser(options);
+    results.push([
+      ...parser.push(buffer.subarray(0, i)),
+      ...parser.push(buffer.subarray(i)),
+      ...parser.flush(),
+    ]);
+  }
+  return results;
+}
+
+test("getState reports an open quote holding back a row", (t) => {
+  const parser = new CsvParser();
+  t.deepEqual(parser.getState(), {
+    lineNumber: 1,
+    inQuote: false,
+    bufferedBytes: 0,
+    headersResolved: false,
+  });
+
+  t.deepEqual(parser.push(Buffer.from('a,b\n1,2\n3,"x\ny')), [{ a: "1", b: "2" }]);
+  t.deepEqual(parser.getState(), {
+    lineNumber: 3,
+    inQuote: true,
+    bufferedBytes: 6,
+    headersResolved: true,
+  });
+
+  t.deepEqual(parser.push(Buffer.from('"\n4,')), [{ a: "3", b: "x\ny" }]);
+  t.like(parser.getState(), { lineNumber: 5, inQuote: false, bufferedBytes: 2 });
+});
+
+test("headers are resolved from the start when given", (t) => {
+  t.true(new CsvParser({ headers: ["a"] }).getState().headersResolved);
+});
+
+test("comments split across chunks are skipped", (t) => {
+  const cases = [
+    [{ skipComments: true }, 'a,b\r\n# it\'s "open\r\n1,2\r\n  # x,"y\n3,4\n'],
+    [{ skipComments: "//" }, '// c"\na,b\n//"\n1,2\n'],
+    [{ skipComments: true, trailingComments: true }, 'a,b\n1,2 # "q\n3,4\n'],
+  ];
+  for (const [options, input] of cases) {
+    const expected = parseWhole(input, options);
+    t.true(expected.length > 0);
+    for (const rows of splits(input, options)) {
+      t.deepEqual(rows, expected);
+    }
+  }
+});
+
+test("skipLines split across chunks drops whole lines", (t) => {
+  const input = 'junk "\n#"\na,b\n1,"x\ny"\n';
+  for (const options of [{ skipLines: 2 }, { skipLines: 1, skipComments: true }]) {
+    for (const rows of splits(input, options)) {
+      t.deepEqual(rows, [{ a: "1", b: "x\ny" }]);
+    }
+  }
+});
@@ -0,0 +1,28 @@
+import test from 'ava'
+
+import { CsvParser, CsvWriter } from '../index.js'
+
+test('empty one-character options are errors, not panics', (t) => {
+  for (const option of ['separator', 'quote', 'escape', 'newline']) {
+    t.throws(() => new CsvParser({ [option]: '' }), { message: `${option} must not be empty`, code: 'InvalidArg' })
+  }
+  t.throws(() => new CsvWriter({ separator: '' }), { message: 'separator must not be empty' })
+})
+
+test('hardened sets column and buffer limits', (t) => {
+  const wide = `${Array.from({ length: 16385 }, (_, i) => `c${i}`).join(',')}\n`
+  t.throws(() => new CsvParser({ hardened: true }).push(wide), {
+    message: 'Row has 16385 columns, more than maxColumns (16384)',
+  })
+  t.is(new CsvParser().push(wide).length, 0)
+
+  // Limits that are given are kept
+  const parser = new CsvParser({ hardened: true, maxColumns: 2 })
+  t.throws(() => parser.push('a,b,c\n'), { message: 'Row has 3 columns, more than maxColumns (2)' })
+})
+
+test('hardened parsers survive serializeState', (t) => {
+  const parser = new CsvParser({ hardened: true, maxColumns: 1 })
+  const restored = CsvParser.deserializeState(parser.serializeState())
+  t.throws(() => restored.push('a,b\n'), { message: 'Row has 2 columns, more than maxColumns (1)' })
+})
@@ -0,0 +1,72 @@
+import test from "ava";
+import { head, readHeaders } from "../main.js";
+import { fileURLToPath } from "url";
+import { mkdtempSync, writeFileSync } from "fs";
+import { tmpdir } from "os";
+import { join } from "path";
+
+test("head returns the first rows, headers and a guessed dialect", (t) => {
+  const input = Buffer.from("name;age\r\nAnn;30\r\nBob;41\r\nCy;5\r\n");
+  const { rows, headers, dialect } = head(input, 2);
+  t.deepEqual(rows, [
+    { name: "Ann", age: "30" },
+    { name: "Bob", age: "41" },
+  ]);
+  t.deepEqual(headers, ["name", "age"]);
+  t.deepEqual(dialect, {
+    separator: ";",
+    quote: '"',
+    newline: "\r\n",
+    encoding: "utf-8",
+    bom: false,
+  });
+});
+
+test("head keeps a given separator", (t) => {
+  const { rows, dialect } = head(Buffer.from("a;b,c\n1;2,3\n"), 10, { separator: "," });
+  t.deepEqual(rows, [{ "a;b": "1;2", c: "3" }]);
+  t.is(dialect.separator, ",");
+});
+
+test("head reads headers without rows", (t) => {
+  const { rows, headers } = head(Buffer.from("a\tb\n1\t2\n"), 0);
+  t.deepEqual(rows, []);
+  t.deepEqual(headers, ["a", "b"]);
+});
+
+test("head reads a file", (t) => {
+  const path = join(mkdtempSync(join(tmpdir(), "head-")), "big.csv");
+  writeFileSync(path, "id|value\n" + "1|x\n".repeat(100_000));
+  const { rows, dialect } = head(path, 3);
+  t.is(rows.length, 3);
+  t.is(dialect.separator, "|");
+});
+
+test("head fails on a missing file", (t) => {
+  const missing = fileURLToPath(new URL("./fixtures/missing.csv", import.meta.url));
+  t.throws(() => head(missing, 1), { message: /^Cannot open/ });
+});
+
+test("readHeaders returns the headers and dialect without rows", (t) => {
+  t.deepEqual(readHeaders(Buffer.from("name;age\r\nAnn;30\r\n")), {
+    headers: ["name", "age"],
+    dialect: {
+      separator: ";",
+      quote: '"',
+      newline: "\r\n",
+      encoding: "utf-8",
+      bom: false,
+    },
+  });
+  t.deepEqual(readHeaders(Buffer.from("a,b")).headers, ["a", "b"]);
+  t.deepEqual(readHeaders(Buffer.from("1,2,3\n4\n"), { headers: false }).headers, ["0", "1", "2"]);
+  t.is(readHeaders(Buffer.from("")).headers, undefined);
+});
+
+test("readHeaders does not parse data rows", (t) => {
+  // The data row would fail, but is never split
+  const input = Buffer.from('a,b\n1,2,3\n"open');
+  const options = { strict: true, onUnterminatedQuote: "error" };
+  t.deepEqual(readHeaders(input, options).headers, ["a", "b"]);
+  t.throws(() => head(input, 1, options));
+});
@@ -1,5 +1,6 @@
 import test from "ava";
 import { collect } from "./helpers/helper.mjs";
+import csv, { CsvParser, readHeaders } from "../main.js";
 
 test("custom escape character", async (t) => {
   const { error, lines } = await collect("option-escape", { escape: "\\" });
@@ -26,3 +27,124 @@ test("headers option", async (t) => {
   t.false(error, "no err");
   t.snapshot(lines);
 });
+
+test("headers event fires before rows for every header mode", async (t) => {
+  const run = async (options, chunks) => {
+    const events = [];
+    const stream = csv(options);
+    stream.on("headers", (headers) => events.push(["headers", headers]));
+    stream.on("data", (row) => events.push(["data", row]));
+    for (const chunk of chunks) {
+      stream.write(chunk);
+    }
+    stream.end();
+    await new Promise((resolve) => stream.on("end", resolve));
+    return events;
+  };
+
+  t.deepEqual(await run({}, ["a,b\n", "1,2\n"]), [
+    ["headers", ["a", "b"]],
+    ["data", { a: "1", b: "2" }],
+  ]);
+  t.deepEqual(await run({ headers: ["x"] }, []), [["headers", ["x"]]]);
+  t.deepEqual(await run({ headers: false }, ["1,2\n", "3,4,5\n"]), [
+    ["headers", ["0", "1"]],
+    ["data", { 0: "1", 1: "2" }],
+    ["headers", ["0", "1", "2"]],
+    ["data", { 0: "3", 1: "4", 2: "5" }],
+  ]);
+});
+
+test("getHeaders is available before data arrives", (t) => {
+  t.deepEqual(csv({ headers: ["a", "b"] }).getHeaders(), ["a", "b"]);
+  t.is(csv().getHeaders(), null);
+
+  const parser = new CsvParser({ headers: false });
+  t.is(parser.getHeaders(), null);
+  parser.push("1\n2,3\n");
+  t.deepEqual(parser.getHeaders(), ["0", "1"]);
+});
+
+test("onceHeaders resolves before the first row", async (t) => {
+  const seen = [];
+  const stream = csv({ onHeaders: (headers) => seen.push(headers) });
+  const headers = stream.onceHeaders();
+  stream.end("a,b\n1,2\n");
+
+  t.deepEqual(await headers, ["a", "b"]);
+  t.deepEqual(seen, [["a", "b"]]);
+  t.deepEqual(await stream.toArray(), [{ a: "1", b: "2" }]);
+  t.deepEqual(await stream.onceHeaders(), ["a", "b"]);
+});
+
+test("onceHeaders resolves with null for empty input", async (t) => {
+  const stream = csv();
+  const headers = stream.onceHeaders();
+  stream.end();
+  stream.resume();
+
+  t.is(await headers, null);
+});
+
+// A finance export whose column names hold the separator, a line break and
+// escaped quotes
+const quotedHeaders = '"Revenue, net","Cost\nQ1","Say ""hi""",id\n1,2,3,4\n';
+const quotedRow = { "Revenue, net": "1", "Cost\nQ1": "2", 'Say "hi"': "3", id: "4" };
+
+test("quoted headers keep their separators, line breaks and quotes", (t) => {
+  const parse = (options) => {
+    const parser = new CsvParser(options);
+    return [...parser.push(quotedHeaders), ...parser.flush()];
+  };
+  t.deepEqual(parse({}), [quotedRow]);
+  t.deepEqual(parse({ separator: "auto" }), [quotedRow]);
+  t.deepEqual(parse({ recordTerminator: "any" }), [quotedRow]);
+  t.deepEqual(parse({ strict: true }), [quotedRow]);
+});
+
+test("quoted headers split across chunks at every byte", (t) => {
+  for (let i = 1; i < quotedHeaders.length; i++) {
+    const parser = new CsvParser();
+    const rows = [
+      ...parser.push(quotedHeaders.slice(0, i)),
+      ...parser.push(quotedHeaders.slice(i)),
+      ...parser.flush(),
+    ];
+    t.deepEqual(rows, [quotedRow], `split at ${i}`);
+  }
+});
+
+test("the separator inside quoted headers does not mislead detection", (t) => {
+  const parse = (input) => {
+    const parser = new CsvParser({ separator: "auto" });
+    return [...parser.push(input), ...parser.flush()];
+  };
+  t.deepEqual(parse('"a;b;c","d;e;f"\n1,2\n3,4\n'), [
+    { "a;b;c": "1", "d;e;f": "2" },
+    { "a;b;c": "3", "d;e;f": "4" },
+  ]);
+  t.deepEqual(readHeaders(Buffer.from('"a,b,c";"d,e"\n1;2\n')).headers, ["a,b,c", "d,e"]);
+});
+
+test("mapHeaders and columnAliases see quoted headers unquoted", async (t) => {
+  const mapped = await csv({ mapHeaders: ({ header, index }) => `${index}:${header}` })
+    .end(quotedHeaders)
+    .toArray();
+  t.deepEqual(Object.keys(mapped[0]), ["0:Revenue, net", "1:Cost\nQ1", '2:Say "hi"', "3:id"]);
+
+  const aliased = await csv({ columnAliases: { revenue: ["revenue, NET"] } })
+    .end(quotedHeaders)
+    .toArray();
+  t.deepEqual(Object.keys(aliased[0]), ["revenue", "Cost\nQ1", 'Say "hi"', "id"]);
+});
+
+test("headers event and skipLines with a multi-line quoted header", async (t) => {
+  const events = [];
+  const stream = csv({ skipLines: 1 });
+  stream.on("headers", (headers) => events.push(headers));
+  stream.write("title\n");
+  stream.write(quotedHeaders.slice(0, 20));
+  stream.end(quotedHeaders.slice(20));
+  t.deepEqual(await stream.toArray(), [quotedRow]);
+  t.deepEqual(events, [["Revenue, net", "Cost\nQ1", 'Say "hi"', "id"]]);
+});
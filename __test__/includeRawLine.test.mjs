@@ -0,0 +1,22 @@
+import test from "ava";
+import { Readable } from "stream";
+import csv, { CsvParser } from "../main.js";
+
+test("includeRawLine adds the source line of each row", (t) => {
+  const parser = new CsvParser({ includeRawLine: true });
+  const rows = parser.push('a,b\r\n"x ""1""", y \r\n"multi\nline",2\n');
+
+  t.deepEqual(rows, [
+    { a: 'x "1"', b: " y ", _raw: '"x ""1""", y ' },
+    { a: "multi\nline", b: "2", _raw: '"multi\nline",2' },
+  ]);
+});
+
+test("rawLineKey names the key and raw lines are Buffers", async (t) => {
+  const rows = await Readable.from(["a\nfoo\n"])
+    .pipe(csv({ includeRawLine: true, rawLineKey: "source", raw: true }))
+    .toArray();
+
+  t.true(Buffer.isBuffer(rows[0].source));
+  t.is(rows[0].source.toString(), "foo");
+});
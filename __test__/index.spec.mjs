@@ -23,3 +23,72 @@ test("random test", (t) => {
 
   t.deepEqual(headers, ["name", "age"]);
 });
+
+test("memoryUsage reports buffered bytes", (t) => {
+  const parser = new CsvParser();
+
+  parser.push(Buffer.from("name,age\nJohn,30\nJane,"));
+  const usage = parser.memoryUsage();
+
+  t.true(usage.decodeBuffer >= "Jane,".length);
+  t.true(usage.strings > 0, "header names counted");
+  t.is(
+    usage.total,
+    usage.inputBuffer + usage.decodeBuffer + usage.strings + usage.arena,
+  );
+});
+
+test("reset allows reusing a parser", (t) => {
+  const parser = new CsvParser();
+
+  parser.push(Buffer.from("a,b\n1,2\n3,"));
+  parser.reset();
+  t.is(parser.getHeaders(), null);
+
+  const rows = [...parser.push(Buffer.from("x,y\n5,6\n")), ...parser.flush()];
+  t.deepEqual(rows, [{ x: "5", y: "6" }]);
+});
+
+test("header keys are reused across calls", (t) => {
+  const parser = new CsvParser();
+  const headers = Array.from({ length: 120 }, (_, i) => `column${i}`);
+  const row = headers.map((_, i) => String(i));
+
+  parser.push(Buffer.from(`${headers.join(",")}\n`));
+  const first = parser.push(Buffer.from(`${row.join(",")}\n`));
+  const second = parser.push(Buffer.from(`${row.join(",")}\n`));
+  t.deepEqual(Object.keys(first[0]), headers);
+  t.deepEqual(second, first);
+
+  parser.reset({ headers: ["column0", "other"] });
+  t.deepEqual(parser.push(Buffer.from("1,2\n")), [{ column0: "1", other: "2" }]);
+});
+
+test("reset with new options", (t) => {
+  const parser = new CsvParser();
+
+  parser.push(Buffer.from("a,b\n1,2\n"));
+  parser.reset({ separator: ";", headers: ["p", "q"] });
+
+  t.deepEqual(parser.push(Buffer.from("7;8\n")), [{ p: "7", q: "8" }]);
+});
+
+test("reset with a new encoding decodes with it", (t) => {
+  const parser = new CsvParser();
+  parser.reset({ encoding: "latin1" });
+
+  const rows = [...parser.push(Buffer.from([0x61, 0x0a, 0xe9, 0x0a])), ...parser.flush()];
+  t.deepEqual(rows, [{ a: "é" }]);
+});
+
+test("reset with invalid options throws a TypeError and changes nothing", (t) => {
+  const parser = new CsvParser({ separator: ";", batchSize: 1 });
+  parser.push(Buffer.from("a;b\n1;2\n3;4\n"));
+
+  t.throws(() => parser.reset({ batchSize: 5, separator: "" }), {
+    instanceOf: TypeError,
+    message: "separator must not be empty",
+  });
+  t.deepEqual(parser.next(), [{ a: "3", b: "4" }]);
+  t.deepEqual(parser.next(), []);
+});
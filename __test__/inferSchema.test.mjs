@@ -0,0 +1,32 @@
+import test from "ava";
+import { inferSchema } from "../index.js";
+import { fixture } from "./helpers/helper.mjs";
+
+test("inferSchema describes each column of a buffer", (t) => {
+  const input = Buffer.from("id,price,name\n1,2.5,Ann\n2,,Bob\n3,4,Ann\n");
+  const [id, price, name] = inferSchema(input);
+
+  t.like(id, { name: "id", type: "integer", nullable: false, distinct: 3 });
+  t.like(price, { type: "float", nullable: true, minLength: 1, maxLength: 3 });
+  t.deepEqual(name.examples, ["Ann", "Bob"]);
+  t.is(name.distinct, 2);
+});
+
+test("inferSchema reads a file path with parser options", (t) => {
+  const schema = inferSchema(fixture("excel-sep.csv"), {
+    sampleRows: 1,
+    parser: { sepDirective: true },
+  });
+
+  t.deepEqual(
+    schema.map((column) => [column.name, column.type]),
+    [
+      ["name", "string"],
+      ["price", "string"],
+    ],
+  );
+});
+
+test("inferSchema reports missing files", (t) => {
+  t.throws(() => inferSchema("does-not-exist.csv"), { message: /Cannot open/ });
+});
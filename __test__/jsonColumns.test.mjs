@@ -0,0 +1,51 @@
+import test from "ava";
+import { Readable } from "stream";
+import csv, { CsvParser } from "../main.js";
+
+test("jsonColumns parses cells as JSON", (t) => {
+  const parser = new CsvParser({ jsonColumns: ["meta"] });
+  const rows = parser.push(
+    'id,meta\n1,"{""b"":1,""a"":[true,null,""x""]}"\n2,\n3,42\n',
+  );
+
+  t.deepEqual(rows, [
+    { id: "1", meta: { b: 1, a: [true, null, "x"] } },
+    { id: "2", meta: null },
+    { id: "3", meta: 42 },
+  ]);
+  t.deepEqual(Object.keys(rows[0].meta), ["b", "a"]);
+});
+
+test("jsonColumns fails on malformed JSON by default", async (t) => {
+  const stream = Readable.from(["id,meta\n1,{oops\n"]).pipe(
+    csv({ jsonColumns: ["meta"] }),
+  );
+
+  await t.throwsAsync(stream.toArray(), {
+    message: /Invalid JSON in column "meta"/,
+  });
+});
+
+test("malformedJson keep leaves malformed cells as strings", (t) => {
+  const parser = new CsvParser({
+    jsonColumns: ["meta"],
+    malformedJson: "keep",
+  });
+  const rows = parser.push("id,meta\n1,{oops\n2,[1]\n");
+
+  t.deepEqual(rows, [
+    { id: "1", meta: "{oops" },
+    { id: "2", meta: [1] },
+  ]);
+});
+
+test("jsonColumns rejects columns that also have a columnTypes entry", (t) => {
+  t.throws(
+    () =>
+      new CsvParser({
+        jsonColumns: ["a"],
+        columnTypes: { a: { type: "date" } },
+      }),
+    { message: /cannot be in both jsonColumns and columnTypes/ },
+  );
+});
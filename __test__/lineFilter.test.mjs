@@ -0,0 +1,30 @@
+import test from "ava";
+import { CsvParser } from "../index.js";
+
+const input = "level,message\nINFO,started\nERROR,disk full\nWARN,slow\nDEBUG,tick\n";
+
+test("lineFilter keeps matching lines", (t) => {
+  const parser = new CsvParser({ lineFilter: { pattern: "^(ERROR|WARN)," } });
+  const rows = [...parser.push(Buffer.from(input)), ...parser.flush()];
+
+  t.deepEqual(rows, [
+    { level: "ERROR", message: "disk full" },
+    { level: "WARN", message: "slow" },
+  ]);
+});
+
+test("lineFilter negate drops matching lines", (t) => {
+  const parser = new CsvParser({ lineFilter: { pattern: "DEBUG|INFO", negate: true } });
+  const rows = [...parser.push(Buffer.from(input)), ...parser.flush()];
+
+  t.deepEqual(
+    rows.map((row) => row.level),
+    ["ERROR", "WARN"],
+  );
+});
+
+test("lineFilter rejects an invalid pattern", (t) => {
+  t.throws(() => new CsvParser({ lineFilter: { pattern: "(" } }), {
+    message: /^Invalid lineFilter pattern/,
+  });
+});
@@ -0,0 +1,84 @@
+import test from "ava";
+import csv from "../main.js";
+
+const input = "a,b\n1,2\n3,4\n5,6\n";
+
+test("mapValuesBatch maps the cells of every row of a call at once", async (t) => {
+  const calls = [];
+  const mapValuesBatch = (cells) => {
+    calls.push(cells);
+    return cells.map(({ value }) => Number(value));
+  };
+  const rows = await csv({ mapValuesBatch }).end(input).toArray();
+
+  t.deepEqual(rows, [
+    { a: 1, b: 2 },
+    { a: 3, b: 4 },
+    { a: 5, b: 6 },
+  ]);
+  t.is(calls.length, 1);
+  t.deepEqual(calls[0].slice(0, 3), [
+    { header: "a", index: 0, value: "1" },
+    { header: "b", index: 1, value: "2" },
+    { header: "a", index: 0, value: "3" },
+  ]);
+});
+
+test("mapBatchRows sets the rows per call", async (t) => {
+  const sizes = [];
+  const mapValuesBatch = (cells) => {
+    sizes.push(cells.length);
+    return cells.map(({ value }) => value);
+  };
+  await csv({ mapValuesBatch, mapBatchRows: 2 }).end(input).toArray();
+  t.deepEqual(sizes, [4, 2]);
+});
+
+test("mapValuesBatch runs before mapHeaders and keeps rowObject", async (t) => {
+  const mapValuesBatch = (cells) => cells.map(({ header, value }) => `${header}${value}`);
+  const mapHeaders = ({ header }) => header.toUpperCase();
+
+  const rows = await csv({ mapValuesBatch, mapHeaders, rowObject: "frozen" }).end(input).toArray();
+  t.deepEqual(rows[0], { A: "a1", B: "b2" });
+  t.true(Object.isFrozen(rows[0]));
+
+  const entries = await csv({ mapValuesBatch, rowObject: "entries" }).end("a,b\n1,2\n").toArray();
+  t.deepEqual(entries, [
+    [
+      ["a", "a1"],
+      ["b", "b2"],
+    ],
+  ]);
+});
+
+test("mapValuesBatch must return a value for each cell", async (t) => {
+  const stream = csv({ mapValuesBatch: (cells) => cells.slice(1) }).end(input);
+  await t.throwsAsync(stream.toArray(), {
+    instanceOf: TypeError,
+    message: "mapValuesBatch must return an array with a value for each cell",
+  });
+});
+
+test("invalid mapValuesBatch options fail the stream", async (t) => {
+  const fails = (options) =>
+    t.throwsAsync(csv(options).end(input).toArray(), { instanceOf: TypeError });
+
+  await fails({ mapValuesBatch: (cells) => cells, mapValues: ({ value }) => value });
+  await fails({ mapValuesBatch: (cells) => cells, mapBatchRows: 0 });
+  await fails({ mapValuesBatch: "upper" });
+});
+
+test("mapHeaders is called once per column, not for every row", async (t) => {
+  const seen = [];
+  const mapHeaders = ({ header, index }) => {
+    seen.push([header, index]);
+    return index === 1 ? null : header.toUpperCase();
+  };
+  const rows = await csv({ mapHeaders }).end(input).toArray();
+
+  t.deepEqual(rows, [{ A: "1" }, { A: "3" }, { A: "5" }]);
+  t.deepEqual(seen, [
+    ["a", 0],
+    ["b", 1],
+  ]);
+});
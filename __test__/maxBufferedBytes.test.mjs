@@ -0,0 +1,27 @@
+import test from "ava";
+import { Readable } from "stream";
+import csv, { CsvParser } from "../main.js";
+
+test("maxBufferedBytes fails on a row that never completes", async (t) => {
+  const chunks = ['a\n1\n"never closed', ...Array(10).fill("x".repeat(100))];
+  const rows = [];
+  const stream = Readable.from(chunks).pipe(csv({ maxBufferedBytes: 512 }));
+  stream.on("data", (row) => rows.push(row));
+
+  const done = new Promise((resolve, reject) => {
+    stream.on("end", resolve);
+    stream.on("error", reject);
+  });
+
+  await t.throwsAsync(done, { message: /more than maxBufferedBytes \(512\)/ });
+  t.deepEqual(rows, [{ a: "1" }]);
+});
+
+test("maxBufferedBytes drops the partial row", (t) => {
+  const parser = new CsvParser({ maxBufferedBytes: 4 });
+  t.deepEqual(parser.push("a\n1\n"), [{ a: "1" }]);
+  t.throws(() => parser.push('"12345'), {
+    message: /^Buffered 6 bytes without completing a row/,
+  });
+  t.deepEqual(parser.push("2\n"), [{ a: "2" }]);
+});
@@ -0,0 +1,21 @@
+import test from "ava";
+import { Readable } from "stream";
+import csv, { CsvParser } from "../main.js";
+
+test("maxColumns fails on rows with too many cells", async (t) => {
+  const stream = Readable.from(["a,b\n1,2\n" + ",".repeat(5000) + "\n"]).pipe(
+    csv({ maxColumns: 10 }),
+  );
+
+  await t.throwsAsync(stream.toArray(), {
+    message: "Row has 5001 columns, more than maxColumns (10)",
+  });
+});
+
+test("maxColumnsAction truncate drops cells past the limit", (t) => {
+  const parser = new CsvParser({ maxColumns: 2, maxColumnsAction: "truncate" });
+  const rows = parser.push("a,b,c\n1,2,3,4\n");
+
+  t.deepEqual(parser.getHeaders(), ["a", "b"]);
+  t.deepEqual(rows, [{ a: "1", b: "2" }]);
+});
@@ -0,0 +1,41 @@
+import test from "ava";
+import { Readable } from "stream";
+import csv, { CsvParser } from "../main.js";
+
+test("maxFieldBytes fails on a row with an oversize cell", async (t) => {
+  const stream = Readable.from(["id,notes\n1,ok\n2," + "x".repeat(100_000) + "\n"]).pipe(
+    csv({ maxFieldBytes: 1024 }),
+  );
+
+  await t.throwsAsync(stream.toArray(), {
+    message: "Field 2 has 100000 bytes, more than maxFieldBytes (1024)",
+  });
+});
+
+test("onOversizeField truncate cuts cells to the limit", (t) => {
+  const parser = new CsvParser({ maxFieldBytes: 4, onOversizeField: "truncate" });
+  const rows = parser.push('id,notes\n12345,"a ""b"" c"\n7,añña\n');
+
+  // The header is cut too
+  t.deepEqual(parser.getHeaders(), ["id", "note"]);
+  // The limit counts the value's bytes, with the escaped quotes collapsed
+  t.deepEqual(rows, [
+    { id: "1234", note: 'a "b' },
+    // `ñ` takes two bytes, so the one cut at the limit is left out
+    { id: "7", note: "añ" },
+  ]);
+});
+
+test("onOversizeField truncate never splits an escaped quote", (t) => {
+  const parser = new CsvParser({ maxFieldBytes: 3, onOversizeField: "truncate" });
+  const rows = [...parser.push('v\n"ab""cd"\n"a""bc"\n""""""""\n'), ...parser.flush()];
+
+  t.deepEqual(rows, [{ v: 'ab"' }, { v: 'a"b' }, { v: '"""' }]);
+});
+
+test("onOversizeField must be error or truncate", (t) => {
+  t.throws(() => new CsvParser({ onOversizeField: "drop" }), {
+    instanceOf: TypeError,
+    message: 'onOversizeField must be "error" or "truncate", got "drop"',
+  });
+});
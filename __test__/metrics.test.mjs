@@ -0,0 +1,50 @@
+import test from "ava";
+import { Readable } from "stream";
+import csv, { CsvParser } from "../main.js";
+
+test("getMetrics counts bytes, rows and comments", (t) => {
+  const parser = new CsvParser({ skipComments: true });
+  parser.push("a,b\n# note\n1,2\n");
+  parser.push("3,4");
+  parser.flush();
+
+  const metrics = parser.getMetrics();
+  t.is(metrics.bytesProcessed, 18);
+  t.is(metrics.rowsEmitted, 2);
+  t.is(metrics.commentLines, 1);
+  t.is(metrics.errors, 0);
+  t.true(metrics.parseTimeMs >= 0);
+});
+
+test("getMetrics counts errors and resets", (t) => {
+  const parser = new CsvParser({ strict: true });
+  t.throws(() => parser.push("a,b\n1\n"));
+  t.is(parser.getMetrics().errors, 1);
+
+  parser.reset();
+  t.like(parser.getMetrics(), { bytesProcessed: 0, rowsEmitted: 0, errors: 0 });
+});
+
+test("stream exposes getMetrics", async (t) => {
+  const stream = Readable.from(["a\n1\n", "2\n"]).pipe(csv());
+  for await (const _ of stream);
+  t.like(stream.getMetrics(), { bytesProcessed: 6, rowsEmitted: 2 });
+});
+
+test("getProfile splits parse time once enabled", (t) => {
+  const parser = new CsvParser();
+  t.is(parser.getProfile(), null);
+
+  parser.enableProfiling();
+  parser.push(Buffer.from("a,b\n1,2\n3,4\n"));
+  const profile = parser.getProfile();
+  for (const key of ["decodeMs", "tokenizeMs", "buildMs"]) {
+    t.true(profile[key] >= 0, key);
+  }
+  t.true(profile.tokenizeMs + profile.buildMs > 0);
+
+  parser.reset();
+  t.deepEqual(parser.getProfile(), { decodeMs: 0, tokenizeMs: 0, buildMs: 0 });
+  parser.enableProfiling(false);
+  t.is(parser.getProfile(), null);
+});
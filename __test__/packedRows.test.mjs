@@ -0,0 +1,44 @@
+import test from "ava";
+import { Worker } from "worker_threads";
+import { unpackRows } from "../main.js";
+import { CsvParser } from "../index.js";
+
+test("pushPacked packs rows that unpack to what push returns", (t) => {
+  const input = 'a,b,\n1,"x,""y"""\n2,é,,extra\n3';
+  const packed = new CsvParser().pushPacked(input);
+
+  t.true(packed.buffer instanceof ArrayBuffer);
+  t.is(packed.rows, 2);
+  t.deepEqual(unpackRows(packed), new CsvParser().push(input));
+
+  const parser = new CsvParser();
+  parser.pushPacked(input);
+  t.deepEqual(unpackRows(parser.flushPacked()), [{ a: "3" }]);
+  t.is(parser.flushPacked().rows, 0);
+});
+
+test("packed rows can be transferred to a worker", async (t) => {
+  const packed = new CsvParser().pushPacked("name,age\nAnn,30\nBob,41\n");
+
+  const source = `
+    const { parentPort, workerData } = require("worker_threads");
+    const { unpackRows } = require(workerData.module);
+    parentPort.once("message", (batch) => parentPort.postMessage(unpackRows(batch)));
+  `;
+  const worker = new Worker(source, {
+    eval: true,
+    workerData: { module: new URL("../main.js", import.meta.url).pathname },
+  });
+  const rows = new Promise((resolve, reject) => {
+    worker.once("message", resolve);
+    worker.once("error", reject);
+  });
+  worker.postMessage(packed, [packed.buffer]);
+
+  t.is(packed.buffer.byteLength, 0);
+  t.deepEqual(await rows, [
+    { name: "Ann", age: "30" },
+    { name: "Bob", age: "41" },
+  ]);
+  await worker.terminate();
+});
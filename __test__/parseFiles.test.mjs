@@ -0,0 +1,111 @@
+import test from "ava";
+import fs from "fs";
+import os from "os";
+import path from "path";
+import { parseFiles } from "../main.js";
+
+function input(name, contents) {
+  const file = path.join(
+    os.tmpdir(),
+    `fast-csv-parser-parseFiles-${name}.csv`,
+  );
+  fs.writeFileSync(file, contents);
+  return file;
+}
+
+function collect(stream) {
+  return new Promise((resolve, reject) => {
+    const rows = [];
+    stream.on("data", (row) => rows.push(row));
+    stream.on("end", () => resolve(rows));
+    stream.on("error", reject);
+  });
+}
+
+const january = input("january", "id,amount\n1,10\n2,20\n");
+const february = input("february", "id,amount\n3,30");
+const march = input("march", "amount,id,note\n40,4,late\n");
+
+test("parseFiles streams files as one dataset, skipping repeated headers", async (t) => {
+  const rows = await collect(
+    parseFiles([january, february], { fileColumn: true }),
+  );
+
+  t.deepEqual(rows, [
+    { id: "1", amount: "10", __file: january },
+    { id: "2", amount: "20", __file: january },
+    { id: "3", amount: "30", __file: february },
+  ]);
+});
+
+test("parseFiles rejects files whose headers differ", async (t) => {
+  await t.throwsAsync(collect(parseFiles([january, march])), {
+    message: /Headers of .*march\.csv do not match/,
+  });
+});
+
+test("parseFiles reconciles headers by name", async (t) => {
+  const rows = await collect(
+    parseFiles([january, march], { reconcile: "union", fileColumn: "source" }),
+  );
+
+  t.deepEqual(rows, [
+    { id: "1", amount: "10", note: null, source: january },
+    { id: "2", amount: "20", note: null, source: january },
+    { id: "4", amount: "40", note: "late", source: march },
+  ]);
+  t.deepEqual(Object.keys(rows[2]), ["id", "amount", "note", "source"]);
+});
+
+test("parseFiles passes parser options through", async (t) => {
+  const semicolons = input("semicolons", "a;b\n1;2\n");
+  const rows = await collect(
+    parseFiles([semicolons, semicolons], {
+      separator: ";",
+      reconcile: "union",
+      mapHeaders: ({ header }) => header.toUpperCase(),
+    }),
+  );
+
+  t.deepEqual(rows, [
+    { A: "1", B: "2" },
+    { A: "1", B: "2" },
+  ]);
+  await t.throwsAsync(
+    collect(parseFiles([semicolons], { reconcile: "loose" })),
+    { instanceOf: TypeError },
+  );
+});
+
+test("parseFiles follows the last file as it grows", async (t) => {
+  const log = input("follow", "level,message\ninfo,started\n");
+  const stream = parseFiles([log], { follow: true, followInterval: 10 });
+  const rows = [];
+  const rowCount = (count) =>
+    new Promise((resolve) => {
+      const check = () => {
+        if (rows.length >= count) {
+          stream.off("data", check);
+          resolve();
+        }
+      };
+      stream.on("data", check);
+      check();
+    });
+  stream.on("data", (row) => rows.push(row));
+
+  await rowCount(1);
+  // The row is only complete once its newline arrives
+  fs.appendFileSync(log, "warn,disk ");
+  await new Promise((resolve) => setTimeout(resolve, 50));
+  t.is(rows.length, 1);
+  fs.appendFileSync(log, "full\nerror,stopped\n");
+  await rowCount(3);
+  stream.destroy();
+
+  t.deepEqual(rows, [
+    { level: "info", message: "started" },
+    { level: "warn", message: "disk full" },
+    { level: "error", message: "stopped" },
+  ]);
+});
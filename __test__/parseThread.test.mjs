@@ -0,0 +1,46 @@
+import test from 'ava'
+
+import { CsvParseThread } from '../index.js'
+
+test('parses pushed chunks on a thread', async (t) => {
+  const parser = new CsvParseThread({ parser: { separator: ';' } })
+  t.true(parser.push('a;b\n1;'))
+  t.true(parser.push(Buffer.from('2\n3;4')))
+  parser.end()
+
+  t.deepEqual(await parser.read(), [])
+  t.deepEqual(await parser.read(), [{ a: '1', b: '2' }])
+  t.deepEqual(await parser.read(), [{ a: '3', b: '4' }])
+  t.is(await parser.read(), null)
+  t.throws(() => parser.push('5;6\n'), { message: 'push() after end()' })
+})
+
+test('push() reports backpressure', async (t) => {
+  const parser = new CsvParseThread({ maxPendingBatches: 2 })
+  t.deepEqual(await parser.read(), [])
+  t.true(parser.push('a\n1\n'))
+  t.false(parser.push('2\n'))
+  t.deepEqual(await parser.read(), [{ a: '1' }])
+  t.false(parser.push('3\n'))
+
+  const rows = []
+  parser.end()
+  for (let batch = await parser.read(); batch !== null; batch = await parser.read()) {
+    rows.push(...batch)
+  }
+  t.deepEqual(rows, [{ a: '2' }, { a: '3' }])
+})
+
+test('rejects a batch whose rows fail and keeps parsing', async (t) => {
+  const parser = new CsvParseThread({ parser: { strict: true } })
+  parser.push('a,b\n1\n')
+  parser.push('2,3\n')
+  await t.throwsAsync(parser.read())
+  t.deepEqual(await parser.read(), [{ a: '2', b: '3' }])
+})
+
+test('rejects a zero maxPendingBatches', (t) => {
+  t.throws(() => new CsvParseThread({ maxPendingBatches: 0 }), {
+    message: 'maxPendingBatches must be a positive integer',
+  })
+})
@@ -0,0 +1,35 @@
+import test from "ava";
+import { CsvParser, ParserTemplate } from "../index.js";
+
+function parse(parser, input) {
+  return [...parser.push(Buffer.from(input)), ...parser.flush()];
+}
+
+test("parsers from one template start fresh", (t) => {
+  const template = new ParserTemplate({
+    headers: ["id", "name"],
+    skipComments: true,
+    batchSize: 1,
+  });
+  t.deepEqual(template.headers, ["id", "name"]);
+
+  const first = CsvParser.fromTemplate(template);
+  t.deepEqual(first.push(Buffer.from("# c\n1,Ann\n2,Bob\n")), [{ id: "1", name: "Ann" }]);
+  // A second parser does not see the state of the first
+  const second = CsvParser.fromTemplate(template);
+  t.deepEqual(parse(second, "3,Cy\n"), [{ id: "3", name: "Cy" }]);
+  t.deepEqual(first.next(), [{ id: "2", name: "Bob" }]);
+});
+
+test("a template reads headers from each input", (t) => {
+  const template = new ParserTemplate({ columnAliases: { email: ["mail"] } });
+  t.is(template.headers, null);
+  t.deepEqual(parse(CsvParser.fromTemplate(template), "mail\na@b\n"), [{ email: "a@b" }]);
+  t.deepEqual(parse(CsvParser.fromTemplate(template), "email,x\nc@d,1\n"), [
+    { email: "c@d", x: "1" },
+  ]);
+});
+
+test("a template checks options as the parser does", (t) => {
+  t.throws(() => new ParserTemplate({ separator: ";;" }), { instanceOf: TypeError });
+});
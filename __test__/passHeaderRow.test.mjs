@@ -0,0 +1,48 @@
+import test from "ava";
+import { CsvParser } from "../index.js";
+import csv from "../main.js";
+import { Readable } from "stream";
+
+function parse(input, options) {
+  const parser = new CsvParser(options);
+  return [...parser.push(Buffer.from(input)), ...parser.flush()];
+}
+
+test("passHeaderRow emits the header row keyed by itself", (t) => {
+  t.deepEqual(parse("id,name\n1,Ann\n", { passHeaderRow: true }), [
+    { id: "id", name: "name" },
+    { id: "1", name: "Ann" },
+  ]);
+  t.deepEqual(parse("id,name\n1,Ann\n", {}), [{ id: "1", name: "Ann" }]);
+});
+
+test("the header row skips the checks of data rows", (t) => {
+  const rows = parse("id,email\n1,a@b.c\n", {
+    passHeaderRow: true,
+    validators: { id: "^\\d+$" },
+    onViolation: "annotate",
+    redact: { email: "mask" },
+  });
+  t.deepEqual(rows, [
+    { id: "id", email: "email", __violations: [] },
+    { id: "1", email: "*@b.c", __violations: [] },
+  ]);
+});
+
+test("passHeaderRow works with entries and the stream", async (t) => {
+  t.deepEqual(parse("a,b\n1,2\n", { passHeaderRow: true, rowObject: "entries" })[0], [
+    ["a", "a"],
+    ["b", "b"],
+  ]);
+  const rows = await Readable.from(["a,b\n1,2\n"])
+    .pipe(csv({ passHeaderRow: true, mapHeaders: ({ header }) => header.toUpperCase() }))
+    .toArray();
+  t.deepEqual(rows, [
+    { A: "a", B: "b" },
+    { A: "1", B: "2" },
+  ]);
+});
+
+test("custom headers leave no header row to pass", (t) => {
+  t.deepEqual(parse("1,2\n", { passHeaderRow: true, headers: ["a", "b"] }), [{ a: "1", b: "2" }]);
+});
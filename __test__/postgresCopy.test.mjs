@@ -0,0 +1,44 @@
+import test from "ava";
+import { CsvParser, CsvRewriter } from "../index.js";
+import { CsvWriter } from "../main.js";
+
+test("the postgres writer dialect writes the COPY text format", (t) => {
+  const writer = new CsvWriter({ dialect: "postgres" });
+  t.is(
+    writer.write([{ id: 1, note: "tab\there\nback\\slash", shipped_at: null }]),
+    "id\tnote\tshipped_at\n1\ttab\\there\\nback\\\\slash\t\\N\n",
+  );
+});
+
+test("COPY text round-trips through the parser and writer", (t) => {
+  const rows = [
+    { id: "1", note: 'line 1\nline 2, "quoted"', shipped_at: null },
+    { id: "2", note: "\\N as text", shipped_at: "" },
+  ];
+  const text = new CsvWriter({ dialect: "postgres" }).write(rows);
+
+  const parser = new CsvParser({ dialect: "postgres" });
+  t.deepEqual([...parser.push(Buffer.from(text)), ...parser.flush()], rows);
+});
+
+test("nullValue sets how the writer writes null", (t) => {
+  t.is(new CsvWriter({ nullValue: "NULL" }).write([["a", null, undefined]]), "a,NULL,\n");
+  t.is(new CsvWriter().write([["a", null]]), "a,\n");
+});
+
+test("the excel writer dialect writes CRLF and a BOM", (t) => {
+  t.is(new CsvWriter({ dialect: "excel" }).write([["a", "b"]]), "\uFEFFa,b\r\n");
+  t.is(new CsvWriter({ dialect: "excel", bom: false }).write([["a"]]), "a\r\n");
+});
+
+test("rewrite converts COPY text to CSV keeping nulls", (t) => {
+  const rewriter = new CsvRewriter({
+    input: { dialect: "postgres" },
+    output: { nullValue: "NULL" },
+  });
+  const out = Buffer.concat([
+    rewriter.push(Buffer.from("id\tnote\n1\t\\N\n2\ta\\tb\n\\.\n")),
+    rewriter.flush(),
+  ]);
+  t.is(out.toString(), "id,note\n1,NULL\n2,a\tb\n");
+});
@@ -0,0 +1,32 @@
+import test from "ava";
+import { collect } from "./helpers/helper.mjs";
+
+test("onProgress reports totals and rates", async (t) => {
+  const reports = [];
+  const { error, lines } = await collect("large-dataset", {
+    onProgress: (progress) => reports.push(progress),
+    progressInterval: 0,
+  });
+
+  t.false(error, "no err");
+  t.true(reports.length > 0, "progress reported");
+
+  const last = reports[reports.length - 1];
+  t.true(last.done, "final report");
+  t.is(last.rows, lines.length, "all rows counted");
+  t.true(last.bytes > 1000000, "all bytes counted");
+  t.true(last.bytesPerSecond >= 0, "byte rate");
+  t.true(last.rowsPerSecond >= 0, "row rate");
+});
+
+test("onProgress is throttled by progressInterval", async (t) => {
+  const reports = [];
+  const { error } = await collect("large-dataset", {
+    onProgress: (progress) => reports.push(progress),
+    progressInterval: 60000,
+  });
+
+  t.false(error, "no err");
+  t.is(reports.length, 1, "only the final report");
+  t.true(reports[0].done);
+});
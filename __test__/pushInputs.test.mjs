@@ -0,0 +1,60 @@
+import test from "ava";
+import { CsvParser } from "../index.js";
+
+const csv = "a,b\n1,2\n";
+const expected = [{ a: "1", b: "2" }];
+
+function parse(chunk) {
+  const parser = new CsvParser();
+  return [...parser.push(chunk), ...parser.flush()];
+}
+
+test("push accepts strings", (t) => {
+  t.deepEqual(parse(csv), expected);
+});
+
+test("push accepts Uint8Array", (t) => {
+  t.deepEqual(parse(new TextEncoder().encode(csv)), expected);
+});
+
+test("push accepts ArrayBuffer", (t) => {
+  t.deepEqual(parse(new TextEncoder().encode(csv).buffer), expected);
+});
+
+test("push respects TypedArray and DataView offsets", (t) => {
+  const bytes = new TextEncoder().encode(`xx${csv}yy`);
+  t.deepEqual(parse(bytes.subarray(2, bytes.length - 2)), expected);
+  t.deepEqual(parse(new DataView(bytes.buffer, 2, bytes.length - 4)), expected);
+});
+
+test("push accepts mixed chunk types", (t) => {
+  const parser = new CsvParser();
+  const rows = [
+    ...parser.push("a,b\n1,"),
+    ...parser.push(new TextEncoder().encode("2\n3,4\n")),
+    ...parser.flush(),
+  ];
+  t.deepEqual(rows, [...expected, { a: "3", b: "4" }]);
+});
+
+test("push accepts empty chunks of every type", (t) => {
+  const parser = new CsvParser();
+  const empty = new Uint8Array(0);
+  const rows = [
+    ...parser.push(Buffer.alloc(0)),
+    ...parser.push("a,b\n"),
+    ...parser.push(empty),
+    ...parser.push(new DataView(empty.buffer)),
+    ...parser.push(new ArrayBuffer(0)),
+    ...parser.push(""),
+    ...parser.push("1,2\n"),
+    ...parser.push(Buffer.alloc(0)),
+    ...parser.flush(),
+  ];
+  t.deepEqual(rows, expected);
+});
+
+test("push rejects other values", (t) => {
+  const parser = new CsvParser();
+  t.throws(() => parser.push(42), { message: /push\(\) expects/ });
+});
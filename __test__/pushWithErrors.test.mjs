@@ -0,0 +1,79 @@
+import test from "ava";
+import { CsvParser } from "../index.js";
+import csv from "../main.js";
+import { Readable } from "stream";
+
+const input = "a,b\n1,2\n3\n4,5\n6,7,8\n";
+
+test("pushWithErrors returns rows and the errors of failed rows together", (t) => {
+  const parser = new CsvParser({ strict: true });
+  const { rows, errors } = parser.pushWithErrors(Buffer.from(input));
+
+  t.deepEqual(rows, [
+    { a: "1", b: "2" },
+    { a: "4", b: "5" },
+  ]);
+  t.deepEqual(errors, [
+    { message: "Row length does not match headers", index: 1 },
+    { message: "Row length does not match headers", index: 2 },
+  ]);
+  t.is(parser.getMetrics().errors, 2);
+});
+
+test("flushWithErrors reports a failed last row", (t) => {
+  const parser = new CsvParser({ strict: true });
+  t.deepEqual(parser.pushWithErrors(Buffer.from("a,b\n1,2\n3")).rows, [{ a: "1", b: "2" }]);
+
+  const { rows, errors } = parser.flushWithErrors();
+  t.deepEqual(rows, []);
+  t.deepEqual(errors, [{ message: "Row length does not match headers", index: 0 }]);
+});
+
+test("push throws a row's error from the call that parsed it", (t) => {
+  const parser = new CsvParser({ strict: true });
+  t.deepEqual(parser.push(Buffer.from("a,b\n1,2\n")), [{ a: "1", b: "2" }]);
+  t.throws(() => parser.push(Buffer.from("3\n")), {
+    message: "Row length does not match headers",
+  });
+
+  // The failed row is dropped, so parsing can go on
+  t.deepEqual(parser.push(Buffer.from("4,5\n")), [{ a: "4", b: "5" }]);
+});
+
+test("the error thrown by push keeps the other rows of the call", (t) => {
+  const parser = new CsvParser({ strict: true });
+  const error = t.throws(() => parser.push(Buffer.from(input)));
+
+  t.deepEqual(error.rows, [
+    { a: "1", b: "2" },
+    { a: "4", b: "5" },
+  ]);
+  t.deepEqual(error.errors, [
+    { message: "Row length does not match headers", index: 1 },
+    { message: "Row length does not match headers", index: 2 },
+  ]);
+});
+
+test("the error thrown by flush keeps the rows of the call", (t) => {
+  const parser = new CsvParser({ jsonColumns: ["b"], batchSize: 1 });
+  t.deepEqual(parser.push(Buffer.from("a,b\n1,{}\n2,{\n3,[1]\n")), [{ a: "1", b: {} }]);
+
+  const error = t.throws(() => parser.flush());
+  t.deepEqual(error.rows, [{ a: "3", b: [1] }]);
+  t.is(error.errors.length, 1);
+  t.is(error.errors[0].index, 0);
+});
+
+test("a stream emits the rows before a failed row, then fails", async (t) => {
+  const rows = [];
+  const error = await new Promise((resolve) => {
+    Readable.from([Buffer.from(input)])
+      .pipe(csv({ strict: true }))
+      .on("data", (row) => rows.push(row))
+      .on("error", resolve)
+      .on("end", () => resolve(null));
+  });
+
+  t.deepEqual(rows, [{ a: "1", b: "2" }]);
+  t.true(error instanceof RangeError);
+});
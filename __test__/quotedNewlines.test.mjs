@@ -0,0 +1,32 @@
+import test from "ava";
+import { CsvParser } from "../main.js";
+
+const input = 'id,note\r\n1,"one\r\ntwo\rthree\nfour"\r\n2,plain\r\n';
+
+test("quotedNewlines keeps line breaks by default", (t) => {
+  const rows = new CsvParser().push(input);
+  t.deepEqual(rows, [
+    { id: "1", note: "one\r\ntwo\rthree\nfour" },
+    { id: "2", note: "plain" },
+  ]);
+});
+
+test("quotedNewlines normalizes, escapes or strips line breaks in quoted cells", (t) => {
+  const note = (quotedNewlines) => new CsvParser({ quotedNewlines }).push(input)[0].note;
+  t.is(note("lf"), "one\ntwo\nthree\nfour");
+  t.is(note("escape"), "one\\ntwo\\nthree\\nfour");
+  t.is(note("strip"), "onetwothreefour");
+});
+
+test("quotedNewlines applies to rows split across chunks", (t) => {
+  const parser = new CsvParser({ quotedNewlines: "escape" });
+  const rows = [...parser.push('a,b\n"x\r'), ...parser.push('\ny",z\n'), ...parser.flush()];
+  t.deepEqual(rows, [{ a: "x\\ny", b: "z" }]);
+});
+
+test("quotedNewlines rejects unknown values", (t) => {
+  t.throws(() => new CsvParser({ quotedNewlines: "crlf" }), {
+    instanceOf: TypeError,
+    message: 'quotedNewlines must be "keep", "lf", "escape" or "strip", got "crlf"',
+  });
+});
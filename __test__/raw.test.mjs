@@ -0,0 +1,27 @@
+import test from "ava";
+import { CsvParser } from "../index.js";
+
+test("raw yields Buffers of the cell bytes", (t) => {
+  const parser = new CsvParser({ raw: true });
+  const latin1 = Buffer.from([0x63, 0x61, 0x66, 0xe9]); // "café" in latin1
+  const input = Buffer.concat([
+    Buffer.from('name,quote\n'),
+    latin1,
+    Buffer.from(',"say ""hi"""\n'),
+  ]);
+
+  const [row] = parser.push(input);
+
+  t.true(Buffer.isBuffer(row.name));
+  t.deepEqual(row.name, latin1, "invalid UTF-8 survives untouched");
+  t.true(Buffer.isBuffer(row.quote));
+  t.is(row.quote.toString(), 'say "hi"', "quotes are unescaped");
+});
+
+test("raw keeps header names as strings", (t) => {
+  const parser = new CsvParser({ raw: true });
+  const rows = [...parser.push(Buffer.from("a,b\n1,2\n")), ...parser.flush()];
+
+  t.deepEqual(Object.keys(rows[0]), ["a", "b"]);
+  t.is(rows[0].b.toString(), "2");
+});
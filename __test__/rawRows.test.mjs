@@ -0,0 +1,59 @@
+import test from "ava";
+import { Readable } from "stream";
+import csv, { CsvParser } from "../main.js";
+
+test("rawRows adds each record's bytes as a Buffer", (t) => {
+  const parser = new CsvParser({ rawRows: true });
+  const rows = [...parser.push('a,b\r\n"x ""1""",y\r\n"multi\nline",2\n3,'), ...parser.flush()];
+
+  t.deepEqual(
+    rows.map((row) => row.__raw.toString()),
+    ['"x ""1""",y', '"multi\nline",2', "3,"]
+  );
+  t.true(rows.every((row) => Buffer.isBuffer(row.__raw)));
+  t.is(rows[0].a, 'x "1"');
+});
+
+test("the Buffers keep their bytes as more input is parsed", (t) => {
+  const parser = new CsvParser({ rawRows: true });
+  const [first, second] = parser.push("id\n1\n22\n333");
+  const later = [...parser.push("4444\n55555\n"), ...parser.push(Buffer.from("666666\n"))];
+  const last = parser.flush();
+  global.gc?.();
+
+  t.is(first.__raw.toString(), "1");
+  t.is(second.__raw.toString(), "22");
+  t.deepEqual(
+    later.map((row) => row.__raw.toString()),
+    ["3334444", "55555", "666666"]
+  );
+  t.deepEqual(last, []);
+});
+
+test("records split across chunks are whole", async (t) => {
+  const rows = await Readable.from(["id,name\n1,A", "nn\n2,\"B\n", "ob\"\n"])
+    .pipe(csv({ rawRows: true }))
+    .toArray();
+
+  t.deepEqual(
+    rows.map((row) => row.__raw.toString()),
+    ["1,Ann", '2,"B\nob"']
+  );
+});
+
+test("rawRows works with every rowObject", (t) => {
+  const entries = new CsvParser({ rawRows: true, rowObject: "entries" }).push("a\n1\n");
+  t.is(entries[0][1][0], "__raw");
+  t.is(entries[0][1][1].toString(), "1");
+
+  const frozen = new CsvParser({ rawRows: true, rowObject: "frozen" }).push("a\n1\n");
+  t.true(Object.isFrozen(frozen[0]));
+  t.is(frozen[0].__raw.toString(), "1");
+});
+
+test("rawRows cannot be used with redact", (t) => {
+  t.throws(() => new CsvParser({ rawRows: true, redact: { a: "drop" } }), {
+    instanceOf: TypeError,
+    message: "rawRows cannot be used with redact",
+  });
+});
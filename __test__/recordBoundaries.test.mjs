@@ -0,0 +1,37 @@
+import test from 'ava'
+import fs from 'fs'
+import os from 'os'
+import path from 'path'
+
+import { CsvParser, findRecordBoundaries } from '../index.js'
+
+test('record starts skip quoted newlines', (t) => {
+  const input = Buffer.from('id,note\n1,"a\nb"\n2,plain\n3,"x ""y"""')
+  t.deepEqual(findRecordBoundaries(input), [0, 8, 16, 24])
+  t.deepEqual(findRecordBoundaries(input, { chunkBytes: 10 }), [0, 16])
+
+  // Postgres text has no quotes, so a quote does not join lines
+  const copy = Buffer.from('a\tb\n"1\t2\n')
+  t.deepEqual(findRecordBoundaries(copy, { parser: { dialect: 'postgres' } }), [0, 4])
+})
+
+test('chunks split at the boundaries parse to the same rows', (t) => {
+  const lines = ['id,text']
+  for (let i = 0; i < 2000; i++) {
+    lines.push(`${i},"line ${i}\nwith ""quotes"", and commas"`)
+  }
+  const file = path.join(fs.mkdtempSync(path.join(os.tmpdir(), 'bounds-')), 'rows.csv')
+  fs.writeFileSync(file, lines.join('\n'))
+
+  const buffer = fs.readFileSync(file)
+  const starts = findRecordBoundaries(file, { chunkBytes: 20000 })
+  t.true(starts.length > 2)
+  const headers = ['id', 'text']
+  const rows = starts.flatMap((start, i) => {
+    const parser = new CsvParser(i === 0 ? {} : { headers })
+    const chunk = buffer.subarray(start, starts[i + 1] ?? buffer.length)
+    return [...parser.push(chunk), ...parser.flush()]
+  })
+  t.is(rows.length, 2000)
+  t.deepEqual(rows[1234], { id: '1234', text: 'line 1234\nwith "quotes", and commas' })
+})
@@ -0,0 +1,46 @@
+import test from "ava";
+import { CsvParser } from "../index.js";
+
+function parse(chunks, options) {
+  const parser = new CsvParser(options);
+  const rows = chunks.flatMap((chunk) => parser.push(Buffer.from(chunk)));
+  return [...rows, ...parser.flush()];
+}
+
+const mixed = 'a,b\r\n1,2\n\n3,4\r5,"x\ry"\r\r\n\n6,7\r';
+const expected = [
+  { a: "1", b: "2" },
+  { a: "3", b: "4" },
+  { a: "5", b: "x\ry" },
+  { a: "6", b: "7" },
+];
+
+test('recordTerminator "any" splits mixed and doubled line breaks', (t) => {
+  t.deepEqual(parse([mixed], { recordTerminator: "any" }), expected);
+  // By default a lone `\r` is data
+  t.deepEqual(parse(["a,b\r1,2\n3,4\n"], {}), [{ a: "3", "b\r1": "4" }]);
+});
+
+test("a line break split across chunks is one boundary", (t) => {
+  for (let i = 1; i < mixed.length; i++) {
+    const chunks = [mixed.slice(0, i), mixed.slice(i)];
+    t.deepEqual(parse(chunks, { recordTerminator: "any" }), expected);
+  }
+});
+
+test("line numbers count each break", (t) => {
+  const parser = new CsvParser({ recordTerminator: "any", strict: "report" });
+  parser.push(Buffer.from("a,b\r1\r\r\n2,3,4\r"));
+  parser.flush();
+  t.deepEqual(parser.getRaggedRows(), [
+    { line: 2, expected: 2, actual: 1 },
+    { line: 4, expected: 2, actual: 3 },
+  ]);
+  t.is(parser.getState().lineNumber, 5);
+});
+
+test("an unknown recordTerminator is refused", (t) => {
+  t.throws(() => new CsvParser({ recordTerminator: "cr" }), {
+    message: 'recordTerminator must be "newline" or "any", got "cr"',
+  });
+});
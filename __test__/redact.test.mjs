@@ -0,0 +1,36 @@
+import test from "ava";
+import { createHash } from "crypto";
+import { CsvParser } from "../main.js";
+
+const input = "id,ssn,email,notes\n1,123-45-6789,ann@example.com,secret\n2,,bob,\n";
+const sha256 = (text) => createHash("sha256").update(text).digest("hex");
+
+test("redact hashes, masks and drops columns", (t) => {
+  const parser = new CsvParser({
+    redact: { ssn: "hash", email: "mask", notes: "drop" },
+    redactSalt: "pepper",
+  });
+  t.deepEqual(parser.push(input), [
+    { id: "1", ssn: sha256("pepper123-45-6789"), email: "***********.com" },
+    { id: "2", ssn: "", email: "***" },
+  ]);
+});
+
+test("redact hides cells that a schema converts", (t) => {
+  const rows = new CsvParser({
+    schema: { properties: { id: { type: "integer" } } },
+    redact: { id: "mask" },
+  }).push("id\n1234567\n");
+  t.deepEqual(rows, [{ id: "***4567" }]);
+});
+
+test("redact checks its options", (t) => {
+  t.throws(() => new CsvParser({ redact: { ssn: "blur" } }), {
+    instanceOf: TypeError,
+    message: 'redact.ssn must be "hash", "mask" or "drop", got "blur"',
+  });
+  t.throws(() => new CsvParser({ redact: { ssn: "hash" }, includeRawLine: true }), {
+    instanceOf: TypeError,
+    message: "includeRawLine cannot be used with redact",
+  });
+});
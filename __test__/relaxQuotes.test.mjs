@@ -0,0 +1,21 @@
+import test from "ava";
+import { collect } from "./helpers/helper.mjs";
+
+test("relaxQuotes keeps mid-field quotes as data", async (t) => {
+  const { error, lines } = await collect("option-relax-quotes", {
+    relaxQuotes: true,
+  });
+
+  t.false(error, "no err");
+  t.deepEqual(lines, [
+    { item: '5" pipe', material: "steel" },
+    { item: '3/4" valve', material: "brass" },
+    { item: "quoted, ok", material: "copper" },
+  ]);
+});
+
+test("without relaxQuotes mid-field quotes merge rows", async (t) => {
+  const { lines } = await collect("option-relax-quotes");
+
+  t.true(lines.length < 3, "rows merged");
+});
@@ -0,0 +1,37 @@
+import test from 'ava'
+
+import { CsvReshaper } from '../index.js'
+
+test('reshapes wide rows to long', (t) => {
+  const reshaper = new CsvReshaper({ ids: ['region'], key: 'month', value: 'sales' })
+  const rows = [...reshaper.push('region,jan,feb\nnorth,10,12\nsou'), ...reshaper.push('th,7'), ...reshaper.flush()]
+
+  t.deepEqual(rows, [
+    { region: 'north', month: 'jan', sales: '10' },
+    { region: 'north', month: 'feb', sales: '12' },
+    { region: 'south', month: 'jan', sales: '7' },
+    { region: 'south', month: 'feb', sales: '' },
+  ])
+})
+
+test('reshapes long rows to wide', (t) => {
+  const input = 'region,month,sales\nnorth,jan,10\nnorth,feb,12\nsouth,feb,7\n'
+  const reshaper = new CsvReshaper({ to: 'wide', ids: ['region'], key: 'month', value: 'sales' })
+  t.deepEqual([...reshaper.push(input), ...reshaper.flush()], [
+    { region: 'north', jan: '10', feb: '12' },
+    { region: 'south', feb: '7' },
+  ])
+
+  const fixed = new CsvReshaper({ to: 'wide', ids: ['region'], key: 'month', value: 'sales', columns: ['jan', 'feb'] })
+  t.deepEqual([...fixed.push(input), ...fixed.flush()], [
+    { region: 'north', jan: '10', feb: '12' },
+    { region: 'south', jan: '', feb: '7' },
+  ])
+})
+
+test('rejects bad options and missing columns', (t) => {
+  t.throws(() => new CsvReshaper({ to: 'tall', ids: [] }), { message: 'to must be "long" or "wide", got "tall"' })
+
+  const reshaper = new CsvReshaper({ to: 'wide', ids: ['id'] })
+  t.throws(() => reshaper.push('id,key\n1,a\n'), { message: 'Column value not found' })
+})
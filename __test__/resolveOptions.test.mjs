@@ -0,0 +1,73 @@
+import test from 'ava'
+
+import csv from '../main.js'
+import { CsvParser, CsvWriter, resolveOptions } from '../index.js'
+
+test('resolveOptions applies defaults and dialect presets', (t) => {
+  t.deepEqual(resolveOptions(), {
+    separator: ',',
+    quote: '"',
+    escape: '"',
+    newline: '\n',
+    encoding: 'utf-8',
+    format: 'csv',
+    excel: false,
+    sepDirective: false,
+    decimalSeparator: '.',
+  })
+  t.like(resolveOptions({ dialect: 'postgres' }), { separator: '\t', format: 'postgres', nullValue: '\\N' })
+  t.like(resolveOptions({ dialect: 'excel', separator: ';' }), { separator: ';', excel: true, sepDirective: true })
+  t.like(resolveOptions({ separator: 'auto', encoding: 'latin1' }), { separator: 'auto', encoding: 'windows-1252' })
+  // `\n` reads CRLF line endings already
+  t.is(resolveOptions({ newline: '\r\n' }).newline, '\n')
+})
+
+test('one-character options must be one ASCII character', (t) => {
+  t.throws(() => new CsvParser({ separator: ';;' }), {
+    instanceOf: TypeError,
+    code: 'InvalidArg',
+    message: 'separator must be a single character, got ";;"',
+  })
+  t.throws(() => new CsvParser({ quote: '§' }), {
+    instanceOf: TypeError,
+    message: 'quote must be an ASCII character, got "§"',
+  })
+  t.throws(() => resolveOptions({ escape: '\\\\' }), { instanceOf: TypeError })
+})
+
+test('separator, quote, escape and newline must differ', (t) => {
+  t.throws(() => new CsvParser({ separator: '"' }), {
+    instanceOf: TypeError,
+    message: `separator and quote must differ, both are '"'`,
+  })
+  t.throws(() => new CsvParser({ separator: '\n' }), {
+    message: `separator and newline must differ, both are '\\n'`,
+  })
+  t.throws(() => new CsvParser({ quote: "'", newline: "'" }), {
+    message: `quote and newline must differ, both are '\\''`,
+  })
+  t.throws(() => new CsvParser({ escape: ',' }), {
+    message: `separator and escape must differ, both are ','`,
+  })
+  // The escape may be the quote, and a detected separator is not checked
+  t.notThrows(() => new CsvParser({ quote: "'", escape: "'" }))
+  t.notThrows(() => new CsvParser({ separator: 'auto', quote: ',' }))
+})
+
+test('writer options are checked', (t) => {
+  t.throws(() => new CsvWriter({ separator: ';', quote: ';' }), {
+    instanceOf: TypeError,
+    message: `separator and quote must differ, both are ';'`,
+  })
+  t.throws(() => new CsvWriter({ newline: ',\n' }), {
+    message: `newline must not contain the separator, ','`,
+  })
+  t.throws(() => new CsvWriter({ newline: '' }), { message: 'newline must not be empty' })
+  t.notThrows(() => new CsvWriter({ newline: '\r\n' }))
+})
+
+test('csv() emits invalid options as a TypeError', async (t) => {
+  const error = await new Promise((resolve) => csv({ quote: ',' }).on('error', resolve))
+  t.true(error instanceof TypeError)
+  t.is(error.message, `separator and quote must differ, both are ','`)
+})
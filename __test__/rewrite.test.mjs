@@ -0,0 +1,89 @@
+import test from "ava";
+import { Readable, Writable } from "stream";
+import { pipeline } from "stream/promises";
+import csv from "../main.js";
+import { CsvRewriter, CsvWriter } from "../index.js";
+
+test("CsvRewriter changes dialect and encoding", (t) => {
+  const rewriter = new CsvRewriter({
+    input: { separator: ";" },
+    inputEncoding: "latin1",
+    output: { newline: "\r\n" },
+  });
+  const out = Buffer.concat([
+    rewriter.push(Buffer.from("name;city\nJos\xe9;S\xe3o", "latin1")),
+    rewriter.push(Buffer.from(" Paulo, BR\n", "latin1")),
+    rewriter.flush(),
+  ]);
+
+  t.is(out.toString(), 'name,city\r\nJosé,"São Paulo, BR"\r\n');
+});
+
+test("CsvRewriter keeps, reorders and renames columns", (t) => {
+  const rewriter = new CsvRewriter({
+    columns: ["c", "a"],
+    rename: { c: "see" },
+    output: { quoteStyle: "always" },
+    outputEncoding: "utf-16le",
+    bom: true,
+  });
+  const out = Buffer.concat([rewriter.push("a,b,c\n1,2,3\n4,5,6"), rewriter.flush()]);
+
+  t.is(out.toString("utf16le"), '\ufeff"see","a"\n"3","1"\n"6","4"\n');
+});
+
+test("CsvRewriter reports bad options and input", (t) => {
+  t.throws(() => new CsvRewriter({ inputEncoding: "klingon" }), {
+    message: /Unknown encoding klingon/,
+  });
+  t.throws(() => new CsvRewriter({ output: { quoteStyle: "some" } }), {
+    message: /quoteStyle must be "necessary", "always", "non-numeric" or "never"/,
+  });
+
+  const rewriter = new CsvRewriter({ columns: ["z"] });
+  t.throws(() => rewriter.push("a\n1\n"), { message: /Column z not found/ });
+});
+
+test("CsvWriter supports quoteStyle and CRLF", (t) => {
+  const writer = new CsvWriter({ quoteStyle: "always", newline: "\r\n" });
+
+  t.is(writer.write([["a", "b"]]), '"a","b"\r\n');
+});
+
+test("CsvWriter writes the headers layout with missingValue", (t) => {
+  const writer = new CsvWriter({ headers: ["sku", "qty", "price"], missingValue: "N/A" });
+
+  t.is(
+    writer.write([
+      { price: 9.5, sku: "A-1", internalId: 7 },
+      { sku: "B-2", qty: null, price: undefined },
+    ]),
+    "sku,qty,price\nA-1,N/A,9.5\nB-2,,N/A\n",
+  );
+});
+
+test("CsvRewriter fills columns the input lacks", (t) => {
+  const rewriter = new CsvRewriter({
+    columns: ["id", "region", "name"],
+    output: { missingValue: "EU" },
+  });
+  const out = Buffer.concat([rewriter.push(Buffer.from("name,id\nAnn,1\n")), rewriter.flush()]);
+
+  t.is(out.toString(), "id,region,name\n1,EU,Ann\n");
+});
+
+test("rewrite() pipes Buffers through", async (t) => {
+  const chunks = [];
+  await pipeline(
+    Readable.from([Buffer.from("a|b\n1|2\n")]),
+    csv.rewrite({ input: { separator: "|" }, output: { separator: "\t" } }),
+    new Writable({
+      write(chunk, encoding, callback) {
+        chunks.push(chunk);
+        callback();
+      },
+    }),
+  );
+
+  t.is(Buffer.concat(chunks).toString(), "a\tb\n1\t2\n");
+});
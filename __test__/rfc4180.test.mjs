@@ -0,0 +1,54 @@
+import test from "ava";
+import { once } from "events";
+import { Readable } from "stream";
+import csv, { CsvValidator } from "../main.js";
+
+test("CsvValidator reports every violation with its position", (t) => {
+  const validator = new CsvValidator();
+  validator.push("a,b\r\n1,2\n3,x");
+  validator.push(Buffer.from('"y\r\n5\r\n'));
+
+  const report = validator.finish();
+  t.false(report.valid);
+  t.is(report.records, 4);
+  t.false(report.truncated);
+  t.deepEqual(
+    report.violations.map(({ line, column, code }) => [line, column, code]),
+    [
+      [2, 4, "bare-lf"],
+      [3, 4, "stray-quote"],
+      [4, 1, "field-count"],
+    ],
+  );
+  t.is(report.violations[2].message, "Expected 2 fields, found 1");
+});
+
+test("CsvValidator accepts valid input and truncates past maxViolations", (t) => {
+  const validator = new CsvValidator({ maxViolations: 1 });
+  validator.push('a,b\r\n"x\r\n""y""",2\r\n');
+  t.deepEqual(validator.finish(), {
+    valid: true,
+    records: 2,
+    violations: [],
+    truncated: false,
+  });
+
+  validator.push("a\nb\nc\n");
+  const report = validator.finish();
+  t.is(report.violations.length, 1);
+  t.true(report.truncated);
+});
+
+test("rfc4180 streams emit a validation report", async (t) => {
+  const stream = Readable.from(["a,b\r\n1,2\r\n3\r\n"]).pipe(
+    csv({ rfc4180: true }),
+  );
+  const [[report], rows] = await Promise.all([
+    once(stream, "validation"),
+    stream.toArray(),
+  ]);
+
+  t.is(rows.length, 2);
+  t.is(report.violations.length, 1);
+  t.is(report.violations[0].code, "field-count");
+});
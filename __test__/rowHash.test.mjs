@@ -0,0 +1,55 @@
+import test from "ava";
+import { createHash } from "crypto";
+import { Readable } from "stream";
+import csv from "../main.js";
+import { CsvParser } from "../index.js";
+
+const sha256 = (text) => createHash("sha256").update(text).digest("hex");
+
+test("rowHash adds a hash of each source line", (t) => {
+  const parser = new CsvParser({ rowHash: "sha256" });
+  const rows = parser.push('id,note\r\n1,"a\nb"\r\n2,c\r\n');
+  t.deepEqual(
+    rows.map((row) => row.__hash),
+    [sha256('1,"a\nb"'), sha256("2,c")],
+  );
+});
+
+test("xxh64 row hashes are 16 hex digits and stable across chunks", (t) => {
+  const whole = new CsvParser({ rowHash: "xxh64" }).push("a,b\n1,2\n3,4\n");
+  const split = new CsvParser({ rowHash: "xxh64" });
+  const rows = [...split.push("a,b\n1,"), ...split.push("2\n3,4"), ...split.flush()];
+  t.deepEqual(rows, whole);
+  t.true(/^[0-9a-f]{16}$/.test(rows[0].__hash));
+  t.not(rows[0].__hash, rows[1].__hash);
+});
+
+test("digest hashes all input pushed", (t) => {
+  const parser = new CsvParser({ digest: "sha256" });
+  t.is(parser.digest, sha256(""));
+  parser.push("a,b\n1,");
+  parser.push(Buffer.from("2\n"));
+  parser.flush();
+  t.is(parser.digest, sha256("a,b\n1,2\n"));
+
+  parser.reset();
+  t.is(parser.digest, sha256(""));
+  t.is(new CsvParser().digest, null);
+});
+
+test("the stream exposes the digest once it ends", async (t) => {
+  const input = "id\n1\n2\n";
+  const parser = Readable.from([input]).pipe(csv({ digest: "sha256" }));
+  const rows = await parser.toArray();
+  t.is(rows.length, 2);
+  t.is(parser.digest, sha256(input));
+});
+
+test("rowHash and digest take only known algorithms", (t) => {
+  t.throws(() => new CsvParser({ rowHash: "md5" }), {
+    message: 'rowHash must be "xxh64" or "sha256", got "md5"',
+  });
+  t.throws(() => new CsvParser({ digest: "crc32" }), {
+    message: 'digest must be "xxh64" or "sha256", got "crc32"',
+  });
+});
@@ -0,0 +1,131 @@
+import test from "ava";
+import { CsvParser, sample } from "../index.js";
+import csv from "../main.js";
+import { Readable } from "stream";
+
+function collect(input, options) {
+  return new Promise((resolve, reject) => {
+    const rows = [];
+    Readable.from([input])
+      .pipe(csv(options))
+      .on("data", (row) => rows.push(row))
+      .on("error", reject)
+      .on("end", () => resolve(rows));
+  });
+}
+
+test("null-prototype rows keep __proto__ as an ordinary key", (t) => {
+  const parser = new CsvParser({ rowObject: "null-prototype" });
+  const [row] = parser.push(Buffer.from("__proto__,constructor\n{},x\n"));
+
+  t.is(Object.getPrototypeOf(row), null);
+  t.deepEqual(Object.keys(row), ["__proto__", "constructor"]);
+  t.is(row.__proto__, "{}");
+  t.is(row.constructor, "x");
+});
+
+test("null-prototype applies to objects nested with expandHeaders", (t) => {
+  const parser = new CsvParser({ rowObject: "null-prototype", expandHeaders: true });
+  const [row] = parser.push(Buffer.from("a.b,__proto__.polluted\n1,yes\n"));
+
+  t.is(Object.getPrototypeOf(row.a), null);
+  t.is(row.a.b, "1");
+  t.is(row.__proto__.polluted, "yes");
+  t.is({}.polluted, undefined);
+});
+
+test("expandHeaders never sets properties on Object.prototype", async (t) => {
+  const input = "a,__proto__.polluted,constructor.prototype.polluted\n1,yes,yes\n";
+  new CsvParser({ expandHeaders: true }).push(Buffer.from(input));
+  await collect(input, { expandHeaders: true, mapValues: ({ value }) => value });
+
+  t.is({}.polluted, undefined);
+  t.is(Object.prototype.polluted, undefined);
+});
+
+test("frozen rows cannot be changed", (t) => {
+  const parser = new CsvParser({ rowObject: "frozen" });
+  const rows = [...parser.push(Buffer.from("a,b\n1,2\n")), ...parser.flush()];
+
+  t.deepEqual(rows, [{ a: "1", b: "2" }]);
+  t.true(Object.isFrozen(rows[0]));
+  t.throws(() => {
+    rows[0].a = "3";
+  });
+});
+
+test("rowObject applies to sample", (t) => {
+  const [row] = sample(Buffer.from("a\n1\n"), 1, { parser: { rowObject: "null-prototype" } });
+  t.is(Object.getPrototypeOf(row), null);
+});
+
+test("the stream respects rowObject after mapHeaders and mapValues", async (t) => {
+  const input = "a,b\n1,2\n";
+  const [nullRow] = await collect(input, {
+    rowObject: "null-prototype",
+    mapHeaders: ({ header }) => header.toUpperCase(),
+  });
+  t.is(Object.getPrototypeOf(nullRow), null);
+  t.deepEqual({ ...nullRow }, { A: "1", B: "2" });
+
+  const [frozenRow] = await collect(input, {
+    rowObject: "frozen",
+    mapValues: ({ value }) => Number(value),
+  });
+  t.true(Object.isFrozen(frozenRow));
+  t.deepEqual(frozenRow, { a: 1, b: 2 });
+});
+
+test("an unknown rowObject is refused", (t) => {
+  t.throws(() => new CsvParser({ rowObject: "sealed" }), {
+    message: 'rowObject must be "plain", "null-prototype", "frozen" or "entries", got "sealed"',
+  });
+});
+
+test("row keys follow column order, overflow columns after the headers", (t) => {
+  const parser = new CsvParser({ includeRawLine: true, rowHash: "sha256" });
+  const [row] = parser.push(Buffer.from("z,a,m\n1,2,3,4,5\n"));
+
+  t.deepEqual(Object.keys(row), ["z", "a", "m", "_3", "_4", "_raw", "__hash"]);
+});
+
+test("entries rows keep integer-like and duplicate headers in column order", (t) => {
+  const parser = new CsvParser({ rowObject: "entries" });
+  const input = Buffer.from("name,2024,2023,name\nAnn,1,2,Lee\n");
+  const rows = [...parser.push(input), ...parser.flush()];
+
+  t.deepEqual(rows, [
+    [
+      ["name", "Ann"],
+      ["2024", "1"],
+      ["2023", "2"],
+      ["name", "Lee"],
+    ],
+  ]);
+  // An object puts integer-like keys first
+  const [row] = new CsvParser().push(Buffer.from("name,2024,2023\nAnn,1,2\n"));
+  t.deepEqual(Object.keys(row), ["2023", "2024", "name"]);
+});
+
+test("the stream maps entries rows pair by pair", async (t) => {
+  const rows = await collect("b,a,b\n1,2,3\n", {
+    rowObject: "entries",
+    mapHeaders: ({ header, index }) => (index === 1 ? null : `${header}${index}`),
+    mapValues: ({ value }) => Number(value),
+  });
+  t.deepEqual(rows, [
+    [
+      ["b0", 1],
+      ["b2", 3],
+    ],
+  ]);
+});
+
+test("entries cannot be used with expandHeaders", async (t) => {
+  const message = 'rowObject "entries" cannot be used with expandHeaders';
+  t.throws(() => new CsvParser({ rowObject: "entries", expandHeaders: true }), { message });
+  await t.throwsAsync(
+    collect("a.b\n1\n", { rowObject: "entries", expandHeaders: true, mapValues: (c) => c.value }),
+    { message },
+  );
+});
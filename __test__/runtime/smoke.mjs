@@ -0,0 +1,30 @@
+// Runtime smoke test for Bun and Deno, which cannot run the ava suite.
+// Exits non-zero if the native module fails to load or parses wrongly.
+import assert from "node:assert/strict";
+import { Readable } from "node:stream";
+import csv from "../../main.js";
+
+const input = 'name,quote\nAnn,"say ""hi"""\nBob,"a\nb"\n';
+const expected = [
+  { name: "Ann", quote: 'say "hi"' },
+  { name: "Bob", quote: "a\nb" },
+];
+
+const nodeRows = [];
+await new Promise((resolve, reject) => {
+  Readable.from([Buffer.from(input)])
+    .pipe(csv())
+    .on("data", (row) => nodeRows.push(row))
+    .on("error", reject)
+    .on("end", resolve);
+});
+assert.deepEqual(nodeRows, expected);
+
+const webRows = [];
+const body = new Response(input).body.pipeThrough(csv.webStream());
+for await (const row of body) {
+  webRows.push(row);
+}
+assert.deepEqual(webRows, expected);
+
+console.log("ok");
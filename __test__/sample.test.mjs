@@ -0,0 +1,37 @@
+import test from "ava";
+import { sample } from "../main.js";
+import { mkdtempSync, writeFileSync } from "fs";
+import { tmpdir } from "os";
+import { join } from "path";
+
+const input = (rows) =>
+  Buffer.from("id,name\n" + Array.from({ length: rows }, (_, i) => `${i},row${i}\n`).join(""));
+
+test("sample returns k rows in input order", (t) => {
+  const rows = sample(input(1000), 10);
+  t.is(rows.length, 10);
+  const ids = rows.map((row) => Number(row.id));
+  t.deepEqual(ids, [...ids].sort((a, b) => a - b));
+  t.is(new Set(ids).size, 10);
+  t.deepEqual(rows[0], { id: String(ids[0]), name: `row${ids[0]}` });
+});
+
+test("sample with a seed is repeatable", (t) => {
+  t.deepEqual(sample(input(1000), 5, { seed: 1 }), sample(input(1000), 5, { seed: 1 }));
+});
+
+test("sample returns every row of a short input", (t) => {
+  t.deepEqual(sample(input(2), 5), [
+    { id: "0", name: "row0" },
+    { id: "1", name: "row1" },
+  ]);
+});
+
+test("sample reads a file with parser options", (t) => {
+  const path = join(mkdtempSync(join(tmpdir(), "sample-")), "rows.csv");
+  writeFileSync(path, "a;b\n1;2\n3;4\n");
+  t.deepEqual(sample(path, 2, { parser: { separator: ";" } }), [
+    { a: "1", b: "2" },
+    { a: "3", b: "4" },
+  ]);
+});
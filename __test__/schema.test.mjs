@@ -0,0 +1,68 @@
+import test from "ava";
+import { Readable } from "stream";
+import csv, { CsvParser } from "../main.js";
+
+const schema = {
+  type: "object",
+  properties: {
+    id: { type: "integer", minimum: 1 },
+    score: { type: ["number", "null"] },
+    active: { type: "boolean" },
+    plan: { enum: ["free", "pro"] },
+  },
+  required: ["id"],
+};
+const input = "id,score,active,plan,note\n1,2.5,TRUE,pro,x\n2,,false,free,\n0,n/a,,gold,y\n";
+
+test("schema converts cells to the types of its properties", (t) => {
+  const rows = new CsvParser({ schema, onViolation: "skip" }).push(input);
+  t.deepEqual(rows, [
+    { id: 1, score: 2.5, active: true, plan: "pro", note: "x" },
+    { id: 2, score: null, active: false, plan: "free", note: "" },
+  ]);
+});
+
+test("schema fails on the first invalid cell by default", (t) => {
+  t.throws(() => new CsvParser({ schema }).push(input), {
+    message: "Line 4: id must be >= 1",
+  });
+});
+
+test("schema annotates rows and reports violations", (t) => {
+  const parser = new CsvParser({ schema, onViolation: "annotate" });
+  const rows = parser.push(input);
+  // The empty boolean cell is left out, as the property allows no null
+  t.deepEqual(rows[2], {
+    id: "0",
+    score: "n/a",
+    plan: "gold",
+    note: "y",
+    __violations: ["id", "score", "plan"],
+  });
+  t.deepEqual(rows[0].__violations, []);
+  t.deepEqual(parser.getViolations(), [
+    { line: 4, column: "id", keyword: "minimum", message: "must be >= 1" },
+    { line: 4, column: "score", keyword: "type", message: "must be null or number" },
+    { line: 4, column: "plan", keyword: "enum", message: "must be one of the allowed values" },
+  ]);
+});
+
+test("schema violations are emitted when the stream ends", async (t) => {
+  const stream = Readable.from([input]).pipe(csv({ schema, onViolation: "skip" }));
+  const reported = new Promise((resolve) => stream.on("violations", resolve));
+  const rows = await stream.toArray();
+
+  t.is(rows.length, 2);
+  t.is(stream.invalidRows, 1);
+  t.deepEqual(
+    (await reported).map(({ column }) => column),
+    ["id", "score", "plan"],
+  );
+});
+
+test("schema rejects unsupported keywords", (t) => {
+  t.throws(() => new CsvParser({ schema: { properties: { id: { $ref: "#/x" } } } }), {
+    instanceOf: TypeError,
+    message: 'Unsupported keyword "$ref" at /properties/id of schema',
+  });
+});
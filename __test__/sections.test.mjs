@@ -0,0 +1,58 @@
+import test from "ava";
+import { Readable } from "stream";
+import csv, { CsvParser } from "../main.js";
+
+const report = "## Orders\nid,total\n1,10\n2,20\n\n## Refunds\nid,order,amount\n7,1,5\n";
+
+test("sections splits the input into tables with their own headers", (t) => {
+  const parser = new CsvParser({ sections: { marker: "## " } });
+
+  t.deepEqual(parser.push(report), [
+    { id: "1", total: "10" },
+    { id: "2", total: "20" },
+  ]);
+  t.is(parser.section, 0);
+  t.is(parser.sectionName, "Orders");
+
+  // The rest of the input stays buffered until asked for
+  t.deepEqual(parser.next(), [{ id: "7", order: "1", amount: "5" }]);
+  t.is(parser.section, 1);
+  t.is(parser.sectionName, "Refunds");
+  t.deepEqual(parser.getHeaders(), ["id", "order", "amount"]);
+  t.deepEqual(parser.next(), []);
+});
+
+test("sections: true splits at blank lines", (t) => {
+  const parser = new CsvParser({ sections: true });
+  const input = "a,b\n1,2\n\n\nc\n3";
+
+  t.deepEqual(parser.push(input), [{ a: "1", b: "2" }]);
+  t.deepEqual(parser.flush(), [{ c: "3" }]);
+  t.is(parser.section, 1);
+  t.is(parser.sectionName, null);
+});
+
+test("the stream emits a section event before the rows of each table", async (t) => {
+  const events = [];
+  const stream = Readable.from([report.slice(0, 20), report.slice(20)]).pipe(
+    csv({ sections: { marker: "## " } }),
+  );
+  stream.on("section", (section) => events.push(section));
+  stream.on("data", (row) => events.push(row));
+  await new Promise((resolve, reject) => stream.on("end", resolve).on("error", reject));
+
+  t.deepEqual(events, [
+    { index: 0, name: "Orders", headers: ["id", "total"] },
+    { id: "1", total: "10" },
+    { id: "2", total: "20" },
+    { index: 1, name: "Refunds", headers: ["id", "order", "amount"] },
+    { id: "7", order: "1", amount: "5" },
+  ]);
+});
+
+test("sections.marker must not be empty", (t) => {
+  t.throws(() => new CsvParser({ sections: { marker: "" } }), {
+    instanceOf: TypeError,
+    message: "sections.marker must not be empty",
+  });
+});
@@ -0,0 +1,16 @@
+import test from "ava";
+import { collect } from "./helpers/helper.mjs";
+
+test("sep= directive sets the separator", async (t) => {
+  const { error, lines } = await collect("excel-sep", { sepDirective: true });
+
+  t.false(error, "no err");
+  t.deepEqual(lines, [{ name: "widget", price: "1,50" }]);
+});
+
+test("sep= directive is data unless enabled", async (t) => {
+  const { error, lines } = await collect("excel-sep");
+
+  t.false(error, "no err");
+  t.deepEqual(Object.keys(lines[0]), ["sep=;"]);
+});
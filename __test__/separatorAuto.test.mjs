@@ -0,0 +1,64 @@
+import test from "ava";
+import { CsvParser, head } from "../index.js";
+import { collect } from "./helpers/helper.mjs";
+
+test("separator auto detects semicolons", (t) => {
+  const parser = new CsvParser({ separator: "auto" });
+  const rows = [...parser.push(Buffer.from("name;price\nwidget;1,50\n")), ...parser.flush()];
+
+  t.deepEqual(rows, [{ name: "widget", price: "1,50" }]);
+});
+
+test("separator auto detects tabs and pipes", (t) => {
+  for (const separator of ["\t", "|"]) {
+    const parser = new CsvParser({ separator: "auto" });
+    const input = `a${separator}b\n1${separator}2\n`;
+    t.deepEqual(parser.push(Buffer.from(input)), [{ a: "1", b: "2" }]);
+  }
+});
+
+test("separator auto keeps commas for comma files", (t) => {
+  const parser = new CsvParser({ separator: "auto" });
+  t.deepEqual(parser.push(Buffer.from('a,b\n"x;y",2\n')), [{ a: "x;y", b: "2" }]);
+});
+
+test("separator auto skips comment lines", (t) => {
+  const parser = new CsvParser({ separator: "auto", skipComments: true });
+  t.deepEqual(parser.push(Buffer.from("# exported; 2024\na|b\n1|2\n")), [{ a: "1", b: "2" }]);
+});
+
+test("separator auto leaves a sep= directive in charge", async (t) => {
+  const { error, lines } = await collect("excel-sep", { separator: "auto", sepDirective: true });
+
+  t.false(error, "no err");
+  t.deepEqual(lines, [{ name: "widget", price: "1,50" }]);
+});
+
+test("separator auto reads a sep= directive by default", async (t) => {
+  const { error, lines } = await collect("excel-sep", { separator: "auto" });
+
+  t.false(error, "no err");
+  t.deepEqual(lines, [{ name: "widget", price: "1,50" }]);
+
+  // The directive line is skipped before the sniffer samples the input
+  const parser = new CsvParser({ separator: "auto" });
+  t.deepEqual(parser.push(Buffer.from("sep=|\na|b;c\n1|2;3\n")), [{ a: "1", "b;c": "2;3" }]);
+
+  // Turned off, the line is sniffed and read as the header
+  const off = new CsvParser({ separator: "auto", sepDirective: false });
+  t.deepEqual(Object.keys(off.push(Buffer.from("sep=;\na;b\n1;2\n"))[0]), ["sep="]);
+});
+
+test("separator auto is reported by head", (t) => {
+  const { rows, dialect } = head(Buffer.from("a;b\n1;2\n"), 1, { separator: "auto" });
+  t.deepEqual(rows, [{ a: "1", b: "2" }]);
+  t.is(dialect.separator, ";");
+});
+
+test("separator auto chooses again after reset", (t) => {
+  const parser = new CsvParser({ separator: "auto" });
+  t.deepEqual(parser.push(Buffer.from("a;b\n1;2\n")), [{ a: "1", b: "2" }]);
+
+  parser.reset();
+  t.deepEqual(parser.push(Buffer.from("a,b\n3,4\n")), [{ a: "3", b: "4" }]);
+});
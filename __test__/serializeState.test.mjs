@@ -0,0 +1,94 @@
+import test from "ava";
+import { Worker } from "worker_threads";
+import { Readable } from "stream";
+import { CsvParser } from "../index.js";
+import csv from "../main.js";
+
+test("a parse resumes from serialized state", (t) => {
+  const parser = new CsvParser({ separator: ";", dedupe: true });
+  t.deepEqual(parser.push("a;b\n1;x\n1;x\n2;\"y"), [{ a: "1", b: "x" }]);
+
+  const resumed = CsvParser.deserializeState(parser.serializeState());
+  t.deepEqual(resumed.getHeaders(), ["a", "b"]);
+  t.deepEqual(resumed.push('\n"\n1;x\n3;z'), [{ a: "2", b: "y\n" }]);
+  t.deepEqual(resumed.flush(), [{ a: "3", b: "z" }]);
+  t.is(resumed.duplicates, 2);
+});
+
+test("a parse resumes on a worker thread", async (t) => {
+  const parser = new CsvParser();
+  parser.push("name,age\nAnn,3");
+
+  const source = `
+    const { parentPort, workerData } = require("worker_threads");
+    const { CsvParser } = require(workerData.module);
+    const parser = CsvParser.deserializeState(Buffer.from(workerData.state));
+    parentPort.postMessage(parser.push("0\\nBob,41\\n"));
+  `;
+  const worker = new Worker(source, {
+    eval: true,
+    workerData: {
+      module: new URL("../index.js", import.meta.url).pathname,
+      state: parser.serializeState(),
+    },
+  });
+  const rows = await new Promise((resolve, reject) => {
+    worker.once("message", resolve);
+    worker.once("error", reject);
+  });
+  await worker.terminate();
+
+  t.deepEqual(rows, [
+    { name: "Ann", age: "30" },
+    { name: "Bob", age: "41" },
+  ]);
+});
+
+test("deserializeState rejects invalid state", (t) => {
+  t.throws(() => CsvParser.deserializeState(Buffer.from("not a state")), {
+    message: /Invalid parser state/,
+  });
+});
+
+test("a parse resumes from a checkpoint at its byte offset", (t) => {
+  const input = Buffer.from("a,b\n1,x\n2,\"y\nz\"\n3,w\n");
+  const parser = new CsvParser({ dedupe: true });
+  t.deepEqual(parser.push(input.subarray(0, 12)), [{ a: "1", b: "x" }]);
+
+  const checkpoint = parser.getCheckpoint();
+  t.is(checkpoint.offset, 12);
+
+  const resumed = CsvParser.fromCheckpoint(checkpoint);
+  t.deepEqual(resumed.push(input.subarray(checkpoint.offset)), [
+    { a: "2", b: "y\nz" },
+    { a: "3", b: "w" },
+  ]);
+  t.is(resumed.getCheckpoint().offset, input.length);
+});
+
+test("fromCheckpoint rejects a mismatched offset", (t) => {
+  const parser = new CsvParser();
+  parser.push("a\n1\n");
+  const { state } = parser.getCheckpoint();
+  t.throws(() => CsvParser.fromCheckpoint({ offset: 1, state }), {
+    message: /^Checkpoint offset 1 does not match its state, which is at 4/,
+  });
+});
+
+test("a stream resumes from a checkpoint", async (t) => {
+  const input = Buffer.from("a;b\n1;x\n2;y\n3;z\n");
+  // The first run is cut off mid-row, without ending the stream
+  const first = csv({ separator: ";" });
+  const before = [];
+  first.on("data", (row) => before.push(row));
+  await new Promise((resolve) => first.write(input.subarray(0, 10), resolve));
+  const checkpoint = first.getCheckpoint();
+  first.destroy();
+
+  const second = Readable.from([input.subarray(checkpoint.offset)]).pipe(csv({ checkpoint }));
+  t.deepEqual([...before, ...(await second.toArray())], [
+    { a: "1", b: "x" },
+    { a: "2", b: "y" },
+    { a: "3", b: "z" },
+  ]);
+});
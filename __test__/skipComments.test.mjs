@@ -18,3 +18,17 @@ test("custom comment", async (t) => {
   t.snapshot(lines);
   t.is(lines.length, 1, "1 row");
 });
+
+test("multi-character comment prefix with trailing comments", async (t) => {
+  const { error, lines } = await collect("option-trailing-comment", {
+    skipComments: "//",
+    trailingComments: true,
+    rtrim: true,
+  });
+
+  t.false(error, "no err");
+  t.deepEqual(lines, [
+    { a: "1", b: "// not a comment" },
+    { a: "2", b: "3" },
+  ]);
+});
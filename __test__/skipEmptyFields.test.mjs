@@ -0,0 +1,30 @@
+import test from "ava";
+import { Readable } from "stream";
+import csv, { CsvParser } from "../main.js";
+
+const input = 'a,b,c\n1,,3\n,"",\n';
+
+test("empty cells are kept by default", (t) => {
+  const parser = new CsvParser();
+  t.deepEqual(parser.push(input), [
+    { a: "1", b: "", c: "3" },
+    { a: "", b: "", c: "" },
+  ]);
+});
+
+test("skipEmptyFields leaves empty cells out", (t) => {
+  const parser = new CsvParser({ skipEmptyFields: true });
+  t.deepEqual(parser.push(input), [{ a: "1", c: "3" }, {}]);
+});
+
+test("skipEmptyFields rows stay sparse through mapHeaders", async (t) => {
+  const rows = await Readable.from([input])
+    .pipe(
+      csv({
+        skipEmptyFields: true,
+        mapHeaders: ({ header }) => header.toUpperCase(),
+      }),
+    )
+    .toArray();
+  t.deepEqual(rows, [{ A: "1", C: "3" }, {}]);
+});
@@ -0,0 +1,33 @@
+import test from "ava";
+import { CsvParser } from "../index.js";
+
+const input = "a,b\n\n  \n1,2\n,\n\"\",\n3,4\n ,  \n";
+
+function parse(options) {
+  const parser = new CsvParser(options);
+  return [...parser.push(input), ...parser.flush()];
+}
+
+test("whitespace-only lines become rows by default", (t) => {
+  t.is(parse().length, 6);
+});
+
+test("skipEmptyLines: true skips whitespace-only lines", (t) => {
+  t.deepEqual(parse({ skipEmptyLines: true }), [
+    { a: "1", b: "2" },
+    { a: "", b: "" },
+    { a: "", b: "" },
+    { a: "3", b: "4" },
+    { a: " ", b: "  " },
+  ]);
+});
+
+test("skipEmptyLines: 'greedy' also skips records of empty or blank cells", (t) => {
+  t.deepEqual(parse({ skipEmptyLines: "greedy" }), [
+    { a: "1", b: "2" },
+    { a: "3", b: "4" },
+  ]);
+  t.throws(() => new CsvParser({ skipEmptyLines: "eager" }), {
+    message: /skipEmptyLines must be a boolean or "greedy"/,
+  });
+});
@@ -1,5 +1,6 @@
 import test from "ava";
 import { collect } from "./helpers/helper.mjs";
+import { CsvParser } from "../main.js";
 
 test("skip lines", async (t) => {
   const { error, lines } = await collect("bad-data", { skipLines: 2 });
@@ -29,3 +30,13 @@ test("skip lines with headers", async (t) => {
     JSON.stringify({ s: "ok", p: "ok", h: "ok!" }),
   );
 });
+
+test("skip lines counts comments, blank lines and open quotes", (t) => {
+  const parser = new CsvParser({ skipLines: 3, skipComments: true });
+  const rows = parser.push(
+    '# generated\r\n\r\n"unbalanced\r\nname,age\r\n# note\r\nAda,36\r\n',
+  );
+
+  t.deepEqual(parser.getHeaders(), ["name", "age"]);
+  t.deepEqual(rows, [{ name: "Ada", age: "36" }]);
+});
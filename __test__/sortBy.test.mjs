@@ -0,0 +1,49 @@
+import test from "ava";
+import fs from "fs";
+import os from "os";
+import path from "path";
+import { sortBy } from "../index.js";
+
+function input(name, contents) {
+  const file = path.join(os.tmpdir(), `fast-csv-parser-sortBy-${name}.csv`);
+  fs.writeFileSync(file, contents);
+  return file;
+}
+
+const scores = "name,score\nAnn,10\nBob,9\nCy,x\nDee,10\nEd,100\n";
+
+test("sortBy writes the sorted CSV", (t) => {
+  const file = input("output", scores);
+  const output = `${file}.sorted`;
+
+  t.is(sortBy(file, { column: "score", numeric: true, desc: true, output }), 5);
+  t.is(
+    fs.readFileSync(output, "utf8"),
+    "name,score\nEd,100\nAnn,10\nDee,10\nBob,9\nCy,x\n",
+  );
+  fs.unlinkSync(output);
+});
+
+test("sortBy returns rows in batches, spilling runs to disk", (t) => {
+  const file = input("rows", scores);
+  const rows = sortBy(file, { column: "name", desc: true, memoryLimit: 1 });
+
+  t.deepEqual(rows.headers, ["name", "score"]);
+  t.deepEqual(
+    rows.next(2).map((row) => row.name),
+    ["Ed", "Dee"],
+  );
+  t.deepEqual(rows.next()[2], { name: "Ann", score: "10" });
+  t.deepEqual(rows.next(), []);
+});
+
+test("sortBy rejects unknown columns", (t) => {
+  const file = input("missing", scores);
+
+  t.throws(() => sortBy(file, { column: "age" }), {
+    message: /Column age not found/,
+  });
+  t.throws(() => sortBy(file, { column: "name", memoryLimit: 0 }), {
+    message: /memoryLimit must be a positive integer/,
+  });
+});
@@ -0,0 +1,46 @@
+import test from "ava";
+import { mkdtempSync, readFileSync, writeFileSync } from "fs";
+import { tmpdir } from "os";
+import { join } from "path";
+import { split } from "../main.js";
+
+function input(contents) {
+  const dir = mkdtempSync(join(tmpdir(), "split-"));
+  const path = join(dir, "data.csv");
+  writeFileSync(path, contents);
+  return { path, out: join(dir, "shards") };
+}
+
+test("split by rows repeats the header and keeps quoted newlines", (t) => {
+  const { path, out } = input('id,note\n1,a\n2,"b\nc"\n3,d\n');
+  const shards = split(path, { rows: 2 }, out);
+  t.deepEqual(
+    shards.map((shard) => shard.path),
+    [join(out, "data-00001.csv"), join(out, "data-00002.csv")],
+  );
+  t.deepEqual(
+    shards.map((shard) => shard.rows),
+    [2, 1],
+  );
+  t.is(readFileSync(shards[0].path, "utf8"), 'id,note\n1,a\n2,"b\nc"\n');
+  t.is(readFileSync(shards[1].path, "utf8"), "id,note\n3,d\n");
+});
+
+test("split by size", (t) => {
+  const { path, out } = input("id\n" + "1234567\n".repeat(300));
+  const shards = split(path, { bytes: "1KB" }, out);
+  t.deepEqual(
+    shards.map((shard) => shard.rows),
+    [127, 127, 46],
+  );
+  t.true(shards.every((shard) => shard.bytes <= 1024));
+});
+
+test("split needs one limit", (t) => {
+  const { path, out } = input("a\n1\n");
+  t.throws(() => split(path, {}, out), { message: "split needs one of rows or bytes" });
+  t.throws(() => split(path, { rows: 1, bytes: 10 }, out));
+  t.throws(() => split(path, { bytes: "10 parsecs" }, out), {
+    message: 'Invalid size "10 parsecs"',
+  });
+});
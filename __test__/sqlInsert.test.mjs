@@ -0,0 +1,69 @@
+import test from "ava";
+import { mkdtempSync, readFileSync } from "fs";
+import { tmpdir } from "os";
+import { join } from "path";
+import { CsvRewriter, CsvWriter } from "../main.js";
+
+test("sql writes batched INSERT statements", (t) => {
+  const writer = new CsvWriter({ sql: { table: "sales.orders", batchSize: 2 } });
+  t.is(
+    writer.write([{ id: 1, note: "O'Neil" }, { id: 2, note: null }, { id: 3 }]),
+    'INSERT INTO "sales"."orders" ("id", "note") VALUES\n'
+      + "('1', 'O''Neil'),\n('2', NULL);\n"
+      + 'INSERT INTO "sales"."orders" ("id", "note") VALUES\n'
+      + "('3', NULL);\n",
+  );
+  // No header line, and later writes start new statements
+  t.is(
+    writer.write([{ id: 4, note: "x" }]),
+    'INSERT INTO "sales"."orders" ("id", "note") VALUES\n' + "('4', 'x');\n",
+  );
+});
+
+test("sql writes numbers bare with non-numeric quoting", (t) => {
+  const writer = new CsvWriter({
+    sql: { table: "t", identifierQuote: "`" },
+    quoteStyle: "non-numeric",
+    newline: "\r\n",
+    missingValue: "",
+    quoteIf: (value, column) => column === 1,
+  });
+  t.is(
+    writer.write([[1.5, "02134", "a\\b"]]),
+    "INSERT INTO `t` VALUES\r\n(1.5, '02134', 'a\\b');\r\n",
+  );
+  t.is(
+    new CsvWriter({ sql: { table: "t" }, missingValue: "" }).write([{ a: 1 }, {}]),
+    'INSERT INTO "t" ("a") VALUES\n(\'1\'),\n(\'\');\n',
+  );
+});
+
+test("sql appends statements to a file", (t) => {
+  const path = join(mkdtempSync(join(tmpdir(), "sql-")), "load.sql");
+  const writer = new CsvWriter({ sql: { table: "t" } });
+  writer.appendTo(path, [{ a: 1 }]);
+  writer.appendTo(path, [{ a: 2 }]);
+  t.is(
+    readFileSync(path, "utf8"),
+    'INSERT INTO "t" ("a") VALUES\n' + "('1');\n" + 'INSERT INTO "t" ("a") VALUES\n' + "('2');\n",
+  );
+});
+
+test("sql options are checked", (t) => {
+  t.throws(() => new CsvWriter({ sql: { table: "" } }), {
+    instanceOf: TypeError,
+    message: "sql.table must not be empty",
+  });
+  t.throws(() => new CsvWriter({ sql: { table: "t", batchSize: 0 } }), {
+    message: "sql.batchSize must be a positive integer",
+  });
+  t.throws(() => new CsvWriter({ sql: { table: "t" }, quoteStyle: "never" }), {
+    message: 'quoteStyle "never" cannot be used with sql',
+  });
+  t.throws(() => new CsvWriter({ sql: { table: "t", identifierQuote: "[]" } }), {
+    message: 'sql.identifierQuote must be a single character, got "[]"',
+  });
+  t.throws(() => new CsvRewriter({ output: { sql: { table: "t" } } }), {
+    message: "output.sql is not supported by rewrite",
+  });
+});
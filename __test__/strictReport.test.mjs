@@ -0,0 +1,61 @@
+import test from "ava";
+import { Readable } from "stream";
+import csv, { CsvParser } from "../main.js";
+
+const ragged = 'a,b\n1,2\n3\n"x\ny",4,5\n6,7\n';
+
+test("strict: 'report' leaves ragged rows out and lists them", (t) => {
+  const parser = new CsvParser({ strict: "report" });
+  const rows = [...parser.push(ragged), ...parser.flush()];
+
+  t.deepEqual(rows, [
+    { a: "1", b: "2" },
+    { a: "6", b: "7" },
+  ]);
+  t.deepEqual(parser.getRaggedRows(), [
+    { line: 3, expected: 2, actual: 1 },
+    { line: 4, expected: 2, actual: 3 },
+  ]);
+});
+
+test("strict: 'report' emits raggedRows when the stream ends", async (t) => {
+  const stream = Readable.from([ragged.slice(0, 11), ragged.slice(11)]).pipe(
+    csv({ strict: "report" }),
+  );
+  const reported = new Promise((resolve) => stream.on("raggedRows", resolve));
+  const rows = await stream.toArray();
+
+  t.is(rows.length, 2);
+  t.deepEqual(await reported, stream.getRaggedRows());
+  t.deepEqual(
+    (await reported).map(({ line }) => line),
+    [3, 4],
+  );
+});
+
+test("strict: 'report' survives a checkpoint", (t) => {
+  const parser = new CsvParser({ strict: "report", headers: false });
+  parser.push("1,2\n3\n");
+  const resumed = CsvParser.fromCheckpoint(parser.getCheckpoint());
+  resumed.push("4,5,6\n");
+
+  t.deepEqual(resumed.getRaggedRows(), [
+    { line: 2, expected: 2, actual: 1 },
+    { line: 3, expected: 2, actual: 3 },
+  ]);
+});
+
+test("strict: true still fails on the first ragged row", (t) => {
+  const parser = new CsvParser({ strict: true });
+  t.throws(() => parser.push(ragged), {
+    message: "Row length does not match headers",
+  });
+  t.deepEqual(parser.getRaggedRows(), []);
+});
+
+test("strict must be a boolean or 'report'", (t) => {
+  t.throws(() => new CsvParser({ strict: "warn" }), {
+    instanceOf: TypeError,
+    message: 'strict must be a boolean or "report", got "warn"',
+  });
+});
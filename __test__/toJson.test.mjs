@@ -0,0 +1,59 @@
+import test from "ava";
+import { mkdtempSync, readFileSync, writeFileSync } from "fs";
+import { tmpdir } from "os";
+import { join } from "path";
+import { CsvParser, toJson } from "../main.js";
+
+const dir = () => mkdtempSync(join(tmpdir(), "toJson-"));
+
+test("toJson writes a JSON array by default", (t) => {
+  const output = join(dir(), "out.json");
+  t.is(toJson(Buffer.from('a,b\n1,x\n2,"y,z"\n'), output), 2);
+  t.is(readFileSync(output, "utf8"), '[\n{"a":"1","b":"x"},\n{"a":"2","b":"y,z"}\n]\n');
+  t.deepEqual(JSON.parse(readFileSync(output, "utf8")), [
+    { a: "1", b: "x" },
+    { a: "2", b: "y,z" },
+  ]);
+
+  t.is(toJson(Buffer.from("a,b\n"), output), 0);
+  t.deepEqual(JSON.parse(readFileSync(output, "utf8")), []);
+});
+
+test("toJson writes NDJSON from a file", (t) => {
+  const input = join(dir(), "in.csv");
+  writeFileSync(input, "id;name\n1;Ann\n2;Bob");
+  const output = join(dir(), "out.ndjson");
+  t.is(toJson(input, output, { format: "ndjson", parser: { separator: ";" } }), 2);
+  t.is(readFileSync(output, "utf8"), '{"id":"1","name":"Ann"}\n{"id":"2","name":"Bob"}\n');
+});
+
+test("toJson writes rows as JSON.stringify writes parsed rows", (t) => {
+  const options = {
+    nullValue: "NULL",
+    columnTypes: { n: { type: "number" }, at: { type: "date" } },
+    arrayColumns: { tags: "|" },
+    jsonColumns: ["meta"],
+    expandHeaders: true,
+    extraColumns: "collect",
+  };
+  const input = "n,at,tags,meta,user.name,user.ids[1]\n"
+    + '1.5,2024-01-02,a|b,"{""k"":[1,2]}",Ann,7\n'
+    + "2,,,NULL,,x,extra\n"
+    + "abc,soon,c,,Bob,\n";
+  const output = join(dir(), "out.json");
+  toJson(Buffer.from(input), output, { format: "ndjson", parser: options });
+
+  const rows = new CsvParser(options).push(input);
+  const expected = rows.map((row) => JSON.stringify(row)).join("\n") + "\n";
+  t.is(readFileSync(output, "utf8"), expected);
+});
+
+test("toJson checks its options and paths", (t) => {
+  const output = join(dir(), "out.json");
+  t.throws(() => toJson(Buffer.from("a\n1\n"), output, { format: "yaml" }), {
+    instanceOf: TypeError,
+    message: 'format must be "array" or "ndjson", got "yaml"',
+  });
+  t.throws(() => toJson(join(dir(), "missing.csv"), output), { message: /^Cannot open / });
+  t.throws(() => toJson(Buffer.from("a,b\n1\n"), output, { parser: { strict: true } }));
+});
@@ -0,0 +1,35 @@
+import test from "ava";
+import { mkdtempSync, writeFileSync } from "fs";
+import { tmpdir } from "os";
+import { join } from "path";
+import { toSqlite } from "../main.js";
+
+// `toSqlite` is only in builds with the `sqlite` feature
+const sqliteTest = toSqlite ? test : test.skip;
+const dir = () => mkdtempSync(join(tmpdir(), "toSqlite-"));
+
+sqliteTest("toSqlite inserts rows and appends to an existing table", (t) => {
+  const db = join(dir(), "rows.db");
+  const input = join(dir(), "people.csv");
+  writeFileSync(input, "name;age\nAnn;30\nBob;41\nCy;\n");
+  const options = { table: "people", types: { age: "integer" }, batchSize: 2 };
+
+  t.is(toSqlite(input, db, { ...options, parser: { separator: ";" } }), 3);
+  t.is(toSqlite(Buffer.from("name,age\nDee,7\n"), db, options), 1);
+});
+
+sqliteTest("toSqlite checks its options", (t) => {
+  const db = join(dir(), "rows.db");
+  const input = Buffer.from("a\n1\n");
+  t.throws(() => toSqlite(input, db, { table: "t", types: { a: "date" } }), {
+    instanceOf: TypeError,
+    message: 'types.a must be "text", "integer" or "real", got "date"',
+  });
+  t.throws(() => toSqlite(input, db, { table: "t", batchSize: 0 }), {
+    instanceOf: TypeError,
+    message: "batchSize must be a positive integer",
+  });
+  t.throws(() => toSqlite(input, db, { table: "missing", createTable: false }), {
+    message: /no such table: missing/,
+  });
+});
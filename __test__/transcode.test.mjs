@@ -0,0 +1,42 @@
+import test from "ava";
+import { Readable } from "stream";
+import { Transcoder } from "../index.js";
+import { transcode } from "../main.js";
+
+test("Transcoder decodes characters split across chunks", (t) => {
+  const transcoder = new Transcoder("shift_jis");
+  const input = Buffer.from([0x93, 0xfa, 0x96, 0x7b, 0x0a]);
+  const out = [...input].map((byte) => transcoder.push(Buffer.from([byte])));
+  out.push(transcoder.flush());
+
+  t.is(Buffer.concat(out).toString(), "日本\n");
+});
+
+test("Transcoder follows a BOM", (t) => {
+  const transcoder = new Transcoder("latin1");
+  t.is(transcoder.encoding, "windows-1252");
+
+  const out = transcoder.push(Buffer.from([0xff, 0xfe, 0x61, 0x00, 0xe9, 0x00]));
+  t.is(out.toString(), "aé");
+  t.is(transcoder.encoding, "UTF-16LE");
+});
+
+test("Transcoder reports bad input", (t) => {
+  t.throws(() => new Transcoder("klingon"), { message: "Unknown encoding klingon" });
+
+  const transcoder = new Transcoder();
+  t.throws(() => transcoder.push(Buffer.from([0x61, 0xff])), {
+    message: "Encoding conversion error: invalid UTF-8 input",
+  });
+});
+
+test("transcode() pipes Buffers through", async (t) => {
+  const chunks = [];
+  for await (const chunk of Readable.from([Buffer.from("Jos"), Buffer.from([0xe9])]).pipe(
+    transcode("latin1"),
+  )) {
+    chunks.push(chunk);
+  }
+
+  t.is(Buffer.concat(chunks).toString(), "José");
+});
@@ -0,0 +1,50 @@
+import test from "ava";
+import { collect } from "./helpers/helper.mjs";
+
+test("transformRow is called once per batch", async (t) => {
+  const batches = [];
+  const transformRow = (rows) => {
+    batches.push(rows.length);
+    for (const row of rows) {
+      row.a = Number(row.a);
+    }
+  };
+
+  const { error, lines } = await collect("option-batch-size", { batchSize: 3, transformRow });
+
+  t.false(error, "no err");
+  t.deepEqual(batches, [3, 3, 1]);
+  t.deepEqual(
+    lines.map((row) => row.a),
+    [1, 2, 3, 4, 5, 6, 7],
+  );
+});
+
+test("transformRow can drop and replace rows", async (t) => {
+  const transformRow = (rows) =>
+    rows.filter((row) => row.a % 2 === 1).map((row) => ({ odd: row.a, mapped: true }));
+
+  const { error, lines } = await collect("option-batch-size", {
+    transformRow,
+    mapValues: ({ value }) => Number(value),
+  });
+
+  t.false(error, "no err");
+  t.deepEqual(
+    lines.map((row) => row.odd),
+    [1, 3, 5, 7],
+  );
+  t.true(lines[0].mapped);
+});
+
+test("transformRow errors fail the stream", async (t) => {
+  const { error } = await collect("option-batch-size", { transformRow: () => "rows" });
+  t.is(error.message, "transformRow must return an array of rows or undefined");
+
+  const thrown = await collect("option-batch-size", {
+    transformRow: () => {
+      throw new Error("bad batch");
+    },
+  });
+  t.is(thrown.error.message, "bad batch");
+});
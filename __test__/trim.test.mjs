@@ -0,0 +1,38 @@
+import test from "ava";
+import { collect } from "./helpers/helper.mjs";
+import { CsvParser } from "../index.js";
+
+test("trim", async (t) => {
+  const { error, lines } = await collect("option-trim", { trim: true });
+
+  t.false(error, "no err");
+  t.deepEqual(lines, [
+    { name: "Ann", city: "Paris, FR" },
+    { name: "Bob", city: " Oslo " },
+  ]);
+});
+
+test("rtrim", async (t) => {
+  const { error, lines } = await collect("option-trim", { rtrim: true });
+
+  t.false(error, "no err");
+  t.deepEqual(Object.keys(lines[0]), ["name", " city"]);
+  t.is(lines[0].name, "  Ann");
+});
+
+test("trim: 'all' also trims inside quotes", async (t) => {
+  const { error, lines } = await collect("option-trim", { trim: "all" });
+
+  t.false(error, "no err");
+  t.deepEqual(lines, [
+    { name: "Ann", city: "Paris, FR" },
+    { name: "Bob", city: "Oslo" },
+  ]);
+});
+
+test("trim must be a boolean or 'all'", (t) => {
+  t.throws(() => new CsvParser({ trim: "both" }), {
+    instanceOf: TypeError,
+    message: 'trim must be a boolean or "all", got "both"',
+  });
+});
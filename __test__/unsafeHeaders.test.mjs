@@ -0,0 +1,57 @@
+import test from "ava";
+import { CsvParser } from "../index.js";
+import csv from "../main.js";
+import { Readable } from "stream";
+
+const input = "id,__proto__,constructor\n1,{},x\n";
+
+function parse(options) {
+  const parser = new CsvParser(options);
+  return [...parser.push(Buffer.from(input)), ...parser.flush()];
+}
+
+test("unsafe header names are kept by default", (t) => {
+  const [row] = parse({});
+  t.deepEqual(Object.keys(row), ["id", "constructor"]);
+});
+
+test("rename appends an underscore to unsafe header names", (t) => {
+  const parser = new CsvParser({ unsafeHeaders: "rename" });
+  const [row] = parser.push(Buffer.from(input));
+
+  t.deepEqual(parser.getHeaders(), ["id", "__proto___", "constructor_"]);
+  t.deepEqual(row, { id: "1", __proto___: "{}", constructor_: "x" });
+  t.is(Object.getPrototypeOf(row), Object.prototype);
+});
+
+test("drop leaves columns with unsafe headers out", (t) => {
+  t.deepEqual(parse({ unsafeHeaders: "drop" }), [{ id: "1" }]);
+});
+
+test("drop and rename check each step of an expandHeaders path", (t) => {
+  const input = "a.b,a.__proto__.polluted,c[constructor]\n1,yes,x\n";
+  const parser = new CsvParser({ unsafeHeaders: "rename", expandHeaders: true });
+  t.deepEqual(parser.push(Buffer.from(input)), [
+    { a: { b: "1", __proto___: { polluted: "yes" } }, c: { constructor_: "x" } },
+  ]);
+
+  const dropping = new CsvParser({ unsafeHeaders: "drop", expandHeaders: true });
+  t.deepEqual(dropping.push(Buffer.from(input)), [{ a: { b: "1" } }]);
+});
+
+test("error fails the stream on an unsafe header", async (t) => {
+  const error = await new Promise((resolve) => {
+    Readable.from([input])
+      .pipe(csv({ unsafeHeaders: "error" }))
+      .on("data", () => {})
+      .on("error", resolve)
+      .on("end", () => resolve(null));
+  });
+  t.is(error?.message, 'Unsafe header "__proto__"');
+});
+
+test("an unknown unsafeHeaders policy is refused", (t) => {
+  t.throws(() => new CsvParser({ unsafeHeaders: "ignore" }), {
+    message: 'unsafeHeaders must be "keep", "rename", "drop" or "error", got "ignore"',
+  });
+});
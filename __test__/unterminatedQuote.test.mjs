@@ -0,0 +1,78 @@
+import test from "ava";
+import { Readable } from "stream";
+import csv, { CsvParser } from "../main.js";
+
+const truncated = 'a,b\n1,2\n3,"abc\n4,5';
+
+test("onUnterminatedQuote emits the open record by default", (t) => {
+  const parser = new CsvParser();
+  const rows = [...parser.push(truncated), ...parser.flush()];
+
+  t.deepEqual(rows, [
+    { a: "1", b: "2" },
+    { a: "3", b: '"abc\n4,5' },
+  ]);
+});
+
+test("onUnterminatedQuote: 'drop' leaves the open record out", (t) => {
+  const parser = new CsvParser({ onUnterminatedQuote: "drop" });
+  const rows = [...parser.push(truncated), ...parser.flush()];
+
+  t.deepEqual(rows, [{ a: "1", b: "2" }]);
+});
+
+test("onUnterminatedQuote: 'error' reports the line the quote opened on", (t) => {
+  const parser = new CsvParser({ onUnterminatedQuote: "error" });
+  t.deepEqual(parser.push(truncated), [{ a: "1", b: "2" }]);
+
+  const { rows, errors } = parser.flushWithErrors();
+  t.deepEqual(rows, []);
+  t.is(errors.length, 1);
+  t.is(errors[0].message, "Quoted field opened on line 3 is never closed");
+});
+
+test("onUnterminatedQuote applies when the open quote swallows a last line break", (t) => {
+  const input = truncated + "\n";
+
+  const parser = new CsvParser({ onUnterminatedQuote: "error" });
+  t.deepEqual(parser.push(input), [{ a: "1", b: "2" }]);
+  t.throws(() => parser.flush(), {
+    message: "Quoted field opened on line 3 is never closed",
+  });
+
+  const dropping = new CsvParser({ onUnterminatedQuote: "drop" });
+  t.deepEqual([...dropping.push(input), ...dropping.flush()], [{ a: "1", b: "2" }]);
+
+  const emitting = new CsvParser();
+  t.deepEqual(emitting.push(input), [{ a: "1", b: "2" }]);
+  t.deepEqual(emitting.flush(), [{ a: "3", b: '"abc\n4,5' }]);
+});
+
+test("onUnterminatedQuote: 'error' counts lines across chunks and checkpoints", (t) => {
+  const parser = new CsvParser({ onUnterminatedQuote: "error" });
+  parser.push('a,b\n1,"x\ny"\n');
+  const resumed = CsvParser.fromCheckpoint(parser.getCheckpoint());
+  resumed.push('2,3\n4,"open');
+
+  t.throws(() => resumed.flush(), {
+    message: "Quoted field opened on line 5 is never closed",
+  });
+});
+
+test("onUnterminatedQuote: 'error' fails the stream", async (t) => {
+  const error = await new Promise((resolve) =>
+    Readable.from([truncated])
+      .pipe(csv({ onUnterminatedQuote: "error" }))
+      .on("data", () => {})
+      .on("error", resolve),
+  );
+
+  t.is(error.message, "Quoted field opened on line 3 is never closed");
+});
+
+test("onUnterminatedQuote must be a known policy", (t) => {
+  t.throws(() => new CsvParser({ onUnterminatedQuote: "keep" }), {
+    instanceOf: TypeError,
+    message: 'onUnterminatedQuote must be "error", "emit" or "drop", got "keep"',
+  });
+});
@@ -0,0 +1,49 @@
+import test from "ava";
+import { CsvParser } from "../main.js";
+
+const codes = (report) =>
+  report.issues.map(({ line, column, code }) => [line, column, code]);
+
+test("validate reports header, row and line ending issues", (t) => {
+  const input = Buffer.concat([
+    Buffer.from("id,name,,id\r\n1,a,b,c\r\n2,"),
+    Buffer.from([0xe9]),
+    Buffer.from(",x\n3,b,c,d\r\n"),
+  ]);
+  const report = CsvParser.validate(input);
+
+  t.false(report.valid);
+  t.is(report.records, 3);
+  t.deepEqual(report.lineEndings, { crlf: 3, lf: 1, cr: 0 });
+  t.deepEqual(codes(report), [
+    [1, 9, "empty-header"],
+    [1, 10, "duplicate-header"],
+    [3, 3, "invalid-utf8"],
+    [3, 6, "mixed-line-endings"],
+    [3, 1, "ragged-row"],
+  ]);
+  t.is(report.issues[4].message, "Expected 4 fields, found 3");
+});
+
+test("validate rules can be turned off and capped", (t) => {
+  const input = Buffer.from("id,id\n1\n2\n");
+  t.deepEqual(codes(CsvParser.validate(input, { duplicateHeaders: false })), [
+    [2, 1, "ragged-row"],
+    [3, 1, "ragged-row"],
+  ]);
+
+  const report = CsvParser.validate(input, { maxIssues: 1 });
+  t.is(report.issues.length, 1);
+  t.true(report.truncated);
+});
+
+test("validate spots a separator that does not fit", (t) => {
+  const input = Buffer.from("a;b;c\n1;2;3\n");
+  t.deepEqual(codes(CsvParser.validate(input)), [
+    [1, 1, "suspicious-delimiter"],
+  ]);
+  t.true(CsvParser.validate(input, { parser: { separator: ";" } }).valid);
+  t.throws(() => CsvParser.validate("does-not-exist.csv"), {
+    message: /Cannot open/,
+  });
+});
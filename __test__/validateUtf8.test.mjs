@@ -0,0 +1,39 @@
+import test from "ava";
+import { CsvParser } from "../index.js";
+
+const euro = Buffer.from("name,price\nwidget,€5\n");
+
+for (const validateUtf8 of ["full", "fast", "off"]) {
+  test(`validateUtf8 ${validateUtf8} decodes split characters`, (t) => {
+    const parser = new CsvParser({ validateUtf8 });
+    // Split the three-byte euro sign across chunks
+    const split = euro.indexOf(0xe2) + 1;
+    const rows = [
+      ...parser.push(euro.subarray(0, split)),
+      ...parser.push(euro.subarray(split)),
+      ...parser.flush(),
+    ];
+
+    t.deepEqual(rows, [{ name: "widget", price: "€5" }]);
+  });
+}
+
+test("validateUtf8 fast rejects invalid input", (t) => {
+  const parser = new CsvParser({ validateUtf8: "fast" });
+  const input = Buffer.concat([Buffer.from("a\n"), Buffer.from([0xff]), Buffer.from("\n")]);
+
+  t.throws(() => parser.push(input), { message: /UTF-8 conversion error/ });
+});
+
+test("validateUtf8 off replaces invalid sequences", (t) => {
+  const parser = new CsvParser({ validateUtf8: "off" });
+  const input = Buffer.concat([Buffer.from("a\n"), Buffer.from([0xff]), Buffer.from("\n")]);
+
+  t.deepEqual(parser.push(input), [{ a: "�" }]);
+});
+
+test("validateUtf8 rejects unknown modes", (t) => {
+  t.throws(() => new CsvParser({ validateUtf8: "sometimes" }), {
+    message: /validateUtf8 must be/,
+  });
+});
@@ -0,0 +1,36 @@
+import test from "ava";
+import { CsvParser } from "../main.js";
+
+const input = "email,zip\na@b.c,12345\nnope,1234\nc@d.e\n";
+const validators = { email: "^[^@]+@[^@]+$", zip: "^\\d{5}$" };
+
+test("validators fail on the first invalid cell by default", (t) => {
+  t.throws(() => new CsvParser({ validators }).push(input), {
+    message: "Line 3: email does not match validators.email (^[^@]+@[^@]+$)",
+  });
+});
+
+test("validators skip invalid rows", (t) => {
+  const parser = new CsvParser({ validators, onViolation: "skip" });
+  t.deepEqual(parser.push(input), [{ email: "a@b.c", zip: "12345" }]);
+  t.is(parser.invalidRows, 2);
+});
+
+test("validators annotate rows with the failing columns", (t) => {
+  const rows = new CsvParser({ validators, onViolation: "annotate" }).push(input);
+  t.deepEqual(
+    rows.map((row) => row.__violations),
+    [[], ["email", "zip"], ["zip"]],
+  );
+});
+
+test("validators check their options", (t) => {
+  t.throws(() => new CsvParser({ validators: { zip: "(" } }), {
+    instanceOf: TypeError,
+    message: /^Invalid validators.zip pattern: /,
+  });
+  t.throws(() => new CsvParser({ validators, onViolation: "drop" }), {
+    instanceOf: TypeError,
+    message: 'onViolation must be "error", "skip" or "annotate", got "drop"',
+  });
+});
@@ -0,0 +1,54 @@
+import test from "ava";
+import csv from "../main.js";
+
+function source(chunks) {
+  return new ReadableStream({
+    start(controller) {
+      for (const chunk of chunks) {
+        controller.enqueue(chunk);
+      }
+      controller.close();
+    },
+  });
+}
+
+async function readAll(stream) {
+  const rows = [];
+  for await (const row of stream) {
+    rows.push(row);
+  }
+  return rows;
+}
+
+test("webStream parses byte and string chunks", async (t) => {
+  const bytes = new TextEncoder().encode("a,b\n1,");
+  const rows = await readAll(
+    source([bytes, "2\n3,4"]).pipeThrough(csv.webStream()),
+  );
+
+  t.deepEqual(rows, [
+    { a: "1", b: "2" },
+    { a: "3", b: "4" },
+  ]);
+});
+
+test("webStream applies stream options", async (t) => {
+  const rows = await readAll(
+    source(["a;b\n1;2\n"]).pipeThrough(
+      csv.webStream({
+        separator: ";",
+        mapValues: ({ value }) => Number(value),
+      }),
+    ),
+  );
+
+  t.deepEqual(rows, [{ a: 1, b: 2 }]);
+});
+
+test("webStream errors the readable side", async (t) => {
+  const stream = source(["a,b\n1,2,3\n"]).pipeThrough(
+    csv.webStream({ strict: true }),
+  );
+
+  await t.throwsAsync(readAll(stream), { instanceOf: RangeError });
+});
@@ -0,0 +1,76 @@
+import test from "ava";
+import { Readable } from "stream";
+import { CsvWriter } from "../main.js";
+
+test("CsvWriter quote styles", (t) => {
+  const rows = [["a", "1", "", 'say "hi"']];
+
+  t.is(new CsvWriter({ quoteStyle: "non-numeric" }).write(rows), '"a",1,"","say ""hi"""\n');
+  t.is(new CsvWriter({ quoteStyle: "all", escape: "\\" }).write(rows), '"a","1","","say \\"hi\\""\n');
+  t.is(new CsvWriter({ quoteStyle: "never", escape: "\\" }).write(rows), 'a,1,,say \\"hi\\"\n');
+  t.throws(() => new CsvWriter({ quoteStyle: "never" }).write([["a,b"]]), {
+    message: 'Cannot write "a,b" without quotes; set an escape character',
+  });
+  t.throws(() => new CsvWriter({ quoteStyle: "some" }), { message: /^quoteStyle must be/ });
+});
+
+test("CsvWriter quoteIf quotes chosen cells", (t) => {
+  const writer = new CsvWriter({
+    quoteStyle: "never",
+    quoteIf: (value, column) => column === "zip" || value === "",
+  });
+
+  t.is(writer.write([{ city: "Boston", zip: "02134", note: "" }]), 'city,zip,note\nBoston,"02134",""\n');
+  t.is(new CsvWriter().write([["x", "y"]], (value, column) => column === 1), 'x,"y"\n');
+});
+
+test("CsvWriter writes CRLF with a BOM", (t) => {
+  const writer = new CsvWriter({ newline: "\r\n", bom: true });
+
+  t.is(writer.write([["a", "b"]]), "\uFEFFa,b\r\n");
+  t.is(writer.write([["c", "d"]]), "c,d\r\n");
+});
+
+test("CsvWriter writeBuffer encodes the output", (t) => {
+  const utf16 = new CsvWriter({ newline: "\r\n", bom: true, encoding: "utf-16le" });
+  t.deepEqual(utf16.writeBuffer([{ name: "Zoë" }]), Buffer.from("\uFEFFname\r\nZoë\r\n", "utf16le"));
+  t.deepEqual(utf16.writeBuffer([{ name: "Al" }]), Buffer.from("Al\r\n", "utf16le"));
+
+  const latin1 = new CsvWriter({ encoding: "latin1" });
+  t.deepEqual(latin1.writeBuffer([["Zoë"]]), Buffer.from([0x5a, 0x6f, 0xeb, 0x0a]));
+  t.deepEqual(new CsvWriter({ bom: true }).writeBuffer([["a"]]), Buffer.from("\uFEFFa\n"));
+
+  t.throws(() => new CsvWriter({ encoding: "klingon" }), { message: "Unknown encoding klingon" });
+});
+
+test("CsvWriter.fromIterable streams rows from iterables and Readables", async (t) => {
+  const collect = async (stream) => Buffer.concat(await stream.toArray()).toString();
+
+  async function* generate() {
+    for (let i = 1; i <= 3; i++) {
+      yield { id: i, name: `row ${i}` };
+    }
+  }
+  t.is(
+    await collect(CsvWriter.fromIterable(generate(), { batchSize: 2 })),
+    "id,name\n1,row 1\n2,row 2\n3,row 3\n",
+  );
+  t.is(await collect(CsvWriter.fromIterable([["a", "b"]], { separator: ";" })), "a;b\n");
+  t.is(await collect(CsvWriter.fromIterable(Readable.from([{ a: 1 }]))), "a\n1\n");
+
+  // Rows are pulled only as the output is read
+  let pulled = 0;
+  function* many() {
+    for (let i = 0; i < 100000; i++) {
+      pulled++;
+      yield [i];
+    }
+  }
+  const output = CsvWriter.fromIterable(many(), { batchSize: 10 });
+  await new Promise((resolve) => output.once("readable", resolve));
+  t.true(pulled < 100000);
+  output.destroy();
+
+  const failed = CsvWriter.fromIterable([], { quoteStyle: "sometimes" });
+  await t.throwsAsync(collect(failed), { message: /quoteStyle must be/ });
+});
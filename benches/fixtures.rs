@@ -0,0 +1,20 @@
+//! Parse throughput over inputs shaped like common real files. Run with
+//! `cargo bench --no-default-features --features bench --bench fixtures`;
+//! the napi symbols of the default features only exist inside Node.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use fast_csv_parser::{bench_fixtures, parse_fixture, CsvParserOptions};
+
+fn fixtures(c: &mut Criterion) {
+  let mut group = c.benchmark_group("fixtures");
+  for (name, data) in bench_fixtures(10_000, 10) {
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.bench_function(name, |b| {
+      b.iter(|| parse_fixture(black_box(&data), CsvParserOptions::default()))
+    });
+  }
+  group.finish();
+}
+
+criterion_group!(benches, fixtures);
+criterion_main!(benches);
@@ -1,5 +1,7 @@
+#[cfg(feature = "node-bindings")]
 extern crate napi_build;
 
 fn main() {
+  #[cfg(feature = "node-bindings")]
   napi_build::setup();
 }
@@ -0,0 +1,68 @@
+#![no_main]
+
+//! Parses arbitrary input under options picked from its first bytes, in two
+//! chunks split at a position also picked from them. A hardened parser must
+//! return rows or errors, never panic.
+
+use fast_csv_parser::{
+  CsvParserOptions, ExtraColumns, MaxColumnsAction, Parser, RecordFormat, SkipComments,
+  SkipEmptyLines, Strict, Utf8Validation,
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+  let [separator, quote, escape, newline, flags, more, split, rest @ ..] = data else {
+    return;
+  };
+  let flag = |byte: u8, bit: u8| byte & (1 << bit) != 0;
+  let options = CsvParserOptions {
+    separator: *separator,
+    quote: *quote,
+    escape: *escape,
+    newline: if flag(*flags, 0) { *newline } else { b'\n' },
+    raw: flag(*flags, 1),
+    strict: match flag(*flags, 2) {
+      true => Strict::Report,
+      false => Strict::Off,
+    },
+    relax_quotes: flag(*flags, 3),
+    ltrim: flag(*flags, 4),
+    rtrim: flag(*flags, 5),
+    excel: flag(*flags, 6),
+    sep_directive: flag(*flags, 7),
+    skip_comments: flag(*more, 0).then_some(SkipComments::Boolean(true)),
+    trailing_comments: flag(*more, 1),
+    detect_separator: flag(*more, 2),
+    detect_encoding: flag(*more, 3),
+    headers: flag(*more, 4).then(Vec::new),
+    skip_empty_lines: match flag(*more, 5) {
+      true => SkipEmptyLines::Blank,
+      false => SkipEmptyLines::Off,
+    },
+    validate_utf8: match flag(*more, 6) {
+      true => Utf8Validation::Fast,
+      false => Utf8Validation::Full,
+    },
+    format: match flag(*more, 7) {
+      true => RecordFormat::PostgresText,
+      false => RecordFormat::Csv,
+    },
+    extra_columns: ExtraColumns::Collect("_extra".to_string()),
+    max_columns: Some(64),
+    max_columns_action: MaxColumnsAction::Truncate,
+    null_value: Some(String::new()),
+    hardened: true,
+    ..Default::default()
+  };
+
+  let split = (*split as usize).min(rest.len());
+  let mut parser = Parser::new(options);
+  for chunk in [&rest[..split], &rest[split..]] {
+    if let Ok(records) = parser.feed(chunk) {
+      records.for_each(drop);
+    }
+  }
+  if let Ok(records) = parser.finish() {
+    records.for_each(drop);
+  }
+});
@@ -6,25 +6,1345 @@
 export interface JsCsvParserOptions {
   escape?: string
   quote?: string
+  /**
+   * Column separator, `,` by default. `"auto"` chooses among `,`, `;`, tab
+   * and `|` from the first lines of each input. It also turns on
+   * `sepDirective`, so an Excel `sep=` line decides instead.
+   */
   separator?: string
   newline?: string
   raw?: boolean
-  strict?: boolean
+  /**
+   * Check each row has as many cells as there are headers: `true` fails on
+   * the first row that does not, `"report"` leaves such rows out and lists
+   * them in `getRaggedRows()`
+   */
+  strict?: boolean | 'report'
   maxRowBytes?: number
   headers?: unknown
   skipComments?: unknown
   skipLines?: number
+  skipEmptyLines?: boolean | 'greedy'
+  extraColumns?: 'drop' | 'collect' | ExtraColumnsOptions
+  skipEmptyFields?: boolean
+  /** `"all"` also strips whitespace inside quoted cells */
+  trim?: boolean | 'all'
+  ltrim?: boolean
+  rtrim?: boolean
+  relaxQuotes?: boolean
+  trailingComments?: boolean
+  sepDirective?: boolean
+  excel?: boolean
+  batchSize?: number
+  validateUtf8?: 'full' | 'fast' | 'off'
   mapHeaders?: (...args: any[]) => any
   mapValues?: (...args: any[]) => any
+  dedupe?: boolean | DedupeOptions
+  columnTypes?: Record<string, ColumnTypeOptions>
+  expandHeaders?: boolean
+  /**
+   * Columns whose cells are split into arrays: header names mapped to the
+   * separator between items, or a list of header names that use
+   * `arraySeparator`
+   */
+  arrayColumns?: Record<string, string> | Array<string>
+  /** Separator for `arrayColumns` given as a list (default `;`) */
+  arraySeparator?: string
+  /** Columns whose cells are parsed as JSON, by header name */
+  jsonColumns?: Array<string>
+  /**
+   * `"error"` (default) fails the row on a cell of `jsonColumns` that is
+   * not valid JSON; `"keep"` keeps the cell as a string
+   */
+  malformedJson?: 'error' | 'keep'
+  /**
+   * Add each row's source line, without its line break, under `rawLineKey`.
+   * It is a Buffer with `raw`, otherwise a string.
+   */
+  includeRawLine?: boolean
+  /** Key for `includeRawLine` (default `_raw`) */
+  rawLineKey?: string
+  /**
+   * Most bytes of input held while waiting for the rest of a row, e.g. a
+   * quoted field that never closes. Past it `push()` fails and the partial
+   * row is dropped.
+   */
+  maxBufferedBytes?: number
+  /** Most cells a row, header row included, may have */
+  maxColumns?: number
+  /**
+   * `"error"` (default) fails on a row with more than `maxColumns` cells;
+   * `"truncate"` drops the cells past the limit
+   */
+  maxColumnsAction?: 'error' | 'truncate'
+  /**
+   * Keep only data lines matching a regular expression, tested on the raw
+   * line before it is split into cells
+   */
+  lineFilter?: LineFilterOptions
+  /**
+   * Encoding of the input: a legacy encoding such as `"latin1"` or `"sjis"`,
+   * `"utf-16le"`, `"utf-32le"`, `"utf-32be"` or an EBCDIC code page such as
+   * `"ibm037"`. By default input is UTF-8, or UTF-16 or UTF-32 when it
+   * starts with a BOM. `"auto"` guesses the encoding of input without a
+   * BOM from its first 64 KiB.
+   */
+  encoding?: string
+  /**
+   * How row objects are created: `"plain"` (default) objects,
+   * `"null-prototype"` objects without a prototype, where a header such as
+   * `__proto__` is an ordinary key, `"frozen"` objects, or `"entries"`:
+   * arrays of `[key, value]` pairs in column order
+   */
+  rowObject?: 'plain' | 'null-prototype' | 'frozen' | 'entries'
+  /**
+   * What happens to header row names `__proto__`, `constructor` and
+   * `prototype`, or with `expandHeaders` paths through them: `"keep"`
+   * (default), `"rename"` appends `_`, `"drop"` leaves the column out and
+   * `"error"` fails
+   */
+  unsafeHeaders?: 'keep' | 'rename' | 'drop' | 'error'
+  /**
+   * Defaults for a family of files, which the other options override:
+   * `"tsv"` and `"psv"` separate cells with tabs and pipes, `"excel"` turns
+   * on `excel` and `sepDirective`, and `"postgres"` reads the PostgreSQL
+   * `COPY` text format, with tabs, backslash escapes and `\N` nulls
+   */
+  dialect?: 'csv' | 'tsv' | 'psv' | 'excel' | 'postgres'
+  /** Cells written exactly as this, before any unescaping, are `null` */
+  nullValue?: string
+  /**
+   * Add a hash of each row's source line, without its line break, as a
+   * hex string under `__hash`
+   */
+  rowHash?: 'xxh64' | 'sha256'
+  /** Hash all input as it is pushed, for the `digest` at the end */
+  digest?: 'xxh64' | 'sha256'
+  /**
+   * Bound memory where `maxRowBytes`, `maxBufferedBytes` and `maxColumns`
+   * are not given (16 MiB, 64 MiB and 16384), and turn an internal panic
+   * into an error instead of crashing the process
+   */
+  hardened?: boolean
+  /**
+   * Separator of the fraction in `number` columns of `columnTypes` (default
+   * `.`)
+   */
+  decimalSeparator?: string
+  /**
+   * Separator between groups of three digits in `number` columns of
+   * `columnTypes`, such as `.` in `1.234,56`; none by default
+   */
+  thousandsSeparator?: string
+  /**
+   * What happens to the last record when the input ends inside a quoted
+   * field: `"emit"` (default) keeps it, the open field holding the rest of
+   * the input as it is; `"error"` fails it with the line the field opened
+   * on; `"drop"` leaves it out
+   */
+  onUnterminatedQuote?: 'error' | 'emit' | 'drop'
+  /**
+   * Split the input into tables, each with its own header line: `true` at
+   * blank lines, or at the lines given as `SectionOptions`
+   */
+  sections?: boolean | SectionOptions
+  /**
+   * Most bytes the value of a cell, header included, may take up, an
+   * escaped quote counting once
+   */
+  maxFieldBytes?: number
+  /**
+   * `"error"` (default) fails on a row with a cell over `maxFieldBytes`;
+   * `"truncate"` cuts the cell to the limit
+   */
+  onOversizeField?: 'error' | 'truncate'
+  /**
+   * Line breaks inside quoted cells: `"keep"` (default) leaves them, `"lf"`
+   * turns `\r\n` and `\r` into `\n`, `"escape"` writes each as the two
+   * characters `\n` and `"strip"` removes them
+   */
+  quotedNewlines?: 'keep' | 'lf' | 'escape' | 'strip'
+  /**
+   * Header names mapped to other names of the same column, such as
+   * `{ email: ["E-Mail", "email_address"] }`: a header matching a key or
+   * one of its names, ignoring case, is read as the key
+   */
+  columnAliases?: Record<string, Array<string>>
+  /**
+   * Regular expressions the cells of columns must match, by header name.
+   * Missing cells are checked as empty.
+   */
+  validators?: Record<string, string>
+  /**
+   * `"error"` (default) fails on a row with a cell failing its validator;
+   * `"skip"` leaves the row out; `"annotate"` keeps it and lists the failing
+   * columns under `__violations`
+   */
+  onViolation?: 'error' | 'skip' | 'annotate'
+  /**
+   * JSON Schema (a draft 2020-12 subset) the rows must match. Cells of its
+   * properties are converted to the types they allow.
+   */
+  schema?: object
+  /**
+   * Columns hidden before rows reach JS, by header name: `"hash"` replaces
+   * cells with the SHA-256 of `redactSalt` and the cell, `"mask"` with `*`
+   * but for the last four characters, and `"drop"` leaves the column out
+   */
+  redact?: Record<string, 'hash' | 'mask' | 'drop'>
+  /** Prefix hashed with each cell by `redact: "hash"` (default empty) */
+  redactSalt?: string
+  /**
+   * Also return the detected header row as the first row, keyed by itself,
+   * e.g. `{ id: "id", name: "name" }` (default false)
+   */
+  passHeaderRow?: boolean
+  /**
+   * `"newline"` (default) ends records at `newline`; `"any"` also at `\n`,
+   * `\r\n` and a lone `\r`, for files that mix them
+   */
+  recordTerminator?: 'newline' | 'any'
+  /**
+   * Name of a native hook registered with `register_batch_hook` by the
+   * addon embedding this parser, run on each batch of records before they
+   * become objects. Rows then hold strings only.
+   */
+  batchHook?: string
+  /**
+   * Columns whose detected header is empty or `_`: `"drop"` (default)
+   * leaves them out of rows, as `getDroppedColumns` lists, `"keep-positional"`
+   * keys them `_<index>` and `"error"` fails the header row
+   */
+  emptyHeaders?: 'drop' | 'keep-positional' | 'error'
+  /**
+   * Also give each row its record's bytes, without the line break, as a
+   * Buffer under `__raw`. The Buffers of the rows of one call are views of
+   * one Buffer, so none is copied on its own (default false)
+   */
+  rawRows?: boolean
+}
+/** How the cells of one column are converted */
+export interface ColumnTypeOptions {
+  type: 'date' | 'number'
+  /**
+   * For dates, chrono `strftime` format, e.g. `%d/%m/%Y %H:%M`; ISO 8601
+   * when omitted
+   */
+  format?: string
+  /**
+   * For dates, zone of values without an offset: `UTC` (default), an offset
+   * such as `+02:00` or an IANA name such as `Europe/Oslo`
+   */
+  tz?: string
+  /**
+   * For dates, `"date"` (default) for `Date` objects or `"millis"` for epoch
+   * milliseconds
+   */
+  output?: 'date' | 'millis'
+  /**
+   * For numbers, strip currency symbols, read `(12.50)` as `-12.5` and
+   * accept thousands separators: `thousandsSeparator`, or else `,` (`.`
+   * when `decimalSeparator` is `,`)
+   */
+  currency?: boolean
+  /** For numbers, read `12.5%` as `0.125` */
+  percent?: boolean
+}
+export interface SectionOptions {
+  /** A blank line ends the table before it (default `false`) */
+  blankLines?: boolean
+  /**
+   * A line starting with this prefix starts a table named by the rest of
+   * the line, e.g. `"## "` for `## Orders`
+   */
+  marker?: string
+}
+export interface ExtraColumnsOptions {
+  /** Key cells beyond the headers `<prefix><index>` (default `_`) */
+  prefix?: string
+  /** Gather cells beyond the headers into one array under this key instead */
+  collect?: string
+}
+export interface LineFilterOptions {
+  /** Regular expression in Rust `regex` syntax, e.g. `ERROR|WARN` */
+  pattern: string
+  /** Keep the lines that do not match instead */
+  negate?: boolean
+}
+export interface DedupeOptions {
+  /** Columns that identify a row; every cell when omitted */
+  keys?: Array<string>
+  /**
+   * Remember keys in a Bloom filter instead of an exact set, so memory
+   * stays bounded. A unique row is dropped now and then, at about
+   * `falsePositiveRate`.
+   */
+  bloom?: BloomOptions
+}
+export interface BloomOptions {
+  /** Rows the filter is sized for */
+  capacity: number
+  /**
+   * Chance of taking a new key for a seen one while under `capacity`
+   * (default 0.001)
+   */
+  falsePositiveRate?: number
+}
+/** Bytes currently held by a parser, by where they are held */
+export interface MemoryUsage {
+  /** Raw input not yet decoded (e.g. a split UTF-16 code unit) */
+  inputBuffer: number
+  /** Decoded input waiting for the rest of its row */
+  decodeBuffer: number
+  /**
+   * Header names, keys remembered by `dedupe` and other strings kept for
+   * the lifetime of the parser
+   */
+  strings: number
+  /** Scratch arena for per-row temporary data */
+  arena: number
+  total: number
 }
 export interface ParsedRow {
   values: Array<string>
 }
+/** Checks run by `CsvParser.validate()`; all are on unless set to `false` */
+export interface LintRules {
+  /** Records with a different number of fields than the header */
+  raggedRows?: boolean
+  duplicateHeaders?: boolean
+  /** Header names that are empty or only whitespace */
+  emptyHeaders?: boolean
+  /** Lines ending differently from the first line */
+  mixedLineEndings?: boolean
+  /** Invalid UTF-8, reported once per line */
+  encoding?: boolean
+  /** Every record has one field, but another separator splits them evenly */
+  suspiciousDelimiter?: boolean
+  /** Issues reported before the rest are left out (default 1000) */
+  maxIssues?: number
+  /** `separator`, `quote`, `escape` and `headers`, as for `CsvParser` */
+  parser?: JsCsvParserOptions
+}
+export interface LintIssue {
+  line: number
+  /** In characters, from 1 */
+  column: number
+  /**
+   * `ragged-row`, `duplicate-header`, `empty-header`,
+   * `mixed-line-endings`, `invalid-utf8` or `suspicious-delimiter`
+   */
+  code: string
+  message: string
+}
+/** Lines ending with each kind of line break, outside quoted fields */
+export interface LineEndingCounts {
+  crlf: number
+  lf: number
+  cr: number
+}
+export interface LintReport {
+  valid: boolean
+  /** Data records, not counting the header record or blank lines */
+  records: number
+  issues: Array<LintIssue>
+  /** Whether issues were left out past `maxIssues` */
+  truncated: boolean
+  lineEndings: LineEndingCounts
+}
+export interface JsCsvWriterOptions {
+  separator?: string
+  quote?: string
+  newline?: string
+  headers?: Array<string>
+  /**
+   * Which cells are quoted: only those that need it (`'necessary'`, the
+   * default), all, all but numbers, or none. `'minimal'` and `'all'` are
+   * aliases of `'necessary'` and `'always'`.
+   */
+  quoteStyle?: 'necessary' | 'minimal' | 'always' | 'all' | 'non-numeric' | 'never'
+  formulaGuard?: boolean
+  /**
+   * Cell for a column an object row has no value for (a missing key or
+   * `undefined`); empty by default. `null` is written as `nullValue`.
+   */
+  missingValue?: string
+  /**
+   * Written before quotes inside quoted cells instead of doubling them,
+   * and before separators, quotes and line breaks with `quoteStyle:
+   * 'never'`, which otherwise fails on such cells
+   */
+  escape?: string
+  /**
+   * Encoding of the Buffers from `writeBuffer()` (e.g. `"utf-16le"`);
+   * UTF-8 by default
+   */
+  encoding?: string
+  /**
+   * Start the first output with a byte order mark, as Excel needs to
+   * detect UTF-8. Only written for UTF-8 and UTF-16.
+   */
+  bom?: boolean
+  /**
+   * Defaults for a family of files, which the other options override:
+   * `"tsv"` and `"psv"` separate cells with tabs and pipes, `"excel"` writes
+   * CRLF line endings and a BOM, and `"postgres"` writes the PostgreSQL
+   * `COPY` text format, with tabs, backslash escapes and `\N` nulls
+   */
+  dialect?: 'csv' | 'tsv' | 'psv' | 'excel' | 'postgres'
+  /** Written as it is for `null` cells; empty by default */
+  nullValue?: string
+  /** Write SQL `INSERT` statements instead of CSV */
+  sql?: SqlInsertOptions
+  /**
+   * Compress the Buffers from `writeBuffer()`; `finish()` returns the end
+   * of the compressed stream
+   */
+  compression?: CompressionOptions
+}
+export interface CompressionOptions {
+  type: 'gzip' | 'zstd'
+  /** 0 to 9 for gzip (default 6), 1 to 22 for zstd (default 3) */
+  level?: number
+}
+export interface SqlInsertOptions {
+  /**
+   * Table rows are inserted into; each `.`-separated part is quoted, so
+   * `"sales.orders"` names a table in a schema
+   */
+  table: string
+  /** Rows per statement (default 100) */
+  batchSize?: number
+  /**
+   * Quote around table and column names: `"` by default, `` ` `` for
+   * MySQL
+   */
+  identifierQuote?: string
+}
+export interface BenchmarkOptions {
+  /** Data rows per generated data set (default 10000) */
+  rows?: number
+  /** Cells per row (default 8) */
+  columns?: number
+  /** Timed parses of each data set (default 5) */
+  iterations?: number
+}
+export interface BenchmarkResult {
+  name: string
+  /**
+   * `micro` for a single cell shape, `macro` for a parser configuration,
+   * `fixture` for input shaped like a common kind of file
+   */
+  kind: string
+  bytes: number
+  rows: number
+  iterations: number
+  elapsedMs: number
+  bytesPerSecond: number
+  rowsPerSecond: number
+}
+/**
+ * Runs the built-in benchmarks on synthetic data and reports the throughput
+ * of each cell shape and parser configuration on this machine.
+ */
+export declare function benchmark(options?: BenchmarkOptions | undefined | null): Array<BenchmarkResult>
+export interface InferSchemaOptions {
+  /** Rows to sample (default 1000) */
+  sampleRows?: number
+  /** Options for parsing the sample, as for `CsvParser` */
+  parser?: JsCsvParserOptions
+}
+export interface ColumnSchema {
+  name: string
+  type: 'empty' | 'boolean' | 'integer' | 'float' | 'date' | 'string'
+  /** Some sampled row had no value for the column */
+  nullable: boolean
+  /** Shortest and longest non-empty value, in characters */
+  minLength: number
+  maxLength: number
+  /** Up to three distinct non-empty values, in order of appearance */
+  examples: Array<string>
+  /** Distinct non-empty values in the sample */
+  distinct: number
+}
+/**
+ * Samples the first rows of a CSV buffer, or of the file at a path, and
+ * describes each column: inferred type, nullability, value lengths,
+ * examples and distinct count.
+ */
+export declare function inferSchema(input: Buffer | string, options?: InferSchemaOptions | undefined | null): Array<ColumnSchema>
+/**
+ * Counts the records in a CSV buffer, or in the file at a path, without
+ * creating cell strings or row objects. Header, comment and skipped lines
+ * are not counted; quoted newlines do not end a record.
+ */
+export declare function countRows(input: Buffer | string, options?: JsCsvParserOptions | undefined | null): number
+export interface ToJsonOptions {
+  /**
+   * `"array"` (default) writes one JSON array, a row per line; `"ndjson"`
+   * writes one row object per line
+   */
+  format?: 'array' | 'ndjson'
+  /** Options for parsing the input, as for `CsvParser` */
+  parser?: JsCsvParserOptions
+}
+/**
+ * Converts a CSV buffer, or the file at a path, to a JSON file at
+ * `outputPath` without creating row objects in JS. Rows are written as
+ * `JSON.stringify` writes the rows of `csv()` with the same options; dates
+ * are ISO strings and raw cells are text. Returns the number of rows.
+ */
+export declare function toJson(input: Buffer | string, outputPath: string, options?: ToJsonOptions | undefined | null): number
+export interface ToSqliteOptions {
+  /** Name of the table rows are inserted into */
+  table: string
+  /**
+   * Create the table, with a column per header, unless it exists
+   * (default true)
+   */
+  createTable?: boolean
+  /** SQLite type of each column by header, `"text"` by default */
+  types?: Record<string, 'text' | 'integer' | 'real'>
+  /** Rows inserted per transaction (default 10000) */
+  batchSize?: number
+  /** Options for parsing the input, as for `CsvParser` */
+  parser?: JsCsvParserOptions
+}
+/**
+ * Inserts the rows of a CSV buffer, or of the file at a path, into a table
+ * of the SQLite database at `dbPath`, without creating row objects in JS.
+ * Rows are inserted in batches of one transaction each. Returns the number
+ * of rows inserted. Only in builds with the `sqlite` feature.
+ */
+export declare function toSqlite(input: Buffer | string, dbPath: string, options: ToSqliteOptions): number
+export interface RecordBoundaryOptions {
+  /**
+   * Only return starts at least this many bytes after the previous one,
+   * to cut the input into chunks of about that size
+   */
+  chunkBytes?: number
+  /** Options for parsing the input, as for `CsvParser` */
+  parser?: JsCsvParserOptions
+}
+/**
+ * Byte offsets where records start in a CSV buffer, or in the file at a
+ * path: points the input can be split at, e.g. to parse chunks of a large
+ * file in several workers. Quoted newlines do not end a record; the
+ * header, comment and skipped lines each count. Offsets are of the raw
+ * bytes, which must be in an ASCII-compatible encoding such as UTF-8.
+ */
+export declare function findRecordBoundaries(input: Buffer | string, options?: RecordBoundaryOptions | undefined | null): Array<number>
+export interface DistinctOptions {
+  /**
+   * Return `{ value, count }` objects with the number of rows holding
+   * each value, instead of the values alone
+   */
+  counts?: boolean
+  /** Options for parsing the input, as for `CsvParser` */
+  parser?: JsCsvParserOptions
+}
+/**
+ * The distinct values of a column in a CSV buffer, or in the file at a
+ * path, in order of first appearance. With several columns, each value is
+ * an array of one cell per column. Only cells of those columns are
+ * decoded and only new values are kept, so memory grows with the number
+ * of distinct values rather than rows. Missing and null cells count as
+ * empty.
+ */
+export declare function distinct(input: Buffer | string, columns: string | Array<string>, options?: DistinctOptions | undefined | null): Array<string> | Array<Array<string>> | Array<{ value: string | Array<string>, count: number }>
+/** How the input of `head()` and `readHeaders()` is written */
+export interface CsvDialect {
+  separator: string
+  quote: string
+  /** How the first line ends, or null if the input read has no line break */
+  newline?: '\n' | '\r\n' | '\r' | null
+  /**
+   * Lowercase encoding name, e.g. `"utf-8"`, `"utf-16le"`, `"utf-32be"` or
+   * the `encoding` option's, such as `"ibm037"`
+   */
+  encoding: string
+  /** The input starts with a byte order mark */
+  bom: boolean
+}
+export interface HeadResult {
+  rows: Array<object>
+  /** Header names, or null if the input ended before they were known */
+  headers?: Array<string>
+  dialect: CsvDialect
+}
+/**
+ * The first `n` rows of a CSV buffer, or of the file at a path, with its
+ * headers and dialect. Without a `separator` option the separator is
+ * guessed from the first lines. Files are read only as far as needed.
+ */
+export declare function head<T extends object = Record<string, string>>(input: Buffer | string, n: number, options?: JsCsvParserOptions | undefined | null): HeadResult & { rows: Array<T> }
+export interface HeadersResult {
+  /** Header names, or null if the input ended before they were known */
+  headers?: Array<string>
+  dialect: CsvDialect
+}
+/**
+ * The headers and dialect of a CSV buffer, or of the file at a path, as
+ * `head()` finds them, without any rows: reading stops once the header
+ * line is parsed, and no row object is created. With `headers: false` the
+ * first row is split for its width.
+ */
+export declare function readHeaders(input: Buffer | string, options?: JsCsvParserOptions | undefined | null): HeadersResult
+export interface SampleOptions {
+  /** Picks the same rows of the same input on every call */
+  seed?: number
+  /** Options for parsing the input, as for `CsvParser` */
+  parser?: JsCsvParserOptions
+}
+/**
+ * `k` rows picked uniformly at random from a CSV buffer, or from the file
+ * at a path, in one pass and in input order. Only the picked rows become
+ * JS objects; with fewer than `k` rows, all of them are returned.
+ */
+export declare function sample<T extends object = Record<string, string>>(input: Buffer | string, k: number, options?: SampleOptions | undefined | null): Array<T>
+export interface CsvIndexOptions {
+  /** Header of the column whose values are the keys */
+  column: string
+  /** Options for parsing the file, as for `CsvParser` */
+  parser?: JsCsvParserOptions
+}
+/** Where a row is in the indexed file, including its newline */
+export interface RowLocation {
+  offset: number
+  length: number
+}
+export interface CsvStatsOptions {
+  /** Most frequent values reported per column (default 5) */
+  topK?: number
+  /** Options for parsing the input, as for `CsvParser` */
+  parser?: JsCsvParserOptions
+}
+export interface ValueCount {
+  value: string
+  count: number
+}
+export interface ColumnStats {
+  name: string
+  /** Rows with a non-empty value */
+  count: number
+  /** Rows where the value was empty or missing */
+  nullCount: number
+  /** Non-empty values that parse as numbers */
+  numericCount: number
+  /** Over the numeric values only */
+  min?: number
+  max?: number
+  mean?: number
+  /** Sample standard deviation, with at least two numeric values */
+  stddev?: number
+  /**
+   * Most frequent values, most frequent first. Counts are exact while the
+   * column has fewer than ten times `topK` distinct values, and lower
+   * bounds after that.
+   */
+  top: Array<ValueCount>
+}
+export interface CsvValidatorOptions {
+  /** Violations reported before the rest are left out (default 1000) */
+  maxViolations?: number
+}
+export interface Violation {
+  line: number
+  /** In characters, from 1 */
+  column: number
+  /**
+   * `unclosed-quote`, `stray-quote`, `text-after-quote`, `bare-lf`,
+   * `bare-cr` or `field-count`
+   */
+  code: string
+  message: string
+}
+export interface ValidationReport {
+  valid: boolean
+  /** Records in the input, including the header record */
+  records: number
+  violations: Array<Violation>
+  /** Whether violations were left out past `maxViolations` */
+  truncated: boolean
+}
+export interface CsvJoinOptions {
+  /** Header of the key column in the streamed (left) input */
+  key: string
+  /** Header of the key column in the right input, when it differs from `key` */
+  rightKey?: string
+  /** `"inner"` (default) or `"left"` */
+  type?: 'inner' | 'left'
+  /** Options for parsing the streamed input, as for `CsvParser` */
+  left?: JsCsvParserOptions
+  /** Options for parsing the right input, as for `CsvParser` */
+  right?: JsCsvParserOptions
+}
+export interface CsvReshapeOptions {
+  /**
+   * `"long"` (one row per id and wide column, the default) or `"wide"`
+   * (one row per run of rows sharing their ids)
+   */
+  to?: 'long' | 'wide'
+  /** Headers of the columns that identify a row, copied as they are */
+  ids: Array<string>
+  /** Header of the long column holding wide column names; `"key"` by default */
+  key?: string
+  /** Header of the long column holding the values; `"value"` by default */
+  value?: string
+  /**
+   * To long, the columns to unpivot (every column but the ids by default);
+   * to wide, the columns of each row, in order (the keys of its rows by
+   * default)
+   */
+  columns?: Array<string>
+  /** Options for parsing the input, as for `CsvParser` */
+  parser?: JsCsvParserOptions
+}
+export interface SortByOptions {
+  /** Header of the column to sort by */
+  column: string
+  /** Compare values as numbers. Values that are not numbers sort last. */
+  numeric?: boolean
+  /** Largest value first */
+  desc?: boolean
+  /** Write the sorted CSV to this path instead of returning the rows */
+  output?: string
+  /**
+   * Approximate bytes of rows held in memory before a sorted run is
+   * spilled to disk (default 64 MiB)
+   */
+  memoryLimit?: number
+  /** Where run files go; the system temp directory by default */
+  tempDir?: string
+  /** Options for parsing the input, as for `CsvParser` */
+  parser?: JsCsvParserOptions
+}
+/**
+ * Sorts the CSV file at `path` by a column with an external merge sort, so
+ * files larger than memory can be sorted. With `output`, writes the sorted
+ * CSV there and returns the number of rows; otherwise returns the rows in
+ * order, to be read in batches.
+ */
+export declare function sortBy(path: string, options: SortByOptions): number | SortedRows
+export interface DiffOptions {
+  /** Header of the column that identifies a row in both files */
+  key: string
+  /**
+   * Approximate bytes of rows each file holds in memory while it is
+   * sorted by key (default 64 MiB)
+   */
+  memoryLimit?: number
+  /** Where sort runs go; the system temp directory by default */
+  tempDir?: string
+  /** Options for parsing both files, as for `CsvParser` */
+  parser?: JsCsvParserOptions
+}
+/** A row added, removed or changed between the files given to `diff()` */
+export interface DiffChange {
+  type: 'added' | 'removed' | 'changed'
+  key: string
+  /** The row in the old file; missing for added rows */
+  before?: Record<string, string>
+  /** The row in the new file; missing for removed rows */
+  after?: Record<string, string>
+  /** For changed rows, the columns whose values differ */
+  columns: Array<string>
+}
+/**
+ * Compares an old CSV file with a new one, matching rows by the `key`
+ * column. Both files are sorted by key with the external sort of
+ * `sortBy()` first, so memory stays bounded whatever their size, and the
+ * changes come in key order, to be read in batches.
+ */
+export declare function diff(oldPath: string, newPath: string, options: DiffOptions): CsvDiff
+/** How much of the input goes into each shard of `split()`; give one of the two */
+export interface SplitLimit {
+  /** Data rows per shard */
+  rows?: number
+  /**
+   * Bytes per shard, header included, as a number or a size such as
+   * `"256MB"` (units are powers of 1024)
+   */
+  bytes?: number | string
+}
+/** A file written by `split()` */
+export interface ShardInfo {
+  path: string
+  /** Data rows, not counting the header */
+  rows: number
+  bytes: number
+}
+/**
+ * Splits the CSV file at `path` into shards in `outDir` (created if
+ * needed), named after the input with a counter: `data-00001.csv`, ...
+ * Shards end between records, never inside a quoted field, and each starts
+ * with the header line.
+ */
+export declare function split(path: string, limit: SplitLimit, outDir: string, options?: JsCsvParserOptions | undefined | null): Array<ShardInfo>
+export interface AggregateOptions {
+  /**
+   * Column, or columns, whose values form the groups. Without it, all rows
+   * form one group.
+   */
+  groupBy?: string | Array<string>
+  /**
+   * Metric, or metrics, per column. A single metric is reported under the
+   * column name, several under `<column>_<metric>`.
+   */
+  metrics: Record<string, AggregateMetric | Array<AggregateMetric>>
+  /** Options for parsing the input, as for `CsvParser` */
+  parser?: JsCsvParserOptions
+}
+export type AggregateMetric = 'count' | 'sum' | 'min' | 'max' | 'mean'
+export interface CsvRewriterOptions {
+  /** Dialect of the input, as for `CsvParser` */
+  input?: JsCsvParserOptions
+  /**
+   * Encoding of the input (e.g. `"latin1"`). By default input is UTF-8, or
+   * UTF-16 when it starts with a BOM.
+   */
+  inputEncoding?: string
+  /**
+   * Dialect of the output, as for `CsvWriter`. `headers` is not used; see
+   * `columns` and `rename`. `missingValue` fills the cells of columns the
+   * input lacks and of rows shorter than the header. `encoding`, `bom` and
+   * `compression` apply when the options of the same name are not set.
+   */
+  output?: JsCsvWriterOptions
+  /** Encoding of the output (e.g. `"utf-16le"`); UTF-8 by default */
+  outputEncoding?: string
+  /** Start the output with a byte order mark, for UTF-8 and UTF-16 output */
+  bom?: boolean
+  /**
+   * Compress the output, e.g. `{ type: "gzip", level: 6 }`; `flush()`
+   * returns the end of the compressed stream
+   */
+  compression?: CompressionOptions
+  /**
+   * Columns to keep, in output order. All columns by default. A column the
+   * input lacks is an error unless `output.missingValue` is set.
+   */
+  columns?: Array<string>
+  /** New names for columns in the header line */
+  rename?: Record<string, string>
+}
+/** A row that could not be parsed, such as one with too many columns */
+export interface RowError {
+  message: string
+  /** Where in `rows` the row would have been: the number of rows before it */
+  index: number
+}
+export interface ParseThreadOptions {
+  /**
+   * Parsed batches the thread may hold before it waits for `read()`, and
+   * chunks `push()` accepts before it returns false; 4 by default
+   */
+  maxPendingBatches?: number
+  /** Options for parsing the input, as for `CsvParser` */
+  parser?: JsCsvParserOptions
+}
+/** Rows parsed by one call and the errors of those that failed */
+export interface ParseResult {
+  rows: Array<object>
+  /** In input order; rows that failed are not in `rows` */
+  errors: Array<RowError>
+}
+/** A batch of rows packed into one ArrayBuffer */
+export interface PackedRows {
+  /**
+   * Per row, its field count; per field, the index of its key in
+   * `columns`, the byte length of its UTF-8 value and the value. Counts,
+   * indexes and lengths are little-endian u32.
+   */
+  buffer: ArrayBuffer
+  /** The keys of the fields in `buffer` */
+  columns: Array<string>
+  /**
+   * The key that `extraColumns` collects cells beyond the headers under.
+   * Fields with this key belong in one array, in order.
+   */
+  rest?: string
+  rows: number
+}
+/**
+ * The dialect parser options resolve to, with the `dialect` preset and
+ * defaults applied
+ */
+export interface ResolvedOptions {
+  /** `"auto"` when the separator is guessed from the input */
+  separator: string
+  quote: string
+  escape: string
+  /** `"\n"` also reads lines ending in `"\r\n"` */
+  newline: string
+  /** Lowercase encoding name, or `"auto"` when it is detected from the input */
+  encoding: string
+  format: 'csv' | 'postgres'
+  nullValue?: string
+  excel: boolean
+  sepDirective: boolean
+  decimalSeparator: string
+  thousandsSeparator?: string
+}
+/**
+ * Checks parser options the way `new CsvParser()` does and returns the
+ * dialect they resolve to. Invalid options throw the same errors.
+ */
+export declare function resolveOptions(options?: JsCsvParserOptions | undefined | null): ResolvedOptions
+/**
+ * Where to resume a parse: read the source from `offset` and continue with
+ * a parser from `CsvParser.fromCheckpoint()`
+ */
+export interface Checkpoint {
+  /** Source bytes pushed so far */
+  offset: number
+  /** The parser state, as from `serializeState()` */
+  state: Buffer
+}
+/** A row left out by `strict: "report"` */
+export interface RaggedRow {
+  /** The line the row starts on, counting from 1 */
+  line: number
+  /** The number of headers */
+  expected: number
+  actual: number
+}
+/**
+ * A cell that failed its validator or schema property, or a key the schema
+ * requires and the row lacks
+ */
+export interface CellViolation {
+  /** The line the row starts on, counting from 1 */
+  line: number
+  column: string
+  /** The schema keyword that failed, `pattern` for `validators` */
+  keyword: string
+  message: string
+}
+/** Counters of a `CsvParser` since it was created or last reset */
+export interface ParserMetrics {
+  /** Input bytes pushed, before decoding */
+  bytesProcessed: number
+  /** Rows returned, not counting header, comment or skipped lines */
+  rowsEmitted: number
+  /** Lines skipped as comments */
+  commentLines: number
+  /** Rows dropped by `dedupe` */
+  duplicates: number
+  /** Calls that failed with an error */
+  errors: number
+  /**
+   * Milliseconds spent in `push()`, `next()`, `flush()` and their packed
+   * variants
+   */
+  parseTimeMs: number
+  /** `bytesProcessed` and `rowsEmitted` per second of `parseTimeMs` */
+  bytesPerSecond: number
+  rowsPerSecond: number
+}
+/** Where parsing time went since profiling was enabled, in milliseconds */
+export interface ParserProfile {
+  /** Detecting the encoding, converting input to UTF-8 and validating it */
+  decodeMs: number
+  /** Finding row ends and splitting rows into cells */
+  tokenizeMs: number
+  /** Creating row objects and their keys and values */
+  buildMs: number
+}
+/**
+ * Where a `CsvParser` is in its input, for finding out why a stream holds
+ * back a row
+ */
+export interface ParserState {
+  /** The line the next row starts on, from 1 */
+  lineNumber: number
+  /**
+   * Whether the buffered partial row has a quoted field left open, so the
+   * parser waits for its closing quote
+   */
+  inQuote: boolean
+  /** Input held without completing a row, as `maxBufferedBytes` counts it */
+  bufferedBytes: number
+  /** Whether the header row has been read, or there is none to read */
+  headersResolved: boolean
+}
+/**
+ * Parses chunks of CSV into row objects. Methods returning rows take the
+ * row type as `T`, `Record<string, string>` by default; pass another when
+ * options change the values, such as numbers and `Date`s from
+ * `columnTypes`, JSON values from `jsonColumns`, arrays from `arrayColumns`
+ * and `extraColumns: "collect"`, or Buffers with `raw`.
+ */
 export declare class CsvParser {
   constructor(options?: JsCsvParserOptions | undefined | null)
-  push(chunk: Buffer): Array<object>
-  finish(cb: (...args: any[]) => any): Array<object>
+  /**
+   * Creates a parser with the options of `template`, without reading and
+   * checking them again
+   */
+  static fromTemplate(template: ParserTemplate): CsvParser
+  /**
+   * Parses a chunk and returns the rows it completes. Throws the error of
+   * the first row that fails, with the chunk's other rows under `rows` and
+   * all its errors under `errors`; `pushWithErrors()` returns both.
+   */
+  push<T extends object = Record<string, string>>(chunk: string | Buffer | ArrayBufferView | ArrayBuffer): Array<T>
+  /**
+   * Like `push()`, but a row that fails is left out and its error returned
+   * in `errors`, and parsing goes on with the next row
+   */
+  pushWithErrors<T extends object = Record<string, string>>(chunk: string | Buffer | ArrayBufferView | ArrayBuffer): ParseResult & { rows: Array<T> }
+  /**
+   * Like `push()`, but packs the rows into a single ArrayBuffer that can be
+   * transferred to a worker with `postMessage` instead of cloned row by
+   * row. `unpackRows()` turns it back into objects.
+   */
+  pushPacked(chunk: string | Buffer | ArrayBufferView | ArrayBuffer): PackedRows
+  /** Like `flush()`, packing the rows as `pushPacked()` does */
+  flushPacked(): PackedRows
+  /**
+   * Returns the next batch of complete rows already buffered, without
+   * pushing more input. Only useful with `batchSize` or `sections`, where
+   * `push()` may leave rows behind; an empty array means no complete rows
+   * are left.
+   */
+  next<T extends object = Record<string, string>>(): Array<T>
+  /** Like `next()`, returning errors as `pushWithErrors()` does */
+  nextWithErrors<T extends object = Record<string, string>>(): ParseResult & { rows: Array<T> }
+  finish<T extends object = Record<string, string>>(cb: (...args: any[]) => any): Array<T>
   getHeaders(): Array<string> | null
+  /**
+   * With `sections`, the index of the table the rows of the last call
+   * belong to, from 0
+   */
+  get section(): number
+  /**
+   * With `sections`, the name the marker line of the current table gave
+   * it, or null
+   */
+  get sectionName(): string | null
+  /**
+   * With `strict: "report"`, the rows left out so far because their number
+   * of cells differs from the headers, in input order
+   */
+  getRaggedRows(): Array<RaggedRow>
+  /**
+   * Zero-based indexes of the columns left out of rows because their
+   * header is empty or `_`
+   */
+  getDroppedColumns(): Array<number>
+  /**
+   * Clears buffered input, headers and line counters so the instance can
+   * parse another file. New options replace the current ones.
+   */
+  reset(options?: JsCsvParserOptions | undefined | null): void
+  /**
+   * A snapshot of the parse so far: options, headers, buffered input and
+   * the keys `dedupe` remembers. Pass it to another thread (e.g. a worker)
+   * and continue there with `CsvParser.deserializeState()`.
+   */
+  serializeState(): Buffer
+  /**
+   * The number of source bytes pushed so far with the parser state at that
+   * point. After a restart, read the source from `offset` and push it to
+   * `CsvParser.fromCheckpoint(checkpoint)` to get the rows that follow
+   * those already returned.
+   */
+  getCheckpoint(): Checkpoint
+  /** Creates a parser that continues from `checkpoint` */
+  static fromCheckpoint(checkpoint: Checkpoint): CsvParser
+  /** Creates a parser that continues where the one that made `state` was */
+  static deserializeState(state: Buffer): CsvParser
+  /**
+   * Checks a CSV buffer, or the file at a path, for ragged rows, duplicate
+   * or empty headers, mixed line endings, invalid UTF-8 and a separator
+   * that does not fit, without creating rows
+   */
+  static validate(input: Buffer | string, rules?: LintRules | undefined | null): LintReport
+  /** Rows dropped as duplicates so far, with the `dedupe` option */
+  get duplicates(): number
+  /**
+   * With `onViolation: "skip"` or `"annotate"`, every failed cell of the
+   * rows left out or kept so far, in input order
+   */
+  getViolations(): Array<CellViolation>
+  /** Rows left out so far by `onViolation: "skip"` */
+  get invalidRows(): number
+  /**
+   * With the `digest` option, the hash of the input pushed so far as hex:
+   * after the last chunk, the digest of the whole input. A parser restored
+   * from a checkpoint or state hashes only the input pushed to it.
+   */
+  get digest(): string | null
+  /**
+   * Bytes in, rows out, comment lines, errors and time spent parsing, for
+   * exporting as telemetry
+   */
+  getMetrics(): ParserMetrics
+  /**
+   * Starts recording where parsing time goes: decoding input, tokenizing
+   * rows or building their objects. Timing each row costs a little, so
+   * profiling is off until enabled; pass false to turn it off again.
+   */
+  enableProfiling(enabled?: boolean | undefined | null): void
+  /**
+   * The times recorded since profiling was enabled or the parser was last
+   * reset; null while profiling is off. Packed rows are all tokenizing.
+   */
+  getProfile(): ParserProfile | null
+  /**
+   * The line, open quote, buffered input and header state of the parse,
+   * for debugging a stream that holds back a partial row
+   */
+  getState(): ParserState
+  memoryUsage(): MemoryUsage
   transform(chunk: Buffer, enc: string, cb: (...args: any[]) => any): Array<object>
-  flush(): Array<object>
+  flush<T extends object = Record<string, string>>(): Array<T>
+  /** Like `flush()`, returning errors as `pushWithErrors()` does */
+  flushWithErrors<T extends object = Record<string, string>>(): ParseResult & { rows: Array<T> }
+  /**
+   * Adds a chunk to the buffered input without parsing any rows, for a
+   * consumer that takes rows with `read()` as it is ready for them
+   */
+  write(chunk: string | Buffer | ArrayBufferView | ArrayBuffer): void
+  /**
+   * Parses up to `count` of the complete rows written so far (`batchSize`
+   * rows, or all of them, by default), returning errors as
+   * `pushWithErrors()` does. With `end` the input is complete, so the last
+   * row is parsed once the others are out. An empty result means no rows
+   * are left until more input is written.
+   */
+  read<T extends object = Record<string, string>>(count?: number | undefined | null, end?: boolean | undefined | null): ParseResult & { rows: Array<T> }
+}
+/**
+ * Maps the values of one column of a UTF-8 file to the rows that hold them.
+ * Rows stay on disk and are read back on lookup.
+ */
+export declare class CsvIndex {
+  /** Reads the file at `path` once and indexes every row by `column` */
+  static build(path: string, options: CsvIndexOptions): CsvIndex
+  /** Distinct keys in the index */
+  get size(): number
+  /** Byte locations of the rows with this key, in file order */
+  locate(key: string): Array<RowLocation>
+  /** The rows with this key, read back from the file */
+  lookup<T extends object = Record<string, string>>(key: string): Array<T>
+}
+/**
+ * Computes per-column statistics in Rust while streaming, without creating
+ * row objects.
+ */
+export declare class CsvStats {
+  constructor(options?: CsvStatsOptions | undefined | null)
+  push(chunk: string | Buffer | ArrayBufferView | ArrayBuffer): void
+  /** Ends the input and returns the statistics of every column */
+  getStats(): Array<ColumnStats>
+  /** Rows counted so far */
+  get rows(): number
+}
+/**
+ * Checks CSV against RFC 4180 (commas, `"` quotes, CRLF line breaks, a
+ * fixed field count) and reports every violation with its line and column
+ * instead of stopping at the first one
+ */
+export declare class CsvValidator {
+  constructor(options?: CsvValidatorOptions | undefined | null)
+  push(chunk: string | Buffer | ArrayBufferView | ArrayBuffer): void
+  /**
+   * Ends the input and returns the report. The validator can then check
+   * another input.
+   */
+  finish(): ValidationReport
+}
+/**
+ * Joins a streamed CSV input against a right input on a key column. The
+ * right input (a buffer, or the file at a path) is loaded into a hash table
+ * up front; the left input is pushed in chunks and joined in Rust.
+ */
+export declare class CsvJoiner {
+  constructor(right: Buffer | string, options: CsvJoinOptions)
+  /** Distinct keys in the right input */
+  get size(): number
+  /** Adds a chunk of the left input and returns the joined rows it completes */
+  push<T extends object = Record<string, string>>(chunk: string | Buffer | ArrayBufferView | ArrayBuffer): Array<T>
+  /** Ends the left input and returns the remaining joined rows */
+  flush<T extends object = Record<string, string>>(): Array<T>
+}
+/**
+ * Reshapes rows between wide and long form while parsing: to long, a row
+ * such as `region,jan,feb` becomes one `region,key,value` row per month; to
+ * wide, consecutive rows sharing their ids are merged back into one, so the
+ * input should be grouped by them.
+ */
+export declare class CsvReshaper {
+  constructor(options: CsvReshapeOptions)
+  /** Adds a chunk of input and returns the reshaped rows it completes */
+  push<T extends object = Record<string, string>>(chunk: string | Buffer | ArrayBufferView | ArrayBuffer): Array<T>
+  /** Ends the input and returns the remaining reshaped rows */
+  flush<T extends object = Record<string, string>>(): Array<T>
+}
+/** Rows returned by `sortBy`, in sorted order */
+export declare class SortedRows {
+  /** The header names rows are keyed by */
+  get headers(): Array<string>
+  /**
+   * Returns up to `count` (default 1000) more rows; an empty array once all
+   * rows have been read
+   */
+  next<T extends object = Record<string, string>>(count?: number | undefined | null): Array<T>
+}
+/** Changes found by `diff()`, in key order */
+export declare class CsvDiff {
+  /**
+   * The columns compared: those of the old file, then those only in the
+   * new one. A column missing from one file counts as empty.
+   */
+  get columns(): Array<string>
+  /** Rows found in both files with the same values so far */
+  get unchanged(): number
+  /**
+   * Returns up to `count` (default 1000) more changes; an empty array once
+   * both files have been read
+   */
+  next(count?: number | undefined | null): Array<DiffChange>
+}
+/**
+ * Groups rows and aggregates columns in Rust while streaming, without
+ * creating row objects. Only the aggregated groups come back to JS.
+ */
+export declare class CsvAggregator {
+  constructor(options: AggregateOptions)
+  push(chunk: string | Buffer | ArrayBufferView | ArrayBuffer): void
+  /**
+   * Ends the input and returns one object per group, holding the group-by
+   * values and the metrics. Metrics of groups without numeric values are
+   * `null`, except counts.
+   */
+  getResult(): Array<Record<string, string | number | null>>
+}
+/**
+ * Parses CSV in one dialect and encoding and writes it in another, in a
+ * single native pass. Output comes back as Buffers, ready to write to a
+ * file.
+ */
+export declare class CsvRewriter {
+  constructor(options?: CsvRewriterOptions | undefined | null)
+  /** Adds a chunk of input and returns the output for the rows it completes */
+  push(chunk: string | Buffer | ArrayBufferView | ArrayBuffer): Buffer
+  /** Ends the input and returns the rest of the output */
+  flush(): Buffer
+}
+/**
+ * Converts input in a named encoding to UTF-8 Buffers without parsing it.
+ * Chunks may split characters anywhere; a BOM at the start is removed and,
+ * for UTF-8 and UTF-16, overrides the given encoding.
+ */
+export declare class Transcoder {
+  /**
+   * `encoding` is a label such as `"latin1"`, `"shift_jis"` or
+   * `"utf-16le"`; UTF-8 by default
+   */
+  constructor(encoding?: string | undefined | null)
+  /**
+   * Name of the encoding input is decoded from, e.g. `"UTF-16LE"` once a
+   * BOM has said so
+   */
+  get encoding(): string
+  /**
+   * Decodes a chunk, keeping a character split across chunks for the next
+   * call
+   */
+  push(chunk: string | Buffer | ArrayBufferView | ArrayBuffer): Buffer
+  /**
+   * Ends the input and returns the rest of the output. Fails if the input
+   * ended inside a character.
+   */
+  flush(): Buffer
+}
+export declare class CsvWriter {
+  constructor(options?: JsCsvWriterOptions | undefined | null)
+  /**
+   * Formats rows (objects or arrays) as CSV. For objects the header line is
+   * written before the first row, using the `headers` option or the keys of
+   * the first row. `headers` also sets which keys are written and in what
+   * order, whatever the key order of each object. `quoteIf` is called with
+   * each data cell and its header (or index, for array rows) and quotes the
+   * cell when it returns true, whatever the `quoteStyle`. With `bom` the
+   * first string starts with U+FEFF.
+   */
+  write(rows: Array<unknown>, quoteIf?: (value: string, column: string | number) => boolean): string
+  /**
+   * Like `write()`, but returns the CSV encoded in `encoding`, ready to
+   * write to a file
+   */
+  writeBuffer(rows: Array<unknown>, quoteIf?: (value: string, column: string | number) => boolean): Buffer
+  /**
+   * Appends rows to the file at `path`, encoded as by `writeBuffer()`. A
+   * missing or empty file is started with the header line and BOM as
+   * usual. Otherwise the file's header line must match the `headers`
+   * option, or the keys of the first object row in any order, and the
+   * rows follow in the file's column order without another header. Array
+   * rows without `headers` are appended unchecked.
+   */
+  appendTo(path: string, rows: Array<unknown>, quoteIf?: (value: string, column: string | number) => boolean): void
+  /**
+   * Ends the compressed stream of `writeBuffer()` output and returns its
+   * rest; output after it starts another gzip member or zstd frame. Empty
+   * without `compression`.
+   */
+  finish(): Buffer
+  /**
+   * Formats the rows of an iterable, an async iterable or an object-mode
+   * Readable as a Readable of CSV bytes, encoded as by `writeBuffer()`. Rows
+   * are pulled only as fast as the output is read, and written `batchSize`
+   * (default 256) at a time.
+   */
+  static fromIterable(rows: Iterable<unknown> | AsyncIterable<unknown>, options?: JsCsvWriterOptions & { quoteIf?: (value: string, column: string | number) => boolean, batchSize?: number }): import('stream').Readable
+}
+/**
+ * Reads a CSV file in the background only as fast as rows are asked for,
+ * so a slow consumer pauses the file reads. Backs `createReadStream()`.
+ */
+export declare class CsvFileReader {
+  constructor(path: string, options?: JsCsvParserOptions | undefined | null)
+  /**
+   * Parses up to `count` more rows on the libuv thread pool. Resolves to an
+   * empty array once the file is done.
+   */
+  read<T extends object = Record<string, string>>(count: number): Promise<Array<T>>
+  /** The header names, once the header line has been read */
+  get headers(): Array<string> | null
+}
+/**
+ * Parses on a dedicated thread, so large chunks do not block the event
+ * loop. `push()` hands a chunk over and returns at once; `read()` resolves
+ * to the rows of the oldest chunk not yet asked for. The thread stops
+ * parsing while `maxPendingBatches` batches wait to be taken.
+ */
+export declare class CsvParseThread {
+  constructor(options?: ParseThreadOptions | undefined | null)
+  /**
+   * Hands a chunk to the parse thread. Returns false once
+   * `maxPendingBatches` batches are pending: wait for `read()` before
+   * pushing more, as for a stream's `write()`.
+   */
+  push(chunk: string | Buffer | ArrayBufferView | ArrayBuffer): boolean
+  /** Ends the input. Its last rows make one more batch. */
+  end(): void
+  /**
+   * Resolves to the rows of the oldest pending batch, which may be empty,
+   * and rejects with its error if a row failed. Resolves at once to an
+   * empty array when nothing is pending, and to null after the last batch.
+   */
+  read<T extends object = Record<string, string>>(): Promise<Array<T> | null>
+}
+/**
+ * Parser options read and checked once, for creating many parsers with
+ * `CsvParser.fromTemplate()`. Parsers share the template and each starts
+ * from the first line, so one template serves any number of inputs.
+ */
+export declare class ParserTemplate {
+  /** Checks `options` as `new CsvParser()` does, throwing the same errors */
+  constructor(options?: JsCsvParserOptions | undefined | null)
+  /** The custom header names, if the options give them */
+  get headers(): Array<string> | null
 }
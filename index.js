@@ -4,7 +4,7 @@
 
 /* auto-generated by NAPI-RS */
 
-const { existsSync, readFileSync } = require('fs')
+const { existsSync, readdirSync, readFileSync } = require('fs')
 const { join } = require('path')
 
 const { platform, arch } = process
@@ -23,8 +23,17 @@ function isMusl() {
       return true
     }
   } else {
-    const { glibcVersionRuntime } = process.report.getReport().header
-    return !glibcVersionRuntime
+    // Bun and Deno may not fill in the report header
+    try {
+      const { glibcVersionRuntime } = process.report.getReport().header
+      return !glibcVersionRuntime
+    } catch (e) {
+      try {
+        return readdirSync('/lib').some((file) => file.startsWith('ld-musl-'))
+      } catch (e) {
+        return false
+      }
+    }
   }
 }
 
@@ -310,6 +319,34 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { CsvParser } = nativeBinding
+const { CsvParser, CsvWriter, benchmark, inferSchema, countRows, toJson, toSqlite, findRecordBoundaries, distinct, head, readHeaders, resolveOptions, sample, CsvIndex, CsvStats, CsvValidator, CsvJoiner, CsvReshaper, sortBy, diff, split, SortedRows, CsvDiff, CsvAggregator, CsvRewriter, Transcoder, CsvFileReader, CsvParseThread, ParserTemplate } = nativeBinding
 
 module.exports.CsvParser = CsvParser
+module.exports.CsvWriter = CsvWriter
+module.exports.benchmark = benchmark
+module.exports.inferSchema = inferSchema
+module.exports.countRows = countRows
+module.exports.toJson = toJson
+module.exports.toSqlite = toSqlite
+module.exports.findRecordBoundaries = findRecordBoundaries
+module.exports.distinct = distinct
+module.exports.head = head
+module.exports.readHeaders = readHeaders
+module.exports.resolveOptions = resolveOptions
+module.exports.sample = sample
+module.exports.CsvIndex = CsvIndex
+module.exports.CsvStats = CsvStats
+module.exports.CsvValidator = CsvValidator
+module.exports.CsvJoiner = CsvJoiner
+module.exports.CsvReshaper = CsvReshaper
+module.exports.sortBy = sortBy
+module.exports.diff = diff
+module.exports.split = split
+module.exports.SortedRows = SortedRows
+module.exports.CsvDiff = CsvDiff
+module.exports.CsvAggregator = CsvAggregator
+module.exports.CsvRewriter = CsvRewriter
+module.exports.Transcoder = Transcoder
+module.exports.CsvFileReader = CsvFileReader
+module.exports.CsvParseThread = CsvParseThread
+module.exports.ParserTemplate = ParserTemplate
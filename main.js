@@ -1,11 +1,42 @@
-const { Transform } = require("stream");
-const { CsvParser } = require("./index.js");
+const fs = require("fs");
+const { Readable, Transform } = require("stream");
+const {
+  CsvAggregator,
+  CsvFileReader,
+  CsvParser,
+  CsvParseThread,
+  CsvRewriter,
+  CsvWriter: NativeCsvWriter,
+  CsvIndex,
+  CsvStats,
+  CsvJoiner,
+  CsvReshaper,
+  CsvValidator,
+  ParserTemplate,
+  Transcoder,
+  benchmark,
+  countRows,
+  toJson,
+  toSqlite,
+  findRecordBoundaries,
+  diff,
+  distinct,
+  head,
+  readHeaders,
+  inferSchema,
+  resolveOptions,
+  sample,
+  sortBy,
+  split,
+} = require("./index.js");
 
 const defaults = {
   escape: '"',
   headers: null,
   mapHeaders: ({ header }) => header,
   mapValues: ({ value }) => value,
+  mapValuesBatch: null,
+  mapBatchRows: null,
   newline: "\n",
   quote: '"',
   raw: false,
@@ -15,8 +46,23 @@ const defaults = {
   maxRowBytes: Number.MAX_SAFE_INTEGER,
   strict: false,
   outputByteOffset: false,
+  onProgress: null,
+  onHeaders: null,
+  transformRow: null,
+  checkpoint: null,
+  progressInterval: 1000,
+  rfc4180: false,
 };
 
+// Time constant for the progress rate moving averages. Rates react to a
+// change in throughput over roughly this many milliseconds.
+const PROGRESS_EWMA_WINDOW_MS = 5000;
+
+// Streams that have taken input but not yet finished, so they can be drained
+// together when the process is asked to shut down. A stream joins on its
+// first chunk, so one that is created and dropped unused is not kept alive.
+const activeStreams = new Set();
+
 class CsvParserStream extends Transform {
   constructor(options = {}) {
     super({ objectMode: true, highWaterMark: 16 });
@@ -33,71 +79,382 @@ class CsvParserStream extends Transform {
     this.mapHeaders = this.options.mapHeaders;
     this.mapValues = this.options.mapValues;
 
-    // Prepare options for native parser
-    const nativeOptions = { ...this.options };
-    delete nativeOptions.mapHeaders;
-    delete nativeOptions.mapValues;
-
-    // Convert null to appropriate defaults for native parser
-    if (nativeOptions.headers === null) {
-      delete nativeOptions.headers;
-    }
-    if (nativeOptions.skipLines === null) {
-      nativeOptions.skipLines = 0;
-    }
+    // mapHeaders and mapValues see flat rows, so with either of them
+    // `expandHeaders` nests the mapped row in JS instead of natively
+    this.expandInJs = Boolean(this.options.expandHeaders) && hasRowMappers(this.options);
 
     try {
-      this.parser = new CsvParser(nativeOptions);
+      checkMapValuesBatch(this.options);
+      // A checkpoint carries the native options it was taken with
+      this.parser = this.options.checkpoint
+        ? CsvParser.fromCheckpoint(this.options.checkpoint)
+        : new CsvParser(toNativeOptions(this.options));
     } catch (error) {
       // If native parser fails, emit error on next tick
       process.nextTick(() => this.emit("error", error));
       return;
     }
 
-    this.headersEmitted = false;
-    this.isFirstRowProcessed = false;
+    // Checks the raw input against RFC 4180 alongside parsing
+    if (this.options.rfc4180) {
+      this.validator = new CsvValidator();
+    }
+
+    // Headers last emitted with the `headers` event
+    this.emittedHeaders = null;
+    // Index of the table last emitted with the `section` event
+    this.emittedSection = null;
+    // The rest of a chunk's rows, waiting for the consumer to read
+    this.pendingPull = null;
+
+    this.sources = new Set();
+    this.on("pipe", (source) => this.sources.add(source));
+    this.on("unpipe", (source) => this.sources.delete(source));
+
+    this.progress = {
+      bytes: 0,
+      rows: 0,
+      startedAt: Date.now(),
+      lastReportAt: 0,
+      lastBytes: 0,
+      lastRows: 0,
+      bytesPerSecond: 0,
+      rowsPerSecond: 0,
+    };
   }
 
   _transform(chunk, encoding, callback) {
+    activeStreams.add(this);
     try {
       // Ensure chunk is a Buffer
       if (typeof chunk === "string") {
         chunk = Buffer.from(chunk, encoding || "utf8");
       }
-      const rows = this.parser.push(chunk);
-      this._processRows(rows);
-      callback();
+      if (this.validator) {
+        this.validator.push(chunk);
+      }
+      this.parser.write(chunk);
+      this._trackProgress(chunk.length, 0, false);
+    } catch (error) {
+      this._handleError(error, callback);
+      return;
+    }
+    this._pull(false, callback);
+  }
+
+  // Emits the rows written to the native parser a batch at a time (the rows
+  // each chunk completes, or `batchSize` rows), then calls `callback`. While
+  // the readable side is full the next batch waits for `_read`, and the
+  // chunk is not acknowledged, so a slow consumer holds back the source
+  // rather than rows piling up here.
+  _pull(end, callback) {
+    try {
+      for (;;) {
+        const result = this.parser.read(undefined, end);
+        if (result.rows.length === 0 && result.errors.length === 0) {
+          this._emitHeaders();
+          break;
+        }
+        this._processResult(result);
+        this._trackProgress(0, result.rows.length, false);
+        if (this.readableLength >= this.readableHighWaterMark) {
+          this.pendingPull = () => this._pull(end, callback);
+          return;
+        }
+      }
     } catch (error) {
       this._handleError(error, callback);
+      return;
+    }
+    callback();
+  }
+
+  _read(size) {
+    const pull = this.pendingPull;
+    this.pendingPull = null;
+    if (pull) {
+      pull();
     }
+    super._read(size);
+  }
+
+  _destroy(error, callback) {
+    activeStreams.delete(this);
+    callback(error);
+  }
+
+  memoryUsage() {
+    return this.parser.memoryUsage();
+  }
+
+  // Rows dropped so far by the `dedupe` option
+  get duplicates() {
+    return this.parser.duplicates;
+  }
+
+  // Rows left out so far by `onViolation: "skip"`
+  get invalidRows() {
+    return this.parser.invalidRows;
+  }
+
+  // Hash of the input so far with the `digest` option; of all of it after `end`
+  get digest() {
+    return this.parser.digest;
+  }
+
+  getMetrics() {
+    return this.parser.getMetrics();
+  }
+
+  enableProfiling(enabled) {
+    this.parser.enableProfiling(enabled);
+  }
+
+  getProfile() {
+    return this.parser.getProfile();
+  }
+
+  // Line, open quote and buffered bytes, for a stream that holds back a row
+  getState() {
+    return this.parser.getState();
+  }
+
+  // Where to resume after the rows emitted so far; see CsvParser#getCheckpoint
+  getCheckpoint() {
+    return this.parser.getCheckpoint();
+  }
+
+  // Stop reading from any piped sources and end the stream, so rows still
+  // buffered in the native parser (including a final row without a trailing
+  // newline) are emitted before the stream finishes. Rows nobody reads keep
+  // it from finishing; with `timeout` (ms) it is destroyed after that long.
+  drain({ timeout } = {}) {
+    return new Promise((resolve) => {
+      if (this.writableFinished || this.destroyed) {
+        resolve();
+        return;
+      }
+
+      let timer;
+      const done = () => {
+        clearTimeout(timer);
+        this.off("finish", done);
+        this.off("error", done);
+        this.off("close", done);
+        resolve();
+      };
+      this.once("finish", done);
+      this.once("error", done);
+      this.once("close", done);
+      if (timeout !== undefined) {
+        timer = setTimeout(() => this.destroy(), timeout);
+      }
+
+      for (const source of this.sources) {
+        source.unpipe(this);
+      }
+      if (!this.writableEnded) {
+        this.end();
+      }
+    });
   }
 
   _flush(callback) {
+    activeStreams.delete(this);
+    this._pull(true, (error) => {
+      if (error) {
+        callback(error);
+        return;
+      }
+      this._finish(callback);
+    });
+  }
+
+  // Reports what is known once every row has been emitted
+  _finish(callback) {
     try {
-      const rows = this.parser.flush();
-      this._processRows(rows);
+      this._trackProgress(0, 0, true);
+      if (this.validator) {
+        this.emit("validation", this.validator.finish());
+      }
+      if (this.options.strict === "report") {
+        this.emit("raggedRows", this.parser.getRaggedRows());
+      }
+      if (this._reportsViolations()) {
+        this.emit("violations", this.parser.getViolations());
+      }
       callback();
     } catch (error) {
       this._handleError(error, callback);
     }
   }
 
+  _trackProgress(bytes, rows, final) {
+    if (typeof this.options.onProgress !== "function") {
+      return;
+    }
+
+    const progress = this.progress;
+    progress.bytes += bytes;
+    progress.rows += rows;
+
+    const now = Date.now();
+    const since = now - (progress.lastReportAt || progress.startedAt);
+    if (!final && since < this.options.progressInterval) {
+      return;
+    }
+
+    // Exponentially weighted moving average of the throughput since the last
+    // report. The source length is unknown, so rates are all we can offer.
+    if (since > 0) {
+      const alpha = 1 - Math.exp(-since / PROGRESS_EWMA_WINDOW_MS);
+      const bytesRate = ((progress.bytes - progress.lastBytes) * 1000) / since;
+      const rowsRate = ((progress.rows - progress.lastRows) * 1000) / since;
+      const first = progress.lastReportAt === 0;
+
+      progress.bytesPerSecond = first
+        ? bytesRate
+        : alpha * bytesRate + (1 - alpha) * progress.bytesPerSecond;
+      progress.rowsPerSecond = first
+        ? rowsRate
+        : alpha * rowsRate + (1 - alpha) * progress.rowsPerSecond;
+    }
+
+    progress.lastReportAt = now;
+    progress.lastBytes = progress.bytes;
+    progress.lastRows = progress.rows;
+
+    this.options.onProgress({
+      bytes: progress.bytes,
+      rows: progress.rows,
+      elapsed: now - progress.startedAt,
+      bytesPerSecond: progress.bytesPerSecond,
+      rowsPerSecond: progress.rowsPerSecond,
+      done: final,
+    });
+  }
+
+  // The header names rows are keyed by, or null until they are known. Custom
+  // headers are known from the start, headers read from the input once the
+  // header line has been parsed, and the numeric names of `headers: false`
+  // once the first row has been.
+  getHeaders() {
+    return this.parser.getHeaders();
+  }
+
+  // With `strict: "report"`, the rows left out so far for their width; see
+  // CsvParser#getRaggedRows
+  getRaggedRows() {
+    return this.parser.getRaggedRows();
+  }
+
+  // Zero-based indexes of the columns left out of rows for an empty or `_`
+  // header; see CsvParser#getDroppedColumns
+  getDroppedColumns() {
+    return this.parser.getDroppedColumns();
+  }
+
+  // With `onViolation: "skip"` or `"annotate"`, the cells that failed their
+  // validator or schema so far; see CsvParser#getViolations
+  getViolations() {
+    return this.parser.getViolations();
+  }
+
+  _reportsViolations() {
+    const { validators, schema, onViolation } = this.options;
+    return (validators || schema) && (onViolation === "skip" || onViolation === "annotate");
+  }
+
+  // Emit `headers` as soon as the headers are known, before any row keyed by
+  // them, and again if a wider row adds numeric names with `headers: false`
+  // or a table of `sections` has its own. `droppedColumns` follows when
+  // some of them are empty or `_`, so their values are not lost unnoticed.
+  _emitHeaders() {
+    const headers = this.parser.getHeaders();
+    if (this.options.sections && headers && this.parser.section !== this.emittedSection) {
+      this.emittedSection = this.parser.section;
+      this.emit("section", {
+        index: this.parser.section,
+        name: this.parser.sectionName,
+        headers,
+      });
+    }
+    if (
+      headers &&
+      (!this.emittedHeaders ||
+        headers.length !== this.emittedHeaders.length ||
+        headers.some((header, i) => header !== this.emittedHeaders[i]))
+    ) {
+      this.emittedHeaders = headers;
+      if (typeof this.options.onHeaders === "function") {
+        this.options.onHeaders(headers);
+      }
+      this.emit("headers", headers);
+      const dropped = this.parser.getDroppedColumns();
+      if (dropped.length > 0) {
+        this.emit("droppedColumns", dropped);
+      }
+    }
+  }
+
+  // Resolves with the header names once they are known, before any row is
+  // emitted, so a UI can show the column mapping while parsing continues.
+  // Resolves with null if the stream finishes without headers and rejects
+  // if it fails first.
+  onceHeaders() {
+    const known = this.emittedHeaders || this.getHeaders();
+    if (known) {
+      return Promise.resolve(known);
+    }
+    return new Promise((resolve, reject) => {
+      const settle = (callback, value) => {
+        this.off("headers", onHeaders);
+        this.off("error", onError);
+        this.off("finish", onFinish);
+        this.off("close", onFinish);
+        callback(value);
+      };
+      const onHeaders = (headers) => settle(resolve, headers);
+      const onError = (error) => settle(reject, error);
+      const onFinish = () => settle(resolve, null);
+      this.on("headers", onHeaders);
+      this.on("error", onError);
+      this.on("finish", onFinish);
+      this.on("close", onFinish);
+    });
+  }
+
+  // Emits the rows of a native call that came before its first failed row,
+  // then throws that row's error, so the stream fails right after them
+  _processResult({ rows, errors }) {
+    if (errors.length === 0) {
+      this._processRows(rows);
+      return;
+    }
+    this._processRows(rows.slice(0, errors[0].index));
+    throw new Error(errors[0].message);
+  }
+
   _processRows(rows) {
-    for (let row of rows) {
-      // Emit headers event on first data row (if not already emitted)
-      if (!this.headersEmitted && !this.isFirstRowProcessed) {
-        const headers = this.parser.getHeaders();
-        if (headers && this.options.headers !== false) {
-          this.emit("headers", headers);
-          this.headersEmitted = true;
+    this._emitHeaders();
+    if (typeof this.options.mapValuesBatch === "function" && rows.length > 0) {
+      rows = this._mapValuesBatch(rows);
+    }
+    rows = rows.map((row) => this._processRow(row));
+
+    // One call per batch of rows from the native parser rather than per row
+    if (typeof this.options.transformRow === "function" && rows.length > 0) {
+      const transformed = this.options.transformRow(rows);
+      if (transformed !== undefined) {
+        if (!Array.isArray(transformed)) {
+          throw new TypeError("transformRow must return an array of rows or undefined");
         }
-        this.isFirstRowProcessed = true;
+        rows = transformed;
       }
+    }
 
-      // Process the row
-      row = this._processRow(row);
-
-      if (row !== null) {
+    for (const row of rows) {
+      if (row !== null && row !== undefined) {
         if (this.options.outputByteOffset) {
           this.push({ row, byteOffset: 0 }); // Native parser would need to provide actual offset
         } else {
@@ -107,14 +464,78 @@ class CsvParserStream extends Transform {
     }
   }
 
+  // The column index of each header and the names mapHeaders gives them,
+  // worked out once each time the headers change rather than for every row
+  _columns() {
+    const headers = this.emittedHeaders;
+    if (!this.columns || this.columns.headers !== headers) {
+      const indexes = new Map();
+      (headers || []).forEach((header, index) => {
+        if (!indexes.has(header)) {
+          indexes.set(header, index);
+        }
+      });
+      const mapped =
+        headers && this.mapHeaders !== defaults.mapHeaders
+          ? headers.map((header, index) => this.mapHeaders({ header, index }))
+          : null;
+      this.columns = { headers, indexes, mapped };
+    }
+    return this.columns;
+  }
+
+  // Maps the values of `mapBatchRows` rows at a time (by default all the rows
+  // of a native call) with one mapValuesBatch call, rather than one call per
+  // cell. Cells are passed in row then column order and the returned values
+  // are taken back in the same order.
+  _mapValuesBatch(rows) {
+    const { indexes } = this._columns();
+    const entries = this.options.rowObject === "entries";
+    const size = this.options.mapBatchRows || rows.length;
+    const mapped = [];
+    for (let start = 0; start < rows.length; start += size) {
+      const batch = rows
+        .slice(start, start + size)
+        .map((row) => (entries ? row : Object.entries(row)));
+      const cells = [];
+      for (const pairs of batch) {
+        for (const [header, value] of pairs) {
+          const index = indexes.has(header) ? indexes.get(header) : -1;
+          cells.push({ header, index, value });
+        }
+      }
+      const values = this.options.mapValuesBatch(cells);
+      if (!Array.isArray(values) || values.length !== cells.length) {
+        throw new TypeError("mapValuesBatch must return an array with a value for each cell");
+      }
+      let i = 0;
+      for (const pairs of batch) {
+        if (entries) {
+          mapped.push(pairs.map(([key]) => [key, values[i++]]));
+          continue;
+        }
+        const row = createRow(this.options.rowObject);
+        for (const [key] of pairs) {
+          row[key] = values[i++];
+        }
+        mapped.push(row);
+      }
+    }
+    return mapped;
+  }
+
   _processRow(row) {
+    if (this.options.rowObject === "entries") {
+      return this._processEntries(row);
+    }
+
     // Apply mapValues first
     if (this.mapValues !== defaults.mapValues) {
-      const headers = this.parser.getHeaders();
-      const processedRow = {};
+      const { indexes } = this._columns();
+      const processedRow = createRow(this.options.rowObject);
 
       for (const [key, value] of Object.entries(row)) {
-        const index = headers ? headers.indexOf(key) : -1;
+        const index = indexes.has(key) ? indexes.get(key) : -1;
         const newValue = this.mapValues({ header: key, index, value });
         processedRow[key] = newValue;
       }
@@ -123,18 +544,20 @@ class CsvParserStream extends Transform {
 
     // Apply mapHeaders (column renaming/filtering)
     if (this.mapHeaders !== defaults.mapHeaders) {
-      const headers = this.parser.getHeaders();
+      const { headers, mapped } = this._columns();
       if (headers) {
-        const newRow = {};
+        const newRow = createRow(this.options.rowObject);
         for (let i = 0; i < headers.length; i++) {
           const originalHeader = headers[i];
-          const mappedHeader = this.mapHeaders({
-            header: originalHeader,
-            index: i,
-          });
+          const mappedHeader = mapped[i];
 
-          // If mapHeaders returns null, skip this column
-          if (mappedHeader !== null && mappedHeader !== undefined) {
+          // If mapHeaders returns null, skip this column. Keep rows sparse
+          // when skipEmptyFields left the cell out.
+          if (
+            mappedHeader !== null &&
+            mappedHeader !== undefined &&
+            (!this.options.skipEmptyFields || originalHeader in row)
+          ) {
             newRow[mappedHeader] = row[originalHeader];
           }
         }
@@ -142,6 +565,43 @@ class CsvParserStream extends Transform {
       }
     }
 
+    if (this.expandInJs) {
+      row = expandRow(row, this.options.rowObject);
+    }
+
+    if (this.options.rowObject === "frozen" && !Object.isFrozen(row)) {
+      Object.freeze(row);
+    }
+    return row;
+  }
+
+  // `_processRow` for rows of `[key, value]` pairs, kept in column order
+  _processEntries(row) {
+    const { headers, indexes, mapped: names } = this._columns();
+    if (this.mapValues !== defaults.mapValues) {
+      row = row.map(([key, value]) => {
+        const index = indexes.has(key) ? indexes.get(key) : -1;
+        return [key, this.mapValues({ header: key, index, value })];
+      });
+    }
+
+    if (this.mapHeaders !== defaults.mapHeaders && headers) {
+      const mapped = [];
+      // Duplicate headers map by position, each pair after the one before
+      let from = 0;
+      for (const [key, value] of row) {
+        const index = headers.indexOf(key, from);
+        if (index === -1) {
+          continue;
+        }
+        from = index + 1;
+        const mappedHeader = names[index];
+        if (mappedHeader !== null && mappedHeader !== undefined) {
+          mapped.push([mappedHeader, value]);
+        }
+      }
+      row = mapped;
+    }
     return row;
   }
 
@@ -160,10 +620,675 @@ class CsvParserStream extends Transform {
   }
 }
 
+// The native writer, taking the JS-only `quoteIf` option and passing it to
+// every write()
+class CsvWriter extends NativeCsvWriter {
+  constructor(options) {
+    const { quoteIf, ...nativeOptions } = options ?? {};
+    super(nativeOptions);
+    this.quoteIf = quoteIf;
+  }
+
+  write(rows, quoteIf = this.quoteIf) {
+    return super.write(rows, quoteIf);
+  }
+
+  writeBuffer(rows, quoteIf = this.quoteIf) {
+    return super.writeBuffer(rows, quoteIf);
+  }
+
+  appendTo(path, rows, quoteIf = this.quoteIf) {
+    return super.appendTo(path, rows, quoteIf);
+  }
+}
+
+function hasRowMappers(options) {
+  return (
+    options.mapHeaders !== defaults.mapHeaders ||
+    options.mapValues !== defaults.mapValues ||
+    typeof options.mapValuesBatch === "function"
+  );
+}
+
+// mapValuesBatch replaces mapValues rather than running alongside it
+function checkMapValuesBatch(options) {
+  const { mapValuesBatch, mapBatchRows } = options;
+  if (mapValuesBatch === null || mapValuesBatch === undefined) {
+    return;
+  }
+  if (typeof mapValuesBatch !== "function") {
+    throw new TypeError("mapValuesBatch must be a function");
+  }
+  if (options.mapValues !== defaults.mapValues) {
+    throw new TypeError("mapValuesBatch cannot be used with mapValues");
+  }
+  if (mapBatchRows !== null && mapBatchRows !== undefined) {
+    if (!Number.isInteger(mapBatchRows) || mapBatchRows < 1) {
+      throw new TypeError(`mapBatchRows must be a positive integer, got ${mapBatchRows}`);
+    }
+  }
+}
+
+// The steps to a nested value named by a header such as `address.city` or
+// `tags[0]`, following the same rules as the native `expandHeaders`. Null
+// for headers that stay flat keys.
+function headerPath(header) {
+  if (!/[.[]/.test(header)) {
+    return null;
+  }
+  const path = [];
+  for (const part of header.split(".")) {
+    const match = /^([^[]+)((?:\[[^\]]+\])*)$/.exec(part);
+    if (!match) {
+      return null;
+    }
+    path.push(match[1]);
+    for (const [, inner] of match[2].matchAll(/\[([^\]]+)\]/g)) {
+      const index = Number(inner);
+      path.push(/^\d+$/.test(inner) && index < 2 ** 32 - 1 ? index : inner);
+    }
+  }
+  return path;
+}
+
+// An empty row object, or object nested in one, for the `rowObject` option
+function createRow(rowObject) {
+  return rowObject === "null-prototype" ? Object.create(null) : {};
+}
+
+// Nests the values of a flat row by their header paths
+function expandRow(row, rowObject) {
+  const expanded = createRow(rowObject);
+  for (const [key, value] of Object.entries(row)) {
+    const path = headerPath(key);
+    if (!path) {
+      expanded[key] = value;
+      continue;
+    }
+    let target = expanded;
+    for (let i = 0; i < path.length - 1; i++) {
+      // Only own properties, so a header such as `__proto__.x` never
+      // reaches a shared prototype
+      const next = Object.prototype.hasOwnProperty.call(target, path[i])
+        ? target[path[i]]
+        : undefined;
+      if (next === null || typeof next !== "object") {
+        target[path[i]] = typeof path[i + 1] === "number" ? [] : createRow(rowObject);
+      }
+      target = target[path[i]];
+    }
+    target[path[path.length - 1]] = value;
+  }
+  return expanded;
+}
+
+// Options for the native parser from stream options merged with the defaults
+function toNativeOptions(options) {
+  const nativeOptions = { ...options };
+  delete nativeOptions.mapHeaders;
+  delete nativeOptions.mapValues;
+  delete nativeOptions.mapValuesBatch;
+  delete nativeOptions.mapBatchRows;
+  delete nativeOptions.onProgress;
+  delete nativeOptions.onHeaders;
+  delete nativeOptions.transformRow;
+  delete nativeOptions.checkpoint;
+  delete nativeOptions.progressInterval;
+  delete nativeOptions.rfc4180;
+  // Entries keep `expandHeaders` so the native parser rejects the pair
+  if (hasRowMappers(options) && options.rowObject !== "entries") {
+    nativeOptions.expandHeaders = false;
+  }
+
+  // Convert null to appropriate defaults for native parser
+  if (nativeOptions.headers === null) {
+    delete nativeOptions.headers;
+  }
+  if (nativeOptions.skipLines === null) {
+    nativeOptions.skipLines = 0;
+  }
+  return nativeOptions;
+}
+
 // Export function that creates new parser instance (matches original API)
 module.exports = function csv(options) {
   return new CsvParserStream(options);
 };
 
+// The same parser as a WHATWG TransformStream, for runtimes and APIs built on
+// web streams (Deno, Bun, fetch() bodies). Takes the same options as csv().
+module.exports.webStream = function webStream(options) {
+  const parser = new CsvParserStream(options);
+  const ended = new Promise((resolve, reject) => {
+    parser.once("end", resolve);
+    parser.once("error", reject);
+  });
+  // Keep the rejection from being reported as unhandled before flush
+  ended.catch(() => {});
+
+  return new TransformStream({
+    start(controller) {
+      parser.on("data", (row) => controller.enqueue(row));
+      parser.once("error", (error) => controller.error(error));
+    },
+    transform(chunk) {
+      return new Promise((resolve, reject) => {
+        parser.write(chunk, (error) => (error ? reject(error) : resolve()));
+      });
+    },
+    flush() {
+      parser.end();
+      return ended;
+    },
+  });
+};
+
+// Group and aggregate CSV input in Rust. Write CSV into the returned stream;
+// when it ends, it emits one object per group instead of the rows.
+module.exports.aggregate = function aggregate(options) {
+  let aggregator;
+  try {
+    aggregator = new CsvAggregator(options);
+  } catch (error) {
+    const failed = new Transform({ readableObjectMode: true });
+    process.nextTick(() => failed.destroy(error));
+    return failed;
+  }
+
+  return new Transform({
+    readableObjectMode: true,
+    transform(chunk, encoding, callback) {
+      try {
+        aggregator.push(chunk);
+        callback();
+      } catch (error) {
+        callback(error);
+      }
+    },
+    flush(callback) {
+      try {
+        for (const group of aggregator.getResult()) {
+          this.push(group);
+        }
+        callback();
+      } catch (error) {
+        callback(error);
+      }
+    },
+  });
+};
+
+// Re-write CSV in another dialect and encoding. Takes the options of
+// CsvRewriter and passes Buffers through, so it can sit between two files.
+module.exports.rewrite = function rewrite(options) {
+  let rewriter;
+  try {
+    rewriter = new CsvRewriter(options);
+  } catch (error) {
+    const failed = new Transform();
+    process.nextTick(() => failed.destroy(error));
+    return failed;
+  }
+
+  return new Transform({
+    transform(chunk, encoding, callback) {
+      try {
+        callback(null, rewriter.push(chunk));
+      } catch (error) {
+        callback(error);
+      }
+    },
+    flush(callback) {
+      try {
+        callback(null, rewriter.flush());
+      } catch (error) {
+        callback(error);
+      }
+    },
+  });
+};
+
+// Decode input in `encoding` to UTF-8 without parsing it, e.g. to feed a
+// legacy-encoded file to another tool. Passes Buffers through.
+module.exports.transcode = function transcode(encoding) {
+  let transcoder;
+  try {
+    transcoder = new Transcoder(encoding);
+  } catch (error) {
+    const failed = new Transform();
+    process.nextTick(() => failed.destroy(error));
+    return failed;
+  }
+
+  return new Transform({
+    transform(chunk, _encoding, callback) {
+      try {
+        callback(null, transcoder.push(chunk));
+      } catch (error) {
+        callback(error);
+      }
+    },
+    flush(callback) {
+      try {
+        callback(null, transcoder.flush());
+      } catch (error) {
+        callback(error);
+      }
+    },
+  });
+};
+
+// Row keys of a file, as the parser would produce them, reading only as far
+// as its header line
+function readColumns(path, options) {
+  const parser = new CsvParser(toNativeOptions(options));
+  const fd = fs.openSync(path, "r");
+  const chunk = Buffer.alloc(64 * 1024);
+  let headers = null;
+  try {
+    while (!headers) {
+      const read = fs.readSync(fd, chunk, 0, chunk.length, null);
+      if (read === 0) {
+        parser.flush();
+      } else {
+        parser.push(chunk.subarray(0, read));
+      }
+      headers = parser.getHeaders();
+      if (read === 0) {
+        break;
+      }
+    }
+  } finally {
+    fs.closeSync(fd);
+  }
+
+  const columns = [];
+  (headers || []).forEach((header, index) => {
+    if (header === "" || header === "_") {
+      return;
+    }
+    const mapped =
+      options.mapHeaders === defaults.mapHeaders
+        ? header
+        : options.mapHeaders({ header, index });
+    if (mapped !== null && mapped !== undefined) {
+      columns.push(mapped);
+    }
+  });
+  return columns;
+}
+
+// Reads a file like `tail -f`: at its end, waits for appended data instead of
+// ending, checking every `interval` milliseconds until destroyed
+function followFile(path, interval) {
+  let fd = null;
+  let position = 0;
+  let timer = null;
+
+  return new Readable({
+    read(size) {
+      const attempt = () => {
+        timer = null;
+        const buffer = Buffer.allocUnsafe(Math.max(size, 64 * 1024));
+        fs.read(fd, buffer, 0, buffer.length, position, (error, read) => {
+          if (error) {
+            this.destroy(error);
+          } else if (read === 0) {
+            timer = setTimeout(attempt, interval);
+          } else {
+            position += read;
+            this.push(buffer.subarray(0, read));
+          }
+        });
+      };
+
+      if (fd !== null) {
+        attempt();
+        return;
+      }
+      fs.open(path, "r", (error, opened) => {
+        if (error) {
+          this.destroy(error);
+          return;
+        }
+        fd = opened;
+        attempt();
+      });
+    },
+    destroy(error, callback) {
+      clearTimeout(timer);
+      if (fd === null) {
+        callback(error);
+        return;
+      }
+      fs.close(fd, () => callback(error));
+    },
+  });
+}
+
+// Stream several CSV files as one dataset. Each file is parsed with its own
+// parser, so every file's header line is read as headers rather than data.
+// With `reconcile: "strict"` (the default) all files must have the same
+// headers; with `"union"` rows get every column of every file, null where
+// their file lacks it. `fileColumn` adds the path of each row's file under
+// `__file`, or under the given name. With `follow: true` the stream does not
+// end with the last file but keeps emitting rows appended to it.
+module.exports.parseFiles = function parseFiles(paths, options = {}) {
+  const {
+    reconcile = "strict",
+    fileColumn = false,
+    follow = false,
+    followInterval = 1000,
+    ...csvOptions
+  } = Array.isArray(options) ? { headers: options } : options;
+  const fileKey = fileColumn === true ? "__file" : fileColumn || null;
+  let source = null;
+  let parser = null;
+
+  const output = new Readable({
+    objectMode: true,
+    read() {
+      if (parser) {
+        parser.resume();
+      }
+    },
+    destroy(error, callback) {
+      if (source) {
+        source.destroy();
+        parser.destroy();
+      }
+      callback(error);
+    },
+  });
+
+  if (reconcile !== "strict" && reconcile !== "union") {
+    const error = new TypeError(
+      `reconcile must be "strict" or "union", got "${reconcile}"`,
+    );
+    process.nextTick(() => output.destroy(error));
+    return output;
+  }
+
+  let columns = null;
+  let expected = null;
+  const check = (path, headers) => {
+    if (expected === null) {
+      expected = { path, headers };
+    } else if (
+      headers.length !== expected.headers.length ||
+      headers.some((header, i) => header !== expected.headers[i])
+    ) {
+      throw new Error(
+        `Headers of ${path} do not match those of ${expected.path}`,
+      );
+    }
+  };
+
+  const parse = (index) => {
+    if (index === paths.length) {
+      output.push(null);
+      return;
+    }
+    const path = paths[index];
+    source =
+      follow && index === paths.length - 1
+        ? followFile(path, followInterval)
+        : fs.createReadStream(path);
+    const fail = (error) => output.destroy(error);
+    let checked = reconcile !== "strict";
+    const checkHeaders = () => {
+      const headers = parser.parser.getHeaders();
+      if (!checked && headers) {
+        checked = true;
+        check(path, headers);
+      }
+    };
+
+    parser = new CsvParserStream(csvOptions);
+    source.on("error", fail);
+    parser.on("error", fail);
+    parser.on("data", (row) => {
+      try {
+        checkHeaders();
+      } catch (error) {
+        fail(error);
+        return;
+      }
+      if (columns) {
+        const reconciled = {};
+        for (const column of columns) {
+          reconciled[column] = column in row ? row[column] : null;
+        }
+        row = Object.assign(reconciled, row);
+      }
+      if (fileKey) {
+        row[fileKey] = path;
+      }
+      if (!output.push(row)) {
+        parser.pause();
+      }
+    });
+    parser.on("end", () => {
+      try {
+        checkHeaders();
+      } catch (error) {
+        fail(error);
+        return;
+      }
+      parse(index + 1);
+    });
+    source.pipe(parser);
+  };
+
+  process.nextTick(() => {
+    try {
+      if (reconcile === "union") {
+        const options = Object.assign({}, defaults, csvOptions);
+        const seen = new Set();
+        for (const path of paths) {
+          for (const column of readColumns(path, options)) {
+            seen.add(column);
+          }
+        }
+        columns = Array.from(seen);
+      }
+    } catch (error) {
+      output.destroy(error);
+      return;
+    }
+    parse(0);
+  });
+  return output;
+};
+
+// An object-mode Readable of the rows of a file. Rows are parsed off the main
+// thread in batches of `highWaterMark` (default 16), and only when the
+// stream's buffer has room, so the file is read no faster than consumed.
+// Takes the options of the native CsvParser.
+module.exports.createReadStream = function createReadStream(path, options = {}) {
+  const { highWaterMark = 16, ...parserOptions } = options;
+  let reader;
+  try {
+    reader = new CsvFileReader(path, parserOptions);
+  } catch (error) {
+    const failed = new Readable({ objectMode: true, read() {} });
+    process.nextTick(() => failed.destroy(error));
+    return failed;
+  }
+
+  return new Readable({
+    objectMode: true,
+    highWaterMark,
+    read(size) {
+      reader.read(size).then(
+        (rows) => {
+          if (rows.length === 0) {
+            this.push(null);
+            return;
+          }
+          for (const row of rows) {
+            this.push(row);
+          }
+        },
+        (error) => this.destroy(error),
+      );
+    },
+  });
+};
+
+// Formats the rows of an iterable, an async iterable or an object-mode
+// Readable as a Readable of CSV bytes. Rows are pulled only as fast as the
+// output is read, and written natively `batchSize` at a time.
+CsvWriter.fromIterable = function fromIterable(rows, options = {}) {
+  const { quoteIf, batchSize = 256, ...writerOptions } = options;
+  let writer;
+  try {
+    writer = new CsvWriter(writerOptions);
+  } catch (error) {
+    const failed = new Readable({ read() {} });
+    process.nextTick(() => failed.destroy(error));
+    return failed;
+  }
+  const iterator = rows[Symbol.asyncIterator]
+    ? rows[Symbol.asyncIterator]()
+    : rows[Symbol.iterator]();
+
+  const pull = async (output) => {
+    const batch = [];
+    let done = false;
+    while (!done && batch.length < batchSize) {
+      const next = await iterator.next();
+      if (next.done) {
+        done = true;
+      } else {
+        batch.push(next.value);
+      }
+    }
+    if (batch.length > 0) {
+      output.push(writer.writeBuffer(batch, quoteIf));
+    }
+    if (done) {
+      // The end of the compressed stream, with `compression`
+      const rest = writer.finish();
+      if (rest.length > 0) {
+        output.push(rest);
+      }
+      output.push(null);
+    }
+  };
+
+  return new Readable({
+    read() {
+      pull(this).catch((error) => this.destroy(error));
+    },
+    destroy(error, callback) {
+      // Let the source clean up, e.g. destroy a Readable
+      Promise.resolve(iterator.return?.()).then(
+        () => callback(error),
+        () => callback(error),
+      );
+    },
+  });
+};
+
+// Turn rows packed by CsvParser#pushPacked() or #flushPacked() back into
+// objects. Works on any thread the batch was transferred to.
+module.exports.unpackRows = function unpackRows({ buffer, columns, rest }) {
+  const bytes = Buffer.from(buffer);
+  const rows = [];
+  let offset = 0;
+  while (offset < bytes.length) {
+    const fields = bytes.readUInt32LE(offset);
+    offset += 4;
+    const row = {};
+    for (let i = 0; i < fields; i++) {
+      const column = columns[bytes.readUInt32LE(offset)];
+      const length = bytes.readUInt32LE(offset + 4);
+      offset += 8;
+      const value = bytes.toString("utf8", offset, offset + length);
+      if (column === rest) {
+        (row[column] ??= []).push(value);
+      } else {
+        row[column] = value;
+      }
+      offset += length;
+    }
+    rows.push(row);
+  }
+  return rows;
+};
+
+// Drain every stream that is still parsing. Resolves once all of them have
+// flushed their buffered rows or, after `timeout` ms, been destroyed.
+// `onCheckpoint(checkpoint, stream)` is called and awaited for each stream
+// that finished, with where to resume its source; a destroyed stream gets
+// none, as rows it had parsed may never have been read.
+module.exports.drainAll = function drainAll({ timeout, onCheckpoint } = {}) {
+  return Promise.all(
+    Array.from(activeStreams, async (stream) => {
+      await stream.drain({ timeout });
+      if (onCheckpoint && stream.writableFinished) {
+        await onCheckpoint(stream.getCheckpoint(), stream);
+      }
+    }),
+  );
+};
+
+// Drain all streams when one of `signals` is received, then re-raise the
+// signal so the process exits the way it would have without the hook. The
+// handlers are removed first, so a second signal exits at once.
+// Returns a function that removes the handlers again.
+module.exports.handleShutdown = function handleShutdown({
+  signals = ["SIGTERM", "SIGINT"],
+  timeout = 10000,
+  onCheckpoint,
+} = {}) {
+  const handlers = signals.map((signal) => {
+    const handler = () => {
+      uninstall();
+      module.exports
+        .drainAll({ timeout, onCheckpoint })
+        .finally(() => process.kill(process.pid, signal));
+    };
+    process.once(signal, handler);
+    return [signal, handler];
+  });
+
+  function uninstall() {
+    for (const [signal, handler] of handlers) {
+      process.off(signal, handler);
+    }
+  }
+
+  return uninstall;
+};
+
 // Also export the native parser class for advanced usage
 module.exports.CsvParser = CsvParser;
+module.exports.CsvParserStream = CsvParserStream;
+module.exports.CsvWriter = CsvWriter;
+module.exports.benchmark = benchmark;
+module.exports.inferSchema = inferSchema;
+module.exports.countRows = countRows;
+module.exports.toJson = toJson;
+module.exports.toSqlite = toSqlite;
+module.exports.findRecordBoundaries = findRecordBoundaries;
+module.exports.distinct = distinct;
+module.exports.head = head;
+module.exports.readHeaders = readHeaders;
+module.exports.resolveOptions = resolveOptions;
+module.exports.sample = sample;
+module.exports.CsvIndex = CsvIndex;
+module.exports.CsvStats = CsvStats;
+module.exports.CsvValidator = CsvValidator;
+module.exports.CsvJoiner = CsvJoiner;
+module.exports.CsvReshaper = CsvReshaper;
+module.exports.sortBy = sortBy;
+module.exports.diff = diff;
+module.exports.split = split;
+module.exports.CsvAggregator = CsvAggregator;
+module.exports.CsvRewriter = CsvRewriter;
+module.exports.Transcoder = Transcoder;
+module.exports.CsvFileReader = CsvFileReader;
+module.exports.CsvParseThread = CsvParseThread;
+module.exports.ParserTemplate = ParserTemplate;
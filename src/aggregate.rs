@@ -0,0 +1,354 @@
+use color_eyre::eyre::{eyre, Result};
+use std::collections::HashMap;
+
+use crate::parser::CsvParserOptions;
+use crate::reader::Parser;
+use crate::schema::is_float;
+
+/// How the values of a column are combined within a group
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+  /// Rows with a non-empty value
+  Count,
+  Sum,
+  Min,
+  Max,
+  Mean,
+}
+
+impl Metric {
+  pub fn parse(name: &str) -> Result<Self> {
+    match name {
+      "count" => Ok(Metric::Count),
+      "sum" => Ok(Metric::Sum),
+      "min" => Ok(Metric::Min),
+      "max" => Ok(Metric::Max),
+      "mean" => Ok(Metric::Mean),
+      _ => Err(eyre!(
+        "Unknown metric \"{}\", expected count, sum, min, max or mean",
+        name
+      )),
+    }
+  }
+
+  pub fn as_str(self) -> &'static str {
+    match self {
+      Metric::Count => "count",
+      Metric::Sum => "sum",
+      Metric::Min => "min",
+      Metric::Max => "max",
+      Metric::Mean => "mean",
+    }
+  }
+}
+
+/// One output value: `metric` over the values of `column`, stored as `name`
+#[derive(Debug, Clone)]
+pub struct MetricSpec {
+  pub name: String,
+  pub column: String,
+  pub metric: Metric,
+}
+
+/// One group of the result
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group {
+  /// The group-by values, in `group_by` order
+  pub keys: Vec<String>,
+  /// One value per metric, in metric order. `None` for sums, minimums,
+  /// maximums and means of groups without numeric values.
+  pub values: Vec<Option<f64>>,
+}
+
+/// Groups rows by some columns and aggregates others while streaming,
+/// without building rows. Only one accumulator per group and metric is
+/// kept, so memory grows with the number of groups. Missing and null cells
+/// count as empty.
+pub struct Aggregator {
+  parser: Parser,
+  groups: Groups,
+}
+
+struct Groups {
+  group_by: Vec<String>,
+  metrics: Vec<MetricSpec>,
+  // Cell indexes of the group-by and metric columns, once headers are known
+  columns: Option<(Vec<usize>, Vec<usize>)>,
+  // Groups in order of first appearance
+  list: Vec<(Vec<String>, Vec<Accumulator>)>,
+  index: HashMap<Vec<String>, usize>,
+}
+
+#[derive(Clone, Copy)]
+struct Accumulator {
+  count: u64,
+  numeric: u64,
+  sum: f64,
+  min: f64,
+  max: f64,
+}
+
+impl Aggregator {
+  pub fn new(options: CsvParserOptions, group_by: Vec<String>, metrics: Vec<MetricSpec>) -> Self {
+    Self {
+      parser: Parser::new(options),
+      groups: Groups {
+        group_by,
+        metrics,
+        columns: None,
+        list: Vec::new(),
+        index: HashMap::new(),
+      },
+    }
+  }
+
+  /// Adds a chunk of input to the aggregates
+  pub fn feed(&mut self, chunk: &[u8]) -> Result<()> {
+    self.groups.add(&mut self.parser, chunk, false)
+  }
+
+  /// Ends the input, counting a last row without a trailing newline
+  pub fn finish(&mut self) -> Result<()> {
+    self.groups.add(&mut self.parser, &[], true)
+  }
+
+  pub fn group_by(&self) -> &[String] {
+    &self.groups.group_by
+  }
+
+  pub fn metrics(&self) -> &[MetricSpec] {
+    &self.groups.metrics
+  }
+
+  /// The aggregates so far, one group per distinct group-by value in order
+  /// of first appearance
+  pub fn groups(&self) -> Vec<Group> {
+    let metrics = &self.groups.metrics;
+    self
+      .groups
+      .list
+      .iter()
+      .map(|(keys, accumulators)| Group {
+        keys: keys.clone(),
+        values: accumulators
+          .iter()
+          .zip(metrics)
+          .map(|(acc, spec)| acc.value(spec.metric))
+          .collect(),
+      })
+      .collect()
+  }
+}
+
+impl Groups {
+  fn add(&mut self, parser: &mut Parser, chunk: &[u8], at_end: bool) -> Result<()> {
+    let Self {
+      group_by,
+      metrics,
+      columns,
+      list: groups,
+      index,
+    } = self;
+    parser.load(chunk, at_end)?;
+
+    while let Some(cells) = parser.next_cells(at_end) {
+      let cells = cells?;
+      let tokenizer = &parser.tokenizer;
+      let buffer = &parser.input.utf8_buffer;
+
+      let (key_columns, metric_columns) = match columns {
+        Some(columns) => columns,
+        None => {
+          // Columns as the parser keys them: blank and `_` headers are left out
+          let headers = parser.headers().unwrap_or_default();
+          let position = |name: &String| {
+            headers
+              .iter()
+              .position(|header| !header.is_empty() && header != "_" && header == name)
+              .ok_or_else(|| eyre!("Column {} not found", name))
+          };
+          let key_columns = group_by.iter().map(position).collect::<Result<_>>()?;
+          let metric_columns = metrics
+            .iter()
+            .map(|spec| position(&spec.column))
+            .collect::<Result<_>>()?;
+          columns.insert((key_columns, metric_columns))
+        }
+      };
+
+      // Cells are taken by column index; missing and null ones are empty
+      let value = |i: usize| match cells.get(i) {
+        Some(&cell) if !tokenizer.is_null(buffer, cell) => tokenizer.cell_value(buffer, cell),
+        _ => Ok("".into()),
+      };
+      let key = key_columns
+        .iter()
+        .map(|&i| Ok(value(i)?.into_owned()))
+        .collect::<Result<Vec<_>>>()?;
+      let group = match index.get(&key) {
+        Some(&group) => group,
+        None => {
+          groups.push((key.clone(), vec![Accumulator::new(); metrics.len()]));
+          index.insert(key, groups.len() - 1);
+          groups.len() - 1
+        }
+      };
+
+      for (acc, &column) in groups[group].1.iter_mut().zip(metric_columns.iter()) {
+        acc.add(&value(column)?);
+      }
+    }
+    Ok(())
+  }
+}
+
+impl Accumulator {
+  fn new() -> Self {
+    Self {
+      count: 0,
+      numeric: 0,
+      sum: 0.0,
+      min: f64::INFINITY,
+      max: f64::NEG_INFINITY,
+    }
+  }
+
+  fn add(&mut self, value: &str) {
+    if value.is_empty() {
+      return;
+    }
+    self.count += 1;
+    if is_float(value) {
+      let x: f64 = value.parse().unwrap_or_default();
+      self.numeric += 1;
+      self.sum += x;
+      self.min = self.min.min(x);
+      self.max = self.max.max(x);
+    }
+  }
+
+  fn value(&self, metric: Metric) -> Option<f64> {
+    let numeric = self.numeric > 0;
+    match metric {
+      Metric::Count => Some(self.count as f64),
+      Metric::Sum => numeric.then_some(self.sum),
+      Metric::Min => numeric.then_some(self.min),
+      Metric::Max => numeric.then_some(self.max),
+      Metric::Mean => numeric.then(|| self.sum / self.numeric as f64),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn spec(column: &str, metric: Metric) -> MetricSpec {
+    MetricSpec {
+      name: format!("{}_{}", column, metric.as_str()),
+      column: column.to_string(),
+      metric,
+    }
+  }
+
+  fn aggregate(input: &str, group_by: &[&str], metrics: Vec<MetricSpec>) -> Result<Vec<Group>> {
+    aggregate_with(input, CsvParserOptions::default(), group_by, metrics)
+  }
+
+  fn aggregate_with(
+    input: &str,
+    options: CsvParserOptions,
+    group_by: &[&str],
+    metrics: Vec<MetricSpec>,
+  ) -> Result<Vec<Group>> {
+    let group_by = group_by.iter().map(|s| s.to_string()).collect();
+    let mut aggregator = Aggregator::new(options, group_by, metrics);
+    let (head, tail) = input.split_at(input.len() / 2);
+    aggregator.feed(head.as_bytes())?;
+    aggregator.feed(tail.as_bytes())?;
+    aggregator.finish()?;
+    Ok(aggregator.groups())
+  }
+
+  #[test]
+  fn test_group_metrics() {
+    let input = "region,revenue,id\nEU,10,1\nUS,5,2\nEU,,3\nEU,2.5,4\nUS,x,5";
+    let groups = aggregate(
+      input,
+      &["region"],
+      vec![
+        spec("revenue", Metric::Sum),
+        spec("revenue", Metric::Mean),
+        spec("revenue", Metric::Max),
+        spec("id", Metric::Count),
+      ],
+    )
+    .unwrap();
+
+    assert_eq!(
+      groups,
+      [
+        Group {
+          keys: vec!["EU".to_string()],
+          values: vec![Some(12.5), Some(6.25), Some(10.0), Some(3.0)],
+        },
+        Group {
+          keys: vec!["US".to_string()],
+          values: vec![Some(5.0), Some(5.0), Some(5.0), Some(2.0)],
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn test_without_group_by_and_numbers() {
+    let groups = aggregate(
+      "a\nx\ny\n",
+      &[],
+      vec![spec("a", Metric::Count), spec("a", Metric::Min)],
+    )
+    .unwrap();
+
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].values, [Some(2.0), None]);
+  }
+
+  #[test]
+  fn test_columns_by_position() {
+    let group = |key: &str, values: &[Option<f64>]| Group {
+      keys: vec![key.to_string()],
+      values: values.to_vec(),
+    };
+    let metrics = || vec![spec("revenue", Metric::Sum), spec("revenue", Metric::Count)];
+
+    let input = "region,,revenue\nEU,,10\nUS,,5\nEU,,2\n";
+    assert_eq!(
+      aggregate(input, &["region"], metrics()).unwrap(),
+      [
+        group("EU", &[Some(12.0), Some(2.0)]),
+        group("US", &[Some(5.0), Some(1.0)])
+      ]
+    );
+
+    // Skipped and null cells are empty, not replaced by the next cell
+    let input = "region,revenue\n,10\nEU,NULL\nNULL,3\n";
+    let options = CsvParserOptions {
+      skip_empty_fields: true,
+      null_value: Some("NULL".to_string()),
+      ..Default::default()
+    };
+    assert_eq!(
+      aggregate_with(input, options, &["region"], metrics()).unwrap(),
+      [
+        group("", &[Some(13.0), Some(2.0)]),
+        group("EU", &[None, Some(0.0)])
+      ]
+    );
+  }
+
+  #[test]
+  fn test_unknown_column_and_metric() {
+    assert!(aggregate("a\n1\n", &["b"], Vec::new()).is_err());
+    assert!(Metric::parse("median").is_err());
+  }
+}
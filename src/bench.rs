@@ -0,0 +1,264 @@
+use crate::decoder::Decoder;
+use crate::parser::{CsvParser, CsvParserOptions, Utf8Validation};
+use std::time::{Duration, Instant};
+
+/// Timing of one benchmark over the synthetic data
+pub struct BenchmarkReport {
+  pub name: String,
+  pub kind: &'static str,
+  pub bytes: usize,
+  pub rows: usize,
+  pub iterations: u32,
+  pub elapsed: Duration,
+}
+
+impl BenchmarkReport {
+  pub fn bytes_per_second(&self) -> f64 {
+    (self.bytes as f64 * self.iterations as f64) / self.elapsed.as_secs_f64().max(f64::EPSILON)
+  }
+
+  pub fn rows_per_second(&self) -> f64 {
+    (self.rows as f64 * self.iterations as f64) / self.elapsed.as_secs_f64().max(f64::EPSILON)
+  }
+}
+
+/// The shape of the cells in a synthetic data set
+#[derive(Clone, Copy)]
+enum CellShape {
+  Plain,
+  Quoted,
+  Escaped,
+  Unicode,
+  Mixed,
+  // Quoted cells, every other one with escaped quotes
+  AllQuoted,
+}
+
+/// Generates `rows` data rows of `columns` cells after a header row. The
+/// output is deterministic so runs on different machines are comparable.
+fn generate(shape: CellShape, rows: usize, columns: usize) -> Vec<u8> {
+  let mut out = Vec::new();
+  let header: Vec<String> = (0..columns).map(|c| format!("column_{}", c)).collect();
+  out.extend_from_slice(header.join(",").as_bytes());
+  out.push(b'\n');
+
+  for row in 0..rows {
+    for column in 0..columns {
+      if column > 0 {
+        out.push(b',');
+      }
+      let shape = match shape {
+        CellShape::Mixed => match (row + column) % 4 {
+          0 => CellShape::Plain,
+          1 => CellShape::Quoted,
+          2 => CellShape::Escaped,
+          _ => CellShape::Unicode,
+        },
+        CellShape::AllQuoted if column % 2 == 0 => CellShape::Quoted,
+        CellShape::AllQuoted => CellShape::Escaped,
+        shape => shape,
+      };
+      let cell = match shape {
+        CellShape::Plain | CellShape::Mixed | CellShape::AllQuoted => {
+          format!("value{}", row * columns + column)
+        }
+        CellShape::Quoted => format!("\"value, {}\"", row),
+        CellShape::Escaped => format!("\"say \"\"{}\"\"\"", row),
+        CellShape::Unicode => format!("café ☕ {}", row),
+      };
+      out.extend_from_slice(cell.as_bytes());
+    }
+    out.push(b'\n');
+  }
+
+  out
+}
+
+/// Parses `data` line by line the way the N-API layer does, without creating
+/// JS values. Returns the number of rows produced.
+fn parse(data: &[u8], options: CsvParserOptions) -> usize {
+  let validation = options.validate_utf8;
+  if validation == Utf8Validation::Fast && simdutf8::basic::from_utf8(data).is_err() {
+    return 0;
+  }
+
+  let mut parser = CsvParser::new(options);
+  let mut rows = 0;
+  let mut start = 0;
+  let mut bytes = 0;
+
+  for end in newlines(data) {
+    if let Ok(Some(cells)) = parser.parse_record(data, start, end + 1) {
+      for (_, _, cell) in parser.row_fields(&cells) {
+        bytes += match validation {
+          Utf8Validation::Full => parser.cell_value(data, cell).map_or(0, |v| v.len()),
+          _ => parser.cell_bytes(data, cell).len(),
+        };
+      }
+      rows += 1;
+    }
+    start = end + 1;
+  }
+
+  std::hint::black_box(bytes);
+  rows
+}
+
+/// Parses `data` as `push()` would, decoding UTF-16 or other input to UTF-8
+/// first, without creating JS values. Returns the number of rows produced.
+pub fn parse_fixture(data: &[u8], options: CsvParserOptions) -> usize {
+  let mut decoder = Decoder::new(None, false);
+  decoder.buffer.extend_from_slice(data);
+  if decoder.decode(false, true).is_err() {
+    return 0;
+  }
+  parse(&decoder.utf8_buffer, options)
+}
+
+// UTF-16LE with a BOM
+fn utf16le(data: &[u8]) -> Vec<u8> {
+  let text = String::from_utf8_lossy(data);
+  let mut out = vec![0xFF, 0xFE];
+  out.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+  out
+}
+
+fn newlines(data: &[u8]) -> impl Iterator<Item = usize> + '_ {
+  data
+    .iter()
+    .enumerate()
+    .filter(|(_, &b)| b == b'\n')
+    .map(|(i, _)| i)
+}
+
+fn time(
+  name: &str,
+  kind: &'static str,
+  data: &[u8],
+  iterations: u32,
+  run: impl Fn(&[u8]) -> usize,
+) -> BenchmarkReport {
+  // Warm up caches and the allocator before timing
+  let rows = run(data);
+
+  let started = Instant::now();
+  for _ in 0..iterations {
+    run(data);
+  }
+
+  BenchmarkReport {
+    name: name.to_string(),
+    kind,
+    bytes: data.len(),
+    rows,
+    iterations,
+    elapsed: started.elapsed(),
+  }
+}
+
+type Configuration = (&'static str, fn() -> CsvParserOptions);
+
+/// Runs the micro benchmarks (one cell shape each, default options) and the
+/// macro benchmarks (mixed data under each parser configuration).
+pub fn run(rows: usize, columns: usize, iterations: u32) -> Vec<BenchmarkReport> {
+  let mut reports = Vec::new();
+
+  let micro = [
+    ("plain cells", CellShape::Plain),
+    ("quoted cells", CellShape::Quoted),
+    ("escaped quotes", CellShape::Escaped),
+    ("unicode cells", CellShape::Unicode),
+  ];
+  for (name, shape) in micro {
+    let data = generate(shape, rows, columns);
+    reports.push(time(name, "micro", &data, iterations, |data| {
+      parse(data, CsvParserOptions::default())
+    }));
+  }
+
+  let data = generate(CellShape::Mixed, rows, columns);
+  let configurations: [Configuration; 5] = [
+    ("default", CsvParserOptions::default),
+    ("validateUtf8: fast", || CsvParserOptions {
+      validate_utf8: Utf8Validation::Fast,
+      ..Default::default()
+    }),
+    ("validateUtf8: off", || CsvParserOptions {
+      validate_utf8: Utf8Validation::Off,
+      ..Default::default()
+    }),
+    ("trim", || CsvParserOptions {
+      ltrim: true,
+      rtrim: true,
+      ..Default::default()
+    }),
+    ("relaxQuotes", || CsvParserOptions {
+      relax_quotes: true,
+      ..Default::default()
+    }),
+  ];
+  for (name, options) in configurations {
+    reports.push(time(name, "macro", &data, iterations, |data| {
+      parse(data, options())
+    }));
+  }
+
+  for (name, data) in fixtures(rows, columns) {
+    reports.push(time(name, "fixture", &data, iterations, |data| {
+      parse_fixture(data, CsvParserOptions::default())
+    }));
+  }
+
+  reports
+}
+
+/// Inputs shaped like common real files, by name: quoted-heavy cells, wide
+/// rows (`columns * 16` cells, a sixteenth of the rows), CRLF line endings
+/// and UTF-16LE with a BOM. Parse them with `parse_fixture`.
+pub fn fixtures(rows: usize, columns: usize) -> Vec<(&'static str, Vec<u8>)> {
+  let mixed = generate(CellShape::Mixed, rows, columns);
+  let wide_rows = (rows / 16).max(1);
+  let crlf = String::from_utf8_lossy(&mixed).replace('\n', "\r\n");
+  vec![
+    (
+      "quoted-heavy",
+      generate(CellShape::AllQuoted, rows, columns),
+    ),
+    (
+      "wide rows",
+      generate(CellShape::Mixed, wide_rows, columns * 16),
+    ),
+    ("CRLF line endings", crlf.into_bytes()),
+    ("UTF-16LE input", utf16le(&mixed)),
+  ]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_generated_data_parses() {
+    for shape in [
+      CellShape::Plain,
+      CellShape::Quoted,
+      CellShape::Escaped,
+      CellShape::Unicode,
+      CellShape::Mixed,
+      CellShape::AllQuoted,
+    ] {
+      let data = generate(shape, 10, 4);
+      assert_eq!(parse(&data, CsvParserOptions::default()), 10);
+    }
+  }
+
+  #[test]
+  fn test_run_reports_every_configuration() {
+    let reports = run(5, 3, 1);
+    assert_eq!(reports.iter().filter(|r| r.kind == "micro").count(), 4);
+    assert_eq!(reports.iter().filter(|r| r.kind == "macro").count(), 5);
+    assert_eq!(reports.iter().filter(|r| r.kind == "fixture").count(), 4);
+    assert!(reports.iter().all(|r| r.rows == 5 || r.name == "wide rows"));
+    assert!(reports.iter().any(|r| r.name == "wide rows" && r.rows == 1));
+  }
+}
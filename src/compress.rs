@@ -0,0 +1,148 @@
+use color_eyre::eyre::{eyre, Result};
+use flate2::write::GzEncoder;
+use std::io::Write;
+
+/// A compression format and level for written output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+  /// Gzip, level 0 (none) to 9
+  Gzip(u32),
+  /// Zstandard, level 1 to 22
+  Zstd(i32),
+}
+
+impl Compression {
+  /// Gzip at level 6, as `gzip` uses by default
+  pub const GZIP: Compression = Compression::Gzip(6);
+  /// Zstandard at level 3, as `zstd` uses by default
+  pub const ZSTD: Compression = Compression::Zstd(3);
+
+  /// Fails for a level outside the range of the format
+  pub fn check(self) -> Result<Self> {
+    match self {
+      Compression::Gzip(level) if level > 9 => Err(eyre!(
+        "compression.level must be between 0 and 9 for gzip, got {}",
+        level
+      )),
+      Compression::Zstd(level) if !(1..=22).contains(&level) => Err(eyre!(
+        "compression.level must be between 1 and 22 for zstd, got {}",
+        level
+      )),
+      _ => Ok(self),
+    }
+  }
+}
+
+enum Stream {
+  Gzip(GzEncoder<Vec<u8>>),
+  Zstd(zstd::stream::write::Encoder<'static, Vec<u8>>),
+}
+
+/// Compresses output as it is written. Each call returns the compressed
+/// bytes ready so far, which may be none; `finish` returns the rest and the
+/// trailer, after which the next output starts a new gzip member or zstd
+/// frame. Concatenated members and frames decompress as one stream.
+pub struct Compressor {
+  compression: Compression,
+  stream: Option<Stream>,
+}
+
+impl Compressor {
+  pub fn new(compression: Compression) -> Result<Self> {
+    Ok(Self {
+      compression: compression.check()?,
+      stream: None,
+    })
+  }
+
+  /// Compresses `bytes`, returning the compressed output ready so far
+  pub fn compress(&mut self, bytes: &[u8]) -> Result<Vec<u8>> {
+    if bytes.is_empty() {
+      return Ok(Vec::new());
+    }
+    let stream = match &mut self.stream {
+      Some(stream) => stream,
+      None => self.stream.insert(match self.compression {
+        Compression::Gzip(level) => {
+          Stream::Gzip(GzEncoder::new(Vec::new(), flate2::Compression::new(level)))
+        }
+        Compression::Zstd(level) => {
+          Stream::Zstd(zstd::stream::write::Encoder::new(Vec::new(), level)?)
+        }
+      }),
+    };
+    let out = match stream {
+      Stream::Gzip(encoder) => {
+        encoder.write_all(bytes)?;
+        encoder.get_mut()
+      }
+      Stream::Zstd(encoder) => {
+        encoder.write_all(bytes)?;
+        encoder.get_mut()
+      }
+    };
+    Ok(std::mem::take(out))
+  }
+
+  /// Ends the compressed stream, returning the rest of it. Empty if nothing
+  /// was compressed since the last call.
+  pub fn finish(&mut self) -> Result<Vec<u8>> {
+    Ok(match self.stream.take() {
+      None => Vec::new(),
+      Some(Stream::Gzip(encoder)) => encoder.finish()?,
+      Some(Stream::Zstd(encoder)) => encoder.finish()?,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::Read;
+
+  fn round_trip(compression: Compression) -> Vec<u8> {
+    let mut compressor = Compressor::new(compression).unwrap();
+    let mut out = Vec::new();
+    for _ in 0..1000 {
+      out.extend(compressor.compress(b"id,name\n1,Ann\n").unwrap());
+    }
+    out.extend(compressor.finish().unwrap());
+    // A second stream follows the first
+    out.extend(compressor.compress(b"2,Bob\n").unwrap());
+    out.extend(compressor.finish().unwrap());
+    assert!(compressor.finish().unwrap().is_empty());
+    out
+  }
+
+  #[test]
+  fn test_gzip() {
+    let out = round_trip(Compression::GZIP);
+    let mut text = String::new();
+    flate2::read::MultiGzDecoder::new(&out[..])
+      .read_to_string(&mut text)
+      .unwrap();
+    assert_eq!(text, "id,name\n1,Ann\n".repeat(1000) + "2,Bob\n");
+    assert!(out.len() < 1000);
+  }
+
+  #[test]
+  fn test_zstd() {
+    let out = round_trip(Compression::ZSTD);
+    let text = zstd::stream::decode_all(&out[..]).unwrap();
+    assert_eq!(
+      text,
+      ("id,name\n1,Ann\n".repeat(1000) + "2,Bob\n").as_bytes()
+    );
+  }
+
+  #[test]
+  fn test_levels() {
+    assert!(Compression::Gzip(9).check().is_ok());
+    let error = Compression::Gzip(10).check().unwrap_err();
+    assert_eq!(
+      error.to_string(),
+      "compression.level must be between 0 and 9 for gzip, got 10"
+    );
+    assert!(Compression::Zstd(0).check().is_err());
+  }
+}
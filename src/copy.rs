@@ -0,0 +1,177 @@
+use bumpalo::Bump;
+use color_eyre::eyre::Result;
+
+use crate::parser::{CellRange, CsvParserOptions, RecordTokenizer};
+
+/// The PostgreSQL `COPY` text format: cells are split at the separator (tab
+/// by default) with no quoting, and a backslash escapes the separator, line
+/// breaks and itself. `\b`, `\f`, `\n`, `\r`, `\t` and `\v` are control
+/// characters, `\123` and `\x53` are octal and hex character codes, and a
+/// `\.` line ends the data. A cell of just `\N` is null, with `null_value`
+/// set to match.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CopyTokenizer;
+
+impl RecordTokenizer for CopyTokenizer {
+  // A backslash before a line break makes it part of the cell
+  fn record_end(&self, options: &CsvParserOptions, buffer: &[u8], start: usize) -> Option<usize> {
+    let mut i = start;
+    while i < buffer.len() {
+      match buffer[i] {
+        b'\\' => i += 1,
+        byte if byte == options.newline => return Some(i + 1),
+        _ => {}
+      }
+      i += 1;
+    }
+    None
+  }
+
+  fn split(
+    &mut self,
+    options: &CsvParserOptions,
+    buffer: &[u8],
+    start: usize,
+    end: usize,
+    cells: &mut Vec<CellRange>,
+  ) -> Result<bool> {
+    // The end-of-data marker
+    if &buffer[start..end] == b"\\." {
+      return Ok(false);
+    }
+
+    let mut cell = CellRange::empty(start);
+    let mut i = start;
+    while i < end {
+      match buffer[i] {
+        b'\\' => {
+          cell.escaped = true;
+          i += 1;
+        }
+        byte if byte == options.separator => {
+          cells.push(CellRange { end: i, ..cell });
+          cell = CellRange::empty(i + 1);
+        }
+        _ => {}
+      }
+      i += 1;
+    }
+    cells.push(CellRange { end, ..cell });
+    Ok(true)
+  }
+
+  fn unescape<'a>(&self, _: &CsvParserOptions, bytes: &[u8], arena: &'a Bump) -> &'a [u8] {
+    let mut result = bumpalo::collections::Vec::with_capacity_in(bytes.len(), arena);
+    let mut i = 0;
+    while i < bytes.len() {
+      if bytes[i] != b'\\' || i + 1 == bytes.len() {
+        result.push(bytes[i]);
+        i += 1;
+        continue;
+      }
+      let (byte, len) = match bytes[i + 1] {
+        b'b' => (0x08, 1),
+        b'f' => (0x0C, 1),
+        b'n' => (b'\n', 1),
+        b'r' => (b'\r', 1),
+        b't' => (b'\t', 1),
+        b'v' => (0x0B, 1),
+        b'0'..=b'7' => character_code(&bytes[i + 1..], 8, 0),
+        b'x' => character_code(&bytes[i + 2..], 16, 1),
+        other => (other, 1),
+      };
+      // Codes past ASCII would not be valid UTF-8 on their own, so they are
+      // kept as written
+      if byte.is_ascii() {
+        result.push(byte);
+      } else {
+        result.extend_from_slice(&bytes[i..i + 1 + len]);
+      }
+      i += 1 + len;
+    }
+    result.into_bump_slice()
+  }
+}
+
+// The byte written by up to 3 octal or 2 hex digits at the start of `digits`,
+// and the length of the escape after its backslash: the digits and `prefix`
+// bytes before them. `\x` without digits is a plain `x`.
+fn character_code(digits: &[u8], radix: u32, prefix: usize) -> (u8, usize) {
+  let max = if radix == 8 { 3 } else { 2 };
+  let count = digits
+    .iter()
+    .take(max)
+    .take_while(|&&digit| (digit as char).is_digit(radix))
+    .count();
+  if count == 0 {
+    return (b'x', 1);
+  }
+  let text = std::str::from_utf8(&digits[..count]).unwrap_or_default();
+  let code = u32::from_str_radix(text, radix).unwrap_or_default();
+  // Octal codes above \377 keep their low 8 bits, as PostgreSQL does
+  ((code & 0xFF) as u8, prefix + count)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::{CsvParser, RecordFormat};
+  use crate::reader::Parser;
+
+  fn cells(line: &str) -> Vec<String> {
+    let mut parser = CsvParser::with_tokenizer(
+      CsvParserOptions {
+        separator: b'\t',
+        headers: Some(Vec::new()),
+        ..Default::default()
+      },
+      Box::new(CopyTokenizer),
+    );
+    let buffer = line.as_bytes();
+    let cells = parser.parse_record(buffer, 0, buffer.len()).unwrap();
+    cells
+      .unwrap_or_default()
+      .into_iter()
+      .map(|cell| parser.cell_value(buffer, cell).unwrap().into_owned())
+      .collect()
+  }
+
+  #[test]
+  fn test_escapes() {
+    assert_eq!(cells("a\\tb\tc\\\\\\nd\t"), ["a\tb", "c\\\nd", ""]);
+    assert_eq!(cells("\\101\\x42\\x\\q\t\\\t"), ["ABxq", "\t"]);
+    // Codes that are not ASCII stay as written
+    assert_eq!(cells("\\351\\xe9"), ["\\351\\xe9"]);
+    assert_eq!(cells("\\."), Vec::<String>::new());
+  }
+
+  #[test]
+  fn test_null_cells() {
+    let mut parser = Parser::new(CsvParserOptions {
+      separator: b'\t',
+      format: RecordFormat::PostgresText,
+      null_value: Some("\\N".to_string()),
+      ..Default::default()
+    });
+    let records = parser
+      .feed(b"a\tb\tc\n\\N\t\\\\N\t\n\\.\n")
+      .unwrap()
+      .collect::<Result<Vec<_>>>()
+      .unwrap();
+
+    // `\\N` is the text `\N`; empty cells are empty strings
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].get("a"), None);
+    assert_eq!(records[0].get("b"), Some("\\N"));
+    assert_eq!(records[0].get("c"), Some(""));
+  }
+
+  #[test]
+  fn test_record_end() {
+    let options = CsvParserOptions::default();
+    let tokenizer = CopyTokenizer;
+    assert_eq!(tokenizer.record_end(&options, b"a\\\nb\nc", 0), Some(5));
+    assert_eq!(tokenizer.record_end(&options, b"\"a\nb\"\n", 0), Some(3));
+    assert_eq!(tokenizer.record_end(&options, b"a\\", 0), None);
+  }
+}
@@ -0,0 +1,195 @@
+use chrono::format::{Fixed, Item, ParseErrorKind, StrftimeItems};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+use color_eyre::eyre::{eyre, Result};
+
+/// Formats tried, in order, for values without a timezone when a column has
+/// no `format`
+const ISO_FORMATS: [&str; 4] = [
+  "%Y-%m-%dT%H:%M:%S%.f",
+  "%Y-%m-%d %H:%M:%S%.f",
+  "%Y-%m-%dT%H:%M",
+  "%Y-%m-%d %H:%M",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Zone {
+  Fixed(FixedOffset),
+  Named(Tz),
+}
+
+/// How the cells of a date column are read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateColumn {
+  format: Option<String>,
+  // Whether `format` reads a UTC offset itself
+  format_has_offset: bool,
+  tz: String,
+  zone: Zone,
+}
+
+/// What a date cell becomes in JS
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DateOutput {
+  /// A `Date` object
+  #[default]
+  Date,
+  /// Milliseconds since the Unix epoch, as a number
+  Millis,
+}
+
+impl DateColumn {
+  /// `format` uses chrono's `strftime` syntax, e.g. `%d/%m/%Y %H:%M`; without
+  /// it ISO 8601 and RFC 3339 values are accepted. `tz` is the zone of values
+  /// that carry no offset: `UTC` (the default), an offset such as `+02:00`
+  /// or an IANA name such as `Europe/Oslo`.
+  pub fn new(format: Option<String>, tz: Option<&str>) -> Result<Self> {
+    if let Some(format) = &format {
+      if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        return Err(eyre!("Invalid date format {:?}", format));
+      }
+    }
+    let tz = tz.unwrap_or("UTC");
+    let zone = if tz.eq_ignore_ascii_case("UTC") || tz == "Z" {
+      Zone::Fixed(FixedOffset::east_opt(0).unwrap())
+    } else if let Ok(offset) = tz.parse::<FixedOffset>() {
+      Zone::Fixed(offset)
+    } else {
+      Zone::Named(
+        tz.parse::<Tz>()
+          .map_err(|_| eyre!("Unknown time zone {:?}", tz))?,
+      )
+    };
+
+    Ok(Self {
+      format_has_offset: format.as_deref().is_some_and(reads_offset),
+      format,
+      tz: tz.to_string(),
+      zone,
+    })
+  }
+
+  pub fn format(&self) -> Option<&str> {
+    self.format.as_deref()
+  }
+
+  pub fn tz(&self) -> &str {
+    &self.tz
+  }
+
+  /// Milliseconds since the Unix epoch, or `None` if `value` does not match
+  /// the format or names a local time that does not exist in the zone
+  pub fn parse(&self, value: &str) -> Option<i64> {
+    let value = value.trim();
+    let Some(format) = &self.format else {
+      if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Some(datetime.timestamp_millis());
+      }
+      if let Some(naive) = ISO_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+      {
+        return self.localize(naive);
+      }
+      let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+      return self.localize(date.and_hms_opt(0, 0, 0)?);
+    };
+
+    if self.format_has_offset {
+      if let Ok(datetime) = DateTime::parse_from_str(value, format) {
+        return Some(datetime.timestamp_millis());
+      }
+    }
+    match NaiveDateTime::parse_from_str(value, format) {
+      Ok(naive) => self.localize(naive),
+      // A format without a time of day
+      Err(e) if e.kind() == ParseErrorKind::NotEnough => {
+        let date = NaiveDate::parse_from_str(value, format).ok()?;
+        self.localize(date.and_hms_opt(0, 0, 0)?)
+      }
+      Err(_) => None,
+    }
+  }
+
+  fn localize(&self, naive: NaiveDateTime) -> Option<i64> {
+    // An ambiguous time (when clocks go back) is taken as the earlier one
+    let millis = match self.zone {
+      Zone::Fixed(offset) => offset
+        .from_local_datetime(&naive)
+        .earliest()?
+        .timestamp_millis(),
+      Zone::Named(tz) => tz
+        .from_local_datetime(&naive)
+        .earliest()?
+        .timestamp_millis(),
+    };
+    Some(millis)
+  }
+}
+
+fn reads_offset(format: &str) -> bool {
+  StrftimeItems::new(format).any(|item| {
+    matches!(
+      item,
+      Item::Fixed(
+        Fixed::TimezoneOffset
+          | Fixed::TimezoneOffsetColon
+          | Fixed::TimezoneOffsetDoubleColon
+          | Fixed::TimezoneOffsetTripleColon
+          | Fixed::TimezoneOffsetColonZ
+          | Fixed::TimezoneOffsetZ
+          | Fixed::RFC2822
+          | Fixed::RFC3339
+          // `%#z`
+          | Fixed::Internal(_)
+      )
+    )
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_formats_and_zones() {
+    let utc = DateColumn::new(Some("%d/%m/%Y %H:%M".to_string()), None).unwrap();
+    assert_eq!(utc.parse("02/01/2024 03:04"), Some(1_704_164_640_000));
+    assert_eq!(utc.parse("2024-01-02"), None);
+
+    let oslo = DateColumn::new(Some("%d/%m/%Y %H:%M".to_string()), Some("Europe/Oslo")).unwrap();
+    assert_eq!(oslo.parse("02/01/2024 04:04"), Some(1_704_164_640_000));
+    // Clocks went forward at 02:00
+    assert_eq!(oslo.parse("31/03/2024 02:30"), None);
+
+    let offset = DateColumn::new(Some("%Y%m%d".to_string()), Some("-01:00")).unwrap();
+    assert_eq!(offset.parse("20240102"), Some(1_704_157_200_000));
+
+    let zoned = DateColumn::new(Some("%Y-%m-%d %H:%M %z".to_string()), Some("Asia/Tokyo")).unwrap();
+    assert_eq!(
+      zoned.parse("2024-01-02 03:04 +0000"),
+      Some(1_704_164_640_000)
+    );
+  }
+
+  #[test]
+  fn test_iso_values() {
+    let column = DateColumn::new(None, Some("+01:00")).unwrap();
+    assert_eq!(
+      column.parse("2024-01-02T03:04:00Z"),
+      Some(1_704_164_640_000)
+    );
+    assert_eq!(
+      column.parse(" 2024-01-02 04:04:00.5 "),
+      Some(1_704_164_640_500)
+    );
+    assert_eq!(column.parse("2024-01-02"), Some(1_704_150_000_000));
+    assert_eq!(column.parse("yesterday"), None);
+  }
+
+  #[test]
+  fn test_invalid_options() {
+    assert!(DateColumn::new(Some("%Y-%Q".to_string()), None).is_err());
+    assert!(DateColumn::new(None, Some("Mars/Olympus")).is_err());
+  }
+}
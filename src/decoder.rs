@@ -0,0 +1,365 @@
+use chardetng::EncodingDetector;
+use color_eyre::eyre::{eyre, Result};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+
+use crate::ebcdic::CodePage;
+use crate::state::{StateReader, StateWriter};
+
+/// An encoding the parser reads. Besides UTF-8 and UTF-16, input may be
+/// UTF-32 or, when named by the `encoding` option, an ASCII-compatible
+/// WHATWG encoding or an EBCDIC code page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+  Web(&'static Encoding),
+  Utf32Le,
+  Utf32Be,
+  Ebcdic(CodePage),
+}
+
+impl TextEncoding {
+  /// The encoding a label such as `latin1`, `shift_jis`, `utf-32le` or
+  /// `ibm037` names. Encodings that are not ASCII-compatible, such as
+  /// `iso-2022-jp`, are refused: their line breaks could not be found before
+  /// decoding.
+  pub fn for_label(label: &str) -> Result<Self> {
+    match label.to_ascii_lowercase().as_str() {
+      "utf-32le" => return Ok(TextEncoding::Utf32Le),
+      "utf-32be" => return Ok(TextEncoding::Utf32Be),
+      _ => {}
+    }
+    if let Some(page) = CodePage::for_label(label) {
+      return Ok(TextEncoding::Ebcdic(page));
+    }
+
+    let encoding =
+      Encoding::for_label(label.as_bytes()).ok_or_else(|| eyre!("Unknown encoding {}", label))?;
+    if !is_utf(encoding) && !encoding.is_ascii_compatible() {
+      return Err(eyre!(
+        "Cannot parse {} input directly; decode it with a Transcoder first",
+        encoding.name()
+      ));
+    }
+    Ok(TextEncoding::Web(encoding))
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      TextEncoding::Web(encoding) => encoding.name(),
+      TextEncoding::Utf32Le => "UTF-32LE",
+      TextEncoding::Utf32Be => "UTF-32BE",
+      TextEncoding::Ebcdic(page) => page.name,
+    }
+  }
+}
+
+fn is_utf(encoding: &'static Encoding) -> bool {
+  encoding == UTF_8 || encoding == UTF_16LE || encoding == UTF_16BE
+}
+
+// The encoding of input without a BOM, from its first bytes: UTF-8 when
+// they are valid UTF-8 (a sequence cut off at the end of the sample
+// included), otherwise the most likely legacy encoding.
+fn guess_encoding(sample: &[u8], at_end: bool) -> Result<TextEncoding> {
+  match simdutf8::compat::from_utf8(sample) {
+    Ok(_) => return Ok(TextEncoding::Web(UTF_8)),
+    Err(e) if e.error_len().is_none() && !at_end => return Ok(TextEncoding::Web(UTF_8)),
+    Err(_) => {}
+  }
+
+  let mut detector = EncodingDetector::new();
+  detector.feed(sample, at_end);
+  let encoding = detector.guess(None, false);
+  TextEncoding::for_label(encoding.name()).map_err(|_| {
+    eyre!(
+      "Detected {} input, which cannot be parsed directly",
+      encoding.name()
+    )
+  })
+}
+
+// Bytes of input a detected encoding is guessed from
+const DETECT_SAMPLE_LEN: usize = 64 * 1024;
+
+// Byte order marks and the encodings they announce. UTF-32LE comes before
+// UTF-16LE, whose BOM it starts with.
+fn boms() -> [(&'static [u8], TextEncoding); 5] {
+  [
+    (b"\xFF\xFE\x00\x00", TextEncoding::Utf32Le),
+    (b"\x00\x00\xFE\xFF", TextEncoding::Utf32Be),
+    (b"\xEF\xBB\xBF", TextEncoding::Web(UTF_8)),
+    (b"\xFF\xFE", TextEncoding::Web(UTF_16LE)),
+    (b"\xFE\xFF", TextEncoding::Web(UTF_16BE)),
+  ]
+}
+
+/// Input on its way to the tokenizer. Detects a BOM on the first bytes,
+/// converts other encodings to UTF-8 and, when asked, validates decoded
+/// UTF-8 a chunk at a time.
+pub struct Decoder {
+  /// Raw input not yet decoded (e.g. a split UTF-16 code unit)
+  pub buffer: Vec<u8>,
+  /// Decoded input waiting for the rest of its row
+  pub utf8_buffer: Vec<u8>,
+  encoding: TextEncoding,
+  // The encoding given by the `encoding` option. A BOM only switches
+  // encodings without one.
+  configured: Option<TextEncoding>,
+  // Guess the encoding of input without a BOM from its first bytes
+  detect: bool,
+  bom_detected: bool,
+  // Bytes of BOM stripped from the start of the input
+  bom_len: usize,
+  // Length of the prefix of `utf8_buffer` known to be valid UTF-8
+  validated_len: usize,
+}
+
+impl Default for Decoder {
+  fn default() -> Self {
+    Self::new(None, false)
+  }
+}
+
+impl Decoder {
+  /// A decoder for input in `encoding`, or by default UTF-8 unless a BOM
+  /// says otherwise. With `detect` and no `encoding`, input without a BOM
+  /// is held until its first 64 KiB (or all of it) can be looked at to
+  /// guess between UTF-8 and legacy encodings such as Windows-1252 or
+  /// Shift_JIS.
+  pub fn new(encoding: Option<TextEncoding>, detect: bool) -> Self {
+    Self {
+      buffer: Vec::new(),
+      utf8_buffer: Vec::new(),
+      encoding: encoding.unwrap_or(TextEncoding::Web(UTF_8)),
+      configured: encoding,
+      detect: detect && encoding.is_none(),
+      bom_detected: false,
+      bom_len: 0,
+      validated_len: 0,
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.buffer.is_empty() && self.utf8_buffer.is_empty()
+  }
+
+  /// Decodes buffered input into `utf8_buffer`. With `validate`, newly
+  /// decoded bytes are checked in one pass so cells can skip their own
+  /// checks; a sequence split across chunks waits for the next call unless
+  /// this is the end of the input.
+  pub fn decode(&mut self, validate: bool, at_end: bool) -> Result<()> {
+    // Detect encoding from BOM if this is the first chunk
+    if !self.bom_detected {
+      if !self.detect_encoding(at_end)? {
+        return Ok(());
+      }
+      self.bom_detected = true;
+    }
+
+    // Convert to UTF-8 and accumulate in utf8_buffer
+    self.process_encoding(at_end)?;
+    if validate {
+      self.validate_decoded(at_end)?;
+    }
+    Ok(())
+  }
+
+  /// Drops the first `len` bytes of utf8_buffer once their rows are parsed.
+  /// Returns the buffer they were in, which the caller may keep.
+  pub fn consume(&mut self, len: usize) -> Vec<u8> {
+    if len == 0 {
+      return Vec::new();
+    }
+    self.validated_len = self.validated_len.saturating_sub(len);
+    let rest = self.utf8_buffer[len..].to_vec();
+    std::mem::replace(&mut self.utf8_buffer, rest)
+  }
+
+  /// Drops all buffered input, keeping the detected encoding
+  pub fn clear(&mut self) {
+    self.buffer.clear();
+    self.utf8_buffer.clear();
+    self.validated_len = 0;
+  }
+
+  /// Drops all buffered input and forgets the detected encoding
+  pub fn reset(&mut self) {
+    self.clear();
+    self.encoding = self.configured.unwrap_or(TextEncoding::Web(UTF_8));
+    self.bom_detected = false;
+    self.bom_len = 0;
+  }
+
+  /// The encoding detected from the BOM, given or guessed, UTF-8 without
+  /// any of these
+  pub fn encoding(&self) -> TextEncoding {
+    self.encoding
+  }
+
+  /// Whether the input started with a byte order mark
+  pub fn has_bom(&self) -> bool {
+    self.bom_len > 0
+  }
+
+  /// Where decoded byte `offset` of the whole input is in the source, which
+  /// is only known for UTF-8 input
+  pub fn source_offset(&self, offset: u64) -> Option<u64> {
+    (self.encoding == TextEncoding::Web(UTF_8)).then_some(offset + self.bom_len as u64)
+  }
+
+  /// Writes the buffered input and the detected encoding to `state`
+  pub(crate) fn save_state(&self, state: &mut StateWriter) {
+    state.bytes(&self.buffer);
+    state.bytes(&self.utf8_buffer);
+    state.str(self.encoding.name());
+    state.bool(self.bom_detected);
+    state.u64(self.bom_len as u64);
+    state.u64(self.validated_len as u64);
+    state.option(
+      self.configured.map(|encoding| encoding.name()),
+      StateWriter::str,
+    );
+    state.bool(self.detect);
+  }
+
+  pub(crate) fn load_state(state: &mut StateReader) -> Result<Self> {
+    let buffer = state.bytes()?.to_vec();
+    let utf8_buffer = state.bytes()?.to_vec();
+    let encoding =
+      TextEncoding::for_label(&state.string()?).map_err(|_| eyre!("Invalid parser state"))?;
+    let bom_detected = state.bool()?;
+    let bom_len = state.usize()?;
+    let validated_len = state.usize()?;
+    if validated_len > utf8_buffer.len() {
+      return Err(eyre!("Invalid parser state"));
+    }
+    let configured = state.option(|state| TextEncoding::for_label(&state.string()?))?;
+    let detect = state.bool()?;
+
+    Ok(Self {
+      buffer,
+      utf8_buffer,
+      encoding,
+      configured,
+      detect,
+      bom_detected,
+      bom_len,
+      validated_len,
+    })
+  }
+
+  fn validate_decoded(&mut self, at_end: bool) -> Result<()> {
+    if self.encoding != TextEncoding::Web(UTF_8) {
+      // Decoded from another encoding, so valid by construction
+      self.validated_len = self.utf8_buffer.len();
+      return Ok(());
+    }
+
+    match simdutf8::compat::from_utf8(&self.utf8_buffer[self.validated_len..]) {
+      Ok(_) => self.validated_len = self.utf8_buffer.len(),
+      Err(e) if e.error_len().is_none() && !at_end => self.validated_len += e.valid_up_to(),
+      Err(e) => {
+        return Err(eyre!(
+          "UTF-8 conversion error: invalid utf-8 sequence from index {}",
+          self.validated_len + e.valid_up_to()
+        ))
+      }
+    }
+    Ok(())
+  }
+
+  // Strips a BOM from the start of the input and, unless an encoding was
+  // given, switches to the encoding it announces or, with `detect`, guesses
+  // one. Returns false while the input so far could still be the start of a
+  // longer BOM or is too short to guess from.
+  fn detect_encoding(&mut self, at_end: bool) -> Result<bool> {
+    let configured = self.configured;
+    let boms = boms();
+    let candidates = boms
+      .iter()
+      .filter(|(_, encoding)| configured.is_none_or(|configured| configured == *encoding));
+
+    let buffer = &self.buffer;
+    if !at_end
+      && candidates
+        .clone()
+        .any(|(bom, _)| bom.len() > buffer.len() && bom.starts_with(buffer))
+    {
+      return Ok(false);
+    }
+    if let Some((bom, encoding)) = candidates.clone().find(|(bom, _)| buffer.starts_with(bom)) {
+      self.encoding = *encoding;
+      self.bom_len = bom.len();
+      self.buffer.drain(..bom.len());
+    } else if self.detect {
+      if !at_end && buffer.len() < DETECT_SAMPLE_LEN {
+        return Ok(false);
+      }
+      self.encoding = guess_encoding(&buffer[..buffer.len().min(DETECT_SAMPLE_LEN)], at_end)?;
+    }
+    Ok(true)
+  }
+
+  fn process_encoding(&mut self, at_end: bool) -> Result<()> {
+    let unit = match self.encoding {
+      TextEncoding::Web(encoding) if encoding == UTF_8 => {
+        // For UTF-8, just append to utf8_buffer
+        self.utf8_buffer.extend_from_slice(&self.buffer);
+        self.buffer.clear();
+        return Ok(());
+      }
+      TextEncoding::Web(encoding) if encoding == UTF_16LE || encoding == UTF_16BE => 2,
+      TextEncoding::Utf32Le | TextEncoding::Utf32Be => 4,
+      // Variable width, split at line breaks below
+      TextEncoding::Web(encoding) if !encoding.is_single_byte() => 0,
+      TextEncoding::Web(_) | TextEncoding::Ebcdic(_) => 1,
+    };
+    let len = match unit {
+      // A multi-byte character never contains a CR or LF byte, so input up
+      // to the last line break decodes on its own
+      0 if at_end => self.buffer.len(),
+      0 => match self.buffer.iter().rposition(|&b| b == b'\n' || b == b'\r') {
+        Some(end) => end + 1,
+        None => return Ok(()),
+      },
+      // Keep complete code units only, saving incomplete bytes for next chunk
+      unit => self.buffer.len() / unit * unit,
+    };
+    let bytes = &self.buffer[..len];
+
+    match self.encoding {
+      TextEncoding::Web(encoding) => {
+        let (decoded, had_errors) = encoding.decode_without_bom_handling(bytes);
+        if had_errors {
+          return Err(eyre!("Encoding conversion error: invalid characters found"));
+        }
+        self.utf8_buffer.extend_from_slice(decoded.as_bytes());
+      }
+      TextEncoding::Utf32Le | TextEncoding::Utf32Be => {
+        for unit in bytes.chunks_exact(4) {
+          let unit = [unit[0], unit[1], unit[2], unit[3]];
+          let code = match self.encoding {
+            TextEncoding::Utf32Le => u32::from_le_bytes(unit),
+            _ => u32::from_be_bytes(unit),
+          };
+          let c = char::from_u32(code)
+            .ok_or_else(|| eyre!("Encoding conversion error: invalid characters found"))?;
+          self
+            .utf8_buffer
+            .extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+        }
+      }
+      TextEncoding::Ebcdic(page) => {
+        for &byte in bytes {
+          let c = page.decode(byte);
+          self
+            .utf8_buffer
+            .extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+        }
+      }
+    }
+
+    // Remove processed bytes, keep any incomplete code unit
+    self.buffer.drain(..len);
+    Ok(())
+  }
+}
@@ -0,0 +1,290 @@
+use color_eyre::eyre::{eyre, Result};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::Hasher;
+
+use crate::state::{StateReader, StateWriter};
+
+/// Which rows count as duplicates of an earlier row
+#[derive(Debug, Clone, Default)]
+pub struct DedupeOptions {
+  /// Columns that identify a row; every cell when empty
+  pub keys: Vec<String>,
+  /// Remember keys in a Bloom filter instead of an exact set, so memory stays
+  /// bounded. A unique row is dropped now and then, at about the given rate.
+  pub bloom: Option<BloomOptions>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BloomOptions {
+  /// Rows the filter is sized for
+  pub capacity: usize,
+  /// Chance of taking a new key for a seen one while under `capacity`
+  pub false_positive_rate: f64,
+}
+
+impl Default for BloomOptions {
+  fn default() -> Self {
+    Self {
+      capacity: 1_000_000,
+      false_positive_rate: 0.001,
+    }
+  }
+}
+
+/// Remembers the keys of the rows seen so far
+pub(crate) struct Dedupe {
+  keys: Vec<String>,
+  // Cell index of each key column, once the headers are known
+  columns: Option<Vec<usize>>,
+  seen: Seen,
+  // Key of the current row, reused between rows
+  key: Vec<u8>,
+  pub(crate) dropped: u64,
+}
+
+enum Seen {
+  Exact {
+    keys: HashSet<Box<[u8]>>,
+    bytes: usize,
+  },
+  Bloom(Bloom),
+}
+
+impl Dedupe {
+  pub(crate) fn new(options: &DedupeOptions) -> Self {
+    let seen = match options.bloom {
+      Some(bloom) => Seen::Bloom(Bloom::new(bloom)),
+      None => Seen::Exact {
+        keys: HashSet::new(),
+        bytes: 0,
+      },
+    };
+    Self {
+      keys: options.keys.clone(),
+      columns: None,
+      seen,
+      key: Vec::new(),
+      dropped: 0,
+    }
+  }
+
+  /// Whether the row with these cell values was seen before. Counts it as
+  /// dropped if so, and remembers it otherwise.
+  pub(crate) fn check<'a>(
+    &mut self,
+    headers: &[String],
+    mut cell: impl FnMut(usize) -> Option<&'a [u8]>,
+    cells: usize,
+  ) -> Result<bool> {
+    if self.columns.is_none() && !self.keys.is_empty() {
+      let columns = self
+        .keys
+        .iter()
+        .map(|key| {
+          headers
+            .iter()
+            .position(|header| header == key)
+            .ok_or_else(|| eyre!("Column {} not found", key))
+        })
+        .collect::<Result<_>>()?;
+      self.columns = Some(columns);
+    }
+
+    // Cells are length-prefixed so `a,bc` and `ab,c` differ
+    self.key.clear();
+    let mut push = |value: &[u8]| {
+      self
+        .key
+        .extend_from_slice(&(value.len() as u32).to_le_bytes());
+      self.key.extend_from_slice(value);
+    };
+    match &self.columns {
+      Some(columns) => {
+        for &i in columns {
+          push(cell(i).unwrap_or_default());
+        }
+      }
+      None => {
+        for i in 0..cells {
+          push(cell(i).unwrap_or_default());
+        }
+      }
+    }
+
+    let duplicate = match &mut self.seen {
+      Seen::Exact { keys, bytes } => {
+        if keys.contains(self.key.as_slice()) {
+          true
+        } else {
+          *bytes += self.key.len();
+          keys.insert(self.key.as_slice().into());
+          false
+        }
+      }
+      Seen::Bloom(bloom) => !bloom.insert(&self.key),
+    };
+    if duplicate {
+      self.dropped += 1;
+    }
+    Ok(duplicate)
+  }
+
+  /// Writes the seen keys and the dropped count to `state`
+  pub(crate) fn save_state(&self, state: &mut StateWriter) {
+    state.option(self.columns.as_ref(), |state, columns| {
+      state.u64(columns.len() as u64);
+      for &column in columns {
+        state.u64(column as u64);
+      }
+    });
+    state.u64(self.dropped);
+    match &self.seen {
+      Seen::Exact { keys, .. } => {
+        state.u8(0);
+        state.u64(keys.len() as u64);
+        for key in keys {
+          state.bytes(key);
+        }
+      }
+      Seen::Bloom(bloom) => {
+        state.u8(1);
+        state.u64(bloom.hashes as u64);
+        state.u64(bloom.bits.len() as u64);
+        for &word in &bloom.bits {
+          state.u64(word);
+        }
+      }
+    }
+  }
+
+  /// Restores what `save_state` wrote into a `Dedupe` made from the same
+  /// options
+  pub(crate) fn load_state(&mut self, state: &mut StateReader) -> Result<()> {
+    self.columns = state.option(|state| (0..state.usize()?).map(|_| state.usize()).collect())?;
+    self.dropped = state.u64()?;
+    match (state.u8()?, &mut self.seen) {
+      (0, Seen::Exact { keys, bytes }) => {
+        for _ in 0..state.usize()? {
+          let key = state.bytes()?;
+          *bytes += key.len();
+          keys.insert(key.into());
+        }
+      }
+      (1, Seen::Bloom(bloom)) => {
+        bloom.hashes = u32::try_from(state.u64()?).map_err(|_| eyre!("Invalid parser state"))?;
+        let words = state.usize()?;
+        bloom.bits = (0..words).map(|_| state.u64()).collect::<Result<_>>()?;
+        if bloom.bits.is_empty() {
+          return Err(eyre!("Invalid parser state"));
+        }
+      }
+      _ => return Err(eyre!("Invalid parser state")),
+    }
+    Ok(())
+  }
+
+  /// Heap bytes held by the seen keys
+  pub(crate) fn heap_size(&self) -> usize {
+    let seen = match &self.seen {
+      Seen::Exact { keys, bytes } => keys.capacity() * std::mem::size_of::<Box<[u8]>>() + bytes,
+      Seen::Bloom(bloom) => bloom.bits.capacity() * std::mem::size_of::<u64>(),
+    };
+    seen + self.key.capacity()
+  }
+}
+
+struct Bloom {
+  bits: Vec<u64>,
+  hashes: u32,
+}
+
+impl Bloom {
+  fn new(options: BloomOptions) -> Self {
+    let capacity = options.capacity.max(1) as f64;
+    let rate = options.false_positive_rate.clamp(1e-9, 0.5);
+    let ln2 = std::f64::consts::LN_2;
+    let bits = (-capacity * rate.ln() / (ln2 * ln2)).ceil().max(64.0);
+    let hashes = (bits / capacity * ln2).round().max(1.0) as u32;
+
+    Self {
+      bits: vec![0; (bits as usize).div_ceil(64)],
+      hashes,
+    }
+  }
+
+  // Sets the bits for `key`. Returns whether any was unset, i.e. the key is
+  // certainly new.
+  fn insert(&mut self, key: &[u8]) -> bool {
+    // Double hashing: probe i is h1 + i * h2
+    let mut hasher = DefaultHasher::new();
+    hasher.write(key);
+    let h1 = hasher.finish();
+    hasher.write_u8(0xff);
+    let h2 = hasher.finish() | 1;
+
+    let len = self.bits.len() as u64 * 64;
+    let mut new = false;
+    for i in 0..self.hashes as u64 {
+      let bit = h1.wrapping_add(i.wrapping_mul(h2)) % len;
+      let (word, mask) = ((bit / 64) as usize, 1 << (bit % 64));
+      new |= self.bits[word] & mask == 0;
+      self.bits[word] |= mask;
+    }
+    new
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn check(dedupe: &mut Dedupe, row: &[&str]) -> bool {
+    let headers = ["a".to_string(), "b".to_string()];
+    dedupe
+      .check(&headers, |i| row.get(i).map(|v| v.as_bytes()), row.len())
+      .unwrap()
+  }
+
+  #[test]
+  fn test_full_row_and_key_columns() {
+    let mut rows = Dedupe::new(&DedupeOptions::default());
+    assert!(!check(&mut rows, &["1", "x"]));
+    assert!(!check(&mut rows, &["1", "y"]));
+    assert!(check(&mut rows, &["1", "x"]));
+    assert!(!check(&mut rows, &["1x", ""]));
+
+    let mut keys = Dedupe::new(&DedupeOptions {
+      keys: vec!["a".to_string()],
+      bloom: None,
+    });
+    assert!(!check(&mut keys, &["1", "x"]));
+    assert!(check(&mut keys, &["1", "y"]));
+    assert_eq!((rows.dropped, keys.dropped), (1, 1));
+  }
+
+  #[test]
+  fn test_bloom_filter() {
+    let mut dedupe = Dedupe::new(&DedupeOptions {
+      keys: Vec::new(),
+      bloom: Some(BloomOptions {
+        capacity: 1000,
+        false_positive_rate: 0.01,
+      }),
+    });
+    let dropped = (0..1000)
+      .filter(|i| check(&mut dedupe, &[&i.to_string()]))
+      .count();
+    assert!(dropped < 30);
+    assert!(check(&mut dedupe, &["7"]));
+  }
+
+  #[test]
+  fn test_missing_key_column() {
+    let mut dedupe = Dedupe::new(&DedupeOptions {
+      keys: vec!["c".to_string()],
+      bloom: None,
+    });
+    assert!(dedupe.check(&[], |_| None, 0).is_err());
+  }
+}
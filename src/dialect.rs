@@ -0,0 +1,103 @@
+use std::cmp::Reverse;
+
+use crate::lint::{LineEnding, CANDIDATES};
+
+/// Lines looked at when guessing the separator
+const SAMPLE_LINES: usize = 20;
+
+/// How a sample of CSV appears to be written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dialect {
+  /// The candidate separator that splits the most sampled lines into as
+  /// many fields as the first line, `None` if the first line has none
+  pub separator: Option<u8>,
+  /// How the first line ends, `None` if the sample has no line break
+  pub line_ending: Option<LineEnding>,
+}
+
+/// Guesses the separator and line ending of `sample`, the start of some
+/// UTF-8 CSV. Separators and line breaks inside `quote`d fields do not
+/// count; blank lines are ignored.
+pub fn sniff_dialect(sample: &[u8], quote: u8) -> Dialect {
+  let mut lines: Vec<[usize; CANDIDATES.len()]> = Vec::new();
+  let mut counts = [0; CANDIDATES.len()];
+  let mut blank = true;
+  let mut line_ending = None;
+  let mut quoted = false;
+  let mut i = 0;
+
+  while i < sample.len() && lines.len() < SAMPLE_LINES {
+    let byte = sample[i];
+    i += 1;
+    if byte == quote {
+      quoted = !quoted;
+      blank = false;
+      continue;
+    }
+    let ending = match byte {
+      _ if quoted => continue,
+      b'\n' => LineEnding::Lf,
+      b'\r' if sample.get(i) == Some(&b'\n') => {
+        i += 1;
+        LineEnding::CrLf
+      }
+      b'\r' => LineEnding::Cr,
+      _ => {
+        if let Some(c) = CANDIDATES.iter().position(|&c| c == byte) {
+          counts[c] += 1;
+        }
+        blank = false;
+        continue;
+      }
+    };
+    line_ending.get_or_insert(ending);
+    if !blank {
+      lines.push(std::mem::take(&mut counts));
+      blank = true;
+    }
+  }
+  // A sample without a line break is one partial line
+  if lines.is_empty() && !blank {
+    lines.push(counts);
+  }
+
+  let separator = lines.first().and_then(|first| {
+    (0..CANDIDATES.len())
+      .filter(|&c| first[c] > 0)
+      .max_by_key(|&c| {
+        let even = lines.iter().filter(|line| line[c] == first[c]).count();
+        (even, first[c], Reverse(c))
+      })
+      .map(|c| CANDIDATES[c])
+  });
+
+  Dialect {
+    separator,
+    line_ending,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_sniff_separator() {
+    let separator = |sample: &str| sniff_dialect(sample.as_bytes(), b'"').separator;
+    assert_eq!(separator("a,b,c\n1,2,3\n"), Some(b','));
+    assert_eq!(separator("a;b;c\n1,5;2,5;3\n"), Some(b';'));
+    assert_eq!(separator("a\tb\n\n\"x\ty\"\t2\n"), Some(b'\t'));
+    assert_eq!(separator("a|b,c\n1|2\n3|4\n"), Some(b'|'));
+    assert_eq!(separator("a;b\n1;2"), Some(b';'));
+    assert_eq!(separator("name\nAnn\n"), None);
+    assert_eq!(separator(""), None);
+  }
+
+  #[test]
+  fn test_sniff_line_ending() {
+    let line_ending = |sample: &str| sniff_dialect(sample.as_bytes(), b'"').line_ending;
+    assert_eq!(line_ending("a\r\nb\n"), Some(LineEnding::CrLf));
+    assert_eq!(line_ending("\"a\r\nb\"\rc\n"), Some(LineEnding::Cr));
+    assert_eq!(line_ending("a,b"), None);
+  }
+}
@@ -0,0 +1,286 @@
+use color_eyre::eyre::{eyre, Result};
+use std::cmp::Ordering;
+use std::io::Read;
+use std::path::PathBuf;
+
+use crate::parser::CsvParserOptions;
+use crate::reader::Record;
+use crate::sort::{sort_rows, SortOptions, SortedRows};
+
+pub struct DiffOptions {
+  /// Header of the column that identifies a row in both inputs
+  pub key: String,
+  /// Approximate bytes of rows each input holds in memory while it is
+  /// sorted by key, as for `SortOptions`
+  pub memory_limit: usize,
+  /// Where sort runs go; the system temp directory by default
+  pub temp_dir: Option<PathBuf>,
+  /// Options for parsing both inputs
+  pub parser: CsvParserOptions,
+}
+
+impl Default for DiffOptions {
+  fn default() -> Self {
+    Self {
+      key: String::new(),
+      memory_limit: SortOptions::default().memory_limit,
+      temp_dir: None,
+      parser: CsvParserOptions::default(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+  /// The key is only in the new input
+  Added,
+  /// The key is only in the old input
+  Removed,
+  /// The key is in both inputs with different values
+  Changed,
+}
+
+#[derive(Debug)]
+pub struct Change {
+  pub kind: ChangeKind,
+  pub key: String,
+  /// The row in the old input; none for added rows
+  pub before: Option<Record>,
+  /// The row in the new input; none for removed rows
+  pub after: Option<Record>,
+  /// For changed rows, the columns whose values differ
+  pub columns: Vec<String>,
+}
+
+/// The differences between two inputs matched by a key column, in key
+/// order. Both inputs are sorted by key with `sort_rows` first, so memory
+/// stays bounded whatever their size.
+///
+/// Keys compare as strings. Rows sharing a key are paired in input order,
+/// and the extra ones are added or removed. A column missing from one input
+/// counts as empty.
+pub struct Diff {
+  key: String,
+  columns: Vec<String>,
+  old: SortedRows,
+  new: SortedRows,
+  // The next row of each input
+  old_next: Option<Record>,
+  new_next: Option<Record>,
+  unchanged: u64,
+}
+
+/// Compares `old` with `new`, reading each once
+pub fn diff(old: impl Read, new: impl Read, options: DiffOptions) -> Result<Diff> {
+  let mut old = sort_by_key(old, &options).map_err(|e| eyre!("Old input: {}", e))?;
+  let mut new = sort_by_key(new, &options).map_err(|e| eyre!("New input: {}", e))?;
+
+  // Rows are keyed without blank and `_` headers, so those are not compared
+  let mut columns: Vec<String> = Vec::new();
+  for header in old.headers().iter().chain(new.headers()) {
+    if !header.is_empty() && header != "_" && !columns.contains(header) {
+      columns.push(header.clone());
+    }
+  }
+  Ok(Diff {
+    key: options.key,
+    columns,
+    old_next: old.next().transpose()?,
+    new_next: new.next().transpose()?,
+    old,
+    new,
+    unchanged: 0,
+  })
+}
+
+fn sort_by_key(input: impl Read, options: &DiffOptions) -> Result<SortedRows> {
+  let options = SortOptions {
+    column: options.key.clone(),
+    memory_limit: options.memory_limit,
+    temp_dir: options.temp_dir.clone(),
+    parser: options.parser.clone(),
+    ..Default::default()
+  };
+  sort_rows(input, options)
+}
+
+impl Diff {
+  /// The columns compared: those of the old input, then those only in the
+  /// new one
+  pub fn columns(&self) -> &[String] {
+    &self.columns
+  }
+
+  /// Rows found in both inputs with the same values so far
+  pub fn unchanged(&self) -> u64 {
+    self.unchanged
+  }
+
+  fn next_change(&mut self) -> Result<Option<Change>> {
+    loop {
+      let ordering = match (&self.old_next, &self.new_next) {
+        (None, None) => return Ok(None),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(old), Some(new)) => value(old, &self.key).cmp(value(new, &self.key)),
+      };
+
+      let (before, after) = match ordering {
+        Ordering::Less => (self.next_old()?, None),
+        Ordering::Greater => (None, self.next_new()?),
+        Ordering::Equal => (self.next_old()?, self.next_new()?),
+      };
+      let change = match (before, after) {
+        (Some(before), None) => Change {
+          kind: ChangeKind::Removed,
+          key: value(&before, &self.key).to_string(),
+          before: Some(before),
+          after: None,
+          columns: Vec::new(),
+        },
+        (None, Some(after)) => Change {
+          kind: ChangeKind::Added,
+          key: value(&after, &self.key).to_string(),
+          before: None,
+          after: Some(after),
+          columns: Vec::new(),
+        },
+        (Some(before), Some(after)) => {
+          let columns: Vec<String> = self
+            .columns
+            .iter()
+            .filter(|column| value(&before, column) != value(&after, column))
+            .cloned()
+            .collect();
+          if columns.is_empty() {
+            self.unchanged += 1;
+            continue;
+          }
+          Change {
+            kind: ChangeKind::Changed,
+            key: value(&after, &self.key).to_string(),
+            before: Some(before),
+            after: Some(after),
+            columns,
+          }
+        }
+        (None, None) => unreachable!("one input has a next row"),
+      };
+      return Ok(Some(change));
+    }
+  }
+
+  fn next_old(&mut self) -> Result<Option<Record>> {
+    let next = self.old.next().transpose()?;
+    Ok(std::mem::replace(&mut self.old_next, next))
+  }
+
+  fn next_new(&mut self) -> Result<Option<Record>> {
+    let next = self.new.next().transpose()?;
+    Ok(std::mem::replace(&mut self.new_next, next))
+  }
+}
+
+// Missing columns count as empty
+fn value<'a>(record: &'a Record, column: &str) -> &'a str {
+  record.get(column).unwrap_or("")
+}
+
+impl Iterator for Diff {
+  type Item = Result<Change>;
+
+  fn next(&mut self) -> Option<Result<Change>> {
+    self.next_change().transpose()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn changes(old: &str, new: &str, memory_limit: usize) -> Vec<(ChangeKind, String, String)> {
+    let options = DiffOptions {
+      key: "id".to_string(),
+      memory_limit,
+      ..Default::default()
+    };
+    diff(old.as_bytes(), new.as_bytes(), options)
+      .unwrap()
+      .map(|change| {
+        let change = change.unwrap();
+        (change.kind, change.key, change.columns.join(","))
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_added_removed_changed() {
+    let old = "id,name,qty\n3,c,1\n1,a,1\n2,b,1\n";
+    let new = "id,qty,name\n1,1,a\n4,1,d\n2,5,B\n";
+    let expected = [
+      (ChangeKind::Changed, "2".to_string(), "name,qty".to_string()),
+      (ChangeKind::Removed, "3".to_string(), String::new()),
+      (ChangeKind::Added, "4".to_string(), String::new()),
+    ];
+
+    assert_eq!(changes(old, new, 64 * 1024 * 1024), expected);
+    // Every row spills to its own sort run
+    assert_eq!(changes(old, new, 1), expected);
+  }
+
+  #[test]
+  fn test_new_columns_and_repeated_keys() {
+    // An empty new column changes nothing; a filled one does
+    let old = "id,name\n1,a\n1,b\n2,c\n";
+    let new = "id,name,note\n1,a,\n2,c,x\n";
+    assert_eq!(
+      changes(old, new, 1024),
+      [
+        (ChangeKind::Removed, "1".to_string(), String::new()),
+        (ChangeKind::Changed, "2".to_string(), "note".to_string()),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_blank_headers_and_nulls() {
+    let options = DiffOptions {
+      key: "id".to_string(),
+      parser: CsvParserOptions {
+        null_value: Some("NULL".to_string()),
+        ..Default::default()
+      },
+      ..Default::default()
+    };
+    let old = "id,,name,qty\n2,x,NULL,1\n1,y,a,1\n";
+    let new = "id,,name,qty\n1,z,a,2\n2,x,b,1\n";
+    let mut rows = diff(old.as_bytes(), new.as_bytes(), options).unwrap();
+    assert_eq!(rows.columns(), ["id", "name", "qty"]);
+
+    let change = rows.next().unwrap().unwrap();
+    assert_eq!((change.key.as_str(), change.columns.join(",")), ("1", "qty".to_string()));
+    let after = change.after.unwrap();
+    assert_eq!((after.get("name"), after.get("qty")), (Some("a"), Some("2")));
+
+    // A null cell counts as empty
+    let change = rows.next().unwrap().unwrap();
+    assert_eq!((change.key.as_str(), change.columns.join(",")), ("2", "name".to_string()));
+    let before = change.before.unwrap();
+    assert_eq!((before.get("name"), before.get("qty")), (Some(""), Some("1")));
+    assert!(rows.next().is_none());
+  }
+
+  #[test]
+  fn test_unchanged_and_missing_key() {
+    let options = || DiffOptions {
+      key: "id".to_string(),
+      ..Default::default()
+    };
+    let mut rows = diff(&b"id\n1\n2\n"[..], &b"id\n2\n1\n"[..], options()).unwrap();
+    assert!(rows.next().is_none());
+    assert_eq!(rows.unchanged(), 2);
+
+    let error = diff(&b"id\n1\n"[..], &b"name\na\n"[..], options()).err();
+    assert_eq!(error.unwrap().to_string(), "New input: Column id not found");
+  }
+}
@@ -0,0 +1,195 @@
+use color_eyre::eyre::{eyre, Result};
+use std::collections::HashMap;
+use std::io::Read;
+
+use crate::parser::CsvParserOptions;
+use crate::reader::Parser;
+
+/// A distinct combination of values and the number of rows holding it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DistinctValue {
+  /// One value per column, in column order
+  pub values: Vec<String>,
+  pub count: u64,
+}
+
+/// Collects the distinct values of some columns while streaming. Only the
+/// cells of those columns are decoded, and only values not seen before are
+/// copied, so memory grows with the number of distinct values rather than
+/// rows. Missing and null cells count as empty.
+pub struct Distinct {
+  parser: Parser,
+  columns: Vec<String>,
+  // Cell index of each column, once headers are known
+  indexes: Option<Vec<usize>>,
+  // In order of first appearance
+  list: Vec<DistinctValue>,
+  // Position in `list` by `key`
+  index: HashMap<String, usize>,
+  key: String,
+}
+
+impl Distinct {
+  pub fn new(options: CsvParserOptions, columns: Vec<String>) -> Self {
+    Self {
+      parser: Parser::new(options),
+      columns,
+      indexes: None,
+      list: Vec::new(),
+      index: HashMap::new(),
+      key: String::new(),
+    }
+  }
+
+  /// Adds a chunk of input
+  pub fn feed(&mut self, chunk: &[u8]) -> Result<()> {
+    self.add(chunk, false)
+  }
+
+  /// Ends the input, counting a last row without a trailing newline
+  pub fn finish(&mut self) -> Result<()> {
+    self.add(&[], true)
+  }
+
+  pub fn columns(&self) -> &[String] {
+    &self.columns
+  }
+
+  /// The distinct values so far, in order of first appearance
+  pub fn values(&self) -> &[DistinctValue] {
+    &self.list
+  }
+
+  pub fn into_values(self) -> Vec<DistinctValue> {
+    self.list
+  }
+
+  fn add(&mut self, chunk: &[u8], at_end: bool) -> Result<()> {
+    self.parser.load(chunk, at_end)?;
+
+    while let Some(cells) = self.parser.next_cells(at_end) {
+      let cells = cells?;
+      let tokenizer = &self.parser.tokenizer;
+      let buffer = &self.parser.input.utf8_buffer;
+
+      let indexes = match &self.indexes {
+        Some(indexes) => indexes,
+        None => {
+          // Columns as the parser keys them: blank and `_` headers are left out
+          let headers = self.parser.headers().unwrap_or_default();
+          let indexes = self
+            .columns
+            .iter()
+            .map(|column| {
+              headers
+                .iter()
+                .position(|header| !header.is_empty() && header != "_" && header == column)
+                .ok_or_else(|| eyre!("Column {} not found", column))
+            })
+            .collect::<Result<_>>()?;
+          self.indexes.insert(indexes)
+        }
+      };
+
+      let values = indexes
+        .iter()
+        .map(|&i| match cells.get(i) {
+          Some(&cell) if !tokenizer.is_null(buffer, cell) => tokenizer.cell_value(buffer, cell),
+          _ => Ok("".into()),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+      // Each value prefixed with its length, so no two combinations share
+      // a key
+      self.key.clear();
+      for value in &values {
+        self.key.push_str(&value.len().to_string());
+        self.key.push(':');
+        self.key.push_str(value);
+      }
+
+      match self.index.get(self.key.as_str()) {
+        Some(&i) => self.list[i].count += 1,
+        None => {
+          self.list.push(DistinctValue {
+            values: values.into_iter().map(|value| value.into_owned()).collect(),
+            count: 1,
+          });
+          self.index.insert(self.key.clone(), self.list.len() - 1);
+        }
+      }
+    }
+    Ok(())
+  }
+}
+
+/// The distinct values of `columns` in `input`, reading it once
+pub fn distinct(
+  mut input: impl Read,
+  options: CsvParserOptions,
+  columns: Vec<String>,
+) -> Result<Vec<DistinctValue>> {
+  let mut distinct = Distinct::new(options, columns);
+  let mut chunk = vec![0; 64 * 1024];
+  loop {
+    let read = input.read(&mut chunk)?;
+    if read == 0 {
+      distinct.finish()?;
+      return Ok(distinct.into_values());
+    }
+    distinct.feed(&chunk[..read])?;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn values(input: &str, columns: &[&str]) -> Result<Vec<(Vec<String>, u64)>> {
+    let columns = columns.iter().map(|column| column.to_string()).collect();
+    Ok(
+      distinct(input.as_bytes(), CsvParserOptions::default(), columns)?
+        .into_iter()
+        .map(|value| (value.values, value.count))
+        .collect(),
+    )
+  }
+
+  #[test]
+  fn test_distinct_values() {
+    let input = "country,city\nFR,Paris\nDE,Berlin\nFR,Lyon\nFR,Paris\n,\nFR";
+    let strings = |values: &[&str]| values.iter().map(|value| value.to_string()).collect();
+
+    assert_eq!(
+      values(input, &["country"]).unwrap(),
+      [
+        (strings(&["FR"]), 4),
+        (strings(&["DE"]), 1),
+        (strings(&[""]), 1)
+      ]
+    );
+    // A missing cell counts as empty
+    assert_eq!(
+      values(input, &["country", "city"]).unwrap(),
+      [
+        (strings(&["FR", "Paris"]), 2),
+        (strings(&["DE", "Berlin"]), 1),
+        (strings(&["FR", "Lyon"]), 1),
+        (strings(&["", ""]), 1),
+        (strings(&["FR", ""]), 1)
+      ]
+    );
+  }
+
+  #[test]
+  fn test_combinations_do_not_collide() {
+    let input = "a,b\n\"x:1\",y\nx,\"1:y\"\n";
+    assert_eq!(values(input, &["a", "b"]).unwrap().len(), 2);
+  }
+
+  #[test]
+  fn test_missing_column() {
+    let error = values("a\n1\n", &["b"]).unwrap_err();
+    assert_eq!(error.to_string(), "Column b not found");
+  }
+}
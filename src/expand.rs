@@ -0,0 +1,75 @@
+/// One step of the path to a nested value, as named by a header with
+/// `expand_headers`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+  Key(String),
+  Index(u32),
+}
+
+/// Splits a header such as `address.city`, `tags[0]` or `items[1].name` into
+/// the steps to its nested value. Headers without `.` or `[`, and headers
+/// that do not form a path (empty names, unclosed brackets), give `None` and
+/// stay flat keys.
+pub fn header_path(header: &str) -> Option<Vec<PathSegment>> {
+  if !header.contains(['.', '[']) {
+    return None;
+  }
+
+  let mut path = Vec::new();
+  for part in header.split('.') {
+    let (name, mut rest) = part.split_at(part.find('[').unwrap_or(part.len()));
+    if name.is_empty() {
+      return None;
+    }
+    path.push(PathSegment::Key(name.to_string()));
+
+    while !rest.is_empty() {
+      let close = rest.find(']')?;
+      let inner = rest.strip_prefix('[')?.get(..close - 1)?;
+      path.push(match inner.parse::<u32>() {
+        // The largest valid JS array index is 2^32 - 2
+        Ok(index) if index < u32::MAX => PathSegment::Index(index),
+        _ if !inner.is_empty() => PathSegment::Key(inner.to_string()),
+        _ => return None,
+      });
+      rest = &rest[close + 1..];
+    }
+  }
+  Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn key(name: &str) -> PathSegment {
+    PathSegment::Key(name.to_string())
+  }
+
+  #[test]
+  fn test_header_paths() {
+    assert_eq!(header_path("name"), None);
+    assert_eq!(
+      header_path("address.city"),
+      Some(vec![key("address"), key("city")])
+    );
+    assert_eq!(
+      header_path("items[1].tags[0][2]"),
+      Some(vec![
+        key("items"),
+        PathSegment::Index(1),
+        key("tags"),
+        PathSegment::Index(0),
+        PathSegment::Index(2)
+      ])
+    );
+    assert_eq!(header_path("meta[en]"), Some(vec![key("meta"), key("en")]));
+  }
+
+  #[test]
+  fn test_flat_headers() {
+    for header in ["a.", ".a", "a..b", "a[", "a[0", "a[]", "a[0]x", "[0]"] {
+      assert_eq!(header_path(header), None, "{}", header);
+    }
+  }
+}
@@ -0,0 +1,100 @@
+use sha2::{Digest as _, Sha256};
+use xxhash_rust::xxh64::{xxh64, Xxh64};
+
+/// Hash functions for row hashes and input digests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+  /// 64-bit xxHash with seed 0: fast, for spotting changed rows
+  Xxh64,
+  /// SHA-256, where a hash must also hold up against tampering
+  Sha256,
+}
+
+impl HashAlgorithm {
+  /// `"xxh64"` or `"sha256"`
+  pub fn for_name(name: &str) -> Option<Self> {
+    match name {
+      "xxh64" => Some(Self::Xxh64),
+      "sha256" => Some(Self::Sha256),
+      _ => None,
+    }
+  }
+
+  pub fn name(self) -> &'static str {
+    match self {
+      Self::Xxh64 => "xxh64",
+      Self::Sha256 => "sha256",
+    }
+  }
+
+  /// The hash of `bytes` as lowercase hex
+  pub fn hex(self, bytes: &[u8]) -> String {
+    match self {
+      Self::Xxh64 => format!("{:016x}", xxh64(bytes, 0)),
+      Self::Sha256 => hex(&Sha256::digest(bytes)),
+    }
+  }
+}
+
+/// A hash of input that arrives in chunks
+#[derive(Clone)]
+pub enum Digest {
+  Xxh64(Xxh64),
+  Sha256(Sha256),
+}
+
+impl Digest {
+  pub fn new(algorithm: HashAlgorithm) -> Self {
+    match algorithm {
+      HashAlgorithm::Xxh64 => Self::Xxh64(Xxh64::new(0)),
+      HashAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+    }
+  }
+
+  pub fn update(&mut self, bytes: &[u8]) {
+    match self {
+      Self::Xxh64(hasher) => hasher.update(bytes),
+      Self::Sha256(hasher) => hasher.update(bytes),
+    }
+  }
+
+  /// The hash of the input so far as lowercase hex, the same as
+  /// `HashAlgorithm::hex` of all of it. More input may follow.
+  pub fn hex(&self) -> String {
+    match self {
+      Self::Xxh64(hasher) => format!("{:016x}", hasher.digest()),
+      Self::Sha256(hasher) => hex(&hasher.clone().finalize()),
+    }
+  }
+}
+
+fn hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_hex() {
+    assert_eq!(HashAlgorithm::Xxh64.hex(b""), "ef46db3751d8e999");
+    assert_eq!(
+      HashAlgorithm::Sha256.hex(b"abc"),
+      "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+  }
+
+  #[test]
+  fn test_digest_matches_whole_input() {
+    for algorithm in [HashAlgorithm::Xxh64, HashAlgorithm::Sha256] {
+      let mut digest = Digest::new(algorithm);
+      digest.update(b"a,b\n");
+      digest.update(b"1,2\n");
+      assert_eq!(digest.hex(), algorithm.hex(b"a,b\n1,2\n"));
+      // Reading the digest does not end it
+      digest.update(b"3,4\n");
+      assert_eq!(digest.hex(), algorithm.hex(b"a,b\n1,2\n3,4\n"));
+    }
+  }
+}
@@ -0,0 +1,68 @@
+use color_eyre::eyre::Result;
+use std::sync::{Arc, Mutex};
+
+use crate::reader::Record;
+
+/// Native post-processing for the rows of a `CsvParser` created with the
+/// `batchHook` option, run on each batch of parsed records before they
+/// become JS objects. A hook may change, add or remove records; an error
+/// fails the batch.
+pub trait BatchHook: Send + Sync {
+  fn process(&self, headers: &[String], records: &mut Vec<Record>) -> Result<()>;
+}
+
+impl<F> BatchHook for F
+where
+  F: Fn(&[String], &mut Vec<Record>) -> Result<()> + Send + Sync,
+{
+  fn process(&self, headers: &[String], records: &mut Vec<Record>) -> Result<()> {
+    self(headers, records)
+  }
+}
+
+static HOOKS: Mutex<Vec<(String, Arc<dyn BatchHook>)>> = Mutex::new(Vec::new());
+
+/// Makes `hook` available to parsers created with `batchHook: name`,
+/// replacing any hook registered under the same name. Register hooks when
+/// the embedding addon loads, before JS creates parsers.
+pub fn register_batch_hook(name: impl Into<String>, hook: impl BatchHook + 'static) {
+  let name = name.into();
+  let mut hooks = HOOKS.lock().unwrap_or_else(|e| e.into_inner());
+  hooks.retain(|(registered, _)| *registered != name);
+  hooks.push((name, Arc::new(hook)));
+}
+
+/// The hook registered under `name`
+pub fn batch_hook(name: &str) -> Option<Arc<dyn BatchHook>> {
+  let hooks = HOOKS.lock().unwrap_or_else(|e| e.into_inner());
+  hooks
+    .iter()
+    .find(|(registered, _)| registered == name)
+    .map(|(_, hook)| hook.clone())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_register() {
+    assert!(batch_hook("test-upper").is_none());
+    register_batch_hook("test-upper", |_: &[String], records: &mut Vec<Record>| {
+      records.retain(|record| !record.is_empty());
+      Ok(())
+    });
+    register_batch_hook("test-upper", |headers: &[String], records: &mut Vec<Record>| {
+      for record in records.iter_mut() {
+        let value = record.get(&headers[0]).unwrap_or_default().to_uppercase();
+        record.set(&headers[0], value);
+      }
+      Ok(())
+    });
+
+    let mut records = vec![Record::from(vec![("name".to_string(), "ann".to_string())])];
+    let hook = batch_hook("test-upper").unwrap();
+    hook.process(&["name".to_string()], &mut records).unwrap();
+    assert_eq!(records[0].get("name"), Some("ANN"));
+  }
+}
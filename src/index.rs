@@ -0,0 +1,165 @@
+use color_eyre::eyre::{eyre, Result};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::parser::{CsvParser, CsvParserOptions};
+use crate::reader::{record, Parser, Record};
+
+/// Where a row is in the indexed file, including its newline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RowLocation {
+  pub offset: u64,
+  pub length: u64,
+}
+
+/// Maps the values of one column to the rows that hold them, so rows of a
+/// large file can be read back on demand instead of kept in memory.
+pub struct CsvIndex {
+  file: File,
+  rows: HashMap<String, Vec<RowLocation>>,
+  // Parses single rows read back from the file
+  pub(crate) tokenizer: CsvParser,
+}
+
+impl CsvIndex {
+  /// Reads the UTF-8 file at `path` once and records the location of every
+  /// row under its value in `column`.
+  pub fn build(path: impl AsRef<Path>, column: &str, options: CsvParserOptions) -> Result<Self> {
+    let mut file = File::open(path)?;
+    let mut parser = Parser::new(options.clone());
+    let mut rows: HashMap<String, Vec<RowLocation>> = HashMap::new();
+    let mut chunk = vec![0; 64 * 1024];
+
+    loop {
+      let read = file.read(&mut chunk)?;
+      let at_end = read == 0;
+      parser.load(&chunk[..read], at_end)?;
+
+      while let Some(cells) = parser.next_cells(at_end) {
+        let cells = cells?;
+        let location = parser
+          .row_location()
+          .ok_or_else(|| eyre!("Only UTF-8 files can be indexed"))?;
+        let tokenizer = &parser.tokenizer;
+        let Some((_, _, cell)) = tokenizer
+          .row_fields(&cells)
+          .find(|(_, key, _)| key == column)
+        else {
+          continue;
+        };
+        let key = tokenizer.cell_value(&parser.input.utf8_buffer, cell)?;
+        rows.entry(key.into_owned()).or_default().push(location);
+      }
+      if at_end {
+        break;
+      }
+    }
+
+    let headers = parser.headers().map(<[String]>::to_vec).unwrap_or_default();
+    if rows.is_empty() && !headers.iter().any(|header| header == column) {
+      return Err(eyre!("Column {} not found", column));
+    }
+
+    // Rows read back are data rows on their own, with the headers and
+    // separator found while building
+    let tokenizer = CsvParser::new(CsvParserOptions {
+      headers: Some(headers),
+      separator: parser.tokenizer.options.separator,
+      skip_lines: None,
+      skip_comments: None,
+      sep_directive: false,
+      detect_separator: false,
+      dedupe: None,
+      ..options
+    });
+
+    Ok(Self {
+      file,
+      rows,
+      tokenizer,
+    })
+  }
+
+  /// Distinct keys in the index
+  pub fn len(&self) -> usize {
+    self.rows.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.rows.is_empty()
+  }
+
+  /// Locations of the rows whose key column holds `key`, in file order
+  pub fn locate(&self, key: &str) -> &[RowLocation] {
+    self.rows.get(key).map_or(&[], Vec::as_slice)
+  }
+
+  /// The bytes of the row at `location`
+  pub fn read_row(&self, location: RowLocation) -> Result<Vec<u8>> {
+    read_at(&self.file, location)
+  }
+
+  /// The rows whose key column holds `key`, read back from the file
+  pub fn lookup(&mut self, key: &str) -> Result<Vec<Record>> {
+    let mut records = Vec::new();
+    for &location in self.rows.get(key).map_or(&[][..], Vec::as_slice) {
+      let row = read_at(&self.file, location)?;
+      if let Some(cells) = self.tokenizer.parse_record(&row, 0, row.len())? {
+        records.push(record(&self.tokenizer, &row, &cells)?);
+      }
+    }
+    Ok(records)
+  }
+}
+
+fn read_at(mut file: &File, location: RowLocation) -> Result<Vec<u8>> {
+  let mut row = vec![0; location.length as usize];
+  file.seek(SeekFrom::Start(location.offset))?;
+  file.read_exact(&mut row)?;
+  Ok(row)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn build(name: &str, contents: &[u8], column: &str) -> Result<CsvIndex> {
+    let path = std::env::temp_dir().join(format!("fast-csv-parser-index-{}.csv", name));
+    std::fs::write(&path, contents).unwrap();
+    let index = CsvIndex::build(&path, column, CsvParserOptions::default());
+    // The open file stays readable after removal, where the platform allows it
+    let _ = std::fs::remove_file(&path);
+    index
+  }
+
+  #[test]
+  fn test_lookup_rows() {
+    let contents = b"\xEF\xBB\xBFid,name\n1,Ann\n2,\"B\nob\"\n1,Cy";
+    let mut index = build("lookup", contents, "id").unwrap();
+
+    assert_eq!(index.len(), 2);
+    assert_eq!(
+      index.locate("2"),
+      [RowLocation {
+        offset: 17,
+        length: 9
+      }]
+    );
+    let names: Vec<_> = index
+      .lookup("1")
+      .unwrap()
+      .iter()
+      .map(|record| record.get("name").unwrap().to_string())
+      .collect();
+    assert_eq!(names, ["Ann", "Cy"]);
+    assert_eq!(index.lookup("2").unwrap()[0].get("name"), Some("B\nob"));
+    assert!(index.lookup("3").unwrap().is_empty());
+  }
+
+  #[test]
+  fn test_missing_column() {
+    assert!(build("missing", b"id\n1\n", "name").is_err());
+  }
+}
@@ -0,0 +1,245 @@
+use color_eyre::eyre::{eyre, Result};
+use std::collections::HashMap;
+use std::io::Read;
+
+use crate::parser::CsvParserOptions;
+use crate::reader::{Parser, Record};
+
+/// Which left rows a join emits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JoinKind {
+  /// Only rows with at least one match on the right
+  #[default]
+  Inner,
+  /// Every row, with empty right columns where nothing matched
+  Left,
+}
+
+#[derive(Clone, Default)]
+pub struct JoinOptions {
+  /// Header of the key column in the streamed (left) input
+  pub key: String,
+  /// Header of the key column in the right input, when it differs from `key`
+  pub right_key: Option<String>,
+  pub kind: JoinKind,
+  /// Options for parsing the streamed input
+  pub left: CsvParserOptions,
+  /// Options for parsing the right input
+  pub right: CsvParserOptions,
+}
+
+/// Hash join of a streamed CSV input against a right input held in memory.
+/// The right input is read once up front, so it should be the smaller side
+/// (e.g. a dimension table); the left input is joined chunk by chunk.
+///
+/// Merged rows hold the left columns followed by the right ones, minus the
+/// right key column. A right column whose name is also a left column is
+/// renamed `<name>_right`. Empty keys never match.
+pub struct Joiner {
+  left: Parser,
+  key: String,
+  kind: JoinKind,
+  // Right column names, in order of first appearance
+  columns: Vec<String>,
+  // Right values by key, aligned with `columns`. Rows seen before a column
+  // first appeared are shorter.
+  rows: HashMap<String, Vec<Vec<String>>>,
+  key_checked: bool,
+}
+
+impl Joiner {
+  /// Reads all of `right` into the hash table
+  pub fn new(mut right: impl Read, options: JoinOptions) -> Result<Self> {
+    let right_key = options.right_key.as_deref().unwrap_or(&options.key);
+    let mut parser = Parser::new(options.right);
+    let mut columns: Vec<String> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut rows: HashMap<String, Vec<Vec<String>>> = HashMap::new();
+    let mut chunk = vec![0; 64 * 1024];
+
+    loop {
+      let read = right.read(&mut chunk)?;
+      let records = if read == 0 {
+        parser.finish()?
+      } else {
+        parser.feed(&chunk[..read])?
+      };
+
+      for record in records {
+        let mut key = None;
+        let mut values = Vec::new();
+        for (name, value) in record?.into_fields() {
+          if name == right_key {
+            key = Some(value);
+            continue;
+          }
+          let i = *index.entry(name).or_insert_with_key(|name| {
+            columns.push(name.clone());
+            columns.len() - 1
+          });
+          if values.len() <= i {
+            values.resize(i + 1, String::new());
+          }
+          values[i] = value;
+        }
+        if let Some(key) = key.filter(|key| !key.is_empty()) {
+          rows.entry(key).or_default().push(values);
+        }
+      }
+
+      if read == 0 {
+        break;
+      }
+    }
+
+    let has_key = |headers: &[String]| headers.iter().any(|header| header == right_key);
+    if rows.is_empty() && !parser.headers().is_some_and(has_key) {
+      return Err(eyre!("Column {} not found", right_key));
+    }
+
+    Ok(Self {
+      left: Parser::new(options.left),
+      key: options.key,
+      kind: options.kind,
+      columns,
+      rows,
+      key_checked: false,
+    })
+  }
+
+  /// Distinct keys on the right
+  pub fn len(&self) -> usize {
+    self.rows.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.rows.is_empty()
+  }
+
+  /// Adds a chunk of the left input and returns the merged rows it completes
+  pub fn feed(&mut self, chunk: &[u8]) -> Result<Vec<Record>> {
+    let records = self.left.feed(chunk)?.collect::<Result<Vec<_>>>()?;
+    self.join(records)
+  }
+
+  /// Ends the left input and returns the remaining merged rows
+  pub fn finish(&mut self) -> Result<Vec<Record>> {
+    let records = self.left.finish()?.collect::<Result<Vec<_>>>()?;
+    self.join(records)
+  }
+
+  fn join(&mut self, records: Vec<Record>) -> Result<Vec<Record>> {
+    if !self.key_checked && !records.is_empty() {
+      let key = &self.key;
+      if !self.left.headers().unwrap_or_default().contains(key) {
+        return Err(eyre!("Column {} not found", key));
+      }
+      self.key_checked = true;
+    }
+
+    let mut joined = Vec::with_capacity(records.len());
+    for record in records {
+      let matches = match record.get(&self.key) {
+        Some(key) if !key.is_empty() => self.rows.get(key).map_or(&[][..], Vec::as_slice),
+        _ => &[],
+      };
+      if matches.is_empty() {
+        if self.kind == JoinKind::Left {
+          joined.push(self.merge(record, &[]));
+        }
+        continue;
+      }
+      for values in matches {
+        joined.push(self.merge(record.clone(), values));
+      }
+    }
+    Ok(joined)
+  }
+
+  fn merge(&self, left: Record, values: &[String]) -> Record {
+    let mut fields = left.into_fields();
+    let left_len = fields.len();
+    fields.reserve(self.columns.len());
+
+    for (i, name) in self.columns.iter().enumerate() {
+      let value = values.get(i).cloned().unwrap_or_default();
+      let name = if fields[..left_len].iter().any(|(key, _)| key == name) {
+        format!("{}_right", name)
+      } else {
+        name.clone()
+      };
+      fields.push((name, value));
+    }
+    Record::from(fields)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const RIGHT: &str = "id,name,city\n1,Ann,Oslo\n2,Bob,Rome\n2,Bo,Lima\n";
+
+  fn join(left: &str, kind: JoinKind) -> Vec<Vec<(String, String)>> {
+    let options = JoinOptions {
+      key: "user".to_string(),
+      right_key: Some("id".to_string()),
+      kind,
+      ..Default::default()
+    };
+    let mut joiner = Joiner::new(RIGHT.as_bytes(), options).unwrap();
+    let (head, tail) = left.split_at(left.len() / 2);
+    let mut rows = joiner.feed(head.as_bytes()).unwrap();
+    rows.extend(joiner.feed(tail.as_bytes()).unwrap());
+    rows.extend(joiner.finish().unwrap());
+    rows.into_iter().map(Record::into_fields).collect()
+  }
+
+  fn values(rows: &[Vec<(String, String)>], column: &str) -> Vec<String> {
+    rows
+      .iter()
+      .map(|row| row.iter().find(|(k, _)| k == column).unwrap().1.clone())
+      .collect()
+  }
+
+  #[test]
+  fn test_inner_join() {
+    let rows = join("user,amount\n1,10\n3,20\n2,30\n", JoinKind::Inner);
+
+    assert_eq!(values(&rows, "amount"), ["10", "30", "30"]);
+    assert_eq!(values(&rows, "name"), ["Ann", "Bob", "Bo"]);
+    assert_eq!(
+      rows[0].iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>(),
+      ["user", "amount", "name", "city"]
+    );
+  }
+
+  #[test]
+  fn test_left_join_keeps_unmatched_rows() {
+    let rows = join("user,amount\n3,20\n,5\n1,10", JoinKind::Left);
+
+    assert_eq!(values(&rows, "amount"), ["20", "5", "10"]);
+    assert_eq!(values(&rows, "city"), ["", "", "Oslo"]);
+  }
+
+  #[test]
+  fn test_conflicting_names_and_missing_keys() {
+    let rows = join("user,name\n1,Al\n", JoinKind::Inner);
+    assert_eq!(values(&rows, "name"), ["Al"]);
+    assert_eq!(values(&rows, "name_right"), ["Ann"]);
+
+    let options = JoinOptions {
+      key: "missing".to_string(),
+      ..Default::default()
+    };
+    assert!(Joiner::new(RIGHT.as_bytes(), options).is_err());
+
+    let options = JoinOptions {
+      key: "missing".to_string(),
+      right_key: Some("id".to_string()),
+      ..Default::default()
+    };
+    let mut joiner = Joiner::new(RIGHT.as_bytes(), options).unwrap();
+    assert!(joiner.feed(b"user\n1\n").is_err());
+  }
+}
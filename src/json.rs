@@ -0,0 +1,266 @@
+use color_eyre::eyre::Result;
+use serde_json::{Map, Value};
+use std::io::{Read, Write};
+
+use crate::dates::DateOutput;
+use crate::expand::{header_path, PathSegment};
+use crate::json_schema::Coerced;
+use crate::parser::{CellRange, CellType, CsvParser, CsvParserOptions, InvalidRow};
+use crate::reader::Parser;
+
+/// How `to_json` lays out the rows it writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JsonFormat {
+  /// One JSON array holding every row, a row per line
+  #[default]
+  Array,
+  /// Newline-delimited JSON: one row object per line
+  Ndjson,
+}
+
+/// Parses `input` and writes each row to `output` as a JSON object, without
+/// building rows for JS. Values are those `JSON.stringify` gives for the rows
+/// of `csv()` with the same options: null cells are `null`, typed, array and
+/// JSON columns keep their values, dates are ISO strings and `expand_headers`
+/// nests objects. Raw cells are written as text, with invalid UTF-8
+/// replaced. Returns the number of rows written.
+pub fn to_json(
+  mut input: impl Read,
+  mut output: impl Write,
+  options: CsvParserOptions,
+  format: JsonFormat,
+) -> Result<u64> {
+  let mut parser = Parser::new(options);
+  let mut paths: Vec<Option<Option<Vec<PathSegment>>>> = Vec::new();
+  let mut rows = 0;
+  let mut chunk = vec![0; 64 * 1024];
+
+  if format == JsonFormat::Array {
+    output.write_all(b"[")?;
+  }
+  loop {
+    let read = input.read(&mut chunk)?;
+    let at_end = read == 0;
+    parser.load(&chunk[..read], at_end)?;
+    while let Some(cells) = parser.next_cells(at_end) {
+      let cells = cells?;
+      let line = parser.row_bytes();
+      let line = parser.tokenizer.line_bytes(line, 0, line.len());
+      let buffer = &parser.input.utf8_buffer;
+      let row = row_value(&parser.tokenizer, buffer, &cells, line, &mut paths)?;
+
+      match format {
+        JsonFormat::Array if rows == 0 => output.write_all(b"\n")?,
+        JsonFormat::Array => output.write_all(b",\n")?,
+        JsonFormat::Ndjson => {}
+      }
+      serde_json::to_writer(&mut output, &row)?;
+      if format == JsonFormat::Ndjson {
+        output.write_all(b"\n")?;
+      }
+      rows += 1;
+    }
+    if at_end {
+      break;
+    }
+  }
+  match format {
+    JsonFormat::Array if rows > 0 => output.write_all(b"\n]\n")?,
+    JsonFormat::Array => output.write_all(b"]\n")?,
+    JsonFormat::Ndjson => {}
+  }
+  output.flush()?;
+  Ok(rows)
+}
+
+// The JSON object of a row, keyed as the Node bindings key its JS object.
+// `paths` caches the nested path of each column for `expand_headers`.
+fn row_value(
+  parser: &CsvParser,
+  buffer: &[u8],
+  cells: &[CellRange],
+  line: &[u8],
+  paths: &mut Vec<Option<Option<Vec<PathSegment>>>>,
+) -> Result<Value> {
+  let text = |bytes: &[u8]| -> Result<String> { Ok(parser.decode(bytes)?.into_owned()) };
+  let convert = |bytes: &[u8], key: &str, cell_type: Option<&CellType>| -> Result<Value> {
+    let text = parser.decode(bytes)?;
+    if parser.is_json_column(key) {
+      return Ok(parser.json_value(key, &text)?.unwrap_or_else(|| text.to_string().into()));
+    }
+    Ok(match cell_type {
+      None => Value::String(text.into_owned()),
+      // Empty cells are null, and values that do not parse stay strings
+      Some(_) if text.trim().is_empty() => Value::Null,
+      Some(CellType::Date(date, output)) => match (date.parse(&text), output) {
+        (None, _) => Value::String(text.into_owned()),
+        (Some(millis), DateOutput::Millis) => millis.into(),
+        // An ISO string, as `Date.prototype.toJSON` writes it
+        (Some(millis), DateOutput::Date) => chrono::DateTime::from_timestamp_millis(millis)
+          .map_or(Value::Null, |date| {
+            date.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string().into()
+          }),
+      },
+      Some(CellType::Number(number)) => match parser.number(number, &text) {
+        Some(number) => number_value(number),
+        None => Value::String(text.into_owned()),
+      },
+    })
+  };
+
+  let mut row = Map::new();
+  for (index, key, cell) in parser.row_fields(cells) {
+    let value = match parser.coerced(index) {
+      Some(Coerced::Missing) => continue,
+      Some(Coerced::Null) => Value::Null,
+      Some(Coerced::Boolean(boolean)) => boolean.into(),
+      Some(Coerced::Integer(integer)) => integer.into(),
+      Some(Coerced::Number(number)) => number_value(number),
+      _ if parser.is_null(buffer, cell) => Value::Null,
+      _ => {
+        let cell_type = parser.cell_type(&key);
+        match parser.array_separator(&key) {
+          // Each item is converted as a whole cell of the column would be
+          Some(separator) => parser
+            .cell_items(buffer, cell, separator)
+            .into_iter()
+            .map(|item| convert(item, &key, cell_type))
+            .collect::<Result<_>>()?,
+          None => convert(parser.cell_bytes(buffer, cell), &key, cell_type)?,
+        }
+      }
+    };
+
+    if parser.options.expand_headers && index >= paths.len() {
+      paths.resize(index + 1, None);
+    }
+    let path = match parser.options.expand_headers {
+      true => paths[index].get_or_insert_with(|| header_path(&key)).as_deref(),
+      false => None,
+    };
+    match path {
+      Some(path) => set_path(&mut row, path, value),
+      None => {
+        row.insert(key.into_owned(), value);
+      }
+    }
+  }
+
+  if let Some((key, extra)) = parser.extra_cells(cells) {
+    let values = extra
+      .iter()
+      .map(|&cell| match parser.is_null(buffer, cell) {
+        true => Ok(Value::Null),
+        false => text(parser.cell_bytes(buffer, cell)).map(Value::String),
+      })
+      .collect::<Result<_>>()?;
+    row.insert(key.to_string(), Value::Array(values));
+  }
+  if let Some(key) = &parser.options.include_raw_line {
+    row.insert(key.clone(), String::from_utf8_lossy(line).into_owned().into());
+  }
+  if let Some(algorithm) = parser.options.row_hash {
+    row.insert("__hash".to_string(), algorithm.hex(line).into());
+  }
+  if parser.options.on_violation == InvalidRow::Annotate && parser.options.validates() {
+    row.insert("__violations".to_string(), parser.violations().into());
+  }
+  Ok(Value::Object(row))
+}
+
+// Whole numbers are written without a fraction, as JS writes them
+fn number_value(number: f64) -> Value {
+  if number.fract() == 0.0 && number.abs() < (1u64 << 53) as f64 {
+    (number as i64).into()
+  } else {
+    // Not finite numbers are `null`, as in `JSON.stringify`
+    serde_json::Number::from_f64(number).map_or(Value::Null, Value::Number)
+  }
+}
+
+// Sets the value at `path` under `row`, creating the objects and arrays on
+// the way. Array holes are `null`, as `JSON.stringify` writes them.
+fn set_path(row: &mut Map<String, Value>, path: &[PathSegment], value: Value) {
+  let [PathSegment::Key(first), rest @ ..] = path else {
+    return;
+  };
+  let mut slot = row.entry(first.clone()).or_insert(Value::Null);
+  for segment in rest {
+    if !slot.is_object() && !slot.is_array() {
+      *slot = match segment {
+        PathSegment::Index(_) => Value::Array(Vec::new()),
+        PathSegment::Key(_) => Value::Object(Map::new()),
+      };
+    }
+    slot = match (slot, segment) {
+      (Value::Object(object), PathSegment::Key(key)) => {
+        object.entry(key.clone()).or_insert(Value::Null)
+      }
+      (Value::Object(object), PathSegment::Index(index)) => {
+        object.entry(index.to_string()).or_insert(Value::Null)
+      }
+      (Value::Array(items), PathSegment::Index(index)) => {
+        let index = *index as usize;
+        if index >= items.len() {
+          items.resize(index + 1, Value::Null);
+        }
+        &mut items[index]
+      }
+      // Named properties of arrays are not written by `JSON.stringify`
+      _ => return,
+    };
+  }
+  *slot = value;
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn json(input: &str, options: CsvParserOptions, format: JsonFormat) -> Result<String> {
+    let mut output = Vec::new();
+    to_json(input.as_bytes(), &mut output, options, format)?;
+    Ok(String::from_utf8(output)?)
+  }
+
+  #[test]
+  fn test_array_and_ndjson() {
+    let input = "a,b\n1,x\n2,\"y,z\"\n";
+    assert_eq!(
+      json(input, CsvParserOptions::default(), JsonFormat::Array).unwrap(),
+      "[\n{\"a\":\"1\",\"b\":\"x\"},\n{\"a\":\"2\",\"b\":\"y,z\"}\n]\n"
+    );
+    assert_eq!(
+      json(input, CsvParserOptions::default(), JsonFormat::Ndjson).unwrap(),
+      "{\"a\":\"1\",\"b\":\"x\"}\n{\"a\":\"2\",\"b\":\"y,z\"}\n"
+    );
+    assert_eq!(
+      json("a,b\n", CsvParserOptions::default(), JsonFormat::Array).unwrap(),
+      "[]\n"
+    );
+  }
+
+  #[test]
+  fn test_values() {
+    let options = CsvParserOptions {
+      null_value: Some("NULL".to_string()),
+      column_types: vec![(
+        "n".to_string(),
+        CellType::Number(Default::default()),
+      )],
+      array_columns: vec![("tags".to_string(), "|".to_string())],
+      json_columns: vec!["meta".to_string()],
+      expand_headers: true,
+      ..Default::default()
+    };
+    let input = "n,tags,meta,user.name,user.ids[1]\n1.5,a|b,{\"k\":1},Ann,7\n2,,NULL,,x\n";
+    assert_eq!(
+      json(input, options, JsonFormat::Ndjson).unwrap(),
+      concat!(
+        "{\"n\":1.5,\"tags\":[\"a\",\"b\"],\"meta\":{\"k\":1},",
+        "\"user\":{\"name\":\"Ann\",\"ids\":[null,\"7\"]}}\n",
+        "{\"n\":2,\"tags\":[],\"meta\":null,\"user\":{\"name\":\"\",\"ids\":[null,\"x\"]}}\n",
+      )
+    );
+  }
+}
@@ -0,0 +1,458 @@
+use color_eyre::eyre::{eyre, Result};
+use regex::Regex;
+use serde_json::{Map, Value};
+
+// The JSON types a property allows, as bits; none set allows any
+const NULL: u8 = 1;
+const BOOLEAN: u8 = 2;
+const INTEGER: u8 = 4;
+const NUMBER: u8 = 8;
+const STRING: u8 = 16;
+const TYPES: [(&str, u8); 5] = [
+  ("null", NULL),
+  ("boolean", BOOLEAN),
+  ("integer", INTEGER),
+  ("number", NUMBER),
+  ("string", STRING),
+];
+
+// Keywords that describe a schema without constraining it
+const ANNOTATIONS: [&str; 9] = [
+  "$schema",
+  "$id",
+  "$comment",
+  "title",
+  "description",
+  "default",
+  "examples",
+  "deprecated",
+  "format",
+];
+
+/// A JSON Schema (draft 2020-12) for row objects. The subset supported is
+/// `type: "object"`, `properties`, `required` and `additionalProperties` at
+/// the root, and `type`, `enum`, `const`, `minLength`, `maxLength`,
+/// `pattern`, `minimum`, `maximum`, `exclusiveMinimum` and
+/// `exclusiveMaximum` in properties. Annotations such as `title` or
+/// `format` are ignored; any other keyword is an error.
+#[derive(Debug, Clone)]
+pub struct RowSchema {
+  source: Value,
+  properties: Vec<Property>,
+  required: Vec<String>,
+  additional_properties: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Property {
+  name: String,
+  types: u8,
+  // `enum`, or `const` as a single value
+  allowed: Option<Vec<Value>>,
+  min_length: Option<usize>,
+  max_length: Option<usize>,
+  pattern: Option<Regex>,
+  minimum: Option<f64>,
+  maximum: Option<f64>,
+  exclusive_minimum: Option<f64>,
+  exclusive_maximum: Option<f64>,
+}
+
+/// A cell converted to a type its schema property allows
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Coerced {
+  Null,
+  Boolean(bool),
+  Integer(i64),
+  Number(f64),
+  /// The cell text, as the row holds it without a schema
+  String,
+  /// An empty cell of a property that allows neither `null` nor strings,
+  /// left out of the row
+  Missing,
+}
+
+impl RowSchema {
+  pub fn new(schema: &Value) -> Result<Self> {
+    let root = object(schema, "")?;
+    let mut properties = Vec::new();
+    let mut required = Vec::new();
+    let mut additional_properties = true;
+    for (keyword, value) in root {
+      match keyword.as_str() {
+        "type" if value == "object" => {}
+        "type" => return Err(eyre!("Schema type must be \"object\", got {}", value)),
+        "properties" => {
+          for (name, property) in object(value, "/properties")? {
+            properties.push(Property::new(name, property)?);
+          }
+        }
+        "required" => {
+          required =
+            strings(value).ok_or_else(|| eyre!("Schema required must be an array of strings"))?;
+        }
+        "additionalProperties" => {
+          additional_properties = value
+            .as_bool()
+            .ok_or_else(|| eyre!("Schema additionalProperties must be true or false"))?;
+        }
+        keyword => check_annotation(keyword, "")?,
+      }
+    }
+    Ok(Self {
+      source: schema.clone(),
+      properties,
+      required,
+      additional_properties,
+    })
+  }
+
+  /// The schema as it was given
+  pub fn source(&self) -> &Value {
+    &self.source
+  }
+
+  /// The index of the property named `key`
+  pub fn property(&self, key: &str) -> Option<usize> {
+    self
+      .properties
+      .iter()
+      .position(|property| property.name == key)
+  }
+
+  /// Keys rows must have
+  pub fn required(&self) -> &[String] {
+    &self.required
+  }
+
+  pub fn additional_properties(&self) -> bool {
+    self.additional_properties
+  }
+
+  /// Converts the text of a cell, `None` for a null cell, to the type
+  /// property `index` allows and checks it against the property. Fails with
+  /// the failing keyword and a message.
+  pub fn check(&self, index: usize, text: Option<&str>) -> Result<Coerced, (&'static str, String)> {
+    let property = &self.properties[index];
+    let value = property.coerce(text)?;
+    property.constrain(value, text.unwrap_or_default())?;
+    Ok(value)
+  }
+}
+
+impl Property {
+  fn new(name: &str, schema: &Value) -> Result<Self> {
+    let path = format!("/properties/{}", name);
+    let mut property = Self {
+      name: name.to_string(),
+      types: 0,
+      allowed: None,
+      min_length: None,
+      max_length: None,
+      pattern: None,
+      minimum: None,
+      maximum: None,
+      exclusive_minimum: None,
+      exclusive_maximum: None,
+    };
+    let length = |value: &Value| {
+      value
+        .as_u64()
+        .map(|n| n as usize)
+        .ok_or_else(|| eyre!("Schema {} must be a non-negative integer", path))
+    };
+    let bound = |value: &Value| {
+      value
+        .as_f64()
+        .ok_or_else(|| eyre!("Schema {} bounds must be numbers", path))
+    };
+    for (keyword, value) in object(schema, &path)? {
+      match keyword.as_str() {
+        "type" => {
+          let names = match value {
+            Value::String(name) => vec![name.clone()],
+            value => strings(value)
+              .ok_or_else(|| eyre!("Schema {}/type must be a string or an array", path))?,
+          };
+          for name in names {
+            property.types |= TYPES
+              .iter()
+              .find(|(type_name, _)| *type_name == name)
+              .map(|&(_, bit)| bit)
+              .ok_or_else(|| eyre!("Unsupported type \"{}\" at {} of schema", name, path))?;
+          }
+        }
+        "enum" => {
+          let values = value
+            .as_array()
+            .ok_or_else(|| eyre!("Schema {}/enum must be an array", path))?;
+          property.allowed = Some(values.clone());
+        }
+        "const" => property.allowed = Some(vec![value.clone()]),
+        "minLength" => property.min_length = Some(length(value)?),
+        "maxLength" => property.max_length = Some(length(value)?),
+        "pattern" => {
+          let pattern = value
+            .as_str()
+            .ok_or_else(|| eyre!("Schema {}/pattern must be a string", path))?;
+          property.pattern = Some(
+            Regex::new(pattern)
+              .map_err(|e| eyre!("Invalid pattern at {} of schema: {}", path, e))?,
+          );
+        }
+        "minimum" => property.minimum = Some(bound(value)?),
+        "maximum" => property.maximum = Some(bound(value)?),
+        "exclusiveMinimum" => property.exclusive_minimum = Some(bound(value)?),
+        "exclusiveMaximum" => property.exclusive_maximum = Some(bound(value)?),
+        keyword => check_annotation(keyword, &path)?,
+      }
+    }
+    Ok(property)
+  }
+
+  fn allows(&self, types: u8) -> bool {
+    self.types & types != 0
+  }
+
+  fn coerce(&self, text: Option<&str>) -> Result<Coerced, (&'static str, String)> {
+    let Some(text) = text else {
+      return match self.types == 0 || self.allows(NULL) {
+        true => Ok(Coerced::Null),
+        false => Err(("type", self.type_message())),
+      };
+    };
+    // Without a type, cells stay strings
+    if self.types == 0 {
+      return Ok(Coerced::String);
+    }
+    if text.is_empty() {
+      return Ok(if self.allows(NULL) {
+        Coerced::Null
+      } else if self.allows(STRING) {
+        Coerced::String
+      } else {
+        Coerced::Missing
+      });
+    }
+    if self.allows(BOOLEAN) {
+      if text.eq_ignore_ascii_case("true") {
+        return Ok(Coerced::Boolean(true));
+      }
+      if text.eq_ignore_ascii_case("false") {
+        return Ok(Coerced::Boolean(false));
+      }
+    }
+    let trimmed = text.trim();
+    if self.allows(INTEGER) {
+      if let Ok(integer) = trimmed.parse() {
+        return Ok(Coerced::Integer(integer));
+      }
+    }
+    if self.allows(NUMBER) {
+      if let Ok(number) = trimmed.parse::<f64>() {
+        if number.is_finite() {
+          return Ok(Coerced::Number(number));
+        }
+      }
+    }
+    if self.allows(STRING) {
+      return Ok(Coerced::String);
+    }
+    Err(("type", self.type_message()))
+  }
+
+  fn type_message(&self) -> String {
+    let names: Vec<_> = TYPES
+      .iter()
+      .filter(|&&(_, bit)| self.allows(bit))
+      .map(|&(name, _)| name)
+      .collect();
+    format!("must be {}", names.join(" or "))
+  }
+
+  fn constrain(&self, value: Coerced, text: &str) -> Result<(), (&'static str, String)> {
+    let number = match value {
+      Coerced::Integer(integer) => Some(integer as f64),
+      Coerced::Number(number) => Some(number),
+      _ => None,
+    };
+    if let Some(number) = number {
+      // Each bound with the comparison a number must pass
+      let bounds = [
+        ("minimum", self.minimum, ">="),
+        ("maximum", self.maximum, "<="),
+        ("exclusiveMinimum", self.exclusive_minimum, ">"),
+        ("exclusiveMaximum", self.exclusive_maximum, "<"),
+      ];
+      for (keyword, bound, operator) in bounds {
+        let Some(bound) = bound else {
+          continue;
+        };
+        let holds = match operator {
+          ">=" => number >= bound,
+          "<=" => number <= bound,
+          ">" => number > bound,
+          _ => number < bound,
+        };
+        if !holds {
+          return Err((keyword, format!("must be {} {}", operator, bound)));
+        }
+      }
+    }
+
+    if value == Coerced::String {
+      let length = text.chars().count();
+      if let Some(min) = self.min_length.filter(|&min| length < min) {
+        return Err((
+          "minLength",
+          format!("must not be shorter than {} characters", min),
+        ));
+      }
+      if let Some(max) = self.max_length.filter(|&max| length > max) {
+        return Err((
+          "maxLength",
+          format!("must not be longer than {} characters", max),
+        ));
+      }
+      if let Some(pattern) = self
+        .pattern
+        .as_ref()
+        .filter(|pattern| !pattern.is_match(text))
+      {
+        return Err((
+          "pattern",
+          format!("must match pattern \"{}\"", pattern.as_str()),
+        ));
+      }
+    }
+
+    if let Some(allowed) = &self.allowed {
+      let json = match value {
+        Coerced::Missing => return Ok(()),
+        Coerced::Null => Value::Null,
+        Coerced::Boolean(boolean) => boolean.into(),
+        Coerced::Integer(integer) => integer.into(),
+        Coerced::Number(number) => number.into(),
+        Coerced::String => text.into(),
+      };
+      // Numbers compare by value, so `1` in a schema allows `1.0` in a cell
+      let equal = |other: &Value| match (other.as_f64(), number) {
+        (Some(other), Some(number)) => other == number,
+        _ => *other == json,
+      };
+      if !allowed.iter().any(equal) {
+        return Err(match allowed.len() {
+          1 => ("const", format!("must be {}", allowed[0])),
+          _ => ("enum", "must be one of the allowed values".to_string()),
+        });
+      }
+    }
+    Ok(())
+  }
+}
+
+fn object<'a>(value: &'a Value, path: &str) -> Result<&'a Map<String, Value>> {
+  value.as_object().ok_or_else(|| match path {
+    "" => eyre!("Schema must be an object"),
+    path => eyre!("Schema {} must be an object", path),
+  })
+}
+
+fn strings(value: &Value) -> Option<Vec<String>> {
+  value
+    .as_array()?
+    .iter()
+    .map(|value| value.as_str().map(str::to_string))
+    .collect()
+}
+
+fn check_annotation(keyword: &str, path: &str) -> Result<()> {
+  match ANNOTATIONS.contains(&keyword) {
+    true => Ok(()),
+    false => Err(eyre!(
+      "Unsupported keyword \"{}\" at {} of schema",
+      keyword,
+      if path.is_empty() { "/" } else { path }
+    )),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  #[test]
+  fn test_coercion() {
+    let schema = RowSchema::new(&json!({
+      "type": "object",
+      "title": "People",
+      "properties": {
+        "id": { "type": "integer", "minimum": 1 },
+        "score": { "type": ["number", "null"], "exclusiveMaximum": 100 },
+        "active": { "type": "boolean" },
+        "name": { "type": "string", "minLength": 2, "pattern": "^[A-Z]" },
+        "role": { "enum": ["admin", "user"] },
+        "note": {}
+      },
+      "required": ["id", "name"]
+    }))
+    .unwrap();
+    let check = |key: &str, text: Option<&str>| schema.check(schema.property(key).unwrap(), text);
+
+    assert_eq!(check("id", Some(" 42 ")), Ok(Coerced::Integer(42)));
+    assert_eq!(check("id", Some("")), Ok(Coerced::Missing));
+    assert_eq!(
+      check("id", Some("4.5")),
+      Err(("type", "must be integer".to_string()))
+    );
+    assert_eq!(
+      check("id", Some("0")),
+      Err(("minimum", "must be >= 1".to_string()))
+    );
+    assert_eq!(check("score", Some("99.5")), Ok(Coerced::Number(99.5)));
+    assert_eq!(check("score", Some("")), Ok(Coerced::Null));
+    assert_eq!(check("score", None), Ok(Coerced::Null));
+    assert_eq!(
+      check("score", Some("100")),
+      Err(("exclusiveMaximum", "must be < 100".to_string()))
+    );
+    assert_eq!(
+      check("score", Some("n/a")),
+      Err(("type", "must be null or number".to_string()))
+    );
+    assert_eq!(check("active", Some("TRUE")), Ok(Coerced::Boolean(true)));
+    assert_eq!(
+      check("active", None),
+      Err(("type", "must be boolean".to_string()))
+    );
+    assert_eq!(check("name", Some("Ann")), Ok(Coerced::String));
+    assert_eq!(check("name", Some("ann")).unwrap_err().0, "pattern");
+    assert_eq!(check("name", Some("A")).unwrap_err().0, "minLength");
+    assert_eq!(check("role", Some("user")), Ok(Coerced::String));
+    assert_eq!(
+      check("role", Some("root")),
+      Err(("enum", "must be one of the allowed values".to_string()))
+    );
+    assert_eq!(check("note", Some("12")), Ok(Coerced::String));
+    assert_eq!(schema.required(), ["id", "name"]);
+  }
+
+  #[test]
+  fn test_unsupported_schemas() {
+    let error = |schema: Value| RowSchema::new(&schema).unwrap_err().to_string();
+    assert_eq!(
+      error(json!({ "properties": { "a": { "oneOf": [] } } })),
+      "Unsupported keyword \"oneOf\" at /properties/a of schema"
+    );
+    assert_eq!(
+      error(json!({ "properties": { "a": { "type": "array" } } })),
+      "Unsupported type \"array\" at /properties/a of schema"
+    );
+    assert_eq!(
+      error(json!({ "type": "array" })),
+      "Schema type must be \"object\", got \"array\""
+    );
+    assert_eq!(error(json!([])), "Schema must be an object");
+  }
+}
@@ -0,0 +1,690 @@
+use color_eyre::eyre::Result;
+use std::fmt;
+use std::io::Read;
+
+use crate::decoder::{Decoder, TextEncoding};
+use crate::parser::CsvParserOptions;
+
+/// Separators looked for when every record has a single field, and when
+/// sniffing a dialect
+pub(crate) const CANDIDATES: [u8; 4] = [b',', b';', b'\t', b'|'];
+
+/// Records sampled for `suspicious_delimiter`
+const DELIMITER_SAMPLE: u64 = 100;
+
+/// Which checks `Linter` runs. All are on by default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintRules {
+  /// Records with a different number of fields than the header
+  pub ragged_rows: bool,
+  pub duplicate_headers: bool,
+  /// Header names that are empty or only whitespace
+  pub empty_headers: bool,
+  /// Lines ending differently from the first line
+  pub mixed_line_endings: bool,
+  /// Invalid UTF-8, reported once per line
+  pub encoding: bool,
+  /// Every record has one field, but another separator splits them evenly
+  pub suspicious_delimiter: bool,
+  /// Issues reported before the rest are left out
+  pub max_issues: usize,
+}
+
+impl Default for LintRules {
+  fn default() -> Self {
+    Self {
+      ragged_rows: true,
+      duplicate_headers: true,
+      empty_headers: true,
+      mixed_line_endings: true,
+      encoding: true,
+      suspicious_delimiter: true,
+      max_issues: 1000,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+  CrLf,
+  Lf,
+  Cr,
+}
+
+impl fmt::Display for LineEnding {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      LineEnding::CrLf => write!(f, "CRLF"),
+      LineEnding::Lf => write!(f, "LF"),
+      LineEnding::Cr => write!(f, "CR"),
+    }
+  }
+}
+
+/// A problem found by `Linter`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IssueKind {
+  RaggedRow {
+    expected: usize,
+    found: usize,
+  },
+  DuplicateHeader {
+    name: String,
+  },
+  EmptyHeader,
+  /// Reported at the first line ending with each other kind than the first
+  MixedLineEndings {
+    expected: LineEnding,
+    found: LineEnding,
+  },
+  InvalidUtf8,
+  SuspiciousDelimiter {
+    separator: u8,
+  },
+}
+
+impl IssueKind {
+  /// Short machine-readable name, e.g. `ragged-row`
+  pub fn code(&self) -> &'static str {
+    match self {
+      IssueKind::RaggedRow { .. } => "ragged-row",
+      IssueKind::DuplicateHeader { .. } => "duplicate-header",
+      IssueKind::EmptyHeader => "empty-header",
+      IssueKind::MixedLineEndings { .. } => "mixed-line-endings",
+      IssueKind::InvalidUtf8 => "invalid-utf8",
+      IssueKind::SuspiciousDelimiter { .. } => "suspicious-delimiter",
+    }
+  }
+}
+
+impl fmt::Display for IssueKind {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      IssueKind::RaggedRow { expected, found } => {
+        write!(f, "Expected {} fields, found {}", expected, found)
+      }
+      IssueKind::DuplicateHeader { name } => write!(f, "Duplicate header {:?}", name),
+      IssueKind::EmptyHeader => write!(f, "Empty header name"),
+      IssueKind::MixedLineEndings { expected, found } => {
+        write!(
+          f,
+          "Line ends with {}, earlier lines with {}",
+          found, expected
+        )
+      }
+      IssueKind::InvalidUtf8 => write!(f, "Invalid UTF-8"),
+      IssueKind::SuspiciousDelimiter { separator } => write!(
+        f,
+        "Every record has one field; the separator looks like {:?}",
+        *separator as char
+      ),
+    }
+  }
+}
+
+/// One issue. Lines and columns count from 1; columns are in characters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+  pub line: u64,
+  pub column: u64,
+  pub kind: IssueKind,
+}
+
+/// How many lines end with each kind of line break. Line breaks inside
+/// quoted fields are not counted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LineEndingCounts {
+  pub crlf: u64,
+  pub lf: u64,
+  pub cr: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintReport {
+  /// Data records, not counting the header record or blank lines
+  pub records: u64,
+  /// In input order, at most `max_issues` of them
+  pub issues: Vec<Issue>,
+  /// Whether issues were left out past `max_issues`
+  pub truncated: bool,
+  pub line_endings: LineEndingCounts,
+}
+
+impl LintReport {
+  pub fn is_valid(&self) -> bool {
+    self.issues.is_empty()
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+  FieldStart,
+  Unquoted,
+  Quoted,
+  // Just saw the escape character inside a quoted field
+  EscapeInQuoted,
+  // Just saw a quote inside a quoted field: an escaped quote or the end
+  QuoteInQuoted,
+  // Past the closing quote, before the next separator or line break
+  AfterQuote,
+}
+
+// How often a candidate separator appears in the sampled records
+#[derive(Clone, Copy)]
+struct Candidate {
+  count: usize,
+  per_record: Option<usize>,
+  even: bool,
+}
+
+const CANDIDATE: Candidate = Candidate {
+  count: 0,
+  per_record: None,
+  even: true,
+};
+
+/// Checks CSV for common problems while streaming, without building rows:
+/// ragged rows, duplicate or empty header names, mixed line endings,
+/// invalid UTF-8 and a separator that does not fit the input. Of the parser
+/// options only `separator`, `quote`, `escape`, `headers` and `encoding`
+/// apply. A BOM selects the encoding as it does for the parser; LF, CRLF and
+/// a lone CR all end a line.
+pub struct Linter {
+  separator: u8,
+  quote: u8,
+  escape: u8,
+  given_headers: Option<Vec<String>>,
+  encoding: Option<TextEncoding>,
+  detect_encoding: bool,
+  rules: LintRules,
+  input: Decoder,
+  report: LintReport,
+  state: State,
+  // Position of the current character
+  line: u64,
+  column: u64,
+  record_line: u64,
+  // Position of a CR that may be followed by LF
+  pending_cr: Option<(u64, u64)>,
+  fields: usize,
+  record_empty: bool,
+  // Header names and the columns they start at, while the header record is
+  // read from the input
+  headers: Option<Vec<(String, u64)>>,
+  field: Vec<u8>,
+  field_column: u64,
+  expected_fields: Option<usize>,
+  first_ending: Option<LineEnding>,
+  reported_endings: Vec<LineEnding>,
+  // Continuation bytes the current UTF-8 sequence still needs, the range
+  // the next one must be in and the column the sequence started at
+  utf8_needed: u8,
+  utf8_range: (u8, u8),
+  utf8_column: u64,
+  utf8_reported_line: u64,
+  candidates: [Candidate; CANDIDATES.len()],
+  sampled: u64,
+  separator_seen: bool,
+}
+
+impl Linter {
+  pub fn new(options: &CsvParserOptions, rules: LintRules) -> Self {
+    let escape = match options.escape {
+      0 => options.quote,
+      escape => escape,
+    };
+    Self::with(
+      options.separator,
+      options.quote,
+      escape,
+      options.headers.clone(),
+      options.encoding,
+      options.detect_encoding,
+      rules,
+    )
+  }
+
+  fn with(
+    separator: u8,
+    quote: u8,
+    escape: u8,
+    given_headers: Option<Vec<String>>,
+    encoding: Option<TextEncoding>,
+    detect_encoding: bool,
+    rules: LintRules,
+  ) -> Self {
+    Self {
+      separator,
+      quote,
+      escape,
+      headers: given_headers.is_none().then(Vec::new),
+      expected_fields: given_headers
+        .as_ref()
+        .filter(|headers| !headers.is_empty())
+        .map(Vec::len),
+      given_headers,
+      rules,
+      encoding,
+      detect_encoding,
+      input: Decoder::new(encoding, detect_encoding),
+      report: LintReport::default(),
+      state: State::FieldStart,
+      line: 1,
+      column: 0,
+      record_line: 1,
+      pending_cr: None,
+      fields: 0,
+      record_empty: true,
+      field: Vec::new(),
+      field_column: 1,
+      first_ending: None,
+      reported_endings: Vec::new(),
+      utf8_needed: 0,
+      utf8_range: (0x80, 0xBF),
+      utf8_column: 0,
+      utf8_reported_line: 0,
+      candidates: [CANDIDATE; CANDIDATES.len()],
+      sampled: 0,
+      separator_seen: false,
+    }
+  }
+
+  /// Checks a chunk of input. Chunks may split records anywhere.
+  pub fn feed(&mut self, chunk: &[u8]) -> Result<()> {
+    self.input.buffer.extend_from_slice(chunk);
+    self.input.decode(false, false)?;
+    self.scan_decoded();
+    Ok(())
+  }
+
+  /// Ends the input and returns the report. The linter is ready for another
+  /// input afterwards.
+  pub fn finish(&mut self) -> Result<LintReport> {
+    self.input.decode(false, true)?;
+    self.scan_decoded();
+
+    if self.utf8_needed > 0 {
+      self.invalid_utf8(self.utf8_column);
+    }
+    if let Some((line, column)) = self.pending_cr.take() {
+      self.line_ending(LineEnding::Cr, line, column);
+      self.end_line();
+    }
+    // The last record may end without a line break
+    self.end_record();
+
+    if self.rules.suspicious_delimiter && self.sampled > 0 && !self.separator_seen {
+      let likely = CANDIDATES
+        .iter()
+        .zip(&self.candidates)
+        .filter(|(&separator, candidate)| separator != self.separator && candidate.even)
+        .filter_map(|(&separator, candidate)| Some((separator, candidate.per_record?)))
+        .filter(|&(_, per_record)| per_record > 0)
+        .max_by_key(|&(_, per_record)| per_record);
+      if let Some((separator, _)) = likely {
+        self.issue(1, 1, IssueKind::SuspiciousDelimiter { separator });
+      }
+    }
+
+    let next = Self::with(
+      self.separator,
+      self.quote,
+      self.escape,
+      self.given_headers.clone(),
+      self.encoding,
+      self.detect_encoding,
+      self.rules.clone(),
+    );
+    Ok(std::mem::replace(self, next).report)
+  }
+
+  fn scan_decoded(&mut self) {
+    let mut decoded = std::mem::take(&mut self.input.utf8_buffer);
+    for &byte in &decoded {
+      self.byte(byte);
+    }
+    decoded.clear();
+    self.input.utf8_buffer = decoded;
+  }
+
+  fn byte(&mut self, byte: u8) {
+    if let Some((line, column)) = self.pending_cr.take() {
+      if byte == b'\n' {
+        self.line_ending(LineEnding::CrLf, line, column);
+        self.end_line();
+        self.end_record();
+        return;
+      }
+      self.line_ending(LineEnding::Cr, line, column);
+      self.end_line();
+      self.end_record();
+    }
+
+    // Count characters, not UTF-8 continuation bytes
+    if byte & 0xC0 != 0x80 {
+      self.column += 1;
+    }
+    if self.rules.encoding {
+      self.check_utf8(byte);
+    }
+
+    match self.state {
+      State::Quoted => {
+        if byte == self.escape && self.escape != self.quote {
+          self.state = State::EscapeInQuoted;
+        } else if byte == self.quote {
+          self.state = State::QuoteInQuoted;
+        } else {
+          self.quoted_byte(byte);
+        }
+        return;
+      }
+      State::EscapeInQuoted => {
+        self.state = State::Quoted;
+        self.quoted_byte(byte);
+        return;
+      }
+      State::QuoteInQuoted if byte == self.quote => {
+        self.state = State::Quoted;
+        self.push_field(byte);
+        return;
+      }
+      State::QuoteInQuoted => self.state = State::AfterQuote,
+      _ => {}
+    }
+
+    if byte == self.separator {
+      self.end_field();
+      self.fields += 1;
+      self.record_empty = false;
+      self.state = State::FieldStart;
+      return;
+    }
+
+    match byte {
+      b'\r' => self.pending_cr = Some((self.line, self.column)),
+      b'\n' => {
+        self.line_ending(LineEnding::Lf, self.line, self.column);
+        self.end_line();
+        self.end_record();
+      }
+      _ => {
+        self.record_empty = false;
+        if let Some(i) = CANDIDATES.iter().position(|&c| c == byte) {
+          self.candidates[i].count += 1;
+        }
+        match self.state {
+          State::FieldStart if byte == self.quote => self.state = State::Quoted,
+          State::FieldStart => {
+            self.state = State::Unquoted;
+            self.push_field(byte);
+          }
+          _ => self.push_field(byte),
+        }
+      }
+    }
+  }
+
+  fn quoted_byte(&mut self, byte: u8) {
+    if byte == b'\n' {
+      self.end_line();
+    }
+    self.push_field(byte);
+  }
+
+  fn push_field(&mut self, byte: u8) {
+    if self.headers.is_some() {
+      self.field.push(byte);
+    }
+  }
+
+  fn end_field(&mut self) {
+    if let Some(headers) = &mut self.headers {
+      let name = String::from_utf8_lossy(&self.field).into_owned();
+      headers.push((name, self.field_column));
+      self.field.clear();
+    }
+    self.field_column = self.column + 1;
+  }
+
+  fn end_line(&mut self) {
+    self.line += 1;
+    self.column = 0;
+  }
+
+  fn end_record(&mut self) {
+    if !self.record_empty {
+      self.end_field();
+      let found = self.fields + 1;
+
+      if let Some(headers) = self.headers.take() {
+        self.check_headers(&headers);
+        self.expected_fields = Some(found);
+      } else {
+        self.report.records += 1;
+        match self.expected_fields {
+          None => self.expected_fields = Some(found),
+          Some(expected) if expected != found && self.rules.ragged_rows => {
+            let kind = IssueKind::RaggedRow { expected, found };
+            self.issue(self.record_line, 1, kind);
+          }
+          Some(_) => {}
+        }
+      }
+
+      if self.sampled < DELIMITER_SAMPLE {
+        self.sampled += 1;
+        self.separator_seen |= found > 1;
+        for candidate in &mut self.candidates {
+          match candidate.per_record {
+            None => candidate.per_record = Some(candidate.count),
+            Some(n) if n != candidate.count => candidate.even = false,
+            Some(_) => {}
+          }
+        }
+      }
+    }
+
+    for candidate in &mut self.candidates {
+      candidate.count = 0;
+    }
+    self.fields = 0;
+    self.record_empty = true;
+    self.record_line = self.line;
+    self.field_column = 1;
+    self.state = State::FieldStart;
+  }
+
+  fn check_headers(&mut self, headers: &[(String, u64)]) {
+    for (i, (name, column)) in headers.iter().enumerate() {
+      if name.trim().is_empty() {
+        if self.rules.empty_headers {
+          self.issue(self.record_line, *column, IssueKind::EmptyHeader);
+        }
+      } else if self.rules.duplicate_headers && headers[..i].iter().any(|(seen, _)| seen == name) {
+        let kind = IssueKind::DuplicateHeader { name: name.clone() };
+        self.issue(self.record_line, *column, kind);
+      }
+    }
+  }
+
+  fn line_ending(&mut self, ending: LineEnding, line: u64, column: u64) {
+    let counts = &mut self.report.line_endings;
+    match ending {
+      LineEnding::CrLf => counts.crlf += 1,
+      LineEnding::Lf => counts.lf += 1,
+      LineEnding::Cr => counts.cr += 1,
+    }
+
+    let first = *self.first_ending.get_or_insert(ending);
+    if self.rules.mixed_line_endings && ending != first && !self.reported_endings.contains(&ending)
+    {
+      self.reported_endings.push(ending);
+      let kind = IssueKind::MixedLineEndings {
+        expected: first,
+        found: ending,
+      };
+      self.issue(line, column, kind);
+    }
+  }
+
+  fn check_utf8(&mut self, byte: u8) {
+    if self.utf8_needed > 0 {
+      let (low, high) = self.utf8_range;
+      if (low..=high).contains(&byte) {
+        self.utf8_needed -= 1;
+        self.utf8_range = (0x80, 0xBF);
+        return;
+      }
+      // The sequence ended early; `byte` starts something new
+      self.utf8_needed = 0;
+      self.invalid_utf8(self.utf8_column);
+    }
+
+    let (needed, range) = match byte {
+      0x00..=0x7F => return,
+      0xC2..=0xDF => (1, (0x80, 0xBF)),
+      0xE0 => (2, (0xA0, 0xBF)),
+      0xED => (2, (0x80, 0x9F)),
+      0xE1..=0xEF => (2, (0x80, 0xBF)),
+      0xF0 => (3, (0x90, 0xBF)),
+      0xF1..=0xF3 => (3, (0x80, 0xBF)),
+      0xF4 => (3, (0x80, 0x8F)),
+      _ => {
+        self.invalid_utf8(self.column.max(1));
+        return;
+      }
+    };
+    self.utf8_needed = needed;
+    self.utf8_range = range;
+    self.utf8_column = self.column;
+  }
+
+  // Reported once per line, so a file in another encoding does not bury
+  // the other issues
+  fn invalid_utf8(&mut self, column: u64) {
+    if self.utf8_reported_line != self.line {
+      self.utf8_reported_line = self.line;
+      self.issue(self.line, column, IssueKind::InvalidUtf8);
+    }
+  }
+
+  fn issue(&mut self, line: u64, column: u64, kind: IssueKind) {
+    if self.report.issues.len() < self.rules.max_issues {
+      self.report.issues.push(Issue { line, column, kind });
+    } else {
+      self.report.truncated = true;
+    }
+  }
+}
+
+/// Lints all of `input` with `Linter`
+pub fn lint(
+  mut input: impl Read,
+  options: &CsvParserOptions,
+  rules: LintRules,
+) -> Result<LintReport> {
+  let mut linter = Linter::new(options, rules);
+  let mut chunk = vec![0; 64 * 1024];
+
+  loop {
+    let read = input.read(&mut chunk)?;
+    if read == 0 {
+      return linter.finish();
+    }
+    linter.feed(&chunk[..read])?;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn check(input: &[u8], options: CsvParserOptions) -> LintReport {
+    let mut linter = Linter::new(&options, LintRules::default());
+    let (head, tail) = input.split_at(input.len() / 2);
+    linter.feed(head).unwrap();
+    linter.feed(tail).unwrap();
+    linter.finish().unwrap()
+  }
+
+  fn codes(report: &LintReport) -> Vec<(u64, u64, &'static str)> {
+    report
+      .issues
+      .iter()
+      .map(|issue| (issue.line, issue.column, issue.kind.code()))
+      .collect()
+  }
+
+  #[test]
+  fn test_clean_input() {
+    let report = check(
+      b"\xEF\xBB\xBFid,\"na\"\"me\"\n1,\"x\r\ny\"\n\n2,\xC3\xA9\n",
+      Default::default(),
+    );
+    assert!(report.is_valid(), "{:?}", report.issues);
+    assert_eq!(report.records, 2);
+    assert_eq!(report.line_endings.lf, 4);
+  }
+
+  #[test]
+  fn test_issues() {
+    let report = check(
+      b"id,name,,id\r\n1,a,b,c\r\n2,\xE9,x\n3,b,c,d\r\n4\rx,y,z,w",
+      Default::default(),
+    );
+    assert_eq!(
+      codes(&report),
+      [
+        (1, 9, "empty-header"),
+        (1, 10, "duplicate-header"),
+        (3, 3, "invalid-utf8"),
+        (3, 6, "mixed-line-endings"),
+        (3, 1, "ragged-row"),
+        (5, 2, "mixed-line-endings"),
+        (5, 1, "ragged-row"),
+      ]
+    );
+    assert_eq!(report.records, 5);
+    assert_eq!(
+      report.line_endings,
+      LineEndingCounts {
+        crlf: 3,
+        lf: 1,
+        cr: 1
+      }
+    );
+    assert_eq!(report.issues[1].kind.to_string(), "Duplicate header \"id\"");
+  }
+
+  #[test]
+  fn test_suspicious_delimiter() {
+    let report = check(b"a;b;c\n1;2;3\n4;5;6\n", Default::default());
+    assert_eq!(codes(&report), [(1, 1, "suspicious-delimiter")]);
+
+    let options = CsvParserOptions {
+      separator: b';',
+      ..Default::default()
+    };
+    assert!(check(b"a;b;c\n1;2;3\n4;\"5,6\";7\n", options).is_valid());
+  }
+
+  #[test]
+  fn test_given_headers_and_limit() {
+    let options = CsvParserOptions {
+      headers: Some(vec!["a".to_string(), "a".to_string()]),
+      ..Default::default()
+    };
+    let report = check(b"1,2\n3\n", options);
+    assert_eq!(codes(&report), [(2, 1, "ragged-row")]);
+    assert_eq!(report.records, 2);
+
+    let rules = LintRules {
+      max_issues: 1,
+      ..Default::default()
+    };
+    let report = lint(&b"a,b\n1\n2\n"[..], &Default::default(), rules).unwrap();
+    assert_eq!(report.issues.len(), 1);
+    assert!(report.truncated);
+  }
+}
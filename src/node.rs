@@ -0,0 +1,5278 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use napi::{
+  bindgen_prelude::{AsyncTask, Buffer, Either, Object, Result, ToNapiValue},
+  Env, Error, JsArrayBuffer, JsDataView, JsFunction, JsString, JsTypeError, JsTypedArray,
+  JsUnknown, NapiRaw, NapiValue, Ref, Status, Task, TypedArrayType, ValueType,
+};
+
+use crate::aggregate::{Aggregator, Metric, MetricSpec};
+use crate::bench;
+use crate::compress::{Compression, Compressor};
+use crate::dates::{DateColumn, DateOutput};
+use crate::decoder::{Decoder, TextEncoding};
+use crate::dedupe;
+use crate::dialect::sniff_dialect;
+use crate::diff::{diff as diff_inputs, ChangeKind, Diff, DiffOptions as RustDiffOptions};
+use crate::distinct::distinct as distinct_values;
+use crate::expand::{header_path, PathSegment};
+use crate::hash::{Digest, HashAlgorithm};
+use crate::hooks;
+use crate::index::CsvIndex as RustCsvIndex;
+use crate::join::{JoinKind, JoinOptions, Joiner};
+use crate::json::{self, JsonFormat};
+use crate::json_schema::{Coerced, RowSchema};
+use crate::lint::{self, LineEnding, LintReport as RustLintReport, LintRules as RustLintRules};
+use crate::numbers::NumberColumn;
+use crate::parser::{
+  CellRange, CellType, CsvParser as RustCsvParser, CsvParserOptions, EmptyHeaders, ExtraColumns,
+  InvalidRow, LineFilter, MalformedJson, MaxColumnsAction, OversizeField, QuotedNewlines,
+  RecordFormat, RecordTerminator, Redaction, RowObject, Sections, SkipComments, SkipEmptyLines,
+  Strict, UnsafeHeaders, UnterminatedQuote, Utf8Validation, Validator,
+};
+use crate::reader;
+use crate::reshape::{ReshapeOptions, Reshaper, Shape};
+use crate::rewrite::{RewriteOptions, Rewriter};
+use crate::sample::sample_rows;
+use crate::schema::{self, ColumnSchema as RustColumnSchema};
+use crate::sort;
+use crate::split::{parse_size, split_file, ShardLimit};
+#[cfg(feature = "sqlite")]
+use crate::sqlite::{self, SqlType, SqliteOptions};
+use crate::state::{StateReader, StateWriter};
+use crate::stats::{ColumnStats as RustColumnStats, StatsCollector};
+use crate::transcode::Transcoder as RustTranscoder;
+use crate::validate::{Rfc4180Validator, ValidationReport as RustValidationReport};
+use crate::writer::{
+  read_append_target, CsvWriter as RustCsvWriter, CsvWriterOptions, Encoder, QuoteStyle, SqlInsert,
+};
+
+#[napi(object)]
+#[derive(Default)]
+pub struct JsCsvParserOptions {
+  pub escape: Option<String>,
+  pub quote: Option<String>,
+  /// Column separator, `,` by default. `"auto"` chooses among `,`, `;`, tab
+  /// and `|` from the first lines of each input. It also turns on
+  /// `sepDirective`, so an Excel `sep=` line decides instead.
+  pub separator: Option<String>,
+  pub newline: Option<String>,
+  pub raw: Option<bool>,
+  /// Check each row has as many cells as there are headers: `true` fails on
+  /// the first row that does not, `"report"` leaves such rows out and lists
+  /// them in `getRaggedRows()`
+  #[napi(ts_type = "boolean | 'report'")]
+  pub strict: Option<Either<bool, String>>,
+  pub max_row_bytes: Option<i64>,
+  pub headers: Option<JsUnknown>,
+  pub skip_comments: Option<JsUnknown>,
+  pub skip_lines: Option<i64>,
+  #[napi(ts_type = "boolean | 'greedy'")]
+  pub skip_empty_lines: Option<Either<bool, String>>,
+  #[napi(ts_type = "'drop' | 'collect' | ExtraColumnsOptions")]
+  pub extra_columns: Option<Either<String, ExtraColumnsOptions>>,
+  pub skip_empty_fields: Option<bool>,
+  /// `"all"` also strips whitespace inside quoted cells
+  #[napi(ts_type = "boolean | 'all'")]
+  pub trim: Option<Either<bool, String>>,
+  pub ltrim: Option<bool>,
+  pub rtrim: Option<bool>,
+  pub relax_quotes: Option<bool>,
+  pub trailing_comments: Option<bool>,
+  pub sep_directive: Option<bool>,
+  pub excel: Option<bool>,
+  pub batch_size: Option<i64>,
+  #[napi(ts_type = "'full' | 'fast' | 'off'")]
+  pub validate_utf8: Option<String>,
+  pub map_headers: Option<JsFunction>,
+  pub map_values: Option<JsFunction>,
+  #[napi(ts_type = "boolean | DedupeOptions")]
+  pub dedupe: Option<Either<bool, DedupeOptions>>,
+  #[napi(ts_type = "Record<string, ColumnTypeOptions>")]
+  pub column_types: Option<HashMap<String, ColumnTypeOptions>>,
+  pub expand_headers: Option<bool>,
+  /// Columns whose cells are split into arrays: header names mapped to the
+  /// separator between items, or a list of header names that use
+  /// `arraySeparator`
+  #[napi(ts_type = "Record<string, string> | Array<string>")]
+  pub array_columns: Option<Either<Vec<String>, HashMap<String, String>>>,
+  /// Separator for `arrayColumns` given as a list (default `;`)
+  pub array_separator: Option<String>,
+  /// Columns whose cells are parsed as JSON, by header name
+  pub json_columns: Option<Vec<String>>,
+  /// `"error"` (default) fails the row on a cell of `jsonColumns` that is
+  /// not valid JSON; `"keep"` keeps the cell as a string
+  #[napi(ts_type = "'error' | 'keep'")]
+  pub malformed_json: Option<String>,
+  /// Add each row's source line, without its line break, under `rawLineKey`.
+  /// It is a Buffer with `raw`, otherwise a string.
+  pub include_raw_line: Option<bool>,
+  /// Key for `includeRawLine` (default `_raw`)
+  pub raw_line_key: Option<String>,
+  /// Most bytes of input held while waiting for the rest of a row, e.g. a
+  /// quoted field that never closes. Past it `push()` fails and the partial
+  /// row is dropped.
+  pub max_buffered_bytes: Option<i64>,
+  /// Most cells a row, header row included, may have
+  pub max_columns: Option<u32>,
+  /// `"error"` (default) fails on a row with more than `maxColumns` cells;
+  /// `"truncate"` drops the cells past the limit
+  #[napi(ts_type = "'error' | 'truncate'")]
+  pub max_columns_action: Option<String>,
+  /// Keep only data lines matching a regular expression, tested on the raw
+  /// line before it is split into cells
+  pub line_filter: Option<LineFilterOptions>,
+  /// Encoding of the input: a legacy encoding such as `"latin1"` or `"sjis"`,
+  /// `"utf-16le"`, `"utf-32le"`, `"utf-32be"` or an EBCDIC code page such as
+  /// `"ibm037"`. By default input is UTF-8, or UTF-16 or UTF-32 when it
+  /// starts with a BOM. `"auto"` guesses the encoding of input without a
+  /// BOM from its first 64 KiB.
+  pub encoding: Option<String>,
+  /// How row objects are created: `"plain"` (default) objects,
+  /// `"null-prototype"` objects without a prototype, where a header such as
+  /// `__proto__` is an ordinary key, `"frozen"` objects, or `"entries"`:
+  /// arrays of `[key, value]` pairs in column order
+  #[napi(ts_type = "'plain' | 'null-prototype' | 'frozen' | 'entries'")]
+  pub row_object: Option<String>,
+  /// What happens to header row names `__proto__`, `constructor` and
+  /// `prototype`, or with `expandHeaders` paths through them: `"keep"`
+  /// (default), `"rename"` appends `_`, `"drop"` leaves the column out and
+  /// `"error"` fails
+  #[napi(ts_type = "'keep' | 'rename' | 'drop' | 'error'")]
+  pub unsafe_headers: Option<String>,
+  /// Defaults for a family of files, which the other options override:
+  /// `"tsv"` and `"psv"` separate cells with tabs and pipes, `"excel"` turns
+  /// on `excel` and `sepDirective`, and `"postgres"` reads the PostgreSQL
+  /// `COPY` text format, with tabs, backslash escapes and `\N` nulls
+  #[napi(ts_type = "'csv' | 'tsv' | 'psv' | 'excel' | 'postgres'")]
+  pub dialect: Option<String>,
+  /// Cells written exactly as this, before any unescaping, are `null`
+  pub null_value: Option<String>,
+  /// Add a hash of each row's source line, without its line break, as a
+  /// hex string under `__hash`
+  #[napi(ts_type = "'xxh64' | 'sha256'")]
+  pub row_hash: Option<String>,
+  /// Hash all input as it is pushed, for the `digest` at the end
+  #[napi(ts_type = "'xxh64' | 'sha256'")]
+  pub digest: Option<String>,
+  /// Bound memory where `maxRowBytes`, `maxBufferedBytes` and `maxColumns`
+  /// are not given (16 MiB, 64 MiB and 16384), and turn an internal panic
+  /// into an error instead of crashing the process
+  pub hardened: Option<bool>,
+  /// Separator of the fraction in `number` columns of `columnTypes` (default
+  /// `.`)
+  pub decimal_separator: Option<String>,
+  /// Separator between groups of three digits in `number` columns of
+  /// `columnTypes`, such as `.` in `1.234,56`; none by default
+  pub thousands_separator: Option<String>,
+  /// What happens to the last record when the input ends inside a quoted
+  /// field: `"emit"` (default) keeps it, the open field holding the rest of
+  /// the input as it is; `"error"` fails it with the line the field opened
+  /// on; `"drop"` leaves it out
+  #[napi(ts_type = "'error' | 'emit' | 'drop'")]
+  pub on_unterminated_quote: Option<String>,
+  /// Split the input into tables, each with its own header line: `true` at
+  /// blank lines, or at the lines given as `SectionOptions`
+  #[napi(ts_type = "boolean | SectionOptions")]
+  pub sections: Option<Either<bool, SectionOptions>>,
+  /// Most bytes the value of a cell, header included, may take up, an
+  /// escaped quote counting once
+  pub max_field_bytes: Option<i64>,
+  /// `"error"` (default) fails on a row with a cell over `maxFieldBytes`;
+  /// `"truncate"` cuts the cell to the limit
+  #[napi(ts_type = "'error' | 'truncate'")]
+  pub on_oversize_field: Option<String>,
+  /// Line breaks inside quoted cells: `"keep"` (default) leaves them, `"lf"`
+  /// turns `\r\n` and `\r` into `\n`, `"escape"` writes each as the two
+  /// characters `\n` and `"strip"` removes them
+  #[napi(ts_type = "'keep' | 'lf' | 'escape' | 'strip'")]
+  pub quoted_newlines: Option<String>,
+  /// Header names mapped to other names of the same column, such as
+  /// `{ email: ["E-Mail", "email_address"] }`: a header matching a key or
+  /// one of its names, ignoring case, is read as the key
+  #[napi(ts_type = "Record<string, Array<string>>")]
+  pub column_aliases: Option<HashMap<String, Vec<String>>>,
+  /// Regular expressions the cells of columns must match, by header name.
+  /// Missing cells are checked as empty.
+  pub validators: Option<HashMap<String, String>>,
+  /// `"error"` (default) fails on a row with a cell failing its validator;
+  /// `"skip"` leaves the row out; `"annotate"` keeps it and lists the failing
+  /// columns under `__violations`
+  #[napi(ts_type = "'error' | 'skip' | 'annotate'")]
+  pub on_violation: Option<String>,
+  /// JSON Schema (a draft 2020-12 subset) the rows must match. Cells of its
+  /// properties are converted to the types they allow.
+  #[napi(ts_type = "object")]
+  pub schema: Option<serde_json::Value>,
+  /// Columns hidden before rows reach JS, by header name: `"hash"` replaces
+  /// cells with the SHA-256 of `redactSalt` and the cell, `"mask"` with `*`
+  /// but for the last four characters, and `"drop"` leaves the column out
+  #[napi(ts_type = "Record<string, 'hash' | 'mask' | 'drop'>")]
+  pub redact: Option<HashMap<String, String>>,
+  /// Prefix hashed with each cell by `redact: "hash"` (default empty)
+  pub redact_salt: Option<String>,
+  /// Also return the detected header row as the first row, keyed by itself,
+  /// e.g. `{ id: "id", name: "name" }` (default false)
+  pub pass_header_row: Option<bool>,
+  /// `"newline"` (default) ends records at `newline`; `"any"` also at `\n`,
+  /// `\r\n` and a lone `\r`, for files that mix them
+  #[napi(ts_type = "'newline' | 'any'")]
+  pub record_terminator: Option<String>,
+  /// Name of a native hook registered with `register_batch_hook` by the
+  /// addon embedding this parser, run on each batch of records before they
+  /// become objects. Rows then hold strings only.
+  pub batch_hook: Option<String>,
+  /// Columns whose detected header is empty or `_`: `"drop"` (default)
+  /// leaves them out of rows, as `getDroppedColumns` lists, `"keep-positional"`
+  /// keys them `_<index>` and `"error"` fails the header row
+  #[napi(ts_type = "'drop' | 'keep-positional' | 'error'")]
+  pub empty_headers: Option<String>,
+  /// Also give each row its record's bytes, without the line break, as a
+  /// Buffer under `__raw`. The Buffers of the rows of one call are views of
+  /// one Buffer, so none is copied on its own (default false)
+  pub raw_rows: Option<bool>,
+}
+
+/// How the cells of one column are converted
+#[napi(object)]
+pub struct ColumnTypeOptions {
+  #[napi(js_name = "type", ts_type = "'date' | 'number'")]
+  pub kind: String,
+  /// For dates, chrono `strftime` format, e.g. `%d/%m/%Y %H:%M`; ISO 8601
+  /// when omitted
+  pub format: Option<String>,
+  /// For dates, zone of values without an offset: `UTC` (default), an offset
+  /// such as `+02:00` or an IANA name such as `Europe/Oslo`
+  pub tz: Option<String>,
+  /// For dates, `"date"` (default) for `Date` objects or `"millis"` for epoch
+  /// milliseconds
+  #[napi(ts_type = "'date' | 'millis'")]
+  pub output: Option<String>,
+  /// For numbers, strip currency symbols, read `(12.50)` as `-12.5` and
+  /// accept thousands separators: `thousandsSeparator`, or else `,` (`.`
+  /// when `decimalSeparator` is `,`)
+  pub currency: Option<bool>,
+  /// For numbers, read `12.5%` as `0.125`
+  pub percent: Option<bool>,
+}
+
+#[napi(object)]
+pub struct SectionOptions {
+  /// A blank line ends the table before it (default `false`)
+  pub blank_lines: Option<bool>,
+  /// A line starting with this prefix starts a table named by the rest of
+  /// the line, e.g. `"## "` for `## Orders`
+  pub marker: Option<String>,
+}
+
+#[napi(object)]
+pub struct ExtraColumnsOptions {
+  /// Key cells beyond the headers `<prefix><index>` (default `_`)
+  pub prefix: Option<String>,
+  /// Gather cells beyond the headers into one array under this key instead
+  pub collect: Option<String>,
+}
+
+#[napi(object)]
+pub struct LineFilterOptions {
+  /// Regular expression in Rust `regex` syntax, e.g. `ERROR|WARN`
+  pub pattern: String,
+  /// Keep the lines that do not match instead
+  pub negate: Option<bool>,
+}
+
+#[napi(object)]
+pub struct DedupeOptions {
+  /// Columns that identify a row; every cell when omitted
+  pub keys: Option<Vec<String>>,
+  /// Remember keys in a Bloom filter instead of an exact set, so memory
+  /// stays bounded. A unique row is dropped now and then, at about
+  /// `falsePositiveRate`.
+  pub bloom: Option<BloomOptions>,
+}
+
+#[napi(object)]
+pub struct BloomOptions {
+  /// Rows the filter is sized for
+  pub capacity: i64,
+  /// Chance of taking a new key for a seen one while under `capacity`
+  /// (default 0.001)
+  pub false_positive_rate: Option<f64>,
+}
+
+#[napi(object)]
+pub struct ParsedRow {
+  #[napi(writable = true)]
+  pub values: Vec<String>,
+}
+
+/// Bytes currently held by a parser, by where they are held
+#[napi(object)]
+pub struct MemoryUsage {
+  /// Raw input not yet decoded (e.g. a split UTF-16 code unit)
+  pub input_buffer: i64,
+  /// Decoded input waiting for the rest of its row
+  pub decode_buffer: i64,
+  /// Header names, keys remembered by `dedupe` and other strings kept for
+  /// the lifetime of the parser
+  pub strings: i64,
+  /// Scratch arena for per-row temporary data
+  pub arena: i64,
+  pub total: i64,
+}
+
+/// A row left out by `strict: "report"`
+#[napi(object)]
+pub struct RaggedRow {
+  /// The line the row starts on, counting from 1
+  pub line: i64,
+  /// The number of headers
+  pub expected: u32,
+  pub actual: u32,
+}
+
+/// A cell that failed its validator or schema property, or a key the schema
+/// requires and the row lacks
+#[napi(object)]
+pub struct CellViolation {
+  /// The line the row starts on, counting from 1
+  pub line: i64,
+  pub column: String,
+  /// The schema keyword that failed, `pattern` for `validators`
+  pub keyword: String,
+  pub message: String,
+}
+
+/// Counters of a `CsvParser` since it was created or last reset
+#[napi(object)]
+pub struct ParserMetrics {
+  /// Input bytes pushed, before decoding
+  pub bytes_processed: i64,
+  /// Rows returned, not counting header, comment or skipped lines
+  pub rows_emitted: i64,
+  /// Lines skipped as comments
+  pub comment_lines: i64,
+  /// Rows dropped by `dedupe`
+  pub duplicates: i64,
+  /// Calls that failed with an error
+  pub errors: i64,
+  /// Milliseconds spent in `push()`, `next()`, `flush()` and their packed
+  /// variants
+  pub parse_time_ms: f64,
+  /// `bytesProcessed` and `rowsEmitted` per second of `parseTimeMs`
+  pub bytes_per_second: f64,
+  pub rows_per_second: f64,
+}
+
+/// Where parsing time went since profiling was enabled, in milliseconds
+#[napi(object)]
+pub struct ParserProfile {
+  /// Detecting the encoding, converting input to UTF-8 and validating it
+  pub decode_ms: f64,
+  /// Finding row ends and splitting rows into cells
+  pub tokenize_ms: f64,
+  /// Creating row objects and their keys and values
+  pub build_ms: f64,
+}
+
+/// Where a `CsvParser` is in its input, for finding out why a stream holds
+/// back a row
+#[napi(object)]
+pub struct ParserState {
+  /// The line the next row starts on, from 1
+  pub line_number: i64,
+  /// Whether the buffered partial row has a quoted field left open, so the
+  /// parser waits for its closing quote
+  pub in_quote: bool,
+  /// Input held without completing a row, as `maxBufferedBytes` counts it
+  pub buffered_bytes: i64,
+  /// Whether the header row has been read, or there is none to read
+  pub headers_resolved: bool,
+}
+
+/// Where to resume a parse: read the source from `offset` and continue with
+/// a parser from `CsvParser.fromCheckpoint()`
+#[napi(object)]
+pub struct Checkpoint {
+  /// Source bytes pushed so far
+  pub offset: i64,
+  /// The parser state, as from `serializeState()`
+  pub state: Buffer,
+}
+
+#[derive(Default)]
+struct Metrics {
+  bytes: u64,
+  rows: u64,
+  errors: u64,
+  parse_time: Duration,
+}
+
+#[derive(Default, Clone, Copy)]
+struct Profile {
+  decode: Duration,
+  tokenize: Duration,
+  build: Duration,
+}
+
+// Splits the time of one pass over buffered rows between tokenizing and
+// building objects. Rows are only timed when profiling is on.
+struct RowTimer {
+  started: Option<Instant>,
+  // Time spent in `parse_row`, and the part of it spent tokenizing
+  rows: Duration,
+  tokenize: Duration,
+}
+
+impl RowTimer {
+  fn new(profile: &Option<Profile>) -> Self {
+    Self {
+      started: profile.is_some().then(Instant::now),
+      rows: Duration::ZERO,
+      tokenize: Duration::ZERO,
+    }
+  }
+
+  fn row<T>(&mut self, row: impl FnOnce(Option<&mut Duration>) -> T) -> T {
+    if self.started.is_none() {
+      return row(None);
+    }
+    let started = Instant::now();
+    let result = row(Some(&mut self.tokenize));
+    self.rows += started.elapsed();
+    result
+  }
+
+  // Time outside `parse_row`, finding where rows end, counts as tokenizing
+  fn finish(self, profile: &mut Option<Profile>) {
+    if let (Some(started), Some(profile)) = (self.started, profile) {
+      profile.tokenize += started.elapsed().saturating_sub(self.rows) + self.tokenize;
+      profile.build += self.rows.saturating_sub(self.tokenize);
+    }
+  }
+}
+
+/// Parses chunks of CSV into row objects. Methods returning rows take the
+/// row type as `T`, `Record<string, string>` by default; pass another when
+/// options change the values, such as numbers and `Date`s from
+/// `columnTypes`, JSON values from `jsonColumns`, arrays from `arrayColumns`
+/// and `extraColumns: "collect"`, or Buffers with `raw`.
+#[napi]
+pub struct CsvParser {
+  inner: RustCsvParser,
+  input: Decoder,
+  // Maximum number of rows returned by a single call
+  batch_size: Option<usize>,
+  // Source bytes pushed since creation or the last reset
+  offset: u64,
+  metrics: Metrics,
+  digest: Option<Digest>,
+  header_keys: HeaderKeys,
+  // Set while profiling is on
+  profile: Option<Profile>,
+}
+
+#[napi]
+impl CsvParser {
+  #[napi(constructor)]
+  pub fn new(env: Env, options: Option<JsCsvParserOptions>) -> Result<Self> {
+    let batch_size = parse_batch_size(options.as_ref()).map_err(|e| type_error(&env, e))?;
+    let opts = parse_options(options).map_err(|e| type_error(&env, e))?;
+    Ok(Self::with_options(opts, batch_size))
+  }
+
+  /// Creates a parser with the options of `template`, without reading and
+  /// checking them again
+  #[napi(factory)]
+  pub fn from_template(template: &ParserTemplate) -> Self {
+    Self::with_options(template.options.clone(), template.batch_size)
+  }
+
+  fn with_options(opts: CsvParserOptions, batch_size: Option<usize>) -> Self {
+    Self {
+      input: Decoder::new(opts.encoding, opts.detect_encoding),
+      digest: opts.digest.map(Digest::new),
+      inner: RustCsvParser::new(opts),
+      batch_size,
+      offset: 0,
+      metrics: Metrics::default(),
+      header_keys: HeaderKeys::default(),
+      profile: None,
+    }
+  }
+
+  /// Parses a chunk and returns the rows it completes. Throws the error of
+  /// the first row that fails, with the chunk's other rows under `rows` and
+  /// all its errors under `errors`; `pushWithErrors()` returns both.
+  #[napi(
+    ts_args_type = "chunk: string | Buffer | ArrayBufferView | ArrayBuffer",
+    ts_generic_types = "T extends object = Record<string, string>",
+    ts_return_type = "Array<T>"
+  )]
+  pub fn push(&mut self, env: Env, chunk: JsUnknown) -> Result<Vec<Object>> {
+    self.measure(Vec::len, |this| this.push_chunk(&env, chunk)?.into_rows(&env))
+  }
+
+  /// Like `push()`, but a row that fails is left out and its error returned
+  /// in `errors`, and parsing goes on with the next row
+  #[napi(
+    ts_args_type = "chunk: string | Buffer | ArrayBufferView | ArrayBuffer",
+    ts_generic_types = "T extends object = Record<string, string>",
+    ts_return_type = "ParseResult & { rows: Array<T> }"
+  )]
+  pub fn push_with_errors(&mut self, env: Env, chunk: JsUnknown) -> Result<ParseResult> {
+    self.measure(ParseResult::len, |this| {
+      let result = this.push_chunk(&env, chunk)?;
+      this.metrics.errors += result.errors.len() as u64;
+      Ok(result)
+    })
+  }
+
+  /// Like `push()`, but packs the rows into a single ArrayBuffer that can be
+  /// transferred to a worker with `postMessage` instead of cloned row by
+  /// row. `unpackRows()` turns it back into objects.
+  #[napi(ts_args_type = "chunk: string | Buffer | ArrayBufferView | ArrayBuffer")]
+  pub fn push_packed(&mut self, env: Env, chunk: JsUnknown) -> Result<PackedRows> {
+    self.measure(
+      |packed: &PackedRows| packed.rows as usize,
+      |this| {
+        this.check_packable()?;
+        this.read_chunk(&env, chunk)?;
+        this.decode(false)?;
+
+        let mut packer = Packer::default();
+        let mut rows = Vec::new();
+        let mut errors = Vec::new();
+        let timer = RowTimer::new(&this.profile);
+        this.parse_buffered_with(&mut rows, &mut errors, |parser, buffer, start, end| {
+          packer.row(parser, buffer, start, end)
+        });
+        timer.finish(&mut this.profile);
+        let packed = packer.finish(&env, rows.len())?;
+        rows_or_error(&env, packed, errors)
+      },
+    )
+  }
+
+  /// Like `flush()`, packing the rows as `pushPacked()` does
+  #[napi]
+  pub fn flush_packed(&mut self, env: Env) -> Result<PackedRows> {
+    self.measure(
+      |packed: &PackedRows| packed.rows as usize,
+      |this| {
+        this.check_packable()?;
+        let mut packer = Packer::default();
+        if this.input.is_empty() {
+          return packer.finish(&env, 0);
+        }
+        this.decode(true)?;
+
+        let mut rows = Vec::new();
+        let mut errors = Vec::new();
+        let timer = RowTimer::new(&this.profile);
+        this.parse_buffered_with(&mut rows, &mut errors, |parser, buffer, start, end| {
+          packer.row(parser, buffer, start, end)
+        });
+        if !this.batched() || rows.is_empty() {
+          this.parse_rest_with(&mut rows, &mut errors, |parser, buffer, start, end| {
+            packer.row(parser, buffer, start, end)
+          });
+        }
+        timer.finish(&mut this.profile);
+        let packed = packer.finish(&env, rows.len())?;
+        rows_or_error(&env, packed, errors)
+      },
+    )
+  }
+
+  /// Returns the next batch of complete rows already buffered, without
+  /// pushing more input. Only useful with `batchSize` or `sections`, where
+  /// `push()` may leave rows behind; an empty array means no complete rows
+  /// are left.
+  #[napi(
+    ts_generic_types = "T extends object = Record<string, string>",
+    ts_return_type = "Array<T>"
+  )]
+  pub fn next(&mut self, env: Env) -> Result<Vec<Object>> {
+    self.measure(Vec::len, |this| this.next_batch(&env).into_rows(&env))
+  }
+
+  /// Like `next()`, returning errors as `pushWithErrors()` does
+  #[napi(
+    ts_generic_types = "T extends object = Record<string, string>",
+    ts_return_type = "ParseResult & { rows: Array<T> }"
+  )]
+  pub fn next_with_errors(&mut self, env: Env) -> Result<ParseResult> {
+    self.measure(ParseResult::len, |this| {
+      let result = this.next_batch(&env);
+      this.metrics.errors += result.errors.len() as u64;
+      Ok(result)
+    })
+  }
+
+  #[napi(
+    ts_generic_types = "T extends object = Record<string, string>",
+    ts_return_type = "Array<T>"
+  )]
+  pub fn finish(&mut self, env: Env, _cb: JsFunction) -> Result<Vec<Object>> {
+    self.measure(Vec::len, |this| {
+      if this.input.is_empty() {
+        return Ok(Vec::new());
+      }
+
+      // Process any remaining bytes in buffer
+      this.decode(true)?;
+
+      let mut result = ParseResult::default();
+      this.parse_buffered(&env, &mut result);
+      this.parse_rest(&env, &mut result);
+      result.into_rows(&env)
+    })
+  }
+
+  #[napi]
+  pub fn get_headers(&self) -> Option<Vec<String>> {
+    self.inner.headers.clone()
+  }
+
+  /// With `sections`, the index of the table the rows of the last call
+  /// belong to, from 0
+  #[napi(getter)]
+  pub fn section(&self) -> i64 {
+    self.inner.section() as i64
+  }
+
+  /// With `sections`, the name the marker line of the current table gave
+  /// it, or null
+  #[napi(getter)]
+  pub fn section_name(&self) -> Option<String> {
+    self.inner.section_name().map(str::to_string)
+  }
+
+  /// With `strict: "report"`, the rows left out so far because their number
+  /// of cells differs from the headers, in input order
+  #[napi]
+  pub fn get_ragged_rows(&self) -> Vec<RaggedRow> {
+    self
+      .inner
+      .ragged_rows()
+      .iter()
+      .map(|row| RaggedRow {
+        line: row.line as i64,
+        expected: row.expected as u32,
+        actual: row.actual as u32,
+      })
+      .collect()
+  }
+
+  /// Zero-based indexes of the columns left out of rows because their
+  /// header is empty or `_`
+  #[napi]
+  pub fn get_dropped_columns(&self) -> Vec<u32> {
+    self.inner.dropped_columns().into_iter().map(|i| i as u32).collect()
+  }
+
+  /// Clears buffered input, headers and line counters so the instance can
+  /// parse another file. New options replace the current ones.
+  #[napi]
+  pub fn reset(&mut self, env: Env, options: Option<JsCsvParserOptions>) -> Result<()> {
+    if let Some(options) = options {
+      // Nothing changes unless all the new options are valid
+      let batch_size = parse_batch_size(Some(&options)).map_err(|e| type_error(&env, e))?;
+      let opts = parse_options(Some(options)).map_err(|e| type_error(&env, e))?;
+      let profile = self.profile.map(|_| Profile::default());
+      *self = Self::with_options(opts, batch_size);
+      self.profile = profile;
+      return Ok(());
+    }
+
+    self.inner.reset();
+    self.input.reset();
+    self.offset = 0;
+    self.metrics = Metrics::default();
+    self.profile = self.profile.map(|_| Profile::default());
+    self.digest = self.inner.options.digest.map(Digest::new);
+    Ok(())
+  }
+
+  /// A snapshot of the parse so far: options, headers, buffered input and
+  /// the keys `dedupe` remembers. Pass it to another thread (e.g. a worker)
+  /// and continue there with `CsvParser.deserializeState()`.
+  #[napi]
+  pub fn serialize_state(&self) -> Buffer {
+    let mut state = StateWriter::new();
+    self.inner.save_state(&mut state);
+    self.input.save_state(&mut state);
+    state.option(self.batch_size, |state, n| state.u64(n as u64));
+    state.u64(self.offset);
+    state.into_bytes().into()
+  }
+
+  /// The number of source bytes pushed so far with the parser state at that
+  /// point. After a restart, read the source from `offset` and push it to
+  /// `CsvParser.fromCheckpoint(checkpoint)` to get the rows that follow
+  /// those already returned.
+  #[napi]
+  pub fn get_checkpoint(&self) -> Checkpoint {
+    Checkpoint {
+      offset: self.offset as i64,
+      state: self.serialize_state(),
+    }
+  }
+
+  /// Creates a parser that continues from `checkpoint`
+  #[napi(factory)]
+  pub fn from_checkpoint(checkpoint: Checkpoint) -> Result<Self> {
+    let parser = Self::deserialize_state(checkpoint.state)?;
+    if parser.offset as i64 != checkpoint.offset {
+      return Err(Error::new(
+        Status::InvalidArg,
+        format!(
+          "Checkpoint offset {} does not match its state, which is at {}",
+          checkpoint.offset, parser.offset
+        ),
+      ));
+    }
+    Ok(parser)
+  }
+
+  /// Creates a parser that continues where the one that made `state` was
+  #[napi(factory)]
+  pub fn deserialize_state(state: Buffer) -> Result<Self> {
+    let restore = |state: &[u8]| -> color_eyre::Result<Self> {
+      let mut state = StateReader::new(state)?;
+      let inner = RustCsvParser::load_state(&mut state)?;
+      let parser = Self {
+        digest: inner.options.digest.map(Digest::new),
+        inner,
+        input: Decoder::load_state(&mut state)?,
+        batch_size: state.option(StateReader::usize)?,
+        offset: state.u64()?,
+        metrics: Metrics::default(),
+        header_keys: HeaderKeys::default(),
+        profile: None,
+      };
+      state.finish()?;
+      Ok(parser)
+    };
+    restore(&state).map_err(|e| Error::new(Status::InvalidArg, e.to_string()))
+  }
+
+  /// Checks a CSV buffer, or the file at a path, for ragged rows, duplicate
+  /// or empty headers, mixed line endings, invalid UTF-8 and a separator
+  /// that does not fit, without creating rows
+  #[napi]
+  pub fn validate(input: Either<Buffer, String>, rules: Option<LintRules>) -> Result<LintReport> {
+    let rules = rules.unwrap_or_default();
+    let options = parse_options(rules.parser)?;
+    let defaults = RustLintRules::default();
+    let rules = RustLintRules {
+      ragged_rows: rules.ragged_rows.unwrap_or(defaults.ragged_rows),
+      duplicate_headers: rules
+        .duplicate_headers
+        .unwrap_or(defaults.duplicate_headers),
+      empty_headers: rules.empty_headers.unwrap_or(defaults.empty_headers),
+      mixed_line_endings: rules
+        .mixed_line_endings
+        .unwrap_or(defaults.mixed_line_endings),
+      encoding: rules.encoding.unwrap_or(defaults.encoding),
+      suspicious_delimiter: rules
+        .suspicious_delimiter
+        .unwrap_or(defaults.suspicious_delimiter),
+      max_issues: rules.max_issues.map_or(defaults.max_issues, |n| n as usize),
+    };
+
+    let report = match input {
+      Either::A(buffer) => lint::lint(&buffer[..], &options, rules),
+      Either::B(path) => {
+        let file = File::open(&path)
+          .map_err(|e| Error::from_reason(format!("Cannot open {}: {}", path, e)))?;
+        lint::lint(file, &options, rules)
+      }
+    }
+    .map_err(|e| Error::from_reason(e.to_string()))?;
+
+    Ok(report.into())
+  }
+
+  /// Rows dropped as duplicates so far, with the `dedupe` option
+  #[napi(getter)]
+  pub fn duplicates(&self) -> i64 {
+    self.inner.duplicates() as i64
+  }
+
+  /// With `onViolation: "skip"` or `"annotate"`, every failed cell of the
+  /// rows left out or kept so far, in input order
+  #[napi]
+  pub fn get_violations(&self) -> Vec<CellViolation> {
+    self
+      .inner
+      .cell_violations()
+      .iter()
+      .map(|violation| CellViolation {
+        line: violation.line as i64,
+        column: violation.column.clone(),
+        keyword: violation.keyword.clone(),
+        message: violation.message.clone(),
+      })
+      .collect()
+  }
+
+  /// Rows left out so far by `onViolation: "skip"`
+  #[napi(getter)]
+  pub fn invalid_rows(&self) -> i64 {
+    self.inner.invalid_rows() as i64
+  }
+
+  /// With the `digest` option, the hash of the input pushed so far as hex:
+  /// after the last chunk, the digest of the whole input. A parser restored
+  /// from a checkpoint or state hashes only the input pushed to it.
+  #[napi(getter)]
+  pub fn digest(&self) -> Option<String> {
+    self.digest.as_ref().map(Digest::hex)
+  }
+
+  /// Bytes in, rows out, comment lines, errors and time spent parsing, for
+  /// exporting as telemetry
+  #[napi]
+  pub fn get_metrics(&self) -> ParserMetrics {
+    let metrics = &self.metrics;
+    let seconds = metrics.parse_time.as_secs_f64();
+    let rate = |count: u64| match seconds {
+      0.0 => 0.0,
+      _ => count as f64 / seconds,
+    };
+    ParserMetrics {
+      bytes_processed: metrics.bytes as i64,
+      rows_emitted: metrics.rows as i64,
+      comment_lines: self.inner.comment_lines() as i64,
+      duplicates: self.inner.duplicates() as i64,
+      errors: metrics.errors as i64,
+      parse_time_ms: seconds * 1000.0,
+      bytes_per_second: rate(metrics.bytes),
+      rows_per_second: rate(metrics.rows),
+    }
+  }
+
+  /// Starts recording where parsing time goes: decoding input, tokenizing
+  /// rows or building their objects. Timing each row costs a little, so
+  /// profiling is off until enabled; pass false to turn it off again.
+  #[napi]
+  pub fn enable_profiling(&mut self, enabled: Option<bool>) {
+    self.profile = match enabled.unwrap_or(true) {
+      true => Some(self.profile.unwrap_or_default()),
+      false => None,
+    };
+  }
+
+  /// The times recorded since profiling was enabled or the parser was last
+  /// reset; null while profiling is off. Packed rows are all tokenizing.
+  #[napi]
+  pub fn get_profile(&self) -> Option<ParserProfile> {
+    let millis = |time: Duration| time.as_secs_f64() * 1000.0;
+    self.profile.map(|profile| ParserProfile {
+      decode_ms: millis(profile.decode),
+      tokenize_ms: millis(profile.tokenize),
+      build_ms: millis(profile.build),
+    })
+  }
+
+  /// The line, open quote, buffered input and header state of the parse,
+  /// for debugging a stream that holds back a partial row
+  #[napi]
+  pub fn get_state(&self) -> ParserState {
+    ParserState {
+      line_number: self.inner.lines() as i64 + 1,
+      in_quote: self.inner.in_quote(&self.input.utf8_buffer),
+      buffered_bytes: (self.input.utf8_buffer.len() + self.input.buffer.len()) as i64,
+      headers_resolved: self.inner.headers_resolved(),
+    }
+  }
+
+  #[napi]
+  pub fn memory_usage(&self) -> MemoryUsage {
+    let input_buffer = self.input.buffer.capacity();
+    let decode_buffer = self.input.utf8_buffer.capacity();
+    let strings = self.inner.heap_size();
+    let arena = self.inner.arena_size();
+
+    MemoryUsage {
+      input_buffer: input_buffer as i64,
+      decode_buffer: decode_buffer as i64,
+      strings: strings as i64,
+      arena: arena as i64,
+      total: (input_buffer + decode_buffer + strings + arena) as i64,
+    }
+  }
+
+  #[napi]
+  pub fn transform(
+    &mut self,
+    env: Env,
+    chunk: Buffer,
+    _enc: String,
+    _cb: JsFunction,
+  ) -> Result<Vec<Object>> {
+    let buffer = &mut self.input.buffer;
+    buffer.extend_from_slice(&chunk);
+    let mut rows = Vec::new();
+    let mut keys = RowKeys::default();
+    let mut start = 0;
+    let mut last_newline = 0;
+
+    for (i, &byte) in buffer.iter().enumerate() {
+      if byte == self.inner.options.newline {
+        if let Some(obj) = parse_row(&mut self.inner, buffer, start, i + 1, &mut keys, &env, None)?
+        {
+          rows.push(obj);
+        }
+        start = i + 1;
+        last_newline = i + 1;
+      }
+    }
+
+    // Remove processed data from buffer
+    if last_newline > 0 {
+      *buffer = buffer[last_newline..].to_vec();
+    }
+
+    Ok(rows)
+  }
+
+  #[napi(
+    ts_generic_types = "T extends object = Record<string, string>",
+    ts_return_type = "Array<T>"
+  )]
+  pub fn flush(&mut self, env: Env) -> Result<Vec<Object>> {
+    self.measure(Vec::len, |this| this.flush_rest(&env)?.into_rows(&env))
+  }
+
+  /// Like `flush()`, returning errors as `pushWithErrors()` does
+  #[napi(
+    ts_generic_types = "T extends object = Record<string, string>",
+    ts_return_type = "ParseResult & { rows: Array<T> }"
+  )]
+  pub fn flush_with_errors(&mut self, env: Env) -> Result<ParseResult> {
+    self.measure(ParseResult::len, |this| {
+      let result = this.flush_rest(&env)?;
+      this.metrics.errors += result.errors.len() as u64;
+      Ok(result)
+    })
+  }
+
+  /// Adds a chunk to the buffered input without parsing any rows, for a
+  /// consumer that takes rows with `read()` as it is ready for them
+  #[napi(ts_args_type = "chunk: string | Buffer | ArrayBufferView | ArrayBuffer")]
+  pub fn write(&mut self, env: Env, chunk: JsUnknown) -> Result<()> {
+    self.measure(
+      |_| 0,
+      |this| {
+        this.read_chunk(&env, chunk)?;
+        this.decode(false)
+      },
+    )
+  }
+
+  /// Parses up to `count` of the complete rows written so far (`batchSize`
+  /// rows, or all of them, by default), returning errors as
+  /// `pushWithErrors()` does. With `end` the input is complete, so the last
+  /// row is parsed once the others are out. An empty result means no rows
+  /// are left until more input is written.
+  #[napi(
+    ts_generic_types = "T extends object = Record<string, string>",
+    ts_return_type = "ParseResult & { rows: Array<T> }"
+  )]
+  pub fn read(&mut self, env: Env, count: Option<u32>, end: Option<bool>) -> Result<ParseResult> {
+    let count = count.map(|count| count.max(1) as usize).or(self.batch_size);
+    let batch_size = std::mem::replace(&mut self.batch_size, count);
+    let result = self.measure(ParseResult::len, |this| {
+      let result = match end {
+        Some(true) => this.flush_rest(&env)?,
+        _ => this.next_batch(&env),
+      };
+      this.metrics.errors += result.errors.len() as u64;
+      Ok(result)
+    });
+    self.batch_size = batch_size;
+    result
+  }
+
+  // Runs one call, adding its rows and time to the metrics
+  fn measure<T>(
+    &mut self,
+    rows: impl Fn(&T) -> usize,
+    call: impl FnOnce(&mut Self) -> Result<T>,
+  ) -> Result<T> {
+    let started = Instant::now();
+    let result = match self.inner.options.hardened {
+      true => self.catch_panic(call),
+      false => call(self),
+    };
+    self.metrics.parse_time += started.elapsed();
+    match &result {
+      Ok(value) => self.metrics.rows += rows(value) as u64,
+      Err(_) => self.metrics.errors += 1,
+    }
+    result
+  }
+
+  // Runs a call, turning a panic into an error. What the call left behind
+  // cannot be trusted, so buffered input and headers are dropped.
+  fn catch_panic<T>(&mut self, call: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+    match std::panic::catch_unwind(AssertUnwindSafe(|| call(self))) {
+      Ok(result) => result,
+      Err(panic) => {
+        let message = match panic.downcast_ref::<&str>() {
+          Some(message) => message.to_string(),
+          None => panic.downcast_ref::<String>().cloned().unwrap_or_default(),
+        };
+        self.inner.reset();
+        self.input.reset();
+        Err(Error::from_reason(format!(
+          "Internal parser error, the parser was reset: {}",
+          message
+        )))
+      }
+    }
+  }
+
+  fn read_chunk(&mut self, env: &Env, chunk: JsUnknown) -> Result<()> {
+    let len = with_chunk_bytes(env, chunk, |bytes| {
+      self.input.buffer.extend_from_slice(bytes);
+      if let Some(digest) = &mut self.digest {
+        digest.update(bytes);
+      }
+      bytes.len()
+    })?;
+    self.offset += len as u64;
+    self.metrics.bytes += len as u64;
+    Ok(())
+  }
+
+  fn decode(&mut self, at_end: bool) -> Result<()> {
+    let options = &self.inner.options;
+    let validate = options.validate_utf8 == Utf8Validation::Fast && !options.raw;
+    let started = self.profile.is_some().then(Instant::now);
+    self.inner.set_input_ended(at_end);
+    let result = self.input.decode(validate, at_end);
+    if let (Some(started), Some(profile)) = (started, &mut self.profile) {
+      profile.decode += started.elapsed();
+    }
+    result.map_err(|e| Error::from_reason(e.to_string()))
+  }
+
+  // Adds a chunk and parses the rows it completes
+  fn push_chunk(&mut self, env: &Env, chunk: JsUnknown) -> Result<ParseResult> {
+    self.read_chunk(env, chunk)?;
+    self.decode(false)?;
+
+    let mut result = ParseResult::default();
+    self.parse_buffered(env, &mut result);
+    Ok(result)
+  }
+
+  // Whether a call may leave complete rows buffered for the next one
+  fn batched(&self) -> bool {
+    self.batch_size.is_some() || self.inner.options.sections.is_some()
+  }
+
+  fn next_batch(&mut self, env: &Env) -> ParseResult {
+    let mut result = ParseResult::default();
+    self.parse_buffered(env, &mut result);
+    result
+  }
+
+  // Ends the input, parsing what is left: with batchSize one batch per call
+  fn flush_rest(&mut self, env: &Env) -> Result<ParseResult> {
+    let mut result = ParseResult::default();
+    if self.input.is_empty() {
+      return Ok(result);
+    }
+
+    // Process any remaining bytes in buffer
+    self.decode(true)?;
+
+    // Complete rows may be queued ahead of the last one: with batchSize or
+    // sections, or when input was held back until its end to detect the
+    // encoding
+    self.parse_buffered(env, &mut result);
+    if !self.batched() || result.rows.is_empty() {
+      self.parse_rest(env, &mut result);
+    }
+    Ok(result)
+  }
+
+  // At the end of input, parse whatever is left as the last row
+  fn parse_rest(&mut self, env: &Env, result: &mut ParseResult) {
+    if self.inner.options.batch_hook.is_some() {
+      let mut records = Vec::new();
+      let mut errors = Vec::new();
+      self.parse_rest_with(&mut records, &mut errors, hook_record);
+      self.finish_batch(env, records, errors, result);
+      return;
+    }
+    let mut keys = RowKeys::with_header_keys(std::mem::take(&mut self.header_keys));
+    keys.share_input();
+    let mut timer = RowTimer::new(&self.profile);
+    let parsed = self.parse_rest_with(
+      &mut result.rows,
+      &mut result.errors,
+      |parser, buffer, start, end| {
+        timer.row(|tokenize| parse_row(parser, buffer, start, end, &mut keys, env, tokenize))
+      },
+    );
+    timer.finish(&mut self.profile);
+    keys.raw_lines.hand_over(parsed);
+    self.header_keys = keys.into_header_keys();
+  }
+
+  // Returns the buffer the row was parsed from
+  fn parse_rest_with<T>(
+    &mut self,
+    rows: &mut Vec<T>,
+    errors: &mut Vec<RowError>,
+    row: impl FnOnce(&mut RustCsvParser, &[u8], usize, usize) -> Result<Option<T>>,
+  ) -> Vec<u8> {
+    let buffer = &self.input.utf8_buffer;
+    if buffer.is_empty() {
+      return Vec::new();
+    }
+
+    match row(&mut self.inner, buffer, 0, buffer.len()) {
+      Ok(result) => rows.extend(result),
+      Err(e) => errors.push(RowError::new(e.reason, rows.len())),
+    }
+
+    let parsed = std::mem::take(&mut self.input.utf8_buffer);
+    self.input.clear();
+    parsed
+  }
+
+  // Parse the complete rows in utf8_buffer, stopping after `batch_size` rows,
+  // and drop their bytes. Trailing partial rows stay buffered.
+  fn parse_buffered(&mut self, env: &Env, result: &mut ParseResult) {
+    if self.inner.options.batch_hook.is_some() {
+      let mut records = Vec::new();
+      let mut errors = Vec::new();
+      self.parse_buffered_with(&mut records, &mut errors, hook_record);
+      self.finish_batch(env, records, errors, result);
+      return;
+    }
+    let mut keys = RowKeys::with_header_keys(std::mem::take(&mut self.header_keys));
+    keys.share_input();
+    let mut timer = RowTimer::new(&self.profile);
+    let parsed = self.parse_buffered_with(
+      &mut result.rows,
+      &mut result.errors,
+      |parser, buffer, start, end| {
+        timer.row(|tokenize| parse_row(parser, buffer, start, end, &mut keys, env, tokenize))
+      },
+    );
+    timer.finish(&mut self.profile);
+    keys.raw_lines.hand_over(parsed);
+    self.header_keys = keys.into_header_keys();
+  }
+
+  // Runs the `batchHook` of the parser on the records of a batch and adds
+  // them to `result` as objects. A hook that fails fails the whole batch.
+  fn finish_batch(
+    &mut self,
+    env: &Env,
+    mut records: Vec<reader::Record>,
+    errors: Vec<RowError>,
+    result: &mut ParseResult,
+  ) {
+    let offset = result.rows.len();
+    result.errors.extend(errors.into_iter().map(|error| RowError {
+      index: error.index + offset as u32,
+      ..error
+    }));
+    if records.is_empty() {
+      return;
+    }
+    let name = self.inner.options.batch_hook.as_deref().unwrap_or_default();
+    let headers = self.inner.headers.as_deref().unwrap_or_default();
+    let processed = match hooks::batch_hook(name) {
+      Some(hook) => hook
+        .process(headers, &mut records)
+        .map_err(|e| Error::from_reason(format!("batchHook \"{}\" failed: {}", name, e))),
+      None => Err(Error::from_reason(format!("batchHook \"{}\" is not registered", name))),
+    };
+    match processed.and_then(|()| records_to_objects(env, records, self.inner.options.row_object)) {
+      Ok(rows) => result.rows.extend(rows),
+      Err(e) => result.errors.push(RowError::new(e.reason, offset)),
+    }
+  }
+
+  // Refuses the packed methods, whose rows a `batchHook` cannot change
+  fn check_packable(&self) -> Result<()> {
+    match self.inner.options.batch_hook {
+      Some(_) => Err(Error::new(
+        Status::InvalidArg,
+        "Packed rows cannot be used with batchHook".to_string(),
+      )),
+      None => Ok(()),
+    }
+  }
+
+  // Rows that fail are dropped, with their errors added to `errors` at the
+  // index in `rows` they would have had. Returns the buffer the rows were
+  // parsed from.
+  fn parse_buffered_with<T>(
+    &mut self,
+    rows: &mut Vec<T>,
+    errors: &mut Vec<RowError>,
+    mut row: impl FnMut(&mut RustCsvParser, &[u8], usize, usize) -> Result<Option<T>>,
+  ) -> Vec<u8> {
+    let mut start = 0;
+    let mut stopped = false;
+
+    while let Some(end) = self.inner.row_end(&self.input.utf8_buffer, start) {
+      // The rows of one call all belong to one table of `sections`
+      let new_section = self
+        .inner
+        .starts_section(&self.input.utf8_buffer, start, end);
+      if new_section && !rows.is_empty() {
+        stopped = true;
+        break;
+      }
+      let result = row(&mut self.inner, &self.input.utf8_buffer, start, end);
+      start = end;
+      match result {
+        Ok(Some(obj)) => {
+          rows.push(obj);
+          if self.batch_size.is_some_and(|n| rows.len() >= n) {
+            stopped = true;
+            break;
+          }
+        }
+        // No row to process (e.g., header line or comment)
+        Ok(None) => {}
+        Err(e) => errors.push(RowError::new(e.reason, rows.len())),
+      }
+    }
+
+    // Remove processed data from utf8_buffer
+    let parsed = self.input.consume(start);
+
+    // What is left is one incomplete row, unless a full batch or the end of
+    // a table stopped early. Past `maxBufferedBytes` it is dropped rather
+    // than buffered further.
+    if !stopped {
+      let buffered = self.input.utf8_buffer.len() + self.input.buffer.len();
+      if let Err(e) = self.inner.check_buffered(buffered) {
+        self.input.clear();
+        errors.push(RowError::new(e.to_string(), rows.len()));
+      }
+    }
+    parsed
+  }
+}
+
+/// A row that could not be parsed, such as one with too many columns
+#[napi(object)]
+pub struct RowError {
+  pub message: String,
+  /// Where in `rows` the row would have been: the number of rows before it
+  pub index: u32,
+}
+
+impl RowError {
+  fn new(message: String, index: usize) -> Self {
+    Self {
+      message,
+      index: index as u32,
+    }
+  }
+}
+
+/// Rows parsed by one call and the errors of those that failed
+#[napi(object)]
+#[derive(Default)]
+pub struct ParseResult {
+  pub rows: Vec<Object>,
+  /// In input order; rows that failed are not in `rows`
+  pub errors: Vec<RowError>,
+}
+
+impl ParseResult {
+  fn len(&self) -> usize {
+    self.rows.len()
+  }
+
+  fn into_rows(self, env: &Env) -> Result<Vec<Object>> {
+    rows_or_error(env, self.rows, self.errors)
+  }
+}
+
+// The rows of a call, or for the methods that throw the error of its first
+// row that failed. The error carries the rows as `rows` and every error as
+// `errors`, so the rows that parsed are not lost with it.
+fn rows_or_error<T: ToNapiValue>(env: &Env, rows: T, errors: Vec<RowError>) -> Result<T> {
+  let Some(first) = errors.first() else {
+    return Ok(rows);
+  };
+  let mut error = env.create_error(Error::from_reason(first.message.clone()))?;
+  error.set_named_property("rows", rows)?;
+  error.set_named_property("errors", errors)?;
+  Err(Error::from(error.into_unknown()))
+}
+
+/// Checks run by `CsvParser.validate()`; all are on unless set to `false`
+#[napi(object)]
+#[derive(Default)]
+pub struct LintRules {
+  /// Records with a different number of fields than the header
+  pub ragged_rows: Option<bool>,
+  pub duplicate_headers: Option<bool>,
+  /// Header names that are empty or only whitespace
+  pub empty_headers: Option<bool>,
+  /// Lines ending differently from the first line
+  pub mixed_line_endings: Option<bool>,
+  /// Invalid UTF-8, reported once per line
+  pub encoding: Option<bool>,
+  /// Every record has one field, but another separator splits them evenly
+  pub suspicious_delimiter: Option<bool>,
+  /// Issues reported before the rest are left out (default 1000)
+  pub max_issues: Option<u32>,
+  /// `separator`, `quote`, `escape` and `headers`, as for `CsvParser`
+  pub parser: Option<JsCsvParserOptions>,
+}
+
+#[napi(object)]
+pub struct LintIssue {
+  pub line: i64,
+  /// In characters, from 1
+  pub column: i64,
+  /// `ragged-row`, `duplicate-header`, `empty-header`,
+  /// `mixed-line-endings`, `invalid-utf8` or `suspicious-delimiter`
+  pub code: String,
+  pub message: String,
+}
+
+/// Lines ending with each kind of line break, outside quoted fields
+#[napi(object)]
+pub struct LineEndingCounts {
+  pub crlf: i64,
+  pub lf: i64,
+  pub cr: i64,
+}
+
+#[napi(object)]
+pub struct LintReport {
+  pub valid: bool,
+  /// Data records, not counting the header record or blank lines
+  pub records: i64,
+  pub issues: Vec<LintIssue>,
+  /// Whether issues were left out past `maxIssues`
+  pub truncated: bool,
+  pub line_endings: LineEndingCounts,
+}
+
+impl From<RustLintReport> for LintReport {
+  fn from(report: RustLintReport) -> Self {
+    Self {
+      valid: report.is_valid(),
+      records: report.records as i64,
+      issues: report
+        .issues
+        .into_iter()
+        .map(|issue| LintIssue {
+          line: issue.line as i64,
+          column: issue.column as i64,
+          code: issue.kind.code().to_string(),
+          message: issue.kind.to_string(),
+        })
+        .collect(),
+      truncated: report.truncated,
+      line_endings: LineEndingCounts {
+        crlf: report.line_endings.crlf as i64,
+        lf: report.line_endings.lf as i64,
+        cr: report.line_endings.cr as i64,
+      },
+    }
+  }
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct JsCsvWriterOptions {
+  pub separator: Option<String>,
+  pub quote: Option<String>,
+  pub newline: Option<String>,
+  pub headers: Option<Vec<String>>,
+  /// Which cells are quoted: only those that need it (`'necessary'`, the
+  /// default), all, all but numbers, or none. `'minimal'` and `'all'` are
+  /// aliases of `'necessary'` and `'always'`.
+  #[napi(ts_type = "'necessary' | 'minimal' | 'always' | 'all' | 'non-numeric' | 'never'")]
+  pub quote_style: Option<String>,
+  pub formula_guard: Option<bool>,
+  /// Written before quotes inside quoted cells instead of doubling them,
+  /// and before separators, quotes and line breaks with `quoteStyle:
+  /// 'never'`, which otherwise fails on such cells
+  pub escape: Option<String>,
+  /// Cell for a column an object row has no value for (a missing key or
+  /// `undefined`); empty by default. `null` is written as `nullValue`.
+  pub missing_value: Option<String>,
+  /// Encoding of the Buffers from `writeBuffer()` (e.g. `"utf-16le"`);
+  /// UTF-8 by default
+  pub encoding: Option<String>,
+  /// Start the first output with a byte order mark, as Excel needs to
+  /// detect UTF-8. Only written for UTF-8 and UTF-16.
+  pub bom: Option<bool>,
+  /// Defaults for a family of files, which the other options override:
+  /// `"tsv"` and `"psv"` separate cells with tabs and pipes, `"excel"` writes
+  /// CRLF line endings and a BOM, and `"postgres"` writes the PostgreSQL
+  /// `COPY` text format, with tabs, backslash escapes and `\N` nulls
+  #[napi(ts_type = "'csv' | 'tsv' | 'psv' | 'excel' | 'postgres'")]
+  pub dialect: Option<String>,
+  /// Written as it is for `null` cells; empty by default
+  pub null_value: Option<String>,
+  /// Write SQL `INSERT` statements instead of CSV
+  pub sql: Option<SqlInsertOptions>,
+  /// Compress the Buffers from `writeBuffer()`; `finish()` returns the end
+  /// of the compressed stream
+  pub compression: Option<CompressionOptions>,
+}
+
+#[napi(object)]
+pub struct CompressionOptions {
+  #[napi(js_name = "type", ts_type = "'gzip' | 'zstd'")]
+  pub kind: String,
+  /// 0 to 9 for gzip (default 6), 1 to 22 for zstd (default 3)
+  pub level: Option<i32>,
+}
+
+#[napi(object)]
+pub struct SqlInsertOptions {
+  /// Table rows are inserted into; each `.`-separated part is quoted, so
+  /// `"sales.orders"` names a table in a schema
+  pub table: String,
+  /// Rows per statement (default 100)
+  pub batch_size: Option<u32>,
+  /// Quote around table and column names: `"` by default, `` ` `` for
+  /// MySQL
+  pub identifier_quote: Option<String>,
+}
+
+#[napi]
+pub struct CsvWriter {
+  inner: RustCsvWriter,
+  encoder: Encoder,
+  encoding: Option<String>,
+  headers: Option<Vec<String>>,
+  header_written: bool,
+  missing_value: Option<String>,
+  compressor: Option<Compressor>,
+}
+
+#[napi]
+impl CsvWriter {
+  #[napi(constructor)]
+  pub fn new(env: Env, options: Option<JsCsvWriterOptions>) -> Result<Self> {
+    let js_opts = options.unwrap_or_default();
+
+    Ok(Self {
+      inner: RustCsvWriter::new(parse_writer_options(&js_opts).map_err(|e| type_error(&env, e))?),
+      encoder: Encoder::new(js_opts.encoding.as_deref(), writer_bom(&js_opts, None))
+        .map_err(|e| Error::new(Status::InvalidArg, e.to_string()))?,
+      encoding: js_opts.encoding,
+      headers: js_opts.headers,
+      header_written: false,
+      missing_value: js_opts.missing_value,
+      compressor: match &js_opts.compression {
+        Some(compression) => {
+          let compression = parse_compression(compression).map_err(|e| type_error(&env, e))?;
+          Some(Compressor::new(compression).map_err(|e| Error::from_reason(e.to_string()))?)
+        }
+        None => None,
+      },
+    })
+  }
+
+  /// Formats rows (objects or arrays) as CSV. For objects the header line is
+  /// written before the first row, using the `headers` option or the keys of
+  /// the first row. `headers` also sets which keys are written and in what
+  /// order, whatever the key order of each object. `quoteIf` is called with
+  /// each data cell and its header (or index, for array rows) and quotes the
+  /// cell when it returns true, whatever the `quoteStyle`. With `bom` the
+  /// first string starts with U+FEFF.
+  #[napi(
+    ts_args_type = "rows: Array<unknown>, quoteIf?: (value: string, column: string | number) => boolean"
+  )]
+  pub fn write(
+    &mut self,
+    env: Env,
+    rows: Vec<JsUnknown>,
+    quote_if: Option<JsFunction>,
+  ) -> Result<String> {
+    if self.compressor.is_some() {
+      return Err(Error::from_reason(
+        "write() cannot compress; use writeBuffer() with compression".to_string(),
+      ));
+    }
+    let out = self.format(env, rows, quote_if)?;
+    if self.encoder.take_bom() {
+      return Ok(format!("\u{FEFF}{}", out));
+    }
+    Ok(out)
+  }
+
+  /// Like `write()`, but returns the CSV encoded in `encoding`, ready to
+  /// write to a file
+  #[napi(
+    ts_args_type = "rows: Array<unknown>, quoteIf?: (value: string, column: string | number) => boolean"
+  )]
+  pub fn write_buffer(
+    &mut self,
+    env: Env,
+    rows: Vec<JsUnknown>,
+    quote_if: Option<JsFunction>,
+  ) -> Result<Buffer> {
+    let out = self.format(env, rows, quote_if)?;
+    let mut bytes = self
+      .encoder
+      .encode(&out)
+      .map_err(|e| Error::from_reason(e.to_string()))?;
+    if let Some(compressor) = &mut self.compressor {
+      bytes = compressor
+        .compress(&bytes)
+        .map_err(|e| Error::from_reason(e.to_string()))?;
+    }
+    Ok(bytes.into())
+  }
+
+  /// Ends the compressed stream of `writeBuffer()` output and returns its
+  /// rest; output after it starts another gzip member or zstd frame. Empty
+  /// without `compression`.
+  #[napi]
+  pub fn finish(&mut self) -> Result<Buffer> {
+    let bytes = match &mut self.compressor {
+      Some(compressor) => compressor
+        .finish()
+        .map_err(|e| Error::from_reason(e.to_string()))?,
+      None => Vec::new(),
+    };
+    Ok(bytes.into())
+  }
+
+  /// Appends rows to the file at `path`, encoded as by `writeBuffer()`. A
+  /// missing or empty file is started with the header line and BOM as
+  /// usual. Otherwise the file's header line must match the `headers`
+  /// option, or the keys of the first object row in any order, and the
+  /// rows follow in the file's column order without another header. Array
+  /// rows without `headers` are appended unchecked.
+  #[napi(
+    ts_args_type = "path: string, rows: Array<unknown>, quoteIf?: (value: string, column: string | number) => boolean"
+  )]
+  pub fn append_to(
+    &mut self,
+    env: Env,
+    path: String,
+    rows: Vec<JsUnknown>,
+    quote_if: Option<JsFunction>,
+  ) -> Result<()> {
+    if self.compressor.is_some() {
+      return Err(Error::from_reason(
+        "appendTo() cannot compress; use writeBuffer() with compression".to_string(),
+      ));
+    }
+    // INSERT statements have no header line to match
+    let target = match self.inner.options.sql {
+      Some(_) => None,
+      None => read_append_target(
+        Path::new(&path),
+        &self.inner.options,
+        self.encoding.as_deref(),
+      )
+      .map_err(|e| Error::from_reason(format!("Cannot read {}: {}", path, e)))?,
+    };
+
+    let mut prefix = String::new();
+    match target {
+      None => self.header_written = false,
+      Some(target) => {
+        if let Some(found) = target.header {
+          self.match_header(&path, &found, rows.first())?;
+          self.header_written = true;
+        }
+        // Only the start of the file has a BOM
+        self.encoder.take_bom();
+        if !target.ends_with_newline {
+          prefix = String::from_utf8_lossy(&self.inner.options.newline).into_owned();
+        }
+      }
+    }
+
+    let out = prefix + &self.format(env, rows, quote_if)?;
+    let bytes = self
+      .encoder
+      .encode(&out)
+      .map_err(|e| Error::from_reason(e.to_string()))?;
+    std::fs::OpenOptions::new()
+      .create(true)
+      .append(true)
+      .open(&path)
+      .and_then(|mut file| file.write_all(&bytes))
+      .map_err(|e| Error::from_reason(format!("Cannot write {}: {}", path, e)))
+  }
+
+  // Checks the header line `found` in the file at `path` against the
+  // headers rows are written with. Headers taken from the keys of `first`
+  // may come in any order, and the file's order is used.
+  fn match_header(
+    &mut self,
+    path: &str,
+    found: &[String],
+    first: Option<&JsUnknown>,
+  ) -> Result<()> {
+    let expected = match (&self.headers, first) {
+      (Some(headers), _) => headers.clone(),
+      (None, Some(row)) if row.get_type()? == ValueType::Object && !row.is_array()? => {
+        let mut keys = object_keys(unsafe { &row.cast() })?;
+        let mut sorted = found.to_vec();
+        keys.sort();
+        sorted.sort();
+        if keys == sorted {
+          self.headers = Some(found.to_vec());
+          return Ok(());
+        }
+        keys
+      }
+      _ => return Ok(()),
+    };
+    if expected != found {
+      return Err(Error::from_reason(format!(
+        "Header of {} does not match: expected {}, found {}",
+        path,
+        self.header_line(&expected)?,
+        self.header_line(found)?
+      )));
+    }
+    Ok(())
+  }
+
+  // `names` as the header line is written, so names holding the separator,
+  // quotes or line breaks read unambiguously in messages
+  fn header_line(&self, names: &[String]) -> Result<String> {
+    let mut out = Vec::new();
+    self
+      .inner
+      .write_record(&mut out, names)
+      .map_err(|e| Error::from_reason(e.to_string()))?;
+    let line = out.strip_suffix(self.inner.options.newline.as_slice()).unwrap_or(&out);
+    Ok(String::from_utf8_lossy(line).into_owned())
+  }
+
+  fn format(
+    &mut self,
+    env: Env,
+    rows: Vec<JsUnknown>,
+    quote_if: Option<JsFunction>,
+  ) -> Result<String> {
+    let mut out = Vec::new();
+    let sql = self.inner.options.sql.is_some();
+    // With `sql`, rows and the cells `quoteIf` quotes, for the statements
+    let mut batch = Vec::new();
+    let mut batch_forced = Vec::new();
+
+    for row in rows {
+      let is_array = row.is_array()?;
+      let fields = if is_array {
+        let array: napi::JsObject = unsafe { row.cast() };
+        let length = array.get_array_length()?;
+        (0..length)
+          .map(|i| js_to_nullable_field(array.get_element::<JsUnknown>(i)?))
+          .collect::<Result<Vec<_>>>()?
+      } else {
+        let object: napi::JsObject = unsafe { row.cast() };
+        if self.headers.is_none() {
+          self.headers = Some(object_keys(&object)?);
+        }
+        self
+          .headers
+          .iter()
+          .flatten()
+          .map(|header| {
+            let value = object.get_named_property::<JsUnknown>(header)?;
+            match value.get_type()? {
+              // `NULL` in INSERT statements unless `missingValue` is set
+              ValueType::Undefined => match &self.missing_value {
+                None if sql => Ok(None),
+                missing => Ok(Some(missing.clone().unwrap_or_default())),
+              },
+              _ => js_to_nullable_field(value),
+            }
+          })
+          .collect::<Result<Vec<_>>>()?
+      };
+
+      if !self.header_written && !sql {
+        if let Some(headers) = &self.headers {
+          self
+            .inner
+            .write_record(&mut out, headers)
+            .map_err(|e| Error::from_reason(e.to_string()))?;
+        }
+        self.header_written = true;
+      }
+
+      let forced = match &quote_if {
+        Some(quote_if) => fields
+          .iter()
+          .enumerate()
+          .map(|(i, field)| {
+            let Some(field) = field else {
+              return Ok(false);
+            };
+            let column = match self.headers.as_ref().filter(|_| !is_array) {
+              Some(headers) => env.create_string(&headers[i])?.into_unknown(),
+              None => env.create_uint32(i as u32)?.into_unknown(),
+            };
+            let value = env.create_string(field)?.into_unknown();
+            quote_if
+              .call(None, &[value, column])?
+              .coerce_to_bool()?
+              .get_value()
+          })
+          .collect::<Result<Vec<_>>>()?,
+        None => Vec::new(),
+      };
+      if sql {
+        batch.push(fields);
+        batch_forced.push(forced);
+        continue;
+      }
+      self
+        .inner
+        .write_nullable_record(&mut out, &fields, |i, _| {
+          forced.get(i).copied().unwrap_or(false)
+        })
+        .map_err(|e| Error::from_reason(e.to_string()))?;
+    }
+    if !batch.is_empty() {
+      self
+        .inner
+        .write_insert(&mut out, self.headers.as_deref(), &batch, |row, i, _| {
+          batch_forced[row].get(i).copied().unwrap_or(false)
+        })
+        .map_err(|e| Error::from_reason(e.to_string()))?;
+    }
+
+    String::from_utf8(out).map_err(|e| Error::from_reason(e.to_string()))
+  }
+}
+
+fn parse_writer_options(js_opts: &JsCsvWriterOptions) -> Result<CsvWriterOptions> {
+  // Defaults the `dialect` preset sets
+  let (separator, newline, format, null_value) = match js_opts.dialect.as_deref() {
+    None | Some("csv") => (b',', "\n", RecordFormat::Csv, ""),
+    Some("tsv") => (b'\t', "\n", RecordFormat::Csv, ""),
+    Some("psv") => (b'|', "\n", RecordFormat::Csv, ""),
+    Some("excel") => (b',', "\r\n", RecordFormat::Csv, ""),
+    Some("postgres") => (b'\t', "\n", RecordFormat::PostgresText, "\\N"),
+    Some(other) => {
+      return Err(Error::new(
+        Status::InvalidArg,
+        format!(
+          "dialect must be \"csv\", \"tsv\", \"psv\", \"excel\" or \"postgres\", got \"{}\"",
+          other
+        ),
+      ))
+    }
+  };
+
+  let separator = option_byte("separator", js_opts.separator.clone())?.unwrap_or(separator);
+  let quote = option_byte("quote", js_opts.quote.clone())?.unwrap_or(b'"');
+  let escape = option_byte("escape", js_opts.escape.clone())?;
+  let newline = js_opts.newline.as_deref().unwrap_or(newline);
+  if newline.is_empty() {
+    return Err(Error::new(
+      Status::InvalidArg,
+      "newline must not be empty".to_string(),
+    ));
+  }
+  check_distinct(&[
+    ("separator", Some(separator)),
+    ("quote", Some(quote)),
+    ("escape", escape.filter(|&escape| escape != quote)),
+  ])?;
+  if let Some(&byte) = newline
+    .as_bytes()
+    .iter()
+    .find(|&&byte| byte == separator || byte == quote)
+  {
+    let option = if byte == separator {
+      "separator"
+    } else {
+      "quote"
+    };
+    return Err(Error::new(
+      Status::InvalidArg,
+      format!(
+        "newline must not contain the {}, {:?}",
+        option, byte as char
+      ),
+    ));
+  }
+
+  let quote_style = match js_opts.quote_style.as_deref() {
+    None | Some("necessary" | "minimal") => QuoteStyle::Necessary,
+    Some("always" | "all") => QuoteStyle::Always,
+    Some("non-numeric") => QuoteStyle::NonNumeric,
+    Some("never") => QuoteStyle::Never,
+    Some(other) => {
+      return Err(Error::new(
+        Status::InvalidArg,
+        format!(
+          "quoteStyle must be \"necessary\", \"always\", \"non-numeric\" or \"never\", got \"{}\"",
+          other
+        ),
+      ))
+    }
+  };
+  let sql = match &js_opts.sql {
+    None => None,
+    Some(sql) => Some(parse_sql_insert(sql, quote_style)?),
+  };
+
+  Ok(CsvWriterOptions {
+    separator,
+    quote,
+    newline: newline.as_bytes().to_vec(),
+    quote_style,
+    formula_guard: js_opts.formula_guard.unwrap_or(false),
+    escape,
+    format,
+    null_value: js_opts
+      .null_value
+      .as_deref()
+      .unwrap_or(null_value)
+      .to_string(),
+    sql,
+  })
+}
+
+fn parse_compression(options: &CompressionOptions) -> Result<Compression> {
+  let invalid = |message: String| Error::new(Status::InvalidArg, message);
+  let compression = match (options.kind.as_str(), options.level) {
+    ("gzip", None) => Compression::GZIP,
+    ("gzip", Some(level)) if level < 0 => {
+      return Err(invalid(format!(
+        "compression.level must be between 0 and 9 for gzip, got {}",
+        level
+      )))
+    }
+    ("gzip", Some(level)) => Compression::Gzip(level as u32),
+    ("zstd", level) => level.map_or(Compression::ZSTD, Compression::Zstd),
+    (other, _) => {
+      return Err(invalid(format!(
+        "compression.type must be \"gzip\" or \"zstd\", got \"{}\"",
+        other
+      )))
+    }
+  };
+  compression.check().map_err(|e| invalid(e.to_string()))
+}
+
+fn parse_sql_insert(sql: &SqlInsertOptions, quote_style: QuoteStyle) -> Result<SqlInsert> {
+  let invalid = |message: &str| Err(Error::new(Status::InvalidArg, message.to_string()));
+  if sql.table.is_empty() {
+    return invalid("sql.table must not be empty");
+  }
+  if quote_style == QuoteStyle::Never {
+    return invalid("quoteStyle \"never\" cannot be used with sql");
+  }
+  let batch_size = match sql.batch_size {
+    None => SqlInsert::default().batch_size,
+    Some(size) if size > 0 => size as usize,
+    Some(_) => return invalid("sql.batchSize must be a positive integer"),
+  };
+  Ok(SqlInsert {
+    table: sql.table.clone(),
+    batch_size,
+    identifier_quote: option_byte("sql.identifierQuote", sql.identifier_quote.clone())?
+      .unwrap_or(b'"'),
+  })
+}
+
+// The enumerable keys of an object row, as column names
+fn object_keys(object: &napi::JsObject) -> Result<Vec<String>> {
+  let keys = object.get_property_names()?;
+  let length = keys.get_array_length()?;
+  (0..length)
+    .map(|i| js_to_field(keys.get_element::<JsUnknown>(i)?))
+    .collect()
+}
+
+// Whether writer output starts with a BOM: `bom`, given here or in the
+// writer options, or else the `excel` dialect
+fn writer_bom(js_opts: &JsCsvWriterOptions, bom: Option<bool>) -> bool {
+  bom
+    .or(js_opts.bom)
+    .unwrap_or(js_opts.dialect.as_deref() == Some("excel"))
+}
+
+// Stringify a JS value for output. `null` and `undefined` become empty cells.
+fn js_to_field(value: JsUnknown) -> Result<String> {
+  match value.get_type()? {
+    ValueType::Null | ValueType::Undefined => Ok(String::new()),
+    _ => value.coerce_to_string()?.into_utf8()?.into_owned(),
+  }
+}
+
+// A cell to write: `None` for `null`, which the writer writes as its
+// `nullValue`
+fn js_to_nullable_field(value: JsUnknown) -> Result<Option<String>> {
+  match value.get_type()? {
+    ValueType::Null => Ok(None),
+    _ => js_to_field(value).map(Some),
+  }
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct BenchmarkOptions {
+  /// Data rows per generated data set (default 10000)
+  pub rows: Option<u32>,
+  /// Cells per row (default 8)
+  pub columns: Option<u32>,
+  /// Timed parses of each data set (default 5)
+  pub iterations: Option<u32>,
+}
+
+#[napi(object)]
+pub struct BenchmarkResult {
+  pub name: String,
+  /// `micro` for a single cell shape, `macro` for a parser configuration,
+  /// `fixture` for input shaped like a common kind of file
+  pub kind: String,
+  pub bytes: i64,
+  pub rows: i64,
+  pub iterations: u32,
+  pub elapsed_ms: f64,
+  pub bytes_per_second: f64,
+  pub rows_per_second: f64,
+}
+
+/// Runs the built-in benchmarks on synthetic data and reports the throughput
+/// of each cell shape and parser configuration on this machine.
+#[napi]
+pub fn benchmark(options: Option<BenchmarkOptions>) -> Vec<BenchmarkResult> {
+  let options = options.unwrap_or_default();
+  let rows = options.rows.unwrap_or(10_000) as usize;
+  let columns = options.columns.unwrap_or(8).max(1) as usize;
+  let iterations = options.iterations.unwrap_or(5).max(1);
+
+  bench::run(rows, columns, iterations)
+    .into_iter()
+    .map(|report| BenchmarkResult {
+      bytes_per_second: report.bytes_per_second(),
+      rows_per_second: report.rows_per_second(),
+      elapsed_ms: report.elapsed.as_secs_f64() * 1000.0,
+      name: report.name,
+      kind: report.kind.to_string(),
+      bytes: report.bytes as i64,
+      rows: report.rows as i64,
+      iterations: report.iterations,
+    })
+    .collect()
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct InferSchemaOptions {
+  /// Rows to sample (default 1000)
+  pub sample_rows: Option<u32>,
+  /// Options for parsing the sample, as for `CsvParser`
+  pub parser: Option<JsCsvParserOptions>,
+}
+
+#[napi(object)]
+pub struct ColumnSchema {
+  pub name: String,
+  #[napi(
+    js_name = "type",
+    ts_type = "'empty' | 'boolean' | 'integer' | 'float' | 'date' | 'string'"
+  )]
+  pub kind: String,
+  /// Some sampled row had no value for the column
+  pub nullable: bool,
+  /// Shortest and longest non-empty value, in characters
+  pub min_length: u32,
+  pub max_length: u32,
+  /// Up to three distinct non-empty values, in order of appearance
+  pub examples: Vec<String>,
+  /// Distinct non-empty values in the sample
+  pub distinct: u32,
+}
+
+impl From<RustColumnSchema> for ColumnSchema {
+  fn from(column: RustColumnSchema) -> Self {
+    Self {
+      name: column.name,
+      kind: column.kind.as_str().to_string(),
+      nullable: column.nullable,
+      min_length: column.min_length as u32,
+      max_length: column.max_length as u32,
+      examples: column.examples,
+      distinct: column.distinct as u32,
+    }
+  }
+}
+
+/// Samples the first rows of a CSV buffer, or of the file at a path, and
+/// describes each column: inferred type, nullability, value lengths,
+/// examples and distinct count.
+#[napi]
+pub fn infer_schema(
+  input: Either<Buffer, String>,
+  options: Option<InferSchemaOptions>,
+) -> Result<Vec<ColumnSchema>> {
+  let options = options.unwrap_or_default();
+  let sample_rows = options.sample_rows.unwrap_or(1000) as usize;
+  let parser_options = parse_options(options.parser)?;
+
+  let columns = match input {
+    Either::A(buffer) => schema::infer_schema(&buffer[..], parser_options, sample_rows),
+    Either::B(path) => {
+      let file = std::fs::File::open(&path)
+        .map_err(|e| Error::from_reason(format!("Cannot open {}: {}", path, e)))?;
+      schema::infer_schema(file, parser_options, sample_rows)
+    }
+  }
+  .map_err(|e| Error::from_reason(e.to_string()))?;
+
+  Ok(columns.into_iter().map(ColumnSchema::from).collect())
+}
+
+/// Counts the records in a CSV buffer, or in the file at a path, without
+/// creating cell strings or row objects. Header, comment and skipped lines
+/// are not counted; quoted newlines do not end a record.
+#[napi]
+pub fn count_rows(
+  input: Either<Buffer, String>,
+  options: Option<JsCsvParserOptions>,
+) -> Result<i64> {
+  let options = parse_options(options)?;
+
+  let rows = match input {
+    Either::A(buffer) => reader::count_rows(&buffer[..], options),
+    Either::B(path) => {
+      let file = std::fs::File::open(&path)
+        .map_err(|e| Error::from_reason(format!("Cannot open {}: {}", path, e)))?;
+      reader::count_rows(file, options)
+    }
+  }
+  .map_err(|e| Error::from_reason(e.to_string()))?;
+
+  Ok(rows as i64)
+}
+
+#[napi(object)]
+pub struct ToJsonOptions {
+  /// `"array"` (default) writes one JSON array, a row per line; `"ndjson"`
+  /// writes one row object per line
+  #[napi(ts_type = "'array' | 'ndjson'")]
+  pub format: Option<String>,
+  /// Options for parsing the input, as for `CsvParser`
+  pub parser: Option<JsCsvParserOptions>,
+}
+
+/// Converts a CSV buffer, or the file at a path, to a JSON file at
+/// `outputPath` without creating row objects in JS. Rows are written as
+/// `JSON.stringify` writes the rows of `csv()` with the same options; dates
+/// are ISO strings and raw cells are text. Returns the number of rows.
+#[napi]
+pub fn to_json(
+  input: Either<Buffer, String>,
+  output_path: String,
+  options: Option<ToJsonOptions>,
+) -> Result<i64> {
+  let (format, parser) = match options {
+    Some(options) => (options.format, options.parser),
+    None => (None, None),
+  };
+  let format = match format.as_deref() {
+    None | Some("array") => JsonFormat::Array,
+    Some("ndjson") => JsonFormat::Ndjson,
+    Some(other) => {
+      return Err(Error::new(
+        Status::InvalidArg,
+        format!("format must be \"array\" or \"ndjson\", got \"{}\"", other),
+      ))
+    }
+  };
+  let parser = parse_options(parser)?;
+
+  // The input is opened first, so a missing file leaves no output behind
+  let input: Box<dyn Read> = match input {
+    Either::A(buffer) => Box::new(std::io::Cursor::new(buffer)),
+    Either::B(path) => Box::new(
+      File::open(&path)
+        .map_err(|e| Error::from_reason(format!("Cannot open {}: {}", path, e)))?,
+    ),
+  };
+  let out = File::create(&output_path)
+    .map_err(|e| Error::from_reason(format!("Cannot create {}: {}", output_path, e)))?;
+  let rows = json::to_json(input, std::io::BufWriter::new(out), parser, format)
+    .map_err(|e| Error::from_reason(e.to_string()))?;
+
+  Ok(rows as i64)
+}
+
+#[cfg(feature = "sqlite")]
+#[napi(object)]
+pub struct ToSqliteOptions {
+  /// Name of the table rows are inserted into
+  pub table: String,
+  /// Create the table, with a column per header, unless it exists
+  /// (default true)
+  pub create_table: Option<bool>,
+  /// SQLite type of each column by header, `"text"` by default
+  #[napi(ts_type = "Record<string, 'text' | 'integer' | 'real'>")]
+  pub types: Option<HashMap<String, String>>,
+  /// Rows inserted per transaction (default 10000)
+  pub batch_size: Option<u32>,
+  /// Options for parsing the input, as for `CsvParser`
+  pub parser: Option<JsCsvParserOptions>,
+}
+
+/// Inserts the rows of a CSV buffer, or of the file at a path, into a table
+/// of the SQLite database at `dbPath`, without creating row objects in JS.
+/// Rows are inserted in batches of one transaction each. Returns the number
+/// of rows inserted. Only in builds with the `sqlite` feature.
+#[cfg(feature = "sqlite")]
+#[napi]
+pub fn to_sqlite(
+  input: Either<Buffer, String>,
+  db_path: String,
+  options: ToSqliteOptions,
+) -> Result<i64> {
+  let mut types = Vec::new();
+  for (column, name) in options.types.unwrap_or_default() {
+    let sql_type = match name.as_str() {
+      "text" => SqlType::Text,
+      "integer" => SqlType::Integer,
+      "real" => SqlType::Real,
+      _ => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!(
+            "types.{} must be \"text\", \"integer\" or \"real\", got \"{}\"",
+            column, name
+          ),
+        ))
+      }
+    };
+    types.push((column, sql_type));
+  }
+  let batch_size = match options.batch_size {
+    None => SqliteOptions::default().batch_size,
+    Some(size) if size > 0 => size as usize,
+    Some(_) => {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "batchSize must be a positive integer".to_string(),
+      ))
+    }
+  };
+  let sqlite_options = SqliteOptions {
+    table: options.table,
+    create_table: options.create_table.unwrap_or(true),
+    types,
+    batch_size,
+    parser: parse_options(options.parser)?,
+  };
+
+  let rows = match input {
+    Either::A(buffer) => sqlite::to_sqlite(&buffer[..], &db_path, sqlite_options),
+    Either::B(path) => {
+      let file = File::open(&path)
+        .map_err(|e| Error::from_reason(format!("Cannot open {}: {}", path, e)))?;
+      sqlite::to_sqlite(file, &db_path, sqlite_options)
+    }
+  }
+  .map_err(|e| Error::from_reason(e.to_string()))?;
+
+  Ok(rows as i64)
+}
+
+#[napi(object)]
+pub struct RecordBoundaryOptions {
+  /// Only return starts at least this many bytes after the previous one,
+  /// to cut the input into chunks of about that size
+  pub chunk_bytes: Option<i64>,
+  /// Options for parsing the input, as for `CsvParser`
+  pub parser: Option<JsCsvParserOptions>,
+}
+
+/// Byte offsets where records start in a CSV buffer, or in the file at a
+/// path: points the input can be split at, e.g. to parse chunks of a large
+/// file in several workers. Quoted newlines do not end a record; the
+/// header, comment and skipped lines each count. Offsets are of the raw
+/// bytes, which must be in an ASCII-compatible encoding such as UTF-8.
+#[napi]
+pub fn find_record_boundaries(
+  input: Either<Buffer, String>,
+  options: Option<RecordBoundaryOptions>,
+) -> Result<Vec<i64>> {
+  let (chunk_bytes, parser) = match options {
+    Some(options) => (options.chunk_bytes.unwrap_or(0).max(0), options.parser),
+    None => (0, None),
+  };
+  let parser = parse_options(parser)?;
+
+  let boundaries = match input {
+    Either::A(buffer) => reader::record_boundaries(&buffer[..], parser, chunk_bytes as u64),
+    Either::B(path) => {
+      let file = std::fs::File::open(&path)
+        .map_err(|e| Error::from_reason(format!("Cannot open {}: {}", path, e)))?;
+      reader::record_boundaries(file, parser, chunk_bytes as u64)
+    }
+  }
+  .map_err(|e| Error::from_reason(e.to_string()))?;
+
+  Ok(boundaries.into_iter().map(|offset| offset as i64).collect())
+}
+
+#[napi(object)]
+pub struct DistinctOptions {
+  /// Return `{ value, count }` objects with the number of rows holding
+  /// each value, instead of the values alone
+  pub counts: Option<bool>,
+  /// Options for parsing the input, as for `CsvParser`
+  pub parser: Option<JsCsvParserOptions>,
+}
+
+/// The distinct values of a column in a CSV buffer, or in the file at a
+/// path, in order of first appearance. With several columns, each value is
+/// an array of one cell per column. Only cells of those columns are
+/// decoded and only new values are kept, so memory grows with the number
+/// of distinct values rather than rows. Missing and null cells count as
+/// empty.
+#[napi(
+  ts_return_type = "Array<string> | Array<Array<string>> | Array<{ value: string | Array<string>, count: number }>"
+)]
+pub fn distinct(
+  env: Env,
+  input: Either<Buffer, String>,
+  #[napi(ts_arg_type = "string | Array<string>")] columns: Either<String, Vec<String>>,
+  options: Option<DistinctOptions>,
+) -> Result<napi::JsObject> {
+  let (counts, parser) = match options {
+    Some(options) => (options.counts.unwrap_or(false), options.parser),
+    None => (false, None),
+  };
+  let parser = parse_options(parser)?;
+  let (columns, single) = match columns {
+    Either::A(column) => (vec![column], true),
+    Either::B(columns) => (columns, false),
+  };
+
+  let values = match input {
+    Either::A(buffer) => distinct_values(&buffer[..], parser, columns),
+    Either::B(path) => {
+      let file = File::open(&path)
+        .map_err(|e| Error::from_reason(format!("Cannot open {}: {}", path, e)))?;
+      distinct_values(file, parser, columns)
+    }
+  }
+  .map_err(|e| Error::from_reason(e.to_string()))?;
+
+  let mut array = env.create_array_with_length(values.len())?;
+  for (i, distinct) in values.into_iter().enumerate() {
+    let value = if single {
+      env.create_string(&distinct.values[0])?.into_unknown()
+    } else {
+      let mut cells = env.create_array_with_length(distinct.values.len())?;
+      for (j, cell) in distinct.values.iter().enumerate() {
+        cells.set_element(j as u32, env.create_string(cell)?)?;
+      }
+      cells.into_unknown()
+    };
+    if counts {
+      let mut entry = env.create_object()?;
+      entry.set_named_property("value", value)?;
+      entry.set_named_property("count", env.create_int64(distinct.count as i64)?)?;
+      array.set_element(i as u32, entry)?;
+    } else {
+      array.set_element(i as u32, value)?;
+    }
+  }
+  Ok(array)
+}
+
+/// How the input of `head()` and `readHeaders()` is written
+#[napi(object)]
+pub struct CsvDialect {
+  pub separator: String,
+  pub quote: String,
+  /// How the first line ends, or null if the input read has no line break
+  #[napi(ts_type = "'\\n' | '\\r\\n' | '\\r' | null")]
+  pub newline: Option<String>,
+  /// Lowercase encoding name, e.g. `"utf-8"`, `"utf-16le"`, `"utf-32be"` or
+  /// the `encoding` option's, such as `"ibm037"`
+  pub encoding: String,
+  /// The input starts with a byte order mark
+  pub bom: bool,
+}
+
+#[napi(object)]
+pub struct HeadResult {
+  pub rows: Vec<Object>,
+  /// Header names, or null if the input ended before they were known
+  pub headers: Option<Vec<String>>,
+  pub dialect: CsvDialect,
+}
+
+/// The first `n` rows of a CSV buffer, or of the file at a path, with its
+/// headers and dialect. Without a `separator` option the separator is
+/// guessed from the first lines. Files are read only as far as needed.
+#[napi(
+  ts_generic_types = "T extends object = Record<string, string>",
+  ts_return_type = "HeadResult & { rows: Array<T> }"
+)]
+pub fn head(
+  env: Env,
+  input: Either<Buffer, String>,
+  n: u32,
+  options: Option<JsCsvParserOptions>,
+) -> Result<HeadResult> {
+  match input {
+    Either::A(buffer) => head_rows(env, &buffer[..], n as usize, options),
+    Either::B(path) => {
+      let file = std::fs::File::open(&path)
+        .map_err(|e| Error::from_reason(format!("Cannot open {}: {}", path, e)))?;
+      head_rows(env, file, n as usize, options)
+    }
+  }
+}
+
+fn head_rows(
+  env: Env,
+  mut input: impl Read,
+  n: usize,
+  options: Option<JsCsvParserOptions>,
+) -> Result<HeadResult> {
+  let guess_separator = options
+    .as_ref()
+    .is_none_or(|o| o.dialect.is_none() && matches!(o.separator.as_deref(), None | Some("auto")));
+  let mut parser = CsvParser::new(env, options)?;
+  let mut chunk = vec![0; 64 * 1024];
+  let mut read = input
+    .read(&mut chunk)
+    .map_err(|e| Error::from_reason(e.to_string()))?;
+
+  // The dialect is guessed from the first chunk only
+  let options = &parser.inner.options;
+  let mut sample = Decoder::new(options.encoding, options.detect_encoding);
+  sample.buffer.extend_from_slice(&chunk[..read]);
+  sample
+    .decode(false, read < chunk.len())
+    .map_err(|e| Error::from_reason(e.to_string()))?;
+  let dialect = sniff_dialect(&sample.utf8_buffer, parser.inner.options.quote);
+  if let Some(separator) = dialect.separator.filter(|_| guess_separator) {
+    parser.inner.options.separator = separator;
+  }
+
+  let mut rows = Vec::new();
+  loop {
+    parser.input.buffer.extend_from_slice(&chunk[..read]);
+    parser.decode(read == 0)?;
+    // Rows past the first `n` are never created
+    parser.batch_size = Some(n - rows.len());
+    if n == 0 {
+      // Records are split until the headers are known, without row objects
+      let mut records = Vec::new();
+      let parse = |parser: &mut RustCsvParser, buffer: &[u8], start, end| {
+        if parser.headers.is_some() {
+          return Ok(Some(()));
+        }
+        let record = parser.parse_record(buffer, start, end);
+        record
+          .map(|cells| cells.map(drop))
+          .map_err(|e| Error::from_reason(e.to_string()))
+      };
+      parser.parse_buffered_with(&mut records, &mut Vec::new(), parse);
+      if read == 0 && records.is_empty() {
+        parser.parse_rest_with(&mut records, &mut Vec::new(), parse);
+      }
+    } else {
+      let mut result = ParseResult::default();
+      parser.parse_buffered(&env, &mut result);
+      if read == 0 && rows.len() + result.rows.len() < n {
+        parser.parse_rest(&env, &mut result);
+      }
+      // Only a row that fails among the first `n` is an error
+      if let Some(error) = result.errors.first() {
+        if rows.len() + (error.index as usize) < n {
+          return Err(Error::from_reason(error.message.clone()));
+        }
+      }
+      rows.extend(result.rows);
+    }
+    if read == 0 || (rows.len() >= n && parser.inner.headers.is_some()) {
+      break;
+    }
+    read = input
+      .read(&mut chunk)
+      .map_err(|e| Error::from_reason(e.to_string()))?;
+  }
+  rows.truncate(n);
+
+  let options = &parser.inner.options;
+  Ok(HeadResult {
+    rows,
+    headers: parser.inner.headers.clone(),
+    dialect: CsvDialect {
+      separator: (options.separator as char).to_string(),
+      quote: (options.quote as char).to_string(),
+      newline: dialect.line_ending.map(|ending| {
+        match ending {
+          LineEnding::Lf => "\n",
+          LineEnding::CrLf => "\r\n",
+          LineEnding::Cr => "\r",
+        }
+        .to_string()
+      }),
+      encoding: parser.input.encoding().name().to_ascii_lowercase(),
+      bom: parser.input.has_bom(),
+    },
+  })
+}
+
+#[napi(object)]
+pub struct HeadersResult {
+  /// Header names, or null if the input ended before they were known
+  pub headers: Option<Vec<String>>,
+  pub dialect: CsvDialect,
+}
+
+/// The headers and dialect of a CSV buffer, or of the file at a path, as
+/// `head()` finds them, without any rows: reading stops once the header
+/// line is parsed, and no row object is created. With `headers: false` the
+/// first row is split for its width.
+#[napi]
+pub fn read_headers(
+  env: Env,
+  input: Either<Buffer, String>,
+  options: Option<JsCsvParserOptions>,
+) -> Result<HeadersResult> {
+  let head = head(env, input, 0, options)?;
+  Ok(HeadersResult {
+    headers: head.headers,
+    dialect: head.dialect,
+  })
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct SampleOptions {
+  /// Picks the same rows of the same input on every call
+  pub seed: Option<u32>,
+  /// Options for parsing the input, as for `CsvParser`
+  pub parser: Option<JsCsvParserOptions>,
+}
+
+/// `k` rows picked uniformly at random from a CSV buffer, or from the file
+/// at a path, in one pass and in input order. Only the picked rows become
+/// JS objects; with fewer than `k` rows, all of them are returned.
+#[napi(
+  ts_generic_types = "T extends object = Record<string, string>",
+  ts_return_type = "Array<T>"
+)]
+pub fn sample(
+  env: Env,
+  input: Either<Buffer, String>,
+  k: u32,
+  options: Option<SampleOptions>,
+) -> Result<Vec<Object>> {
+  let options = options.unwrap_or_default();
+  let seed = options.seed.map(u64::from);
+  let parser_options = parse_options(options.parser)?;
+  let row_object = parser_options.row_object;
+
+  let records = match input {
+    Either::A(buffer) => sample_rows(&buffer[..], parser_options, k as usize, seed),
+    Either::B(path) => {
+      let file = std::fs::File::open(&path)
+        .map_err(|e| Error::from_reason(format!("Cannot open {}: {}", path, e)))?;
+      sample_rows(file, parser_options, k as usize, seed)
+    }
+  }
+  .map_err(|e| Error::from_reason(e.to_string()))?;
+
+  records_to_objects(&env, records, row_object)
+}
+
+#[napi(object)]
+pub struct CsvIndexOptions {
+  /// Header of the column whose values are the keys
+  pub column: String,
+  /// Options for parsing the file, as for `CsvParser`
+  pub parser: Option<JsCsvParserOptions>,
+}
+
+/// Where a row is in the indexed file, including its newline
+#[napi(object)]
+pub struct RowLocation {
+  pub offset: i64,
+  pub length: i64,
+}
+
+/// Maps the values of one column of a UTF-8 file to the rows that hold them.
+/// Rows stay on disk and are read back on lookup.
+#[napi]
+pub struct CsvIndex {
+  inner: RustCsvIndex,
+}
+
+#[napi]
+impl CsvIndex {
+  /// Reads the file at `path` once and indexes every row by `column`
+  #[napi(factory)]
+  pub fn build(path: String, options: CsvIndexOptions) -> Result<Self> {
+    let parser_options = parse_options(options.parser)?;
+    let inner = RustCsvIndex::build(&path, &options.column, parser_options)
+      .map_err(|e| Error::from_reason(format!("Cannot index {}: {}", path, e)))?;
+    Ok(Self { inner })
+  }
+
+  /// Distinct keys in the index
+  #[napi(getter)]
+  pub fn size(&self) -> u32 {
+    self.inner.len() as u32
+  }
+
+  /// Byte locations of the rows with this key, in file order
+  #[napi]
+  pub fn locate(&self, key: String) -> Vec<RowLocation> {
+    self
+      .inner
+      .locate(&key)
+      .iter()
+      .map(|location| RowLocation {
+        offset: location.offset as i64,
+        length: location.length as i64,
+      })
+      .collect()
+  }
+
+  /// The rows with this key, read back from the file
+  #[napi(
+    ts_generic_types = "T extends object = Record<string, string>",
+    ts_return_type = "Array<T>"
+  )]
+  pub fn lookup(&mut self, env: Env, key: String) -> Result<Vec<Object>> {
+    let mut rows = Vec::new();
+    let mut keys = RowKeys::default();
+    for location in self.inner.locate(&key).to_vec() {
+      let row = self
+        .inner
+        .read_row(location)
+        .map_err(|e| Error::from_reason(e.to_string()))?;
+      let tokenizer = &mut self.inner.tokenizer;
+      if let Some(obj) = parse_row(tokenizer, &row, 0, row.len(), &mut keys, &env, None)? {
+        rows.push(obj);
+      }
+    }
+    Ok(rows)
+  }
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct CsvStatsOptions {
+  /// Most frequent values reported per column (default 5)
+  pub top_k: Option<u32>,
+  /// Options for parsing the input, as for `CsvParser`
+  pub parser: Option<JsCsvParserOptions>,
+}
+
+#[napi(object)]
+pub struct ValueCount {
+  pub value: String,
+  pub count: i64,
+}
+
+#[napi(object)]
+pub struct ColumnStats {
+  pub name: String,
+  /// Rows with a non-empty value
+  pub count: i64,
+  /// Rows where the value was empty or missing
+  pub null_count: i64,
+  /// Non-empty values that parse as numbers
+  pub numeric_count: i64,
+  /// Over the numeric values only
+  pub min: Option<f64>,
+  pub max: Option<f64>,
+  pub mean: Option<f64>,
+  /// Sample standard deviation, with at least two numeric values
+  pub stddev: Option<f64>,
+  /// Most frequent values, most frequent first. Counts are exact while the
+  /// column has fewer than ten times `topK` distinct values, and lower
+  /// bounds after that.
+  pub top: Vec<ValueCount>,
+}
+
+impl From<RustColumnStats> for ColumnStats {
+  fn from(column: RustColumnStats) -> Self {
+    Self {
+      name: column.name,
+      count: column.count as i64,
+      null_count: column.null_count as i64,
+      numeric_count: column.numeric_count as i64,
+      min: column.min,
+      max: column.max,
+      mean: column.mean,
+      stddev: column.stddev,
+      top: column
+        .top
+        .into_iter()
+        .map(|(value, count)| ValueCount {
+          value,
+          count: count as i64,
+        })
+        .collect(),
+    }
+  }
+}
+
+/// Computes per-column statistics in Rust while streaming, without creating
+/// row objects.
+#[napi]
+pub struct CsvStats {
+  inner: StatsCollector,
+}
+
+#[napi]
+impl CsvStats {
+  #[napi(constructor)]
+  pub fn new(options: Option<CsvStatsOptions>) -> Result<Self> {
+    let options = options.unwrap_or_default();
+    let top_k = options.top_k.unwrap_or(5) as usize;
+
+    Ok(Self {
+      inner: StatsCollector::new(parse_options(options.parser)?, top_k),
+    })
+  }
+
+  #[napi(ts_args_type = "chunk: string | Buffer | ArrayBufferView | ArrayBuffer")]
+  pub fn push(&mut self, env: Env, chunk: JsUnknown) -> Result<()> {
+    with_chunk_bytes(&env, chunk, |bytes| self.inner.feed(bytes))?
+      .map_err(|e| Error::from_reason(e.to_string()))
+  }
+
+  /// Ends the input and returns the statistics of every column
+  #[napi]
+  pub fn get_stats(&mut self) -> Result<Vec<ColumnStats>> {
+    self
+      .inner
+      .finish()
+      .map_err(|e| Error::from_reason(e.to_string()))?;
+
+    Ok(
+      self
+        .inner
+        .stats()
+        .into_iter()
+        .map(ColumnStats::from)
+        .collect(),
+    )
+  }
+
+  /// Rows counted so far
+  #[napi(getter)]
+  pub fn rows(&self) -> i64 {
+    self.inner.rows() as i64
+  }
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct CsvValidatorOptions {
+  /// Violations reported before the rest are left out (default 1000)
+  pub max_violations: Option<u32>,
+}
+
+#[napi(object)]
+pub struct Violation {
+  pub line: i64,
+  /// In characters, from 1
+  pub column: i64,
+  /// `unclosed-quote`, `stray-quote`, `text-after-quote`, `bare-lf`,
+  /// `bare-cr` or `field-count`
+  pub code: String,
+  pub message: String,
+}
+
+#[napi(object)]
+pub struct ValidationReport {
+  pub valid: bool,
+  /// Records in the input, including the header record
+  pub records: i64,
+  pub violations: Vec<Violation>,
+  /// Whether violations were left out past `maxViolations`
+  pub truncated: bool,
+}
+
+impl From<RustValidationReport> for ValidationReport {
+  fn from(report: RustValidationReport) -> Self {
+    Self {
+      valid: report.is_valid(),
+      records: report.records as i64,
+      violations: report
+        .violations
+        .into_iter()
+        .map(|violation| Violation {
+          line: violation.line as i64,
+          column: violation.column as i64,
+          code: violation.kind.code().to_string(),
+          message: violation.kind.to_string(),
+        })
+        .collect(),
+      truncated: report.truncated,
+    }
+  }
+}
+
+/// Checks CSV against RFC 4180 (commas, `"` quotes, CRLF line breaks, a
+/// fixed field count) and reports every violation with its line and column
+/// instead of stopping at the first one
+#[napi]
+pub struct CsvValidator {
+  inner: Rfc4180Validator,
+}
+
+#[napi]
+impl CsvValidator {
+  #[napi(constructor)]
+  pub fn new(options: Option<CsvValidatorOptions>) -> Self {
+    let options = options.unwrap_or_default();
+    Self {
+      inner: Rfc4180Validator::new(options.max_violations.unwrap_or(1000) as usize),
+    }
+  }
+
+  #[napi(ts_args_type = "chunk: string | Buffer | ArrayBufferView | ArrayBuffer")]
+  pub fn push(&mut self, env: Env, chunk: JsUnknown) -> Result<()> {
+    with_chunk_bytes(&env, chunk, |bytes| self.inner.feed(bytes))
+  }
+
+  /// Ends the input and returns the report. The validator can then check
+  /// another input.
+  #[napi]
+  pub fn finish(&mut self) -> ValidationReport {
+    self.inner.finish().into()
+  }
+}
+
+#[napi(object)]
+pub struct CsvJoinOptions {
+  /// Header of the key column in the streamed (left) input
+  pub key: String,
+  /// Header of the key column in the right input, when it differs from `key`
+  pub right_key: Option<String>,
+  /// `"inner"` (default) or `"left"`
+  #[napi(js_name = "type", ts_type = "'inner' | 'left'")]
+  pub kind: Option<String>,
+  /// Options for parsing the streamed input, as for `CsvParser`
+  pub left: Option<JsCsvParserOptions>,
+  /// Options for parsing the right input, as for `CsvParser`
+  pub right: Option<JsCsvParserOptions>,
+}
+
+/// Joins a streamed CSV input against a right input on a key column. The
+/// right input (a buffer, or the file at a path) is loaded into a hash table
+/// up front; the left input is pushed in chunks and joined in Rust.
+#[napi]
+pub struct CsvJoiner {
+  inner: Joiner,
+  row_object: RowObject,
+}
+
+#[napi]
+impl CsvJoiner {
+  #[napi(constructor)]
+  pub fn new(right: Either<Buffer, String>, options: CsvJoinOptions) -> Result<Self> {
+    let kind = match options.kind.as_deref() {
+      None | Some("inner") => JoinKind::Inner,
+      Some("left") => JoinKind::Left,
+      Some(other) => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!("type must be \"inner\" or \"left\", got \"{}\"", other),
+        ))
+      }
+    };
+    let options = JoinOptions {
+      key: options.key,
+      right_key: options.right_key,
+      kind,
+      left: parse_options(options.left)?,
+      right: parse_options(options.right)?,
+    };
+    let row_object = options.left.row_object;
+
+    let inner = match right {
+      Either::A(buffer) => Joiner::new(&buffer[..], options),
+      Either::B(path) => {
+        let file = std::fs::File::open(&path)
+          .map_err(|e| Error::from_reason(format!("Cannot open {}: {}", path, e)))?;
+        Joiner::new(file, options)
+      }
+    }
+    .map_err(|e| Error::from_reason(e.to_string()))?;
+
+    Ok(Self { inner, row_object })
+  }
+
+  /// Distinct keys in the right input
+  #[napi(getter)]
+  pub fn size(&self) -> u32 {
+    self.inner.len() as u32
+  }
+
+  /// Adds a chunk of the left input and returns the joined rows it completes
+  #[napi(
+    ts_args_type = "chunk: string | Buffer | ArrayBufferView | ArrayBuffer",
+    ts_generic_types = "T extends object = Record<string, string>",
+    ts_return_type = "Array<T>"
+  )]
+  pub fn push(&mut self, env: Env, chunk: JsUnknown) -> Result<Vec<Object>> {
+    let records = with_chunk_bytes(&env, chunk, |bytes| self.inner.feed(bytes))?
+      .map_err(|e| Error::from_reason(e.to_string()))?;
+    records_to_objects(&env, records, self.row_object)
+  }
+
+  /// Ends the left input and returns the remaining joined rows
+  #[napi(
+    ts_generic_types = "T extends object = Record<string, string>",
+    ts_return_type = "Array<T>"
+  )]
+  pub fn flush(&mut self, env: Env) -> Result<Vec<Object>> {
+    let records = self
+      .inner
+      .finish()
+      .map_err(|e| Error::from_reason(e.to_string()))?;
+    records_to_objects(&env, records, self.row_object)
+  }
+}
+
+#[napi(object)]
+pub struct CsvReshapeOptions {
+  /// `"long"` (one row per id and wide column, the default) or `"wide"`
+  /// (one row per run of rows sharing their ids)
+  #[napi(ts_type = "'long' | 'wide'")]
+  pub to: Option<String>,
+  /// Headers of the columns that identify a row, copied as they are
+  pub ids: Vec<String>,
+  /// Header of the long column holding wide column names; `"key"` by default
+  pub key: Option<String>,
+  /// Header of the long column holding the values; `"value"` by default
+  pub value: Option<String>,
+  /// To long, the columns to unpivot (every column but the ids by default);
+  /// to wide, the columns of each row, in order (the keys of its rows by
+  /// default)
+  pub columns: Option<Vec<String>>,
+  /// Options for parsing the input, as for `CsvParser`
+  pub parser: Option<JsCsvParserOptions>,
+}
+
+/// Reshapes rows between wide and long form while parsing: to long, a row
+/// such as `region,jan,feb` becomes one `region,key,value` row per month; to
+/// wide, consecutive rows sharing their ids are merged back into one, so the
+/// input should be grouped by them.
+#[napi]
+pub struct CsvReshaper {
+  inner: Reshaper,
+  row_object: RowObject,
+}
+
+#[napi]
+impl CsvReshaper {
+  #[napi(constructor)]
+  pub fn new(options: CsvReshapeOptions) -> Result<Self> {
+    let to = match options.to.as_deref() {
+      None | Some("long") => Shape::Long,
+      Some("wide") => Shape::Wide,
+      Some(other) => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!("to must be \"long\" or \"wide\", got \"{}\"", other),
+        ))
+      }
+    };
+    let defaults = ReshapeOptions::default();
+    let options = ReshapeOptions {
+      to,
+      ids: options.ids,
+      key: options.key.unwrap_or(defaults.key),
+      value: options.value.unwrap_or(defaults.value),
+      columns: options.columns,
+      parser: parse_options(options.parser)?,
+    };
+    let row_object = options.parser.row_object;
+
+    Ok(Self {
+      inner: Reshaper::new(options),
+      row_object,
+    })
+  }
+
+  /// Adds a chunk of input and returns the reshaped rows it completes
+  #[napi(
+    ts_args_type = "chunk: string | Buffer | ArrayBufferView | ArrayBuffer",
+    ts_generic_types = "T extends object = Record<string, string>",
+    ts_return_type = "Array<T>"
+  )]
+  pub fn push(&mut self, env: Env, chunk: JsUnknown) -> Result<Vec<Object>> {
+    let records = with_chunk_bytes(&env, chunk, |bytes| self.inner.feed(bytes))?
+      .map_err(|e| Error::from_reason(e.to_string()))?;
+    records_to_objects(&env, records, self.row_object)
+  }
+
+  /// Ends the input and returns the remaining reshaped rows
+  #[napi(
+    ts_generic_types = "T extends object = Record<string, string>",
+    ts_return_type = "Array<T>"
+  )]
+  pub fn flush(&mut self, env: Env) -> Result<Vec<Object>> {
+    let records = self
+      .inner
+      .finish()
+      .map_err(|e| Error::from_reason(e.to_string()))?;
+    records_to_objects(&env, records, self.row_object)
+  }
+}
+
+fn records_to_objects(
+  env: &Env,
+  records: Vec<reader::Record>,
+  kind: RowObject,
+) -> Result<Vec<Object>> {
+  let mut objects = RowObjects::default();
+  records
+    .into_iter()
+    .map(|record| {
+      let mut row = Row::new(env, kind, objects.create(env, kind)?)?;
+      for (key, value) in record.iter() {
+        row.set(env, env.create_string(key)?, env.create_string(value)?)?;
+      }
+      if let Some((key, values)) = record.extra() {
+        let mut array = env.create_array_with_length(values.len())?;
+        for (i, value) in values.iter().enumerate() {
+          array.set_element(i as u32, env.create_string(value)?)?;
+        }
+        row.set(env, env.create_string(key)?, array)?;
+      }
+      objects.finish(env, kind, &row.object)?;
+      Ok(row.object)
+    })
+    .collect()
+}
+
+#[napi(object)]
+pub struct SortByOptions {
+  /// Header of the column to sort by
+  pub column: String,
+  /// Compare values as numbers. Values that are not numbers sort last.
+  pub numeric: Option<bool>,
+  /// Largest value first
+  pub desc: Option<bool>,
+  /// Write the sorted CSV to this path instead of returning the rows
+  pub output: Option<String>,
+  /// Approximate bytes of rows held in memory before a sorted run is
+  /// spilled to disk (default 64 MiB)
+  pub memory_limit: Option<i64>,
+  /// Where run files go; the system temp directory by default
+  pub temp_dir: Option<String>,
+  /// Options for parsing the input, as for `CsvParser`
+  pub parser: Option<JsCsvParserOptions>,
+}
+
+/// Sorts the CSV file at `path` by a column with an external merge sort, so
+/// files larger than memory can be sorted. With `output`, writes the sorted
+/// CSV there and returns the number of rows; otherwise returns the rows in
+/// order, to be read in batches.
+#[napi(ts_return_type = "number | SortedRows")]
+pub fn sort_by(path: String, options: SortByOptions) -> Result<Either<i64, SortedRows>> {
+  let sort_options = sort::SortOptions {
+    column: options.column,
+    numeric: options.numeric.unwrap_or(false),
+    desc: options.desc.unwrap_or(false),
+    memory_limit: parse_memory_limit(options.memory_limit)?,
+    temp_dir: options.temp_dir.map(Into::into),
+    parser: parse_options(options.parser)?,
+  };
+  let row_object = sort_options.parser.row_object;
+
+  let file = std::fs::File::open(&path)
+    .map_err(|e| Error::from_reason(format!("Cannot open {}: {}", path, e)))?;
+  let rows = sort::sort_rows(file, sort_options).map_err(|e| Error::from_reason(e.to_string()))?;
+
+  match options.output {
+    Some(output) => {
+      let out = std::fs::File::create(&output)
+        .map_err(|e| Error::from_reason(format!("Cannot create {}: {}", output, e)))?;
+      let written = rows
+        .write_to(out)
+        .map_err(|e| Error::from_reason(e.to_string()))?;
+      Ok(Either::A(written as i64))
+    }
+    None => Ok(Either::B(SortedRows {
+      inner: rows,
+      row_object,
+    })),
+  }
+}
+
+fn parse_memory_limit(memory_limit: Option<i64>) -> Result<usize> {
+  match memory_limit {
+    None => Ok(sort::SortOptions::default().memory_limit),
+    Some(limit) if limit > 0 => Ok(limit as usize),
+    Some(_) => Err(Error::new(
+      Status::InvalidArg,
+      "memoryLimit must be a positive integer".to_string(),
+    )),
+  }
+}
+
+#[napi(object)]
+pub struct DiffOptions {
+  /// Header of the column that identifies a row in both files
+  pub key: String,
+  /// Approximate bytes of rows each file holds in memory while it is
+  /// sorted by key (default 64 MiB)
+  pub memory_limit: Option<i64>,
+  /// Where sort runs go; the system temp directory by default
+  pub temp_dir: Option<String>,
+  /// Options for parsing both files, as for `CsvParser`
+  pub parser: Option<JsCsvParserOptions>,
+}
+
+/// A row added, removed or changed between the files given to `diff()`
+#[napi(object)]
+pub struct DiffChange {
+  #[napi(js_name = "type", ts_type = "'added' | 'removed' | 'changed'")]
+  pub kind: String,
+  pub key: String,
+  /// The row in the old file; missing for added rows
+  #[napi(ts_type = "Record<string, string>")]
+  pub before: Option<Object>,
+  /// The row in the new file; missing for removed rows
+  #[napi(ts_type = "Record<string, string>")]
+  pub after: Option<Object>,
+  /// For changed rows, the columns whose values differ
+  pub columns: Vec<String>,
+}
+
+/// Compares an old CSV file with a new one, matching rows by the `key`
+/// column. Both files are sorted by key with the external sort of
+/// `sortBy()` first, so memory stays bounded whatever their size, and the
+/// changes come in key order, to be read in batches.
+#[napi]
+pub fn diff(old_path: String, new_path: String, options: DiffOptions) -> Result<CsvDiff> {
+  let diff_options = RustDiffOptions {
+    key: options.key,
+    memory_limit: parse_memory_limit(options.memory_limit)?,
+    temp_dir: options.temp_dir.map(Into::into),
+    parser: parse_options(options.parser)?,
+  };
+  let row_object = diff_options.parser.row_object;
+
+  let open = |path: &String| {
+    File::open(path).map_err(|e| Error::from_reason(format!("Cannot open {}: {}", path, e)))
+  };
+  let inner = diff_inputs(open(&old_path)?, open(&new_path)?, diff_options)
+    .map_err(|e| Error::from_reason(e.to_string()))?;
+  Ok(CsvDiff { inner, row_object })
+}
+
+/// Changes found by `diff()`, in key order
+#[napi]
+pub struct CsvDiff {
+  inner: Diff,
+  row_object: RowObject,
+}
+
+#[napi]
+impl CsvDiff {
+  /// The columns compared: those of the old file, then those only in the
+  /// new one. A column missing from one file counts as empty.
+  #[napi(getter)]
+  pub fn columns(&self) -> Vec<String> {
+    self.inner.columns().to_vec()
+  }
+
+  /// Rows found in both files with the same values so far
+  #[napi(getter)]
+  pub fn unchanged(&self) -> i64 {
+    self.inner.unchanged() as i64
+  }
+
+  /// Returns up to `count` (default 1000) more changes; an empty array once
+  /// both files have been read
+  #[napi]
+  pub fn next(&mut self, env: Env, count: Option<u32>) -> Result<Vec<DiffChange>> {
+    let changes = self
+      .inner
+      .by_ref()
+      .take(count.unwrap_or(1000) as usize)
+      .collect::<color_eyre::Result<Vec<_>>>()
+      .map_err(|e| Error::from_reason(e.to_string()))?;
+
+    let row = |record: Option<reader::Record>| -> Result<Option<Object>> {
+      let Some(record) = record else {
+        return Ok(None);
+      };
+      Ok(records_to_objects(&env, vec![record], self.row_object)?.pop())
+    };
+    changes
+      .into_iter()
+      .map(|change| {
+        Ok(DiffChange {
+          kind: match change.kind {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Changed => "changed",
+          }
+          .to_string(),
+          key: change.key,
+          before: row(change.before)?,
+          after: row(change.after)?,
+          columns: change.columns,
+        })
+      })
+      .collect()
+  }
+}
+
+/// How much of the input goes into each shard of `split()`; give one of the two
+#[napi(object)]
+pub struct SplitLimit {
+  /// Data rows per shard
+  pub rows: Option<i64>,
+  /// Bytes per shard, header included, as a number or a size such as
+  /// `"256MB"` (units are powers of 1024)
+  #[napi(ts_type = "number | string")]
+  pub bytes: Option<Either<i64, String>>,
+}
+
+/// A file written by `split()`
+#[napi(object)]
+pub struct ShardInfo {
+  pub path: String,
+  /// Data rows, not counting the header
+  pub rows: i64,
+  pub bytes: i64,
+}
+
+/// Splits the CSV file at `path` into shards in `outDir` (created if
+/// needed), named after the input with a counter: `data-00001.csv`, ...
+/// Shards end between records, never inside a quoted field, and each starts
+/// with the header line.
+#[napi]
+pub fn split(
+  path: String,
+  limit: SplitLimit,
+  out_dir: String,
+  options: Option<JsCsvParserOptions>,
+) -> Result<Vec<ShardInfo>> {
+  let invalid = |message: String| Error::new(Status::InvalidArg, message);
+  let limit = match (limit.rows, limit.bytes) {
+    (Some(rows), None) if rows > 0 => ShardLimit::Rows(rows as u64),
+    (None, Some(Either::A(bytes))) if bytes > 0 => ShardLimit::Bytes(bytes as u64),
+    (None, Some(Either::B(size))) => match parse_size(&size) {
+      Ok(bytes) if bytes > 0 => ShardLimit::Bytes(bytes),
+      Ok(_) => return Err(invalid("bytes must be positive".to_string())),
+      Err(e) => return Err(invalid(e.to_string())),
+    },
+    (Some(_), None) => return Err(invalid("rows must be a positive integer".to_string())),
+    (None, Some(_)) => return Err(invalid("bytes must be positive".to_string())),
+    _ => return Err(invalid("split needs one of rows or bytes".to_string())),
+  };
+  let options = parse_options(options)?;
+
+  let shards = split_file(&path, &out_dir, limit, options)
+    .map_err(|e| Error::from_reason(format!("Cannot split {}: {}", path, e)))?;
+  Ok(
+    shards
+      .into_iter()
+      .map(|shard| ShardInfo {
+        path: shard.path.to_string_lossy().into_owned(),
+        rows: shard.rows as i64,
+        bytes: shard.bytes as i64,
+      })
+      .collect(),
+  )
+}
+
+/// Rows returned by `sortBy`, in sorted order
+#[napi]
+pub struct SortedRows {
+  inner: sort::SortedRows,
+  row_object: RowObject,
+}
+
+#[napi]
+impl SortedRows {
+  /// The header names rows are keyed by
+  #[napi(getter)]
+  pub fn headers(&self) -> Vec<String> {
+    self.inner.headers().to_vec()
+  }
+
+  /// Returns up to `count` (default 1000) more rows; an empty array once all
+  /// rows have been read
+  #[napi(
+    ts_generic_types = "T extends object = Record<string, string>",
+    ts_return_type = "Array<T>"
+  )]
+  pub fn next(&mut self, env: Env, count: Option<u32>) -> Result<Vec<Object>> {
+    let records = self
+      .inner
+      .by_ref()
+      .take(count.unwrap_or(1000) as usize)
+      .collect::<color_eyre::Result<Vec<_>>>()
+      .map_err(|e| Error::from_reason(e.to_string()))?;
+    records_to_objects(&env, records, self.row_object)
+  }
+}
+
+#[napi(object)]
+pub struct AggregateOptions {
+  /// Column, or columns, whose values form the groups. Without it, all rows
+  /// form one group.
+  #[napi(ts_type = "string | Array<string>")]
+  pub group_by: Option<Either<String, Vec<String>>>,
+  /// Metric, or metrics, per column. A single metric is reported under the
+  /// column name, several under `<column>_<metric>`.
+  #[napi(ts_type = "Record<string, AggregateMetric | Array<AggregateMetric>>")]
+  pub metrics: Object,
+  /// Options for parsing the input, as for `CsvParser`
+  pub parser: Option<JsCsvParserOptions>,
+}
+
+/// Groups rows and aggregates columns in Rust while streaming, without
+/// creating row objects. Only the aggregated groups come back to JS.
+#[napi]
+pub struct CsvAggregator {
+  inner: Aggregator,
+}
+
+#[napi]
+impl CsvAggregator {
+  #[napi(constructor)]
+  pub fn new(options: AggregateOptions) -> Result<Self> {
+    let group_by = match options.group_by {
+      None => Vec::new(),
+      Some(Either::A(column)) => vec![column],
+      Some(Either::B(columns)) => columns,
+    };
+
+    let mut metrics = Vec::new();
+    let columns = options.metrics.get_property_names()?;
+    for i in 0..columns.get_array_length()? {
+      let column = columns
+        .get_element::<JsString>(i)?
+        .into_utf8()?
+        .into_owned()?;
+      let value: JsUnknown = options.metrics.get_named_property(&column)?;
+      let names = if value.is_array()? {
+        let array = unsafe { value.cast::<Object>() };
+        (0..array.get_array_length()?)
+          .map(|i| array.get_element::<JsString>(i)?.into_utf8()?.into_owned())
+          .collect::<Result<Vec<_>>>()?
+      } else if value.get_type()? == ValueType::String {
+        vec![unsafe { value.cast::<JsString>() }
+          .into_utf8()?
+          .into_owned()?]
+      } else {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!(
+            "metrics.{} must be a metric name or an array of them",
+            column
+          ),
+        ));
+      };
+
+      let single = names.len() == 1;
+      for name in names {
+        let metric =
+          Metric::parse(&name).map_err(|e| Error::new(Status::InvalidArg, e.to_string()))?;
+        metrics.push(MetricSpec {
+          name: if single {
+            column.clone()
+          } else {
+            format!("{}_{}", column, name)
+          },
+          column: column.clone(),
+          metric,
+        });
+      }
+    }
+
+    Ok(Self {
+      inner: Aggregator::new(parse_options(options.parser)?, group_by, metrics),
+    })
+  }
+
+  #[napi(ts_args_type = "chunk: string | Buffer | ArrayBufferView | ArrayBuffer")]
+  pub fn push(&mut self, env: Env, chunk: JsUnknown) -> Result<()> {
+    with_chunk_bytes(&env, chunk, |bytes| self.inner.feed(bytes))?
+      .map_err(|e| Error::from_reason(e.to_string()))
+  }
+
+  /// Ends the input and returns one object per group, holding the group-by
+  /// values and the metrics. Metrics of groups without numeric values are
+  /// `null`, except counts.
+  #[napi(ts_return_type = "Array<Record<string, string | number | null>>")]
+  pub fn get_result(&mut self, env: Env) -> Result<Vec<Object>> {
+    self
+      .inner
+      .finish()
+      .map_err(|e| Error::from_reason(e.to_string()))?;
+
+    let group_by = self.inner.group_by();
+    let metrics = self.inner.metrics();
+    self
+      .inner
+      .groups()
+      .into_iter()
+      .map(|group| {
+        let mut obj = env.create_object()?;
+        for (column, key) in group_by.iter().zip(&group.keys) {
+          obj.set_named_property(column, env.create_string(key)?)?;
+        }
+        for (spec, value) in metrics.iter().zip(group.values) {
+          match value {
+            Some(value) => obj.set_named_property(&spec.name, env.create_double(value)?)?,
+            None => obj.set_named_property(&spec.name, env.get_null()?)?,
+          }
+        }
+        Ok(obj)
+      })
+      .collect()
+  }
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct CsvRewriterOptions {
+  /// Dialect of the input, as for `CsvParser`
+  pub input: Option<JsCsvParserOptions>,
+  /// Encoding of the input (e.g. `"latin1"`). By default input is UTF-8, or
+  /// UTF-16 when it starts with a BOM.
+  pub input_encoding: Option<String>,
+  /// Dialect of the output, as for `CsvWriter`. `headers` is not used; see
+  /// `columns` and `rename`. `missingValue` fills the cells of columns the
+  /// input lacks and of rows shorter than the header. `encoding`, `bom` and
+  /// `compression` apply when the options of the same name are not set.
+  pub output: Option<JsCsvWriterOptions>,
+  /// Encoding of the output (e.g. `"utf-16le"`); UTF-8 by default
+  pub output_encoding: Option<String>,
+  /// Start the output with a byte order mark, for UTF-8 and UTF-16 output
+  pub bom: Option<bool>,
+  /// Compress the output, e.g. `{ type: "gzip", level: 6 }`; `flush()`
+  /// returns the end of the compressed stream
+  pub compression: Option<CompressionOptions>,
+  /// Columns to keep, in output order. All columns by default. A column the
+  /// input lacks is an error unless `output.missingValue` is set.
+  pub columns: Option<Vec<String>>,
+  /// New names for columns in the header line
+  pub rename: Option<HashMap<String, String>>,
+}
+
+/// Parses CSV in one dialect and encoding and writes it in another, in a
+/// single native pass. Output comes back as Buffers, ready to write to a
+/// file.
+#[napi]
+pub struct CsvRewriter {
+  inner: Rewriter,
+}
+
+#[napi]
+impl CsvRewriter {
+  #[napi(constructor)]
+  pub fn new(options: Option<CsvRewriterOptions>) -> Result<Self> {
+    let options = options.unwrap_or_default();
+    let output = options.output.unwrap_or_default();
+    let bom = writer_bom(&output, options.bom);
+    let inner = Rewriter::new(RewriteOptions {
+      parser: parse_options(options.input)?,
+      input_encoding: options.input_encoding,
+      writer: parse_writer_options(&output)?,
+      output_encoding: options.output_encoding.or(output.encoding),
+      bom,
+      columns: options.columns,
+      rename: options.rename.unwrap_or_default().into_iter().collect(),
+      missing_value: output.missing_value,
+      compression: match options.compression.or(output.compression) {
+        Some(compression) => Some(parse_compression(&compression)?),
+        None => None,
+      },
+    })
+    .map_err(|e| Error::new(Status::InvalidArg, e.to_string()))?;
+
+    Ok(Self { inner })
+  }
+
+  /// Adds a chunk of input and returns the output for the rows it completes
+  #[napi(ts_args_type = "chunk: string | Buffer | ArrayBufferView | ArrayBuffer")]
+  pub fn push(&mut self, env: Env, chunk: JsUnknown) -> Result<Buffer> {
+    let out = with_chunk_bytes(&env, chunk, |bytes| self.inner.feed(bytes))?
+      .map_err(|e| Error::from_reason(e.to_string()))?;
+    Ok(out.into())
+  }
+
+  /// Ends the input and returns the rest of the output
+  #[napi]
+  pub fn flush(&mut self) -> Result<Buffer> {
+    let out = self
+      .inner
+      .finish()
+      .map_err(|e| Error::from_reason(e.to_string()))?;
+    Ok(out.into())
+  }
+}
+
+/// Converts input in a named encoding to UTF-8 Buffers without parsing it.
+/// Chunks may split characters anywhere; a BOM at the start is removed and,
+/// for UTF-8 and UTF-16, overrides the given encoding.
+#[napi]
+pub struct Transcoder {
+  inner: RustTranscoder,
+}
+
+#[napi]
+impl Transcoder {
+  /// `encoding` is a label such as `"latin1"`, `"shift_jis"` or
+  /// `"utf-16le"`; UTF-8 by default
+  #[napi(constructor)]
+  pub fn new(encoding: Option<String>) -> Result<Self> {
+    let inner = RustTranscoder::new(encoding.as_deref())
+      .map_err(|e| Error::new(Status::InvalidArg, e.to_string()))?;
+    Ok(Self { inner })
+  }
+
+  /// Name of the encoding input is decoded from, e.g. `"UTF-16LE"` once a
+  /// BOM has said so
+  #[napi(getter)]
+  pub fn encoding(&self) -> String {
+    self.inner.encoding().name().to_string()
+  }
+
+  /// Decodes a chunk, keeping a character split across chunks for the next
+  /// call
+  #[napi(ts_args_type = "chunk: string | Buffer | ArrayBufferView | ArrayBuffer")]
+  pub fn push(&mut self, env: Env, chunk: JsUnknown) -> Result<Buffer> {
+    let out = with_chunk_bytes(&env, chunk, |bytes| self.inner.feed(bytes))?
+      .map_err(|e| Error::from_reason(e.to_string()))?;
+    Ok(out.into())
+  }
+
+  /// Ends the input and returns the rest of the output. Fails if the input
+  /// ended inside a character.
+  #[napi]
+  pub fn flush(&mut self) -> Result<Buffer> {
+    let out = self
+      .inner
+      .finish()
+      .map_err(|e| Error::from_reason(e.to_string()))?;
+    Ok(out.into())
+  }
+}
+
+/// Reads a CSV file in the background only as fast as rows are asked for,
+/// so a slow consumer pauses the file reads. Backs `createReadStream()`.
+#[napi]
+pub struct CsvFileReader {
+  inner: Arc<Mutex<FileRows>>,
+  row_object: RowObject,
+}
+
+struct FileRows {
+  file: File,
+  parser: reader::Parser,
+  chunk: Vec<u8>,
+  at_end: bool,
+}
+
+impl FileRows {
+  // Parses up to `count` more rows, reading the file only as far as needed
+  fn read(&mut self, count: usize) -> color_eyre::Result<Vec<reader::Record>> {
+    let mut rows = Vec::new();
+    let mut read = 0;
+    loop {
+      let records = if self.at_end {
+        self.parser.finish()?
+      } else {
+        self.parser.feed(&self.chunk[..read])?
+      };
+      for record in records.take(count - rows.len()) {
+        rows.push(record?);
+      }
+      if rows.len() == count || self.at_end {
+        return Ok(rows);
+      }
+      read = self.file.read(&mut self.chunk)?;
+      self.at_end = read == 0;
+    }
+  }
+}
+
+pub struct ReadRows {
+  rows: Arc<Mutex<FileRows>>,
+  count: usize,
+  row_object: RowObject,
+}
+
+impl Task for ReadRows {
+  type Output = Vec<reader::Record>;
+  type JsValue = Vec<Object>;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    let mut rows = self
+      .rows
+      .lock()
+      .map_err(|_| Error::from_reason("A previous read failed".to_string()))?;
+    rows
+      .read(self.count)
+      .map_err(|e| Error::from_reason(e.to_string()))
+  }
+
+  fn resolve(&mut self, env: Env, output: Self::Output) -> Result<Self::JsValue> {
+    records_to_objects(&env, output, self.row_object)
+  }
+}
+
+#[napi]
+impl CsvFileReader {
+  #[napi(constructor)]
+  pub fn new(path: String, options: Option<JsCsvParserOptions>) -> Result<Self> {
+    let options = parse_options(options)?;
+    let row_object = options.row_object;
+    let parser = reader::Parser::new(options);
+    let file =
+      File::open(&path).map_err(|e| Error::from_reason(format!("Cannot open {}: {}", path, e)))?;
+
+    Ok(Self {
+      inner: Arc::new(Mutex::new(FileRows {
+        file,
+        parser,
+        chunk: vec![0; 64 * 1024],
+        at_end: false,
+      })),
+      row_object,
+    })
+  }
+
+  /// Parses up to `count` more rows on the libuv thread pool. Resolves to an
+  /// empty array once the file is done.
+  #[napi(
+    ts_generic_types = "T extends object = Record<string, string>",
+    ts_return_type = "Promise<Array<T>>"
+  )]
+  pub fn read(&self, count: u32) -> AsyncTask<ReadRows> {
+    AsyncTask::new(ReadRows {
+      rows: self.inner.clone(),
+      count: count.max(1) as usize,
+      row_object: self.row_object,
+    })
+  }
+
+  /// The header names, once the header line has been read
+  #[napi(getter)]
+  pub fn headers(&self) -> Option<Vec<String>> {
+    let rows = self.inner.lock().ok()?;
+    rows.parser.headers().map(<[String]>::to_vec)
+  }
+}
+
+#[napi(object)]
+pub struct ParseThreadOptions {
+  /// Parsed batches the thread may hold before it waits for `read()`, and
+  /// chunks `push()` accepts before it returns false; 4 by default
+  pub max_pending_batches: Option<u32>,
+  /// Options for parsing the input, as for `CsvParser`
+  pub parser: Option<JsCsvParserOptions>,
+}
+
+// What `push()` and `end()` send to the parse thread
+enum ThreadInput {
+  Chunk(Vec<u8>),
+  End,
+}
+
+type Batch = std::result::Result<Vec<reader::Record>, String>;
+
+/// Parses on a dedicated thread, so large chunks do not block the event
+/// loop. `push()` hands a chunk over and returns at once; `read()` resolves
+/// to the rows of the oldest chunk not yet asked for. The thread stops
+/// parsing while `maxPendingBatches` batches wait to be taken.
+#[napi]
+pub struct CsvParseThread {
+  input: mpsc::Sender<ThreadInput>,
+  batches: Arc<Mutex<mpsc::Receiver<Batch>>>,
+  max_pending_batches: usize,
+  // Batches pushed or ended but not yet asked for by `read()`
+  pending: usize,
+  ended: bool,
+  row_object: RowObject,
+}
+
+pub struct ReadBatch {
+  // None when no batch is pending
+  batches: Option<Arc<Mutex<mpsc::Receiver<Batch>>>>,
+  // Whether the last batch has been taken
+  done: bool,
+  row_object: RowObject,
+}
+
+impl Task for ReadBatch {
+  type Output = Option<Vec<reader::Record>>;
+  type JsValue = Option<Vec<Object>>;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    let Some(batches) = &self.batches else {
+      return Ok((!self.done).then(Vec::new));
+    };
+    let batch = batches
+      .lock()
+      .map_err(|_| Error::from_reason("A previous read failed".to_string()))?
+      .recv()
+      .map_err(|_| Error::from_reason("The parse thread stopped".to_string()))?;
+    batch.map(Some).map_err(Error::from_reason)
+  }
+
+  fn resolve(&mut self, env: Env, output: Self::Output) -> Result<Self::JsValue> {
+    output
+      .map(|records| records_to_objects(&env, records, self.row_object))
+      .transpose()
+  }
+}
+
+#[napi]
+impl CsvParseThread {
+  #[napi(constructor)]
+  pub fn new(options: Option<ParseThreadOptions>) -> Result<Self> {
+    let (max_pending_batches, parser) = match options {
+      Some(options) => (options.max_pending_batches, options.parser),
+      None => (None, None),
+    };
+    let max_pending_batches = match max_pending_batches {
+      Some(0) => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          "maxPendingBatches must be a positive integer",
+        ))
+      }
+      n => n.unwrap_or(4) as usize,
+    };
+    let options = parse_options(parser)?;
+    let row_object = options.row_object;
+
+    let (input, chunks) = mpsc::channel();
+    let (sender, batches) = mpsc::sync_channel(max_pending_batches);
+    std::thread::Builder::new()
+      .name("fast-csv-parser".to_string())
+      .spawn(move || parse_thread(reader::Parser::new(options), chunks, sender))
+      .map_err(|e| Error::from_reason(format!("Cannot start the parse thread: {}", e)))?;
+
+    Ok(Self {
+      input,
+      batches: Arc::new(Mutex::new(batches)),
+      max_pending_batches,
+      pending: 0,
+      ended: false,
+      row_object,
+    })
+  }
+
+  /// Hands a chunk to the parse thread. Returns false once
+  /// `maxPendingBatches` batches are pending: wait for `read()` before
+  /// pushing more, as for a stream's `write()`.
+  #[napi(ts_args_type = "chunk: string | Buffer | ArrayBufferView | ArrayBuffer")]
+  pub fn push(&mut self, env: Env, chunk: JsUnknown) -> Result<bool> {
+    if self.ended {
+      return Err(Error::from_reason("push() after end()".to_string()));
+    }
+    let chunk = with_chunk_bytes(&env, chunk, <[u8]>::to_vec)?;
+    self
+      .input
+      .send(ThreadInput::Chunk(chunk))
+      .map_err(|_| Error::from_reason("The parse thread stopped".to_string()))?;
+    self.pending += 1;
+    Ok(self.pending < self.max_pending_batches)
+  }
+
+  /// Ends the input. Its last rows make one more batch.
+  #[napi]
+  pub fn end(&mut self) -> Result<()> {
+    if !self.ended {
+      self.ended = true;
+      self
+        .input
+        .send(ThreadInput::End)
+        .map_err(|_| Error::from_reason("The parse thread stopped".to_string()))?;
+      self.pending += 1;
+    }
+    Ok(())
+  }
+
+  /// Resolves to the rows of the oldest pending batch, which may be empty,
+  /// and rejects with its error if a row failed. Resolves at once to an
+  /// empty array when nothing is pending, and to null after the last batch.
+  #[napi(
+    ts_generic_types = "T extends object = Record<string, string>",
+    ts_return_type = "Promise<Array<T> | null>"
+  )]
+  pub fn read(&mut self) -> AsyncTask<ReadBatch> {
+    let batches = if self.pending > 0 {
+      self.pending -= 1;
+      Some(self.batches.clone())
+    } else {
+      None
+    };
+    let done = batches.is_none() && self.ended;
+    AsyncTask::new(ReadBatch {
+      batches,
+      row_object: self.row_object,
+      done,
+    })
+  }
+}
+
+// Parses chunks as they arrive, one batch per chunk and one at the end. Stops
+// when the `CsvParseThread` is dropped.
+fn parse_thread(
+  mut parser: reader::Parser,
+  chunks: mpsc::Receiver<ThreadInput>,
+  batches: mpsc::SyncSender<Batch>,
+) {
+  while let Ok(input) = chunks.recv() {
+    let records = match &input {
+      ThreadInput::Chunk(chunk) => parser.feed(chunk),
+      ThreadInput::End => parser.finish(),
+    };
+    let batch = records
+      .and_then(|records| records.collect::<color_eyre::Result<Vec<_>>>())
+      .map_err(|e| e.to_string());
+    if batches.send(batch).is_err() || matches!(input, ThreadInput::End) {
+      return;
+    }
+  }
+}
+
+// Pass the bytes of a chunk given to `push` to `f`. Binary inputs are
+// borrowed without copying; strings are encoded as UTF-8.
+fn with_chunk_bytes<R>(env: &Env, chunk: JsUnknown, f: impl FnOnce(&[u8]) -> R) -> Result<R> {
+  if chunk.get_type()? == ValueType::String {
+    let utf8 = unsafe { chunk.cast::<JsString>() }.into_utf8()?;
+    return Ok(f(utf8.as_slice()));
+  }
+
+  // napi gives a null pointer for an empty Buffer or ArrayBuffer, which
+  // must not become a slice
+  if chunk.is_buffer()? {
+    let mut data = std::ptr::null_mut();
+    let mut len = 0;
+    napi::check_status!(unsafe {
+      napi::sys::napi_get_buffer_info(env.raw(), chunk.raw(), &mut data, &mut len)
+    })?;
+    return Ok(f(unsafe { raw_bytes(data, len) }));
+  }
+
+  if chunk.is_typedarray()? {
+    let array = unsafe { chunk.cast::<JsTypedArray>() }.into_value()?;
+    let element_size = match array.typedarray_type {
+      TypedArrayType::Int8 | TypedArrayType::Uint8 | TypedArrayType::Uint8Clamped => 1,
+      TypedArrayType::Int16 | TypedArrayType::Uint16 => 2,
+      TypedArrayType::Int32 | TypedArrayType::Uint32 | TypedArrayType::Float32 => 4,
+      _ => 8,
+    };
+    let start = array.byte_offset;
+    let end = start + array.length * element_size;
+    if start == end {
+      return Ok(f(&[]));
+    }
+    let data = array.arraybuffer.into_value()?;
+    return Ok(f(&data[start..end]));
+  }
+
+  if chunk.is_dataview()? {
+    let view = unsafe { chunk.cast::<JsDataView>() }.into_value()?;
+    let start = view.byte_offset as usize;
+    let end = start + view.length as usize;
+    if start == end {
+      return Ok(f(&[]));
+    }
+    let data = view.arraybuffer.into_value()?;
+    return Ok(f(&data[start..end]));
+  }
+
+  let mut is_arraybuffer = false;
+  napi::check_status!(unsafe {
+    napi::sys::napi_is_arraybuffer(env.raw(), chunk.raw(), &mut is_arraybuffer)
+  })?;
+  if is_arraybuffer {
+    let mut data = std::ptr::null_mut();
+    let mut len = 0;
+    napi::check_status!(unsafe {
+      napi::sys::napi_get_arraybuffer_info(env.raw(), chunk.raw(), &mut data, &mut len)
+    })?;
+    return Ok(f(unsafe { raw_bytes(data, len) }));
+  }
+
+  Err(Error::new(
+    Status::InvalidArg,
+    "push() expects a string, Buffer, TypedArray, DataView or ArrayBuffer",
+  ))
+}
+
+// The `len` bytes at `data`, which may be null when `len` is 0
+unsafe fn raw_bytes<'a>(data: *mut std::ffi::c_void, len: usize) -> &'a [u8] {
+  if len == 0 {
+    return &[];
+  }
+  std::slice::from_raw_parts(data as *const u8, len)
+}
+
+/// The dialect parser options resolve to, with the `dialect` preset and
+/// defaults applied
+#[napi(object)]
+pub struct ResolvedOptions {
+  /// `"auto"` when the separator is guessed from the input
+  pub separator: String,
+  pub quote: String,
+  pub escape: String,
+  /// `"\n"` also reads lines ending in `"\r\n"`
+  pub newline: String,
+  /// Lowercase encoding name, or `"auto"` when it is detected from the input
+  pub encoding: String,
+  #[napi(ts_type = "'csv' | 'postgres'")]
+  pub format: String,
+  pub null_value: Option<String>,
+  pub excel: bool,
+  pub sep_directive: bool,
+  pub decimal_separator: String,
+  pub thousands_separator: Option<String>,
+}
+
+/// Checks parser options the way `new CsvParser()` does and returns the
+/// dialect they resolve to. Invalid options throw the same errors.
+#[napi]
+pub fn resolve_options(env: Env, options: Option<JsCsvParserOptions>) -> Result<ResolvedOptions> {
+  let options = parse_options(options).map_err(|e| type_error(&env, e))?;
+  let byte = |byte: u8| (byte as char).to_string();
+  Ok(ResolvedOptions {
+    separator: if options.detect_separator {
+      "auto".to_string()
+    } else {
+      byte(options.separator)
+    },
+    quote: byte(options.quote),
+    escape: byte(options.escape),
+    newline: byte(options.newline),
+    encoding: match options.encoding {
+      _ if options.detect_encoding => "auto".to_string(),
+      Some(encoding) => encoding.name().to_ascii_lowercase(),
+      None => "utf-8".to_string(),
+    },
+    format: match options.format {
+      RecordFormat::Csv => "csv",
+      RecordFormat::PostgresText => "postgres",
+    }
+    .to_string(),
+    null_value: options.null_value,
+    excel: options.excel,
+    sep_directive: options.sep_directive,
+    decimal_separator: byte(options.decimal_separator),
+    thousands_separator: options.thousands_separator.map(byte),
+  })
+}
+
+/// Parser options read and checked once, for creating many parsers with
+/// `CsvParser.fromTemplate()`. Parsers share the template and each starts
+/// from the first line, so one template serves any number of inputs.
+#[napi]
+pub struct ParserTemplate {
+  options: CsvParserOptions,
+  batch_size: Option<usize>,
+}
+
+#[napi]
+impl ParserTemplate {
+  /// Checks `options` as `new CsvParser()` does, throwing the same errors
+  #[napi(constructor)]
+  pub fn new(env: Env, options: Option<JsCsvParserOptions>) -> Result<Self> {
+    let batch_size = parse_batch_size(options.as_ref()).map_err(|e| type_error(&env, e))?;
+    let options = parse_options(options).map_err(|e| type_error(&env, e))?;
+    Ok(Self {
+      options,
+      batch_size,
+    })
+  }
+
+  /// The custom header names, if the options give them
+  #[napi(getter)]
+  pub fn headers(&self) -> Option<Vec<String>> {
+    self.options.headers.clone().filter(|headers| !headers.is_empty())
+  }
+}
+
+// `batchSize` only affects how rows are handed back to JS, so it is kept out
+// of the parser options
+fn parse_batch_size(options: Option<&JsCsvParserOptions>) -> Result<Option<usize>> {
+  match options.and_then(|o| o.batch_size) {
+    Some(n) if n < 1 => Err(Error::new(
+      Status::InvalidArg,
+      "batchSize must be a positive integer",
+    )),
+    n => Ok(n.map(|n| n as usize)),
+  }
+}
+
+// Convert the JS options object into parser options
+fn parse_options(options: Option<JsCsvParserOptions>) -> Result<CsvParserOptions> {
+  let Some(js_opts) = options else {
+    return Ok(CsvParserOptions::default());
+  };
+
+  let (trim, trim_quoted) = match &js_opts.trim {
+    None => (None, false),
+    Some(Either::A(trim)) => (Some(*trim), false),
+    Some(Either::B(value)) if value == "all" => (Some(true), true),
+    Some(Either::B(other)) => {
+      return Err(Error::new(
+        Status::InvalidArg,
+        format!("trim must be a boolean or \"all\", got \"{}\"", other),
+      ))
+    }
+  };
+
+  let skip_comments: Option<SkipComments> = if let Some(skip_comments) = js_opts.skip_comments {
+    let value_type = skip_comments.get_type()?;
+
+    match value_type {
+      ValueType::Boolean => {
+        let js_bool: napi::JsBoolean = unsafe { skip_comments.cast() };
+        let value = js_bool.get_value()?;
+        Some(SkipComments::Boolean(value))
+      }
+      ValueType::String => {
+        let js_string: napi::JsString = unsafe { skip_comments.cast() };
+        let utf8 = js_string.into_utf8()?;
+        let value = utf8.as_str()?;
+        Some(SkipComments::String(value.to_string()))
+      }
+      _ => None,
+    }
+  } else {
+    None
+  };
+
+  // let map_headers: Option<ThreadsafeFunction<()>> = js_opts.map_headers.map(|f| {
+  //   let func = f.into_threadsafe_function()?;
+  //   func
+  // });
+  // let map_values: Option<TheadsafeFunction<()>> = js_opts.map_values.map(|f| {
+  //   let func = f.into_threadsafe_function()?;
+  //   func
+  // });
+
+  let line_filter = match js_opts.line_filter {
+    Some(filter) => Some(
+      LineFilter::new(&filter.pattern, filter.negate.unwrap_or(false))
+        .map_err(|e| Error::new(Status::InvalidArg, e.to_string()))?,
+    ),
+    None => None,
+  };
+
+  let detect_separator = js_opts.separator.as_deref() == Some("auto");
+  let detect_encoding = js_opts.encoding.as_deref() == Some("auto");
+  let encoding = match js_opts.encoding.as_deref().filter(|_| !detect_encoding) {
+    Some(label) => Some(
+      TextEncoding::for_label(label).map_err(|e| Error::new(Status::InvalidArg, e.to_string()))?,
+    ),
+    None => None,
+  };
+
+  let column_types = parse_column_types(js_opts.column_types)?;
+  let json_columns = js_opts.json_columns.unwrap_or_default();
+  if let Some(column) = json_columns
+    .iter()
+    .find(|column| column_types.iter().any(|(typed, _)| typed == *column))
+  {
+    return Err(Error::new(
+      Status::InvalidArg,
+      format!(
+        "Column \"{}\" cannot be in both jsonColumns and columnTypes",
+        column
+      ),
+    ));
+  }
+
+  // Defaults the `dialect` preset sets
+  let (separator, excel, format, null_value) = match js_opts.dialect.as_deref() {
+    None | Some("csv") => (b',', false, RecordFormat::Csv, None),
+    Some("tsv") => (b'\t', false, RecordFormat::Csv, None),
+    Some("psv") => (b'|', false, RecordFormat::Csv, None),
+    Some("excel") => (b',', true, RecordFormat::Csv, None),
+    Some("postgres") => (b'\t', false, RecordFormat::PostgresText, Some("\\N")),
+    Some(other) => {
+      return Err(Error::new(
+        Status::InvalidArg,
+        format!(
+          "dialect must be \"csv\", \"tsv\", \"psv\", \"excel\" or \"postgres\", got \"{}\"",
+          other
+        ),
+      ))
+    }
+  };
+
+  let separator =
+    option_byte("separator", js_opts.separator.filter(|_| !detect_separator))?.unwrap_or(separator);
+  let quote = option_byte("quote", js_opts.quote)?.unwrap_or(b'"');
+  let escape = option_byte("escape", js_opts.escape)?.unwrap_or(b'"');
+  // Lines ending in `\r\n` are read with `\n`
+  let newline = match js_opts.newline {
+    Some(newline) if newline == "\r\n" => b'\n',
+    newline => option_byte("newline", newline)?.unwrap_or(b'\n'),
+  };
+  let decimal_separator =
+    option_byte("decimalSeparator", js_opts.decimal_separator)?.unwrap_or(b'.');
+  let thousands_separator = option_byte("thousandsSeparator", js_opts.thousands_separator)?;
+  check_distinct(&[
+    ("decimalSeparator", Some(decimal_separator)),
+    ("thousandsSeparator", thousands_separator),
+  ])?;
+  // A detected separator is only known once input arrives
+  let given_separator = Some(separator).filter(|_| !detect_separator);
+  check_distinct(&[
+    ("separator", given_separator),
+    ("quote", Some(quote)),
+    ("newline", Some(newline)),
+  ])?;
+  // The escape is the quote unless set otherwise
+  check_distinct(&[
+    ("separator", given_separator),
+    ("newline", Some(newline)),
+    ("escape", Some(escape).filter(|&escape| escape != quote)),
+  ])?;
+  // The raw line holds the cells `redact` hides
+  let redacts = js_opts.redact.as_ref().is_some_and(|redact| !redact.is_empty());
+  if js_opts.include_raw_line == Some(true) && redacts {
+    return Err(Error::new(
+      Status::InvalidArg,
+      "includeRawLine cannot be used with redact".to_string(),
+    ));
+  }
+  if js_opts.raw_rows == Some(true) && redacts {
+    return Err(Error::new(
+      Status::InvalidArg,
+      "rawRows cannot be used with redact".to_string(),
+    ));
+  }
+  // Hooks see and return records of strings, so nothing else builds values
+  if let Some(name) = &js_opts.batch_hook {
+    if hooks::batch_hook(name).is_none() {
+      return Err(Error::new(
+        Status::InvalidArg,
+        format!("batchHook \"{}\" is not registered", name),
+      ));
+    }
+    let converting = [
+      ("columnTypes", !column_types.is_empty()),
+      ("jsonColumns", !json_columns.is_empty()),
+      ("arrayColumns", js_opts.array_columns.is_some()),
+      ("expandHeaders", js_opts.expand_headers == Some(true)),
+      ("schema", js_opts.schema.is_some()),
+      ("raw", js_opts.raw == Some(true)),
+      ("includeRawLine", js_opts.include_raw_line == Some(true)),
+      ("rawRows", js_opts.raw_rows == Some(true)),
+      ("rowHash", js_opts.row_hash.is_some()),
+    ];
+    if let Some((option, _)) = converting.iter().find(|(_, set)| *set) {
+      return Err(Error::new(
+        Status::InvalidArg,
+        format!("batchHook cannot be used with {}", option),
+      ));
+    }
+  }
+  // Entries are flat pairs, with no objects to nest values in
+  if js_opts.row_object.as_deref() == Some("entries") && js_opts.expand_headers == Some(true) {
+    return Err(Error::new(
+      Status::InvalidArg,
+      "rowObject \"entries\" cannot be used with expandHeaders".to_string(),
+    ));
+  }
+
+  Ok(CsvParserOptions {
+    escape,
+    quote,
+    separator,
+    newline,
+    raw: js_opts.raw.unwrap_or(false),
+    strict: match js_opts.strict {
+      None | Some(Either::A(false)) => Strict::Off,
+      Some(Either::A(true)) => Strict::Error,
+      Some(Either::B(value)) if value == "report" => Strict::Report,
+      Some(Either::B(other)) => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!("strict must be a boolean or \"report\", got \"{}\"", other),
+        ))
+      }
+    },
+    max_row_bytes: js_opts.max_row_bytes.unwrap_or(i64::MAX),
+    headers: if let Some(headers_val) = js_opts.headers {
+      let value_type = headers_val.get_type()?;
+      match value_type {
+        ValueType::Boolean => {
+          let js_bool: napi::JsBoolean = unsafe { headers_val.cast() };
+          let value = js_bool.get_value()?;
+          if value {
+            // headers: true means auto-detect headers from first row
+            None
+          } else {
+            // headers: false means no headers, use numeric column names
+            Some(vec![])
+          }
+        }
+        ValueType::Object => {
+          // Assume it's an array
+          let js_array: napi::JsObject = unsafe { headers_val.cast() };
+          let length: u32 = js_array
+            .get_named_property::<napi::JsNumber>("length")?
+            .get_uint32()?;
+          let mut headers = Vec::new();
+          for i in 0..length {
+            let element: napi::JsString = js_array.get_element(i)?;
+            let utf8 = element.into_utf8()?;
+            headers.push(utf8.as_str()?.to_string());
+          }
+          Some(headers)
+        }
+        _ => None,
+      }
+    } else {
+      None
+    },
+    skip_comments,
+    skip_lines: js_opts.skip_lines,
+    skip_empty_lines: match js_opts.skip_empty_lines {
+      None | Some(Either::A(false)) => SkipEmptyLines::Off,
+      Some(Either::A(true)) => SkipEmptyLines::Blank,
+      Some(Either::B(value)) if value == "greedy" => SkipEmptyLines::Greedy,
+      Some(Either::B(other)) => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!(
+            "skipEmptyLines must be a boolean or \"greedy\", got \"{}\"",
+            other
+          ),
+        ))
+      }
+    },
+    extra_columns: match js_opts.extra_columns {
+      None => ExtraColumns::default(),
+      Some(Either::A(value)) if value == "drop" => ExtraColumns::Drop,
+      Some(Either::A(value)) if value == "collect" => ExtraColumns::Collect("_rest".to_string()),
+      Some(Either::A(other)) => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!(
+            "extraColumns must be \"drop\", \"collect\" or an object, got \"{}\"",
+            other
+          ),
+        ))
+      }
+      Some(Either::B(ExtraColumnsOptions {
+        prefix: Some(_),
+        collect: Some(_),
+      })) => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          "extraColumns takes either prefix or collect, not both".to_string(),
+        ))
+      }
+      Some(Either::B(ExtraColumnsOptions {
+        collect: Some(key), ..
+      })) => ExtraColumns::Collect(key),
+      Some(Either::B(ExtraColumnsOptions { prefix, .. })) => {
+        ExtraColumns::Prefix(prefix.unwrap_or_else(|| "_".to_string()))
+      }
+    },
+    skip_empty_fields: js_opts.skip_empty_fields.unwrap_or(false),
+    ltrim: js_opts.ltrim.or(trim).unwrap_or(false),
+    rtrim: js_opts.rtrim.or(trim).unwrap_or(false),
+    trim_quoted,
+    relax_quotes: js_opts.relax_quotes.unwrap_or(false),
+    trailing_comments: js_opts.trailing_comments.unwrap_or(false),
+    // A sniffed file may well come from Excel, which writes `sep=` lines
+    sep_directive: js_opts.sep_directive.unwrap_or(excel || detect_separator),
+    excel: js_opts.excel.unwrap_or(excel),
+    validate_utf8: match js_opts.validate_utf8.as_deref() {
+      None | Some("full") => Utf8Validation::Full,
+      Some("fast") => Utf8Validation::Fast,
+      Some("off") => Utf8Validation::Off,
+      Some(other) => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!(
+            "validateUtf8 must be \"full\", \"fast\" or \"off\", got \"{}\"",
+            other
+          ),
+        ))
+      }
+    },
+    dedupe: match js_opts.dedupe {
+      None | Some(Either::A(false)) => None,
+      Some(Either::A(true)) => Some(dedupe::DedupeOptions::default()),
+      Some(Either::B(options)) => Some(dedupe::DedupeOptions {
+        keys: options.keys.unwrap_or_default(),
+        bloom: options.bloom.map(|bloom| {
+          let defaults = dedupe::BloomOptions::default();
+          dedupe::BloomOptions {
+            capacity: bloom.capacity.max(1) as usize,
+            false_positive_rate: bloom
+              .false_positive_rate
+              .unwrap_or(defaults.false_positive_rate),
+          }
+        }),
+      }),
+    },
+    column_types,
+    expand_headers: js_opts.expand_headers.unwrap_or(false),
+    array_columns: parse_array_columns(js_opts.array_columns, js_opts.array_separator)?,
+    json_columns,
+    include_raw_line: match js_opts.include_raw_line {
+      Some(true) => Some(js_opts.raw_line_key.unwrap_or_else(|| "_raw".to_string())),
+      _ => None,
+    },
+    max_buffered_bytes: js_opts.max_buffered_bytes.map(|n| n.max(0) as usize),
+    max_columns: js_opts.max_columns.map(|n| n as usize),
+    row_object: match js_opts.row_object.as_deref() {
+      None | Some("plain") => RowObject::Plain,
+      Some("null-prototype") => RowObject::NullPrototype,
+      Some("frozen") => RowObject::Frozen,
+      Some("entries") => RowObject::Entries,
+      Some(other) => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!(
+            "rowObject must be \"plain\", \"null-prototype\", \"frozen\" or \"entries\", \
+             got \"{}\"",
+            other
+          ),
+        ))
+      }
+    },
+    unsafe_headers: match js_opts.unsafe_headers.as_deref() {
+      None | Some("keep") => UnsafeHeaders::Keep,
+      Some("rename") => UnsafeHeaders::Rename,
+      Some("drop") => UnsafeHeaders::Drop,
+      Some("error") => UnsafeHeaders::Error,
+      Some(other) => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!(
+            "unsafeHeaders must be \"keep\", \"rename\", \"drop\" or \"error\", got \"{}\"",
+            other
+          ),
+        ))
+      }
+    },
+    max_columns_action: match js_opts.max_columns_action.as_deref() {
+      None | Some("error") => MaxColumnsAction::Error,
+      Some("truncate") => MaxColumnsAction::Truncate,
+      Some(other) => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!(
+            "maxColumnsAction must be \"error\" or \"truncate\", got \"{}\"",
+            other
+          ),
+        ))
+      }
+    },
+    malformed_json: match js_opts.malformed_json.as_deref() {
+      None | Some("error") => MalformedJson::Error,
+      Some("keep") => MalformedJson::Keep,
+      Some(other) => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!(
+            "malformedJson must be \"error\" or \"keep\", got \"{}\"",
+            other
+          ),
+        ))
+      }
+    },
+    line_filter,
+    encoding,
+    detect_encoding,
+    detect_separator,
+    format,
+    null_value: js_opts.null_value.or(null_value.map(str::to_string)),
+    row_hash: parse_hash_algorithm("rowHash", js_opts.row_hash)?,
+    digest: parse_hash_algorithm("digest", js_opts.digest)?,
+    hardened: js_opts.hardened.unwrap_or(false),
+    decimal_separator,
+    thousands_separator,
+    on_unterminated_quote: match js_opts.on_unterminated_quote.as_deref() {
+      Some("error") => UnterminatedQuote::Error,
+      None | Some("emit") => UnterminatedQuote::Emit,
+      Some("drop") => UnterminatedQuote::Drop,
+      Some(other) => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!(
+            "onUnterminatedQuote must be \"error\", \"emit\" or \"drop\", got \"{}\"",
+            other
+          ),
+        ))
+      }
+    },
+    sections: match js_opts.sections {
+      None | Some(Either::A(false)) => None,
+      Some(Either::A(true)) => Some(Sections {
+        blank_lines: true,
+        marker: None,
+      }),
+      Some(Either::B(options)) => {
+        if options.marker.as_deref() == Some("") {
+          return Err(Error::new(
+            Status::InvalidArg,
+            "sections.marker must not be empty",
+          ));
+        }
+        Some(Sections {
+          blank_lines: options.blank_lines.unwrap_or(false),
+          marker: options.marker,
+        })
+      }
+    },
+    max_field_bytes: js_opts.max_field_bytes.map(|n| n.max(0) as usize),
+    on_oversize_field: match js_opts.on_oversize_field.as_deref() {
+      None | Some("error") => OversizeField::Error,
+      Some("truncate") => OversizeField::Truncate,
+      Some(other) => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!(
+            "onOversizeField must be \"error\" or \"truncate\", got \"{}\"",
+            other
+          ),
+        ))
+      }
+    },
+    quoted_newlines: match js_opts.quoted_newlines.as_deref() {
+      None | Some("keep") => QuotedNewlines::Keep,
+      Some("lf") => QuotedNewlines::Lf,
+      Some("escape") => QuotedNewlines::Escape,
+      Some("strip") => QuotedNewlines::Strip,
+      Some(other) => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!(
+            "quotedNewlines must be \"keep\", \"lf\", \"escape\" or \"strip\", got \"{}\"",
+            other
+          ),
+        ))
+      }
+    },
+    column_aliases: parse_column_aliases(js_opts.column_aliases)?,
+    validators: parse_validators(js_opts.validators)?,
+    schema: js_opts
+      .schema
+      .map(|schema| RowSchema::new(&schema))
+      .transpose()
+      .map_err(|e| Error::new(Status::InvalidArg, e.to_string()))?,
+    redact: parse_redact(js_opts.redact)?,
+    redact_salt: js_opts.redact_salt.unwrap_or_default(),
+    pass_header_row: js_opts.pass_header_row.unwrap_or(false),
+    batch_hook: js_opts.batch_hook,
+    raw_rows: js_opts.raw_rows.unwrap_or(false),
+    empty_headers: match js_opts.empty_headers.as_deref() {
+      None | Some("drop") => EmptyHeaders::Drop,
+      Some("keep-positional") => EmptyHeaders::KeepPositional,
+      Some("error") => EmptyHeaders::Error,
+      Some(other) => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!(
+            "emptyHeaders must be \"drop\", \"keep-positional\" or \"error\", got \"{}\"",
+            other
+          ),
+        ))
+      }
+    },
+    record_terminator: match js_opts.record_terminator.as_deref() {
+      None | Some("newline") => RecordTerminator::Newline,
+      Some("any") => RecordTerminator::Any,
+      Some(other) => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!("recordTerminator must be \"newline\" or \"any\", got \"{}\"", other),
+        ))
+      }
+    },
+    on_violation: match js_opts.on_violation.as_deref() {
+      None | Some("error") => InvalidRow::Error,
+      Some("skip") => InvalidRow::Skip,
+      Some("annotate") => InvalidRow::Annotate,
+      Some(other) => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!(
+            "onViolation must be \"error\", \"skip\" or \"annotate\", got \"{}\"",
+            other
+          ),
+        ))
+      }
+    },
+  })
+}
+
+// Invalid options throw TypeErrors, which keep their InvalidArg code
+fn type_error(env: &Env, error: Error) -> Error {
+  if error.status != Status::InvalidArg {
+    return error;
+  }
+  Error::from(JsTypeError::from(error).into_unknown(*env))
+}
+
+// The byte a one-character option such as `separator` sets
+fn option_byte(option: &str, value: Option<String>) -> Result<Option<u8>> {
+  let Some(value) = value else {
+    return Ok(None);
+  };
+  match value.as_bytes() {
+    [] => Err(Error::new(
+      Status::InvalidArg,
+      format!("{} must not be empty", option),
+    )),
+    &[byte] => Ok(Some(byte)),
+    _ if value.chars().count() == 1 => Err(Error::new(
+      Status::InvalidArg,
+      format!("{} must be an ASCII character, got \"{}\"", option, value),
+    )),
+    _ => Err(Error::new(
+      Status::InvalidArg,
+      format!("{} must be a single character, got \"{}\"", option, value),
+    )),
+  }
+}
+
+// Checks the one-character options that must not share a byte, in the order
+// given. `None` stands for an option that does not apply.
+fn check_distinct(options: &[(&str, Option<u8>)]) -> Result<()> {
+  for (i, &(first, a)) in options.iter().enumerate() {
+    for &(second, b) in &options[i + 1..] {
+      if let (Some(a), Some(b)) = (a, b) {
+        if a == b {
+          return Err(Error::new(
+            Status::InvalidArg,
+            format!(
+              "{} and {} must differ, both are {:?}",
+              first, second, a as char
+            ),
+          ));
+        }
+      }
+    }
+  }
+  Ok(())
+}
+
+fn parse_hash_algorithm(option: &str, name: Option<String>) -> Result<Option<HashAlgorithm>> {
+  name
+    .map(|name| {
+      HashAlgorithm::for_name(&name).ok_or_else(|| {
+        Error::new(
+          Status::InvalidArg,
+          format!(
+            "{} must be \"xxh64\" or \"sha256\", got \"{}\"",
+            option, name
+          ),
+        )
+      })
+    })
+    .transpose()
+}
+
+fn parse_array_columns(
+  array_columns: Option<Either<Vec<String>, HashMap<String, String>>>,
+  array_separator: Option<String>,
+) -> Result<Vec<(String, String)>> {
+  let mut array_columns: Vec<_> = match array_columns {
+    None => Vec::new(),
+    Some(Either::A(columns)) => {
+      let separator = array_separator.unwrap_or_else(|| ";".to_string());
+      if separator.is_empty() {
+        return Err(Error::new(
+          Status::InvalidArg,
+          "arraySeparator must not be empty".to_string(),
+        ));
+      }
+      columns
+        .into_iter()
+        .map(|column| (column, separator.clone()))
+        .collect()
+    }
+    Some(Either::B(columns)) => columns.into_iter().collect(),
+  };
+  if let Some((column, _)) = array_columns
+    .iter()
+    .find(|(_, separator)| separator.is_empty())
+  {
+    return Err(Error::new(
+      Status::InvalidArg,
+      format!("arrayColumns.{} must not be empty", column),
+    ));
+  }
+  array_columns.sort();
+  Ok(array_columns)
+}
+
+fn parse_column_aliases(
+  column_aliases: Option<HashMap<String, Vec<String>>>,
+) -> Result<Vec<(String, Vec<String>)>> {
+  let mut column_aliases: Vec<_> = column_aliases.unwrap_or_default().into_iter().collect();
+  column_aliases.sort();
+  // Each name, keys included, may lead to one key only
+  let mut keys: HashMap<String, &str> = HashMap::new();
+  for (key, aliases) in &column_aliases {
+    for name in std::iter::once(key).chain(aliases) {
+      match keys.insert(name.to_lowercase(), key) {
+        Some(other) if other != key => {
+          return Err(Error::new(
+            Status::InvalidArg,
+            format!(
+              "columnAliases: \"{}\" names both \"{}\" and \"{}\"",
+              name, other, key
+            ),
+          ))
+        }
+        _ => {}
+      }
+    }
+  }
+  Ok(column_aliases)
+}
+
+fn parse_redact(redact: Option<HashMap<String, String>>) -> Result<Vec<(String, Redaction)>> {
+  let mut redact = redact
+    .unwrap_or_default()
+    .into_iter()
+    .map(|(column, redaction)| {
+      let redaction = match redaction.as_str() {
+        "hash" => Redaction::Hash,
+        "mask" => Redaction::Mask,
+        "drop" => Redaction::Drop,
+        other => {
+          return Err(Error::new(
+            Status::InvalidArg,
+            format!(
+              "redact.{} must be \"hash\", \"mask\" or \"drop\", got \"{}\"",
+              column, other
+            ),
+          ))
+        }
+      };
+      Ok((column, redaction))
+    })
+    .collect::<Result<Vec<_>>>()?;
+  redact.sort_by(|(a, _), (b, _)| a.cmp(b));
+  Ok(redact)
+}
+
+fn parse_validators(validators: Option<HashMap<String, String>>) -> Result<Vec<Validator>> {
+  let mut validators: Vec<_> = validators.unwrap_or_default().into_iter().collect();
+  validators.sort();
+  validators
+    .iter()
+    .map(|(column, pattern)| {
+      Validator::new(column, pattern).map_err(|e| Error::new(Status::InvalidArg, e.to_string()))
+    })
+    .collect()
+}
+
+fn parse_column_types(
+  column_types: Option<HashMap<String, ColumnTypeOptions>>,
+) -> Result<Vec<(String, CellType)>> {
+  let mut column_types = column_types
+    .unwrap_or_default()
+    .into_iter()
+    .map(|(column, options)| {
+      match options.kind.as_str() {
+        "date" => {}
+        "number" => {
+          let number = NumberColumn {
+            currency: options.currency.unwrap_or(false),
+            percent: options.percent.unwrap_or(false),
+          };
+          return Ok((column, CellType::Number(number)));
+        }
+        other => {
+          return Err(Error::new(
+            Status::InvalidArg,
+            format!(
+              "columnTypes.{}.type must be \"date\" or \"number\", got \"{}\"",
+              column, other
+            ),
+          ))
+        }
+      }
+      let output = match options.output.as_deref() {
+        None | Some("date") => DateOutput::Date,
+        Some("millis") => DateOutput::Millis,
+        Some(other) => {
+          return Err(Error::new(
+            Status::InvalidArg,
+            format!(
+              "columnTypes.{}.output must be \"date\" or \"millis\", got \"{}\"",
+              column, other
+            ),
+          ))
+        }
+      };
+      let date = DateColumn::new(options.format, options.tz.as_deref())
+        .map_err(|e| Error::new(Status::InvalidArg, format!("columnTypes.{}: {}", column, e)))?;
+      Ok((column, CellType::Date(date, output)))
+    })
+    .collect::<Result<Vec<_>>>()?;
+  column_types.sort_by(|(a, _), (b, _)| a.cmp(b));
+  Ok(column_types)
+}
+
+// Parse the line at `start..end` and build its JS object, creating each value
+// straight from the line buffer. Returns `None` for lines that produce no row.
+// Adds the time spent splitting the line to `tokenize`, if given.
+// A parsed record as an owned `Record` of strings, for a `batchHook`
+fn hook_record(
+  parser: &mut RustCsvParser,
+  buffer: &[u8],
+  start: usize,
+  end: usize,
+) -> Result<Option<reader::Record>> {
+  let record = match parser.parse_record(buffer, start, end) {
+    Ok(Some(cells)) => reader::record(parser, buffer, &cells),
+    Ok(None) => return Ok(None),
+    Err(e) => Err(e),
+  };
+  record
+    .map(Some)
+    .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))
+}
+
+fn parse_row(
+  parser: &mut RustCsvParser,
+  buffer: &[u8],
+  start: usize,
+  end: usize,
+  keys: &mut RowKeys,
+  env: &Env,
+  tokenize: Option<&mut Duration>,
+) -> Result<Option<Object>> {
+  let started = tokenize.is_some().then(Instant::now);
+  let record = parser.parse_record(buffer, start, end);
+  if let (Some(started), Some(tokenize)) = (started, tokenize) {
+    *tokenize += started.elapsed();
+  }
+  let cells = match record {
+    Ok(Some(cells)) => cells,
+    Ok(None) => return Ok(None),
+    Err(e) => return Err(Error::new(Status::GenericFailure, e.to_string())),
+  };
+
+  let trusted = parser.options.validate_utf8 != Utf8Validation::Full;
+  let bytes_value = |bytes: &[u8]| -> Result<JsUnknown> {
+    if parser.options.raw {
+      // Raw cells are Buffers of the cell bytes, copied straight from the line
+      let value = env.create_buffer_copy(bytes)?;
+      Ok(value.into_raw().into_unknown())
+    } else if trusted {
+      Ok(create_string_unchecked(env, bytes)?.into_unknown())
+    } else {
+      let value = parser
+        .decode(bytes)
+        .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))?;
+      Ok(env.create_string(&value)?.into_unknown())
+    }
+  };
+  let value = |cell: CellRange| bytes_value(parser.cell_bytes(buffer, cell));
+
+  // `Date` is looked up once per row that has a date column
+  let mut date_constructor = None;
+  let mut typed_value = |bytes: &[u8], cell_type: &CellType| -> Result<JsUnknown> {
+    let text = parser
+      .decode(bytes)
+      .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))?;
+    match cell_type {
+      // Empty cells are null, and values that do not parse stay strings
+      CellType::Date(..) if text.trim().is_empty() => Ok(env.get_null()?.into_unknown()),
+      CellType::Date(date, output) => match (date.parse(&text), output) {
+        (None, _) => Ok(env.create_string(&text)?.into_unknown()),
+        (Some(millis), DateOutput::Millis) => Ok(env.create_int64(millis)?.into_unknown()),
+        (Some(millis), DateOutput::Date) => {
+          let constructor: &JsFunction = match &mut date_constructor {
+            Some(constructor) => constructor,
+            None => date_constructor.insert(env.get_global()?.get_named_property("Date")?),
+          };
+          let date = constructor.new_instance(&[env.create_double(millis as f64)?])?;
+          Ok(date.into_unknown())
+        }
+      },
+      CellType::Number(_) if text.trim().is_empty() => Ok(env.get_null()?.into_unknown()),
+      CellType::Number(number) => match parser.number(number, &text) {
+        Some(number) => Ok(env.create_double(number)?.into_unknown()),
+        None => Ok(env.create_string(&text)?.into_unknown()),
+      },
+    }
+  };
+
+  let json_value = |bytes: &[u8], key: &str| -> Result<JsUnknown> {
+    let text = parser
+      .decode(bytes)
+      .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))?;
+    match parser.json_value(key, &text) {
+      Ok(Some(value)) => json_to_js(env, &value),
+      Ok(None) => Ok(env.create_string(&text)?.into_unknown()),
+      Err(e) => Err(Error::new(Status::GenericFailure, e.to_string())),
+    }
+  };
+
+  let kind = parser.options.row_object;
+  // Another handle to `Object.create`, for the objects `expandHeaders` nests
+  let create = keys
+    .objects
+    .create(env, kind)?
+    .map(|create| unsafe { JsFunction::from_raw_unchecked(env.raw(), create.raw()) });
+  let mut row = Row::new(env, kind, create.as_ref())?;
+  for (index, key, cell) in parser.row_fields(&cells) {
+    // Null cells, and cells `schema` converted to another type than strings
+    let value = match parser.coerced(index) {
+      Some(Coerced::Missing) => continue,
+      Some(Coerced::Null) => Some(env.get_null()?.into_unknown()),
+      Some(Coerced::Boolean(boolean)) => Some(env.get_boolean(boolean)?.into_unknown()),
+      Some(Coerced::Integer(integer)) => Some(env.create_int64(integer)?.into_unknown()),
+      Some(Coerced::Number(number)) => Some(env.create_double(number)?.into_unknown()),
+      _ if parser.is_null(buffer, cell) => Some(env.get_null()?.into_unknown()),
+      _ => None,
+    };
+    if let Some(value) = value {
+      match keys.path(parser.options.expand_headers, index, &key) {
+        Some(path) => set_path(env, &row.object, path, value, create.as_ref())?,
+        None => row.set(env, keys.get(env, index, &key)?, value)?,
+      }
+      continue;
+    }
+    let cell_type = parser.cell_type(&key);
+    let json = parser.is_json_column(&key);
+    let mut convert = |bytes: &[u8]| match (json, cell_type) {
+      (true, _) => json_value(bytes, &key),
+      (false, Some(cell_type)) => typed_value(bytes, cell_type),
+      (false, None) => bytes_value(bytes),
+    };
+    let value = match parser.array_separator(&key) {
+      // Each item is converted as a whole cell of the column would be
+      Some(separator) => {
+        let items = parser.cell_items(buffer, cell, separator);
+        let mut array = env.create_array_with_length(items.len())?;
+        for (i, item) in items.into_iter().enumerate() {
+          array.set_element(i as u32, convert(item)?)?;
+        }
+        array.into_unknown()
+      }
+      None => convert(parser.cell_bytes(buffer, cell))?,
+    };
+    match keys.path(parser.options.expand_headers, index, &key) {
+      Some(path) => set_path(env, &row.object, path, value, create.as_ref())?,
+      None => row.set(env, keys.get(env, index, &key)?, value)?,
+    }
+  }
+  if let Some((key, extra)) = parser.extra_cells(&cells) {
+    let mut array = env.create_array_with_length(extra.len())?;
+    for (i, &cell) in extra.iter().enumerate() {
+      let value = match parser.is_null(buffer, cell) {
+        true => env.get_null()?.into_unknown(),
+        false => value(cell)?,
+      };
+      array.set_element(i as u32, value)?;
+    }
+    row.set(env, env.create_string(key)?, array)?;
+  }
+  if let Some(key) = &parser.options.include_raw_line {
+    let line = parser.line_bytes(buffer, start, end);
+    let line = if parser.options.raw {
+      env.create_buffer_copy(line)?.into_raw().into_unknown()
+    } else {
+      create_string_unchecked(env, line)?.into_unknown()
+    };
+    row.set(env, env.create_string(key)?, line)?;
+  }
+  if parser.options.raw_rows {
+    let length = parser.line_bytes(buffer, start, end).len();
+    let line = keys.raw_lines.get(env, buffer, start, start + length)?;
+    row.set(env, env.create_string("__raw")?, line)?;
+  }
+  if let Some(algorithm) = parser.options.row_hash {
+    let hash = algorithm.hex(parser.line_bytes(buffer, start, end));
+    row.set(env, env.create_string("__hash")?, env.create_string(&hash)?)?;
+  }
+  if parser.options.on_violation == InvalidRow::Annotate && parser.options.validates() {
+    let violations = parser.violations();
+    let mut array = env.create_array_with_length(violations.len())?;
+    for (i, column) in violations.iter().enumerate() {
+      array.set_element(i as u32, env.create_string(column)?)?;
+    }
+    row.set(env, env.create_string("__violations")?, array)?;
+  }
+  keys.objects.finish(env, kind, &row.object)?;
+
+  Ok(Some(row.object))
+}
+
+/// A batch of rows packed into one ArrayBuffer
+#[napi(object)]
+pub struct PackedRows {
+  /// Per row, its field count; per field, the index of its key in
+  /// `columns`, the byte length of its UTF-8 value and the value. Counts,
+  /// indexes and lengths are little-endian u32.
+  #[napi(ts_type = "ArrayBuffer")]
+  pub buffer: JsArrayBuffer,
+  /// The keys of the fields in `buffer`
+  pub columns: Vec<String>,
+  /// The key that `extraColumns` collects cells beyond the headers under.
+  /// Fields with this key belong in one array, in order.
+  pub rest: Option<String>,
+  pub rows: u32,
+}
+
+#[derive(Default)]
+struct Packer {
+  data: Vec<u8>,
+  columns: Vec<String>,
+  // Position in `columns` of each cell index's key
+  cell_columns: Vec<Option<u32>>,
+  // Position in `columns` of the key collected cells go under
+  rest_column: Option<u32>,
+}
+
+impl Packer {
+  fn row(
+    &mut self,
+    parser: &mut RustCsvParser,
+    buffer: &[u8],
+    start: usize,
+    end: usize,
+  ) -> Result<Option<()>> {
+    let cells = match parser.parse_record(buffer, start, end) {
+      Ok(Some(cells)) => cells,
+      Ok(None) => return Ok(None),
+      Err(e) => return Err(Error::new(Status::GenericFailure, e.to_string())),
+    };
+    let trusted = parser.options.raw || parser.options.validate_utf8 != Utf8Validation::Full;
+
+    // The field count is filled in once the fields are written
+    let count_at = self.data.len();
+    self.data.extend_from_slice(&0u32.to_le_bytes());
+    let mut count = 0u32;
+    // Null cells are left out, as packed values are strings
+    let mut field = |packer: &mut Self, column: u32, cell: CellRange| -> Result<()> {
+      if parser.is_null(buffer, cell) {
+        return Ok(());
+      }
+      packer.data.extend_from_slice(&column.to_le_bytes());
+      if trusted {
+        packer.value(parser.cell_bytes(buffer, cell));
+      } else {
+        let value = parser
+          .cell_value(buffer, cell)
+          .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))?;
+        packer.value(value.as_bytes());
+      }
+      count += 1;
+      Ok(())
+    };
+    for (index, key, cell) in parser.row_fields(&cells) {
+      let column = self.column(index, &key);
+      field(self, column, cell)?;
+    }
+    if let Some((key, extra)) = parser.extra_cells(&cells) {
+      let column = *self.rest_column.get_or_insert_with(|| {
+        self.columns.push(key.to_string());
+        self.columns.len() as u32 - 1
+      });
+      for &cell in extra {
+        field(self, column, cell)?;
+      }
+    }
+    self.data[count_at..count_at + 4].copy_from_slice(&count.to_le_bytes());
+
+    Ok(Some(()))
+  }
+
+  fn column(&mut self, index: usize, key: &str) -> u32 {
+    if index >= self.cell_columns.len() {
+      self.cell_columns.resize(index + 1, None);
+    }
+    *self.cell_columns[index].get_or_insert_with(|| {
+      self.columns.push(key.to_string());
+      self.columns.len() as u32 - 1
+    })
+  }
+
+  fn value(&mut self, bytes: &[u8]) {
+    self
+      .data
+      .extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    self.data.extend_from_slice(bytes);
+  }
+
+  // Copies the rows into an ArrayBuffer owned by V8, which unlike external
+  // buffers can be transferred between threads
+  fn finish(self, env: &Env, rows: usize) -> Result<PackedRows> {
+    let mut buffer = env.create_arraybuffer(self.data.len())?;
+    buffer.copy_from_slice(&self.data);
+    let rest = self.rest_column.map(|i| self.columns[i as usize].clone());
+    Ok(PackedRows {
+      buffer: buffer.into_raw(),
+      columns: self.columns,
+      rest,
+      rows: rows as u32,
+    })
+  }
+}
+
+// JS strings for row keys, by column. Created on first use and shared by every
+// row object built in the same call, rather than converting the header names
+// again for each row. Handles are only valid until the call returns. With
+// `expandHeaders`, the paths headers name are kept the same way.
+#[derive(Default)]
+struct RowKeys {
+  keys: Vec<Option<JsString>>,
+  paths: Vec<Option<Option<Vec<PathSegment>>>>,
+  objects: RowObjects,
+  header_keys: Option<HeaderKeys>,
+  raw_lines: RawLines,
+}
+
+impl RowKeys {
+  // Keys taken from, and added to, strings a parser keeps between calls
+  fn with_header_keys(header_keys: HeaderKeys) -> Self {
+    Self {
+      header_keys: Some(header_keys),
+      ..Default::default()
+    }
+  }
+
+  fn into_header_keys(self) -> HeaderKeys {
+    self.header_keys.unwrap_or_default()
+  }
+
+  // The rows of this call are all parsed from one buffer, which the Buffers
+  // of `rawRows` can share once it is handed over
+  fn share_input(&mut self) {
+    self.raw_lines.shared = Some(Rc::default());
+  }
+
+  fn get(&mut self, env: &Env, index: usize, name: &str) -> Result<JsString> {
+    if index >= self.keys.len() {
+      self.keys.resize(index + 1, None);
+    }
+    match self.keys[index] {
+      Some(key) => Ok(key),
+      None => {
+        let key = match &mut self.header_keys {
+          Some(header_keys) => header_keys.get(env, index, name)?,
+          None => env.create_string(name)?,
+        };
+        self.keys[index] = Some(key);
+        Ok(key)
+      }
+    }
+  }
+
+  // The nested path column `index` is stored at, if `expand` is set and its
+  // header names one
+  fn path(&mut self, expand: bool, index: usize, name: &str) -> Option<&[PathSegment]> {
+    if !expand {
+      return None;
+    }
+    if index >= self.paths.len() {
+      self.paths.resize(index + 1, None);
+    }
+    self.paths[index]
+      .get_or_insert_with(|| header_path(name))
+      .as_deref()
+  }
+}
+
+// Row keys kept by a parser between calls, so each header name is converted
+// to a JS string once rather than on every call. Node only references
+// objects, so the strings are held in an array, by column. A column whose
+// header changes gets a new string.
+#[derive(Default)]
+struct HeaderKeys {
+  names: Vec<Option<String>>,
+  // The array and the env it belongs to
+  strings: Option<(Ref<()>, napi::sys::napi_env)>,
+}
+
+impl HeaderKeys {
+  fn get(&mut self, env: &Env, index: usize, name: &str) -> Result<JsString> {
+    let mut strings: napi::JsObject = match &self.strings {
+      Some((strings, _)) => env.get_reference_value(strings)?,
+      None => {
+        let strings = env.create_empty_array()?;
+        self.strings = Some((env.create_reference(&strings)?, env.raw()));
+        strings
+      }
+    };
+    if index >= self.names.len() {
+      self.names.resize(index + 1, None);
+    }
+    if self.names[index].as_deref() == Some(name) {
+      return strings.get_element(index as u32);
+    }
+
+    let key = env.create_string(name)?;
+    strings.set_element(index as u32, key)?;
+    self.names[index] = Some(name.to_string());
+    Ok(key)
+  }
+}
+
+// Parsers are dropped on the JS thread, by their finalizer or by the call
+// that made them
+impl Drop for HeaderKeys {
+  fn drop(&mut self) {
+    if let Some((mut strings, env)) = self.strings.take() {
+      let _ = strings.unref(unsafe { Env::from_raw(env) });
+    }
+  }
+}
+
+// `Object.create` and `Object.freeze`, looked up on first use and shared by
+// every row object built in the same call, for the `rowObject` option
+#[derive(Default)]
+struct RowObjects {
+  create: Option<JsFunction>,
+  freeze: Option<JsFunction>,
+}
+
+impl RowObjects {
+  // `Object.create` if rows are made without a prototype
+  fn create(&mut self, env: &Env, kind: RowObject) -> Result<Option<&JsFunction>> {
+    if kind != RowObject::NullPrototype {
+      return Ok(None);
+    }
+    if self.create.is_none() {
+      self.create = Some(object_function(env, "create")?);
+    }
+    Ok(self.create.as_ref())
+  }
+
+  // Freezes a filled row if rows are frozen
+  fn finish(&mut self, env: &Env, kind: RowObject, row: &Object) -> Result<()> {
+    if kind != RowObject::Frozen {
+      return Ok(());
+    }
+    let freeze = match &mut self.freeze {
+      Some(freeze) => freeze,
+      None => self.freeze.insert(object_function(env, "freeze")?),
+    };
+    freeze.call(
+      None,
+      &[unsafe { Object::from_raw_unchecked(env.raw(), row.raw()) }],
+    )?;
+    Ok(())
+  }
+}
+
+// The `__raw` Buffers of `rawRows`. When the rows of a call share their input
+// buffer, each row gets an external Buffer pointing into it, and once parsed
+// the buffer is handed over to those Buffers instead of being freed, so no
+// byte is copied; otherwise each row gets a copy of its own bytes.
+#[derive(Default)]
+struct RawLines {
+  // Empty until `hand_over`; the Buffers keep it alive until collected
+  shared: Option<Rc<RefCell<Vec<u8>>>>,
+}
+
+impl RawLines {
+  fn get(&mut self, env: &Env, buffer: &[u8], start: usize, end: usize) -> Result<JsUnknown> {
+    let line = &buffer[start..end];
+    let Some(shared) = self.shared.as_ref().filter(|_| !line.is_empty()) else {
+      return Ok(env.create_buffer_copy(line)?.into_raw().into_unknown());
+    };
+    // `buffer` is not written to until it is handed over, after the rows are
+    // built, and its bytes do not move when it is
+    let view = unsafe {
+      env.create_buffer_with_borrowed_data(
+        line.as_ptr() as *mut u8,
+        line.len(),
+        shared.clone(),
+        |shared, _| drop(shared),
+      )
+    }?;
+    Ok(view.into_raw().into_unknown())
+  }
+
+  // Takes the buffer the rows were parsed from, if any of their Buffers
+  // point into it
+  fn hand_over(&mut self, buffer: Vec<u8>) {
+    if let Some(shared) = self.shared.take().filter(|shared| Rc::strong_count(shared) > 1) {
+      *shared.borrow_mut() = buffer;
+    }
+  }
+}
+
+// A row being filled: an object keyed by column, or for `RowObject::Entries`
+// an array of `[key, value]` pairs in column order
+struct Row {
+  object: Object,
+  // Pairs appended so far, for `RowObject::Entries`
+  entries: Option<u32>,
+}
+
+impl Row {
+  fn new(env: &Env, kind: RowObject, create: Option<&JsFunction>) -> Result<Self> {
+    Ok(match kind {
+      RowObject::Entries => Self {
+        object: env.create_empty_array()?,
+        entries: Some(0),
+      },
+      _ => Self {
+        object: new_object(env, create)?,
+        entries: None,
+      },
+    })
+  }
+
+  fn set<V: NapiRaw>(&mut self, env: &Env, key: JsString, value: V) -> Result<()> {
+    let Some(length) = &mut self.entries else {
+      return self.object.set_property(key, value);
+    };
+    let mut pair = env.create_array_with_length(2)?;
+    pair.set_element(0, key)?;
+    pair.set_element(1, value)?;
+    self.object.set_element(*length, pair)?;
+    *length += 1;
+    Ok(())
+  }
+}
+
+fn object_function(env: &Env, name: &str) -> Result<JsFunction> {
+  let object: JsFunction = env.get_global()?.get_named_property("Object")?;
+  object.coerce_to_object()?.get_named_property(name)
+}
+
+// An empty row object, or object nested in one: without a prototype when
+// `create` is `Object.create`
+fn new_object(env: &Env, create: Option<&JsFunction>) -> Result<Object> {
+  match create {
+    Some(create) => Ok(unsafe { create.call(None, &[env.get_null()?])?.cast() }),
+    None => env.create_object(),
+  }
+}
+
+// Sets `value` at `path` under `obj`, creating the objects and arrays on the
+// way. A plain value already in the way is replaced.
+fn set_path(
+  env: &Env,
+  obj: &Object,
+  path: &[PathSegment],
+  value: JsUnknown,
+  create: Option<&JsFunction>,
+) -> Result<()> {
+  // Another handle to the same JS object
+  let handle = |object: &Object| unsafe { Object::from_raw_unchecked(env.raw(), object.raw()) };
+  // Only own properties, so a header such as `__proto__.x` never reaches a
+  // shared prototype
+  let get = |parent: &Object, segment: &PathSegment| -> Result<JsUnknown> {
+    match segment {
+      PathSegment::Key(key) if !parent.has_own_property(key)? => {
+        Ok(env.get_undefined()?.into_unknown())
+      }
+      PathSegment::Key(key) => parent.get_named_property(key),
+      PathSegment::Index(index) => parent.get_element(*index),
+    }
+  };
+  let set = |parent: &mut Object, segment: &PathSegment, value: JsUnknown| -> Result<()> {
+    match segment {
+      PathSegment::Key(key) => parent.set_named_property(key, value),
+      PathSegment::Index(index) => parent.set_element(*index, value),
+    }
+  };
+
+  let mut parent = handle(obj);
+  for (i, segment) in path[..path.len() - 1].iter().enumerate() {
+    let existing = get(&parent, segment)?;
+    parent = if existing.get_type()? == ValueType::Object {
+      unsafe { existing.cast() }
+    } else {
+      let child = match path[i + 1] {
+        PathSegment::Index(_) => env.create_array_with_length(0)?,
+        PathSegment::Key(_) => new_object(env, create)?,
+      };
+      set(&mut parent, segment, handle(&child).into_unknown())?;
+      child
+    };
+  }
+  set(&mut parent, &path[path.len() - 1], value)
+}
+
+fn json_to_js(env: &Env, value: &serde_json::Value) -> Result<JsUnknown> {
+  use serde_json::Value;
+
+  Ok(match value {
+    Value::Null => env.get_null()?.into_unknown(),
+    Value::Bool(value) => env.get_boolean(*value)?.into_unknown(),
+    // Integers beyond 2^53 lose precision, as with `JSON.parse`
+    Value::Number(number) => env
+      .create_double(number.as_f64().unwrap_or(f64::NAN))?
+      .into_unknown(),
+    Value::String(value) => env.create_string(value)?.into_unknown(),
+    Value::Array(items) => {
+      let mut array = env.create_array_with_length(items.len())?;
+      for (i, item) in items.iter().enumerate() {
+        array.set_element(i as u32, json_to_js(env, item)?)?;
+      }
+      array.into_unknown()
+    }
+    Value::Object(entries) => {
+      let mut object = env.create_object()?;
+      for (key, value) in entries {
+        object.set_property(env.create_string(key)?, json_to_js(env, value)?)?;
+      }
+      object.into_unknown()
+    }
+  })
+}
+
+// Create a JS string from bytes without validating them in Rust. V8 decodes
+// the bytes itself and replaces invalid sequences with U+FFFD.
+fn create_string_unchecked(env: &Env, bytes: &[u8]) -> Result<JsString> {
+  let mut value = std::ptr::null_mut();
+  napi::check_status!(unsafe {
+    napi::sys::napi_create_string_utf8(env.raw(), bytes.as_ptr().cast(), bytes.len(), &mut value)
+  })?;
+  Ok(unsafe { JsString::from_raw_unchecked(env.raw(), value) })
+}
@@ -0,0 +1,247 @@
+/// How the numbers of a column are written, e.g. `1.234,56` in much of
+/// Europe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberFormat {
+  pub decimal_separator: u8,
+  /// Separator between groups of three digits, which is then optional
+  pub thousands_separator: Option<u8>,
+}
+
+impl Default for NumberFormat {
+  fn default() -> Self {
+    Self {
+      decimal_separator: b'.',
+      thousands_separator: None,
+    }
+  }
+}
+
+impl NumberFormat {
+  /// The number `text` holds, or `None` if it is not a decimal number in this
+  /// format. Surrounding whitespace, a sign and an exponent are accepted;
+  /// `NaN`, `Infinity` and hex are not.
+  pub fn parse(&self, text: &str) -> Option<f64> {
+    self.normalize(text)?.parse().ok()
+  }
+
+  // `text` as Rust parses numbers, without separators
+  fn normalize(&self, text: &str) -> Option<String> {
+    let bytes = text.trim().as_bytes();
+    let mut number = String::with_capacity(bytes.len());
+    let mut i = 0;
+    if let Some(&sign @ (b'+' | b'-')) = bytes.first() {
+      number.push(sign as char);
+      i += 1;
+    }
+
+    // Integer digits, grouped by the thousands separator: one to three
+    // digits, then groups of exactly three
+    let mut digits = 0;
+    let mut group = 0;
+    let mut grouped = false;
+    while let Some(&byte) = bytes.get(i) {
+      if byte.is_ascii_digit() {
+        number.push(byte as char);
+        digits += 1;
+        group += 1;
+      } else if Some(byte) == self.thousands_separator {
+        if group == 0 || group > 3 || (grouped && group != 3) {
+          return None;
+        }
+        grouped = true;
+        group = 0;
+      } else {
+        break;
+      }
+      i += 1;
+    }
+    if grouped && group != 3 {
+      return None;
+    }
+
+    if bytes.get(i) == Some(&self.decimal_separator) {
+      number.push('.');
+      i += 1;
+      while let Some(&byte) = bytes.get(i).filter(|byte| byte.is_ascii_digit()) {
+        number.push(byte as char);
+        digits += 1;
+        i += 1;
+      }
+    }
+    if digits == 0 {
+      return None;
+    }
+
+    if let Some(&(b'e' | b'E')) = bytes.get(i) {
+      number.push('e');
+      i += 1;
+      if let Some(&sign @ (b'+' | b'-')) = bytes.get(i) {
+        number.push(sign as char);
+        i += 1;
+      }
+      let start = i;
+      while let Some(&byte) = bytes.get(i).filter(|byte| byte.is_ascii_digit()) {
+        number.push(byte as char);
+        i += 1;
+      }
+      if i == start {
+        return None;
+      }
+    }
+
+    (i == bytes.len()).then_some(number)
+  }
+}
+
+/// How the cells of a number column are normalized before they are read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NumberColumn {
+  /// Strip currency symbols before or after the number, read `(12.50)` as
+  /// `-12.5`, and accept thousands separators even where none is set: `,`,
+  /// or `.` when the decimal separator is `,`
+  pub currency: bool,
+  /// Read `12.5%` as `0.125`; values without `%` are read as they are
+  pub percent: bool,
+}
+
+impl NumberColumn {
+  /// The number a cell holds, or `None` if it is not one
+  pub fn parse(&self, format: &NumberFormat, text: &str) -> Option<f64> {
+    let mut text = text.trim();
+    let mut format = *format;
+    let mut negative = false;
+    if self.currency {
+      if let Some(inner) = text
+        .strip_prefix('(')
+        .and_then(|text| text.strip_suffix(')'))
+      {
+        negative = true;
+        text = inner.trim();
+      }
+      if format.thousands_separator.is_none() {
+        let thousands = if format.decimal_separator == b',' {
+          b'.'
+        } else {
+          b','
+        };
+        format.thousands_separator = Some(thousands);
+      }
+    }
+
+    let mut percent = false;
+    if self.percent {
+      if let Some(number) = text.strip_suffix('%') {
+        percent = true;
+        text = number.trim_end();
+      }
+    }
+
+    let mut sign = "";
+    if self.currency {
+      if let Some(rest) = text.strip_prefix(['-', '+']) {
+        if rest.starts_with(is_currency_symbol) {
+          sign = &text[..1];
+          text = rest;
+        }
+      }
+      text = text.trim_matches(is_currency_symbol).trim();
+    }
+
+    let number = format.normalize(text)?;
+    let value: f64 = match percent {
+      // Shifting the exponent rounds once, where dividing by 100 may not
+      true if !number.contains('e') => format!("{}{}e-2", sign, number).parse().ok()?,
+      true => format!("{}{}", sign, number).parse::<f64>().ok()? / 100.0,
+      false => format!("{}{}", sign, number).parse().ok()?,
+    };
+    Some(if negative { -value } else { value })
+  }
+}
+
+// Characters of the Unicode currency symbol category
+fn is_currency_symbol(c: char) -> bool {
+  matches!(
+    c,
+    '$'
+      | '\u{a2}'..='\u{a5}'
+      | '\u{58f}'
+      | '\u{60b}'
+      | '\u{7fe}'..='\u{7ff}'
+      | '\u{9f2}'..='\u{9f3}'
+      | '\u{9fb}'
+      | '\u{af1}'
+      | '\u{bf9}'
+      | '\u{e3f}'
+      | '\u{17db}'
+      | '\u{20a0}'..='\u{20c0}'
+      | '\u{a838}'
+      | '\u{fdfc}'
+      | '\u{fe69}'
+      | '\u{ff04}'
+      | '\u{ffe0}'..='\u{ffe1}'
+      | '\u{ffe5}'..='\u{ffe6}'
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_default_format() {
+    let format = NumberFormat::default();
+    assert_eq!(format.parse("42"), Some(42.0));
+    assert_eq!(format.parse(" -1.5e3 "), Some(-1500.0));
+    assert_eq!(format.parse(".5"), Some(0.5));
+    assert_eq!(format.parse("1,234"), None);
+    for text in ["", "-", ".", "1e", "NaN", "Infinity", "0x10", "1.2.3"] {
+      assert_eq!(format.parse(text), None, "{:?}", text);
+    }
+  }
+
+  #[test]
+  fn test_european_format() {
+    let format = NumberFormat {
+      decimal_separator: b',',
+      thousands_separator: Some(b'.'),
+    };
+    assert_eq!(format.parse("1.234,56"), Some(1234.56));
+    assert_eq!(format.parse("-1.234.567"), Some(-1234567.0));
+    assert_eq!(format.parse("1234,5"), Some(1234.5));
+    assert_eq!(format.parse("0,25"), Some(0.25));
+    // Groups after the first have exactly three digits
+    for text in ["1.23,4", "12.3456", "1..234", ".234", "1.234.", "1,234.5"] {
+      assert_eq!(format.parse(text), None, "{:?}", text);
+    }
+  }
+
+  #[test]
+  fn test_currency_and_percent() {
+    let us = NumberFormat::default();
+    let eu = NumberFormat {
+      decimal_separator: b',',
+      thousands_separator: None,
+    };
+    let currency = NumberColumn {
+      currency: true,
+      ..Default::default()
+    };
+    assert_eq!(currency.parse(&us, "$1,234.56"), Some(1234.56));
+    assert_eq!(currency.parse(&us, "-$5"), Some(-5.0));
+    assert_eq!(currency.parse(&us, "(12.50)"), Some(-12.5));
+    assert_eq!(currency.parse(&eu, "1.234,56 \u{20ac}"), Some(1234.56));
+    assert_eq!(currency.parse(&us, "\u{a3} 7"), Some(7.0));
+    assert_eq!(currency.parse(&us, "USD 7"), None);
+    assert_eq!(currency.parse(&us, "12%"), None);
+
+    let percent = NumberColumn {
+      percent: true,
+      ..Default::default()
+    };
+    assert_eq!(percent.parse(&us, "12.5%"), Some(0.125));
+    assert_eq!(percent.parse(&us, "1.1 %"), Some(0.011));
+    assert_eq!(percent.parse(&us, "-7%"), Some(-0.07));
+    assert_eq!(percent.parse(&us, "0.5"), Some(0.5));
+    assert_eq!(percent.parse(&us, "%"), None);
+  }
+}
@@ -1,36 +1,539 @@
+use bumpalo::Bump;
 use color_eyre::eyre::{eyre, Result};
 // use napi::threadsafe_function::ThreadsafeFunction;
-use std::collections::HashMap;
+use regex::bytes::Regex;
+use std::borrow::Cow;
+use std::ops::Range;
+
+use crate::dates::{DateColumn, DateOutput};
+use crate::decoder::TextEncoding;
+use crate::copy::CopyTokenizer;
+use crate::dedupe::{BloomOptions, Dedupe, DedupeOptions};
+use crate::dialect::sniff_dialect;
+use crate::expand::{header_path, PathSegment};
+use crate::hash::HashAlgorithm;
+use crate::json_schema::{Coerced, RowSchema};
+use crate::numbers::{NumberColumn, NumberFormat};
+use crate::state::{StateReader, StateWriter};
 
 #[derive(Debug)]
 pub struct CsvParserState {
-  escaped: bool,
-  quoted: bool,
   first: bool,
-  line_number: u64,
-  previous_end: usize,
-  row_length: usize,
+  // Physical lines dropped so far by `skip_lines`
+  skipped_lines: u64,
+  directive_checked: bool,
+  // The configured separator, while a `sep=` directive or the one
+  // `detect_separator` chose overrides it
+  configured_separator: Option<u8>,
+  // Physical lines parsed so far
+  lines: u64,
+  // With `sections`, the index and name of the current table, and whether
+  // any line of it has been read
+  section: u64,
+  section_name: Option<String>,
+  section_open: bool,
 }
 
-#[derive(Debug)]
+/// A cell's value as a range of the line buffer. Cells stay as ranges until
+/// the row is handed to JS, so no intermediate `String` is built per cell.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellRange {
+  pub start: usize,
+  pub end: usize,
+  /// Must be unescaped by the tokenizer before use, e.g. to collapse
+  /// doubled quotes
+  pub escaped: bool,
+}
+
+impl CellRange {
+  pub fn empty(at: usize) -> Self {
+    Self {
+      start: at,
+      end: at,
+      escaped: false,
+    }
+  }
+}
+
+/// How cell text is checked for valid UTF-8
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Utf8Validation {
+  /// Validate each cell as its value is created
+  #[default]
+  Full,
+  /// Validate whole chunks once as they arrive; cells are then trusted
+  Fast,
+  /// Trust the input. Invalid sequences become U+FFFD in JS strings
+  Off,
+}
+
+/// Which lines are comments: `Boolean(true)` uses `#`, `String` any prefix
+#[derive(Debug, Clone)]
 pub enum SkipComments {
   Boolean(bool),
   String(String),
 }
 
+/// Which lines produce no row even though they are not comments
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SkipEmptyLines {
+  /// Only truly empty lines are skipped
+  #[default]
+  Off,
+  /// Lines with nothing but whitespace are skipped too
+  Blank,
+  /// Records whose cells are all empty or whitespace, such as `,,` or
+  /// `" , "`, are skipped too
+  Greedy,
+}
+
+/// What happens to rows with a different number of cells than the headers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strict {
+  /// The row is kept; cells beyond the headers follow `extra_columns`
+  #[default]
+  Off,
+  /// The row fails with an error
+  Error,
+  /// The row is left out and recorded in `CsvParser::ragged_rows`, so the
+  /// input can be checked in full
+  Report,
+}
+
+/// A row left out by `Strict::Report`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RaggedRow {
+  /// The line the row starts on, counting from 1
+  pub line: u64,
+  /// The number of headers
+  pub expected: usize,
+  pub actual: usize,
+}
+
+/// What happens to cells beyond the headers, outside strict mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtraColumns {
+  /// Keyed `<prefix><index>`
+  Prefix(String),
+  /// Gathered, in order, into one array under the given key
+  Collect(String),
+  /// Left out
+  Drop,
+}
+
+impl Default for ExtraColumns {
+  fn default() -> Self {
+    ExtraColumns::Prefix("_".to_string())
+  }
+}
+
+/// What happens to cells of `json_columns` that are not valid JSON
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MalformedJson {
+  /// The row fails with an error naming the column
+  #[default]
+  Error,
+  /// The cell is kept as a string
+  Keep,
+}
+
+/// What happens to a record whose quoted field is still open when the input
+/// ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnterminatedQuote {
+  /// The record fails with an error naming the line the field opened on
+  Error,
+  /// The record is kept, the open field holding the rest of the input as
+  /// it is, opening quote included
+  #[default]
+  Emit,
+  /// The record is left out
+  Drop,
+}
+
+/// Lines that end one table of the input and start the next, for exports
+/// that hold several tables, each with its own header line
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Sections {
+  /// A blank line ends the table before it
+  pub blank_lines: bool,
+  /// A line starting with this prefix starts a table named by the rest of
+  /// the line
+  pub marker: Option<String>,
+}
+
+// A line that delimits sections
+enum SectionLine<'a> {
+  Blank,
+  Marker(&'a [u8]),
+}
+
+/// How the Node bindings create row objects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RowObject {
+  /// Ordinary objects
+  #[default]
+  Plain,
+  /// Objects without a prototype, so a header such as `__proto__` is an
+  /// ordinary key
+  NullPrototype,
+  /// Ordinary objects, frozen once filled
+  Frozen,
+  /// Arrays of `[key, value]` pairs in column order, which keep the order of
+  /// integer-like headers and every cell of duplicate headers
+  Entries,
+}
+
+/// The rules records are split into cells by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RecordFormat {
+  /// RFC 4180 quoting, with `CsvTokenizer`
+  #[default]
+  Csv,
+  /// The PostgreSQL `COPY` text format, with `CopyTokenizer`
+  PostgresText,
+}
+
+impl RecordFormat {
+  pub(crate) fn tokenizer(self) -> Box<dyn RecordTokenizer> {
+    match self {
+      RecordFormat::Csv => Box::new(CsvTokenizer),
+      RecordFormat::PostgresText => Box::new(CopyTokenizer),
+    }
+  }
+}
+
+/// What happens to headers read from the input that are `__proto__`,
+/// `constructor` or `prototype`, or with `expand_headers` name a path through
+/// one, so a hostile file cannot set surprising keys on row objects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnsafeHeaders {
+  /// The header is used as it is
+  #[default]
+  Keep,
+  /// `_` is appended to each unsafe name, so `__proto__` becomes
+  /// `__proto___` and `a.constructor` becomes `a.constructor_`
+  Rename,
+  /// The column is left out of rows, as with a blank header
+  Drop,
+  /// Parsing fails with an error naming the header
+  Error,
+}
+
+impl UnsafeHeaders {
+  const NAMES: [&'static str; 3] = ["__proto__", "constructor", "prototype"];
+
+  /// The header to store in place of `header`
+  pub fn apply(self, header: String, expand: bool) -> Result<String> {
+    let is_unsafe = |name: &str| Self::NAMES.contains(&name);
+    let path = if expand { header_path(&header) } else { None };
+    let found = match &path {
+      Some(path) => path
+        .iter()
+        .any(|segment| matches!(segment, PathSegment::Key(key) if is_unsafe(key))),
+      None => is_unsafe(&header),
+    };
+    if !found {
+      return Ok(header);
+    }
+
+    match (self, path) {
+      (UnsafeHeaders::Keep, _) => Ok(header),
+      (UnsafeHeaders::Drop, _) => Ok(String::new()),
+      (UnsafeHeaders::Error, _) => Err(eyre!("Unsafe header \"{}\"", header)),
+      (UnsafeHeaders::Rename, None) => Ok(format!("{}_", header)),
+      (UnsafeHeaders::Rename, Some(path)) => {
+        let mut renamed = String::new();
+        for segment in path {
+          match segment {
+            PathSegment::Key(key) => {
+              if !renamed.is_empty() {
+                renamed.push('.');
+              }
+              renamed.push_str(&key);
+              if is_unsafe(&key) {
+                renamed.push('_');
+              }
+            }
+            PathSegment::Index(index) => renamed.push_str(&format!("[{}]", index)),
+          }
+        }
+        Ok(renamed)
+      }
+    }
+  }
+}
+
+/// What happens to a row with more cells than `max_columns`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MaxColumnsAction {
+  /// Parsing fails with an error
+  #[default]
+  Error,
+  /// Cells past the limit are dropped
+  Truncate,
+}
+
+/// What happens to a cell longer than `max_field_bytes`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OversizeField {
+  /// The row fails with an error
+  #[default]
+  Error,
+  /// The cell is cut to the limit, at a UTF-8 character boundary
+  Truncate,
+}
+
+/// What happens to line breaks inside cells, which only quoted cells hold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuotedNewlines {
+  /// Line breaks are kept as they are
+  #[default]
+  Keep,
+  /// `\r\n` and `\r` become `\n`
+  Lf,
+  /// Each line break becomes the two characters `\n`
+  Escape,
+  /// Line breaks are removed
+  Strip,
+}
+
+/// The line breaks that end records, outside quoted cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RecordTerminator {
+  /// `newline`, which for `\n` also ends `\r\n` records
+  #[default]
+  Newline,
+  /// `newline`, `\n`, `\r\n` and a lone `\r`, so files mixing them split
+  /// cleanly. Empty lines are dropped, so a run of breaks is one boundary.
+  Any,
+}
+
+/// What happens to columns whose detected header is empty or `_`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmptyHeaders {
+  /// The columns are left out of every row, as `CsvParser::dropped_columns`
+  /// lists
+  #[default]
+  Drop,
+  /// The columns are kept, keyed `_<index>` by their zero-based position
+  KeepPositional,
+  /// The header row fails
+  Error,
+}
+
+/// Keeps only the data lines `pattern` matches, or with `negate` those it
+/// does not. Lines are matched as raw bytes, without their line break,
+/// before they are split into cells; a record with quoted line breaks is
+/// matched as a whole.
+#[derive(Debug, Clone)]
+pub struct LineFilter {
+  pub pattern: Regex,
+  pub negate: bool,
+}
+
+impl LineFilter {
+  pub fn new(pattern: &str, negate: bool) -> Result<Self> {
+    let pattern = Regex::new(pattern).map_err(|e| eyre!("Invalid lineFilter pattern: {}", e))?;
+    Ok(Self { pattern, negate })
+  }
+
+  pub fn keeps(&self, line: &[u8]) -> bool {
+    self.pattern.is_match(line) != self.negate
+  }
+}
+
+/// A pattern each cell of `column` must match, as `validators` checks it
+#[derive(Clone)]
+pub struct Validator {
+  pub column: String,
+  pub pattern: Regex,
+}
+
+impl Validator {
+  pub fn new(column: &str, pattern: &str) -> Result<Self> {
+    let pattern = Regex::new(pattern)
+      .map_err(|e| eyre!("Invalid validators.{} pattern: {}", column, e))?;
+    Ok(Self {
+      column: column.to_string(),
+      pattern,
+    })
+  }
+}
+
+/// What happens to a row with a cell that fails its `validators` pattern or
+/// its `schema` property. Rows kept or left out are recorded in
+/// `CsvParser::cell_violations`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InvalidRow {
+  /// Parsing fails with an error naming the line and column
+  #[default]
+  Error,
+  /// The row is left out
+  Skip,
+  /// The row is kept, with the failing columns listed under `__violations`
+  Annotate,
+}
+
+/// A cell that failed its `validators` pattern or `schema` property, or a
+/// key the schema requires and the row lacks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellViolation {
+  /// The line the row starts on, counting from 1
+  pub line: u64,
+  pub column: String,
+  /// The schema keyword that failed, `pattern` for `validators`
+  pub keyword: String,
+  pub message: String,
+}
+
+/// How `redact` hides the cells of a column before rows are built from them.
+/// Empty and null cells are left as they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Redaction {
+  /// The SHA-256 of `redact_salt` followed by the cell, as hex
+  Hash,
+  /// Every character but the last four becomes `*`, and all of them in
+  /// cells of four characters or fewer
+  Mask,
+  /// The column is left out of rows
+  Drop,
+}
+
+/// What the cells of a column named in `column_types` are converted to when
+/// rows are handed to JS
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CellType {
+  Date(DateColumn, DateOutput),
+  /// A number, read with `decimal_separator` and `thousands_separator`
+  Number(NumberColumn),
+}
+
+/// Parser options. Each field mirrors the JS option of the same name.
+#[derive(Clone)]
 pub struct CsvParserOptions {
-  pub(crate) escape: u8,
-  pub(crate) quote: u8,
-  pub(crate) separator: u8,
-  pub(crate) newline: u8,
-  pub(crate) raw: bool,
-  pub(crate) strict: bool,
-  pub(crate) max_row_bytes: i64,
-  pub(crate) headers: Option<Vec<String>>, // None = auto-detect, Some(empty) = no headers/numeric, Some(vec) = custom
-  pub(crate) skip_comments: Option<SkipComments>,
-  pub(crate) skip_lines: Option<i64>,
+  /// Escape character; `0` means the same as `quote`
+  pub escape: u8,
+  pub quote: u8,
+  pub separator: u8,
+  pub newline: u8,
+  /// Decode cells lossily instead of failing on invalid UTF-8
+  pub raw: bool,
+  /// What happens to rows with a different number of cells than the headers
+  pub strict: Strict,
+  pub max_row_bytes: i64,
+  /// `None` reads headers from the first row, `Some(vec![])` keys cells by
+  /// column index and anything else uses the given names
+  pub headers: Option<Vec<String>>,
+  pub skip_comments: Option<SkipComments>,
+  pub skip_lines: Option<i64>,
+  pub skip_empty_lines: SkipEmptyLines,
+  pub extra_columns: ExtraColumns,
+  /// Leave empty cells out of rows instead of keying them to `""`
+  pub skip_empty_fields: bool,
+  pub ltrim: bool,
+  pub rtrim: bool,
+  /// Also strip the whitespace `ltrim`/`rtrim` strip inside quotes
+  pub trim_quoted: bool,
+  pub relax_quotes: bool,
+  pub trailing_comments: bool,
+  pub sep_directive: bool,
+  pub excel: bool,
+  pub validate_utf8: Utf8Validation,
+  /// Drop rows whose key was seen before
+  pub dedupe: Option<DedupeOptions>,
+  /// Conversions by header name
+  pub column_types: Vec<(String, CellType)>,
+  /// Build nested objects and arrays from headers such as `address.city` or
+  /// `tags[0]`
+  pub expand_headers: bool,
+  /// Columns whose cells are lists, by header name, with the separator
+  /// between items
+  pub array_columns: Vec<(String, String)>,
+  /// Columns whose cells are parsed as JSON, by header name
+  pub json_columns: Vec<String>,
+  pub malformed_json: MalformedJson,
+  /// Key to add each row's source line under, without its line break
+  pub include_raw_line: Option<String>,
+  /// Most bytes of input held while waiting for the rest of a row
+  pub max_buffered_bytes: Option<usize>,
+  /// Most cells a row, header row included, may have
+  pub max_columns: Option<usize>,
+  pub max_columns_action: MaxColumnsAction,
+  /// Drops data lines before they are split; the header line always passes
+  pub line_filter: Option<LineFilter>,
+  /// Encoding of the input. By default input is UTF-8, or UTF-16 or UTF-32
+  /// when it starts with a BOM.
+  pub encoding: Option<TextEncoding>,
+  /// Without `encoding`, guess the encoding of input that has no BOM from
+  /// its first 64 KiB instead of assuming UTF-8
+  pub detect_encoding: bool,
+  pub row_object: RowObject,
+  /// What happens to header row names such as `__proto__`
+  pub unsafe_headers: UnsafeHeaders,
+  /// Choose the separator among `,`, `;`, tab and `|` from the first lines
+  /// that are not skipped or comments, instead of using `separator`
+  pub detect_separator: bool,
+  /// How records are split into cells, unless a tokenizer is given
+  pub format: RecordFormat,
+  /// Cells written exactly as this, before unescaping, are null: `null` in
+  /// JS rows and left out of `Record`s
+  pub null_value: Option<String>,
+  /// Add a hash of each row's source line, without its line break, under
+  /// `__hash`
+  pub row_hash: Option<HashAlgorithm>,
+  /// Hash the whole input as it arrives, for a digest at the end
+  pub digest: Option<HashAlgorithm>,
+  /// Bound memory with the `HARDENED_*` limits where none are given, and in
+  /// the Node bindings, report a panic as an error instead of crashing
+  pub hardened: bool,
+  /// Separator of the fraction in `CellType::Number` columns
+  pub decimal_separator: u8,
+  /// Separator between groups of three digits in `CellType::Number` columns
+  pub thousands_separator: Option<u8>,
+  pub on_unterminated_quote: UnterminatedQuote,
+  /// Split the input into tables at these lines; headers are read again
+  /// after each
+  pub sections: Option<Sections>,
+  /// Most bytes the value of a cell, header included, may take up, an
+  /// escaped quote counting once
+  pub max_field_bytes: Option<usize>,
+  pub on_oversize_field: OversizeField,
+  pub quoted_newlines: QuotedNewlines,
+  /// Keys mapped to other names of their column: a header read from the
+  /// input that matches a key or one of its names, ignoring case, is stored
+  /// as the key
+  pub column_aliases: Vec<(String, Vec<String>)>,
+  /// Patterns the cells of columns must match, by key. A missing cell is
+  /// checked as empty.
+  pub validators: Vec<Validator>,
+  /// JSON Schema rows must match. Cells of its properties are converted to
+  /// the types they allow.
+  pub schema: Option<RowSchema>,
+  pub on_violation: InvalidRow,
+  /// Columns hidden before rows are built, by key. Validation and `dedupe`
+  /// see the cells as they are; `include_raw_line` would not hide them.
+  pub redact: Vec<(String, Redaction)>,
+  pub redact_salt: String,
+  /// Also return the detected header row as the first row, keyed by itself
+  pub pass_header_row: bool,
+  pub record_terminator: RecordTerminator,
+  /// Name of the `register_batch_hook` hook the Node bindings run on each
+  /// batch of records
+  pub batch_hook: Option<String>,
+  pub empty_headers: EmptyHeaders,
+  /// The Node bindings also give each row its record's bytes, without the
+  /// line break, as a Buffer under `__raw`
+  pub raw_rows: bool,
 }
 
+/// `max_row_bytes` of a hardened parser unless given
+pub const HARDENED_MAX_ROW_BYTES: i64 = 16 * 1024 * 1024;
+/// `max_buffered_bytes` of a hardened parser unless given
+pub const HARDENED_MAX_BUFFERED_BYTES: usize = 64 * 1024 * 1024;
+/// `max_columns` of a hardened parser unless given
+pub const HARDENED_MAX_COLUMNS: usize = 16 * 1024;
+
 impl Default for CsvParserOptions {
   fn default() -> Self {
     Self {
@@ -39,37 +542,558 @@ impl Default for CsvParserOptions {
       separator: b',',
       newline: b'\n',
       raw: false,
-      strict: false,
+      strict: Strict::Off,
       max_row_bytes: i64::MAX,
       headers: None,
       skip_comments: None,
       skip_lines: None,
+      skip_empty_lines: SkipEmptyLines::Off,
+      extra_columns: ExtraColumns::default(),
+      skip_empty_fields: false,
+      trim_quoted: false,
+      ltrim: false,
+      rtrim: false,
+      relax_quotes: false,
+      trailing_comments: false,
+      sep_directive: false,
+      excel: false,
+      validate_utf8: Utf8Validation::Full,
+      dedupe: None,
+      column_types: Vec::new(),
+      expand_headers: false,
+      array_columns: Vec::new(),
+      json_columns: Vec::new(),
+      malformed_json: MalformedJson::Error,
+      include_raw_line: None,
+      max_buffered_bytes: None,
+      max_columns: None,
+      max_columns_action: MaxColumnsAction::Error,
+      line_filter: None,
+      encoding: None,
+      detect_encoding: false,
+      row_object: RowObject::Plain,
+      unsafe_headers: UnsafeHeaders::Keep,
+      detect_separator: false,
+      format: RecordFormat::Csv,
+      null_value: None,
+      row_hash: None,
+      digest: None,
+      hardened: false,
+      decimal_separator: b'.',
+      thousands_separator: None,
+      on_unterminated_quote: UnterminatedQuote::Emit,
+      sections: None,
+      max_field_bytes: None,
+      on_oversize_field: OversizeField::Error,
+      quoted_newlines: QuotedNewlines::Keep,
+      column_aliases: Vec::new(),
+      validators: Vec::new(),
+      schema: None,
+      on_violation: InvalidRow::Error,
+      redact: Vec::new(),
+      redact_salt: String::new(),
+      pass_header_row: false,
+      record_terminator: RecordTerminator::Newline,
+      batch_hook: None,
+      empty_headers: EmptyHeaders::Drop,
+      raw_rows: false,
+    }
+  }
+}
+
+impl CsvParserOptions {
+  /// Whether rows are checked against `validators` or a `schema`
+  pub fn validates(&self) -> bool {
+    !self.validators.is_empty() || self.schema.is_some()
+  }
+
+  /// The key `column_aliases` gives the column headed `header`, or `header`
+  pub fn alias_key(&self, header: String) -> String {
+    if self.column_aliases.is_empty() {
+      return header;
+    }
+    let lower = header.to_lowercase();
+    self
+      .column_aliases
+      .iter()
+      .find(|(key, aliases)| {
+        key.to_lowercase() == lower || aliases.iter().any(|alias| alias.to_lowercase() == lower)
+      })
+      .map_or(header, |(key, _)| key.clone())
+  }
+
+  pub(crate) fn save_state(&self, state: &mut StateWriter) {
+    for byte in [self.escape, self.quote, self.separator, self.newline] {
+      state.u8(byte);
+    }
+    state.bool(self.raw);
+    state.u8(match self.strict {
+      Strict::Off => 0,
+      Strict::Error => 1,
+      Strict::Report => 2,
+    });
+    state.i64(self.max_row_bytes);
+    state.option(self.headers.as_deref(), StateWriter::strings);
+    state.option(self.skip_comments.as_ref(), |state, skip| match skip {
+      SkipComments::Boolean(skip) => {
+        state.u8(0);
+        state.bool(*skip);
+      }
+      SkipComments::String(prefix) => {
+        state.u8(1);
+        state.str(prefix);
+      }
+    });
+    state.option(self.skip_lines, StateWriter::i64);
+    state.u8(match self.skip_empty_lines {
+      SkipEmptyLines::Off => 0,
+      SkipEmptyLines::Blank => 1,
+      SkipEmptyLines::Greedy => 2,
+    });
+    match &self.extra_columns {
+      ExtraColumns::Prefix(prefix) => {
+        state.u8(0);
+        state.str(prefix);
+      }
+      ExtraColumns::Collect(key) => {
+        state.u8(1);
+        state.str(key);
+      }
+      ExtraColumns::Drop => state.u8(2),
+    }
+    state.bool(self.skip_empty_fields);
+    for flag in [
+      self.ltrim,
+      self.rtrim,
+      self.trim_quoted,
+      self.relax_quotes,
+      self.trailing_comments,
+      self.sep_directive,
+      self.excel,
+    ] {
+      state.bool(flag);
     }
+    state.u8(match self.validate_utf8 {
+      Utf8Validation::Full => 0,
+      Utf8Validation::Fast => 1,
+      Utf8Validation::Off => 2,
+    });
+    state.option(self.dedupe.as_ref(), |state, dedupe| {
+      state.strings(&dedupe.keys);
+      state.option(dedupe.bloom, |state, bloom| {
+        state.u64(bloom.capacity as u64);
+        state.f64(bloom.false_positive_rate);
+      });
+    });
+    state.u64(self.column_types.len() as u64);
+    for (column, cell_type) in &self.column_types {
+      state.str(column);
+      match cell_type {
+        CellType::Date(date, output) => {
+          state.u8(0);
+          state.option(date.format(), StateWriter::str);
+          state.str(date.tz());
+          state.bool(*output == DateOutput::Millis);
+        }
+        CellType::Number(number) => {
+          state.u8(1);
+          state.bool(number.currency);
+          state.bool(number.percent);
+        }
+      }
+    }
+    state.bool(self.expand_headers);
+    state.u64(self.array_columns.len() as u64);
+    for (column, separator) in &self.array_columns {
+      state.str(column);
+      state.str(separator);
+    }
+    state.strings(&self.json_columns);
+    state.bool(self.malformed_json == MalformedJson::Keep);
+    state.option(self.include_raw_line.as_deref(), StateWriter::str);
+    state.option(self.max_buffered_bytes.map(|n| n as u64), StateWriter::u64);
+    state.option(self.max_columns.map(|n| n as u64), StateWriter::u64);
+    state.bool(self.max_columns_action == MaxColumnsAction::Truncate);
+    state.option(self.line_filter.as_ref(), |state, filter| {
+      state.str(filter.pattern.as_str());
+      state.bool(filter.negate);
+    });
+    state.option(self.encoding.map(|encoding| encoding.name()), StateWriter::str);
+    state.bool(self.detect_encoding);
+    state.u8(match self.row_object {
+      RowObject::Plain => 0,
+      RowObject::NullPrototype => 1,
+      RowObject::Frozen => 2,
+      RowObject::Entries => 3,
+    });
+    state.u8(match self.unsafe_headers {
+      UnsafeHeaders::Keep => 0,
+      UnsafeHeaders::Rename => 1,
+      UnsafeHeaders::Drop => 2,
+      UnsafeHeaders::Error => 3,
+    });
+    state.bool(self.detect_separator);
+    state.bool(self.format == RecordFormat::PostgresText);
+    state.option(self.null_value.as_deref(), StateWriter::str);
+    state.option(self.row_hash.map(HashAlgorithm::name), StateWriter::str);
+    state.option(self.digest.map(HashAlgorithm::name), StateWriter::str);
+    state.bool(self.hardened);
+    state.u8(self.decimal_separator);
+    state.option(self.thousands_separator, StateWriter::u8);
+    state.u8(match self.on_unterminated_quote {
+      UnterminatedQuote::Error => 0,
+      UnterminatedQuote::Emit => 1,
+      UnterminatedQuote::Drop => 2,
+    });
+    state.option(self.sections.as_ref(), |state, sections| {
+      state.bool(sections.blank_lines);
+      state.option(sections.marker.as_deref(), StateWriter::str);
+    });
+    state.option(self.max_field_bytes.map(|n| n as u64), StateWriter::u64);
+    state.bool(self.on_oversize_field == OversizeField::Truncate);
+    state.u8(match self.quoted_newlines {
+      QuotedNewlines::Keep => 0,
+      QuotedNewlines::Lf => 1,
+      QuotedNewlines::Escape => 2,
+      QuotedNewlines::Strip => 3,
+    });
+    state.u64(self.column_aliases.len() as u64);
+    for (key, aliases) in &self.column_aliases {
+      state.str(key);
+      state.strings(aliases);
+    }
+    state.u64(self.validators.len() as u64);
+    for validator in &self.validators {
+      state.str(&validator.column);
+      state.str(validator.pattern.as_str());
+    }
+    state.option(
+      self.schema.as_ref().map(|schema| schema.source().to_string()).as_deref(),
+      StateWriter::str,
+    );
+    state.u8(match self.on_violation {
+      InvalidRow::Error => 0,
+      InvalidRow::Skip => 1,
+      InvalidRow::Annotate => 2,
+    });
+    state.u64(self.redact.len() as u64);
+    for (column, redaction) in &self.redact {
+      state.str(column);
+      state.u8(match redaction {
+        Redaction::Hash => 0,
+        Redaction::Mask => 1,
+        Redaction::Drop => 2,
+      });
+    }
+    state.str(&self.redact_salt);
+    state.bool(self.pass_header_row);
+    state.bool(self.record_terminator == RecordTerminator::Any);
+    state.option(self.batch_hook.as_deref(), StateWriter::str);
+    state.u8(match self.empty_headers {
+      EmptyHeaders::Drop => 0,
+      EmptyHeaders::KeepPositional => 1,
+      EmptyHeaders::Error => 2,
+    });
+    state.bool(self.raw_rows);
+  }
+
+  pub(crate) fn load_state(state: &mut StateReader) -> Result<Self> {
+    let hash_algorithm = |state: &mut StateReader| {
+      HashAlgorithm::for_name(&state.string()?).ok_or_else(|| eyre!("Invalid parser state"))
+    };
+    Ok(Self {
+      escape: state.u8()?,
+      quote: state.u8()?,
+      separator: state.u8()?,
+      newline: state.u8()?,
+      raw: state.bool()?,
+      strict: match state.u8()? {
+        0 => Strict::Off,
+        1 => Strict::Error,
+        2 => Strict::Report,
+        _ => return Err(eyre!("Invalid parser state")),
+      },
+      max_row_bytes: state.i64()?,
+      headers: state.option(StateReader::strings)?,
+      skip_comments: state.option(|state| match state.u8()? {
+        0 => Ok(SkipComments::Boolean(state.bool()?)),
+        1 => Ok(SkipComments::String(state.string()?)),
+        _ => Err(eyre!("Invalid parser state")),
+      })?,
+      skip_lines: state.option(StateReader::i64)?,
+      skip_empty_lines: match state.u8()? {
+        0 => SkipEmptyLines::Off,
+        1 => SkipEmptyLines::Blank,
+        2 => SkipEmptyLines::Greedy,
+        _ => return Err(eyre!("Invalid parser state")),
+      },
+      extra_columns: match state.u8()? {
+        0 => ExtraColumns::Prefix(state.string()?),
+        1 => ExtraColumns::Collect(state.string()?),
+        2 => ExtraColumns::Drop,
+        _ => return Err(eyre!("Invalid parser state")),
+      },
+      skip_empty_fields: state.bool()?,
+      ltrim: state.bool()?,
+      rtrim: state.bool()?,
+      trim_quoted: state.bool()?,
+      relax_quotes: state.bool()?,
+      trailing_comments: state.bool()?,
+      sep_directive: state.bool()?,
+      excel: state.bool()?,
+      validate_utf8: match state.u8()? {
+        0 => Utf8Validation::Full,
+        1 => Utf8Validation::Fast,
+        2 => Utf8Validation::Off,
+        _ => return Err(eyre!("Invalid parser state")),
+      },
+      dedupe: state.option(|state| {
+        Ok(DedupeOptions {
+          keys: state.strings()?,
+          bloom: state.option(|state| {
+            Ok(BloomOptions {
+              capacity: state.usize()?,
+              false_positive_rate: state.f64()?,
+            })
+          })?,
+        })
+      })?,
+      column_types: (0..state.usize()?)
+        .map(|_| {
+          let column = state.string()?;
+          let cell_type = match state.u8()? {
+            0 => {
+              let format = state.option(StateReader::string)?;
+              let date = DateColumn::new(format, Some(&state.string()?))?;
+              let output = match state.bool()? {
+                true => DateOutput::Millis,
+                false => DateOutput::Date,
+              };
+              CellType::Date(date, output)
+            }
+            1 => CellType::Number(NumberColumn {
+              currency: state.bool()?,
+              percent: state.bool()?,
+            }),
+            _ => return Err(eyre!("Invalid parser state")),
+          };
+          Ok((column, cell_type))
+        })
+        .collect::<Result<_>>()?,
+      expand_headers: state.bool()?,
+      array_columns: (0..state.usize()?)
+        .map(|_| Ok((state.string()?, state.string()?)))
+        .collect::<Result<_>>()?,
+      json_columns: state.strings()?,
+      malformed_json: match state.bool()? {
+        true => MalformedJson::Keep,
+        false => MalformedJson::Error,
+      },
+      include_raw_line: state.option(StateReader::string)?,
+      max_buffered_bytes: state.option(StateReader::usize)?,
+      max_columns: state.option(StateReader::usize)?,
+      max_columns_action: match state.bool()? {
+        true => MaxColumnsAction::Truncate,
+        false => MaxColumnsAction::Error,
+      },
+      line_filter: state.option(|state| LineFilter::new(&state.string()?, state.bool()?))?,
+      encoding: state.option(|state| TextEncoding::for_label(&state.string()?))?,
+      detect_encoding: state.bool()?,
+      row_object: match state.u8()? {
+        0 => RowObject::Plain,
+        1 => RowObject::NullPrototype,
+        2 => RowObject::Frozen,
+        3 => RowObject::Entries,
+        _ => return Err(eyre!("Invalid parser state")),
+      },
+      unsafe_headers: match state.u8()? {
+        0 => UnsafeHeaders::Keep,
+        1 => UnsafeHeaders::Rename,
+        2 => UnsafeHeaders::Drop,
+        3 => UnsafeHeaders::Error,
+        _ => return Err(eyre!("Invalid parser state")),
+      },
+      detect_separator: state.bool()?,
+      format: match state.bool()? {
+        true => RecordFormat::PostgresText,
+        false => RecordFormat::Csv,
+      },
+      null_value: state.option(StateReader::string)?,
+      row_hash: state.option(hash_algorithm)?,
+      digest: state.option(hash_algorithm)?,
+      hardened: state.bool()?,
+      decimal_separator: state.u8()?,
+      thousands_separator: state.option(StateReader::u8)?,
+      on_unterminated_quote: match state.u8()? {
+        0 => UnterminatedQuote::Error,
+        1 => UnterminatedQuote::Emit,
+        2 => UnterminatedQuote::Drop,
+        _ => return Err(eyre!("Invalid parser state")),
+      },
+      sections: state.option(|state| {
+        Ok(Sections {
+          blank_lines: state.bool()?,
+          marker: state.option(StateReader::string)?,
+        })
+      })?,
+      max_field_bytes: state.option(StateReader::usize)?,
+      on_oversize_field: match state.bool()? {
+        true => OversizeField::Truncate,
+        false => OversizeField::Error,
+      },
+      quoted_newlines: match state.u8()? {
+        0 => QuotedNewlines::Keep,
+        1 => QuotedNewlines::Lf,
+        2 => QuotedNewlines::Escape,
+        3 => QuotedNewlines::Strip,
+        _ => return Err(eyre!("Invalid parser state")),
+      },
+      column_aliases: (0..state.usize()?)
+        .map(|_| Ok((state.string()?, state.strings()?)))
+        .collect::<Result<_>>()?,
+      validators: (0..state.usize()?)
+        .map(|_| Validator::new(&state.string()?, &state.string()?))
+        .collect::<Result<_>>()?,
+      schema: state.option(|state| RowSchema::new(&serde_json::from_str(&state.string()?)?))?,
+      on_violation: match state.u8()? {
+        0 => InvalidRow::Error,
+        1 => InvalidRow::Skip,
+        2 => InvalidRow::Annotate,
+        _ => return Err(eyre!("Invalid parser state")),
+      },
+      redact: (0..state.usize()?)
+        .map(|_| {
+          let column = state.string()?;
+          let redaction = match state.u8()? {
+            0 => Redaction::Hash,
+            1 => Redaction::Mask,
+            2 => Redaction::Drop,
+            _ => return Err(eyre!("Invalid parser state")),
+          };
+          Ok((column, redaction))
+        })
+        .collect::<Result<_>>()?,
+      redact_salt: state.string()?,
+      pass_header_row: state.bool()?,
+      record_terminator: match state.bool()? {
+        true => RecordTerminator::Any,
+        false => RecordTerminator::Newline,
+      },
+      batch_hook: state.option(StateReader::string)?,
+      empty_headers: match state.u8()? {
+        0 => EmptyHeaders::Drop,
+        1 => EmptyHeaders::KeepPositional,
+        2 => EmptyHeaders::Error,
+        _ => return Err(eyre!("Invalid parser state")),
+      },
+      raw_rows: state.bool()?,
+    })
+  }
+
+  /// Whether the byte at `i` ends a line. A `\r` that `RecordTerminator::Any`
+  /// reads as a break only does once the next byte shows it is not `\r\n`.
+  #[inline]
+  pub(crate) fn ends_line(&self, buffer: &[u8], i: usize) -> bool {
+    let byte = buffer[i];
+    if byte == self.newline {
+      return true;
+    }
+    self.record_terminator == RecordTerminator::Any
+      && match byte {
+        b'\n' => true,
+        b'\r' => buffer.get(i + 1).is_some_and(|&next| next != b'\n'),
+        _ => false,
+      }
+  }
+
+  pub(crate) fn comment_prefix(&self) -> Option<&[u8]> {
+    match &self.skip_comments {
+      Some(SkipComments::Boolean(true)) => Some(b"#"),
+      Some(SkipComments::String(prefix)) if !prefix.is_empty() => Some(prefix.as_bytes()),
+      _ => None,
+    }
+  }
+
+  pub(crate) fn is_comment_at(&self, buffer: &[u8], i: usize, end: usize) -> bool {
+    self
+      .comment_prefix()
+      .is_some_and(|prefix| buffer[i..end].starts_with(prefix))
+  }
+
+  /// Whether a comment begins at `i` in the row starting at `row_start`, i.e.
+  /// the comment prefix is there and it either starts the line or
+  /// `trailing_comments` allows it after data. Callers only ask outside quotes.
+  pub(crate) fn comment_starts(&self, buffer: &[u8], row_start: usize, i: usize) -> bool {
+    let Some(prefix) = self.comment_prefix() else {
+      return false;
+    };
+    if buffer[i] != prefix[0] || !buffer[i..].starts_with(prefix) {
+      return false;
+    }
+    self.trailing_comments || buffer[row_start..i].iter().all(|x| x.is_ascii_whitespace())
   }
 }
 
 impl CsvParserState {
   pub fn new() -> Self {
     Self {
-      escaped: false,
-      quoted: false,
       first: true,
-      line_number: 0,
-      previous_end: 0,
-      row_length: 0,
+      skipped_lines: 0,
+      directive_checked: false,
+      configured_separator: None,
+      lines: 0,
+      section: 0,
+      section_name: None,
+      section_open: false,
     }
   }
 }
 
-#[napi]
 pub struct CsvParser {
   pub(crate) state: CsvParserState,
   pub(crate) options: CsvParserOptions,
   pub(crate) headers: Option<Vec<String>>,
+  tokenizer: Box<dyn RecordTokenizer>,
+  // Scratch space for per-row temporary data, reset at the start of each row
+  arena: Bump,
+  dedupe: Option<Dedupe>,
+  comment_lines: u64,
+  ragged_rows: Vec<RaggedRow>,
+  // Columns of the last row that failed their `validators` pattern or
+  // `schema` property
+  violations: Vec<String>,
+  // The cells of the last row converted by `schema`, by cell index
+  coerced: Vec<Option<Coerced>>,
+  cell_violations: Vec<CellViolation>,
+  invalid_rows: u64,
+  // Cells of the last row hidden by `redact`, with the range of
+  // `redacted_bytes` that replaces them, or `None` if dropped
+  redacted: Vec<(CellRange, Option<Range<usize>>)>,
+  redacted_bytes: Vec<u8>,
+  // Whether the buffer given to `parse_record` ends where the input does
+  input_ended: bool,
 }
 
 impl CsvParser {
-  pub fn new(mut options: CsvParserOptions) -> Self {
+  pub fn new(options: CsvParserOptions) -> Self {
+    let tokenizer = options.format.tokenizer();
+    Self::with_tokenizer(options, tokenizer)
+  }
+
+  /// Creates a parser that splits records with `tokenizer` instead of the
+  /// CSV rules, keeping the rest of the pipeline
+  pub fn with_tokenizer(
+    mut options: CsvParserOptions,
+    tokenizer: Box<dyn RecordTokenizer>,
+  ) -> Self {
+    if options.hardened {
+      if options.max_row_bytes == i64::MAX {
+        options.max_row_bytes = HARDENED_MAX_ROW_BYTES;
+      }
+      options.max_buffered_bytes.get_or_insert(HARDENED_MAX_BUFFERED_BYTES);
+      options.max_columns.get_or_insert(HARDENED_MAX_COLUMNS);
+    }
+
     // Set escape to quote if not defined
     if options.escape == 0 {
       options.escape = options.quote;
@@ -101,84 +1125,378 @@ impl CsvParser {
 
     Self {
       state,
+      dedupe: options.dedupe.as_ref().map(Dedupe::new),
       options,
       headers,
+      tokenizer,
+      arena: Bump::new(),
+      comment_lines: 0,
+      violations: Vec::new(),
+      coerced: Vec::new(),
+      cell_violations: Vec::new(),
+      invalid_rows: 0,
+      redacted: Vec::new(),
+      redacted_bytes: Vec::new(),
+      ragged_rows: Vec::new(),
+      input_ended: false,
     }
   }
 
-  pub fn parse_cell(&self, buffer: &[u8], start: usize, end: usize) -> Result<String> {
-    if start >= end {
-      return Ok(String::new());
+  /// Tells the parser whether the buffers it parses end where the input
+  /// does, so the record reaching the end of one is known to be the last
+  /// even when it ends in a line break, as an unclosed quote leaves it
+  pub fn set_input_ended(&mut self, ended: bool) {
+    self.input_ended = ended;
+  }
+
+  /// Returns the parser to its initial state with the same options, so the
+  /// next line is treated as the start of a new file. Allocations are kept.
+  pub fn reset(&mut self) {
+    if let Some(separator) = self.state.configured_separator.take() {
+      self.options.separator = separator;
     }
-    
-    let mut cell_start = start;
-    let mut cell_end = end;
-    let mut is_quoted = false;
+    let mut arena = std::mem::take(&mut self.arena);
+    arena.reset();
+    let mut tokenizer = std::mem::replace(&mut self.tokenizer, Box::new(CsvTokenizer));
+    tokenizer.reset();
 
-    // Check if cell is quoted
-    if buffer[cell_start] == self.options.quote && cell_end > cell_start && buffer[cell_end - 1] == self.options.quote {
-      cell_start += 1;
-      cell_end -= 1;
-      is_quoted = true;
+    *self = Self::with_tokenizer(std::mem::take(&mut self.options), tokenizer);
+    self.arena = arena;
+  }
+
+  /// The text of `cell`, borrowed from `buffer` unless escaped quotes had to
+  /// be collapsed (into the row arena) or `raw` replaced invalid UTF-8. Raw
+  /// data cells are normally taken as bytes with `cell_bytes` instead.
+  pub fn cell_value<'a>(&'a self, buffer: &'a [u8], cell: CellRange) -> Result<Cow<'a, str>> {
+    self.decode(self.cell_bytes(buffer, cell))
+  }
+
+  /// Decodes the bytes of a cell or part of one: lossily with `raw`, otherwise
+  /// failing on invalid UTF-8
+  pub fn decode<'a>(&self, bytes: &'a [u8]) -> Result<Cow<'a, str>> {
+    if self.options.raw {
+      Ok(String::from_utf8_lossy(bytes))
+    } else {
+      std::str::from_utf8(bytes)
+        .map(Cow::Borrowed)
+        .map_err(|e| eyre!("UTF-8 conversion error: {}", e))
     }
+  }
+
+  /// Whether `cell` is written as `null_value`
+  pub fn is_null(&self, buffer: &[u8], cell: CellRange) -> bool {
+    let null = self.options.null_value.as_ref();
+    null.is_some_and(|null| &buffer[cell.start..cell.end] == null.as_bytes())
+  }
 
-    let mut result = if cell_end > cell_start {
-      Vec::with_capacity(cell_end - cell_start)
+  /// The bytes of `cell`, without validating them as UTF-8
+  pub fn cell_bytes<'a>(&'a self, buffer: &'a [u8], cell: CellRange) -> &'a [u8] {
+    if let Some((_, range)) = self.redacted.iter().find(|(redacted, _)| *redacted == cell) {
+      return range.clone().map_or(&[], |range| &self.redacted_bytes[range]);
+    }
+    let bytes = &buffer[cell.start..cell.end];
+    let bytes = if cell.escaped {
+      self.tokenizer.unescape(&self.options, bytes, &self.arena)
     } else {
-      Vec::new()
+      bytes
     };
-    let mut i = cell_start;
+    match self.options.quoted_newlines {
+      QuotedNewlines::Keep => bytes,
+      quoted_newlines => self.normalize_newlines(bytes, quoted_newlines),
+    }
+  }
 
-    while i < cell_end {
-      if is_quoted && buffer[i] == self.options.quote && i + 1 < cell_end && buffer[i + 1] == self.options.quote {
-        // Handle escaped quotes (double quotes)
-        result.push(self.options.quote);
-        i += 2; // Skip both quotes
-      } else {
-        result.push(buffer[i]);
-        i += 1;
+  // `bytes` with its line breaks rewritten as `quoted_newlines` asks, into
+  // the row arena
+  fn normalize_newlines<'a>(&'a self, bytes: &'a [u8], quoted_newlines: QuotedNewlines) -> &'a [u8] {
+    if !bytes.iter().any(|&b| b == b'\r' || b == b'\n') {
+      return bytes;
+    }
+    let replacement: &[u8] = match quoted_newlines {
+      QuotedNewlines::Keep => return bytes,
+      QuotedNewlines::Lf => b"\n",
+      QuotedNewlines::Escape => b"\\n",
+      QuotedNewlines::Strip => b"",
+    };
+    let mut result = bumpalo::collections::Vec::with_capacity_in(bytes.len() + 8, &self.arena);
+    let mut i = 0;
+    while i < bytes.len() {
+      match bytes[i] {
+        b'\r' if bytes.get(i + 1) == Some(&b'\n') => {
+          result.extend_from_slice(replacement);
+          i += 1;
+        }
+        b'\r' | b'\n' => result.extend_from_slice(replacement),
+        byte => result.push(byte),
       }
+      i += 1;
+    }
+    result.into_bump_slice()
+  }
+
+  /// Heap bytes held by header names, string options and the keys `dedupe`
+  /// remembers
+  pub fn heap_size(&self) -> usize {
+    let strings_size = |strings: &Vec<String>| {
+      strings.capacity() * std::mem::size_of::<String>()
+        + strings.iter().map(String::capacity).sum::<usize>()
+    };
+
+    let comment_size = match &self.options.skip_comments {
+      Some(SkipComments::String(prefix)) => prefix.capacity(),
+      _ => 0,
+    };
+
+    self.headers.as_ref().map_or(0, strings_size)
+      + self.options.headers.as_ref().map_or(0, strings_size)
+      + comment_size
+      + self.dedupe.as_ref().map_or(0, Dedupe::heap_size)
+  }
+
+  /// Bytes reserved by the per-row scratch arena
+  pub fn arena_size(&self) -> usize {
+    self.arena.allocated_bytes()
+  }
+
+
+  /// Writes the options, headers, line counters, ragged rows, cell
+  /// violations and the keys `dedupe` remembers to `state`. A custom
+  /// tokenizer's own state is not included.
+  pub(crate) fn save_state(&self, state: &mut StateWriter) {
+    self.options.save_state(state);
+    state.bool(self.state.first);
+    state.u64(self.state.skipped_lines);
+    state.bool(self.state.directive_checked);
+    state.option(self.state.configured_separator, StateWriter::u8);
+    state.u64(self.state.lines);
+    state.u64(self.state.section);
+    state.option(self.state.section_name.as_deref(), StateWriter::str);
+    state.bool(self.state.section_open);
+    state.option(self.headers.as_deref(), StateWriter::strings);
+    state.u64(self.ragged_rows.len() as u64);
+    for row in &self.ragged_rows {
+      state.u64(row.line);
+      state.u64(row.expected as u64);
+      state.u64(row.actual as u64);
+    }
+    state.u64(self.cell_violations.len() as u64);
+    for violation in &self.cell_violations {
+      state.u64(violation.line);
+      state.str(&violation.column);
+      state.str(&violation.keyword);
+      state.str(&violation.message);
+    }
+    state.u64(self.invalid_rows);
+    if let Some(dedupe) = &self.dedupe {
+      dedupe.save_state(state);
+    }
+  }
+
+  /// Restores a parser written by `save_state`, with the tokenizer of its
+  /// `format`
+  pub(crate) fn load_state(state: &mut StateReader) -> Result<Self> {
+    let mut parser = Self::new(CsvParserOptions::load_state(state)?);
+    parser.state = CsvParserState {
+      first: state.bool()?,
+      skipped_lines: state.u64()?,
+      directive_checked: state.bool()?,
+      configured_separator: state.option(StateReader::u8)?,
+      lines: state.u64()?,
+      section: state.u64()?,
+      section_name: state.option(StateReader::string)?,
+      section_open: state.bool()?,
+    };
+    parser.headers = state.option(StateReader::strings)?;
+    for _ in 0..state.usize()? {
+      parser.ragged_rows.push(RaggedRow {
+        line: state.u64()?,
+        expected: state.usize()?,
+        actual: state.usize()?,
+      });
+    }
+    for _ in 0..state.usize()? {
+      parser.cell_violations.push(CellViolation {
+        line: state.u64()?,
+        column: state.string()?,
+        keyword: state.string()?,
+        message: state.string()?,
+      });
+    }
+    parser.invalid_rows = state.u64()?;
+    if let Some(dedupe) = &mut parser.dedupe {
+      dedupe.load_state(state)?;
     }
+    Ok(parser)
+  }
+
+  /// Finds the end of the row starting at `start`: the index just past its
+  /// newline. Returns `None` while the row is still incomplete.
+  pub fn row_end(&self, buffer: &[u8], start: usize) -> Option<usize> {
+    if self.skipping_lines() {
+      // A skipped line ends at its line break, whatever quotes it holds
+      return (start..buffer.len())
+        .find(|&i| self.options.ends_line(buffer, i))
+        .map(|i| i + 1);
+    }
+    self.tokenizer.record_end(&self.options, buffer, start)
+  }
+
+  /// Physical lines parsed so far, skipped and comment lines included
+  pub fn lines(&self) -> u64 {
+    self.state.lines
+  }
+
+  /// Whether the header row has been read, or there is none to read
+  pub fn headers_resolved(&self) -> bool {
+    !self.state.first || self.options.headers.is_some()
+  }
 
-    self.parse_value(&result, 0, result.len())
+  /// Whether the incomplete row at the start of `buffer` has a quoted field
+  /// left open, so a line break in the next input does not end it
+  pub fn in_quote(&self, buffer: &[u8]) -> bool {
+    !self.skipping_lines()
+      && self.row_end(buffer, 0).is_none()
+      && self.tokenizer.open_quote(&self.options, buffer, 0).is_some()
+  }
+
+  /// Fails once `len` bytes of input are held without completing a row, past
+  /// `max_buffered_bytes`
+  pub fn check_buffered(&self, len: usize) -> Result<()> {
+    match self.options.max_buffered_bytes {
+      Some(max) if len > max => Err(eyre!(
+        "Buffered {} bytes without completing a row, more than maxBufferedBytes ({}); is a quoted field left open?",
+        len,
+        max
+      )),
+      _ => Ok(()),
+    }
+  }
+
+  // Whether the next line is one of the first `skip_lines`
+  fn skipping_lines(&self) -> bool {
+    self.state.skipped_lines < self.options.skip_lines.unwrap_or(0).max(0) as u64
+  }
+
+  /// The line at `start..end` without its line break
+  pub fn line_bytes<'a>(&self, buffer: &'a [u8], start: usize, end: usize) -> &'a [u8] {
+    let mut line = &buffer[start..end];
+    if let Some(rest) = line.strip_suffix(&[self.options.newline]) {
+      line = rest;
+    }
+    line.strip_suffix(b"\r").unwrap_or(line)
   }
 
-  pub fn parse_line(
+  /// Parses the line at `start..end` into cell ranges of `buffer`. Returns
+  /// `None` for lines that produce no row (headers, comments, skipped lines).
+  pub fn parse_record(
     &mut self,
     buffer: &[u8],
     start: usize,
     end: usize,
-  ) -> Result<Option<HashMap<String, String>>> {
+  ) -> Result<Option<Vec<CellRange>>> {
     if start >= end {
       return Ok(None);
     }
-    
-    let mut end = end;
-    // trim newline
-    if end > start && buffer[end - 1] == self.options.newline {
-      end -= 1;
+
+    // Temporary data from the previous row is no longer referenced
+    self.arena.reset();
+    self.redacted.clear();
+    self.redacted_bytes.clear();
+
+    // Only the last record of the input can lack a line break
+    let newline = self.options.newline;
+    let at_input_end =
+      self.input_ended && end == buffer.len() || !self.options.ends_line(buffer, end - 1);
+    let line = self.state.lines;
+    let record = &buffer[start..end];
+    self.state.lines += match self.options.record_terminator {
+      RecordTerminator::Newline => record.iter().filter(|&&byte| byte == newline).count(),
+      // A `\r` ending the input ends its last line
+      RecordTerminator::Any => (start..end)
+        .filter(|&i| {
+          self.options.ends_line(buffer, i) || i + 1 == buffer.len() && buffer[i] == b'\r'
+        })
+        .count(),
+    } as u64;
+    let record_end = end;
+    let end = start + self.line_bytes(buffer, start, end).len();
+
+    // Excel may declare the separator with a `sep=;` first line. Like any
+    // other line, it counts toward `skip_lines`.
+    if start < end && self.options.sep_directive && !self.state.directive_checked {
+      self.state.directive_checked = true;
+      if let Some(separator) = parse_sep_directive(&buffer[start..end]) {
+        self.state.configured_separator = Some(self.options.separator);
+        self.options.separator = separator;
+        self.state.skipped_lines += 1;
+        return Ok(None);
+      }
     }
-    if end > start && buffer[end - 1] == b'\r' {
-      end -= 1;
+
+    // `skip_lines` drops the first physical lines, blank lines and comments
+    // included, before anything else looks at them
+    if self.skipping_lines() {
+      self.state.skipped_lines += 1;
+      return Ok(None);
+    }
+
+    // A blank or marker line ends the current table, unless nothing of it
+    // has been read yet; a marker then names it
+    if let Some(line) = self.section_line(buffer, start, end) {
+      if self.state.section_open {
+        self.start_section();
+      }
+      if let SectionLine::Marker(name) = line {
+        self.state.section_name = Some(String::from_utf8_lossy(name).trim().to_string());
+      }
+      return Ok(None);
     }
-    
+
     if start >= end {
       return Ok(None);
     }
 
     // Handle skip comments
-    if self.should_skip_comment(buffer, start) {
+    if self.should_skip_comment(buffer, start, end) {
+      self.comment_lines += 1;
       return Ok(None);
     }
+    self.state.section_open = true;
+
+    if at_input_end {
+      let open = self.tokenizer.open_quote(&self.options, &buffer[..record_end], start);
+      match (open, self.options.on_unterminated_quote) {
+        (None, _) | (_, UnterminatedQuote::Emit) => {}
+        (Some(_), UnterminatedQuote::Drop) => return Ok(None),
+        (Some(quote), UnterminatedQuote::Error) => {
+          let lines = buffer[start..quote].iter().filter(|&&byte| byte == newline).count();
+          return Err(eyre!(
+            "Quoted field opened on line {} is never closed",
+            line + lines as u64 + 1
+          ));
+        }
+      }
+    }
 
-    let mut cells = Vec::new();
-    let mut is_quoted = false;
-    let mut offset = start;
+    // `detect_separator` looks at the first line that gets here and the
+    // complete lines buffered after it, then keeps its choice
+    if self.options.detect_separator && self.state.configured_separator.is_none() {
+      self.state.configured_separator = Some(self.options.separator);
+      let rest = &buffer[start..];
+      let newline = self.options.newline;
+      let sample = match rest.iter().rposition(|&byte| byte == newline) {
+        Some(last) => &rest[..=last],
+        None => rest,
+      };
+      if let Some(separator) = sniff_dialect(sample, self.options.quote).separator {
+        self.options.separator = separator;
+      }
+    }
 
-    // Skip lines if needed
-    if let Some(skip_lines) = self.options.skip_lines {
-      if self.state.line_number < skip_lines as u64 {
-        self.state.line_number += 1;
+    // Grep before tokenizing, so unwanted lines cost one regex scan
+    let header_line = self.state.first && self.options.headers.is_none();
+    if let Some(filter) = self.options.line_filter.as_ref().filter(|_| !header_line) {
+      if !filter.keeps(&buffer[start..end]) {
         return Ok(None);
       }
     }
@@ -189,39 +1507,74 @@ impl CsvParser {
       return Err(eyre!("Row exceeds the maximum size"));
     }
 
-    let mut i = start;
-    while i < end {
-      let byte = buffer[i];
-      
-      if byte == self.options.quote {
-        if !is_quoted {
-          // Starting quote
-          is_quoted = true;
-        } else if i + 1 < end && buffer[i + 1] == self.options.quote {
-          // Escaped quote - skip both characters
-          i += 1; // Skip the escape quote, will increment again at end of loop
-        } else {
-          // Ending quote
-          is_quoted = false;
-        }
-      } else if byte == self.options.separator && !is_quoted {
-        let value = self.parse_cell(buffer, offset, i)?;
-        cells.push(value);
-        offset = i + 1;
-      }
-      
-      i += 1;
+    let skip_empty = self.options.skip_empty_lines;
+    if skip_empty != SkipEmptyLines::Off && buffer[start..end].iter().all(u8::is_ascii_whitespace) {
+      return Ok(None);
     }
 
-    // Handle last cell
-    if offset < end {
-      let value = self.parse_cell(buffer, offset, end)?;
-      cells.push(value);
+    let mut cells = Vec::new();
+    if !self.tokenizer.split(&self.options, buffer, start, end, &mut cells)? {
+      return Ok(None);
     }
 
-    // Handle trailing comma
-    if end > start && buffer[end - 1] == self.options.separator {
-      cells.push(String::new());
+    let blank = |cell: &CellRange| buffer[cell.start..cell.end].iter().all(u8::is_ascii_whitespace);
+    if skip_empty == SkipEmptyLines::Greedy && cells.iter().all(blank) {
+      return Ok(None);
+    }
+
+    if let Some(max_columns) = self.options.max_columns.filter(|&n| cells.len() > n) {
+      match self.options.max_columns_action {
+        MaxColumnsAction::Error => {
+          return Err(eyre!(
+            "Row has {} columns, more than maxColumns ({})",
+            cells.len(),
+            max_columns
+          ))
+        }
+        MaxColumnsAction::Truncate => cells.truncate(max_columns),
+      }
+    }
+
+    if let Some(max) = self.options.max_field_bytes {
+      for (i, cell) in cells.iter_mut().enumerate() {
+        // Measured on the value, with escaped quotes collapsed
+        let value_len = |end: usize| match cell.escaped {
+          true => {
+            let bytes = &buffer[cell.start..end];
+            self.tokenizer.unescape(&self.options, bytes, &self.arena).len()
+          }
+          false => end - cell.start,
+        };
+        let len = value_len(cell.end);
+        if len <= max {
+          continue;
+        }
+        if self.options.on_oversize_field == OversizeField::Error {
+          return Err(eyre!(
+            "Field {} has {} bytes, more than maxFieldBytes ({})",
+            i + 1,
+            len,
+            max
+          ));
+        }
+        // The longest prefix within the limit. Completing an escape does not
+        // lengthen the value, so the prefix never ends inside one.
+        let (mut within, mut over) = (cell.start + max, cell.end);
+        while over - within > 1 {
+          let mid = within + (over - within) / 2;
+          if value_len(mid) <= max {
+            within = mid;
+          } else {
+            over = mid;
+          }
+        }
+        // Continuation bytes of a character cut at the limit go with it
+        let mut end = within;
+        while end > cell.start && buffer[end] & 0xC0 == 0x80 {
+          end -= 1;
+        }
+        cell.end = end;
+      }
     }
 
     // Handle headers
@@ -230,15 +1583,36 @@ impl CsvParser {
       match &self.options.headers {
         None => {
           // Auto-detect headers from first row
-          self.headers = Some(cells);
-          self.state.line_number += 1;
-          return Ok(None);
+          let (policy, expand) = (self.options.unsafe_headers, self.options.expand_headers);
+          let headers = cells
+            .iter()
+            .enumerate()
+            .map(|(i, &cell)| {
+              let header = self.cell_value(buffer, cell)?.into_owned();
+              if header.is_empty() || header == "_" {
+                match self.options.empty_headers {
+                  EmptyHeaders::Drop => {}
+                  EmptyHeaders::KeepPositional => return Ok(format!("_{}", i)),
+                  EmptyHeaders::Error => {
+                    return Err(eyre!("Column {} has an empty or \"_\" header", i + 1))
+                  }
+                }
+              }
+              policy.apply(self.options.alias_key(header), expand)
+            })
+            .collect::<Result<Vec<_>>>()?;
+          self.headers = Some(headers);
+          if !self.options.pass_header_row {
+            return Ok(None);
+          }
+          // Passed on as it is, without the checks data rows go through, so
+          // nothing converted or found in an earlier row applies to it
+          self.coerced.clear();
+          self.violations.clear();
+          return Ok(Some(cells));
         }
         Some(ref headers) if headers.is_empty() => {
-          // headers: false - generate numeric column names based on first row
-          let numeric_headers: Vec<String> = (0..cells.len()).map(|i| i.to_string()).collect();
-          self.headers = Some(numeric_headers);
-          // Don't return early - process this row as data
+          // headers: false - numeric column names are generated below
         }
         Some(headers) => {
           // Use provided custom headers
@@ -248,88 +1622,424 @@ impl CsvParser {
       }
     }
 
-    let mapped_cells = cells;
-    // .into_iter()
-    // .enumerate()
-    // .map(|(index, value)| {
-    //   let header = self
-    //     .headers
-    //     .as_ref()
-    //     .and_then(|h| h.get(index))
-    //     .map(|h| h.to_string())
-    //     .unwrap_or_else(|| format!("_{}", index));
-    //   self.map_value(header, index, value)
-    // })
-    // .collect::<Result<Vec<_>>>()?;
+    // headers: false names columns by index. The names grow with the widest
+    // row so far, except in strict mode where the first row sets the width.
+    if self.options.headers.as_ref().is_some_and(Vec::is_empty) {
+      let headers = self.headers.get_or_insert_with(Vec::new);
+      let grow = headers.is_empty() || self.options.strict == Strict::Off;
+      if headers.len() < cells.len() && grow {
+        headers.extend((headers.len()..cells.len()).map(|i| i.to_string()));
+      }
+    }
+
+    if self.headers.is_none() {
+      return Err(eyre!("No headers defined"));
+    }
 
     // Validate row length if strict mode is enabled
-    if self.options.strict {
-      if let Some(headers) = &self.headers {
-        if mapped_cells.len() != headers.len() {
+    if self.options.strict != Strict::Off {
+      let expected = self.headers.as_ref().map_or(0, Vec::len);
+      if cells.len() != expected {
+        if self.options.strict == Strict::Error {
           return Err(eyre!("Row length does not match headers"));
         }
+        self.ragged_rows.push(RaggedRow {
+          line: line + 1,
+          expected,
+          actual: cells.len(),
+        });
+        return Ok(None);
       }
     }
 
-    self.state.line_number += 1;
-    Ok(Some(self.write_row(mapped_cells)?))
-  }
+    if self.options.validates() && !self.validate(buffer, &cells, line)? {
+      return Ok(None);
+    }
 
-  fn parse_value(&self, buffer: &[u8], start: usize, end: usize) -> Result<String> {
-    if self.options.raw {
-      Ok(String::from_utf8_lossy(&buffer[start..end]).into_owned())
-    } else {
-      String::from_utf8(buffer[start..end].to_vec())
-        .map_err(|e| eyre!("UTF-8 conversion error: {}", e))
+    if let Some(mut dedupe) = self.dedupe.take() {
+      let headers = self.headers.as_deref().unwrap_or_default();
+      let cell = |i: usize| cells.get(i).map(|&cell| self.cell_bytes(buffer, cell));
+      let duplicate = dedupe.check(headers, cell, cells.len());
+      self.dedupe = Some(dedupe);
+      if duplicate? {
+        return Ok(None);
+      }
+    }
+
+    if !self.options.redact.is_empty() {
+      self.redact(buffer, &cells);
     }
+
+    Ok(Some(cells))
   }
 
-  fn write_row(&self, cells: Vec<String>) -> Result<HashMap<String, String>> {
-    let mut row = HashMap::new();
-    let headers = match &self.headers {
-      Some(h) => h,
-      None => return Err(eyre!("No headers defined")),
-    };
+  // Hides the cells of the columns in `redact`, which `cell_bytes` and
+  // `row_fields` then read in their place
+  fn redact(&mut self, buffer: &[u8], cells: &[CellRange]) {
+    let headers = self.headers.as_deref().unwrap_or_default();
+    let mut redacted = std::mem::take(&mut self.redacted);
+    let mut bytes = std::mem::take(&mut self.redacted_bytes);
+    for (column, redaction) in &self.options.redact {
+      let Some(index) = headers.iter().position(|header| header == column) else {
+        continue;
+      };
+      let Some(&cell) = cells.get(index) else {
+        continue;
+      };
+      // The value `schema` converted the cell to would reveal it
+      if let Some(coerced) = self.coerced.get_mut(index) {
+        *coerced = None;
+      }
+      if *redaction == Redaction::Drop {
+        redacted.push((cell, None));
+        continue;
+      }
+      let value = self.cell_bytes(buffer, cell);
+      if value.is_empty() || self.is_null(buffer, cell) {
+        continue;
+      }
+      let start = bytes.len();
+      match redaction {
+        Redaction::Hash => {
+          let mut salted = self.options.redact_salt.as_bytes().to_vec();
+          salted.extend_from_slice(value);
+          bytes.extend_from_slice(HashAlgorithm::Sha256.hex(&salted).as_bytes());
+        }
+        _ => {
+          let text = String::from_utf8_lossy(value);
+          let count = text.chars().count();
+          let masked = if count > 4 { count - 4 } else { count };
+          for (i, char) in text.chars().enumerate() {
+            match i < masked {
+              true => bytes.push(b'*'),
+              false => bytes.extend_from_slice(char.encode_utf8(&mut [0; 4]).as_bytes()),
+            }
+          }
+        }
+      }
+      redacted.push((cell, Some(start..bytes.len())));
+    }
+    self.redacted = redacted;
+    self.redacted_bytes = bytes;
+  }
+
+  // Whether `redact` drops `cell` from the last row
+  fn is_dropped(&self, cell: CellRange) -> bool {
+    self
+      .redacted
+      .iter()
+      .any(|(redacted, range)| range.is_none() && *redacted == cell)
+  }
+
+  /// Rows dropped by `dedupe` so far
+  pub fn duplicates(&self) -> u64 {
+    self.dedupe.as_ref().map_or(0, |dedupe| dedupe.dropped)
+  }
 
-    // Handle strict mode
-    if self.options.strict && cells.len() != headers.len() {
-      return Err(eyre!("Row length does not match headers"));
+  // Checks the cells of a row against `validators` and `schema`, converting
+  // the cells of schema properties and collecting the failing columns. False
+  // for a row `InvalidRow::Skip` leaves out.
+  fn validate(&mut self, buffer: &[u8], cells: &[CellRange], line: u64) -> Result<bool> {
+    let headers = self.headers.as_deref().unwrap_or_default();
+    let mut violations = std::mem::take(&mut self.violations);
+    violations.clear();
+    // Column, keyword and message of each failure
+    let mut failures: Vec<(String, &'static str, String)> = Vec::new();
+    for validator in &self.options.validators {
+      let cell = headers
+        .iter()
+        .position(|header| *header == validator.column)
+        .and_then(|i| cells.get(i));
+      let bytes = cell.map_or(&b""[..], |&cell| self.cell_bytes(buffer, cell));
+      if validator.pattern.is_match(bytes) {
+        continue;
+      }
+      if self.options.on_violation == InvalidRow::Error {
+        return Err(eyre!(
+          "Line {}: {} does not match validators.{} ({})",
+          line + 1,
+          validator.column,
+          validator.column,
+          validator.pattern.as_str()
+        ));
+      }
+      let message = format!("must match pattern \"{}\"", validator.pattern.as_str());
+      failures.push((validator.column.clone(), "pattern", message));
     }
 
-    for (index, cell) in cells.into_iter().enumerate() {
-      if let Some(header) = headers.get(index) {
-        if !header.is_empty() && header != "_" {
-          row.insert(header.clone(), cell);
+    let mut coerced = std::mem::take(&mut self.coerced);
+    coerced.clear();
+    if let Some(schema) = &self.options.schema {
+      coerced.resize(cells.len(), None);
+      // Keys the row has, for `required`
+      let mut present = Vec::new();
+      for (index, key, cell) in self.row_fields(cells) {
+        let Some(property) = schema.property(&key) else {
+          if !schema.additional_properties() {
+            failures.push((key.to_string(), "additionalProperties", "is not allowed".to_string()));
+          }
+          present.push(key);
+          continue;
+        };
+        let text = match self.is_null(buffer, cell) {
+          true => None,
+          false => Some(self.cell_value(buffer, cell)?),
+        };
+        match schema.check(property, text.as_deref()) {
+          Ok(Coerced::Missing) => coerced[index] = Some(Coerced::Missing),
+          Ok(value) => {
+            coerced[index] = Some(value);
+            present.push(key);
+          }
+          Err((keyword, message)) => {
+            failures.push((key.to_string(), keyword, message));
+            present.push(key);
+          }
+        }
+      }
+      for name in schema.required() {
+        if !present.iter().any(|key| key == name) {
+          failures.push((name.clone(), "required", "is missing".to_string()));
         }
-      } else if !self.options.strict {
-        // Only add extra columns if not in strict mode
-        row.insert(format!("_{}", index), cell);
       }
     }
+    self.coerced = coerced;
+
+    if let Some((column, _, message)) = failures.first() {
+      if self.options.on_violation == InvalidRow::Error {
+        return Err(eyre!("Line {}: {} {}", line + 1, column, message));
+      }
+    }
+    for (column, keyword, message) in failures {
+      violations.push(column.clone());
+      self.cell_violations.push(CellViolation {
+        line: line + 1,
+        column,
+        keyword: keyword.to_string(),
+        message,
+      });
+    }
+    let valid = violations.is_empty() || self.options.on_violation == InvalidRow::Annotate;
+    if !valid {
+      self.invalid_rows += 1;
+      violations.clear();
+    }
+    self.violations = violations;
+    Ok(valid)
+  }
 
-    Ok(row)
+  /// With `InvalidRow::Annotate`, the columns of the last row that failed
+  /// their `validators` pattern or `schema` property
+  pub fn violations(&self) -> &[String] {
+    &self.violations
   }
 
-  fn should_skip_comment(&self, buffer: &[u8], start: usize) -> bool {
-    match &self.options.skip_comments {
-      Some(SkipComments::Boolean(true)) => {
-        let trimmed_start = buffer[start..]
-          .iter()
-          .position(|&x| !x.is_ascii_whitespace())
-          .map_or(start, |pos| start + pos);
-        buffer.get(trimmed_start) == Some(&b'#')
-      }
-      Some(SkipComments::String(char)) => {
-        let trimmed_start = buffer[start..]
-          .iter()
-          .position(|&x| !x.is_ascii_whitespace())
-          .map_or(start, |pos| start + pos);
-        buffer.get(trimmed_start) == Some(&char.as_bytes()[0])
+  /// How `schema` converted cell `index` of the last row, if it is a
+  /// property and its cell passed
+  pub fn coerced(&self, index: usize) -> Option<Coerced> {
+    self.coerced.get(index).copied().flatten()
+  }
+
+  /// Every failed cell of the rows kept or left out so far, in input order
+  pub fn cell_violations(&self) -> &[CellViolation] {
+    &self.cell_violations
+  }
+
+  /// Rows left out by `InvalidRow::Skip` so far
+  pub fn invalid_rows(&self) -> u64 {
+    self.invalid_rows
+  }
+
+  /// Lines skipped as comments so far
+  pub fn comment_lines(&self) -> u64 {
+    self.comment_lines
+  }
+
+  /// Rows left out by `Strict::Report` so far, in input order
+  pub fn ragged_rows(&self) -> &[RaggedRow] {
+    &self.ragged_rows
+  }
+
+  /// Zero-based indexes of the header columns left out of rows because their
+  /// header is empty or `_`, after `EmptyHeaders::Drop` or `UnsafeHeaders`
+  pub fn dropped_columns(&self) -> Vec<usize> {
+    let headers = self.headers.as_deref().unwrap_or_default();
+    (0..headers.len())
+      .filter(|&i| headers[i].is_empty() || headers[i] == "_")
+      .collect()
+  }
+
+  /// Pairs the cells of a record with their column index and the key they are
+  /// stored under, leaving out cells whose header is blank or `_`, and empty
+  /// cells with `skip_empty_fields`. Cells beyond the headers are keyed
+  /// `_<index>` (or the `extra_columns` prefix), except in strict mode and
+  /// when `extra_columns` collects or drops them.
+  pub fn row_fields<'a>(
+    &'a self,
+    cells: &'a [CellRange],
+  ) -> impl Iterator<Item = (usize, Cow<'a, str>, CellRange)> + 'a {
+    let headers = self.headers.as_deref().unwrap_or_default();
+    cells
+      .iter()
+      .enumerate()
+      .filter(|(_, cell)| !(self.options.skip_empty_fields && cell.start == cell.end))
+      .filter(|&(_, &cell)| self.redacted.is_empty() || !self.is_dropped(cell))
+      .filter_map(move |(index, &cell)| match headers.get(index) {
+        Some(header) if header.is_empty() || header == "_" => None,
+        Some(header) => Some((index, Cow::Borrowed(header.as_str()), cell)),
+        None if self.options.strict != Strict::Off => None,
+        None => match &self.options.extra_columns {
+          ExtraColumns::Prefix(prefix) => {
+            Some((index, Cow::Owned(format!("{}{}", prefix, index)), cell))
+          }
+          _ => None,
+        },
+      })
+  }
+
+  /// The conversion `column_types` sets for the column stored under `key`
+  pub fn cell_type(&self, key: &str) -> Option<&CellType> {
+    self
+      .options
+      .column_types
+      .iter()
+      .find(|(column, _)| column == key)
+      .map(|(_, cell_type)| cell_type)
+  }
+
+  /// The number a cell of a `CellType::Number` column holds, if it is one
+  pub fn number(&self, column: &NumberColumn, text: &str) -> Option<f64> {
+    let format = NumberFormat {
+      decimal_separator: self.options.decimal_separator,
+      thousands_separator: self.options.thousands_separator,
+    };
+    column.parse(&format, text)
+  }
+
+  /// The item separator `array_columns` sets for the column stored under
+  /// `key`
+  pub fn array_separator(&self, key: &str) -> Option<&str> {
+    self
+      .options
+      .array_columns
+      .iter()
+      .find(|(column, _)| column == key)
+      .map(|(_, separator)| separator.as_str())
+  }
+
+  /// Whether `json_columns` names the column stored under `key`
+  pub fn is_json_column(&self, key: &str) -> bool {
+    self.options.json_columns.iter().any(|column| column == key)
+  }
+
+  /// The JSON value of `text`, a cell of the JSON column stored under `key`.
+  /// Blank cells are `null`. `None` means the cell is malformed and
+  /// `malformed_json` keeps it as a string.
+  pub fn json_value(&self, key: &str, text: &str) -> Result<Option<serde_json::Value>> {
+    if text.trim().is_empty() {
+      return Ok(Some(serde_json::Value::Null));
+    }
+    match serde_json::from_str(text) {
+      Ok(value) => Ok(Some(value)),
+      Err(_) if self.options.malformed_json == MalformedJson::Keep => Ok(None),
+      Err(e) => Err(eyre!("Invalid JSON in column \"{}\": {}", key, e)),
+    }
+  }
+
+  /// The bytes of each item in a list cell. An empty cell has no items.
+  pub fn cell_items<'a>(
+    &'a self,
+    buffer: &'a [u8],
+    cell: CellRange,
+    separator: &'a str,
+  ) -> Vec<&'a [u8]> {
+    let mut bytes = self.cell_bytes(buffer, cell);
+    let separator = separator.as_bytes();
+    if bytes.is_empty() {
+      return Vec::new();
+    }
+    let mut items = Vec::new();
+    while let Some(at) = bytes
+      .windows(separator.len())
+      .position(|window| window == separator)
+    {
+      items.push(&bytes[..at]);
+      bytes = &bytes[at + separator.len()..];
+    }
+    items.push(bytes);
+    items
+  }
+
+  /// The cells beyond the headers and the key to gather them under, when
+  /// `extra_columns` collects them and there are any
+  pub fn extra_cells<'a>(&'a self, cells: &'a [CellRange]) -> Option<(&'a str, &'a [CellRange])> {
+    let ExtraColumns::Collect(key) = &self.options.extra_columns else {
+      return None;
+    };
+    let headers = self.headers.as_deref().unwrap_or_default();
+    match cells.get(headers.len()..) {
+      Some(extra) if !extra.is_empty() && self.options.strict == Strict::Off => {
+        Some((key.as_str(), extra))
       }
-      _ => false,
+      _ => None,
+    }
+  }
+
+  /// Whether the line at `start..end` ends the current table of `sections`
+  /// and starts another
+  pub fn starts_section(&self, buffer: &[u8], start: usize, end: usize) -> bool {
+    self.state.section_open
+      && !self.skipping_lines()
+      && self.section_line(buffer, start, end).is_some()
+  }
+
+  /// The index of the current table of `sections`, from 0
+  pub fn section(&self) -> u64 {
+    self.state.section
+  }
+
+  /// The name the marker line of the current table gave it
+  pub fn section_name(&self) -> Option<&str> {
+    self.state.section_name.as_deref()
+  }
+
+  fn section_line<'a>(
+    &self,
+    buffer: &'a [u8],
+    start: usize,
+    end: usize,
+  ) -> Option<SectionLine<'a>> {
+    let sections = self.options.sections.as_ref()?;
+    let line = self.line_bytes(buffer, start, end);
+    if let Some(name) = sections
+      .marker
+      .as_ref()
+      .and_then(|marker| line.strip_prefix(marker.as_bytes()))
+    {
+      return Some(SectionLine::Marker(name));
     }
+    let blank = line.iter().all(u8::is_ascii_whitespace);
+    (sections.blank_lines && blank).then_some(SectionLine::Blank)
+  }
+
+  // Reads headers again from the next line, as at the start of the input
+  fn start_section(&mut self) {
+    self.state.section += 1;
+    self.state.section_name = None;
+    self.state.section_open = false;
+    self.state.first = self.options.headers.is_none();
+    self.headers = self.options.headers.clone().filter(|headers| !headers.is_empty());
+  }
+
+  fn should_skip_comment(&self, buffer: &[u8], start: usize, end: usize) -> bool {
+    let trimmed_start = buffer[start..end]
+      .iter()
+      .position(|&x| !x.is_ascii_whitespace())
+      .map_or(end, |pos| start + pos);
+    self.options.is_comment_at(buffer, trimmed_start, end)
   }
 
+
   // fn map_header(&self, header: String) -> napi::Result<String> {
   //   if let Some(map_fn) = &self.options.map_headers {
   //     map_fn.call_with_return_value(
@@ -363,9 +2073,330 @@ impl CsvParser {
   //   }
   // }
 }
+
+/// Finds records in the input and splits them into cells. `CsvParser` runs
+/// the rest of the pipeline on top of it (line endings, `sep=` directives,
+/// comments, skipped lines, headers, strict checks and `dedupe`), so another
+/// record format only has to implement this. The options are passed to each
+/// call since a `sep=` directive can change the separator mid-stream.
+/// Tokenizers are `Send` so parsers can move to other threads.
+pub trait RecordTokenizer: Send {
+  /// Finds the end of the record starting at `start`: the index just past its
+  /// line break. Returns `None` while the record is still incomplete.
+  fn record_end(&self, options: &CsvParserOptions, buffer: &[u8], start: usize) -> Option<usize>;
+
+  /// Where the quoted field still open at the end of `buffer` starts, for a
+  /// record from `start` that the end of input cut off. Formats without
+  /// quoting have none.
+  fn open_quote(&self, _options: &CsvParserOptions, _buffer: &[u8], _start: usize) -> Option<usize> {
+    None
+  }
+
+  /// Splits the record at `start..end`, without its line break, into `cells`.
+  /// Returns `false` for lines that hold no record, such as directives.
+  fn split(
+    &mut self,
+    options: &CsvParserOptions,
+    buffer: &[u8],
+    start: usize,
+    end: usize,
+    cells: &mut Vec<CellRange>,
+  ) -> Result<bool>;
+
+  /// The value of a cell marked `escaped`, written into `arena`
+  fn unescape<'a>(&self, options: &CsvParserOptions, bytes: &[u8], arena: &'a Bump) -> &'a [u8];
+
+  /// Forgets any state kept between records, before a new file
+  fn reset(&mut self) {}
+}
+
+/// The default tokenizer: RFC 4180 quoting plus the `relaxQuotes`, `ltrim`,
+/// `rtrim`, `excel` and `trailingComments` options
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CsvTokenizer;
+
+impl RecordTokenizer for CsvTokenizer {
+  fn record_end(&self, options: &CsvParserOptions, buffer: &[u8], start: usize) -> Option<usize> {
+    self.scan(options, buffer, start).ok()
+  }
+
+  fn open_quote(&self, options: &CsvParserOptions, buffer: &[u8], start: usize) -> Option<usize> {
+    self.scan(options, buffer, start).err().flatten()
+  }
+
+  fn split(
+    &mut self,
+    options: &CsvParserOptions,
+    buffer: &[u8],
+    start: usize,
+    mut end: usize,
+    cells: &mut Vec<CellRange>,
+  ) -> Result<bool> {
+    let mut is_quoted = false;
+    let mut offset = start;
+
+    let mut i = start;
+    while i < end {
+      let byte = buffer[i];
+      
+      if byte == options.quote {
+        if !is_quoted {
+          // Starting quote, unless relaxed quoting treats it as literal data
+          is_quoted = self.opens_quote(options, buffer, offset, i);
+        } else if i + 1 < end && buffer[i + 1] == options.quote {
+          // Escaped quote - skip both characters
+          i += 1; // Skip the escape quote, will increment again at end of loop
+        } else {
+          // Ending quote
+          is_quoted = false;
+        }
+      } else if !is_quoted && options.trailing_comments && options.is_comment_at(buffer, i, end) {
+        // The rest of the line is a comment
+        end = i;
+        break;
+      } else if byte == options.separator && !is_quoted {
+        cells.push(self.cell_range(options, buffer, offset, i));
+        offset = i + 1;
+      }
+      
+      i += 1;
+    }
+
+    // Handle last cell
+    if offset < end {
+      cells.push(self.cell_range(options, buffer, offset, end));
+    }
+
+    // Handle trailing comma
+    if end > start && buffer[end - 1] == options.separator {
+      cells.push(CellRange::empty(end));
+    }
+
+    Ok(true)
+  }
+
+  fn unescape<'a>(&self, options: &CsvParserOptions, bytes: &[u8], arena: &'a Bump) -> &'a [u8] {
+    let mut result = bumpalo::collections::Vec::with_capacity_in(bytes.len(), arena);
+    let mut i = 0;
+
+    while i < bytes.len() {
+      if bytes[i] == options.quote && i + 1 < bytes.len() && bytes[i + 1] == options.quote {
+        // Handle escaped quotes (double quotes)
+        result.push(options.quote);
+        i += 2; // Skip both quotes
+      } else {
+        result.push(bytes[i]);
+        i += 1;
+      }
+    }
+
+    result.into_bump_slice()
+  }
+}
+
+impl CsvTokenizer {
+  /// Locates the value of the cell spanning `start..end`: trims it, strips
+  /// its quotes and notes whether it contains escaped quotes.
+  fn cell_range(
+    &self,
+    options: &CsvParserOptions,
+    buffer: &[u8],
+    start: usize,
+    end: usize,
+  ) -> CellRange {
+    if start >= end {
+      return CellRange::empty(start);
+    }
+
+    let (mut cell_start, mut cell_end) = self.trim_cell(options, buffer, start, end);
+    if cell_start >= cell_end {
+      return CellRange::empty(cell_start);
+    }
+    let mut is_quoted = false;
+
+    // Excel writes `="000123"` to keep leading zeros; the value is the string
+    if options.excel && self.is_excel_string(options, buffer, cell_start, cell_end) {
+      cell_start += 1;
+    }
+
+    // Check if cell is quoted
+    if buffer[cell_start] == options.quote && cell_end > cell_start && buffer[cell_end - 1] == options.quote {
+      cell_start += 1;
+      cell_end -= 1;
+      is_quoted = true;
+      if options.trim_quoted && cell_start < cell_end {
+        (cell_start, cell_end) = self.trim_cell(options, buffer, cell_start, cell_end);
+      }
+    }
+
+    // Only quoted cells with escaped quotes need rewriting; everything else is
+    // decoded straight from the input
+    let escaped = is_quoted
+      && buffer[cell_start..cell_end.max(cell_start)]
+        .windows(2)
+        .any(|w| w[0] == options.quote && w[1] == options.quote);
+
+    CellRange {
+      start: cell_start,
+      end: cell_end.max(cell_start),
+      escaped,
+    }
+  }
+
+  /// Narrows `start..end` by the whitespace the `ltrim`/`rtrim` options strip.
+  /// Called on a whole cell, this removes padding around its quotes; the
+  /// quoted content is only trimmed as well with `trim_quoted`.
+  fn trim_cell(
+    &self,
+    options: &CsvParserOptions,
+    buffer: &[u8],
+    start: usize,
+    end: usize,
+  ) -> (usize, usize) {
+    let mut start = start;
+    let mut end = end;
+    if options.ltrim {
+      while start < end && is_trimmable(buffer[start]) {
+        start += 1;
+      }
+    }
+    if options.rtrim {
+      while end > start && is_trimmable(buffer[end - 1]) {
+        end -= 1;
+      }
+    }
+    (start, end)
+  }
+
+  // Finds the end of the record from `start`, just past its newline.
+  // Newlines inside quoted cells and comments do not end it. Without an
+  // end, gives where the quoted field still open at the end of `buffer`
+  // starts, if any.
+  fn scan(
+    &self,
+    options: &CsvParserOptions,
+    buffer: &[u8],
+    start: usize,
+  ) -> std::result::Result<usize, Option<usize>> {
+    let mut quote_start = None;
+    let mut in_comment = false;
+    let mut field_start = start;
+    let mut i = start;
+    while i < buffer.len() {
+      let byte = buffer[i];
+      let is_quoted = quote_start.is_some();
+      // Quotes and separators inside a comment are not data
+      if !is_quoted && !in_comment {
+        in_comment = options.comment_starts(buffer, start, i);
+      }
+      if in_comment && !options.ends_line(buffer, i) {
+        i += 1;
+        continue;
+      }
+      in_comment = false;
+
+      // Track quote state to avoid treating quoted newlines as row separators
+      if byte == options.quote {
+        if !is_quoted {
+          quote_start = self.opens_quote(options, buffer, field_start, i).then_some(i);
+        } else if i + 1 < buffer.len() && buffer[i + 1] == options.quote {
+          // Skip escaped quote - advance past both quote characters
+          i += 2;
+          continue;
+        } else {
+          quote_start = None;
+        }
+      }
+
+      let is_quoted = quote_start.is_some();
+      if byte == options.separator && !is_quoted {
+        field_start = i + 1;
+      }
+
+      if !is_quoted && options.ends_line(buffer, i) {
+        return Ok(i + 1);
+      }
+      i += 1;
+    }
+    Err(quote_start)
+  }
+
+  /// Whether a quote at `i` opens a quoted field starting at `field_start`.
+  /// With `relax_quotes` only a quote at the start of the field (after any
+  /// whitespace `ltrim` strips) does; quotes elsewhere are literal data.
+  fn opens_quote(
+    &self,
+    options: &CsvParserOptions,
+    buffer: &[u8],
+    field_start: usize,
+    i: usize,
+  ) -> bool {
+    if !options.relax_quotes {
+      return true;
+    }
+    let (trimmed_start, _) = self.trim_cell(options, buffer, field_start, i);
+    trimmed_start == i
+      || (options.excel && trimmed_start + 1 == i && buffer[trimmed_start] == b'=')
+  }
+
+  fn is_excel_string(
+    &self,
+    options: &CsvParserOptions,
+    buffer: &[u8],
+    start: usize,
+    end: usize,
+  ) -> bool {
+    end - start >= 3
+      && buffer[start] == b'='
+      && buffer[start + 1] == options.quote
+      && buffer[end - 1] == options.quote
+  }
+}
+
+/// Returns the separator declared by an Excel `sep=X` line, which may be
+/// wrapped in quotes (`"sep=;"`).
+fn parse_sep_directive(line: &[u8]) -> Option<u8> {
+  let line = match line {
+    [b'"', inner @ .., b'"'] => inner,
+    _ => line,
+  };
+  match line {
+    [s, e, p, b'=', separator] if [*s, *e, *p].eq_ignore_ascii_case(b"sep") => Some(*separator),
+    _ => None,
+  }
+}
+
+fn is_trimmable(byte: u8) -> bool {
+  matches!(byte, b' ' | b'\t' | b'\x0B' | b'\x0C')
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
+  use std::collections::HashMap;
+
+  impl CsvParser {
+    fn parse_line(
+      &mut self,
+      buffer: &[u8],
+      start: usize,
+      end: usize,
+    ) -> Result<Option<HashMap<String, String>>> {
+      let Some(cells) = self.parse_record(buffer, start, end)? else {
+        return Ok(None);
+      };
+      self
+        .row_fields(&cells)
+        .map(|(_, key, cell)| {
+          Ok((
+            key.into_owned(),
+            self.cell_value(buffer, cell)?.into_owned(),
+          ))
+        })
+        .collect::<Result<HashMap<_, _>>>()
+        .map(Some)
+    }
+  }
 
   #[test]
   fn test_basic_parsing() {
@@ -395,7 +2426,7 @@ mod tests {
     let result = parser.parse_line(input, 0, 16).unwrap();
     assert!(result.is_none());
 
-    let result = parser.parse_line(input, 17, 42).unwrap();
+    let result = parser.parse_line(input, 17, 43).unwrap();
     assert_eq!(
       result.expect("Failed to parse line"),
       HashMap::from([
@@ -423,8 +2454,10 @@ mod tests {
 
   #[test]
   fn test_custom_separator() {
-    let mut options = CsvParserOptions::default();
-    options.separator = b';';
+    let options = CsvParserOptions {
+      separator: b';',
+      ..Default::default()
+    };
     let mut parser = CsvParser::new(options);
 
     let input = b"name;age\nJohn;30\nJane;25";
@@ -443,8 +2476,10 @@ mod tests {
 
   #[test]
   fn test_strict_mode() {
-    let mut options = CsvParserOptions::default();
-    options.strict = true;
+    let options = CsvParserOptions {
+      strict: Strict::Error,
+      ..Default::default()
+    };
     let mut parser = CsvParser::new(options);
 
     let input = b"a,b\n1,2,3";
@@ -457,8 +2492,10 @@ mod tests {
 
   #[test]
   fn test_skip_comments() {
-    let mut options = CsvParserOptions::default();
-    options.skip_comments = Some(SkipComments::Boolean(true));
+    let options = CsvParserOptions {
+      skip_comments: Some(SkipComments::Boolean(true)),
+      ..Default::default()
+    };
     let mut parser = CsvParser::new(options);
 
     let input = b"a,b\n#comment\n1,2";
@@ -467,6 +2504,7 @@ mod tests {
 
     let result = parser.parse_line(input, 4, 12).unwrap();
     assert!(result.is_none());
+    assert_eq!(parser.comment_lines(), 1);
 
     let result = parser.parse_line(input, 13, 16).unwrap();
     assert_eq!(
@@ -480,8 +2518,10 @@ mod tests {
 
   #[test]
   fn test_custom_comment_char() {
-    let mut options = CsvParserOptions::default();
-    options.skip_comments = Some(SkipComments::String("~".to_string()));
+    let options = CsvParserOptions {
+      skip_comments: Some(SkipComments::String("~".to_string())),
+      ..Default::default()
+    };
     let mut parser = CsvParser::new(options);
 
     let input = b"a,b\n~comment\n1,2";
@@ -531,13 +2571,13 @@ mod tests {
     let result = parser.parse_line(input, 0, 5).unwrap();
     assert!(result.is_none());
 
-    let result = parser.parse_line(input, 6, 10).unwrap();
+    // The trailing empty header is unnamed, so its cell is dropped
+    let result = parser.parse_line(input, 5, 9).unwrap();
     assert_eq!(
       result.expect("Failed to parse line"),
       HashMap::from([
         ("a".to_string(), "1".to_string()),
-        ("b".to_string(), "2".to_string()),
-        ("".to_string(), "".to_string())
+        ("b".to_string(), "2".to_string())
       ])
     );
   }
@@ -563,8 +2603,10 @@ mod tests {
 
   #[test]
   fn test_custom_headers() {
-    let mut options = CsvParserOptions::default();
-    options.headers = Some(vec!["col1".to_string(), "col2".to_string()]);
+    let options = CsvParserOptions {
+      headers: Some(vec!["col1".to_string(), "col2".to_string()]),
+      ..Default::default()
+    };
     let mut parser = CsvParser::new(options);
 
     let input = b"1,2\n3,4";
@@ -586,4 +2628,1029 @@ mod tests {
       ])
     );
   }
+
+  #[test]
+  fn test_generated_headers() {
+    let generated = |strict| {
+      let mut parser = CsvParser::new(CsvParserOptions {
+        headers: Some(vec![]),
+        strict,
+        ..Default::default()
+      });
+      let input = b"1,2\n3,4,5\n6\n";
+      assert_eq!(parser.headers, None);
+      parser.parse_line(input, 0, 4).unwrap();
+      assert_eq!(parser.headers, Some(vec!["0".into(), "1".into()]));
+      let wide = parser.parse_line(input, 4, 10);
+      parser.parse_line(input, 10, input.len()).ok();
+      (parser.headers.unwrap(), wide.map(Option::unwrap))
+    };
+
+    let (headers, row) = generated(Strict::Off);
+    assert_eq!(headers, vec!["0", "1", "2"]);
+    assert_eq!(row.unwrap().get("2").map(String::as_str), Some("5"));
+
+    let (headers, row) = generated(Strict::Error);
+    assert_eq!(headers, vec!["0", "1"]);
+    assert!(row.is_err());
+  }
+
+  #[test]
+  fn test_trim() {
+    let options = CsvParserOptions {
+      ltrim: true,
+      rtrim: true,
+      ..Default::default()
+    };
+    let mut parser = CsvParser::new(options);
+
+    let input = b" a , b \n  1 ,\t\" two \"  ";
+    let result = parser.parse_line(input, 0, 8).unwrap();
+    assert!(result.is_none());
+
+    let result = parser.parse_line(input, 8, input.len()).unwrap();
+    assert_eq!(
+      result.expect("Failed to parse line"),
+      HashMap::from([
+        ("a".to_string(), "1".to_string()),
+        ("b".to_string(), " two ".to_string())
+      ])
+    );
+  }
+
+  #[test]
+  fn test_trim_quoted() {
+    let options = CsvParserOptions {
+      ltrim: true,
+      rtrim: true,
+      trim_quoted: true,
+      headers: Some(vec!["a".to_string(), "b".to_string()]),
+      ..Default::default()
+    };
+    let mut parser = CsvParser::new(options);
+
+    let input = b" \" one \" ,\"\t\"\"two\"\" \"";
+    let result = parser.parse_line(input, 0, input.len()).unwrap();
+    assert_eq!(
+      result.expect("Failed to parse line"),
+      HashMap::from([
+        ("a".to_string(), "one".to_string()),
+        ("b".to_string(), "\"two\"".to_string())
+      ])
+    );
+  }
+
+  #[test]
+  fn test_ltrim_only() {
+    let options = CsvParserOptions {
+      ltrim: true,
+      headers: Some(vec!["a".to_string(), "b".to_string()]),
+      ..Default::default()
+    };
+    let mut parser = CsvParser::new(options);
+
+    let input = b"  1 ,  2 ";
+    let result = parser.parse_line(input, 0, input.len()).unwrap();
+    assert_eq!(
+      result.expect("Failed to parse line"),
+      HashMap::from([
+        ("a".to_string(), "1 ".to_string()),
+        ("b".to_string(), "2 ".to_string())
+      ])
+    );
+  }
+
+  #[test]
+  fn test_relax_quotes() {
+    let options = CsvParserOptions {
+      relax_quotes: true,
+      headers: Some(vec!["item".to_string(), "material".to_string()]),
+      ..Default::default()
+    };
+    let mut parser = CsvParser::new(options);
+
+    let input = b"5\" pipe,steel";
+    let result = parser.parse_line(input, 0, input.len()).unwrap();
+    assert_eq!(
+      result.expect("Failed to parse line"),
+      HashMap::from([
+        ("item".to_string(), "5\" pipe".to_string()),
+        ("material".to_string(), "steel".to_string())
+      ])
+    );
+
+    let input = b" \"a,b\",\"c,d\"";
+    let result = parser.parse_line(input, 0, input.len()).unwrap();
+    assert_eq!(
+      result.expect("Failed to parse line"),
+      HashMap::from([
+        ("item".to_string(), " \"a".to_string()),
+        ("material".to_string(), "b\"".to_string()),
+        ("_2".to_string(), "c,d".to_string())
+      ])
+    );
+  }
+
+  #[test]
+  fn test_multi_char_comment_prefix() {
+    let options = CsvParserOptions {
+      skip_comments: Some(SkipComments::String("//".to_string())),
+      ..Default::default()
+    };
+    let mut parser = CsvParser::new(options);
+
+    let input = b"a,b\n  // comment\n/,2";
+    let result = parser.parse_line(input, 0, 4).unwrap();
+    assert!(result.is_none());
+
+    let result = parser.parse_line(input, 4, 17).unwrap();
+    assert!(result.is_none());
+
+    let result = parser.parse_line(input, 17, input.len()).unwrap();
+    assert_eq!(
+      result.expect("Failed to parse line"),
+      HashMap::from([
+        ("a".to_string(), "/".to_string()),
+        ("b".to_string(), "2".to_string())
+      ])
+    );
+  }
+
+  #[test]
+  fn test_trailing_comments() {
+    let options = CsvParserOptions {
+      skip_comments: Some(SkipComments::Boolean(true)),
+      trailing_comments: true,
+      headers: Some(vec!["a".to_string(), "b".to_string()]),
+      ..Default::default()
+    };
+    let mut parser = CsvParser::new(options);
+
+    let input = b"\"#1\",2# note \"quoted\"";
+    let result = parser.parse_line(input, 0, input.len()).unwrap();
+    assert_eq!(
+      result.expect("Failed to parse line"),
+      HashMap::from([
+        ("a".to_string(), "#1".to_string()),
+        ("b".to_string(), "2".to_string())
+      ])
+    );
+  }
+
+  #[test]
+  fn test_arena_reset_between_rows() {
+    let options = CsvParserOptions {
+      headers: Some(vec!["text".to_string()]),
+      ..Default::default()
+    };
+    let mut parser = CsvParser::new(options);
+
+    let input = b"\"say \"\"hi\"\"\"";
+    parser.parse_line(input, 0, input.len()).unwrap();
+    let allocated = parser.arena_size();
+
+    for _ in 0..1000 {
+      let result = parser.parse_line(input, 0, input.len()).unwrap();
+      assert_eq!(
+        result.expect("Failed to parse line"),
+        HashMap::from([("text".to_string(), "say \"hi\"".to_string())])
+      );
+    }
+    assert_eq!(parser.arena_size(), allocated);
+  }
+
+  #[test]
+  fn test_sep_directive() {
+    let options = CsvParserOptions {
+      sep_directive: true,
+      ..Default::default()
+    };
+    let mut parser = CsvParser::new(options);
+
+    let input = b"sep=;\na;b\n1;2";
+    let result = parser.parse_line(input, 0, 6).unwrap();
+    assert!(result.is_none());
+
+    let result = parser.parse_line(input, 6, 10).unwrap();
+    assert!(result.is_none());
+
+    let result = parser.parse_line(input, 10, input.len()).unwrap();
+    assert_eq!(
+      result.expect("Failed to parse line"),
+      HashMap::from([
+        ("a".to_string(), "1".to_string()),
+        ("b".to_string(), "2".to_string())
+      ])
+    );
+  }
+
+  #[test]
+  fn test_detect_separator() {
+    let mut parser = CsvParser::new(CsvParserOptions {
+      detect_separator: true,
+      skip_comments: Some(SkipComments::Boolean(true)),
+      ..Default::default()
+    });
+
+    // The comment line is not looked at; `1,5` does not split evenly
+    let input = b"# a,b,c\na;b\n1,5;2\n";
+    assert!(parser.parse_line(input, 0, 8).unwrap().is_none());
+    assert!(parser.parse_line(input, 8, 12).unwrap().is_none());
+    assert_eq!(parser.options.separator, b';');
+    assert_eq!(
+      parser.parse_line(input, 12, input.len()).unwrap(),
+      Some(HashMap::from([
+        ("a".to_string(), "1,5".to_string()),
+        ("b".to_string(), "2".to_string())
+      ]))
+    );
+
+    // Each new input is looked at again
+    parser.reset();
+    let input = b"x\ty\n3\t4\n";
+    parser.parse_line(input, 0, 4).unwrap();
+    assert_eq!(parser.options.separator, b'\t');
+  }
+
+  #[test]
+  fn test_sep_directive_only_on_first_line() {
+    let options = CsvParserOptions {
+      sep_directive: true,
+      ..Default::default()
+    };
+    let mut parser = CsvParser::new(options);
+
+    let input = b"a\n\"sep=;\"";
+    let result = parser.parse_line(input, 0, 2).unwrap();
+    assert!(result.is_none());
+
+    let result = parser.parse_line(input, 2, input.len()).unwrap();
+    assert_eq!(
+      result.expect("Failed to parse line"),
+      HashMap::from([("a".to_string(), "sep=;".to_string())])
+    );
+  }
+
+  #[test]
+  fn test_cells_borrow_from_buffer() {
+    let options = CsvParserOptions {
+      headers: Some(vec!["a".to_string(), "b".to_string()]),
+      ..Default::default()
+    };
+    let mut parser = CsvParser::new(options);
+
+    let input = b"plain,\"say \"\"hi\"\"\"";
+    let cells = parser
+      .parse_record(input, 0, input.len())
+      .unwrap()
+      .expect("Failed to parse line");
+    assert_eq!(
+      cells,
+      vec![
+        CellRange {
+          start: 0,
+          end: 5,
+          escaped: false
+        },
+        CellRange {
+          start: 7,
+          end: 17,
+          escaped: true
+        },
+      ]
+    );
+
+    assert!(matches!(
+      parser.cell_value(input, cells[0]).unwrap(),
+      Cow::Borrowed("plain")
+    ));
+    assert_eq!(parser.cell_value(input, cells[1]).unwrap(), "say \"hi\"");
+  }
+
+  #[test]
+  fn test_excel_string_cells() {
+    let options = CsvParserOptions {
+      excel: true,
+      relax_quotes: true,
+      headers: Some(vec![
+        "id".to_string(),
+        "name".to_string(),
+        "sum".to_string(),
+      ]),
+      ..Default::default()
+    };
+    let mut parser = CsvParser::new(options);
+
+    let input = b"=\"000123\",=\"a,b\",=SUM(A1:A2)";
+    let result = parser.parse_line(input, 0, input.len()).unwrap();
+    assert_eq!(
+      result.expect("Failed to parse line"),
+      HashMap::from([
+        ("id".to_string(), "000123".to_string()),
+        ("name".to_string(), "a,b".to_string()),
+        ("sum".to_string(), "=SUM(A1:A2)".to_string())
+      ])
+    );
+  }
+
+  #[test]
+  fn test_skip_lines() {
+    let parse = |skip_lines, input: &[u8]| {
+      let mut parser = CsvParser::new(CsvParserOptions {
+        skip_lines: Some(skip_lines),
+        skip_comments: Some(SkipComments::Boolean(true)),
+        sep_directive: true,
+        ..Default::default()
+      });
+      let mut rows = Vec::new();
+      let mut start = 0;
+      while start < input.len() {
+        let end = parser.row_end(input, start).unwrap_or(input.len());
+        if let Some(row) = parser.parse_line(input, start, end).unwrap() {
+          rows.push(row);
+        }
+        start = end;
+      }
+      (parser.headers.unwrap_or_default(), rows)
+    };
+
+    // A comment, a blank line and an unclosed quote are one line each
+    let input = b"# generated\r\n\r\n\"unbalanced\r\nname,age\r\n# note\r\nAda,36\r\n";
+    let (headers, rows) = parse(3, input);
+    assert_eq!(headers, vec!["name", "age"]);
+    assert_eq!(
+      rows,
+      vec![HashMap::from([
+        ("name".to_string(), "Ada".to_string()),
+        ("age".to_string(), "36".to_string())
+      ])]
+    );
+
+    // The skipped lines end before the header, whatever follows them
+    let (headers, rows) = parse(1, b"sep=;\na;b\n1;2\n");
+    assert_eq!(headers, vec!["a", "b"]);
+    assert_eq!(rows.len(), 1);
+    let (headers, rows) = parse(2, b"title\na,b\n1,2\n3,4");
+    assert_eq!(headers, vec!["1", "2"]);
+    assert_eq!(rows.len(), 1);
+  }
+
+  #[test]
+  fn test_max_columns() {
+    let parse = |max_columns_action| {
+      let mut parser = CsvParser::new(CsvParserOptions {
+        max_columns: Some(2),
+        max_columns_action,
+        ..Default::default()
+      });
+      let input = b"a,b\n1,2,3,4\n";
+      parser.parse_line(input, 0, 4).unwrap();
+      parser.parse_line(input, 4, input.len())
+    };
+
+    let error = parse(MaxColumnsAction::Error).unwrap_err();
+    assert_eq!(
+      error.to_string(),
+      "Row has 4 columns, more than maxColumns (2)"
+    );
+    assert_eq!(
+      parse(MaxColumnsAction::Truncate).unwrap(),
+      Some(HashMap::from([
+        ("a".to_string(), "1".to_string()),
+        ("b".to_string(), "2".to_string())
+      ]))
+    );
+  }
+
+  #[test]
+  fn test_max_field_bytes() {
+    let parse = |on_oversize_field| {
+      let mut parser = CsvParser::new(CsvParserOptions {
+        max_field_bytes: Some(4),
+        on_oversize_field,
+        ..Default::default()
+      });
+      let input = "a,b\nabcdef,\"h\u{e9}\u{e9}\"\n".as_bytes();
+      parser.parse_line(input, 0, 4).unwrap();
+      parser.parse_line(input, 4, input.len())
+    };
+
+    let error = parse(OversizeField::Error).unwrap_err();
+    assert_eq!(
+      error.to_string(),
+      "Field 1 has 6 bytes, more than maxFieldBytes (4)"
+    );
+    // A character cut at the limit is left out whole
+    assert_eq!(
+      parse(OversizeField::Truncate).unwrap(),
+      Some(HashMap::from([
+        ("a".to_string(), "abcd".to_string()),
+        ("b".to_string(), "h\u{e9}".to_string())
+      ]))
+    );
+  }
+
+  #[test]
+  fn test_quoted_newlines() {
+    let parse = |quoted_newlines| {
+      let mut parser = CsvParser::new(CsvParserOptions {
+        quoted_newlines,
+        ..Default::default()
+      });
+      let input = "a,b\n\"one\r\ntwo\rthree\nfour\",\"say \"\"hi\"\"\n\"\n".as_bytes();
+      parser.parse_line(input, 0, 4).unwrap();
+      let row = parser.parse_line(input, 4, input.len()).unwrap().unwrap();
+      (row["a"].clone(), row["b"].clone())
+    };
+
+    assert_eq!(
+      parse(QuotedNewlines::Keep),
+      ("one\r\ntwo\rthree\nfour".to_string(), "say \"hi\"\n".to_string())
+    );
+    assert_eq!(
+      parse(QuotedNewlines::Lf),
+      ("one\ntwo\nthree\nfour".to_string(), "say \"hi\"\n".to_string())
+    );
+    assert_eq!(
+      parse(QuotedNewlines::Escape),
+      ("one\\ntwo\\nthree\\nfour".to_string(), "say \"hi\"\\n".to_string())
+    );
+    assert_eq!(
+      parse(QuotedNewlines::Strip),
+      ("onetwothreefour".to_string(), "say \"hi\"".to_string())
+    );
+  }
+
+  #[test]
+  fn test_hardened_limits() {
+    let parser = CsvParser::new(CsvParserOptions {
+      hardened: true,
+      max_columns: Some(2),
+      ..Default::default()
+    });
+    assert_eq!(parser.options.max_row_bytes, HARDENED_MAX_ROW_BYTES);
+    assert_eq!(parser.options.max_buffered_bytes, Some(HARDENED_MAX_BUFFERED_BYTES));
+    // Limits that are given stay
+    assert_eq!(parser.options.max_columns, Some(2));
+
+    let parser = CsvParser::new(CsvParserOptions::default());
+    assert_eq!(parser.options.max_buffered_bytes, None);
+  }
+
+  #[test]
+  fn test_sections() {
+    let parse = |sections, input: &[u8]| {
+      let mut parser = CsvParser::new(CsvParserOptions {
+        sections: Some(sections),
+        ..Default::default()
+      });
+      let mut rows = Vec::new();
+      let mut start = 0;
+      while start < input.len() {
+        let end = parser.row_end(input, start).unwrap_or(input.len());
+        if let Some(row) = parser.parse_line(input, start, end).unwrap() {
+          let name = parser.section_name().map(str::to_string);
+          let mut cells: Vec<_> = row.into_iter().collect();
+          cells.sort();
+          rows.push((parser.section(), name, cells));
+        }
+        start = end;
+      }
+      rows
+    };
+    let cells = |cells: &[(&str, &str)]| {
+      cells
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect::<Vec<_>>()
+    };
+
+    // Runs of blank lines end a table once; the next line is its header
+    let blank_lines = Sections {
+      blank_lines: true,
+      marker: None,
+    };
+    let rows = parse(blank_lines, b"\na,b\n1,2\n\n \nc\n3\n");
+    assert_eq!(
+      rows,
+      [
+        (0, None, cells(&[("a", "1"), ("b", "2")])),
+        (1, None, cells(&[("c", "3")]))
+      ]
+    );
+
+    // A marker names the table after it, including the first
+    let markers = Sections {
+      blank_lines: false,
+      marker: Some("## ".to_string()),
+    };
+    let rows = parse(markers, b"## Orders\nid\n1\n\n## Refunds\nid,amount\n2,5");
+    assert_eq!(
+      rows,
+      [
+        (0, Some("Orders".to_string()), cells(&[("id", "1")])),
+        (1, Some("Refunds".to_string()), cells(&[("amount", "5"), ("id", "2")]))
+      ]
+    );
+  }
+
+  #[test]
+  fn test_unsafe_headers() {
+    let headers = |unsafe_headers, expand_headers| {
+      let mut parser = CsvParser::new(CsvParserOptions {
+        unsafe_headers,
+        expand_headers,
+        ..Default::default()
+      });
+      let input = b"id,__proto__,a.constructor,meta[prototype],items[0].name\n";
+      parser.parse_line(input, 0, input.len() - 1)?;
+      Ok::<_, color_eyre::Report>(parser.headers.unwrap())
+    };
+
+    assert_eq!(
+      headers(UnsafeHeaders::Rename, true).unwrap(),
+      ["id", "__proto___", "a.constructor_", "meta.prototype_", "items[0].name"]
+    );
+    // Without expandHeaders only whole names are unsafe
+    assert_eq!(
+      headers(UnsafeHeaders::Rename, false).unwrap(),
+      ["id", "__proto___", "a.constructor", "meta[prototype]", "items[0].name"]
+    );
+    assert_eq!(
+      headers(UnsafeHeaders::Drop, true).unwrap(),
+      ["id", "", "", "", "items[0].name"]
+    );
+    assert_eq!(
+      headers(UnsafeHeaders::Error, false).unwrap_err().to_string(),
+      "Unsafe header \"__proto__\""
+    );
+    assert_eq!(headers(UnsafeHeaders::Keep, true).unwrap()[1], "__proto__");
+  }
+
+  #[test]
+  fn test_skip_empty_lines() {
+    let input = b"a,b\n \t\n,\n\"\",\n1,\n \t, \n";
+    let rows = |skip_empty_lines| {
+      let mut parser = CsvParser::new(CsvParserOptions {
+        skip_empty_lines,
+        ..Default::default()
+      });
+      [(0, 4), (4, 7), (7, 9), (9, 13), (13, 16), (16, 21)]
+        .into_iter()
+        .filter_map(|(start, end)| parser.parse_line(input, start, end).unwrap())
+        .count()
+    };
+
+    assert_eq!(rows(SkipEmptyLines::Off), 5);
+    assert_eq!(rows(SkipEmptyLines::Blank), 4);
+    assert_eq!(rows(SkipEmptyLines::Greedy), 1);
+  }
+
+  #[test]
+  fn test_column_aliases() {
+    let mut parser = CsvParser::new(CsvParserOptions {
+      column_aliases: vec![
+        (
+          "email".to_string(),
+          vec!["E-Mail".to_string(), "email_address".to_string()],
+        ),
+        ("name".to_string(), vec!["Full Name".to_string()]),
+      ],
+      ..Default::default()
+    });
+    let input = b"ID,EMAIL_ADDRESS,full name,Phone
+1,a@b.c,Ann,555
+";
+    parser.parse_line(input, 0, 32).unwrap();
+    assert_eq!(
+      parser.headers.as_deref().unwrap(),
+      ["ID", "email", "name", "Phone"]
+    );
+    let row = parser.parse_line(input, 33, input.len() - 1).unwrap().unwrap();
+    assert_eq!(row["email"], "a@b.c");
+    assert_eq!(row["name"], "Ann");
+
+    // A key matches in any case too
+    let options = CsvParserOptions {
+      column_aliases: vec![("email".to_string(), Vec::new())],
+      ..Default::default()
+    };
+    assert_eq!(options.alias_key("Email".to_string()), "email");
+    assert_eq!(options.alias_key("mail".to_string()), "mail");
+  }
+
+  #[test]
+  fn test_validators() {
+    let input = b"email,zip\na@b.c,12345\nnope,1234\nc@d.e\n";
+    let rows = |on_violation| {
+      let mut parser = CsvParser::new(CsvParserOptions {
+        validators: vec![
+          Validator::new("email", "^[^@]+@[^@]+$").unwrap(),
+          Validator::new("zip", r"^\d{5}$").unwrap(),
+        ],
+        on_violation,
+        ..Default::default()
+      });
+      let mut rows = Vec::new();
+      for (start, end) in [(0, 10), (10, 22), (22, 32), (32, 38)] {
+        if let Some(row) = parser.parse_line(input, start, end)? {
+          rows.push((row["email"].clone(), parser.violations().to_vec()));
+        }
+      }
+      Ok::<_, color_eyre::Report>((rows, parser.invalid_rows()))
+    };
+
+    assert_eq!(
+      rows(InvalidRow::Annotate).unwrap(),
+      (
+        vec![
+          ("a@b.c".to_string(), vec![]),
+          ("nope".to_string(), vec!["email".to_string(), "zip".to_string()]),
+          // A missing cell is checked as empty
+          ("c@d.e".to_string(), vec!["zip".to_string()]),
+        ],
+        0
+      )
+    );
+    assert_eq!(
+      rows(InvalidRow::Skip).unwrap(),
+      (vec![("a@b.c".to_string(), vec![])], 2)
+    );
+    assert_eq!(
+      rows(InvalidRow::Error).unwrap_err().to_string(),
+      "Line 3: email does not match validators.email (^[^@]+@[^@]+$)"
+    );
+    assert!(Validator::new("zip", "(").is_err());
+  }
+
+  #[test]
+  fn test_schema() {
+    let schema = RowSchema::new(&serde_json::json!({
+      "properties": {
+        "id": { "type": "integer" },
+        "score": { "type": "number", "maximum": 10 },
+        "ok": { "type": "boolean" }
+      },
+      "required": ["id", "ok"],
+      "additionalProperties": false
+    }))
+    .unwrap();
+    let mut parser = CsvParser::new(CsvParserOptions {
+      schema: Some(schema),
+      on_violation: InvalidRow::Annotate,
+      ..Default::default()
+    });
+    let input = b"id,score,ok\n1,2.5,true\nx,11,\n";
+    parser.parse_record(input, 0, 12).unwrap();
+    parser.parse_record(input, 12, 23).unwrap().unwrap();
+    assert_eq!(parser.coerced(0), Some(Coerced::Integer(1)));
+    assert_eq!(parser.coerced(1), Some(Coerced::Number(2.5)));
+    assert_eq!(parser.coerced(2), Some(Coerced::Boolean(true)));
+    assert!(parser.violations().is_empty());
+
+    parser.parse_record(input, 23, input.len()).unwrap().unwrap();
+    assert_eq!(parser.coerced(0), None);
+    assert_eq!(parser.coerced(2), Some(Coerced::Missing));
+    assert_eq!(parser.violations(), ["id", "score", "ok"]);
+    let violations: Vec<_> = parser
+      .cell_violations()
+      .iter()
+      .map(|violation| (violation.line, violation.keyword.as_str(), violation.message.as_str()))
+      .collect();
+    assert_eq!(
+      violations,
+      [
+        (3, "type", "must be integer"),
+        (3, "maximum", "must be <= 10"),
+        (3, "required", "is missing")
+      ]
+    );
+
+    let mut state = StateWriter::new();
+    parser.save_state(&mut state);
+    let bytes = state.into_bytes();
+    let restored = CsvParser::load_state(&mut StateReader::new(&bytes).unwrap()).unwrap();
+    assert_eq!(restored.cell_violations(), parser.cell_violations());
+    assert!(restored.options.schema.is_some());
+
+    let mut parser = CsvParser::new(CsvParserOptions {
+      schema: parser.options.schema.clone(),
+      ..Default::default()
+    });
+    parser.parse_record(input, 0, 12).unwrap();
+    assert_eq!(
+      parser.parse_record(b"1,2,no,extra\n", 0, 13).unwrap_err().to_string(),
+      "Line 2: ok must be boolean"
+    );
+  }
+
+  #[test]
+  fn test_redact() {
+    let mut parser = CsvParser::new(CsvParserOptions {
+      redact: vec![
+        ("ssn".to_string(), Redaction::Hash),
+        ("email".to_string(), Redaction::Mask),
+        ("notes".to_string(), Redaction::Drop),
+      ],
+      redact_salt: "pepper".to_string(),
+      null_value: Some("NULL".to_string()),
+      ..Default::default()
+    });
+    let input = "id,ssn,email,notes\n1,123-45-6789,ann@x.io,secret\n2,NULL,bob,\n".as_bytes();
+    parser.parse_line(input, 0, 19).unwrap();
+    let row = parser.parse_line(input, 19, 50).unwrap().unwrap();
+    assert_eq!(row.len(), 3);
+    assert_eq!(row["id"], "1");
+    assert_eq!(row["ssn"], HashAlgorithm::Sha256.hex(b"pepper123-45-6789"));
+    assert_eq!(row["email"], "****x.io");
+    assert!(!row.contains_key("notes"));
+
+    // Null and empty cells stay as they are; short cells are masked whole
+    let row = parser.parse_line(input, 50, input.len()).unwrap().unwrap();
+    assert_eq!(row["ssn"], "NULL");
+    assert_eq!(row["email"], "***");
+  }
+
+  #[test]
+  fn test_line_filter() {
+    let input = b"level,msg\nINFO,ok\nERROR,disk\nWARN,\"ERROR\nlater\"\n";
+    let levels = |negate| {
+      let mut parser = CsvParser::new(CsvParserOptions {
+        line_filter: Some(LineFilter::new("^ERROR", negate).unwrap()),
+        ..Default::default()
+      });
+      [(0, 10), (10, 18), (18, 29), (29, input.len())]
+        .into_iter()
+        .filter_map(|(start, end)| parser.parse_line(input, start, end).unwrap())
+        .map(|row| row["level"].clone())
+        .collect::<Vec<_>>()
+    };
+
+    // The header line is kept although it does not match
+    assert_eq!(levels(false), ["ERROR"]);
+    assert_eq!(levels(true), ["INFO", "WARN"]);
+    assert!(LineFilter::new("(", false).is_err());
+  }
+
+  #[test]
+  fn test_extra_columns() {
+    let input = b"a\n1,2,3";
+    let parse = |extra_columns| {
+      let mut parser = CsvParser::new(CsvParserOptions {
+        extra_columns,
+        ..Default::default()
+      });
+      parser.parse_line(input, 0, 2).unwrap();
+      let cells = parser.parse_record(input, 2, input.len()).unwrap().unwrap();
+      let keys: Vec<String> = parser.row_fields(&cells).map(|(_, key, _)| key.into_owned()).collect();
+      let extra = parser
+        .extra_cells(&cells)
+        .map(|(key, cells)| (key.to_string(), cells.len()));
+      (keys, extra)
+    };
+
+    assert_eq!(
+      parse(ExtraColumns::default()),
+      (vec!["a".into(), "_1".into(), "_2".into()], None)
+    );
+    assert_eq!(
+      parse(ExtraColumns::Prefix("col".to_string())),
+      (vec!["a".into(), "col1".into(), "col2".into()], None)
+    );
+    assert_eq!(
+      parse(ExtraColumns::Collect("_rest".to_string())),
+      (vec!["a".into()], Some(("_rest".to_string(), 2)))
+    );
+    assert_eq!(parse(ExtraColumns::Drop), (vec!["a".into()], None));
+  }
+
+  #[test]
+  fn test_skip_empty_fields() {
+    let mut parser = CsvParser::new(CsvParserOptions {
+      skip_empty_fields: true,
+      ..Default::default()
+    });
+    let input = b"a,b,c\n,\"\",x";
+    parser.parse_line(input, 0, 6).unwrap();
+    let row = parser.parse_line(input, 6, input.len()).unwrap();
+    assert_eq!(
+      row,
+      Some(HashMap::from([("c".to_string(), "x".to_string())]))
+    );
+  }
+
+  #[test]
+  fn test_cell_items() {
+    let mut parser = CsvParser::new(CsvParserOptions {
+      array_columns: vec![("tags".to_string(), "; ".to_string())],
+      ..Default::default()
+    });
+    let input = b"id,tags\n1,\"red; green;; blue\"\n2,";
+    parser.parse_record(input, 0, 8).unwrap();
+    assert_eq!(parser.array_separator("tags"), Some("; "));
+    assert_eq!(parser.array_separator("id"), None);
+
+    let cells = parser.parse_record(input, 8, 30).unwrap().unwrap();
+    assert_eq!(
+      parser.cell_items(input, cells[1], "; "),
+      vec![&b"red"[..], b"green;", b"blue"]
+    );
+    let cells = parser.parse_record(input, 30, input.len()).unwrap().unwrap();
+    assert!(parser.cell_items(input, cells[1], "; ").is_empty());
+  }
+
+  #[test]
+  fn test_json_values() {
+    let mut parser = CsvParser::new(CsvParserOptions {
+      json_columns: vec!["meta".to_string()],
+      ..Default::default()
+    });
+    assert!(parser.is_json_column("meta"));
+    assert!(!parser.is_json_column("id"));
+    assert_eq!(
+      parser.json_value("meta", r#"{"b":1,"a":[true]}"#).unwrap(),
+      Some(serde_json::json!({"b": 1, "a": [true]}))
+    );
+    assert_eq!(
+      parser.json_value("meta", " ").unwrap(),
+      Some(serde_json::Value::Null)
+    );
+    let error = parser.json_value("meta", "{oops").unwrap_err();
+    assert!(error
+      .to_string()
+      .starts_with("Invalid JSON in column \"meta\": key must be a string"));
+
+    parser.options.malformed_json = MalformedJson::Keep;
+    assert_eq!(parser.json_value("meta", "{oops").unwrap(), None);
+  }
+
+  #[test]
+  fn test_line_bytes() {
+    let parser = CsvParser::new(CsvParserOptions::default());
+    let input = b"a,b\r\n\"1\n2\",3\n4\r";
+    assert_eq!(parser.line_bytes(input, 0, 5), b"a,b");
+    assert_eq!(parser.line_bytes(input, 5, 13), b"\"1\n2\",3");
+    assert_eq!(parser.line_bytes(input, 13, input.len()), b"4");
+  }
+
+  #[test]
+  fn test_reset() {
+    let options = CsvParserOptions {
+      sep_directive: true,
+      ..Default::default()
+    };
+    let mut parser = CsvParser::new(options);
+
+    let input = b"sep=;\na;b\n1;2";
+    parser.parse_line(input, 0, 6).unwrap();
+    parser.parse_line(input, 6, 10).unwrap();
+    parser.parse_line(input, 10, input.len()).unwrap();
+
+    parser.reset();
+    assert_eq!(parser.headers, None);
+    assert_eq!(parser.options.separator, b',');
+
+    let input = b"x,y\n3,4";
+    let result = parser.parse_line(input, 0, 4).unwrap();
+    assert!(result.is_none());
+
+    let result = parser.parse_line(input, 4, input.len()).unwrap();
+    assert_eq!(
+      result.expect("Failed to parse line"),
+      HashMap::from([
+        ("x".to_string(), "3".to_string()),
+        ("y".to_string(), "4".to_string())
+      ])
+    );
+  }
+
+  #[test]
+  fn test_state() {
+    let mut parser = CsvParser::new(CsvParserOptions {
+      skip_comments: Some(SkipComments::Boolean(true)),
+      ..Default::default()
+    });
+    assert!(!parser.headers_resolved());
+
+    let input = b"# \"\na,b\n1,\"x\ny";
+    // A quote in a comment opens nothing
+    assert!(!parser.in_quote(&input[..4]));
+    parser.parse_line(input, 0, 4).unwrap();
+    parser.parse_line(input, 4, 8).unwrap();
+    assert!(parser.headers_resolved());
+    assert_eq!(parser.lines(), 2);
+    assert!(parser.in_quote(&input[8..]));
+    assert!(!parser.in_quote(&input[8..10]));
+  }
+
+  #[test]
+  fn test_pass_header_row() {
+    let mut parser = CsvParser::new(CsvParserOptions {
+      pass_header_row: true,
+      strict: Strict::Error,
+      ..Default::default()
+    });
+    let input = b"id,name\n1,Ann\n";
+    assert_eq!(
+      parser.parse_line(input, 0, 8).unwrap(),
+      Some(HashMap::from([
+        ("id".to_string(), "id".to_string()),
+        ("name".to_string(), "name".to_string()),
+      ]))
+    );
+    assert_eq!(
+      parser.parse_line(input, 8, input.len()).unwrap(),
+      Some(HashMap::from([
+        ("id".to_string(), "1".to_string()),
+        ("name".to_string(), "Ann".to_string()),
+      ]))
+    );
+  }
+
+  #[test]
+  fn test_record_terminator() {
+    let options = CsvParserOptions {
+      record_terminator: RecordTerminator::Any,
+      ..Default::default()
+    };
+    let parser = CsvParser::new(options.clone());
+    let input = b"a,b\r1,\"x\ry\"\r\r\n2,3\r";
+    assert_eq!(parser.row_end(input, 0), Some(4));
+    assert_eq!(parser.row_end(input, 4), Some(12));
+    assert_eq!(parser.row_end(input, 12), Some(14));
+    // The last `\r` could start a `\r\n`
+    assert_eq!(parser.row_end(input, 14), None);
+
+    let mut parser = CsvParser::new(options);
+    let mut rows = Vec::new();
+    let mut start = 0;
+    while let Some(end) = parser.row_end(input, start) {
+      rows.extend(parser.parse_line(input, start, end).unwrap());
+      start = end;
+    }
+    rows.extend(parser.parse_line(input, start, input.len()).unwrap());
+    assert_eq!(
+      rows,
+      [
+        HashMap::from([
+          ("a".to_string(), "1".to_string()),
+          ("b".to_string(), "x\ry".to_string())
+        ]),
+        HashMap::from([
+          ("a".to_string(), "2".to_string()),
+          ("b".to_string(), "3".to_string())
+        ]),
+      ]
+    );
+    assert_eq!(parser.lines(), 5);
+  }
+
+  #[test]
+  fn test_empty_headers() {
+    let input = b"a,,_,d\n1,2,3,4\n";
+    let mut parser = CsvParser::new(CsvParserOptions::default());
+    assert!(parser.parse_line(input, 0, 7).unwrap().is_none());
+    assert_eq!(parser.dropped_columns(), [1, 2]);
+    assert_eq!(
+      parser.parse_line(input, 7, input.len()).unwrap().unwrap(),
+      HashMap::from([
+        ("a".to_string(), "1".to_string()),
+        ("d".to_string(), "4".to_string())
+      ])
+    );
+
+    let mut parser = CsvParser::new(CsvParserOptions {
+      empty_headers: EmptyHeaders::KeepPositional,
+      ..Default::default()
+    });
+    assert!(parser.parse_line(input, 0, 7).unwrap().is_none());
+    assert!(parser.dropped_columns().is_empty());
+    let row = parser.parse_line(input, 7, input.len()).unwrap().unwrap();
+    assert_eq!(row.get("_1").map(String::as_str), Some("2"));
+    assert_eq!(row.get("_2").map(String::as_str), Some("3"));
+
+    let mut parser = CsvParser::new(CsvParserOptions {
+      empty_headers: EmptyHeaders::Error,
+      ..Default::default()
+    });
+    let error = parser.parse_line(input, 0, 7).unwrap_err();
+    assert_eq!(error.to_string(), "Column 2 has an empty or \"_\" header");
+  }
+
+  #[test]
+  fn test_quoted_headers() {
+    let input = b"\"Revenue, net\",\"Cost\nQ1\",\"Say \"\"hi\"\"\"\n1,2,3\n";
+    let mut parser = CsvParser::new(CsvParserOptions::default());
+    let end = parser.row_end(input, 0).unwrap();
+    assert_eq!(&input[end..], b"1,2,3\n");
+    assert!(parser.parse_line(input, 0, end).unwrap().is_none());
+    assert_eq!(
+      parser.headers.as_deref().unwrap(),
+      ["Revenue, net", "Cost\nQ1", "Say \"hi\""]
+    );
+    let row = parser.parse_line(input, end, input.len()).unwrap().unwrap();
+    assert_eq!(row.get("Revenue, net").map(String::as_str), Some("1"));
+    assert_eq!(row.get("Cost\nQ1").map(String::as_str), Some("2"));
+    assert_eq!(parser.lines(), 3);
+  }
 }
@@ -0,0 +1,748 @@
+use color_eyre::eyre::{eyre, Result};
+use std::io::Read;
+
+use crate::decoder::Decoder;
+use crate::index::RowLocation;
+use crate::parser::{
+  CellRange, CsvParser, CsvParserOptions, RaggedRow, RecordTokenizer, Utf8Validation,
+};
+use crate::state::{StateReader, StateWriter};
+
+/// Incremental CSV parser over byte chunks, for use from Rust. Input is
+/// decoded and split into rows exactly as the Node bindings do it.
+pub struct Parser {
+  pub(crate) tokenizer: CsvParser,
+  pub(crate) input: Decoder,
+  // Start of the first row in the decoded input not yet returned
+  cursor: usize,
+  // Start of the row last returned by `next_cells`
+  row_start: usize,
+  // Decoded bytes dropped from the front of the input so far
+  consumed: u64,
+}
+
+impl Parser {
+  pub fn new(options: CsvParserOptions) -> Self {
+    let tokenizer = options.format.tokenizer();
+    Self::with_tokenizer(options, tokenizer)
+  }
+
+  /// A parser for another record format: `tokenizer` finds and splits the
+  /// records, while decoding, headers, comments and the other options work
+  /// as for CSV
+  pub fn with_tokenizer(options: CsvParserOptions, tokenizer: Box<dyn RecordTokenizer>) -> Self {
+    Self {
+      input: Decoder::new(options.encoding, options.detect_encoding),
+      tokenizer: CsvParser::with_tokenizer(options, tokenizer),
+      cursor: 0,
+      row_start: 0,
+      consumed: 0,
+    }
+  }
+
+  /// Adds a chunk of input and returns the records it completes. A row split
+  /// across chunks is returned once its newline arrives.
+  pub fn feed(&mut self, chunk: &[u8]) -> Result<Records<'_>> {
+    self.load(chunk, false)?;
+    Ok(Records {
+      parser: self,
+      at_end: false,
+    })
+  }
+
+  /// Ends the input and returns the remaining records, including a last row
+  /// without a trailing newline.
+  pub fn finish(&mut self) -> Result<Records<'_>> {
+    self.load(&[], true)?;
+    Ok(Records {
+      parser: self,
+      at_end: true,
+    })
+  }
+
+  /// The header names, once known
+  pub fn headers(&self) -> Option<&[String]> {
+    self.tokenizer.headers.as_deref()
+  }
+
+  /// Rows dropped as duplicates so far, with the `dedupe` option
+  pub fn duplicates(&self) -> u64 {
+    self.tokenizer.duplicates()
+  }
+
+  /// Rows left out so far for their width, with `Strict::Report`
+  pub fn ragged_rows(&self) -> &[RaggedRow] {
+    self.tokenizer.ragged_rows()
+  }
+
+  /// A snapshot of everything the parser holds: options, headers, buffered
+  /// input and the keys `dedupe` remembers. `from_state` resumes the parse
+  /// from it, e.g. on another thread or in another process.
+  pub fn save_state(&self) -> Vec<u8> {
+    let mut state = StateWriter::new();
+    self.tokenizer.save_state(&mut state);
+    self.input.save_state(&mut state);
+    for position in [self.cursor as u64, self.row_start as u64, self.consumed] {
+      state.u64(position);
+    }
+    state.into_bytes()
+  }
+
+  /// Resumes a parse from a `save_state` snapshot. A custom tokenizer is not
+  /// part of the snapshot; the parser continues with the one `format` names.
+  pub fn from_state(state: &[u8]) -> Result<Self> {
+    let mut state = StateReader::new(state)?;
+    let parser = Self {
+      tokenizer: CsvParser::load_state(&mut state)?,
+      input: Decoder::load_state(&mut state)?,
+      cursor: state.usize()?,
+      row_start: state.usize()?,
+      consumed: state.u64()?,
+    };
+    state.finish()?;
+    if parser.cursor > parser.input.utf8_buffer.len() || parser.row_start > parser.cursor {
+      return Err(eyre!("Invalid parser state"));
+    }
+    Ok(parser)
+  }
+
+  /// Clears buffered input, headers, line counters and remembered duplicate
+  /// keys so the parser can
+  /// read another file with the same options.
+  pub fn reset(&mut self) {
+    self.tokenizer.reset();
+    self.input.reset();
+    self.cursor = 0;
+    self.row_start = 0;
+    self.consumed = 0;
+  }
+
+  // Drops the rows already returned and decodes `chunk` after the rest
+  pub(crate) fn load(&mut self, chunk: &[u8], at_end: bool) -> Result<()> {
+    let parsed = std::mem::take(&mut self.cursor);
+    self.input.consume(parsed);
+    self.consumed += parsed as u64;
+    self.row_start = 0;
+    self.input.buffer.extend_from_slice(chunk);
+
+    self.tokenizer.set_input_ended(at_end);
+    let options = &self.tokenizer.options;
+    let validate = options.validate_utf8 == Utf8Validation::Fast && !options.raw;
+    self.input.decode(validate, at_end)
+  }
+
+  /// Byte range in the source of the row last returned by `next_cells`, for
+  /// UTF-8 input
+  pub(crate) fn row_location(&self) -> Option<RowLocation> {
+    let offset = self
+      .input
+      .source_offset(self.consumed + self.row_start as u64)?;
+    Some(RowLocation {
+      offset,
+      length: (self.cursor - self.row_start) as u64,
+    })
+  }
+
+  /// The decoded bytes of the row last returned by `next_cells`, including
+  /// its newline
+  pub(crate) fn row_bytes(&self) -> &[u8] {
+    &self.input.utf8_buffer[self.row_start..self.cursor]
+  }
+
+  // Parses the row at the cursor, skipping lines that produce no row. `None`
+  // once no complete row is left; at the end of input the rest is one row.
+  pub(crate) fn next_cells(&mut self, at_end: bool) -> Option<Result<Vec<CellRange>>> {
+    let buffer = &self.input.utf8_buffer;
+
+    loop {
+      let start = self.cursor;
+      if start >= buffer.len() {
+        return None;
+      }
+      let end = match self.tokenizer.row_end(buffer, start) {
+        Some(end) => end,
+        None if at_end => buffer.len(),
+        None => {
+          let buffered = buffer.len() - start + self.input.buffer.len();
+          let error = self.tokenizer.check_buffered(buffered).err()?;
+          // The partial row is dropped rather than buffered further
+          self.consumed += buffer.len() as u64;
+          self.input.clear();
+          self.cursor = 0;
+          return Some(Err(error));
+        }
+      };
+      self.row_start = start;
+      self.cursor = end;
+
+      match self.tokenizer.parse_record(buffer, start, end) {
+        Ok(Some(cells)) => return Some(Ok(cells)),
+        // No row to return (e.g., header line or comment)
+        Ok(None) => continue,
+        Err(e) => return Some(Err(e)),
+      }
+    }
+  }
+}
+
+/// Counts the records in `input` without building them. Header, comment and
+/// skipped lines are not counted; quoted newlines do not end a record.
+pub fn count_rows(mut input: impl Read, options: CsvParserOptions) -> Result<u64> {
+  let mut parser = Parser::new(options);
+  let mut rows = 0;
+  let mut chunk = vec![0; 64 * 1024];
+
+  loop {
+    let read = input.read(&mut chunk)?;
+    let at_end = read == 0;
+    parser.load(&chunk[..read], at_end)?;
+    while let Some(cells) = parser.next_cells(at_end) {
+      cells?;
+      rows += 1;
+    }
+    if at_end {
+      return Ok(rows);
+    }
+  }
+}
+
+/// Byte offsets in `input` where records start: safe points to split it
+/// at, since quoted newlines do not end a record. Every line outside quotes
+/// counts, including the header, comments and skipped lines. With `spacing`
+/// above 0, a start is only kept at least that many bytes after the last one
+/// kept, for chunks of about that size.
+///
+/// Input is scanned as it is, so offsets are those of the raw bytes; it must
+/// be in an ASCII-compatible encoding, and `separator` is not detected.
+pub fn record_boundaries(
+  mut input: impl Read,
+  options: CsvParserOptions,
+  spacing: u64,
+) -> Result<Vec<u64>> {
+  let tokenizer = options.format.tokenizer();
+  let mut skip_lines = options.skip_lines.unwrap_or(0).max(0);
+  let mut boundaries: Vec<u64> = Vec::new();
+  let mut keep = |start: u64| {
+    if boundaries.last().is_none_or(|&last| start - last >= spacing) {
+      boundaries.push(start);
+    }
+  };
+
+  let mut buffer = Vec::new();
+  // Offset of `buffer[0]` in the input
+  let mut offset = 0;
+  let mut chunk = vec![0; 64 * 1024];
+  loop {
+    let read = input.read(&mut chunk)?;
+    if read == 0 {
+      if !buffer.is_empty() {
+        keep(offset);
+      }
+      return Ok(boundaries);
+    }
+    buffer.extend_from_slice(&chunk[..read]);
+
+    let mut start = 0;
+    loop {
+      let end = if skip_lines > 0 {
+        // A skipped line ends at its line break, whatever quotes it holds
+        let end = (start..buffer.len()).find(|&i| options.ends_line(&buffer, i));
+        end.map(|i| i + 1)
+      } else {
+        tokenizer.record_end(&options, &buffer, start)
+      };
+      let Some(end) = end else {
+        break;
+      };
+      keep(offset + start as u64);
+      skip_lines -= (skip_lines > 0) as i64;
+      start = end;
+    }
+    buffer.drain(..start);
+    offset += start as u64;
+  }
+}
+
+/// The records available after a `feed` or `finish` call. Records that are
+/// not consumed stay buffered and come back from the next call.
+pub struct Records<'a> {
+  parser: &'a mut Parser,
+  at_end: bool,
+}
+
+impl Records<'_> {
+  /// The header names, once known
+  pub fn headers(&self) -> Option<&[String]> {
+    self.parser.headers()
+  }
+}
+
+impl Iterator for Records<'_> {
+  type Item = Result<Record>;
+
+  fn next(&mut self) -> Option<Result<Record>> {
+    let parser = &mut *self.parser;
+    Some(
+      parser
+        .next_cells(self.at_end)?
+        .and_then(|cells| record(&parser.tokenizer, &parser.input.utf8_buffer, &cells)),
+    )
+  }
+}
+
+pub(crate) fn record(tokenizer: &CsvParser, buffer: &[u8], cells: &[CellRange]) -> Result<Record> {
+  let value = |cell| Ok(tokenizer.cell_value(buffer, cell)?.into_owned());
+  let fields = tokenizer
+    .row_fields(cells)
+    .filter(|&(_, _, cell)| !tokenizer.is_null(buffer, cell))
+    .map(|(_, key, cell)| Ok((key.into_owned(), value(cell)?)))
+    .collect::<Result<_>>()?;
+  let extra = match tokenizer.extra_cells(cells) {
+    Some((key, cells)) => Some((
+      key.to_string(),
+      cells
+        .iter()
+        .filter(|&&cell| !tokenizer.is_null(buffer, cell))
+        .map(|&cell| value(cell))
+        .collect::<Result<_>>()?,
+    )),
+    None => None,
+  };
+  Ok(Record { fields, extra })
+}
+
+/// One row: cell values keyed by header, in column order
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Record {
+  fields: Vec<(String, String)>,
+  extra: Option<(String, Vec<String>)>,
+}
+
+impl Record {
+  /// The value stored under `key`
+  pub fn get(&self, key: &str) -> Option<&str> {
+    self
+      .fields
+      .iter()
+      .find(|(k, _)| k == key)
+      .map(|(_, value)| value.as_str())
+  }
+
+  /// The keys and values of the row, in column order
+  pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+    self
+      .fields
+      .iter()
+      .map(|(key, value)| (key.as_str(), value.as_str()))
+  }
+
+  /// Replaces the value under `key`, or adds it after the other keys
+  pub fn set(&mut self, key: &str, value: impl Into<String>) {
+    match self.fields.iter_mut().find(|(k, _)| k == key) {
+      Some((_, old)) => *old = value.into(),
+      None => self.fields.push((key.to_string(), value.into())),
+    }
+  }
+
+  /// Removes `key`, returning its value
+  pub fn remove(&mut self, key: &str) -> Option<String> {
+    let index = self.fields.iter().position(|(k, _)| k == key)?;
+    Some(self.fields.remove(index).1)
+  }
+
+  pub fn len(&self) -> usize {
+    self.fields.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.fields.is_empty()
+  }
+
+  /// The key and values of the cells beyond the headers, when
+  /// `ExtraColumns::Collect` gathers them and the row has any
+  pub fn extra(&self) -> Option<(&str, &[String])> {
+    self
+      .extra
+      .as_ref()
+      .map(|(key, values)| (key.as_str(), values.as_slice()))
+  }
+
+  pub fn into_fields(self) -> Vec<(String, String)> {
+    self.fields
+  }
+}
+
+impl From<Vec<(String, String)>> for Record {
+  fn from(fields: Vec<(String, String)>) -> Self {
+    Self {
+      fields,
+      extra: None,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::decoder::TextEncoding;
+  use crate::parser::{SkipComments, Strict, UnterminatedQuote};
+  use bumpalo::Bump;
+
+  fn collect(records: Records) -> Vec<Vec<(String, String)>> {
+    records.map(|r| r.unwrap().into_fields()).collect()
+  }
+
+  fn row(fields: &[(&str, &str)]) -> Vec<(String, String)> {
+    fields
+      .iter()
+      .map(|(k, v)| (k.to_string(), v.to_string()))
+      .collect()
+  }
+
+  #[test]
+  fn test_rows_split_across_chunks() {
+    let mut parser = Parser::new(CsvParserOptions::default());
+
+    assert!(collect(parser.feed(b"a,b\n1,\"x\n").unwrap()).is_empty());
+    assert_eq!(
+      collect(parser.feed(b"y\"\n2,3").unwrap()),
+      vec![row(&[("a", "1"), ("b", "x\ny")])]
+    );
+    assert_eq!(
+      collect(parser.finish().unwrap()),
+      vec![row(&[("a", "2"), ("b", "3")])]
+    );
+    assert_eq!(
+      parser.headers(),
+      Some(&["a".to_string(), "b".to_string()][..])
+    );
+  }
+
+  // Pipe-separated cells where `\|` is a literal pipe
+  struct PipeEscaped;
+
+  impl RecordTokenizer for PipeEscaped {
+    fn record_end(&self, _: &CsvParserOptions, buffer: &[u8], start: usize) -> Option<usize> {
+      let newline = buffer[start..].iter().position(|&b| b == b'\n')?;
+      Some(start + newline + 1)
+    }
+
+    fn split(
+      &mut self,
+      _: &CsvParserOptions,
+      buffer: &[u8],
+      start: usize,
+      end: usize,
+      cells: &mut Vec<CellRange>,
+    ) -> Result<bool> {
+      let mut cell = CellRange::empty(start);
+      let mut i = start;
+      while i < end {
+        match buffer[i] {
+          b'\\' => {
+            cell.escaped = true;
+            i += 1;
+          }
+          b'|' => {
+            cells.push(CellRange { end: i, ..cell });
+            cell = CellRange::empty(i + 1);
+          }
+          _ => {}
+        }
+        i += 1;
+      }
+      cells.push(CellRange { end, ..cell });
+      Ok(true)
+    }
+
+    fn unescape<'a>(&self, _: &CsvParserOptions, bytes: &[u8], arena: &'a Bump) -> &'a [u8] {
+      let mut result = bumpalo::collections::Vec::with_capacity_in(bytes.len(), arena);
+      let mut bytes = bytes.iter();
+      while let Some(&byte) = bytes.next() {
+        result.push(if byte == b'\\' {
+          *bytes.next().unwrap_or(&byte)
+        } else {
+          byte
+        });
+      }
+      result.into_bump_slice()
+    }
+  }
+
+  #[test]
+  fn test_custom_tokenizer() {
+    let options = CsvParserOptions {
+      skip_comments: Some(SkipComments::Boolean(true)),
+      ..Default::default()
+    };
+    let mut parser = Parser::with_tokenizer(options, Box::new(PipeEscaped));
+
+    let mut rows = collect(parser.feed(b"#Version: 1.0\na|b\nx\\|y,\"|").unwrap());
+    rows.extend(collect(parser.finish().unwrap()));
+    assert_eq!(rows, vec![row(&[("a", "x|y,\""), ("b", "")])]);
+  }
+
+  #[test]
+  fn test_resume_from_state() {
+    let mut parser = Parser::new(CsvParserOptions {
+      separator: b';',
+      dedupe: Some(Default::default()),
+      ..Default::default()
+    });
+    let first = collect(parser.feed(b"a;b\n1;x\n1;x\n2;\"y").unwrap());
+    assert_eq!(first, vec![row(&[("a", "1"), ("b", "x")])]);
+
+    let mut resumed = Parser::from_state(&parser.save_state()).unwrap();
+    assert_eq!(
+      collect(resumed.feed(b"\n\"\n1;x\n3;z").unwrap()),
+      vec![row(&[("a", "2"), ("b", "y\n")])]
+    );
+    assert_eq!(
+      collect(resumed.finish().unwrap()),
+      vec![row(&[("a", "3"), ("b", "z")])]
+    );
+    assert_eq!(resumed.duplicates(), 2);
+
+    let state = parser.save_state();
+    assert!(Parser::from_state(&state[..state.len() - 1]).is_err());
+  }
+
+  #[test]
+  fn test_unread_records_are_kept() {
+    let mut parser = Parser::new(CsvParserOptions::default());
+
+    let first = parser.feed(b"a\n1\n2\n").unwrap().next().unwrap().unwrap();
+    assert_eq!(first.get("a"), Some("1"));
+    assert_eq!(
+      collect(parser.feed(b"3\n").unwrap()),
+      vec![row(&[("a", "2")]), row(&[("a", "3")])]
+    );
+  }
+
+  #[test]
+  fn test_bom_and_comments() {
+    let mut parser = Parser::new(CsvParserOptions {
+      skip_comments: Some(crate::parser::SkipComments::Boolean(true)),
+      ..Default::default()
+    });
+
+    assert_eq!(
+      collect(parser.feed(b"\xEF\xBB\xBFa\n# \"note\n1\n").unwrap()),
+      vec![row(&[("a", "1")])]
+    );
+  }
+
+  #[test]
+  fn test_utf32_and_ebcdic() {
+    let parse = |encoding: Option<&str>, input: &[u8]| {
+      let mut parser = Parser::new(CsvParserOptions {
+        encoding: encoding.map(|label| TextEncoding::for_label(label).unwrap()),
+        ..Default::default()
+      });
+      let mut rows = Vec::new();
+      // One byte at a time, so BOMs and code units are split
+      for byte in input {
+        rows.extend(collect(parser.feed(&[*byte]).unwrap()));
+      }
+      rows.extend(collect(parser.finish().unwrap()));
+      rows
+    };
+
+    let utf32: Vec<u8> = "\u{FEFF}a,b\n1,é\n"
+      .chars()
+      .flat_map(|c| (c as u32).to_le_bytes())
+      .collect();
+    assert_eq!(parse(None, &utf32), vec![row(&[("a", "1"), ("b", "é")])]);
+    let utf32: Vec<u8> = "a\n😀\n"
+      .chars()
+      .flat_map(|c| (c as u32).to_be_bytes())
+      .collect();
+    assert_eq!(parse(Some("utf-32be"), &utf32), vec![row(&[("a", "😀")])]);
+
+    // `a,b` NL `1,\u{E9}` NL in IBM037
+    let ebcdic = b"\x81\x6B\x82\x15\xF1\x6B\x51\x15";
+    assert_eq!(
+      parse(Some("ibm037"), ebcdic),
+      vec![row(&[("a", "1"), ("b", "é")])]
+    );
+    assert_eq!(
+      parse(Some("latin1"), b"a\n\xE9\n"),
+      vec![row(&[("a", "é")])]
+    );
+
+    // Multi-byte characters split across chunks
+    assert_eq!(
+      parse(Some("shift_jis"), b"\x96\xbc\x91\x4f\n\x93\x8c\x8b\x9e\n"),
+      vec![row(&[("名前", "東京")])]
+    );
+
+    assert!(TextEncoding::for_label("iso-2022-jp").is_err());
+    assert!(TextEncoding::for_label("klingon").is_err());
+  }
+
+  #[test]
+  fn test_detect_encoding() {
+    let parse = |input: &[u8]| {
+      let mut parser = Parser::new(CsvParserOptions {
+        detect_encoding: true,
+        ..Default::default()
+      });
+      let mut rows = collect(parser.feed(input).unwrap());
+      rows.extend(collect(parser.finish().unwrap()));
+      (rows, parser.input.encoding().name())
+    };
+
+    let (rows, encoding) = parse("name,city\nJosé,Zürich\n".as_bytes());
+    assert_eq!(rows, vec![row(&[("name", "José"), ("city", "Zürich")])]);
+    assert_eq!(encoding, "UTF-8");
+
+    let (rows, encoding) = parse(b"name,city\nJos\xe9,Z\xfcrich\nFran\xe7ois,Gen\xe8ve\n");
+    assert_eq!(rows[0], row(&[("name", "José"), ("city", "Zürich")]));
+    assert_eq!(encoding, "windows-1252");
+
+    let (rows, encoding) =
+      parse(b"\x96\xbc\x91\x4f,\x93\x73\x8e\x73\n\x8e\x52\x93\x63,\x93\x8c\x8b\x9e\n");
+    assert_eq!(rows, vec![row(&[("名前", "山田"), ("都市", "東京")])]);
+    assert_eq!(encoding, "Shift_JIS");
+
+    // A BOM still decides
+    let (_, encoding) = parse(b"\xFF\xFEa\x00");
+    assert_eq!(encoding, "UTF-16LE");
+  }
+
+  #[test]
+  fn test_reset() {
+    let mut parser = Parser::new(CsvParserOptions::default());
+    assert_eq!(collect(parser.feed(b"a\n1\n2").unwrap()).len(), 1);
+
+    parser.reset();
+    assert_eq!(
+      collect(parser.feed(b"b\n3\n").unwrap()),
+      vec![row(&[("b", "3")])]
+    );
+  }
+
+  #[test]
+  fn test_max_buffered_bytes() {
+    let mut parser = Parser::new(CsvParserOptions {
+      max_buffered_bytes: Some(8),
+      ..Default::default()
+    });
+
+    let mut records = parser.feed(b"a\n1\n\"open").unwrap();
+    assert_eq!(records.next().unwrap().unwrap().get("a"), Some("1"));
+    assert!(records.next().is_none());
+
+    let mut records = parser.feed(b" and on").unwrap();
+    let error = records.next().unwrap().unwrap_err();
+    assert!(error.to_string().starts_with(
+      "Buffered 12 bytes without completing a row, more than maxBufferedBytes (8)"
+    ));
+    assert!(records.next().is_none());
+    assert_eq!(collect(parser.feed(b"2\n").unwrap()), vec![row(&[("a", "2")])]);
+  }
+
+  #[test]
+  fn test_count_rows() {
+    let options = CsvParserOptions {
+      skip_comments: Some(crate::parser::SkipComments::Boolean(true)),
+      skip_lines: Some(1),
+      ..Default::default()
+    };
+    let input = "title\na,b\n# comment\n1,\"x\ny\"\n2,3";
+
+    assert_eq!(count_rows(input.as_bytes(), options).unwrap(), 2);
+  }
+
+  #[test]
+  fn test_record_boundaries() {
+    let options = CsvParserOptions {
+      skip_lines: Some(1),
+      ..Default::default()
+    };
+    // The skipped line's quote does not open a cell
+    let input = "ti\"tle\na,b\n1,\"x\ny\"\n2,3";
+    assert_eq!(
+      record_boundaries(input.as_bytes(), options.clone(), 0).unwrap(),
+      [0, 7, 11, 19]
+    );
+    assert_eq!(
+      record_boundaries(input.as_bytes(), options, 10).unwrap(),
+      [0, 11]
+    );
+    assert!(record_boundaries(&b""[..], CsvParserOptions::default(), 0)
+      .unwrap()
+      .is_empty());
+  }
+
+  #[test]
+  fn test_dedupe() {
+    let mut parser = Parser::new(CsvParserOptions {
+      dedupe: Some(crate::dedupe::DedupeOptions {
+        keys: vec!["id".to_string()],
+        bloom: None,
+      }),
+      ..Default::default()
+    });
+
+    let rows = collect(parser.feed(b"id,v\n1,a\n2,b\n1,c\n2,\"b\"\n").unwrap());
+    assert_eq!(
+      rows,
+      vec![
+        row(&[("id", "1"), ("v", "a")]),
+        row(&[("id", "2"), ("v", "b")])
+      ]
+    );
+    assert_eq!(parser.duplicates(), 2);
+  }
+
+  #[test]
+  fn test_unterminated_quote() {
+    let input = b"a,b\n1,2\n3,\"abc\n4,5";
+    let options = |policy| CsvParserOptions {
+      on_unterminated_quote: policy,
+      ..Default::default()
+    };
+
+    let mut parser = Parser::new(options(UnterminatedQuote::Emit));
+    let mut rows = collect(parser.feed(input).unwrap());
+    rows.extend(collect(parser.finish().unwrap()));
+    assert_eq!(rows[1], row(&[("a", "3"), ("b", "\"abc\n4,5")]));
+
+    let mut parser = Parser::new(options(UnterminatedQuote::Drop));
+    let mut rows = collect(parser.feed(input).unwrap());
+    rows.extend(collect(parser.finish().unwrap()));
+    assert_eq!(rows, vec![row(&[("a", "1"), ("b", "2")])]);
+
+    let mut parser = Parser::new(options(UnterminatedQuote::Error));
+    let rows = collect(parser.feed(input).unwrap());
+    assert_eq!(rows, vec![row(&[("a", "1"), ("b", "2")])]);
+    let error = parser.finish().unwrap().next().unwrap().unwrap_err();
+    assert_eq!(error.to_string(), "Quoted field opened on line 3 is never closed");
+  }
+
+  #[test]
+  fn test_strict_report() {
+    let mut parser = Parser::new(CsvParserOptions {
+      strict: Strict::Report,
+      ..Default::default()
+    });
+    let rows = collect(parser.feed(b"a,b\n1,2\n3\n\"x\ny\",4,5\n").unwrap());
+    assert_eq!(rows, vec![row(&[("a", "1"), ("b", "2")])]);
+
+    // Ragged rows carry over to a resumed parser
+    let mut resumed = Parser::from_state(&parser.save_state()).unwrap();
+    let rows = collect(resumed.feed(b"6,7\n8,9,10").unwrap());
+    assert_eq!(rows, vec![row(&[("a", "6"), ("b", "7")])]);
+    assert!(collect(resumed.finish().unwrap()).is_empty());
+    let ragged = |line, actual| RaggedRow {
+      line,
+      expected: 2,
+      actual,
+    };
+    assert_eq!(
+      resumed.ragged_rows(),
+      [ragged(3, 1), ragged(4, 3), ragged(7, 3)]
+    );
+  }
+}
@@ -0,0 +1,292 @@
+use color_eyre::eyre::{eyre, Result};
+
+use crate::parser::CsvParserOptions;
+use crate::reader::{Parser, Record};
+
+/// The shape rows are reshaped to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Shape {
+  /// One row per id and wide column, holding the column name under `key`
+  /// and its value under `value`
+  #[default]
+  Long,
+  /// One row per run of rows sharing their ids, with a column per `key`
+  /// value holding the matching `value`
+  Wide,
+}
+
+#[derive(Clone)]
+pub struct ReshapeOptions {
+  pub to: Shape,
+  /// Headers of the columns that identify a row; they are copied as they are
+  pub ids: Vec<String>,
+  /// Header of the long column holding wide column names; `"key"` by default
+  pub key: String,
+  /// Header of the long column holding the values; `"value"` by default
+  pub value: String,
+  /// The wide columns. To long, the columns to unpivot (every column but the
+  /// ids by default); to wide, the columns each row holds, in order (the keys
+  /// of its rows by default).
+  pub columns: Option<Vec<String>>,
+  pub parser: CsvParserOptions,
+}
+
+impl Default for ReshapeOptions {
+  fn default() -> Self {
+    Self {
+      to: Shape::default(),
+      ids: Vec::new(),
+      key: "key".to_string(),
+      value: "value".to_string(),
+      columns: None,
+      parser: CsvParserOptions::default(),
+    }
+  }
+}
+
+/// Reshapes rows between wide and long form while parsing, chunk by chunk.
+///
+/// To long, each row becomes one row per wide column, so a row per month
+/// turns `region,jan,feb` into `region,key,value` rows. Missing and null
+/// cells become empty values.
+///
+/// To wide, consecutive rows with the same ids are merged into one row, with
+/// the value of each row under the name in its key column; a key repeated in
+/// a run keeps its last value. Only the current run is held in memory, so the
+/// input should be grouped by its ids. With `columns` set, every row holds
+/// exactly those columns, empty where no key matched, and other keys are
+/// left out.
+pub struct Reshaper {
+  parser: Parser,
+  options: ReshapeOptions,
+  // Set once the headers are checked; to long, the columns to unpivot
+  columns: Option<Vec<String>>,
+  // To wide, the current run of rows
+  run: Option<Run>,
+}
+
+// Rows sharing their ids, merged into one wide row
+struct Run {
+  ids: Vec<String>,
+  // Key and value pairs, in order of first appearance
+  pairs: Vec<(String, String)>,
+}
+
+impl Reshaper {
+  pub fn new(options: ReshapeOptions) -> Self {
+    Self {
+      parser: Parser::new(options.parser.clone()),
+      options,
+      columns: None,
+      run: None,
+    }
+  }
+
+  /// Adds a chunk of input and returns the reshaped rows it completes
+  pub fn feed(&mut self, chunk: &[u8]) -> Result<Vec<Record>> {
+    let records = self.parser.feed(chunk)?.collect::<Result<Vec<_>>>()?;
+    self.reshape(records)
+  }
+
+  /// Ends the input and returns the remaining reshaped rows
+  pub fn finish(&mut self) -> Result<Vec<Record>> {
+    let records = self.parser.finish()?.collect::<Result<Vec<_>>>()?;
+    let mut rows = self.reshape(records)?;
+    rows.extend(self.run.take().map(|run| self.wide_row(run)));
+    Ok(rows)
+  }
+
+  fn reshape(&mut self, records: Vec<Record>) -> Result<Vec<Record>> {
+    if self.columns.is_none() && !records.is_empty() {
+      self.columns = Some(self.check_columns()?);
+    }
+
+    let mut rows = Vec::new();
+    for record in records {
+      let ids: Vec<String> = self
+        .options
+        .ids
+        .iter()
+        .map(|id| record.get(id).unwrap_or_default().to_string())
+        .collect();
+
+      match self.options.to {
+        Shape::Long => {
+          for column in self.columns.as_deref().unwrap_or_default() {
+            let mut fields = self.id_fields(&ids);
+            fields.push((self.options.key.clone(), column.clone()));
+            let value = record.get(column).unwrap_or_default().to_string();
+            fields.push((self.options.value.clone(), value));
+            rows.push(Record::from(fields));
+          }
+        }
+        Shape::Wide => {
+          let key = record
+            .get(&self.options.key)
+            .unwrap_or_default()
+            .to_string();
+          let value = record
+            .get(&self.options.value)
+            .unwrap_or_default()
+            .to_string();
+          if self.run.as_ref().is_some_and(|run| run.ids != ids) {
+            let run = self.run.take().unwrap();
+            rows.push(self.wide_row(run));
+          }
+          let run = self.run.get_or_insert_with(|| Run {
+            ids,
+            pairs: Vec::new(),
+          });
+          match run.pairs.iter_mut().find(|(name, _)| *name == key) {
+            Some(pair) => pair.1 = value,
+            None => run.pairs.push((key, value)),
+          }
+        }
+      }
+    }
+    Ok(rows)
+  }
+
+  // Checks the input has the columns named in the options, and returns the
+  // columns to unpivot when reshaping to long
+  fn check_columns(&self) -> Result<Vec<String>> {
+    let headers = self.parser.headers().unwrap_or_default();
+    let options = &self.options;
+    let mut required = options.ids.clone();
+    match options.to {
+      Shape::Long => required.extend(options.columns.iter().flatten().cloned()),
+      Shape::Wide => required.extend([options.key.clone(), options.value.clone()]),
+    }
+    if let Some(missing) = required.iter().find(|column| !headers.contains(column)) {
+      return Err(eyre!("Column {} not found", missing));
+    }
+
+    Ok(match (options.to, &options.columns) {
+      (Shape::Long, Some(columns)) => columns.clone(),
+      // Blank and `_` headers are not columns
+      (Shape::Long, None) => headers
+        .iter()
+        .filter(|header| !header.is_empty() && *header != "_" && !options.ids.contains(header))
+        .cloned()
+        .collect(),
+      (Shape::Wide, _) => Vec::new(),
+    })
+  }
+
+  fn id_fields(&self, ids: &[String]) -> Vec<(String, String)> {
+    self
+      .options
+      .ids
+      .iter()
+      .cloned()
+      .zip(ids.iter().cloned())
+      .collect()
+  }
+
+  fn wide_row(&self, mut run: Run) -> Record {
+    let mut fields = self.id_fields(&run.ids);
+    match &self.options.columns {
+      Some(columns) => fields.extend(columns.iter().map(|column| {
+        let value = match run.pairs.iter_mut().find(|(key, _)| key == column) {
+          Some((_, value)) => std::mem::take(value),
+          None => String::new(),
+        };
+        (column.clone(), value)
+      })),
+      None => fields.extend(run.pairs),
+    }
+    Record::from(fields)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn reshape(input: &str, options: ReshapeOptions) -> Result<Vec<Vec<(String, String)>>> {
+    let mut reshaper = Reshaper::new(options);
+    let (head, tail) = input.split_at(input.len() / 2);
+    let mut rows = reshaper.feed(head.as_bytes())?;
+    rows.extend(reshaper.feed(tail.as_bytes())?);
+    rows.extend(reshaper.finish()?);
+    Ok(rows.into_iter().map(Record::into_fields).collect())
+  }
+
+  fn lines(rows: &[Vec<(String, String)>]) -> Vec<String> {
+    rows
+      .iter()
+      .map(|row| {
+        let cells: Vec<_> = row
+          .iter()
+          .map(|(key, value)| format!("{}={}", key, value))
+          .collect();
+        cells.join(",")
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_to_long() {
+    let options = ReshapeOptions {
+      ids: vec!["region".to_string()],
+      key: "month".to_string(),
+      value: "sales".to_string(),
+      ..Default::default()
+    };
+    let rows = reshape("region,jan,feb\nnorth,1,2\nsouth,3", options.clone()).unwrap();
+    assert_eq!(
+      lines(&rows),
+      [
+        "region=north,month=jan,sales=1",
+        "region=north,month=feb,sales=2",
+        "region=south,month=jan,sales=3",
+        "region=south,month=feb,sales=",
+      ]
+    );
+
+    let options = ReshapeOptions {
+      columns: Some(vec!["feb".to_string()]),
+      ..options
+    };
+    let rows = reshape("region,jan,feb\nnorth,1,2\n", options).unwrap();
+    assert_eq!(lines(&rows), ["region=north,month=feb,sales=2"]);
+  }
+
+  #[test]
+  fn test_to_wide() {
+    let input = "region,month,sales\nnorth,jan,1\nnorth,feb,2\nnorth,feb,5\nsouth,mar,3\n";
+    let options = ReshapeOptions {
+      to: Shape::Wide,
+      ids: vec!["region".to_string()],
+      key: "month".to_string(),
+      value: "sales".to_string(),
+      ..Default::default()
+    };
+    let rows = reshape(input, options.clone()).unwrap();
+    assert_eq!(
+      lines(&rows),
+      ["region=north,jan=1,feb=5", "region=south,mar=3"]
+    );
+
+    let options = ReshapeOptions {
+      columns: Some(vec!["mar".to_string(), "jan".to_string()]),
+      ..options
+    };
+    let rows = reshape(input, options).unwrap();
+    assert_eq!(
+      lines(&rows),
+      ["region=north,mar=,jan=1", "region=south,mar=3,jan="]
+    );
+  }
+
+  #[test]
+  fn test_missing_column() {
+    let options = ReshapeOptions {
+      to: Shape::Wide,
+      ids: vec!["id".to_string()],
+      ..Default::default()
+    };
+    let error = reshape("id,key\n1,a\n", options).unwrap_err();
+    assert_eq!(error.to_string(), "Column value not found");
+  }
+}
@@ -0,0 +1,309 @@
+use color_eyre::eyre::{eyre, Result};
+
+use crate::compress::{Compression, Compressor};
+use crate::parser::CsvParserOptions;
+use crate::reader::Parser;
+use crate::transcode::Transcoder;
+use crate::writer::{CsvWriter, CsvWriterOptions, Encoder};
+
+#[derive(Clone, Default)]
+pub struct RewriteOptions {
+  /// Dialect of the input
+  pub parser: CsvParserOptions,
+  /// Encoding label of the input (e.g. `latin1`, `windows-1252`). By default
+  /// input is UTF-8, or UTF-16 when it starts with a BOM.
+  pub input_encoding: Option<String>,
+  /// Dialect of the output
+  pub writer: CsvWriterOptions,
+  /// Encoding label of the output; UTF-8 by default
+  pub output_encoding: Option<String>,
+  /// Start the output with a byte order mark, for UTF-8 and UTF-16 output
+  pub bom: bool,
+  /// Columns to keep, in output order. All columns by default.
+  pub columns: Option<Vec<String>>,
+  /// New names for columns in the header line, as (old, new) pairs
+  pub rename: Vec<(String, String)>,
+  /// Cell for columns in `columns` that the input lacks and for cells a
+  /// short row lacks. Without it a missing column is an error and missing
+  /// cells are empty.
+  pub missing_value: Option<String>,
+  /// Compress the output, after encoding it
+  pub compression: Option<Compression>,
+}
+
+/// Re-writes CSV from one dialect and encoding to another in one pass.
+/// Cells beyond the header columns are dropped.
+pub struct Rewriter {
+  parser: Parser,
+  input_encoding: Option<Transcoder>,
+  writer: CsvWriter,
+  encoder: Encoder,
+  compressor: Option<Compressor>,
+  columns: Option<Vec<String>>,
+  rename: Vec<(String, String)>,
+  missing_value: Option<String>,
+  write_headers: bool,
+  // Field index of each output column, once the headers are known; `None`
+  // for columns the input lacks
+  fields: Option<Vec<Option<usize>>>,
+}
+
+impl Rewriter {
+  pub fn new(options: RewriteOptions) -> Result<Self> {
+    if options.writer.sql.is_some() {
+      return Err(eyre!("output.sql is not supported by rewrite"));
+    }
+    let input_encoding = match options.input_encoding.as_deref() {
+      Some(label) => Some(Transcoder::new(Some(label))?),
+      None => None,
+    };
+
+    Ok(Self {
+      // `headers: false` input has no header line to write back
+      write_headers: !matches!(&options.parser.headers, Some(h) if h.is_empty()),
+      parser: Parser::new(options.parser),
+      input_encoding,
+      writer: CsvWriter::new(options.writer),
+      encoder: Encoder::new(options.output_encoding.as_deref(), options.bom)?,
+      compressor: options.compression.map(Compressor::new).transpose()?,
+      columns: options.columns,
+      rename: options.rename,
+      missing_value: options.missing_value,
+      fields: None,
+    })
+  }
+
+  /// Adds a chunk of input and returns the output for the rows it completes
+  pub fn feed(&mut self, chunk: &[u8]) -> Result<Vec<u8>> {
+    let out = match &mut self.input_encoding {
+      Some(transcoder) => {
+        let decoded = transcoder.feed(chunk)?;
+        self.rewrite(&decoded, false)?
+      }
+      None => self.rewrite(chunk, false)?,
+    };
+    let bytes = self.encoder.encode(&out)?;
+    match &mut self.compressor {
+      Some(compressor) => compressor.compress(&bytes),
+      None => Ok(bytes),
+    }
+  }
+
+  /// Ends the input and returns the rest of the output
+  pub fn finish(&mut self) -> Result<Vec<u8>> {
+    let out = match &mut self.input_encoding {
+      Some(transcoder) => {
+        let decoded = transcoder.finish()?;
+        self.rewrite(&decoded, true)?
+      }
+      None => self.rewrite(&[], true)?,
+    };
+    let bytes = self.encoder.encode(&out)?;
+    match &mut self.compressor {
+      Some(compressor) => {
+        let mut compressed = compressor.compress(&bytes)?;
+        compressed.extend(compressor.finish()?);
+        Ok(compressed)
+      }
+      None => Ok(bytes),
+    }
+  }
+
+  fn rewrite(&mut self, chunk: &[u8], at_end: bool) -> Result<String> {
+    self.parser.load(chunk, at_end)?;
+    let mut out = Vec::new();
+
+    while let Some(cells) = self.parser.next_cells(at_end) {
+      let cells = cells?;
+      let tokenizer = &self.parser.tokenizer;
+      let headers = self.parser.headers().unwrap_or_default();
+
+      let fields = match &self.fields {
+        Some(fields) => fields,
+        None => {
+          // Columns as the parser keys them: blank and `_` headers are left out
+          let keyed = |header: &String| !header.is_empty() && header != "_";
+          let fields: Vec<Option<usize>> = match &self.columns {
+            Some(columns) => columns
+              .iter()
+              .map(|column| {
+                match headers
+                  .iter()
+                  .position(|header| keyed(header) && header == column)
+                {
+                  None if self.missing_value.is_none() => Err(eyre!("Column {} not found", column)),
+                  position => Ok(position),
+                }
+              })
+              .collect::<Result<_>>()?,
+            None => (0..headers.len())
+              .filter(|&i| keyed(&headers[i]))
+              .map(Some)
+              .collect(),
+          };
+
+          if self.write_headers {
+            let names: Vec<&str> = fields
+              .iter()
+              .enumerate()
+              .map(|(n, &i)| {
+                // A column the input lacks is named as it was listed
+                let header = match i {
+                  Some(i) => headers[i].as_str(),
+                  None => self.columns.as_ref().map_or("", |columns| &columns[n]),
+                };
+                self
+                  .rename
+                  .iter()
+                  .find(|(from, _)| from == header)
+                  .map_or(header, |(_, to)| to.as_str())
+              })
+              .collect();
+            self.writer.write_record(&mut out, &names)?;
+          }
+          self.fields.insert(fields)
+        }
+      };
+
+      // Null cells stay null, written as the writer's `null_value`
+      let buffer = &self.parser.input.utf8_buffer;
+      let missing = self.missing_value.as_deref().unwrap_or("");
+      let row = fields
+        .iter()
+        .map(|&i| match i.and_then(|i| cells.get(i)) {
+          Some(&cell) if tokenizer.is_null(buffer, cell) => Ok(None),
+          Some(&cell) => Ok(Some(tokenizer.cell_value(buffer, cell)?)),
+          None => Ok(Some(missing.into())),
+        })
+        .collect::<Result<Vec<_>>>()?;
+      self
+        .writer
+        .write_nullable_record(&mut out, &row, |_, _| false)?;
+    }
+
+    // Cells came from valid UTF-8 and the writer only adds ASCII
+    Ok(String::from_utf8(out)?)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::RecordFormat;
+
+  fn rewrite(input: &[u8], options: RewriteOptions) -> Result<Vec<u8>> {
+    let mut rewriter = Rewriter::new(options)?;
+    let (head, tail) = input.split_at(input.len() / 2);
+    let mut out = rewriter.feed(head)?;
+    out.extend(rewriter.feed(tail)?);
+    out.extend(rewriter.finish()?);
+    Ok(out)
+  }
+
+  #[test]
+  fn test_dialect_and_encoding() {
+    let options = RewriteOptions {
+      parser: CsvParserOptions {
+        separator: b';',
+        ..Default::default()
+      },
+      input_encoding: Some("latin1".to_string()),
+      ..Default::default()
+    };
+    let out = rewrite(b"name;city\nJos\xE9;\"S\xE3o Paulo, BR\"\n", options).unwrap();
+
+    assert_eq!(out, "name,city\nJosé,\"São Paulo, BR\"\n".as_bytes());
+  }
+
+  #[test]
+  fn test_select_rename_and_output_encoding() {
+    let options = RewriteOptions {
+      writer: CsvWriterOptions {
+        newline: b"\r\n".to_vec(),
+        ..Default::default()
+      },
+      output_encoding: Some("utf-16le".to_string()),
+      bom: true,
+      columns: Some(vec!["c".to_string(), "a".to_string()]),
+      rename: vec![("c".to_string(), "see".to_string())],
+      ..Default::default()
+    };
+    let out = rewrite(b"a,b,c\n1,2,3\n4,5,6", options).unwrap();
+    let expected: Vec<u8> = "\u{FEFF}see,a\r\n3,1\r\n6,4\r\n"
+      .encode_utf16()
+      .flat_map(u16::to_le_bytes)
+      .collect();
+
+    assert_eq!(out, expected);
+  }
+
+  #[test]
+  fn test_missing_columns() {
+    let options = RewriteOptions {
+      columns: Some(vec!["b".to_string(), "z".to_string(), "a".to_string()]),
+      rename: vec![("z".to_string(), "zed".to_string())],
+      missing_value: Some("NA".to_string()),
+      ..Default::default()
+    };
+    let out = rewrite(b"a,b\n1,2\n3\n", options).unwrap();
+
+    assert_eq!(out, b"b,zed,a\n2,NA,1\nNA,NA,3\n");
+  }
+
+  #[test]
+  fn test_postgres_text() {
+    let copy = || CsvParserOptions {
+      separator: b'\t',
+      format: RecordFormat::PostgresText,
+      null_value: Some("\\N".to_string()),
+      ..Default::default()
+    };
+    let options = RewriteOptions {
+      parser: copy(),
+      writer: CsvWriterOptions {
+        null_value: "NULL".to_string(),
+        ..Default::default()
+      },
+      ..Default::default()
+    };
+    let out = rewrite(b"a\tb\tc\n1\t\\N\tx\\ty\n\\.\n", options).unwrap();
+    assert_eq!(out, b"a,b,c\n1,NULL,x\ty\n");
+
+    let options = RewriteOptions {
+      parser: CsvParserOptions {
+        null_value: Some("NULL".to_string()),
+        ..Default::default()
+      },
+      writer: CsvWriterOptions {
+        separator: b'\t',
+        format: RecordFormat::PostgresText,
+        null_value: "\\N".to_string(),
+        ..Default::default()
+      },
+      ..Default::default()
+    };
+    let out = rewrite(b"a,b\nNULL,\"x\ny\"\n", options).unwrap();
+    assert_eq!(out, b"a\tb\n\\N\tx\\ny\n");
+  }
+
+  #[test]
+  fn test_errors() {
+    let options = RewriteOptions {
+      columns: Some(vec!["z".to_string()]),
+      ..Default::default()
+    };
+    assert!(rewrite(b"a\n1\n", options).is_err());
+
+    let options = RewriteOptions {
+      output_encoding: Some("latin1".to_string()),
+      ..Default::default()
+    };
+    assert!(rewrite("a\n日本\n".as_bytes(), options).is_err());
+
+    let options = RewriteOptions {
+      input_encoding: Some("klingon".to_string()),
+      ..Default::default()
+    };
+    assert!(Rewriter::new(options).is_err());
+  }
+}
@@ -0,0 +1,131 @@
+use color_eyre::eyre::Result;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::Read;
+
+use crate::parser::CsvParserOptions;
+use crate::reader::{record, Parser, Record};
+
+/// Picks `k` records of `input` uniformly at random in one pass (reservoir
+/// sampling) and returns them in input order. Rows that are not picked are
+/// never turned into strings. The same `seed` picks the same rows of the
+/// same input; without one each call picks differently.
+pub fn sample_rows(
+  mut input: impl Read,
+  options: CsvParserOptions,
+  k: usize,
+  seed: Option<u64>,
+) -> Result<Vec<Record>> {
+  let mut parser = Parser::new(options);
+  let seed = seed.unwrap_or_else(|| RandomState::new().build_hasher().finish());
+  let mut random = SplitMix64(seed);
+  // Picked records and their row numbers
+  let mut reservoir: Vec<(u64, Record)> = Vec::with_capacity(k);
+  let mut rows = 0;
+  let mut chunk = vec![0; 64 * 1024];
+
+  loop {
+    let read = input.read(&mut chunk)?;
+    let at_end = read == 0;
+    parser.load(&chunk[..read], at_end)?;
+    while let Some(cells) = parser.next_cells(at_end) {
+      let cells = cells?;
+      // Once the reservoir is full, row `rows` replaces a random pick with
+      // probability k / (rows + 1)
+      let slot = match reservoir.len() {
+        len if len < k => Some(len),
+        _ => Some(random.below(rows + 1) as usize).filter(|&slot| slot < k),
+      };
+      if let Some(slot) = slot {
+        let picked = (
+          rows,
+          record(&parser.tokenizer, &parser.input.utf8_buffer, &cells)?,
+        );
+        match reservoir.get_mut(slot) {
+          Some(old) => *old = picked,
+          None => reservoir.push(picked),
+        }
+      }
+      rows += 1;
+    }
+    if at_end {
+      break;
+    }
+  }
+
+  reservoir.sort_unstable_by_key(|&(row, _)| row);
+  Ok(reservoir.into_iter().map(|(_, record)| record).collect())
+}
+
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+  fn next(&mut self) -> u64 {
+    self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = self.0;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+  }
+
+  // A number in `0..n`
+  fn below(&mut self, n: u64) -> u64 {
+    ((self.next() as u128 * n as u128) >> 64) as u64
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn input(rows: usize) -> String {
+    let mut input = "id\n".to_string();
+    for row in 0..rows {
+      input.push_str(&format!("{}\n", row));
+    }
+    input
+  }
+
+  fn ids(records: &[Record]) -> Vec<&str> {
+    records.iter().map(|r| r.get("id").unwrap()).collect()
+  }
+
+  #[test]
+  fn test_small_input() {
+    let options = CsvParserOptions::default;
+    let records = sample_rows(input(3).as_bytes(), options(), 5, None).unwrap();
+    assert_eq!(ids(&records), ["0", "1", "2"]);
+    assert!(sample_rows(input(3).as_bytes(), options(), 0, None)
+      .unwrap()
+      .is_empty());
+  }
+
+  #[test]
+  fn test_seeded_sample() {
+    let input = input(1000);
+    let sample = |seed| sample_rows(input.as_bytes(), CsvParserOptions::default(), 10, seed);
+    let records = sample(Some(7)).unwrap();
+    assert_eq!(records.len(), 10);
+    assert_eq!(records, sample(Some(7)).unwrap());
+
+    let numbers: Vec<u32> = ids(&records).iter().map(|id| id.parse().unwrap()).collect();
+    assert!(numbers.windows(2).all(|pair| pair[0] < pair[1]));
+  }
+
+  #[test]
+  fn test_uniform_picks() {
+    let input = input(10);
+    let mut counts = [0; 10];
+    for seed in 0..2000 {
+      let records = sample_rows(input.as_bytes(), CsvParserOptions::default(), 1, Some(seed));
+      let id: usize = records.unwrap()[0].get("id").unwrap().parse().unwrap();
+      counts[id] += 1;
+    }
+    // Each row is expected 200 times
+    assert!(
+      counts.iter().all(|&count| (140..260).contains(&count)),
+      "{:?}",
+      counts
+    );
+  }
+}
@@ -0,0 +1,253 @@
+use color_eyre::eyre::Result;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+
+use crate::parser::CsvParserOptions;
+use crate::reader::Parser;
+
+/// Example values kept per column
+const EXAMPLES: usize = 3;
+
+/// The narrowest type that fits every non-empty value of a column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+  /// No non-empty values were seen
+  Empty,
+  Boolean,
+  Integer,
+  Float,
+  /// `YYYY-MM-DD`, optionally followed by a time
+  Date,
+  String,
+}
+
+impl ColumnType {
+  pub fn as_str(self) -> &'static str {
+    match self {
+      ColumnType::Empty => "empty",
+      ColumnType::Boolean => "boolean",
+      ColumnType::Integer => "integer",
+      ColumnType::Float => "float",
+      ColumnType::Date => "date",
+      ColumnType::String => "string",
+    }
+  }
+
+  fn of(value: &str) -> Self {
+    if value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false") {
+      ColumnType::Boolean
+    } else if value.parse::<i64>().is_ok() {
+      ColumnType::Integer
+    } else if is_float(value) {
+      ColumnType::Float
+    } else if is_date(value) {
+      ColumnType::Date
+    } else {
+      ColumnType::String
+    }
+  }
+
+  fn widen(self, other: Self) -> Self {
+    match (self, other) {
+      (a, b) if a == b => a,
+      (ColumnType::Empty, t) | (t, ColumnType::Empty) => t,
+      (ColumnType::Integer, ColumnType::Float) | (ColumnType::Float, ColumnType::Integer) => {
+        ColumnType::Float
+      }
+      _ => ColumnType::String,
+    }
+  }
+}
+
+/// What a sample of rows says about one column
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnSchema {
+  pub name: String,
+  pub kind: ColumnType,
+  /// Some sampled row had no value for the column
+  pub nullable: bool,
+  /// Shortest and longest non-empty value, in characters
+  pub min_length: usize,
+  pub max_length: usize,
+  /// Up to three distinct non-empty values, in order of appearance
+  pub examples: Vec<String>,
+  /// Distinct non-empty values in the sample
+  pub distinct: usize,
+}
+
+struct Column {
+  schema: ColumnSchema,
+  values: HashSet<String>,
+  // Rows sampled before the column first appeared
+  missing_before: bool,
+}
+
+/// Reads up to `sample_rows` records from `input` and infers a schema for
+/// each column, in column order.
+pub fn infer_schema(
+  mut input: impl Read,
+  options: CsvParserOptions,
+  sample_rows: usize,
+) -> Result<Vec<ColumnSchema>> {
+  let mut parser = Parser::new(options);
+  let mut columns: Vec<Column> = Vec::new();
+  let mut index: HashMap<String, usize> = HashMap::new();
+  let mut rows = 0;
+  let mut chunk = vec![0; 64 * 1024];
+
+  loop {
+    let read = input.read(&mut chunk)?;
+    let records = if read == 0 {
+      parser.finish()?
+    } else {
+      parser.feed(&chunk[..read])?
+    };
+
+    for record in records {
+      if rows == sample_rows {
+        break;
+      }
+      let record = record?;
+      let mut seen = vec![false; columns.len()];
+
+      for (key, value) in record.iter() {
+        let i = *index.entry(key.to_string()).or_insert_with(|| {
+          columns.push(Column::new(key, rows > 0));
+          seen.push(false);
+          columns.len() - 1
+        });
+        seen[i] = true;
+        columns[i].add(value);
+      }
+      for (column, seen) in columns.iter_mut().zip(seen) {
+        if !seen {
+          column.schema.nullable = true;
+        }
+      }
+      rows += 1;
+    }
+
+    if read == 0 || rows == sample_rows {
+      break;
+    }
+  }
+
+  Ok(columns.into_iter().map(Column::finish).collect())
+}
+
+impl Column {
+  fn new(name: &str, missing_before: bool) -> Self {
+    Self {
+      schema: ColumnSchema {
+        name: name.to_string(),
+        kind: ColumnType::Empty,
+        nullable: false,
+        min_length: 0,
+        max_length: 0,
+        examples: Vec::new(),
+        distinct: 0,
+      },
+      values: HashSet::new(),
+      missing_before,
+    }
+  }
+
+  fn add(&mut self, value: &str) {
+    let schema = &mut self.schema;
+    if value.is_empty() {
+      schema.nullable = true;
+      return;
+    }
+
+    let length = value.chars().count();
+    if self.values.is_empty() {
+      schema.min_length = length;
+    }
+    schema.min_length = schema.min_length.min(length);
+    schema.max_length = schema.max_length.max(length);
+    schema.kind = schema.kind.widen(ColumnType::of(value));
+
+    if !self.values.contains(value) {
+      if schema.examples.len() < EXAMPLES {
+        schema.examples.push(value.to_string());
+      }
+      self.values.insert(value.to_string());
+    }
+  }
+
+  fn finish(self) -> ColumnSchema {
+    ColumnSchema {
+      nullable: self.schema.nullable || self.missing_before,
+      distinct: self.values.len(),
+      ..self.schema
+    }
+  }
+}
+
+pub(crate) fn is_float(value: &str) -> bool {
+  // Rust also accepts `inf` and `NaN`, which are text as far as CSV goes
+  value.bytes().any(|b| b.is_ascii_digit()) && value.parse::<f64>().is_ok()
+}
+
+fn is_date(value: &str) -> bool {
+  let bytes = value.as_bytes();
+  let digits = |range: std::ops::Range<usize>| bytes[range].iter().all(u8::is_ascii_digit);
+  bytes.len() >= 10
+    && digits(0..4)
+    && bytes[4] == b'-'
+    && digits(5..7)
+    && bytes[7] == b'-'
+    && digits(8..10)
+    && (bytes.len() == 10 || matches!(bytes[10], b'T' | b' '))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn infer(input: &str, sample_rows: usize) -> Vec<ColumnSchema> {
+    infer_schema(input.as_bytes(), CsvParserOptions::default(), sample_rows).unwrap()
+  }
+
+  #[test]
+  fn test_column_types() {
+    let schema = infer(
+      "id,price,active,day,name,blank\n1,2.5,true,2024-01-02,Ann,\n2,3,FALSE,2024-02-03T10:00,Bob,\n",
+      100,
+    );
+    let kinds: Vec<_> = schema.iter().map(|c| c.kind).collect();
+
+    assert_eq!(
+      kinds,
+      [
+        ColumnType::Integer,
+        ColumnType::Float,
+        ColumnType::Boolean,
+        ColumnType::Date,
+        ColumnType::String,
+        ColumnType::Empty,
+      ]
+    );
+    assert!(!schema[0].nullable);
+    assert!(schema[5].nullable);
+  }
+
+  #[test]
+  fn test_lengths_examples_and_distinct() {
+    let schema = infer("name\nAnn\nBob\nAnn\nCarla\nDe\n", 100);
+
+    assert_eq!(schema[0].min_length, 2);
+    assert_eq!(schema[0].max_length, 5);
+    assert_eq!(schema[0].examples, ["Ann", "Bob", "Carla"]);
+    assert_eq!(schema[0].distinct, 4);
+  }
+
+  #[test]
+  fn test_sample_limit_and_nullability() {
+    let schema = infer("a,b\n1,x\n2,\nnot a number,y\n", 2);
+
+    assert_eq!(schema[0].kind, ColumnType::Integer);
+    assert!(schema[1].nullable);
+    assert_eq!(schema[1].distinct, 1);
+  }
+}
@@ -0,0 +1,591 @@
+use color_eyre::eyre::{eyre, Result};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+use crate::parser::{CellRange, CsvParserOptions};
+use crate::reader::{Parser, Record};
+use crate::writer::{CsvWriter, CsvWriterOptions};
+
+// Per-row and per-cell bookkeeping counted against the memory limit, on top
+// of the cell bytes
+const ROW_OVERHEAD: usize = 48;
+const CELL_OVERHEAD: usize = 24;
+
+// Distinguishes the run files of concurrent sorts in one process
+static RUN_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// Runs merged at once, each holding an open file. More runs are first
+// merged in groups of this many into longer runs.
+const MAX_FAN_IN: usize = 64;
+
+pub struct SortOptions {
+  /// Header of the column to sort by
+  pub column: String,
+  /// Compare values as numbers. Values that are not numbers sort last.
+  pub numeric: bool,
+  /// Largest value first
+  pub desc: bool,
+  /// Approximate bytes of rows held in memory before a sorted run is spilled
+  /// to disk
+  pub memory_limit: usize,
+  /// Where run files go; the system temp directory by default
+  pub temp_dir: Option<PathBuf>,
+  pub parser: CsvParserOptions,
+}
+
+impl Default for SortOptions {
+  fn default() -> Self {
+    Self {
+      column: String::new(),
+      numeric: false,
+      desc: false,
+      memory_limit: 64 * 1024 * 1024,
+      temp_dir: None,
+      parser: CsvParserOptions::default(),
+    }
+  }
+}
+
+struct Row {
+  // The sort value parsed as a number, NaN if it is not one
+  number: f64,
+  values: Vec<String>,
+}
+
+#[derive(Clone, Copy)]
+struct Order {
+  column: usize,
+  numeric: bool,
+  desc: bool,
+}
+
+/// The rows of a sorted input, in order. Rows that compare equal keep their
+/// input order. Spilled runs are merged lazily and their files removed on
+/// drop.
+pub struct SortedRows {
+  headers: Vec<String>,
+  write_headers: bool,
+  separator: u8,
+  quote: u8,
+  source: Source,
+}
+
+enum Source {
+  Memory(std::vec::IntoIter<Row>),
+  Runs(Merge),
+}
+
+// A sorted run on disk, removed on drop
+struct Run {
+  path: PathBuf,
+}
+
+// Merges sorted runs by taking the smallest of their next rows
+struct Merge {
+  readers: Vec<BufReader<File>>,
+  heads: BinaryHeap<Head>,
+  order: Order,
+  // Held so their files outlive the readers
+  _runs: Vec<Run>,
+}
+
+// The next row of the run at `run`
+struct Head {
+  row: Row,
+  run: usize,
+  order: Order,
+}
+
+/// Sorts `input` by a column, reading it once. Rows beyond the memory limit
+/// are sorted in runs on disk and merged as the result is read.
+pub fn sort_rows(mut input: impl Read, options: SortOptions) -> Result<SortedRows> {
+  // `headers: false` input has no header line to write back
+  let write_headers = !matches!(&options.parser.headers, Some(h) if h.is_empty());
+  let mut parser = Parser::new(options.parser);
+  let mut order = None;
+  let mut rows = Vec::new();
+  let mut size = 0;
+  let mut runs = Vec::new();
+  let mut chunk = vec![0; 64 * 1024];
+
+  loop {
+    let read = input.read(&mut chunk)?;
+    let at_end = read == 0;
+    parser.load(&chunk[..read], at_end)?;
+
+    while let Some(cells) = parser.next_cells(at_end) {
+      let cells = cells?;
+      let headers = parser.headers().unwrap_or_default();
+      let values = row_values(&parser, &cells, headers.len())?;
+      let order = match &order {
+        Some(order) => order,
+        None => {
+          // Columns as the parser keys them: blank and `_` headers are left out
+          let column = headers
+            .iter()
+            .position(|header| !header.is_empty() && header != "_" && *header == options.column)
+            .ok_or_else(|| eyre!("Column {} not found", options.column))?;
+          order.insert(Order {
+            column,
+            numeric: options.numeric,
+            desc: options.desc,
+          })
+        }
+      };
+
+      size += ROW_OVERHEAD
+        + values
+          .iter()
+          .map(|value| value.len() + CELL_OVERHEAD)
+          .sum::<usize>();
+      rows.push(order.row(values));
+
+      if size >= options.memory_limit {
+        order.sort(&mut rows);
+        runs.push(Run::write(options.temp_dir.as_ref(), rows.drain(..).map(Ok))?);
+        size = 0;
+      }
+    }
+
+    if at_end {
+      break;
+    }
+  }
+
+  let headers = parser.headers().map(<[String]>::to_vec).unwrap_or_default();
+  let order = match order {
+    Some(order) => order,
+    // No rows: the column only has to exist
+    None if headers.is_empty() || headers.contains(&options.column) => Order {
+      column: 0,
+      numeric: options.numeric,
+      desc: options.desc,
+    },
+    None => return Err(eyre!("Column {} not found", options.column)),
+  };
+  order.sort(&mut rows);
+
+  let source = if runs.is_empty() {
+    Source::Memory(rows.into_iter())
+  } else {
+    if !rows.is_empty() {
+      runs.push(Run::write(options.temp_dir.as_ref(), rows.into_iter().map(Ok))?);
+    }
+    // Groups are of adjacent runs, so equal rows still keep their order
+    while runs.len() > MAX_FAN_IN {
+      let mut groups = runs.into_iter().peekable();
+      runs = Vec::new();
+      while groups.peek().is_some() {
+        let mut merge = Merge::new(groups.by_ref().take(MAX_FAN_IN).collect(), order)?;
+        let rows = std::iter::from_fn(|| merge.next_row().transpose());
+        runs.push(Run::write(options.temp_dir.as_ref(), rows)?);
+      }
+    }
+    Source::Runs(Merge::new(runs, order)?)
+  };
+
+  let tokenizer = &parser.tokenizer.options;
+  Ok(SortedRows {
+    headers,
+    write_headers,
+    separator: tokenizer.separator,
+    quote: tokenizer.quote,
+    source,
+  })
+}
+
+// One value per column, so values stay under their header when written
+// back: null, missing and `redact`-dropped cells are empty, and cells beyond
+// the headers follow them
+fn row_values(parser: &Parser, cells: &[CellRange], columns: usize) -> Result<Vec<String>> {
+  let tokenizer = &parser.tokenizer;
+  let buffer = &parser.input.utf8_buffer;
+  (0..columns.max(cells.len()))
+    .map(|i| match cells.get(i) {
+      Some(&cell) if !tokenizer.is_null(buffer, cell) => {
+        Ok(tokenizer.cell_value(buffer, cell)?.into_owned())
+      }
+      _ => Ok(String::new()),
+    })
+    .collect()
+}
+
+/// Sorts `input` by a column and writes it to `output` as CSV, with the
+/// input's separator and quote. Returns the number of rows written.
+pub fn sort_by(input: impl Read, output: impl Write, options: SortOptions) -> Result<u64> {
+  sort_rows(input, options)?.write_to(output)
+}
+
+impl SortedRows {
+  /// The header names of the input. Rows are keyed by them, leaving out
+  /// blank and `_` ones as the parser does.
+  pub fn headers(&self) -> &[String] {
+    &self.headers
+  }
+
+  /// Writes the header line, unless the input had none, and the remaining
+  /// rows. Returns the number of rows written.
+  pub fn write_to(mut self, output: impl Write) -> Result<u64> {
+    let writer = CsvWriter::new(CsvWriterOptions {
+      separator: self.separator,
+      quote: self.quote,
+      ..Default::default()
+    });
+    let mut output = BufWriter::new(output);
+    let mut line = Vec::new();
+    let mut rows = 0;
+
+    if self.write_headers && !self.headers.is_empty() {
+      writer.write_record(&mut line, &self.headers)?;
+      output.write_all(&line)?;
+    }
+    while let Some(row) = self.next_row()? {
+      line.clear();
+      writer.write_record(&mut line, &row.values)?;
+      output.write_all(&line)?;
+      rows += 1;
+    }
+    output.flush()?;
+    Ok(rows)
+  }
+
+  fn next_row(&mut self) -> Result<Option<Row>> {
+    match &mut self.source {
+      Source::Memory(rows) => Ok(rows.next()),
+      Source::Runs(merge) => merge.next_row(),
+    }
+  }
+}
+
+impl Iterator for SortedRows {
+  type Item = Result<Record>;
+
+  fn next(&mut self) -> Option<Result<Record>> {
+    let row = match self.next_row() {
+      Ok(row) => row?,
+      Err(e) => return Some(Err(e)),
+    };
+    // Keyed as the parser keys rows, without blank and `_` headers
+    let fields = row
+      .values
+      .into_iter()
+      .enumerate()
+      .filter_map(|(i, value)| match self.headers.get(i) {
+        Some(header) if header.is_empty() || header == "_" => None,
+        Some(header) => Some((header.clone(), value)),
+        None => Some((format!("_{}", i), value)),
+      })
+      .collect::<Vec<_>>();
+    Some(Ok(Record::from(fields)))
+  }
+}
+
+impl Row {
+  fn value(&self, column: usize) -> &str {
+    self.values.get(column).map_or("", String::as_str)
+  }
+}
+
+impl Order {
+  fn row(&self, values: Vec<String>) -> Row {
+    let number = match values.get(self.column) {
+      Some(value) if self.numeric => value.trim().parse().unwrap_or(f64::NAN),
+      _ => f64::NAN,
+    };
+    Row { number, values }
+  }
+
+  fn sort(&self, rows: &mut [Row]) {
+    // Stable, so equal rows keep their input order
+    rows.sort_by(|a, b| self.compare(a, b));
+  }
+
+  fn compare(&self, a: &Row, b: &Row) -> Ordering {
+    let ordering = if self.numeric {
+      match (a.number.is_nan(), b.number.is_nan()) {
+        (false, false) => a.number.total_cmp(&b.number),
+        // Values that are not numbers come last either way
+        (false, true) => return Ordering::Less,
+        (true, false) => return Ordering::Greater,
+        (true, true) => return Ordering::Equal,
+      }
+    } else {
+      a.value(self.column).cmp(b.value(self.column))
+    };
+
+    if self.desc {
+      ordering.reverse()
+    } else {
+      ordering
+    }
+  }
+}
+
+impl Run {
+  // Run files hold each row as a cell count followed by length-prefixed
+  // cells, all little-endian u32. The file is closed once written.
+  fn write(dir: Option<&PathBuf>, rows: impl Iterator<Item = Result<Row>>) -> Result<Self> {
+    let dir = dir.cloned().unwrap_or_else(std::env::temp_dir);
+    let run = Self {
+      path: dir.join(format!(
+        "fast-csv-parser-sort-{}-{}.run",
+        std::process::id(),
+        RUN_COUNTER.fetch_add(1, AtomicOrdering::Relaxed)
+      )),
+    };
+    let cannot_write = |e: std::io::Error| eyre!("Cannot write {}: {}", run.path.display(), e);
+
+    let mut out = BufWriter::new(File::create(&run.path).map_err(cannot_write)?);
+    for row in rows {
+      let row = row?;
+      let mut write = || -> std::io::Result<()> {
+        out.write_all(&(row.values.len() as u32).to_le_bytes())?;
+        for value in &row.values {
+          out.write_all(&(value.len() as u32).to_le_bytes())?;
+          out.write_all(value.as_bytes())?;
+        }
+        Ok(())
+      };
+      write().map_err(cannot_write)?;
+    }
+    out.flush().map_err(cannot_write)?;
+    Ok(run)
+  }
+}
+
+impl Drop for Run {
+  fn drop(&mut self) {
+    let _ = std::fs::remove_file(&self.path);
+  }
+}
+
+impl Merge {
+  fn new(runs: Vec<Run>, order: Order) -> Result<Self> {
+    let mut merge = Self {
+      readers: Vec::with_capacity(runs.len()),
+      heads: BinaryHeap::with_capacity(runs.len()),
+      order,
+      _runs: Vec::new(),
+    };
+    for (i, run) in runs.iter().enumerate() {
+      let file = File::open(&run.path)
+        .map_err(|e| eyre!("Cannot read {}: {}", run.path.display(), e))?;
+      merge.readers.push(BufReader::new(file));
+      merge.advance(i)?;
+    }
+    merge._runs = runs;
+    Ok(merge)
+  }
+
+  fn next_row(&mut self) -> Result<Option<Row>> {
+    let Some(head) = self.heads.pop() else {
+      return Ok(None);
+    };
+    self.advance(head.run)?;
+    Ok(Some(head.row))
+  }
+
+  // Reads the next row of run `run` into the heap, if it has one
+  fn advance(&mut self, run: usize) -> Result<()> {
+    let reader = &mut self.readers[run];
+    let cells = match read_u32(reader) {
+      Ok(cells) => cells,
+      Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+      Err(e) => return Err(e.into()),
+    };
+    let mut values = Vec::with_capacity(cells as usize);
+    for _ in 0..cells {
+      let mut value = vec![0; read_u32(reader)? as usize];
+      reader.read_exact(&mut value)?;
+      values.push(String::from_utf8(value)?);
+    }
+    self.heads.push(Head {
+      row: self.order.row(values),
+      run,
+      order: self.order,
+    });
+    Ok(())
+  }
+}
+
+fn read_u32(reader: &mut impl Read) -> std::io::Result<u32> {
+  let mut bytes = [0; 4];
+  reader.read_exact(&mut bytes)?;
+  Ok(u32::from_le_bytes(bytes))
+}
+
+// `BinaryHeap` pops the greatest head, so the smallest row is greatest.
+// Ties go to the earlier run, which holds the earlier input rows.
+impl Ord for Head {
+  fn cmp(&self, other: &Self) -> Ordering {
+    self
+      .order
+      .compare(&other.row, &self.row)
+      .then(other.run.cmp(&self.run))
+  }
+}
+
+impl PartialOrd for Head {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl PartialEq for Head {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+
+impl Eq for Head {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const INPUT: &str = "name,score\nAnn,10\nBob,9\nCy,x\nDee,10\nEd,100\n";
+
+  fn sort(options: SortOptions) -> String {
+    let mut out = Vec::new();
+    sort_by(INPUT.as_bytes(), &mut out, options).unwrap();
+    String::from_utf8(out).unwrap()
+  }
+
+  #[test]
+  fn test_sort_as_text_and_numbers() {
+    let options = SortOptions {
+      column: "score".to_string(),
+      ..Default::default()
+    };
+    assert_eq!(
+      sort(options),
+      "name,score\nAnn,10\nDee,10\nEd,100\nBob,9\nCy,x\n"
+    );
+
+    let options = SortOptions {
+      column: "score".to_string(),
+      numeric: true,
+      desc: true,
+      ..Default::default()
+    };
+    assert_eq!(
+      sort(options),
+      "name,score\nEd,100\nAnn,10\nDee,10\nBob,9\nCy,x\n"
+    );
+  }
+
+  #[test]
+  fn test_spilled_runs_merge_stably() {
+    let options = SortOptions {
+      column: "score".to_string(),
+      numeric: true,
+      // Every row ends a run
+      memory_limit: 1,
+      ..Default::default()
+    };
+    let rows = sort_rows(INPUT.as_bytes(), options).unwrap();
+    let names: Vec<_> = rows
+      .map(|record| record.unwrap().get("name").unwrap().to_string())
+      .collect();
+
+    assert_eq!(names, ["Bob", "Ann", "Dee", "Ed", "Cy"]);
+  }
+
+  #[test]
+  fn test_many_runs_merge_in_passes() {
+    // Three keys over more runs than are merged at once, each row its own run
+    let rows = MAX_FAN_IN * 3 + 5;
+    let mut input = String::from("key,n\n");
+    for n in 0..rows {
+      input.push_str(&format!("{},{}\n", n % 3, n));
+    }
+    let options = SortOptions {
+      column: "key".to_string(),
+      desc: true,
+      memory_limit: 1,
+      ..Default::default()
+    };
+    let sorted: Vec<_> = sort_rows(input.as_bytes(), options)
+      .unwrap()
+      .map(|record| {
+        let record = record.unwrap();
+        let field = |key| record.get(key).unwrap().parse::<usize>().unwrap();
+        (field("key"), field("n"))
+      })
+      .collect();
+
+    let mut expected: Vec<_> = (0..rows).map(|n| (n % 3, n)).collect();
+    expected.sort_by_key(|&(key, _)| std::cmp::Reverse(key));
+    assert_eq!(sorted, expected);
+  }
+
+  #[test]
+  fn test_values_stay_under_their_headers() {
+    let sorted = |input: &str, parser: CsvParserOptions| {
+      let options = SortOptions {
+        column: "score".to_string(),
+        numeric: true,
+        parser,
+        ..Default::default()
+      };
+      let mut out = Vec::new();
+      sort_by(input.as_bytes(), &mut out, options).unwrap();
+      String::from_utf8(out).unwrap()
+    };
+
+    let input = "name,,score\nBob,,2\nAnn,,10\nCy,,5\n";
+    assert_eq!(
+      sorted(input, CsvParserOptions::default()),
+      "name,,score\nBob,,2\nCy,,5\nAnn,,10\n"
+    );
+    let input = "name,note,score\nBob,NULL,2\nAnn,x,10\nCy,,5\n";
+    let options = CsvParserOptions {
+      null_value: Some("NULL".to_string()),
+      ..Default::default()
+    };
+    assert_eq!(
+      sorted(input, options),
+      "name,note,score\nBob,,2\nCy,,5\nAnn,x,10\n"
+    );
+    let input = "name,note,score\n,,2\nAnn,x,10\nCy,,5\n";
+    let options = CsvParserOptions {
+      skip_empty_fields: true,
+      ..Default::default()
+    };
+    assert_eq!(
+      sorted(input, options),
+      "name,note,score\n,,2\nCy,,5\nAnn,x,10\n"
+    );
+
+    // Rows are keyed without the blank header
+    let options = SortOptions {
+      column: "name".to_string(),
+      ..Default::default()
+    };
+    let rows: Vec<_> = sort_rows("name,,score\nBob,,2\nAnn,,10\n".as_bytes(), options)
+      .unwrap()
+      .map(|record| record.unwrap().into_fields())
+      .collect();
+    let field = |key: &str, value: &str| (key.to_string(), value.to_string());
+    assert_eq!(
+      rows,
+      [
+        vec![field("name", "Ann"), field("score", "10")],
+        vec![field("name", "Bob"), field("score", "2")],
+      ]
+    );
+  }
+
+  #[test]
+  fn test_missing_column() {
+    let options = SortOptions {
+      column: "age".to_string(),
+      ..Default::default()
+    };
+    assert!(sort_rows(INPUT.as_bytes(), options).is_err());
+  }
+}
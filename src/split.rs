@@ -0,0 +1,239 @@
+use color_eyre::eyre::{eyre, Result};
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::parser::CsvParserOptions;
+use crate::reader::Parser;
+use crate::writer::{CsvWriter, CsvWriterOptions};
+
+/// How much of the input goes into each shard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShardLimit {
+  /// Data rows per shard
+  Rows(u64),
+  /// Bytes per shard, header included. A row longer than this gets a shard
+  /// of its own.
+  Bytes(u64),
+}
+
+/// One file written by `split_file`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shard {
+  pub path: PathBuf,
+  /// Data rows, not counting the header
+  pub rows: u64,
+  pub bytes: u64,
+}
+
+struct ShardWriter {
+  out: BufWriter<File>,
+  shard: Shard,
+}
+
+/// Splits the CSV file at `path` into shards in `out_dir`, named after the
+/// input with a counter (`data-00001.csv`, `data-00002.csv`, ...). Shards
+/// end between records, never inside a quoted field, and each starts with
+/// the header line when the input has one. Rows are copied as they are,
+/// decoded to UTF-8; comment, blank and skipped lines are left out.
+pub fn split_file(
+  path: impl AsRef<Path>,
+  out_dir: impl AsRef<Path>,
+  limit: ShardLimit,
+  options: CsvParserOptions,
+) -> Result<Vec<Shard>> {
+  if matches!(limit, ShardLimit::Rows(0) | ShardLimit::Bytes(0)) {
+    return Err(eyre!("The shard limit must be positive"));
+  }
+  let path = path.as_ref();
+  let out_dir = out_dir.as_ref();
+  let stem = path
+    .file_stem()
+    .ok_or_else(|| eyre!("{} is not a file name", path.display()))?
+    .to_string_lossy()
+    .into_owned();
+  let extension = path
+    .extension()
+    .map_or("csv".into(), |extension| extension.to_string_lossy());
+  std::fs::create_dir_all(out_dir)?;
+
+  let headers_from_input = options.headers.is_none();
+  let mut input = File::open(path)?;
+  let mut parser = Parser::new(options);
+  let mut shards = Vec::new();
+  let mut current: Option<ShardWriter> = None;
+  // The header line as written to every shard
+  let mut header: Option<Vec<u8>> = None;
+  let mut chunk = vec![0; 64 * 1024];
+
+  loop {
+    let read = input.read(&mut chunk)?;
+    let at_end = read == 0;
+    parser.load(&chunk[..read], at_end)?;
+
+    while let Some(cells) = parser.next_cells(at_end) {
+      cells?;
+      let row = parser.row_bytes();
+      let newline = line_ending(row);
+      if header.is_none() {
+        let mut line = Vec::new();
+        if let Some(names) = parser.headers().filter(|_| headers_from_input) {
+          let writer = CsvWriter::new(CsvWriterOptions {
+            separator: parser.tokenizer.options.separator,
+            quote: parser.tokenizer.options.quote,
+            newline: newline.unwrap_or(b"\n").to_vec(),
+            ..CsvWriterOptions::default()
+          });
+          writer.write_record(&mut line, names)?;
+        }
+        header = Some(line);
+      }
+      let header = header.as_deref().unwrap_or_default();
+      let row_len = (row.len() + newline.map_or(1, |_| 0)) as u64;
+
+      let full = current.as_ref().is_some_and(|writer| match limit {
+        ShardLimit::Rows(rows) => writer.shard.rows >= rows,
+        ShardLimit::Bytes(bytes) => writer.shard.rows > 0 && writer.shard.bytes + row_len > bytes,
+      });
+      if full {
+        shards.push(finish(current.take().unwrap())?);
+      }
+      let writer = match &mut current {
+        Some(writer) => writer,
+        None => {
+          let name = format!("{}-{:05}.{}", stem, shards.len() + 1, extension);
+          let shard_path = out_dir.join(name);
+          let mut out = BufWriter::new(File::create(&shard_path)?);
+          out.write_all(header)?;
+          current.insert(ShardWriter {
+            out,
+            shard: Shard {
+              path: shard_path,
+              rows: 0,
+              bytes: header.len() as u64,
+            },
+          })
+        }
+      };
+
+      writer.out.write_all(row)?;
+      // The last row of the input may end without a newline
+      if newline.is_none() {
+        writer.out.write_all(b"\n")?;
+      }
+      writer.shard.rows += 1;
+      writer.shard.bytes += row_len;
+    }
+    if at_end {
+      break;
+    }
+  }
+
+  if let Some(writer) = current {
+    shards.push(finish(writer)?);
+  }
+  Ok(shards)
+}
+
+fn finish(mut writer: ShardWriter) -> Result<Shard> {
+  writer.out.flush()?;
+  Ok(writer.shard)
+}
+
+fn line_ending(row: &[u8]) -> Option<&'static [u8]> {
+  if row.ends_with(b"\r\n") {
+    Some(b"\r\n")
+  } else if row.ends_with(b"\n") {
+    Some(b"\n")
+  } else if row.ends_with(b"\r") {
+    Some(b"\r")
+  } else {
+    None
+  }
+}
+
+/// Reads a size such as `4096`, `64KB`, `256MB` or `1.5GB`. Units are powers
+/// of 1024; `KiB`, `MiB` and `GiB` mean the same as `KB`, `MB` and `GB`.
+pub fn parse_size(text: &str) -> Result<u64> {
+  let text = text.trim();
+  let split = text
+    .find(|c: char| !c.is_ascii_digit() && c != '.')
+    .unwrap_or(text.len());
+  let (number, unit) = text.split_at(split);
+  let scale: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+    "" | "B" => 1,
+    "K" | "KB" | "KIB" => 1 << 10,
+    "M" | "MB" | "MIB" => 1 << 20,
+    "G" | "GB" | "GIB" => 1 << 30,
+    "T" | "TB" | "TIB" => 1 << 40,
+    _ => return Err(eyre!("Invalid size {:?}", text)),
+  };
+  match number.parse::<f64>() {
+    Ok(number) if number.is_finite() => Ok((number * scale as f64) as u64),
+    _ => Err(eyre!("Invalid size {:?}", text)),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn split(name: &str, contents: &str, limit: ShardLimit) -> Vec<(String, u64)> {
+    let dir = std::env::temp_dir().join(format!("fast-csv-parser-split-{}", name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("data.csv");
+    std::fs::write(&path, contents).unwrap();
+
+    let shards = split_file(&path, dir.join("out"), limit, CsvParserOptions::default()).unwrap();
+    let contents = shards
+      .iter()
+      .map(|shard| {
+        let contents = std::fs::read_to_string(&shard.path).unwrap();
+        assert_eq!(shard.bytes, contents.len() as u64);
+        (contents, shard.rows)
+      })
+      .collect();
+    std::fs::remove_dir_all(&dir).unwrap();
+    contents
+  }
+
+  fn shard(contents: &str, rows: u64) -> (String, u64) {
+    (contents.to_string(), rows)
+  }
+
+  #[test]
+  fn test_split_by_rows() {
+    let contents = "id,note\n1,a\n2,\"b\nc\"\n# not a comment\n3,d";
+    assert_eq!(
+      split("rows", contents, ShardLimit::Rows(2)),
+      [
+        shard("id,note\n1,a\n2,\"b\nc\"\n", 2),
+        shard("id,note\n# not a comment\n3,d\n", 2)
+      ]
+    );
+  }
+
+  #[test]
+  fn test_split_by_bytes() {
+    let contents = "id\r\n1\r\n22\r\n333333333\r\n4\r\n";
+    assert_eq!(
+      split("bytes", contents, ShardLimit::Bytes(12)),
+      [
+        shard("id\r\n1\r\n22\r\n", 2),
+        shard("id\r\n333333333\r\n", 1),
+        shard("id\r\n4\r\n", 1)
+      ]
+    );
+  }
+
+  #[test]
+  fn test_parse_size() {
+    assert_eq!(parse_size("4096").unwrap(), 4096);
+    assert_eq!(parse_size("256MB").unwrap(), 256 << 20);
+    assert_eq!(parse_size("1.5 GiB").unwrap(), 3 << 29);
+    assert_eq!(parse_size("64k").unwrap(), 64 << 10);
+    assert!(parse_size("MB").is_err());
+    assert!(parse_size("12 parsecs").is_err());
+  }
+}
@@ -0,0 +1,308 @@
+use color_eyre::eyre::{eyre, Result};
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection};
+use std::io::Read;
+use std::path::Path;
+
+use crate::numbers::NumberColumn;
+use crate::parser::{CellRange, CsvParser, CsvParserOptions};
+use crate::reader::Parser;
+
+/// The SQLite type of a column written by `to_sqlite`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SqlType {
+  #[default]
+  Text,
+  /// Whole numbers; other values are stored as they are
+  Integer,
+  /// Numbers, read with the parser's `decimal_separator` and
+  /// `thousands_separator`; other values are stored as they are
+  Real,
+}
+
+impl SqlType {
+  fn name(self) -> &'static str {
+    match self {
+      SqlType::Text => "TEXT",
+      SqlType::Integer => "INTEGER",
+      SqlType::Real => "REAL",
+    }
+  }
+}
+
+#[derive(Clone)]
+pub struct SqliteOptions {
+  pub table: String,
+  /// Create the table, with a column per header, unless it exists
+  pub create_table: bool,
+  /// The type of each column by header; other columns are `Text`
+  pub types: Vec<(String, SqlType)>,
+  /// Rows inserted per transaction
+  pub batch_size: usize,
+  pub parser: CsvParserOptions,
+}
+
+impl Default for SqliteOptions {
+  fn default() -> Self {
+    Self {
+      table: String::new(),
+      create_table: true,
+      types: Vec::new(),
+      batch_size: 10_000,
+      parser: CsvParserOptions::default(),
+    }
+  }
+}
+
+/// Parses `input` and inserts its rows into `options.table` of the SQLite
+/// database at `db_path`, creating the file if needed. Rows are inserted
+/// `batch_size` at a time, one transaction per batch, so a failure keeps the
+/// batches before it. Missing and null cells are `NULL`, as are empty cells
+/// of `Integer` and `Real` columns; cells beyond the headers are dropped.
+/// Returns the number of rows inserted.
+pub fn to_sqlite(
+  mut input: impl Read,
+  db_path: impl AsRef<Path>,
+  options: SqliteOptions,
+) -> Result<u64> {
+  if options.table.is_empty() {
+    return Err(eyre!("table must not be empty"));
+  }
+  if options.batch_size == 0 {
+    return Err(eyre!("batch_size must be above 0"));
+  }
+  let db = Connection::open(db_path)?;
+  let mut parser = Parser::new(options.parser.clone());
+  let mut loader: Option<Loader> = None;
+  let mut rows = 0;
+  let mut chunk = vec![0; 64 * 1024];
+
+  let result = (|| -> Result<()> {
+    loop {
+      let read = input.read(&mut chunk)?;
+      let at_end = read == 0;
+      parser.load(&chunk[..read], at_end)?;
+      while let Some(cells) = parser.next_cells(at_end) {
+        let cells = cells?;
+        let loader = match &mut loader {
+          Some(loader) => loader,
+          None => loader.insert(Loader::new(&db, &parser.tokenizer, &options)?),
+        };
+        if rows % options.batch_size as u64 == 0 {
+          db.execute_batch("BEGIN")?;
+        }
+        let values = loader.values(&parser.tokenizer, &parser.input.utf8_buffer, &cells)?;
+        db.prepare_cached(&loader.insert)?
+          .execute(params_from_iter(values))?;
+        rows += 1;
+        if rows % options.batch_size as u64 == 0 {
+          db.execute_batch("COMMIT")?;
+        }
+      }
+      if at_end {
+        return Ok(());
+      }
+    }
+  })();
+
+  match result {
+    Ok(()) if !db.is_autocommit() => db.execute_batch("COMMIT")?,
+    Ok(()) => {}
+    Err(e) => {
+      if !db.is_autocommit() {
+        db.execute_batch("ROLLBACK")?;
+      }
+      return Err(e);
+    }
+  }
+  Ok(rows)
+}
+
+// The insert statement and how to bind the cells of a row, set up from the
+// headers
+struct Loader {
+  insert: String,
+  // Cell index and type of each column, in insert order
+  columns: Vec<(usize, SqlType)>,
+}
+
+impl Loader {
+  fn new(db: &Connection, parser: &CsvParser, options: &SqliteOptions) -> Result<Self> {
+    // Columns as the parser keys them: blank and `_` headers are left out
+    let headers = parser.headers.as_deref().unwrap_or_default();
+    let columns: Vec<(usize, &str, SqlType)> = headers
+      .iter()
+      .enumerate()
+      .filter(|(_, header)| !header.is_empty() && *header != "_")
+      .map(|(i, header)| {
+        let sql_type = options
+          .types
+          .iter()
+          .find(|(column, _)| column == header)
+          .map_or(SqlType::Text, |&(_, sql_type)| sql_type);
+        (i, header.as_str(), sql_type)
+      })
+      .collect();
+    if columns.is_empty() {
+      return Err(eyre!("No columns to insert into {}", options.table));
+    }
+
+    let table = quote_identifier(&options.table);
+    if options.create_table {
+      let definitions: Vec<_> = columns
+        .iter()
+        .map(|(_, name, sql_type)| format!("{} {}", quote_identifier(name), sql_type.name()))
+        .collect();
+      db.execute_batch(&format!(
+        "CREATE TABLE IF NOT EXISTS {} ({})",
+        table,
+        definitions.join(", ")
+      ))?;
+    }
+
+    let names: Vec<_> = columns
+      .iter()
+      .map(|(_, name, _)| quote_identifier(name))
+      .collect();
+    let placeholders = vec!["?"; columns.len()].join(", ");
+    let insert = format!(
+      "INSERT INTO {} ({}) VALUES ({})",
+      table,
+      names.join(", "),
+      placeholders
+    );
+    // Prepared once here so a mismatched existing table fails before any row
+    db.prepare_cached(&insert)?;
+
+    Ok(Self {
+      insert,
+      columns: columns
+        .into_iter()
+        .map(|(i, _, sql_type)| (i, sql_type))
+        .collect(),
+    })
+  }
+
+  fn values(&self, parser: &CsvParser, buffer: &[u8], cells: &[CellRange]) -> Result<Vec<Value>> {
+    self
+      .columns
+      .iter()
+      .map(|&(i, sql_type)| {
+        let cell = match cells.get(i) {
+          Some(&cell) if !parser.is_null(buffer, cell) => cell,
+          _ => return Ok(Value::Null),
+        };
+        let text = parser.cell_value(buffer, cell)?;
+        Ok(match sql_type {
+          SqlType::Text => Value::Text(text.into_owned()),
+          _ if text.trim().is_empty() => Value::Null,
+          SqlType::Integer => match text.trim().parse() {
+            Ok(integer) => Value::Integer(integer),
+            Err(_) => Value::Text(text.into_owned()),
+          },
+          SqlType::Real => match parser.number(&NumberColumn::default(), &text) {
+            Some(number) => Value::Real(number),
+            None => Value::Text(text.into_owned()),
+          },
+        })
+      })
+      .collect()
+  }
+}
+
+fn quote_identifier(name: &str) -> String {
+  format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn load(input: &str, db: &Path, options: SqliteOptions) -> Result<u64> {
+    to_sqlite(input.as_bytes(), db, options)
+  }
+
+  #[test]
+  fn test_to_sqlite() {
+    let dir = std::env::temp_dir().join(format!("fast-csv-sqlite-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let db_path = dir.join("rows.db");
+    let _ = std::fs::remove_file(&db_path);
+    let options = SqliteOptions {
+      table: "people".to_string(),
+      types: vec![
+        ("age".to_string(), SqlType::Integer),
+        ("score".to_string(), SqlType::Real),
+      ],
+      batch_size: 2,
+      ..Default::default()
+    };
+
+    let input = "name,age,score,_\nAnn,30,1.5,x\n\"O\"\"Neil\",,n/a\nBob,forty,2\n";
+    assert_eq!(load(input, &db_path, options.clone()).unwrap(), 3);
+    // A second load appends to the table
+    assert_eq!(
+      load("name,age\nCy,7\n", &db_path, options.clone()).unwrap(),
+      1
+    );
+
+    let db = Connection::open(&db_path).unwrap();
+    let mut statement = db
+      .prepare("SELECT name, age, typeof(age), score FROM people ORDER BY rowid")
+      .unwrap();
+    let rows: Vec<(String, Value, String, Value)> = statement
+      .query_map([], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+      })
+      .unwrap()
+      .collect::<rusqlite::Result<_>>()
+      .unwrap();
+    assert_eq!(
+      rows,
+      [
+        (
+          "Ann".into(),
+          Value::Integer(30),
+          "integer".into(),
+          Value::Real(1.5)
+        ),
+        (
+          "O\"Neil".into(),
+          Value::Null,
+          "null".into(),
+          Value::Text("n/a".into())
+        ),
+        (
+          "Bob".into(),
+          Value::Text("forty".into()),
+          "text".into(),
+          Value::Real(2.0)
+        ),
+        (
+          "Cy".into(),
+          Value::Integer(7),
+          "integer".into(),
+          Value::Null
+        ),
+      ]
+    );
+
+    // A failing batch is rolled back; the batch before it is kept
+    let strict = SqliteOptions {
+      parser: CsvParserOptions {
+        strict: crate::parser::Strict::Error,
+        ..Default::default()
+      },
+      ..options.clone()
+    };
+    assert!(load("name,age\nDee,1\nEve,2\nFay\n", &db_path, strict).is_err());
+    let count: i64 = db
+      .query_row("SELECT count(*) FROM people", [], |row| row.get(0))
+      .unwrap();
+    assert_eq!(count, 6);
+
+    let error = load("nope\n1\n", &db_path, options).unwrap_err();
+    assert!(error.to_string().contains("nope"), "{}", error);
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -0,0 +1,209 @@
+use color_eyre::eyre::{eyre, Result};
+
+// Start of every snapshot, followed by the format version
+const MAGIC: &[u8; 4] = b"FCSV";
+const VERSION: u8 = 1;
+
+/// Builds a binary snapshot of parser state. Integers are little-endian and
+/// byte strings are length-prefixed.
+pub(crate) struct StateWriter {
+  out: Vec<u8>,
+}
+
+impl StateWriter {
+  pub(crate) fn new() -> Self {
+    let mut out = MAGIC.to_vec();
+    out.push(VERSION);
+    Self { out }
+  }
+
+  pub(crate) fn into_bytes(self) -> Vec<u8> {
+    self.out
+  }
+
+  pub(crate) fn u8(&mut self, value: u8) {
+    self.out.push(value);
+  }
+
+  pub(crate) fn bool(&mut self, value: bool) {
+    self.u8(value as u8);
+  }
+
+  pub(crate) fn u64(&mut self, value: u64) {
+    self.out.extend_from_slice(&value.to_le_bytes());
+  }
+
+  pub(crate) fn i64(&mut self, value: i64) {
+    self.out.extend_from_slice(&value.to_le_bytes());
+  }
+
+  pub(crate) fn f64(&mut self, value: f64) {
+    self.out.extend_from_slice(&value.to_le_bytes());
+  }
+
+  pub(crate) fn bytes(&mut self, value: &[u8]) {
+    self.u64(value.len() as u64);
+    self.out.extend_from_slice(value);
+  }
+
+  pub(crate) fn str(&mut self, value: &str) {
+    self.bytes(value.as_bytes());
+  }
+
+  pub(crate) fn strings(&mut self, values: &[String]) {
+    self.u64(values.len() as u64);
+    for value in values {
+      self.str(value);
+    }
+  }
+
+  /// Writes whether `value` is present, then the value itself with `write`
+  pub(crate) fn option<T>(&mut self, value: Option<T>, write: impl FnOnce(&mut Self, T)) {
+    self.bool(value.is_some());
+    if let Some(value) = value {
+      write(self, value);
+    }
+  }
+}
+
+/// Reads back a snapshot made by `StateWriter`, failing on anything that
+/// does not parse rather than trusting it
+pub(crate) struct StateReader<'a> {
+  data: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+  pub(crate) fn new(data: &'a [u8]) -> Result<Self> {
+    let mut reader = Self { data };
+    if reader.take(MAGIC.len())? != MAGIC {
+      return Err(eyre!("Invalid parser state"));
+    }
+    let version = reader.u8()?;
+    if version != VERSION {
+      return Err(eyre!("Unsupported parser state version {}", version));
+    }
+    Ok(reader)
+  }
+
+  /// Fails if anything is left over
+  pub(crate) fn finish(self) -> Result<()> {
+    if self.data.is_empty() {
+      Ok(())
+    } else {
+      Err(eyre!("Invalid parser state"))
+    }
+  }
+
+  fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+    if self.data.len() < len {
+      return Err(eyre!("Invalid parser state"));
+    }
+    let (head, tail) = self.data.split_at(len);
+    self.data = tail;
+    Ok(head)
+  }
+
+  fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+    let mut bytes = [0; N];
+    bytes.copy_from_slice(self.take(N)?);
+    Ok(bytes)
+  }
+
+  pub(crate) fn u8(&mut self) -> Result<u8> {
+    Ok(self.take(1)?[0])
+  }
+
+  pub(crate) fn bool(&mut self) -> Result<bool> {
+    match self.u8()? {
+      0 => Ok(false),
+      1 => Ok(true),
+      _ => Err(eyre!("Invalid parser state")),
+    }
+  }
+
+  pub(crate) fn u64(&mut self) -> Result<u64> {
+    Ok(u64::from_le_bytes(self.array()?))
+  }
+
+  pub(crate) fn usize(&mut self) -> Result<usize> {
+    usize::try_from(self.u64()?).map_err(|_| eyre!("Invalid parser state"))
+  }
+
+  pub(crate) fn i64(&mut self) -> Result<i64> {
+    Ok(i64::from_le_bytes(self.array()?))
+  }
+
+  pub(crate) fn f64(&mut self) -> Result<f64> {
+    Ok(f64::from_le_bytes(self.array()?))
+  }
+
+  pub(crate) fn bytes(&mut self) -> Result<&'a [u8]> {
+    let len = self.usize()?;
+    self.take(len)
+  }
+
+  pub(crate) fn string(&mut self) -> Result<String> {
+    let bytes = self.bytes()?;
+    String::from_utf8(bytes.to_vec()).map_err(|_| eyre!("Invalid parser state"))
+  }
+
+  pub(crate) fn strings(&mut self) -> Result<Vec<String>> {
+    let len = self.usize()?;
+    // Each string takes at least its length prefix, so a bogus count fails
+    // here instead of reserving memory for it
+    if len > self.data.len() / 8 {
+      return Err(eyre!("Invalid parser state"));
+    }
+    (0..len).map(|_| self.string()).collect()
+  }
+
+  /// Reads a value written by `StateWriter::option`
+  pub(crate) fn option<T>(
+    &mut self,
+    read: impl FnOnce(&mut Self) -> Result<T>,
+  ) -> Result<Option<T>> {
+    if self.bool()? {
+      read(self).map(Some)
+    } else {
+      Ok(None)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_round_trip() {
+    let mut writer = StateWriter::new();
+    writer.u8(7);
+    writer.i64(-3);
+    writer.f64(0.5);
+    writer.strings(&["a".to_string(), "bc".to_string()]);
+    writer.option(None::<u8>, |w, v| w.u8(v));
+    writer.option(Some(true), |w, v| w.bool(v));
+    let bytes = writer.into_bytes();
+
+    let mut reader = StateReader::new(&bytes).unwrap();
+    assert_eq!(reader.u8().unwrap(), 7);
+    assert_eq!(reader.i64().unwrap(), -3);
+    assert_eq!(reader.f64().unwrap(), 0.5);
+    assert_eq!(reader.strings().unwrap(), ["a", "bc"]);
+    assert_eq!(reader.option(|r| r.u8()).unwrap(), None);
+    assert_eq!(reader.option(|r| r.bool()).unwrap(), Some(true));
+    reader.finish().unwrap();
+  }
+
+  #[test]
+  fn test_rejects_bad_input() {
+    assert!(StateReader::new(b"nope").is_err());
+    assert!(StateReader::new(b"FCSV\x09").is_err());
+
+    let mut writer = StateWriter::new();
+    writer.u64(u64::MAX);
+    let bytes = writer.into_bytes();
+    let mut reader = StateReader::new(&bytes).unwrap();
+    assert!(reader.strings().is_err());
+  }
+}
@@ -0,0 +1,232 @@
+use color_eyre::eyre::Result;
+use std::collections::HashMap;
+
+use crate::parser::CsvParserOptions;
+use crate::reader::{Parser, Records};
+use crate::schema::is_float;
+
+/// Values tracked per column for every value reported in `top`
+const TOP_CAPACITY_FACTOR: usize = 10;
+
+/// Summary of one column over all rows seen
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnStats {
+  pub name: String,
+  /// Rows with a non-empty value
+  pub count: u64,
+  /// Rows where the value was empty or missing
+  pub null_count: u64,
+  /// Non-empty values that parse as numbers
+  pub numeric_count: u64,
+  /// Over the numeric values only
+  pub min: Option<f64>,
+  pub max: Option<f64>,
+  pub mean: Option<f64>,
+  /// Sample standard deviation, with at least two numeric values
+  pub stddev: Option<f64>,
+  /// Most frequent values, most frequent first. Counts are exact while the
+  /// column has fewer than ten times `top_k` distinct values, and lower
+  /// bounds after that.
+  pub top: Vec<(String, u64)>,
+}
+
+/// Computes per-column statistics while streaming, without building rows
+pub struct StatsCollector {
+  parser: Parser,
+  summary: Summary,
+}
+
+struct Summary {
+  columns: Vec<Column>,
+  index: HashMap<String, usize>,
+  rows: u64,
+  top_k: usize,
+}
+
+struct Column {
+  name: String,
+  count: u64,
+  min: f64,
+  max: f64,
+  // Welford's running mean and sum of squared differences
+  numeric_count: u64,
+  mean: f64,
+  m2: f64,
+  // Misra-Gries summary for the most frequent values
+  frequent: HashMap<String, u64>,
+}
+
+impl StatsCollector {
+  pub fn new(options: CsvParserOptions, top_k: usize) -> Self {
+    Self {
+      parser: Parser::new(options),
+      summary: Summary {
+        columns: Vec::new(),
+        index: HashMap::new(),
+        rows: 0,
+        top_k,
+      },
+    }
+  }
+
+  /// Adds a chunk of input to the statistics
+  pub fn feed(&mut self, chunk: &[u8]) -> Result<()> {
+    let records = self.parser.feed(chunk)?;
+    self.summary.add(records)
+  }
+
+  /// Ends the input, counting a last row without a trailing newline
+  pub fn finish(&mut self) -> Result<()> {
+    let records = self.parser.finish()?;
+    self.summary.add(records)
+  }
+
+  /// Rows seen so far
+  pub fn rows(&self) -> u64 {
+    self.summary.rows
+  }
+
+  /// The statistics so far, in column order
+  pub fn stats(&self) -> Vec<ColumnStats> {
+    let summary = &self.summary;
+    summary
+      .columns
+      .iter()
+      .map(|column| column.stats(summary.rows, summary.top_k))
+      .collect()
+  }
+}
+
+impl Summary {
+  fn add(&mut self, records: Records) -> Result<()> {
+    let capacity = self.top_k * TOP_CAPACITY_FACTOR;
+
+    for record in records {
+      let record = record?;
+      for (key, value) in record.iter() {
+        let i = match self.index.get(key) {
+          Some(&i) => i,
+          None => {
+            self.columns.push(Column::new(key));
+            self.index.insert(key.to_string(), self.columns.len() - 1);
+            self.columns.len() - 1
+          }
+        };
+        self.columns[i].add(value, capacity);
+      }
+      self.rows += 1;
+    }
+    Ok(())
+  }
+}
+
+impl Column {
+  fn new(name: &str) -> Self {
+    Self {
+      name: name.to_string(),
+      count: 0,
+      min: f64::INFINITY,
+      max: f64::NEG_INFINITY,
+      numeric_count: 0,
+      mean: 0.0,
+      m2: 0.0,
+      frequent: HashMap::new(),
+    }
+  }
+
+  fn add(&mut self, value: &str, capacity: usize) {
+    if value.is_empty() {
+      return;
+    }
+    self.count += 1;
+
+    if is_float(value) {
+      let x: f64 = value.parse().unwrap_or_default();
+      self.min = self.min.min(x);
+      self.max = self.max.max(x);
+      self.numeric_count += 1;
+      let delta = x - self.mean;
+      self.mean += delta / self.numeric_count as f64;
+      self.m2 += delta * (x - self.mean);
+    }
+
+    if capacity == 0 {
+      return;
+    }
+    if let Some(count) = self.frequent.get_mut(value) {
+      *count += 1;
+    } else if self.frequent.len() < capacity {
+      self.frequent.insert(value.to_string(), 1);
+    } else {
+      // Full: every tracked value loses one, as does the new one
+      self.frequent.retain(|_, count| {
+        *count -= 1;
+        *count > 0
+      });
+    }
+  }
+
+  fn stats(&self, rows: u64, top_k: usize) -> ColumnStats {
+    let numeric = self.numeric_count > 0;
+    let mut top: Vec<_> = self
+      .frequent
+      .iter()
+      .map(|(value, &count)| (value.clone(), count))
+      .collect();
+    top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    top.truncate(top_k);
+
+    ColumnStats {
+      name: self.name.clone(),
+      count: self.count,
+      // Empty, or missing from a row with fewer cells
+      null_count: rows - self.count,
+      numeric_count: self.numeric_count,
+      min: numeric.then_some(self.min),
+      max: numeric.then_some(self.max),
+      mean: numeric.then_some(self.mean),
+      stddev: (self.numeric_count > 1).then(|| (self.m2 / (self.numeric_count - 1) as f64).sqrt()),
+      top,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn stats(input: &str, top_k: usize) -> Vec<ColumnStats> {
+    let mut collector = StatsCollector::new(CsvParserOptions::default(), top_k);
+    let (head, tail) = input.split_at(input.len() / 2);
+    collector.feed(head.as_bytes()).unwrap();
+    collector.feed(tail.as_bytes()).unwrap();
+    collector.finish().unwrap();
+    collector.stats()
+  }
+
+  #[test]
+  fn test_numeric_stats() {
+    let columns = stats("n,s\n2,a\n4,b\n,c\n4,d\n6,e", 0);
+    let n = &columns[0];
+
+    assert_eq!((n.count, n.null_count, n.numeric_count), (4, 1, 4));
+    assert_eq!((n.min, n.max, n.mean), (Some(2.0), Some(6.0), Some(4.0)));
+    assert!((n.stddev.unwrap() - 1.632_993).abs() < 1e-6);
+    assert_eq!(columns[1].mean, None);
+  }
+
+  #[test]
+  fn test_top_values() {
+    let columns = stats("c\nx\ny\nx\nz\nx\ny\n", 2);
+
+    assert_eq!(columns[0].top, [("x".to_string(), 3), ("y".to_string(), 2)]);
+  }
+
+  #[test]
+  fn test_missing_columns_count_as_null() {
+    let columns = stats("a\n1\n2,3\n4\n", 1);
+
+    assert_eq!(columns[1].name, "_1");
+    assert_eq!((columns[1].count, columns[1].null_count), (1, 2));
+  }
+}
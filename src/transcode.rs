@@ -0,0 +1,116 @@
+use color_eyre::eyre::{eyre, Result};
+use encoding_rs::{DecoderResult, Encoding, UTF_8};
+
+/// Converts input in a named encoding to UTF-8, a chunk at a time. Chunks
+/// may split characters anywhere. A BOM at the start of the input is removed
+/// and, for UTF-8 and UTF-16, overrides the given encoding.
+pub struct Transcoder {
+  encoding: &'static Encoding,
+  decoder: encoding_rs::Decoder,
+}
+
+impl Transcoder {
+  /// `label` is a WHATWG encoding label (e.g. `latin1`, `shift_jis`,
+  /// `utf-16le`); UTF-8 by default
+  pub fn new(label: Option<&str>) -> Result<Self> {
+    let encoding = match label {
+      Some(label) => {
+        Encoding::for_label(label.as_bytes()).ok_or_else(|| eyre!("Unknown encoding {}", label))?
+      }
+      None => UTF_8,
+    };
+    Ok(Self {
+      encoding,
+      decoder: encoding.new_decoder(),
+    })
+  }
+
+  /// The encoding input is decoded from: the given one until a BOM says
+  /// otherwise
+  pub fn encoding(&self) -> &'static Encoding {
+    self.decoder.encoding()
+  }
+
+  /// Decodes a chunk, keeping a character split across chunks for the next
+  /// call. Fails on bytes that are invalid in the encoding.
+  pub fn feed(&mut self, chunk: &[u8]) -> Result<Vec<u8>> {
+    self.decode(chunk, false)
+  }
+
+  /// Ends the input and returns the rest of the output. The transcoder is
+  /// ready for another input afterwards.
+  pub fn finish(&mut self) -> Result<Vec<u8>> {
+    let out = self.decode(&[], true);
+    self.decoder = self.encoding.new_decoder();
+    out
+  }
+
+  fn decode(&mut self, chunk: &[u8], last: bool) -> Result<Vec<u8>> {
+    let decoder = &mut self.decoder;
+    let mut out = String::with_capacity(
+      decoder
+        .max_utf8_buffer_length_without_replacement(chunk.len())
+        .unwrap_or(chunk.len() * 3),
+    );
+    let (result, _) = decoder.decode_to_string_without_replacement(chunk, &mut out, last);
+    match result {
+      DecoderResult::InputEmpty => Ok(out.into_bytes()),
+      DecoderResult::Malformed(..) => Err(eyre!(
+        "Encoding conversion error: invalid {} input",
+        decoder.encoding().name()
+      )),
+      DecoderResult::OutputFull => unreachable!("output sized for the input"),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn transcode(label: Option<&str>, input: &[u8]) -> Result<Vec<u8>> {
+    let mut transcoder = Transcoder::new(label)?;
+    let mut out = Vec::new();
+    for byte in input {
+      out.extend(transcoder.feed(&[*byte])?);
+    }
+    out.extend(transcoder.finish()?);
+    Ok(out)
+  }
+
+  #[test]
+  fn test_split_characters() {
+    assert_eq!(
+      transcode(Some("latin1"), b"Jos\xE9").unwrap(),
+      "José".as_bytes()
+    );
+    assert_eq!(
+      transcode(Some("shift_jis"), b"\x93\xfa\x96\x7b").unwrap(),
+      "日本".as_bytes()
+    );
+    assert_eq!(
+      transcode(None, "\u{FEFF}é".as_bytes()).unwrap(),
+      "é".as_bytes()
+    );
+  }
+
+  #[test]
+  fn test_bom_overrides_label() {
+    let mut transcoder = Transcoder::new(Some("latin1")).unwrap();
+    let out = transcoder.feed(b"\xFF\xFEa\x00").unwrap();
+    assert_eq!(out, b"a");
+    assert_eq!(transcoder.encoding().name(), "UTF-16LE");
+
+    // A new input starts with the given encoding again
+    transcoder.finish().unwrap();
+    assert_eq!(transcoder.encoding().name(), "windows-1252");
+  }
+
+  #[test]
+  fn test_errors() {
+    assert!(Transcoder::new(Some("klingon")).is_err());
+    assert!(transcode(None, b"a\xFFb").is_err());
+    // An incomplete character at the end of the input
+    assert!(transcode(None, b"a\xC3").is_err());
+  }
+}
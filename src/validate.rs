@@ -0,0 +1,325 @@
+use std::fmt;
+
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// A way in which input breaks RFC 4180
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ViolationKind {
+  /// A quoted field runs to the end of the input
+  UnclosedQuote,
+  /// A quote inside a field that does not start with one
+  StrayQuote,
+  /// Text between a closing quote and the next separator or line break
+  TextAfterQuote,
+  /// A line break that is LF without CR
+  BareLf,
+  /// A CR that is not followed by LF
+  BareCr,
+  /// A record with a different number of fields than the first one
+  FieldCount { expected: usize, found: usize },
+}
+
+impl ViolationKind {
+  /// Short machine-readable name, e.g. `stray-quote`
+  pub fn code(&self) -> &'static str {
+    match self {
+      ViolationKind::UnclosedQuote => "unclosed-quote",
+      ViolationKind::StrayQuote => "stray-quote",
+      ViolationKind::TextAfterQuote => "text-after-quote",
+      ViolationKind::BareLf => "bare-lf",
+      ViolationKind::BareCr => "bare-cr",
+      ViolationKind::FieldCount { .. } => "field-count",
+    }
+  }
+}
+
+impl fmt::Display for ViolationKind {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      ViolationKind::UnclosedQuote => write!(f, "Quoted field is never closed"),
+      ViolationKind::StrayQuote => write!(f, "Quote in an unquoted field"),
+      ViolationKind::TextAfterQuote => write!(f, "Text after the closing quote of a field"),
+      ViolationKind::BareLf => write!(f, "Line ends with LF instead of CRLF"),
+      ViolationKind::BareCr => write!(f, "CR without LF"),
+      ViolationKind::FieldCount { expected, found } => {
+        write!(f, "Expected {} fields, found {}", expected, found)
+      }
+    }
+  }
+}
+
+/// One violation. Lines and columns count from 1; columns are in characters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+  pub line: u64,
+  pub column: u64,
+  pub kind: ViolationKind,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+  /// Records in the input, including the header record
+  pub records: u64,
+  /// In input order, at most `max_violations` of them
+  pub violations: Vec<Violation>,
+  /// Whether violations were left out past `max_violations`
+  pub truncated: bool,
+}
+
+impl ValidationReport {
+  pub fn is_valid(&self) -> bool {
+    self.violations.is_empty()
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+  FieldStart,
+  Unquoted,
+  Quoted,
+  // Just saw a quote inside a quoted field: an escaped quote or the end
+  QuoteInQuoted,
+  // Past the closing quote, before the next separator or line break
+  AfterQuote,
+}
+
+/// Checks input against RFC 4180 while streaming: comma separators, `"`
+/// quotes, CRLF line breaks and the same number of fields in every record.
+/// Unlike the parser, it keeps going after a problem and reports every one
+/// it finds, with its line and column. Input is expected to be UTF-8; a BOM
+/// is allowed.
+pub struct Rfc4180Validator {
+  max_violations: usize,
+  report: ValidationReport,
+  state: State,
+  // Position of the current byte
+  line: u64,
+  column: u64,
+  // Where the current record and the current quoted field started
+  record_line: u64,
+  quote_at: (u64, u64),
+  // Position of a CR still waiting for its LF
+  pending_cr: Option<(u64, u64)>,
+  fields: usize,
+  record_empty: bool,
+  expected_fields: Option<usize>,
+  // Bytes read so far, and how many of them were a BOM
+  offset: usize,
+  bom: usize,
+}
+
+impl Rfc4180Validator {
+  pub fn new(max_violations: usize) -> Self {
+    Self {
+      max_violations,
+      report: ValidationReport::default(),
+      state: State::FieldStart,
+      line: 1,
+      column: 0,
+      record_line: 1,
+      quote_at: (1, 1),
+      pending_cr: None,
+      fields: 0,
+      record_empty: true,
+      expected_fields: None,
+      offset: 0,
+      bom: 0,
+    }
+  }
+
+  /// Checks a chunk of input. Chunks may split records anywhere.
+  pub fn feed(&mut self, chunk: &[u8]) {
+    for &byte in chunk {
+      if self.offset == self.bom && self.bom < BOM.len() && byte == BOM[self.bom] {
+        self.offset += 1;
+        self.bom += 1;
+        continue;
+      }
+      self.offset += 1;
+      // Count characters, not UTF-8 continuation bytes
+      if byte & 0xC0 != 0x80 {
+        self.column += 1;
+      }
+      self.byte(byte);
+    }
+  }
+
+  /// Ends the input and returns the report. The validator is ready for
+  /// another input afterwards.
+  pub fn finish(&mut self) -> ValidationReport {
+    if let Some((line, column)) = self.pending_cr.take() {
+      self.violation(line, column, ViolationKind::BareCr);
+      self.end_record();
+    }
+    if self.state == State::Quoted {
+      let (line, column) = self.quote_at;
+      self.violation(line, column, ViolationKind::UnclosedQuote);
+    }
+    // The last record may end without a line break
+    if !self.record_empty {
+      self.end_record();
+    }
+    std::mem::replace(self, Self::new(self.max_violations)).report
+  }
+
+  fn byte(&mut self, byte: u8) {
+    if let Some((line, column)) = self.pending_cr.take() {
+      if byte == b'\n' {
+        self.end_line();
+        self.end_record();
+        return;
+      }
+      self.violation(line, column, ViolationKind::BareCr);
+      self.end_record();
+    }
+
+    match self.state {
+      State::Quoted => {
+        if byte == b'"' {
+          self.state = State::QuoteInQuoted;
+        } else if byte == b'\n' {
+          self.end_line();
+        }
+        return;
+      }
+      State::QuoteInQuoted if byte == b'"' => {
+        self.state = State::Quoted;
+        return;
+      }
+      State::QuoteInQuoted => self.state = State::AfterQuote,
+      _ => {}
+    }
+
+    match byte {
+      b',' => {
+        self.fields += 1;
+        self.record_empty = false;
+        self.state = State::FieldStart;
+      }
+      b'\r' => self.pending_cr = Some((self.line, self.column)),
+      b'\n' => {
+        self.violation(self.line, self.column, ViolationKind::BareLf);
+        self.end_line();
+        self.end_record();
+      }
+      _ => {
+        self.record_empty = false;
+        match self.state {
+          State::FieldStart if byte == b'"' => {
+            self.quote_at = (self.line, self.column);
+            self.state = State::Quoted;
+          }
+          State::FieldStart => self.state = State::Unquoted,
+          State::Unquoted if byte == b'"' => {
+            self.violation(self.line, self.column, ViolationKind::StrayQuote);
+          }
+          // Reported once per field
+          State::AfterQuote => {
+            self.violation(self.line, self.column, ViolationKind::TextAfterQuote);
+            self.state = State::Unquoted;
+          }
+          _ => {}
+        }
+      }
+    }
+  }
+
+  fn end_line(&mut self) {
+    self.line += 1;
+    self.column = 0;
+  }
+
+  fn end_record(&mut self) {
+    let found = self.fields + 1;
+    match self.expected_fields {
+      None => self.expected_fields = Some(found),
+      Some(expected) if expected != found => {
+        let kind = ViolationKind::FieldCount { expected, found };
+        self.violation(self.record_line, 1, kind);
+      }
+      Some(_) => {}
+    }
+    self.report.records += 1;
+    self.fields = 0;
+    self.record_empty = true;
+    self.record_line = self.line;
+    self.state = State::FieldStart;
+  }
+
+  fn violation(&mut self, line: u64, column: u64, kind: ViolationKind) {
+    if self.report.violations.len() < self.max_violations {
+      self
+        .report
+        .violations
+        .push(Violation { line, column, kind });
+    } else {
+      self.report.truncated = true;
+    }
+  }
+}
+
+impl Default for Rfc4180Validator {
+  fn default() -> Self {
+    Self::new(1000)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn validate(input: &[u8]) -> ValidationReport {
+    let mut validator = Rfc4180Validator::default();
+    let (head, tail) = input.split_at(input.len() / 2);
+    validator.feed(head);
+    validator.feed(tail);
+    validator.finish()
+  }
+
+  fn codes(report: &ValidationReport) -> Vec<(u64, u64, &'static str)> {
+    report
+      .violations
+      .iter()
+      .map(|v| (v.line, v.column, v.kind.code()))
+      .collect()
+  }
+
+  #[test]
+  fn test_valid_input() {
+    let report = validate(b"\xEF\xBB\xBFa,b\r\n\"x,\"\"y\"\"\r\nz\",2\r\n,\r\n3,4");
+    assert!(report.is_valid(), "{:?}", report.violations);
+    assert_eq!(report.records, 4);
+  }
+
+  #[test]
+  fn test_violations() {
+    let report = validate(b"a,b\r\n1,2\n3,x\"y\r\n\"q\"z,4\r\n5\r\n6,\"7\r\n");
+    assert_eq!(
+      codes(&report),
+      [
+        (2, 4, "bare-lf"),
+        (3, 4, "stray-quote"),
+        (4, 4, "text-after-quote"),
+        (5, 1, "field-count"),
+        (6, 3, "unclosed-quote"),
+      ]
+    );
+    assert_eq!(
+      report.violations[3].kind.to_string(),
+      "Expected 2 fields, found 1"
+    );
+  }
+
+  #[test]
+  fn test_bare_cr_and_limit() {
+    let report = validate(b"a\rb\r");
+    assert_eq!(codes(&report), [(1, 2, "bare-cr"), (1, 4, "bare-cr")]);
+    assert_eq!(report.records, 2);
+
+    let mut validator = Rfc4180Validator::new(1);
+    validator.feed(b"a\nb\nc\n");
+    let report = validator.finish();
+    assert_eq!(report.violations.len(), 1);
+    assert!(report.truncated);
+  }
+}
@@ -0,0 +1,661 @@
+use std::fs::File;
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use color_eyre::eyre::{eyre, Result};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+
+use crate::parser::{CsvParserOptions, RecordFormat};
+use crate::reader::Parser;
+use crate::schema::is_float;
+use crate::transcode::Transcoder;
+
+/// When cells are wrapped in quotes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuoteStyle {
+  /// Only cells holding a separator, quote or line break
+  #[default]
+  Necessary,
+  /// Every cell
+  Always,
+  /// Every cell that is not a number, including empty cells
+  NonNumeric,
+  /// No cell. A separator, quote or line break in a cell is written after
+  /// the `escape` character; without one such a cell is an error.
+  Never,
+}
+
+/// Where `INSERT` statements written instead of CSV go, and how many rows
+/// each holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqlInsert {
+  /// Table name. Each `.`-separated part is quoted as an identifier, so
+  /// `sales.orders` names a table in a schema.
+  pub table: String,
+  /// Rows per statement
+  pub batch_size: usize,
+  /// Quote around table and column names: `"` by default, `` ` `` for
+  /// MySQL
+  pub identifier_quote: u8,
+}
+
+impl Default for SqlInsert {
+  fn default() -> Self {
+    Self {
+      table: String::new(),
+      batch_size: 100,
+      identifier_quote: b'"',
+    }
+  }
+}
+
+/// Writer options. Each field mirrors the JS option of the same name.
+#[derive(Clone)]
+pub struct CsvWriterOptions {
+  pub separator: u8,
+  pub quote: u8,
+  /// Line ending, e.g. `\n` or `\r\n`
+  pub newline: Vec<u8>,
+  pub quote_style: QuoteStyle,
+  /// Prefix cells that spreadsheets would evaluate as formulas with `'`
+  pub formula_guard: bool,
+  /// Written before quotes inside quoted cells instead of doubling them,
+  /// and before special characters with `QuoteStyle::Never`
+  pub escape: Option<u8>,
+  /// How cells are written. With `RecordFormat::PostgresText` nothing is
+  /// quoted and special characters are written as backslash escapes.
+  pub format: RecordFormat,
+  /// Written as it is for null cells; empty by default
+  pub null_value: String,
+  /// Write rows as SQL `INSERT` statements with `write_insert` instead of
+  /// CSV
+  pub sql: Option<SqlInsert>,
+}
+
+impl Default for CsvWriterOptions {
+  fn default() -> Self {
+    Self {
+      separator: b',',
+      quote: b'"',
+      newline: b"\n".to_vec(),
+      quote_style: QuoteStyle::Necessary,
+      formula_guard: false,
+      escape: None,
+      format: RecordFormat::Csv,
+      null_value: String::new(),
+      sql: None,
+    }
+  }
+}
+
+/// Formats records as CSV, quoting cells only when needed
+pub struct CsvWriter {
+  pub(crate) options: CsvWriterOptions,
+}
+
+impl CsvWriter {
+  pub fn new(options: CsvWriterOptions) -> Self {
+    Self { options }
+  }
+
+  /// Appends one record, terminated by the newline, to `out`. Fails only
+  /// with `QuoteStyle::Never` and no `escape`, for a cell that would need
+  /// quotes.
+  pub fn write_record<S: AsRef<str>>(&self, out: &mut Vec<u8>, fields: &[S]) -> Result<()> {
+    self.write_record_quoting(out, fields, |_, _| false)
+  }
+
+  /// Like `write_record`, also quoting each field for which
+  /// `quote(index, field)` holds, whatever the quote style
+  pub fn write_record_quoting<S: AsRef<str>>(
+    &self,
+    out: &mut Vec<u8>,
+    fields: &[S],
+    quote: impl FnMut(usize, &str) -> bool,
+  ) -> Result<()> {
+    self.write_fields(out, fields.iter().map(|field| Some(field.as_ref())), quote)
+  }
+
+  /// Like `write_record_quoting`, writing `None` fields as `null_value`
+  pub fn write_nullable_record<S: AsRef<str>>(
+    &self,
+    out: &mut Vec<u8>,
+    fields: &[Option<S>],
+    quote: impl FnMut(usize, &str) -> bool,
+  ) -> Result<()> {
+    let fields = fields.iter().map(|field| field.as_ref().map(AsRef::as_ref));
+    self.write_fields(out, fields, quote)
+  }
+
+  /// Appends `INSERT` statements for `rows` to `out`, `batch_size` rows
+  /// each, listing `columns` when given. Cells are string literals with
+  /// quotes doubled and `None` cells are `NULL`; with
+  /// `QuoteStyle::NonNumeric` numbers are written bare unless
+  /// `quote(row, index, field)` holds. Fails unless `sql` is set.
+  pub fn write_insert<S: AsRef<str>>(
+    &self,
+    out: &mut Vec<u8>,
+    columns: Option<&[S]>,
+    rows: &[Vec<Option<S>>],
+    mut quote: impl FnMut(usize, usize, &str) -> bool,
+  ) -> Result<()> {
+    let options = &self.options;
+    let sql = options
+      .sql
+      .as_ref()
+      .ok_or_else(|| eyre!("INSERT statements need the sql option"))?;
+    let identifier = |out: &mut Vec<u8>, name: &str| {
+      let quote = sql.identifier_quote;
+      out.push(quote);
+      for &byte in name.as_bytes() {
+        if byte == quote {
+          out.push(quote);
+        }
+        out.push(byte);
+      }
+      out.push(quote);
+    };
+
+    let mut statement = Vec::new();
+    statement.extend_from_slice(b"INSERT INTO ");
+    for (i, part) in sql.table.split('.').enumerate() {
+      if i > 0 {
+        statement.push(b'.');
+      }
+      identifier(&mut statement, part);
+    }
+    if let Some(columns) = columns {
+      statement.extend_from_slice(b" (");
+      for (i, column) in columns.iter().enumerate() {
+        if i > 0 {
+          statement.extend_from_slice(b", ");
+        }
+        identifier(&mut statement, column.as_ref());
+      }
+      statement.push(b')');
+    }
+    statement.extend_from_slice(b" VALUES");
+
+    for (batch, chunk) in rows.chunks(sql.batch_size.max(1)).enumerate() {
+      out.extend_from_slice(&statement);
+      for (i, fields) in chunk.iter().enumerate() {
+        let row = batch * sql.batch_size.max(1) + i;
+        out.extend_from_slice(&options.newline);
+        out.push(b'(');
+        for (index, field) in fields.iter().enumerate() {
+          if index > 0 {
+            out.extend_from_slice(b", ");
+          }
+          let Some(field) = field.as_ref().map(AsRef::as_ref) else {
+            out.extend_from_slice(b"NULL");
+            continue;
+          };
+          let bare = options.quote_style == QuoteStyle::NonNumeric
+            && is_float(field)
+            && !quote(row, index, field);
+          if bare {
+            out.extend_from_slice(field.as_bytes());
+            continue;
+          }
+          out.push(b'\'');
+          for &byte in field.as_bytes() {
+            if byte == b'\'' {
+              out.push(b'\'');
+            }
+            out.push(byte);
+          }
+          out.push(b'\'');
+        }
+        out.push(b')');
+        if i + 1 < chunk.len() {
+          out.push(b',');
+        }
+      }
+      out.push(b';');
+      out.extend_from_slice(&options.newline);
+    }
+    Ok(())
+  }
+
+  fn write_fields<'a>(
+    &self,
+    out: &mut Vec<u8>,
+    fields: impl Iterator<Item = Option<&'a str>>,
+    mut quote: impl FnMut(usize, &str) -> bool,
+  ) -> Result<()> {
+    for (index, field) in fields.enumerate() {
+      if index > 0 {
+        out.push(self.options.separator);
+      }
+      match field {
+        Some(field) => {
+          let forced = quote(index, field);
+          self.write_field(out, field, forced)?;
+        }
+        None => out.extend_from_slice(self.options.null_value.as_bytes()),
+      }
+    }
+    out.extend_from_slice(&self.options.newline);
+    Ok(())
+  }
+
+  fn write_field(&self, out: &mut Vec<u8>, field: &str, forced: bool) -> Result<()> {
+    let options = &self.options;
+    let bytes = field.as_bytes();
+    if options.format == RecordFormat::PostgresText {
+      self.write_copy_field(out, bytes);
+      return Ok(());
+    }
+
+    // Spreadsheets evaluate cells starting with these as formulas, which
+    // makes exported user data a CSV injection vector
+    let guard = self.options.formula_guard
+      && matches!(
+        bytes.first(),
+        Some(b'=' | b'+' | b'-' | b'@' | b'\t' | b'\r')
+      );
+
+    let special = |b: u8| {
+      b == options.separator
+        || b == options.quote
+        || b == b'\r'
+        || b == b'\n'
+        || options.newline.contains(&b)
+    };
+    let quoted = forced
+      || match options.quote_style {
+        QuoteStyle::Necessary => bytes.iter().any(|&b| special(b)),
+        QuoteStyle::Always => true,
+        QuoteStyle::NonNumeric => !is_float(field),
+        QuoteStyle::Never => false,
+      };
+
+    if !quoted && options.escape.is_none() && bytes.iter().any(|&b| special(b)) {
+      return Err(eyre!(
+        "Cannot write {:?} without quotes; set an escape character",
+        field
+      ));
+    }
+
+    if quoted {
+      out.push(options.quote);
+    }
+    if guard {
+      out.push(b'\'');
+    }
+    for &byte in bytes {
+      let escaped = match options.escape {
+        _ if quoted => byte == options.quote || Some(byte) == options.escape,
+        Some(escape) => special(byte) || byte == escape,
+        None => false,
+      };
+      if escaped {
+        out.push(options.escape.unwrap_or(options.quote));
+      }
+      out.push(byte);
+    }
+    if quoted {
+      out.push(options.quote);
+    }
+    Ok(())
+  }
+
+  // A cell of the PostgreSQL `COPY` text format: backslash escapes for the
+  // characters `CopyTokenizer` reads them back from, and the separator
+  fn write_copy_field(&self, out: &mut Vec<u8>, bytes: &[u8]) {
+    for &byte in bytes {
+      let escape = match byte {
+        b'\\' => b'\\',
+        b'\n' => b'n',
+        b'\r' => b'r',
+        b'\t' => b't',
+        0x08 => b'b',
+        0x0C => b'f',
+        0x0B => b'v',
+        _ if byte == self.options.separator => byte,
+        _ => {
+          out.push(byte);
+          continue;
+        }
+      };
+      out.extend_from_slice(&[b'\\', escape]);
+    }
+  }
+}
+
+/// Encodes written CSV for a file, starting the first output with a byte
+/// order mark if asked
+pub struct Encoder {
+  encoding: &'static Encoding,
+  bom: bool,
+}
+
+impl Encoder {
+  /// `label` is a WHATWG encoding label (e.g. `utf-16le`, `windows-1252`);
+  /// UTF-8 by default. A BOM is only written for UTF-8 and UTF-16.
+  pub fn new(label: Option<&str>, bom: bool) -> Result<Self> {
+    let encoding = match label {
+      Some(label) => {
+        Encoding::for_label(label.as_bytes()).ok_or_else(|| eyre!("Unknown encoding {}", label))?
+      }
+      None => UTF_8,
+    };
+    Ok(Self { encoding, bom })
+  }
+
+  /// Whether the BOM is still to be written, clearing it. For callers that
+  /// output text rather than bytes.
+  pub fn take_bom(&mut self) -> bool {
+    std::mem::take(&mut self.bom)
+  }
+
+  pub fn encode(&mut self, text: &str) -> Result<Vec<u8>> {
+    let bom = self.take_bom();
+    let encoding = self.encoding;
+
+    let mut out = Vec::new();
+    if encoding == UTF_16LE || encoding == UTF_16BE {
+      // encoding_rs only decodes UTF-16
+      let le = encoding == UTF_16LE;
+      let bom = bom.then_some(0xFEFF);
+      for unit in bom.into_iter().chain(text.encode_utf16()) {
+        let bytes = if le {
+          unit.to_le_bytes()
+        } else {
+          unit.to_be_bytes()
+        };
+        out.extend_from_slice(&bytes);
+      }
+      return Ok(out);
+    }
+
+    if encoding == UTF_8 {
+      if bom {
+        out.extend_from_slice(b"\xEF\xBB\xBF");
+      }
+      out.extend_from_slice(text.as_bytes());
+      return Ok(out);
+    }
+
+    let (bytes, _, unmappable) = encoding.encode(text);
+    if unmappable {
+      return Err(eyre!(
+        "Output contains characters that {} cannot represent",
+        encoding.name()
+      ));
+    }
+    Ok(bytes.into_owned())
+  }
+}
+
+/// What is already in a file that rows are appended to
+#[derive(Debug, PartialEq, Eq)]
+pub struct AppendTarget {
+  /// The names on the first line; none when the file holds no records,
+  /// e.g. only a BOM
+  pub header: Option<Vec<String>>,
+  /// Whether the last line is complete, so rows can follow directly
+  pub ends_with_newline: bool,
+}
+
+/// Reads the header line of `path`, written in the dialect of `options` and
+/// in `encoding` (a WHATWG label, UTF-8 by default). None for a missing or
+/// empty file.
+pub fn read_append_target(
+  path: &Path,
+  options: &CsvWriterOptions,
+  encoding: Option<&str>,
+) -> Result<Option<AppendTarget>> {
+  let mut file = match File::open(path) {
+    Ok(file) => file,
+    Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+    Err(e) => return Err(e.into()),
+  };
+  let length = file.metadata()?.len();
+  if length == 0 {
+    return Ok(None);
+  }
+
+  let mut transcoder = Transcoder::new(encoding)?;
+  let mut parser = Parser::new(CsvParserOptions {
+    separator: options.separator,
+    quote: options.quote,
+    escape: options.escape.unwrap_or(options.quote),
+    format: options.format,
+    ..Default::default()
+  });
+  let mut chunk = vec![0; 64 * 1024];
+  while parser.headers().is_none() {
+    let read = file.read(&mut chunk)?;
+    if read == 0 {
+      parser.feed(&transcoder.finish()?)?.next().transpose()?;
+      parser.finish()?.next().transpose()?;
+      break;
+    }
+    parser
+      .feed(&transcoder.feed(&chunk[..read])?)?
+      .next()
+      .transpose()?;
+  }
+  let header = parser.headers().map(<[String]>::to_vec);
+
+  // The last character, in the file's encoding
+  let mut tail = Vec::new();
+  file.seek(SeekFrom::Start(length.saturating_sub(4)))?;
+  file.read_to_end(&mut tail)?;
+  let mut encoder = Encoder::new(encoding, false)?;
+  let ends_with_newline = ["\n", "\r"]
+    .into_iter()
+    .map(|newline| encoder.encode(newline))
+    .collect::<Result<Vec<_>>>()?
+    .iter()
+    .any(|newline| tail.ends_with(newline));
+
+  Ok(Some(AppendTarget {
+    header,
+    ends_with_newline,
+  }))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn write(writer: &CsvWriter, fields: &[&str]) -> String {
+    let mut out = Vec::new();
+    writer.write_record(&mut out, fields).unwrap();
+    String::from_utf8(out).unwrap()
+  }
+
+  #[test]
+  fn test_quotes_when_needed() {
+    let writer = CsvWriter::new(CsvWriterOptions::default());
+
+    assert_eq!(write(&writer, &["a", "b c", ""]), "a,b c,\n");
+    assert_eq!(
+      write(&writer, &["a,b", "say \"hi\"", "two\nlines"]),
+      "\"a,b\",\"say \"\"hi\"\"\",\"two\nlines\"\n"
+    );
+  }
+
+  #[test]
+  fn test_formula_guard() {
+    let writer = CsvWriter::new(CsvWriterOptions {
+      formula_guard: true,
+      ..Default::default()
+    });
+
+    assert_eq!(
+      write(&writer, &["=SUM(A1:A2)", "+1", "-2", "@cmd", "a=b"]),
+      "'=SUM(A1:A2),'+1,'-2,'@cmd,a=b\n"
+    );
+    assert_eq!(write(&writer, &["=1,2"]), "\"'=1,2\"\n");
+  }
+
+  #[test]
+  fn test_quote_style_and_newline() {
+    let writer = CsvWriter::new(CsvWriterOptions {
+      quote_style: QuoteStyle::Always,
+      newline: b"\r\n".to_vec(),
+      ..Default::default()
+    });
+
+    assert_eq!(
+      write(&writer, &["a", "", "b\"c"]),
+      "\"a\",\"\",\"b\"\"c\"\r\n"
+    );
+  }
+
+  #[test]
+  fn test_non_numeric_and_forced_quotes() {
+    let writer = CsvWriter::new(CsvWriterOptions {
+      quote_style: QuoteStyle::NonNumeric,
+      ..Default::default()
+    });
+    assert_eq!(
+      write(&writer, &["a", "1", "-2.5e3", "", "NaN"]),
+      "\"a\",1,-2.5e3,\"\",\"NaN\"\n"
+    );
+
+    let writer = CsvWriter::new(CsvWriterOptions::default());
+    let mut out = Vec::new();
+    writer
+      .write_record_quoting(&mut out, &["007", "x", "42"], |index, field| {
+        index == 0 || field == "42"
+      })
+      .unwrap();
+    assert_eq!(out, b"\"007\",x,\"42\"\n");
+  }
+
+  #[test]
+  fn test_never_quote_and_escape() {
+    let never = CsvWriter::new(CsvWriterOptions {
+      quote_style: QuoteStyle::Never,
+      ..Default::default()
+    });
+    assert_eq!(write(&never, &["a", "b c"]), "a,b c\n");
+    assert!(never.write_record(&mut Vec::new(), &["a,b"]).is_err());
+
+    let escaped = CsvWriter::new(CsvWriterOptions {
+      quote_style: QuoteStyle::Never,
+      escape: Some(b'\\'),
+      ..Default::default()
+    });
+    assert_eq!(
+      write(&escaped, &["a,b", "say \"hi\"", "x\ny", "c:\\"]),
+      "a\\,b,say \\\"hi\\\",x\\\ny,c:\\\\\n"
+    );
+
+    let quoted = CsvWriter::new(CsvWriterOptions {
+      escape: Some(b'\\'),
+      ..Default::default()
+    });
+    assert_eq!(write(&quoted, &["say \"hi\""]), "\"say \\\"hi\\\"\"\n");
+  }
+
+  #[test]
+  fn test_postgres_text() {
+    let writer = CsvWriter::new(CsvWriterOptions {
+      separator: b'\t',
+      format: RecordFormat::PostgresText,
+      null_value: "\\N".to_string(),
+      ..Default::default()
+    });
+    let mut out = Vec::new();
+    writer
+      .write_nullable_record(
+        &mut out,
+        &[Some("a\tb"), None, Some("\"x\",\\N\r\n"), Some("")],
+        |_, _| true,
+      )
+      .unwrap();
+    assert_eq!(out, b"a\\tb\t\\N\t\"x\",\\\\N\\r\\n\t\n");
+  }
+
+  #[test]
+  fn test_write_insert() {
+    let writer = CsvWriter::new(CsvWriterOptions {
+      sql: Some(SqlInsert {
+        table: "shop.or\"ders".to_string(),
+        batch_size: 2,
+        ..Default::default()
+      }),
+      ..Default::default()
+    });
+    let rows = [
+      vec![Some("1"), Some("O'Neil")],
+      vec![Some("2"), None],
+      vec![Some("3"), Some("")],
+    ];
+    let mut out = Vec::new();
+    writer
+      .write_insert(&mut out, Some(&["id", "name"]), &rows, |_, _, _| false)
+      .unwrap();
+    assert_eq!(
+      String::from_utf8(out).unwrap(),
+      concat!(
+        "INSERT INTO \"shop\".\"or\"\"ders\" (\"id\", \"name\") VALUES\n",
+        "('1', 'O''Neil'),\n('2', NULL);\n",
+        "INSERT INTO \"shop\".\"or\"\"ders\" (\"id\", \"name\") VALUES\n",
+        "('3', '');\n",
+      )
+    );
+
+    let writer = CsvWriter::new(CsvWriterOptions {
+      quote_style: QuoteStyle::NonNumeric,
+      sql: Some(SqlInsert {
+        table: "t".to_string(),
+        identifier_quote: b'`',
+        ..Default::default()
+      }),
+      ..Default::default()
+    });
+    let rows = [vec![Some("1.5"), Some("007"), Some("x")]];
+    let mut out = Vec::new();
+    writer
+      .write_insert::<&str>(&mut out, None, &rows, |_, index, _| index == 1)
+      .unwrap();
+    assert_eq!(out, b"INSERT INTO `t` VALUES\n(1.5, '007', 'x');\n");
+
+    let csv = CsvWriter::new(CsvWriterOptions::default());
+    assert!(csv
+      .write_insert::<&str>(&mut Vec::new(), None, &[], |_, _, _| false)
+      .is_err());
+  }
+
+  #[test]
+  fn test_read_append_target() {
+    let path = std::env::temp_dir().join("fast-csv-parser-append-target.csv");
+    let options = CsvWriterOptions::default();
+    let read = |contents: &[u8], encoding| {
+      std::fs::write(&path, contents).unwrap();
+      read_append_target(&path, &options, encoding).unwrap()
+    };
+
+    assert_eq!(read(b"", None), None);
+    assert_eq!(
+      read(b"\xEF\xBB\xBFid,\"a,b\"\n1,2\n", None),
+      Some(AppendTarget {
+        header: Some(vec!["id".to_string(), "a,b".to_string()]),
+        ends_with_newline: true,
+      })
+    );
+    assert_eq!(
+      read(b"i\x00d\x00\r\x00", Some("utf-16le")),
+      Some(AppendTarget {
+        header: Some(vec!["id".to_string()]),
+        ends_with_newline: true,
+      })
+    );
+    assert_eq!(
+      read(b"id", None),
+      Some(AppendTarget {
+        header: Some(vec!["id".to_string()]),
+        ends_with_newline: false,
+      })
+    );
+
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(read_append_target(&path, &options, None).unwrap(), None);
+  }
+}